@@ -0,0 +1,204 @@
+//! Shared ADC service
+//!
+//! Boards usually have a single ADC peripheral muxed across many inputs, e.g. thermistors, battery current sense and
+//! ambient light. [`AdcService`] owns the peripheral and arbitrates access to it, so each driver samples its input
+//! through a [`Channel`] handle without coordinating with the others. Conversions are serialized, a client only holds
+//! the peripheral for the duration of a single conversion, and the channel's [`Calibration`] is applied to every
+//! reading.
+//!
+//! ```ignore
+//! static ADC: StaticCell<AdcService<'static, BoardAdc>> = StaticCell::new();
+//! let adc = ADC.init(AdcService::new(board_adc, &[(Input::Thermistor0, Calibration { gain: 1.002, offset: -3.0 })]));
+//!
+//! let reading = adc.channel(Input::Thermistor0).sample().await?;
+//!
+//! let mut current = adc.channel(Input::CurrentSense).periodic(Duration::from_millis(10));
+//! loop {
+//!     let reading = current.next().await?;
+//! }
+//! ```
+#![no_std]
+
+use core::future::Future;
+
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Ticker};
+use embedded_services::GlobalRawMutex;
+
+/// ADC peripheral shared through the service.
+///
+/// embedded-hal doesn't define an ADC trait, so this is implemented by the platform over its ADC driver.
+pub trait Adc {
+    /// Identifies an input of the ADC
+    type Channel: Copy + PartialEq;
+    /// Error type
+    type Error;
+
+    /// Performs a single conversion on `channel`, returning the raw reading.
+    fn convert(&mut self, channel: Self::Channel) -> impl Future<Output = Result<u16, Self::Error>>;
+}
+
+/// Linear correction applied to raw readings, `calibrated = raw * gain + offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Calibration {
+    /// Gain correction
+    pub gain: f32,
+    /// Offset correction in ADC counts
+    pub offset: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self { gain: 1.0, offset: 0.0 }
+    }
+}
+
+impl Calibration {
+    /// Applies the correction to a raw reading, rounding and saturating to the `u16` range.
+    pub fn apply(&self, raw: u16) -> u16 {
+        // Float to integer casts saturate
+        (f32::from(raw) * self.gain + self.offset + 0.5) as u16
+    }
+}
+
+/// ADC shared between multiple drivers.
+pub struct AdcService<'hw, A: Adc> {
+    adc: Mutex<GlobalRawMutex, A>,
+    calibration: &'hw [(A::Channel, Calibration)],
+}
+
+impl<'hw, A: Adc> AdcService<'hw, A> {
+    /// Create a new service, `calibration` lists the correction for each channel that needs one.
+    pub fn new(adc: A, calibration: &'hw [(A::Channel, Calibration)]) -> Self {
+        Self {
+            adc: Mutex::new(adc),
+            calibration,
+        }
+    }
+
+    /// Returns the calibration applied to `channel`.
+    pub fn calibration(&self, channel: A::Channel) -> Calibration {
+        self.calibration
+            .iter()
+            .find_map(|&(c, calibration)| (c == channel).then_some(calibration))
+            .unwrap_or_default()
+    }
+
+    /// Performs a single conversion on `channel` and returns the raw reading, waiting for any other conversion to
+    /// finish first.
+    pub async fn sample_raw(&self, channel: A::Channel) -> Result<u16, A::Error> {
+        self.adc.lock().await.convert(channel).await
+    }
+
+    /// Performs a single conversion on `channel` and returns the calibrated reading.
+    pub async fn sample(&self, channel: A::Channel) -> Result<u16, A::Error> {
+        let raw = self.sample_raw(channel).await?;
+        Ok(self.calibration(channel).apply(raw))
+    }
+
+    /// Returns a handle for sampling `channel`.
+    pub fn channel(&self, channel: A::Channel) -> Channel<'_, 'hw, A> {
+        Channel { service: self, channel }
+    }
+}
+
+/// Handle for sampling a single ADC channel.
+pub struct Channel<'a, 'hw, A: Adc> {
+    service: &'a AdcService<'hw, A>,
+    channel: A::Channel,
+}
+
+impl<'a, 'hw, A: Adc> Channel<'a, 'hw, A> {
+    /// Performs a single conversion and returns the calibrated reading.
+    pub async fn sample(&self) -> Result<u16, A::Error> {
+        self.service.sample(self.channel).await
+    }
+
+    /// Converts the handle into a sampler taking a reading every `period`.
+    pub fn periodic(self, period: Duration) -> PeriodicSampler<'a, 'hw, A> {
+        PeriodicSampler {
+            channel: self,
+            ticker: Ticker::every(period),
+        }
+    }
+}
+
+/// Samples an ADC channel at a fixed period.
+pub struct PeriodicSampler<'a, 'hw, A: Adc> {
+    channel: Channel<'a, 'hw, A>,
+    ticker: Ticker,
+}
+
+impl<'a, 'hw, A: Adc> PeriodicSampler<'a, 'hw, A> {
+    /// Waits for the next period and returns the calibrated reading.
+    ///
+    /// Periods missed while waiting for the ADC are skipped rather than sampled back to back.
+    pub async fn next(&mut self) -> Result<u16, A::Error> {
+        self.ticker.next().await;
+        self.channel.sample().await
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+    use embassy_futures::join::join;
+    use embassy_futures::yield_now;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Input {
+        Thermistor,
+        CurrentSense,
+    }
+
+    /// ADC returning a fixed reading per channel, tracking overlapping conversions
+    #[derive(Default)]
+    struct MockAdc {
+        converting: bool,
+        conversions: u8,
+    }
+
+    impl Adc for MockAdc {
+        type Channel = Input;
+        type Error = ();
+
+        async fn convert(&mut self, channel: Input) -> Result<u16, ()> {
+            assert!(!self.converting);
+            self.converting = true;
+            yield_now().await;
+            self.converting = false;
+            self.conversions += 1;
+            Ok(match channel {
+                Input::Thermistor => 1000,
+                Input::CurrentSense => 2000,
+            })
+        }
+    }
+
+    #[test]
+    fn calibration_saturates() {
+        let calibration = Calibration {
+            gain: 2.0,
+            offset: -10.0,
+        };
+        assert_eq!(calibration.apply(100), 190);
+        assert_eq!(calibration.apply(0), 0);
+        assert_eq!(calibration.apply(u16::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn channels_are_calibrated_and_serialized() {
+        let calibration = [(Input::Thermistor, Calibration { gain: 1.0, offset: 5.0 })];
+        let service = AdcService::new(MockAdc::default(), &calibration);
+        let thermistor = service.channel(Input::Thermistor);
+        let current = service.channel(Input::CurrentSense);
+
+        let (thermistor, current) = block_on(join(thermistor.sample(), current.sample()));
+        assert_eq!(thermistor.unwrap(), 1005);
+        assert_eq!(current.unwrap(), 2000);
+        assert_eq!(block_on(service.adc.lock()).conversions, 2);
+    }
+}
@@ -18,6 +18,8 @@ use embedded_batteries_async::{
     },
 };
 
+use crate::state_machine::{LifecycleEvent, LifecycleState, StateMachine, TransitionListener};
+
 /// Fuel gauge errors.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -27,6 +29,19 @@ pub enum FuelGaugeError {
     Timeout,
     /// The underlying bus reported an error.
     BusError,
+    /// The fuel gauge didn't acknowledge its address, e.g. because the battery was removed.
+    NotAcknowledged,
+}
+
+impl FuelGaugeError {
+    /// Error code reported to the host alongside the battery error.
+    pub const fn code(self) -> u32 {
+        match self {
+            Self::Timeout => 1,
+            Self::BusError => 2,
+            Self::NotAcknowledged => 3,
+        }
+    }
 }
 
 impl From<core::convert::Infallible> for FuelGaugeError {
@@ -340,6 +355,47 @@ impl StaticBatteryData for StaticBatteryMsgs {
 pub trait DynamicBatteryData {
     /// Returns a reference to the standard dynamic battery data.
     fn standard(&self) -> &DynamicBatteryMsgs;
+
+    /// Returns the safety faults reported by the fuel gauge.
+    ///
+    /// The default derives them from the Smart Battery `BatteryStatus` alarms, which don't report short circuits.
+    /// OEM types that cache a gauge specific safety status should override this to report every fault.
+    fn safety_faults(&self) -> SafetyFaults {
+        SafetyFaults::from_battery_status(self.standard().battery_status)
+    }
+}
+
+/// Safety faults reported by the fuel gauge.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SafetyFaults {
+    /// Cell temperature is outside the safe operating range.
+    pub over_temperature: bool,
+    /// Cell voltage is above the safe charging limit.
+    pub over_voltage: bool,
+    /// A short circuit was detected.
+    pub short_circuit: bool,
+}
+
+impl SafetyFaults {
+    /// `BatteryStatus` OVER_CHARGED_ALARM bit.
+    const OVER_CHARGED_ALARM: u16 = 1 << 15;
+    /// `BatteryStatus` OVER_TEMP_ALARM bit.
+    const OVER_TEMP_ALARM: u16 = 1 << 12;
+
+    /// Decode the faults reported through the Smart Battery `BatteryStatus` alarms.
+    pub const fn from_battery_status(battery_status: u16) -> Self {
+        Self {
+            over_temperature: battery_status & Self::OVER_TEMP_ALARM != 0,
+            over_voltage: battery_status & Self::OVER_CHARGED_ALARM != 0,
+            short_circuit: false,
+        }
+    }
+
+    /// Returns true if any fault is active.
+    pub const fn any(&self) -> bool {
+        self.over_temperature || self.over_voltage || self.short_circuit
+    }
 }
 
 impl DynamicBatteryData for DynamicBatteryMsgs {
@@ -348,6 +404,32 @@ impl DynamicBatteryData for DynamicBatteryMsgs {
     }
 }
 
+/// Host-requested measurement configuration (ACPI `_BMS` and `_BMA`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MeasurementConfig {
+    /// Requested sampling time in milliseconds, `None` if the host hasn't set one.
+    ///
+    /// Drivers should poll dynamic data at this interval when set.
+    pub sampling_time_ms: Option<u32>,
+    /// Requested averaging interval in milliseconds, `None` if the host hasn't set one.
+    pub averaging_interval_ms: Option<u32>,
+    /// `true` if the fuel gauge applies the averaging interval in hardware.
+    ///
+    /// Otherwise [`State`] averages the reported current and voltage in software.
+    pub hardware_averaging: bool,
+}
+
+/// Current and voltage averaged in software over the requested averaging interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AveragedMeasurements {
+    /// Averaged battery current in mA.
+    pub current: MilliAmpsSigned,
+    /// Averaged battery voltage in mV.
+    pub voltage: MilliVolts,
+}
+
 /// Operational state substates.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -394,6 +476,10 @@ pub struct State<S: StaticBatteryData = StaticBatteryMsgs, D: DynamicBatteryData
     state: InternalState,
     static_cache: S,
     dynamic_cache: D,
+    measurement: MeasurementConfig,
+    average: Option<AveragedMeasurements>,
+    lifecycle: StateMachine,
+    last_error: Option<FuelGaugeError>,
 }
 
 impl<S: StaticBatteryData, D: DynamicBatteryData> State<S, D> {
@@ -402,6 +488,29 @@ impl<S: StaticBatteryData, D: DynamicBatteryData> State<S, D> {
         self.state
     }
 
+    /// The error that made the fuel gauge non-operational, cleared once it recovers.
+    pub fn last_error(&self) -> Option<FuelGaugeError> {
+        self.last_error
+    }
+
+    /// The current lifecycle state.
+    pub fn lifecycle_state(&self) -> LifecycleState {
+        self.lifecycle.state()
+    }
+
+    /// Subscribe to lifecycle transitions, replacing any previous subscriber.
+    pub fn subscribe_transitions(&mut self, listener: &'static dyn TransitionListener) {
+        self.lifecycle.subscribe(listener);
+    }
+
+    /// Feed an event to the lifecycle state machine.
+    ///
+    /// Events that don't apply to the current lifecycle state, e.g. a repeated initialization, are rejected by the
+    /// state machine and otherwise ignored.
+    fn lifecycle_event(&mut self, event: LifecycleEvent) {
+        let _ = self.lifecycle.handle(event);
+    }
+
     /// A reference to the cached static battery data.
     pub fn static_cache(&self) -> &S {
         &self.static_cache
@@ -446,6 +555,18 @@ impl<S: StaticBatteryData, D: DynamicBatteryData> State<S, D> {
     /// driver after hardware initialization succeeds.
     pub fn on_initialized(&mut self) {
         self.state = InternalState::Present(PresentSubstate::Operational(OperationalSubstate::Init));
+        self.lifecycle_event(LifecycleEvent::Detected);
+    }
+
+    /// Handle the battery being absent.
+    ///
+    /// Transitions to `NotPresent`. Should be called by the driver when it detects that no battery is present. The
+    /// cached data is kept but no longer reported, it is replaced once the next battery has been initialized.
+    pub fn on_removed(&mut self) {
+        self.state = InternalState::NotPresent;
+        // Samples from the old battery must not leak into the average of the next one
+        self.average = None;
+        self.lifecycle_event(LifecycleEvent::Removed);
     }
 
     /// Update the cached static battery data in place.
@@ -468,9 +589,75 @@ impl<S: StaticBatteryData, D: DynamicBatteryData> State<S, D> {
     /// writes the freshly read values directly into it, so a (potentially large)
     /// `D` is never moved or copied through this call. Should be called by the
     /// driver after a successful dynamic-data read while in the
-    /// `Present(Operational(Polling))` state.
+    /// `Present(Operational(Polling))` state. The sign of the reported current
+    /// moves the lifecycle state between `Present`, `Charging` and `Discharging`.
     pub fn on_dynamic_data(&mut self, update: impl FnOnce(&mut D)) {
         update(&mut self.dynamic_cache);
+        self.update_average();
+
+        let event = match self.dynamic_cache.standard().current {
+            current if current > 0 => LifecycleEvent::ChargeStarted,
+            current if current < 0 => LifecycleEvent::DischargeStarted,
+            _ => LifecycleEvent::Idle,
+        };
+        self.lifecycle_event(event);
+    }
+
+    /// The host-requested measurement configuration.
+    pub fn measurement_config(&self) -> MeasurementConfig {
+        self.measurement
+    }
+
+    /// Update the host-requested measurement configuration.
+    ///
+    /// Restarts any software averaging. Called by the battery service when handling `_BMS` and `_BMA`.
+    pub fn on_measurement_config(&mut self, config: MeasurementConfig) {
+        self.measurement = config;
+        self.average = None;
+    }
+
+    /// Current and voltage averaged in software, `None` if software averaging isn't active or no
+    /// samples have been collected since the configuration changed.
+    pub fn averaged_measurements(&self) -> Option<AveragedMeasurements> {
+        self.average
+    }
+
+    /// Fold the latest dynamic data into the software average.
+    fn update_average(&mut self) {
+        let Some(interval_ms) = self.measurement.averaging_interval_ms else {
+            return;
+        };
+        if self.measurement.hardware_averaging {
+            return;
+        }
+
+        let sampling_time_ms = self
+            .measurement
+            .sampling_time_ms
+            .unwrap_or(self.static_cache.standard().min_sample_time_ms);
+        // Number of samples in the averaging window, at least one
+        let window = i32::try_from(interval_ms / sampling_time_ms.max(1))
+            .unwrap_or(i32::MAX)
+            .max(1);
+
+        let sample = self.dynamic_cache.standard();
+        self.average = Some(match self.average {
+            None => AveragedMeasurements {
+                current: sample.current,
+                voltage: sample.voltage,
+            },
+            // Exponential moving average, the result always lies between the previous average and the sample
+            Some(average) => {
+                let current =
+                    i32::from(average.current) + (i32::from(sample.current) - i32::from(average.current)) / window;
+                let voltage =
+                    i32::from(average.voltage) + (i32::from(sample.voltage) - i32::from(average.voltage)) / window;
+                AveragedMeasurements {
+                    current: MilliAmpsSigned::try_from(current).unwrap_or(average.current),
+                    voltage: MilliVolts::try_from(voltage).unwrap_or(average.voltage),
+                }
+            }
+        });
     }
 
     /// Handle a communication timeout.
@@ -478,8 +665,18 @@ impl<S: StaticBatteryData, D: DynamicBatteryData> State<S, D> {
     /// Transitions a present fuel gauge to `Present(NotOperational)`. Should be
     /// called by the driver when a communication timeout is detected.
     pub fn on_timeout(&mut self) {
+        self.on_error(FuelGaugeError::Timeout);
+    }
+
+    /// Handle a failed fuel gauge transaction.
+    ///
+    /// Like [`Self::on_timeout`], but records `error` so it can be reported to the host while the fuel gauge is
+    /// non-operational.
+    pub fn on_error(&mut self, error: FuelGaugeError) {
         if self.is_present() {
             self.state = InternalState::Present(PresentSubstate::NotOperational);
+            self.last_error = Some(error);
+            self.lifecycle_event(LifecycleEvent::CommunicationLost);
         }
     }
 
@@ -491,6 +688,8 @@ impl<S: StaticBatteryData, D: DynamicBatteryData> State<S, D> {
     pub fn on_recovered(&mut self) {
         if matches!(self.state, InternalState::Present(PresentSubstate::NotOperational)) {
             self.state = InternalState::Present(PresentSubstate::Operational(OperationalSubstate::Init));
+            self.last_error = None;
+            self.lifecycle_event(LifecycleEvent::Recovered);
         }
     }
 }
@@ -538,9 +737,69 @@ pub trait FuelGauge: embedded_batteries_async::smart_battery::SmartBattery {
     /// The driver should cache the result by calling [`State::on_dynamic_data`].
     fn update_dynamic_data(&mut self) -> impl Future<Output = Result<(), Self::FuelGaugeError>>;
 
+    /// Configure the hardware sampling time (ACPI `_BMS`).
+    ///
+    /// Returns `Ok(false)` if the fuel gauge can't be reconfigured, the default. Either way the driver should
+    /// poll dynamic data at [`MeasurementConfig::sampling_time_ms`].
+    fn set_sampling_time(
+        &mut self,
+        _sampling_time_ms: u32,
+    ) -> impl Future<Output = Result<bool, Self::FuelGaugeError>> {
+        async { Ok(false) }
+    }
+
+    /// Configure the hardware averaging interval (ACPI `_BMA`).
+    ///
+    /// Returns `Ok(false)` if the fuel gauge can't be reconfigured, the default. In that case [`State`] averages
+    /// current and voltage in software instead.
+    fn set_averaging_interval(
+        &mut self,
+        _averaging_interval_ms: u32,
+    ) -> impl Future<Output = Result<bool, Self::FuelGaugeError>> {
+        async { Ok(false) }
+    }
+
+    /// Detect whether the battery is present.
+    ///
+    /// The default reports a fixed battery that is always present. Drivers for removable batteries should override
+    /// this to read the platform's battery presence pin, or to report a NACKed address as absent if the bus driver
+    /// can tell a NACK from other bus errors. A communication failure alone doesn't mean the battery was removed, so
+    /// it should be returned as an error instead. The driver shouldn't drive the state machine from here, insertion
+    /// and removal are handled by the battery service.
+    fn detect_presence(&mut self) -> impl Future<Output = Result<bool, FuelGaugeError>> {
+        async { Ok(true) }
+    }
+
     /// Return an immutable reference to the current fuel gauge state.
     fn state(&self) -> &State<Self::StaticData, Self::DynamicData>;
 
     /// Return a mutable reference to the current fuel gauge state.
     fn state_mut(&mut self) -> &mut State<Self::StaticData, Self::DynamicData>;
 }
+
+/// Fuel gauge that can be programmed with a configuration image, e.g. a TI golden file flash stream.
+///
+/// The battery service streams the image to the fuel gauge through these raw register accesses, see
+/// `battery_service::gauge_config`.
+pub trait GaugeConfig: FuelGauge {
+    /// Read the signature of the configuration programmed in the fuel gauge.
+    ///
+    /// Returns `None` if the fuel gauge holds no known configuration, e.g. at first boot or after a gauge reset.
+    fn config_signature(&mut self) -> impl Future<Output = Result<Option<u32>, Self::FuelGaugeError>>;
+
+    /// Write `data` to `register` of the device at bus `address`.
+    fn write_config(
+        &mut self,
+        address: u8,
+        register: u8,
+        data: &[u8],
+    ) -> impl Future<Output = Result<(), Self::FuelGaugeError>>;
+
+    /// Read `data.len()` bytes from `register` of the device at bus `address`.
+    fn read_config(
+        &mut self,
+        address: u8,
+        register: u8,
+        data: &mut [u8],
+    ) -> impl Future<Output = Result<(), Self::FuelGaugeError>>;
+}
@@ -1,6 +1,7 @@
 #![no_std]
 
 pub mod fuel_gauge;
+pub mod state_machine;
 
 pub use embedded_batteries_async::acpi::{
     BatteryState, BatterySwapCapability, BatteryTechnology, Bct, BctReturnResult, Bma, Bmc, BmcControlFlags, Bmd,
@@ -70,6 +71,69 @@ pub struct BixFixedStrings {
     pub battery_swapping_capability: BatterySwapCapability,
 }
 
+/// Battery information in the ACPI _BIF layout, for hosts that don't support _BIX.
+#[derive(PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BifFixedStrings {
+    /// Unit used for capacity and rate values.
+    pub power_unit: PowerUnit,
+    /// Design capacity of the battery (in mWh or mAh).
+    pub design_capacity: u32,
+    /// Last full charge capacity (in mWh or mAh).
+    pub last_full_charge_capacity: u32,
+    /// Battery technology type.
+    pub battery_technology: BatteryTechnology,
+    /// Design voltage (in mV).
+    pub design_voltage: u32,
+    /// Warning capacity threshold (in mWh or mAh).
+    pub design_cap_of_warning: u32,
+    /// Low capacity threshold (in mWh or mAh).
+    pub design_cap_of_low: u32,
+    /// Capacity granularity between low and warning (in mWh or mAh).
+    pub battery_capacity_granularity_1: u32,
+    /// Capacity granularity between warning and full (in mWh or mAh).
+    pub battery_capacity_granularity_2: u32,
+    /// OEM-specific model number (ASCIIZ).
+    pub model_number: [u8; STD_BIX_MODEL_SIZE],
+    /// OEM-specific serial number (ASCIIZ).
+    pub serial_number: [u8; STD_BIX_SERIAL_SIZE],
+    /// OEM-specific battery type (ASCIIZ).
+    pub battery_type: [u8; STD_BIX_BATTERY_SIZE],
+    /// OEM-specific information (ASCIIZ).
+    pub oem_info: [u8; STD_BIX_OEM_SIZE],
+}
+
+impl From<BixFixedStrings> for BifFixedStrings {
+    fn from(bix: BixFixedStrings) -> Self {
+        Self {
+            power_unit: bix.power_unit,
+            design_capacity: bix.design_capacity,
+            last_full_charge_capacity: bix.last_full_charge_capacity,
+            battery_technology: bix.battery_technology,
+            design_voltage: bix.design_voltage,
+            design_cap_of_warning: bix.design_cap_of_warning,
+            design_cap_of_low: bix.design_cap_of_low,
+            battery_capacity_granularity_1: bix.battery_capacity_granularity_1,
+            battery_capacity_granularity_2: bix.battery_capacity_granularity_2,
+            model_number: bix.model_number,
+            serial_number: bix.serial_number,
+            battery_type: bix.battery_type,
+            oem_info: bix.oem_info,
+        }
+    }
+}
+
+/// Layout of the static battery information returned to the host.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BatteryInfoFormat {
+    /// _BIX revision 1.
+    #[default]
+    Bix,
+    /// _BIF, for hosts that predate _BIX. _BIX requests are answered with _BIF data.
+    Bif,
+}
+
 #[derive(PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct PifFixedStrings {
@@ -91,6 +155,78 @@ pub struct PifFixedStrings {
     pub oem_info: [u8; STD_PIF_OEM_SIZE],
 }
 
+/// Result of a measurement configuration request. Corresponds to the return value of ACPI's _BMS and _BMA methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u32)]
+pub enum MeasurementStatus {
+    /// The new value was applied.
+    Success = 0,
+    /// The requested value is outside the range reported in _BIX.
+    OutOfRange = 1,
+    /// The fuel gauge failed to apply the new value, reported as the ACPI battery hardware failure code.
+    HardwareFailure = 0xFFFF_FFFF,
+}
+
+impl From<MeasurementStatus> for u32 {
+    fn from(value: MeasurementStatus) -> Self {
+        value as u32
+    }
+}
+
+/// Battery-saver charge limit.
+///
+/// Charging is inhibited once the state of charge reaches the ceiling and resumes once it drops to the resume
+/// threshold, so the battery isn't topped up on every small discharge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChargeLimit {
+    ceiling_percent: u8,
+    resume_percent: u8,
+}
+
+impl ChargeLimit {
+    /// Create a new charge limit, returns `None` unless `resume_percent < ceiling_percent <= 100`.
+    pub const fn new(ceiling_percent: u8, resume_percent: u8) -> Option<Self> {
+        if resume_percent < ceiling_percent && ceiling_percent <= 100 {
+            Some(Self {
+                ceiling_percent,
+                resume_percent,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// State of charge at which charging is inhibited.
+    pub const fn ceiling_percent(&self) -> u8 {
+        self.ceiling_percent
+    }
+
+    /// State of charge at or below which charging resumes.
+    pub const fn resume_percent(&self) -> u8 {
+        self.resume_percent
+    }
+}
+
+/// Current charge limit configuration and enforcement state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChargeLimitStatus {
+    /// The configured limit, `None` if charging is unlimited.
+    pub limit: Option<ChargeLimit>,
+    /// Whether charging is currently inhibited by the limit.
+    pub inhibited: bool,
+}
+
+/// Requested completion time for an adaptive charging schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChargeTarget {
+    /// Seconds from now by which the battery should be fully charged.
+    pub seconds_until_complete: u32,
+}
+
 /// Fuel gauge ID
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -110,12 +246,25 @@ pub trait BatteryService {
         battery_id: DeviceId,
     ) -> impl core::future::Future<Output = Result<BixFixedStrings, BatteryError>>;
 
+    /// Bitmask of battery device IDs, with bit N set if battery N is present. Reported to the host as the battery
+    /// capabilities.
+    ///
+    /// By default, a single battery with device ID 0.
+    fn battery_mask(&self) -> u32 {
+        1
+    }
+
+    /// Returns the layout of the static battery information expected by the host.
+    fn battery_info_format(&self) -> BatteryInfoFormat {
+        BatteryInfoFormat::Bix
+    }
+
     /// Sets the averaging interval of battery capacity measurement in milliseconds. Corresponds to ACPI's _BMA method.
     fn set_battery_measurement_averaging_interval(
         &self,
         battery_id: DeviceId,
         bma: Bma,
-    ) -> impl core::future::Future<Output = Result<(), BatteryError>>;
+    ) -> impl core::future::Future<Output = Result<MeasurementStatus, BatteryError>>;
 
     /// Battery maintenance control. Corresponds to ACPI's _BMC method.
     fn battery_maintenance_control(
@@ -135,7 +284,7 @@ pub trait BatteryService {
         &self,
         battery_id: DeviceId,
         battery_measurement_sampling: Bms,
-    ) -> impl core::future::Future<Output = Result<(), BatteryError>>;
+    ) -> impl core::future::Future<Output = Result<MeasurementStatus, BatteryError>>;
 
     /// Queries the current power characteristics of the battery. Corresponds to ACPI's _BPC method.
     fn battery_power_characteristics(
@@ -193,6 +342,22 @@ pub trait BatteryService {
         &self,
         battery_id: DeviceId,
     ) -> impl core::future::Future<Output = Result<StaReturn, BatteryError>>;
+
+    /// Sets the battery-saver charge limit, `None` removes the limit. The setting persists across resets.
+    fn set_charge_limit(
+        &self,
+        limit: Option<ChargeLimit>,
+    ) -> impl core::future::Future<Output = Result<(), BatteryError>>;
+
+    /// Queries the battery-saver charge limit and whether it is currently inhibiting charge.
+    fn charge_limit_status(&self) -> impl core::future::Future<Output = Result<ChargeLimitStatus, BatteryError>>;
+
+    /// Schedules charging to complete by the given target, trickle charging until fast charge is needed to finish
+    /// on time. `None` cancels the schedule and charges as fast as possible.
+    fn set_charge_target(
+        &self,
+        target: Option<ChargeTarget>,
+    ) -> impl core::future::Future<Output = Result<(), BatteryError>>;
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -204,4 +369,46 @@ pub enum BatteryError {
 
     /// An unknown error occurred while processing the request.
     UnspecifiedFailure,
+
+    /// The request is valid but the feature it needs isn't configured on this platform.
+    Unsupported,
+
+    /// The bus to the fuel gauge reported an error.
+    BusError {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+
+    /// The fuel gauge timed out responding.
+    Timeout {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+
+    /// No battery is present.
+    NotPresent {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+
+    /// The fuel gauge is not in a state that can service the request, e.g. static data hasn't been read yet.
+    InvalidState {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+}
+
+impl From<fuel_gauge::FuelGaugeError> for BatteryError {
+    fn from(error: fuel_gauge::FuelGaugeError) -> Self {
+        match error {
+            fuel_gauge::FuelGaugeError::Timeout => BatteryError::Timeout {
+                error_code: error.code(),
+            },
+            fuel_gauge::FuelGaugeError::BusError | fuel_gauge::FuelGaugeError::NotAcknowledged => {
+                BatteryError::BusError {
+                    error_code: error.code(),
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,227 @@
+//! Battery lifecycle state machine.
+//!
+//! The fuel gauge [`State`](crate::fuel_gauge::State) drives a [`StateMachine`] from its `on_*` transition methods.
+//! Every legal transition is listed in [`next_state`], events that don't apply to the current state are rejected and
+//! leave the state unchanged.
+//!
+//! | From                                                  | Event               | To            |
+//! |-------------------------------------------------------|---------------------|---------------|
+//! | `Init`, `NotPresent`                                  | `Detected`          | `Present`     |
+//! | `Init`, `Present`, `Charging`, `Discharging`, `Fault` | `Removed`           | `NotPresent`  |
+//! | `Present`, `Discharging`                              | `ChargeStarted`     | `Charging`    |
+//! | `Present`, `Charging`                                 | `DischargeStarted`  | `Discharging` |
+//! | `Charging`, `Discharging`                             | `Idle`              | `Present`     |
+//! | `Present`, `Charging`, `Discharging`                  | `CommunicationLost` | `Fault`       |
+//! | `Fault`                                               | `Recovered`         | `Present`     |
+
+/// Battery lifecycle state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LifecycleState {
+    /// Battery presence hasn't been determined yet.
+    #[default]
+    Init,
+    /// No battery is present.
+    NotPresent,
+    /// A battery is present, neither charging nor discharging.
+    Present,
+    /// The battery is charging.
+    Charging,
+    /// The battery is discharging.
+    Discharging,
+    /// The battery is present but communication with the fuel gauge has been lost.
+    Fault,
+}
+
+/// Events that drive the lifecycle state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LifecycleEvent {
+    /// The fuel gauge was detected and initialized.
+    Detected,
+    /// The battery was found to be absent.
+    Removed,
+    /// Current started flowing into the battery.
+    ChargeStarted,
+    /// Current started flowing out of the battery.
+    DischargeStarted,
+    /// Current stopped flowing.
+    Idle,
+    /// The fuel gauge stopped responding.
+    CommunicationLost,
+    /// Communication with the fuel gauge was re-established.
+    Recovered,
+}
+
+/// A state transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Transition {
+    /// State before the event.
+    pub from: LifecycleState,
+    /// Event that caused the transition.
+    pub event: LifecycleEvent,
+    /// State after the event.
+    pub to: LifecycleState,
+}
+
+/// An event that isn't legal in the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidTransition {
+    /// The current state.
+    pub state: LifecycleState,
+    /// The rejected event.
+    pub event: LifecycleEvent,
+}
+
+/// Returns the state reached by handling `event` in `state`, `None` if the transition is illegal.
+pub const fn next_state(state: LifecycleState, event: LifecycleEvent) -> Option<LifecycleState> {
+    use LifecycleEvent as E;
+    use LifecycleState as S;
+
+    match (state, event) {
+        (S::Init | S::NotPresent, E::Detected) => Some(S::Present),
+        (S::Init | S::Present | S::Charging | S::Discharging | S::Fault, E::Removed) => Some(S::NotPresent),
+        (S::Present | S::Discharging, E::ChargeStarted) => Some(S::Charging),
+        (S::Present | S::Charging, E::DischargeStarted) => Some(S::Discharging),
+        (S::Charging | S::Discharging, E::Idle) => Some(S::Present),
+        (S::Present | S::Charging | S::Discharging, E::CommunicationLost) => Some(S::Fault),
+        (S::Fault, E::Recovered) => Some(S::Present),
+        _ => None,
+    }
+}
+
+/// Receives lifecycle transitions.
+pub trait TransitionListener {
+    /// Called after each accepted transition, must not block.
+    fn on_transition(&self, transition: Transition);
+}
+
+/// Lifecycle state machine with an optional transition subscriber.
+#[derive(Default)]
+pub struct StateMachine {
+    state: LifecycleState,
+    listener: Option<&'static dyn TransitionListener>,
+}
+
+impl StateMachine {
+    /// The current state.
+    pub fn state(&self) -> LifecycleState {
+        self.state
+    }
+
+    /// Subscribe to transitions, replacing any previous subscriber.
+    pub fn subscribe(&mut self, listener: &'static dyn TransitionListener) {
+        self.listener = Some(listener);
+    }
+
+    /// Handle an event, rejecting it if it's not legal in the current state.
+    pub fn handle(&mut self, event: LifecycleEvent) -> Result<Transition, InvalidTransition> {
+        let to = next_state(self.state, event).ok_or(InvalidTransition {
+            state: self.state,
+            event,
+        })?;
+        let transition = Transition {
+            from: self.state,
+            event,
+            to,
+        };
+        self.state = to;
+        if let Some(listener) = self.listener {
+            listener.on_transition(transition);
+        }
+        Ok(transition)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    const STATES: [LifecycleState; 6] = [
+        LifecycleState::Init,
+        LifecycleState::NotPresent,
+        LifecycleState::Present,
+        LifecycleState::Charging,
+        LifecycleState::Discharging,
+        LifecycleState::Fault,
+    ];
+
+    #[test]
+    fn transition_table() {
+        use LifecycleEvent as E;
+        use LifecycleState as S;
+
+        // Expected destination for each event, indexed like `STATES`
+        let table = [
+            (
+                E::Detected,
+                [Some(S::Present), Some(S::Present), None, None, None, None],
+            ),
+            (
+                E::Removed,
+                [
+                    Some(S::NotPresent),
+                    None,
+                    Some(S::NotPresent),
+                    Some(S::NotPresent),
+                    Some(S::NotPresent),
+                    Some(S::NotPresent),
+                ],
+            ),
+            (
+                E::ChargeStarted,
+                [None, None, Some(S::Charging), None, Some(S::Charging), None],
+            ),
+            (
+                E::DischargeStarted,
+                [None, None, Some(S::Discharging), Some(S::Discharging), None, None],
+            ),
+            (E::Idle, [None, None, None, Some(S::Present), Some(S::Present), None]),
+            (
+                E::CommunicationLost,
+                [None, None, Some(S::Fault), Some(S::Fault), Some(S::Fault), None],
+            ),
+            (E::Recovered, [None, None, None, None, None, Some(S::Present)]),
+        ];
+
+        for (event, expected) in table {
+            for (state, expected) in STATES.into_iter().zip(expected) {
+                assert_eq!(next_state(state, event), expected, "{state:?} on {event:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_jump_rejected() {
+        let mut machine = StateMachine::default();
+        assert_eq!(
+            machine.handle(LifecycleEvent::ChargeStarted),
+            Err(InvalidTransition {
+                state: LifecycleState::Init,
+                event: LifecycleEvent::ChargeStarted,
+            })
+        );
+        assert_eq!(machine.state(), LifecycleState::Init);
+    }
+
+    #[test]
+    fn subscriber_notified() {
+        struct Listener(AtomicUsize);
+        impl TransitionListener for Listener {
+            fn on_transition(&self, _transition: Transition) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        static LISTENER: Listener = Listener(AtomicUsize::new(0));
+
+        let mut machine = StateMachine::default();
+        machine.subscribe(&LISTENER);
+        machine.handle(LifecycleEvent::Detected).unwrap();
+        assert!(machine.handle(LifecycleEvent::Recovered).is_err());
+        assert_eq!(LISTENER.0.load(Ordering::Relaxed), 1);
+    }
+}
@@ -16,6 +16,11 @@ impl<S: battery_service_interface::BatteryService> BatteryServiceRelayHandler<S>
     pub fn new(service: S) -> Self {
         Self { service }
     }
+
+    /// Returns the battery service used to handle requests.
+    pub fn service(&self) -> &S {
+        &self.service
+    }
 }
 
 impl<S: battery_service_interface::BatteryService> embedded_services::relay::mctp::RelayServiceHandlerTypes
@@ -31,8 +36,16 @@ impl<S: battery_service_interface::BatteryService> embedded_services::relay::mct
     async fn process_request(&self, request: Self::RequestType) -> Self::ResultType {
         trace!("Battery service: ACPI cmd recvd");
         Ok(match request {
-            AcpiBatteryRequest::GetBix { battery_id } => AcpiBatteryResponse::GetBix {
-                bix: self.service.battery_info(DeviceId(battery_id)).await?,
+            AcpiBatteryRequest::GetBix { battery_id } => {
+                let bix = self.service.battery_info(DeviceId(battery_id)).await?;
+                // Hosts that only understand _BIF get its layout, tagged as a _BIF response so it can't be misparsed
+                match self.service.battery_info_format() {
+                    BatteryInfoFormat::Bix => AcpiBatteryResponse::GetBix { bix },
+                    BatteryInfoFormat::Bif => AcpiBatteryResponse::GetBif { bif: bix.into() },
+                }
+            }
+            AcpiBatteryRequest::GetBif { battery_id } => AcpiBatteryResponse::GetBif {
+                bif: self.service.battery_info(DeviceId(battery_id)).await?.into(),
             },
             AcpiBatteryRequest::GetBst { battery_id } => AcpiBatteryResponse::GetBst {
                 bst: self.service.battery_status(DeviceId(battery_id)).await?,
@@ -76,25 +89,37 @@ impl<S: battery_service_interface::BatteryService> embedded_services::relay::mct
                 btm_response: self.service.battery_time_to_empty(DeviceId(battery_id), btm).await?,
             },
 
-            AcpiBatteryRequest::SetBms { battery_id, bms } => {
-                self.service
+            AcpiBatteryRequest::SetBms { battery_id, bms } => AcpiBatteryResponse::SetBms {
+                status: self
+                    .service
                     .set_battery_measurement_sampling_time(DeviceId(battery_id), bms)
-                    .await?;
-                AcpiBatteryResponse::SetBms {
-                    status: 0, // TODO once we have a working reference platform, we should consider dropping this field, since it's redundant with the error type on Result.
-                }
-            }
-            AcpiBatteryRequest::SetBma { battery_id, bma } => {
-                self.service
+                    .await?
+                    .into(),
+            },
+            AcpiBatteryRequest::SetBma { battery_id, bma } => AcpiBatteryResponse::SetBma {
+                status: self
+                    .service
                     .set_battery_measurement_averaging_interval(DeviceId(battery_id), bma)
-                    .await?;
-                AcpiBatteryResponse::SetBma {
-                    status: 0, // TODO once we have a working reference platform, we should consider dropping this field, since it's redundant with the error type on Result.
-                }
-            }
+                    .await?
+                    .into(),
+            },
             AcpiBatteryRequest::GetSta { battery_id } => AcpiBatteryResponse::GetSta {
                 sta: self.service.device_status(DeviceId(battery_id)).await?,
             },
+            AcpiBatteryRequest::SetChargeLimit { limit } => {
+                self.service.set_charge_limit(limit).await?;
+                AcpiBatteryResponse::SetChargeLimit {}
+            }
+            AcpiBatteryRequest::GetChargeLimit {} => AcpiBatteryResponse::GetChargeLimit {
+                status: self.service.charge_limit_status().await?,
+            },
+            AcpiBatteryRequest::SetChargeTarget { target } => {
+                self.service.set_charge_target(target).await?;
+                AcpiBatteryResponse::SetChargeTarget {}
+            }
+            AcpiBatteryRequest::GetCaps {} => AcpiBatteryResponse::GetCaps {
+                battery_mask: self.service.battery_mask(),
+            },
         })
     }
 }
@@ -1,4 +1,5 @@
 use battery_service_interface::*;
+use embedded_services::relay::host_error::{ErrorClass, HostError};
 use embedded_services::relay::{MessageSerializationError, SerializableMessage};
 
 #[derive(num_enum::IntoPrimitive, num_enum::TryFromPrimitive, Copy, Clone, Debug, PartialEq)]
@@ -36,6 +37,16 @@ enum BatteryCmd {
     SetBma = 14,
     /// Device Status
     GetSta = 15,
+    /// Battery-saver charge limit
+    SetChargeLimit = 16,
+    /// Battery-saver charge limit status
+    GetChargeLimit = 17,
+    /// Adaptive charging schedule target
+    SetChargeTarget = 18,
+    /// Battery InFormation
+    GetBif = 19,
+    /// Battery capabilities
+    GetCaps = 20,
 }
 
 impl From<&AcpiBatteryRequest> for BatteryCmd {
@@ -56,6 +67,11 @@ impl From<&AcpiBatteryRequest> for BatteryCmd {
             AcpiBatteryRequest::SetBms { .. } => BatteryCmd::SetBms,
             AcpiBatteryRequest::SetBma { .. } => BatteryCmd::SetBma,
             AcpiBatteryRequest::GetSta { .. } => BatteryCmd::GetSta,
+            AcpiBatteryRequest::SetChargeLimit { .. } => BatteryCmd::SetChargeLimit,
+            AcpiBatteryRequest::GetChargeLimit {} => BatteryCmd::GetChargeLimit,
+            AcpiBatteryRequest::SetChargeTarget { .. } => BatteryCmd::SetChargeTarget,
+            AcpiBatteryRequest::GetBif { .. } => BatteryCmd::GetBif,
+            AcpiBatteryRequest::GetCaps {} => BatteryCmd::GetCaps,
         }
     }
 }
@@ -78,6 +94,11 @@ impl From<&AcpiBatteryResponse> for BatteryCmd {
             AcpiBatteryResponse::SetBms { .. } => BatteryCmd::SetBms,
             AcpiBatteryResponse::SetBma { .. } => BatteryCmd::SetBma,
             AcpiBatteryResponse::GetSta { .. } => BatteryCmd::GetSta,
+            AcpiBatteryResponse::SetChargeLimit {} => BatteryCmd::SetChargeLimit,
+            AcpiBatteryResponse::GetChargeLimit { .. } => BatteryCmd::GetChargeLimit,
+            AcpiBatteryResponse::SetChargeTarget {} => BatteryCmd::SetChargeTarget,
+            AcpiBatteryResponse::GetBif { .. } => BatteryCmd::GetBif,
+            AcpiBatteryResponse::GetCaps { .. } => BatteryCmd::GetCaps,
         }
     }
 }
@@ -130,12 +151,28 @@ pub enum AcpiBatteryResponse {
 
     /// Battery device status. Analogous to the return value of the _STA method.
     GetSta { sta: StaReturn },
+
+    /// Result of setting the battery-saver charge limit. Semantically equivalent to ().
+    SetChargeLimit {},
+
+    /// Battery-saver charge limit configuration and whether it is currently inhibiting charge.
+    GetChargeLimit { status: ChargeLimitStatus },
+
+    /// Result of setting the adaptive charging schedule target. Semantically equivalent to ().
+    SetChargeTarget {},
+
+    /// Battery information. Analogous to the return value of the _BIF method.
+    GetBif { bif: BifFixedStrings },
+
+    /// Battery capabilities, bit N of `battery_mask` is set if battery N is present.
+    GetCaps { battery_mask: u32 },
 }
 
 impl SerializableMessage for AcpiBatteryResponse {
     fn serialize(self, buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
         match self {
             Self::GetBix { bix } => bix_to_bytes(bix, buffer),
+            Self::GetBif { bif } => bif_to_bytes(bif, buffer),
             Self::GetBst { bst } => Ok(safe_put_dword(buffer, 0, bst.battery_state.bits())?
                 + safe_put_dword(buffer, 4, bst.battery_present_rate)?
                 + safe_put_dword(buffer, 8, bst.battery_remaining_capacity)?
@@ -165,6 +202,12 @@ impl SerializableMessage for AcpiBatteryResponse {
             Self::SetBms { status } => safe_put_dword(buffer, 0, status),
             Self::SetBma { status } => safe_put_dword(buffer, 0, status),
             Self::GetSta { sta } => safe_put_dword(buffer, 0, sta.bits()),
+            Self::SetChargeLimit {} => Ok(0),
+            Self::SetChargeTarget {} => Ok(0),
+            Self::GetChargeLimit { status } => {
+                Ok(charge_limit_to_bytes(status.limit, buffer)? + safe_put_u8(buffer, 2, status.inhibited.into())?)
+            }
+            Self::GetCaps { battery_mask } => safe_put_dword(buffer, 0, battery_mask),
         }
     }
 
@@ -176,6 +219,9 @@ impl SerializableMessage for AcpiBatteryResponse {
                 BatteryCmd::GetBix => Self::GetBix {
                     bix: bix_from_bytes(buffer)?,
                 },
+                BatteryCmd::GetBif => Self::GetBif {
+                    bif: bif_from_bytes(buffer)?,
+                },
                 BatteryCmd::GetBst => {
                     let bst = BstReturn {
                         battery_state: BatteryState::from_bits(safe_get_dword(buffer, 0)?)
@@ -244,6 +290,17 @@ impl SerializableMessage for AcpiBatteryResponse {
                     sta: StaReturn::from_bits(safe_get_dword(buffer, 0)?)
                         .ok_or(MessageSerializationError::InvalidPayload("Invalid STA flags"))?,
                 },
+                BatteryCmd::SetChargeLimit => Self::SetChargeLimit {},
+                BatteryCmd::SetChargeTarget => Self::SetChargeTarget {},
+                BatteryCmd::GetChargeLimit => Self::GetChargeLimit {
+                    status: ChargeLimitStatus {
+                        limit: charge_limit_from_bytes(buffer)?,
+                        inhibited: safe_get_u8(buffer, 2)? != 0,
+                    },
+                },
+                BatteryCmd::GetCaps => Self::GetCaps {
+                    battery_mask: safe_get_dword(buffer, 0)?,
+                },
             },
         )
     }
@@ -300,12 +357,28 @@ pub enum AcpiBatteryRequest {
 
     /// Queries the current status of the battery device. Analogous to ACPI's _STA method.
     GetSta { battery_id: u8 },
+
+    /// Sets the battery-saver charge limit, `None` removes the limit.
+    SetChargeLimit { limit: Option<ChargeLimit> },
+
+    /// Queries the battery-saver charge limit status.
+    GetChargeLimit {},
+
+    /// Sets the time by which charging should complete, `None` cancels the schedule.
+    SetChargeTarget { target: Option<ChargeTarget> },
+
+    /// Queries battery information. Analogous to ACPI's _BIF method.
+    GetBif { battery_id: u8 },
+
+    /// Queries which batteries are present.
+    GetCaps {},
 }
 
 impl SerializableMessage for AcpiBatteryRequest {
     fn serialize(self, buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
         match self {
             Self::GetBix { battery_id } => safe_put_u8(buffer, 0, battery_id),
+            Self::GetBif { battery_id } => safe_put_u8(buffer, 0, battery_id),
             Self::GetBst { battery_id } => safe_put_u8(buffer, 0, battery_id),
             Self::GetPsr { battery_id } => safe_put_u8(buffer, 0, battery_id),
             Self::GetPif { battery_id } => safe_put_u8(buffer, 0, battery_id),
@@ -336,6 +409,13 @@ impl SerializableMessage for AcpiBatteryRequest {
                 Ok(safe_put_u8(buffer, 0, battery_id)? + safe_put_dword(buffer, 1, bma.averaging_interval_ms)?)
             }
             Self::GetSta { battery_id } => safe_put_u8(buffer, 0, battery_id),
+            Self::SetChargeLimit { limit } => charge_limit_to_bytes(limit, buffer),
+            Self::GetChargeLimit {} => Ok(0),
+            Self::GetCaps {} => Ok(0),
+            // A zero target cancels the schedule
+            Self::SetChargeTarget { target } => {
+                safe_put_dword(buffer, 0, target.map_or(0, |target| target.seconds_until_complete))
+            }
         }
     }
 
@@ -347,6 +427,9 @@ impl SerializableMessage for AcpiBatteryRequest {
                 BatteryCmd::GetBix => Self::GetBix {
                     battery_id: safe_get_u8(buffer, 0)?,
                 },
+                BatteryCmd::GetBif => Self::GetBif {
+                    battery_id: safe_get_u8(buffer, 0)?,
+                },
                 BatteryCmd::GetBst => Self::GetBst {
                     battery_id: safe_get_u8(buffer, 0)?,
                 },
@@ -414,6 +497,17 @@ impl SerializableMessage for AcpiBatteryRequest {
                 BatteryCmd::GetSta => Self::GetSta {
                     battery_id: safe_get_u8(buffer, 0)?,
                 },
+                BatteryCmd::SetChargeLimit => Self::SetChargeLimit {
+                    limit: charge_limit_from_bytes(buffer)?,
+                },
+                BatteryCmd::GetChargeLimit => Self::GetChargeLimit {},
+                BatteryCmd::GetCaps => Self::GetCaps {},
+                BatteryCmd::SetChargeTarget => Self::SetChargeTarget {
+                    target: match safe_get_dword(buffer, 0)? {
+                        0 => None,
+                        seconds_until_complete => Some(ChargeTarget { seconds_until_complete }),
+                    },
+                },
             },
         )
     }
@@ -429,29 +523,124 @@ pub type AcpiBatteryResult = Result<AcpiBatteryResponse, AcpiBatteryError>;
 #[derive(num_enum::IntoPrimitive, num_enum::TryFromPrimitive, Copy, Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u16)]
+/// ACPI battery error discriminants
+enum BatteryErrorCode {
+    UnknownDeviceId = 1,
+    UnspecifiedFailure = 2,
+    BusError = 3,
+    Timeout = 4,
+    NotPresent = 5,
+    InvalidState = 6,
+    Unsupported = 7,
+}
+
+impl From<&AcpiBatteryError> for BatteryErrorCode {
+    fn from(error: &AcpiBatteryError) -> Self {
+        match error {
+            AcpiBatteryError::UnknownDeviceId => BatteryErrorCode::UnknownDeviceId,
+            AcpiBatteryError::UnspecifiedFailure => BatteryErrorCode::UnspecifiedFailure,
+            AcpiBatteryError::BusError { .. } => BatteryErrorCode::BusError,
+            AcpiBatteryError::Timeout { .. } => BatteryErrorCode::Timeout,
+            AcpiBatteryError::NotPresent { .. } => BatteryErrorCode::NotPresent,
+            AcpiBatteryError::InvalidState { .. } => BatteryErrorCode::InvalidState,
+            AcpiBatteryError::Unsupported => BatteryErrorCode::Unsupported,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Errors that can occur while processing ACPI battery requests.
+///
+/// Device errors carry a 32-bit payload slot for the underlying device specific error code.
 pub enum AcpiBatteryError {
     /// The provided battery ID does not correspond to any known battery device.
-    UnknownDeviceId = 1,
+    UnknownDeviceId,
 
     /// An unspecified error occurred while processing the request.
-    UnspecifiedFailure = 2,
+    UnspecifiedFailure,
+
+    /// The bus to the fuel gauge reported an error.
+    BusError {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+
+    /// The fuel gauge timed out responding.
+    Timeout {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+
+    /// No battery is present.
+    NotPresent {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+
+    /// The fuel gauge is not in a state that can service the request.
+    InvalidState {
+        /// Device specific error code, 0 if unavailable.
+        error_code: u32,
+    },
+
+    /// The request is valid but the feature it needs isn't configured on this platform.
+    Unsupported,
 }
 
 impl SerializableMessage for AcpiBatteryError {
-    fn serialize(self, _buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
+    fn serialize(self, buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
         match self {
-            AcpiBatteryError::UnknownDeviceId | AcpiBatteryError::UnspecifiedFailure => Ok(0),
+            AcpiBatteryError::UnknownDeviceId
+            | AcpiBatteryError::UnspecifiedFailure
+            | AcpiBatteryError::Unsupported => Ok(0),
+            AcpiBatteryError::BusError { error_code }
+            | AcpiBatteryError::Timeout { error_code }
+            | AcpiBatteryError::NotPresent { error_code }
+            | AcpiBatteryError::InvalidState { error_code } => safe_put_dword(buffer, 0, error_code),
         }
     }
 
-    fn deserialize(discriminant: u16, _buffer: &[u8]) -> Result<Self, MessageSerializationError> {
-        AcpiBatteryError::try_from(discriminant)
-            .map_err(|_| MessageSerializationError::UnknownMessageDiscriminant(discriminant))
+    fn deserialize(discriminant: u16, buffer: &[u8]) -> Result<Self, MessageSerializationError> {
+        Ok(
+            match BatteryErrorCode::try_from(discriminant)
+                .map_err(|_| MessageSerializationError::UnknownMessageDiscriminant(discriminant))?
+            {
+                BatteryErrorCode::UnknownDeviceId => Self::UnknownDeviceId,
+                BatteryErrorCode::UnspecifiedFailure => Self::UnspecifiedFailure,
+                BatteryErrorCode::BusError => Self::BusError {
+                    error_code: safe_get_dword(buffer, 0)?,
+                },
+                BatteryErrorCode::Timeout => Self::Timeout {
+                    error_code: safe_get_dword(buffer, 0)?,
+                },
+                BatteryErrorCode::NotPresent => Self::NotPresent {
+                    error_code: safe_get_dword(buffer, 0)?,
+                },
+                BatteryErrorCode::InvalidState => Self::InvalidState {
+                    error_code: safe_get_dword(buffer, 0)?,
+                },
+                BatteryErrorCode::Unsupported => Self::Unsupported,
+            },
+        )
     }
 
     fn discriminant(&self) -> u16 {
-        (*self).into()
+        BatteryErrorCode::from(self).into()
+    }
+}
+
+impl HostError for AcpiBatteryError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            AcpiBatteryError::UnknownDeviceId => ErrorClass::InvalidArgument,
+            AcpiBatteryError::UnspecifiedFailure => ErrorClass::Unspecified,
+            AcpiBatteryError::Unsupported => ErrorClass::Unsupported,
+            AcpiBatteryError::BusError { .. } => ErrorClass::Hardware,
+            AcpiBatteryError::Timeout { .. } => ErrorClass::Timeout,
+            // A battery may be inserted or finish identification later
+            AcpiBatteryError::NotPresent { .. } | AcpiBatteryError::InvalidState { .. } => ErrorClass::NotReady,
+        }
     }
 }
 
@@ -460,6 +649,11 @@ impl From<BatteryError> for AcpiBatteryError {
         match error {
             BatteryError::UnknownDeviceId => AcpiBatteryError::UnknownDeviceId,
             BatteryError::UnspecifiedFailure => AcpiBatteryError::UnspecifiedFailure,
+            BatteryError::BusError { error_code } => AcpiBatteryError::BusError { error_code },
+            BatteryError::Timeout { error_code } => AcpiBatteryError::Timeout { error_code },
+            BatteryError::NotPresent { error_code } => AcpiBatteryError::NotPresent { error_code },
+            BatteryError::InvalidState { error_code } => AcpiBatteryError::InvalidState { error_code },
+            BatteryError::Unsupported => AcpiBatteryError::Unsupported,
         }
     }
 }
@@ -578,6 +772,58 @@ fn bix_from_bytes(src_slice: &[u8]) -> Result<BixFixedStrings, MessageSerializat
     })
 }
 
+// _BIF has no revision, cycle count, measurement accuracy, sampling or averaging fields, so its strings start earlier
+const BIF_MODEL_NUM_START_IDX: usize = 36;
+const BIF_MODEL_NUM_END_IDX: usize = BIF_MODEL_NUM_START_IDX + STD_BIX_MODEL_SIZE;
+const BIF_SERIAL_NUM_START_IDX: usize = BIF_MODEL_NUM_END_IDX;
+const BIF_SERIAL_NUM_END_IDX: usize = BIF_SERIAL_NUM_START_IDX + STD_BIX_SERIAL_SIZE;
+const BIF_BATTERY_TYPE_START_IDX: usize = BIF_SERIAL_NUM_END_IDX;
+const BIF_BATTERY_TYPE_END_IDX: usize = BIF_BATTERY_TYPE_START_IDX + STD_BIX_BATTERY_SIZE;
+const BIF_OEM_INFO_START_IDX: usize = BIF_BATTERY_TYPE_END_IDX;
+const BIF_OEM_INFO_END_IDX: usize = BIF_OEM_INFO_START_IDX + STD_BIX_OEM_SIZE;
+
+fn bif_to_bytes(bif: BifFixedStrings, dst_slice: &mut [u8]) -> Result<usize, MessageSerializationError> {
+    if dst_slice.len() < BIF_OEM_INFO_END_IDX {
+        return Err(MessageSerializationError::BufferTooSmall);
+    }
+
+    Ok(safe_put_dword(dst_slice, 0, bif.power_unit.into())?
+        + safe_put_dword(dst_slice, 4, bif.design_capacity)?
+        + safe_put_dword(dst_slice, 8, bif.last_full_charge_capacity)?
+        + safe_put_dword(dst_slice, 12, bif.battery_technology.into())?
+        + safe_put_dword(dst_slice, 16, bif.design_voltage)?
+        + safe_put_dword(dst_slice, 20, bif.design_cap_of_warning)?
+        + safe_put_dword(dst_slice, 24, bif.design_cap_of_low)?
+        + safe_put_dword(dst_slice, 28, bif.battery_capacity_granularity_1)?
+        + safe_put_dword(dst_slice, 32, bif.battery_capacity_granularity_2)?
+        + safe_put_bytes(dst_slice, BIF_MODEL_NUM_START_IDX, &bif.model_number)?
+        + safe_put_bytes(dst_slice, BIF_SERIAL_NUM_START_IDX, &bif.serial_number)?
+        + safe_put_bytes(dst_slice, BIF_BATTERY_TYPE_START_IDX, &bif.battery_type)?
+        + safe_put_bytes(dst_slice, BIF_OEM_INFO_START_IDX, &bif.oem_info)?)
+}
+
+fn bif_from_bytes(src_slice: &[u8]) -> Result<BifFixedStrings, MessageSerializationError> {
+    Ok(BifFixedStrings {
+        power_unit: safe_get_dword(src_slice, 0)?
+            .try_into()
+            .map_err(|_| MessageSerializationError::InvalidPayload("Invalid PowerUnit"))?,
+        design_capacity: safe_get_dword(src_slice, 4)?,
+        last_full_charge_capacity: safe_get_dword(src_slice, 8)?,
+        battery_technology: safe_get_dword(src_slice, 12)?
+            .try_into()
+            .map_err(|_| MessageSerializationError::InvalidPayload("Invalid BatteryTechnology"))?,
+        design_voltage: safe_get_dword(src_slice, 16)?,
+        design_cap_of_warning: safe_get_dword(src_slice, 20)?,
+        design_cap_of_low: safe_get_dword(src_slice, 24)?,
+        battery_capacity_granularity_1: safe_get_dword(src_slice, 28)?,
+        battery_capacity_granularity_2: safe_get_dword(src_slice, 32)?,
+        model_number: safe_get_bytes::<STD_BIX_MODEL_SIZE>(src_slice, BIF_MODEL_NUM_START_IDX)?,
+        serial_number: safe_get_bytes::<STD_BIX_SERIAL_SIZE>(src_slice, BIF_SERIAL_NUM_START_IDX)?,
+        battery_type: safe_get_bytes::<STD_BIX_BATTERY_SIZE>(src_slice, BIF_BATTERY_TYPE_START_IDX)?,
+        oem_info: safe_get_bytes::<STD_BIX_OEM_SIZE>(src_slice, BIF_OEM_INFO_START_IDX)?,
+    })
+}
+
 const PIF_MODEL_NUM_START_IDX: usize = 12;
 const PIF_MODEL_NUM_END_IDX: usize = PIF_MODEL_NUM_START_IDX + STD_PIF_MODEL_SIZE;
 const PIF_SERIAL_NUM_START_IDX: usize = PIF_MODEL_NUM_END_IDX;
@@ -609,3 +855,112 @@ fn pif_from_bytes(src_slice: &[u8]) -> Result<PifFixedStrings, MessageSerializat
         oem_info: safe_get_bytes::<STD_PIF_OEM_SIZE>(src_slice, PIF_OEM_INFO_START_IDX)?,
     })
 }
+
+/// Charge limits are encoded as a ceiling byte followed by a resume threshold byte, a zero ceiling means no limit.
+fn charge_limit_to_bytes(limit: Option<ChargeLimit>, dst_slice: &mut [u8]) -> Result<usize, MessageSerializationError> {
+    let (ceiling, resume) = limit.map_or((0, 0), |limit| (limit.ceiling_percent(), limit.resume_percent()));
+    Ok(safe_put_u8(dst_slice, 0, ceiling)? + safe_put_u8(dst_slice, 1, resume)?)
+}
+
+fn charge_limit_from_bytes(src_slice: &[u8]) -> Result<Option<ChargeLimit>, MessageSerializationError> {
+    match (safe_get_u8(src_slice, 0)?, safe_get_u8(src_slice, 1)?) {
+        (0, _) => Ok(None),
+        (ceiling, resume) => ChargeLimit::new(ceiling, resume)
+            .map(Some)
+            .ok_or(MessageSerializationError::InvalidPayload("Invalid ChargeLimit")),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurement_status_round_trip() {
+        let cases = [
+            (MeasurementStatus::Success, [0x00, 0x00, 0x00, 0x00]),
+            (MeasurementStatus::OutOfRange, [0x01, 0x00, 0x00, 0x00]),
+            (MeasurementStatus::HardwareFailure, [0xff, 0xff, 0xff, 0xff]),
+        ];
+
+        for (status, expected) in cases {
+            for response in [
+                AcpiBatteryResponse::SetBms { status: status.into() },
+                AcpiBatteryResponse::SetBma { status: status.into() },
+            ] {
+                let discriminant = response.discriminant();
+                let mut buffer = [0u8; 4];
+                assert_eq!(response.serialize(&mut buffer).unwrap(), 4);
+                assert_eq!(buffer, expected);
+                assert!(AcpiBatteryResponse::deserialize(discriminant, &buffer).unwrap() == response);
+            }
+        }
+    }
+
+    #[test]
+    fn caps_round_trip() {
+        let request = AcpiBatteryRequest::GetCaps {};
+        let discriminant = request.discriminant();
+        assert_eq!(discriminant, 20);
+        assert_eq!(request.serialize(&mut []).unwrap(), 0);
+        assert!(AcpiBatteryRequest::deserialize(discriminant, &[]).unwrap() == request);
+
+        let response = AcpiBatteryResponse::GetCaps { battery_mask: 0b101 };
+        let mut buffer = [0u8; 4];
+        assert_eq!(response.serialize(&mut buffer).unwrap(), 4);
+        assert_eq!(buffer, [0x05, 0x00, 0x00, 0x00]);
+        assert!(AcpiBatteryResponse::deserialize(discriminant, &buffer).unwrap() == response);
+    }
+
+    #[test]
+    fn error_round_trip() {
+        let cases = [
+            (AcpiBatteryError::UnknownDeviceId, 1, 0),
+            (AcpiBatteryError::UnspecifiedFailure, 2, 0),
+            (AcpiBatteryError::BusError { error_code: 2 }, 3, 4),
+            (AcpiBatteryError::Timeout { error_code: 1 }, 4, 4),
+            (AcpiBatteryError::NotPresent { error_code: 0 }, 5, 4),
+            (AcpiBatteryError::InvalidState { error_code: 0x1234 }, 6, 4),
+            (AcpiBatteryError::Unsupported, 7, 0),
+        ];
+
+        for (error, discriminant, len) in cases {
+            assert_eq!(error.discriminant(), discriminant);
+            let mut buffer = [0u8; 4];
+            assert_eq!(error.serialize(&mut buffer).unwrap(), len);
+            assert_eq!(
+                AcpiBatteryError::deserialize(discriminant, buffer.get(..len).unwrap()).unwrap(),
+                error
+            );
+        }
+
+        // The error code is carried through from the fuel gauge error
+        let error = AcpiBatteryError::from(BatteryError::from(fuel_gauge::FuelGaugeError::NotAcknowledged));
+        let mut buffer = [0u8; 4];
+        assert_eq!(error.serialize(&mut buffer).unwrap(), 4);
+        assert_eq!(
+            u32::from_le_bytes(buffer),
+            fuel_gauge::FuelGaugeError::NotAcknowledged.code()
+        );
+
+        assert!(matches!(
+            AcpiBatteryError::deserialize(4, &[0; 2]),
+            Err(MessageSerializationError::BufferTooSmall)
+        ));
+    }
+
+    #[test]
+    fn error_class() {
+        // Features the platform didn't configure are reported as unsupported rather than as a failure
+        assert_eq!(
+            AcpiBatteryError::from(BatteryError::Unsupported).class(),
+            ErrorClass::Unsupported
+        );
+        assert_eq!(AcpiBatteryError::UnspecifiedFailure.class(), ErrorClass::Unspecified);
+        assert_eq!(
+            AcpiBatteryError::NotPresent { error_code: 0 }.class(),
+            ErrorClass::NotReady
+        );
+    }
+}
@@ -1,20 +1,25 @@
 #![allow(dead_code)]
 
 use battery_service_interface::BatteryError;
-use battery_service_interface::fuel_gauge::{DynamicBatteryData, FuelGauge, StaticBatteryData};
+use battery_service_interface::fuel_gauge::{
+    DynamicBatteryData, DynamicBatteryMsgs, FuelGauge, FuelGaugeError, InternalState, OperationalSubstate,
+    PresentSubstate, State, StaticBatteryData,
+};
 use embedded_batteries_async::acpi::{PowerSourceState, PowerUnit};
-use embedded_batteries_async::smart_battery::CapacityModeValue;
+use embedded_batteries_async::smart_battery::{CapacityModeValue, Minutes};
 use embedded_services::sync::Lockable;
-use embedded_services::{info, trace};
+use embedded_services::{error, info, trace};
 
 use battery_service_interface::{
-    BctReturnResult, BixFixedStrings, Bmd, Bpc, Bps, BstReturn, BtmReturnResult, DeviceId, PifFixedStrings, PsrReturn,
-    STD_BIX_BATTERY_SIZE, STD_BIX_MODEL_SIZE, STD_BIX_OEM_SIZE, STD_BIX_SERIAL_SIZE, STD_PIF_MODEL_SIZE,
-    STD_PIF_OEM_SIZE, STD_PIF_SERIAL_SIZE, StaReturn,
+    BctReturnResult, BixFixedStrings, Bmd, Bpc, Bps, BstReturn, BtmReturnResult, DeviceId, MeasurementStatus,
+    PifFixedStrings, PsrReturn, STD_BIX_BATTERY_SIZE, STD_BIX_MODEL_SIZE, STD_BIX_OEM_SIZE, STD_BIX_SERIAL_SIZE,
+    STD_PIF_MODEL_SIZE, STD_PIF_OEM_SIZE, STD_PIF_SERIAL_SIZE, StaReturn,
 };
 
 use power_policy_interface::capability::PowerCapability;
 
+use crate::TimeEstimation;
+
 /// Cached power-supply state used when answering ACPI power-source queries.
 ///
 /// Currently always the default; this is a placeholder for future power policy
@@ -35,13 +40,42 @@ fn capacity_raw(value: CapacityModeValue) -> u32 {
     }
 }
 
+/// Map the fuel gauge state to the error reported to the host when its cached data can't be used.
+///
+/// Cached data is only valid once the fuel gauge is polling, this lets the host distinguish a missing
+/// battery from a fuel gauge that stopped communicating. A fuel gauge that stopped communicating reports the code of
+/// the error that caused it.
+pub(crate) fn check_state<S: StaticBatteryData, D: DynamicBatteryData>(
+    state: &State<S, D>,
+) -> Result<(), BatteryError> {
+    match state.internal_state() {
+        InternalState::NotPresent => Err(BatteryError::NotPresent { error_code: 0 }),
+        InternalState::Present(PresentSubstate::NotOperational) => {
+            Err(state.last_error().unwrap_or(FuelGaugeError::Timeout).into())
+        }
+        InternalState::Present(PresentSubstate::Operational(OperationalSubstate::Init)) => {
+            Err(BatteryError::InvalidState { error_code: 0 })
+        }
+        InternalState::Present(PresentSubstate::Operational(OperationalSubstate::Polling)) => Ok(()),
+    }
+}
+
+/// Check `value` against the range reported in _BIX, a maximum of zero means the fuel gauge doesn't report a range.
+fn in_range(value: u32, min: u32, max: u32) -> bool {
+    max == 0 || (min..=max).contains(&value)
+}
+
 pub(crate) fn compute_bst<D: DynamicBatteryData>(cache: &D) -> embedded_batteries_async::acpi::BstReturn {
+    let faulted = cache.safety_faults().any();
     let cache = cache.standard();
-    let charging = if cache.battery_status & (1 << 6) == 0 {
+    let mut charging = if cache.battery_status & (1 << 6) == 0 {
         embedded_batteries_async::acpi::BatteryState::CHARGING
     } else {
         embedded_batteries_async::acpi::BatteryState::DISCHARGING
     };
+    if faulted {
+        charging = charging.union(embedded_batteries_async::acpi::BatteryState::CRITICAL);
+    }
 
     // TODO: add critical energy state and charge limiting state
     embedded_batteries_async::acpi::BstReturn {
@@ -153,27 +187,106 @@ pub(crate) fn compute_bmd<S: StaticBatteryData, D: DynamicBatteryData>(
     }
 }
 
+/// Smart battery time value reported when the fuel gauge can't provide an estimate.
+const GAUGE_TIME_UNSUPPORTED: Minutes = 0xffff;
+
+/// _BCT/_BTM value reported when the time can't be estimated, e.g. the battery isn't charging for _BCT.
+const ACPI_TIME_UNKNOWN: u32 = 0xffff_ffff;
+
+/// Convert a fuel gauge time estimate to seconds, `None` if the fuel gauge doesn't provide it.
+fn gauge_time_s(minutes: Minutes) -> Option<u32> {
+    (minutes != GAUGE_TIME_UNSUPPORTED).then(|| u32::from(minutes) * 60)
+}
+
+/// Convert a current to a rate in the battery's capacity units per hour, i.e. mA or cW.
+fn capacity_rate(current_ma: u32, voltage_mv: u32, capacity: CapacityModeValue) -> u32 {
+    match capacity {
+        CapacityModeValue::MilliAmpUnsigned(_) => current_ma,
+        CapacityModeValue::CentiWattUnsigned(_) => current_ma.saturating_mul(voltage_mv) / 10_000,
+    }
+}
+
+/// Time in seconds to move `capacity` at `rate`, both in the battery's capacity units.
+fn time_at_rate_s(capacity: u32, rate: u32) -> u32 {
+    if rate == 0 {
+        return ACPI_TIME_UNKNOWN;
+    }
+    u32::try_from(u64::from(capacity) * 3600 / u64::from(rate)).unwrap_or(ACPI_TIME_UNKNOWN)
+}
+
+/// Estimate the time in seconds to charge to `level_percent` from the averaged charge current.
+fn estimate_charge_time_s(level_percent: u32, cache: &DynamicBatteryMsgs) -> u32 {
+    let full = capacity_raw(cache.full_charge_capacity);
+    let remaining = capacity_raw(cache.remaining_capacity);
+    let target = full.saturating_mul(level_percent.min(100)) / 100;
+    if target <= remaining {
+        return 0;
+    }
+
+    // Positive current flows into the battery
+    let charge_current = u32::try_from(cache.average_current).unwrap_or(0);
+    let rate = capacity_rate(charge_current, cache.voltage.into(), cache.remaining_capacity);
+    time_at_rate_s(target - remaining, rate)
+}
+
+/// Estimate the run time in seconds at `rate`, or at the averaged discharge rate if `rate` is zero.
+fn estimate_run_time_s(rate: u32, cache: &DynamicBatteryMsgs) -> u32 {
+    let rate = if rate == 0 {
+        // Negative current flows out of the battery
+        let discharge_current = u32::try_from(-i32::from(cache.average_current)).unwrap_or(0);
+        capacity_rate(discharge_current, cache.voltage.into(), cache.remaining_capacity)
+    } else {
+        rate
+    };
+    time_at_rate_s(capacity_raw(cache.remaining_capacity), rate)
+}
+
 pub(crate) fn compute_bct<D: DynamicBatteryData>(
     payload: &embedded_batteries_async::acpi::Bct,
-    _dynamic_cache: &D,
+    dynamic_cache: &D,
+    estimation: TimeEstimation,
 ) -> embedded_batteries_async::acpi::BctReturnResult {
-    // Just echo back charge level for now
-    // TODO: Actually compute time from charge level
-    embedded_batteries_async::acpi::BctReturnResult::from(payload.charge_level_percent)
+    let cache = dynamic_cache.standard();
+    // The fuel gauge only estimates the time to a full charge
+    let gauge_time = (estimation == TimeEstimation::PreferGauge && payload.charge_level_percent >= 100)
+        .then(|| gauge_time_s(cache.average_time_to_full))
+        .flatten();
+    let time = gauge_time.unwrap_or_else(|| estimate_charge_time_s(payload.charge_level_percent, cache));
+    embedded_batteries_async::acpi::BctReturnResult::from(time)
 }
 
 pub(crate) fn compute_btm<D: DynamicBatteryData>(
     payload: &embedded_batteries_async::acpi::Btm,
-    _dynamic_cache: &D,
+    dynamic_cache: &D,
+    estimation: TimeEstimation,
 ) -> embedded_batteries_async::acpi::BtmReturnResult {
-    // Just echo back charge level for now
-    // TODO: Actually compute time from charge level
-    embedded_batteries_async::acpi::BtmReturnResult::from(payload.discharge_rate)
+    let cache = dynamic_cache.standard();
+    // The fuel gauge only estimates the run time at the present rate
+    let gauge_time = (estimation == TimeEstimation::PreferGauge && payload.discharge_rate == 0)
+        .then(|| gauge_time_s(cache.run_time_to_empty))
+        .flatten();
+    let time = gauge_time.unwrap_or_else(|| estimate_run_time_s(payload.discharge_rate, cache));
+    embedded_batteries_async::acpi::BtmReturnResult::from(time)
 }
 
-pub(crate) fn compute_sta() -> embedded_batteries_async::acpi::StaReturn {
-    // TODO: Grab real state values
-    embedded_batteries_async::acpi::StaReturn::all()
+/// ACPI _STA battery present bit.
+const STA_BATTERY_PRESENT: u32 = 1 << 4;
+const STA_FUNCTIONING: u32 = 1 << 3;
+
+pub(crate) fn compute_sta(present: bool, faulted: bool) -> embedded_batteries_async::acpi::StaReturn {
+    let sta = embedded_batteries_async::acpi::StaReturn::all();
+    if !present {
+        // The battery slot itself is still present and functioning
+        sta.difference(embedded_batteries_async::acpi::StaReturn::from_bits_truncate(
+            STA_BATTERY_PRESENT,
+        ))
+    } else if faulted {
+        sta.difference(embedded_batteries_async::acpi::StaReturn::from_bits_truncate(
+            STA_FUNCTIONING,
+        ))
+    } else {
+        sta
+    }
 }
 
 pub(crate) fn compute_psr(psu_state: &PsuState) -> embedded_batteries_async::acpi::PsrReturn {
@@ -238,7 +351,12 @@ impl<'hw, Reg: crate::registration::Registration<'hw>> crate::Service<'hw, Reg>
     ) -> Result<BctReturnResult, BatteryError> {
         trace!("Battery service: got BCT command!");
         info!("Recvd BCT charge_level_percent: {}", bct.charge_level_percent);
-        Ok(compute_bct(&bct, fuel_gauge.state().dynamic_cache()))
+        check_state(fuel_gauge.state())?;
+        Ok(compute_bct(
+            &bct,
+            fuel_gauge.state().dynamic_cache(),
+            self.config.time_estimation,
+        ))
     }
 
     /// Returns static information about the battery. Corresponds to ACPI's _BIX method.
@@ -247,53 +365,113 @@ impl<'hw, Reg: crate::registration::Registration<'hw>> crate::Service<'hw, Reg>
         fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
     ) -> Result<BixFixedStrings, BatteryError> {
         trace!("Battery service: got BIX command!");
+        check_state(fuel_gauge.state())?;
         compute_bix(fuel_gauge.state().static_cache(), fuel_gauge.state().dynamic_cache())
             .map_err(|_| BatteryError::UnspecifiedFailure)
     }
 
     /// Sets the averaging interval of battery capacity measurement in milliseconds. Corresponds to ACPI's _BMA method.
-    pub fn set_battery_measurement_averaging_interval(
+    ///
+    /// The fuel gauge is reconfigured if it supports it, otherwise the current and voltage reported by _BST are
+    /// averaged in software.
+    pub async fn set_battery_measurement_averaging_interval(
         &self,
-        _fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
+        fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
         bma: embedded_batteries_async::acpi::Bma,
-    ) -> Result<(), BatteryError> {
+    ) -> Result<MeasurementStatus, BatteryError> {
         trace!("Battery service: got BMA command!");
         info!("Recvd BMA averaging_interval_ms: {}", bma.averaging_interval_ms);
-        Ok(())
+        check_state(fuel_gauge.state())?;
+
+        let static_cache = fuel_gauge.state().static_cache().standard();
+        if !in_range(
+            bma.averaging_interval_ms,
+            static_cache.min_averaging_interval_ms,
+            static_cache.max_averaging_interval_ms,
+        ) {
+            return Ok(MeasurementStatus::OutOfRange);
+        }
+
+        let hardware_averaging = match fuel_gauge.set_averaging_interval(bma.averaging_interval_ms).await {
+            Ok(supported) => supported,
+            Err(_) => {
+                error!("Battery service: failed to set averaging interval");
+                return Ok(MeasurementStatus::HardwareFailure);
+            }
+        };
+
+        let mut config = fuel_gauge.state().measurement_config();
+        config.averaging_interval_ms = Some(bma.averaging_interval_ms);
+        config.hardware_averaging = hardware_averaging;
+        fuel_gauge.state_mut().on_measurement_config(config);
+        Ok(MeasurementStatus::Success)
     }
 
     /// Battery maintenance control. Corresponds to ACPI's _BMC method.
+    ///
+    /// The request is carried out by [`Service::update_calibration`](crate::Service::update_calibration), it's
+    /// rejected if no [`Calibrator`](crate::Calibrator) was provided to the service.
     pub fn battery_maintenance_control(
         &self,
-        _fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
+        battery_id: DeviceId,
+        fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
         bmc: embedded_batteries_async::acpi::Bmc,
     ) -> Result<(), BatteryError> {
         trace!("Battery service: got BMC command!");
         info!("Battery service: Bmc {}", bmc.maintenance_control_flags.bits());
-        Ok(())
+        check_state(fuel_gauge.state())?;
+        self.charge_control
+            .calibrator
+            .ok_or(BatteryError::Unsupported)?
+            .request(battery_id, bmc.maintenance_control_flags)
     }
 
     /// Retrieves battery maintenance data. Corresponds to ACPI's _BMD method.
     pub fn battery_maintenance_data(
         &self,
+        battery_id: DeviceId,
         fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
     ) -> Result<Bmd, BatteryError> {
         trace!("Battery service: got BMD command!");
-        Ok(compute_bmd(
-            fuel_gauge.state().static_cache(),
-            fuel_gauge.state().dynamic_cache(),
-        ))
+        check_state(fuel_gauge.state())?;
+        let mut bmd = compute_bmd(fuel_gauge.state().static_cache(), fuel_gauge.state().dynamic_cache());
+        if let Some(calibrator) = self.charge_control.calibrator {
+            bmd.status_flags |= calibrator.status_flags(battery_id);
+        }
+        Ok(bmd)
     }
 
     /// Sets the battery measurement sampling time in milliseconds. Corresponds to ACPI's _BMS method.
-    pub fn set_battery_measurement_sampling_time(
+    ///
+    /// The fuel gauge is reconfigured if it supports it. The driver is expected to poll dynamic data at the
+    /// requested sampling time, which also sets the window used for software averaging.
+    pub async fn set_battery_measurement_sampling_time(
         &self,
-        _fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
+        fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
         bms: embedded_batteries_async::acpi::Bms,
-    ) -> Result<(), BatteryError> {
+    ) -> Result<MeasurementStatus, BatteryError> {
         trace!("Battery service: got BMS command!");
         info!("Recvd BMS sampling_time: {}", bms.sampling_time_ms);
-        Ok(())
+        check_state(fuel_gauge.state())?;
+
+        let static_cache = fuel_gauge.state().static_cache().standard();
+        if !in_range(
+            bms.sampling_time_ms,
+            static_cache.min_sample_time_ms,
+            static_cache.max_sample_time_ms,
+        ) {
+            return Ok(MeasurementStatus::OutOfRange);
+        }
+
+        if fuel_gauge.set_sampling_time(bms.sampling_time_ms).await.is_err() {
+            error!("Battery service: failed to set sampling time");
+            return Ok(MeasurementStatus::HardwareFailure);
+        }
+
+        let mut config = fuel_gauge.state().measurement_config();
+        config.sampling_time_ms = Some(bms.sampling_time_ms);
+        fuel_gauge.state_mut().on_measurement_config(config);
+        Ok(MeasurementStatus::Success)
     }
 
     /// Queries the current power characteristics of the battery. Corresponds to ACPI's _BPC method.
@@ -302,6 +480,7 @@ impl<'hw, Reg: crate::registration::Registration<'hw>> crate::Service<'hw, Reg>
         fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
     ) -> Result<Bpc, BatteryError> {
         trace!("Battery service: got BPC command!");
+        check_state(fuel_gauge.state())?;
         Ok(compute_bpc(fuel_gauge.state().static_cache()))
     }
 
@@ -311,6 +490,7 @@ impl<'hw, Reg: crate::registration::Registration<'hw>> crate::Service<'hw, Reg>
         fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
     ) -> Result<Bps, BatteryError> {
         trace!("Battery service: got BPS command!");
+        check_state(fuel_gauge.state())?;
         Ok(compute_bps(fuel_gauge.state().dynamic_cache()))
     }
 
@@ -334,7 +514,18 @@ impl<'hw, Reg: crate::registration::Registration<'hw>> crate::Service<'hw, Reg>
         fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
     ) -> Result<BstReturn, BatteryError> {
         trace!("Battery service: got BST command!");
-        Ok(compute_bst(fuel_gauge.state().dynamic_cache()))
+        // _BST is still answered without a battery, the host checks presence through _STA
+        match check_state(fuel_gauge.state()) {
+            Ok(()) | Err(BatteryError::NotPresent { .. }) => (),
+            Err(e) => return Err(e),
+        }
+        let mut bst = compute_bst(fuel_gauge.state().dynamic_cache());
+        // Report the software average when the fuel gauge can't average in hardware
+        if let Some(average) = fuel_gauge.state().averaged_measurements() {
+            bst.battery_present_rate = average.current.unsigned_abs().into();
+            bst.battery_present_voltage = average.voltage.into();
+        }
+        Ok(bst)
     }
 
     /// Queries the estimated time remaining until the battery is fully discharged at the current discharge rate. Corresponds to ACPI's _BTM method.
@@ -345,7 +536,12 @@ impl<'hw, Reg: crate::registration::Registration<'hw>> crate::Service<'hw, Reg>
     ) -> Result<BtmReturnResult, BatteryError> {
         trace!("Battery service: got BTM command!");
         info!("Recvd BTM discharge_rate: {}", btm.discharge_rate);
-        Ok(compute_btm(&btm, fuel_gauge.state().dynamic_cache()))
+        check_state(fuel_gauge.state())?;
+        Ok(compute_btm(
+            &btm,
+            fuel_gauge.state().dynamic_cache(),
+            self.config.time_estimation,
+        ))
     }
 
     /// Sets a battery trip point. Corresponds to ACPI's _BTP method.
@@ -381,10 +577,14 @@ impl<'hw, Reg: crate::registration::Registration<'hw>> crate::Service<'hw, Reg>
     /// Queries the battery's status. Corresponds to ACPI's _STA method.
     pub fn device_status(
         &self,
-        _fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
+        fuel_gauge: &mut <Reg::FuelGauge as Lockable>::Inner,
     ) -> Result<StaReturn, BatteryError> {
         trace!("Battery service: got STA command!");
-        Ok(compute_sta())
+        let state = fuel_gauge.state();
+        Ok(compute_sta(
+            state.is_present(),
+            state.dynamic_cache().safety_faults().any(),
+        ))
     }
 }
 
@@ -394,9 +594,15 @@ mod tests {
 
     use embedded_batteries_async::smart_battery::CapacityModeValue;
 
-    use super::{compute_bix, compute_bpc, compute_bst};
+    use super::{
+        ACPI_TIME_UNKNOWN, GAUGE_TIME_UNSUPPORTED, STA_BATTERY_PRESENT, STA_FUNCTIONING, check_state, compute_bct,
+        compute_bix, compute_bpc, compute_bst, compute_btm, compute_sta, in_range,
+    };
+    use crate::TimeEstimation;
+    use battery_service_interface::BatteryError;
     use battery_service_interface::fuel_gauge::{
-        DynamicBatteryData, DynamicBatteryMsgs, StaticBatteryData, StaticBatteryMsgs,
+        AveragedMeasurements, DynamicBatteryData, DynamicBatteryMsgs, FuelGaugeError, MeasurementConfig, State,
+        StaticBatteryData, StaticBatteryMsgs,
     };
 
     /// An OEM dynamic data type that embeds the standard messages and extends
@@ -483,4 +689,182 @@ mod tests {
         );
         assert_eq!(oem_static.oem_part_number, 0xABCD);
     }
+
+    /// Each fuel gauge state maps to a distinct error so the host can tell a missing battery
+    /// from a fuel gauge that stopped communicating.
+    #[test]
+    fn check_state_maps_fuel_gauge_state() {
+        let mut state: State = State::default();
+        assert_eq!(check_state(&state), Err(BatteryError::NotPresent { error_code: 0 }));
+
+        state.on_initialized();
+        assert_eq!(check_state(&state), Err(BatteryError::InvalidState { error_code: 0 }));
+
+        state.on_static_data(|_| {});
+        assert_eq!(check_state(&state), Ok(()));
+
+        state.on_timeout();
+        assert_eq!(
+            check_state(&state),
+            Err(BatteryError::Timeout {
+                error_code: FuelGaugeError::Timeout.code()
+            })
+        );
+
+        // The error that stopped communication is reported until the fuel gauge recovers
+        state.on_recovered();
+        state.on_static_data(|_| {});
+        state.on_error(FuelGaugeError::NotAcknowledged);
+        assert_eq!(
+            check_state(&state),
+            Err(BatteryError::BusError {
+                error_code: FuelGaugeError::NotAcknowledged.code()
+            })
+        );
+        state.on_recovered();
+        assert_eq!(state.last_error(), None);
+    }
+
+    /// Values outside the range reported in _BIX are rejected, unless the fuel gauge doesn't report one.
+    #[test]
+    fn in_range_checks_reported_range() {
+        assert!(in_range(1000, 500, 2000));
+        assert!(!in_range(100, 500, 2000));
+        assert!(!in_range(3000, 500, 2000));
+        assert!(in_range(3000, 0, 0));
+    }
+
+    /// Without hardware averaging, current and voltage are averaged over `averaging_interval / sampling_time`
+    /// samples.
+    #[test]
+    fn software_averaging_over_window() {
+        let mut state: State = State::default();
+        state.on_dynamic_data(|d| d.current = -1000);
+        assert_eq!(state.averaged_measurements(), None);
+
+        state.on_measurement_config(MeasurementConfig {
+            sampling_time_ms: Some(1000),
+            averaging_interval_ms: Some(4000),
+            hardware_averaging: false,
+        });
+        state.on_dynamic_data(|d| {
+            d.current = -1000;
+            d.voltage = 12000;
+        });
+        assert_eq!(
+            state.averaged_measurements(),
+            Some(AveragedMeasurements {
+                current: -1000,
+                voltage: 12000,
+            })
+        );
+
+        state.on_dynamic_data(|d| {
+            d.current = -2000;
+            d.voltage = 11000;
+        });
+        assert_eq!(
+            state.averaged_measurements(),
+            Some(AveragedMeasurements {
+                current: -1250,
+                voltage: 11750,
+            })
+        );
+    }
+
+    /// Software averaging is skipped when the fuel gauge averages in hardware.
+    #[test]
+    fn hardware_averaging_skips_software_average() {
+        let mut state: State = State::default();
+        state.on_measurement_config(MeasurementConfig {
+            sampling_time_ms: Some(1000),
+            averaging_interval_ms: Some(4000),
+            hardware_averaging: true,
+        });
+        state.on_dynamic_data(|d| d.current = -1000);
+        assert_eq!(state.averaged_measurements(), None);
+    }
+
+    /// Charge and run time are computed on the EC from the averaged current when the fuel gauge doesn't provide them.
+    #[test]
+    fn time_estimation_falls_back_to_ec() {
+        let mut cache = DynamicBatteryMsgs {
+            full_charge_capacity: CapacityModeValue::MilliAmpUnsigned(4000),
+            remaining_capacity: CapacityModeValue::MilliAmpUnsigned(1000),
+            average_current: 1000,
+            average_time_to_full: GAUGE_TIME_UNSUPPORTED,
+            run_time_to_empty: GAUGE_TIME_UNSUPPORTED,
+            ..Default::default()
+        };
+        let bct = |level| embedded_batteries_async::acpi::Bct {
+            charge_level_percent: level,
+        };
+        let btm = |rate| embedded_batteries_async::acpi::Btm { discharge_rate: rate };
+
+        // 2000 mAh at 1000 mA to reach 75 %
+        assert_eq!(
+            u32::from(compute_bct(&bct(75), &cache, TimeEstimation::PreferGauge)),
+            7200
+        );
+        // Already above the requested level
+        assert_eq!(u32::from(compute_bct(&bct(20), &cache, TimeEstimation::PreferGauge)), 0);
+        // Not discharging, so no run time at the present rate, but one at an explicit rate
+        assert_eq!(
+            u32::from(compute_btm(&btm(0), &cache, TimeEstimation::PreferGauge)),
+            ACPI_TIME_UNKNOWN
+        );
+        assert_eq!(
+            u32::from(compute_btm(&btm(500), &cache, TimeEstimation::PreferGauge)),
+            7200
+        );
+
+        cache.average_current = -2000;
+        assert_eq!(
+            u32::from(compute_bct(&bct(100), &cache, TimeEstimation::PreferGauge)),
+            ACPI_TIME_UNKNOWN
+        );
+        assert_eq!(
+            u32::from(compute_btm(&btm(0), &cache, TimeEstimation::PreferGauge)),
+            1800
+        );
+
+        // Gauge estimates are preferred when available, unless the EC is configured to compute them
+        cache.run_time_to_empty = 20;
+        assert_eq!(
+            u32::from(compute_btm(&btm(0), &cache, TimeEstimation::PreferGauge)),
+            1200
+        );
+        assert_eq!(u32::from(compute_btm(&btm(0), &cache, TimeEstimation::Ec)), 1800);
+    }
+
+    #[test]
+    fn sta_reports_battery_presence() {
+        assert_ne!(compute_sta(true, false).bits() & STA_BATTERY_PRESENT, 0);
+
+        let absent = compute_sta(false, false);
+        assert_eq!(absent.bits() & STA_BATTERY_PRESENT, 0);
+        // The slot itself is still reported as present
+        assert_ne!(absent.bits() & 1, 0);
+    }
+
+    #[test]
+    fn safety_fault_reported_in_sta_and_bst() {
+        let faulted = compute_sta(true, true);
+        assert_ne!(faulted.bits() & STA_BATTERY_PRESENT, 0);
+        assert_eq!(faulted.bits() & STA_FUNCTIONING, 0);
+
+        let mut cache = DynamicBatteryMsgs::default();
+        assert!(
+            !compute_bst(&cache)
+                .battery_state
+                .contains(embedded_batteries_async::acpi::BatteryState::CRITICAL)
+        );
+        // OVER_TEMP_ALARM
+        cache.battery_status = 1 << 12;
+        assert!(
+            compute_bst(&cache)
+                .battery_state
+                .contains(embedded_batteries_async::acpi::BatteryState::CRITICAL)
+        );
+    }
 }
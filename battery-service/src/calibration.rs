@@ -0,0 +1,391 @@
+//! Host-requested battery maintenance and recalibration.
+//!
+//! The host controls battery maintenance through ACPI `_BMC` and observes it through `_BMD`. [`Calibrator`] records
+//! the requested control flags and [`Service::update_calibration`](crate::Service::update_calibration), called by the
+//! OEM after each fuel gauge update, drives a [`LearnMode`] charger accordingly:
+//!
+//! * A calibration (learn) cycle disables charge and discharges the battery in learn mode at
+//!   [`discharge_current_ma`](CalibrationConfig::discharge_current_ma) down to
+//!   [`discharge_end_soc`](CalibrationConfig::discharge_end_soc), then charges it back to full so the fuel gauge
+//!   learns the full charge capacity.
+//! * Outside of a cycle, the disable charging and discharge while on AC flags are applied as requested.
+//!
+//! The cycle is aborted if the adapter is removed, the state of charge falls below
+//! [`critical_soc`](CalibrationConfig::critical_soc), the battery reports a safety fault, or the OEM calls
+//! [`Calibrator::interrupt`], e.g. on lid close. Charge is always restored when a cycle ends.
+use core::cell::RefCell;
+
+use battery_service_interface::fuel_gauge::{DynamicBatteryData, FuelGauge};
+use battery_service_interface::{BatteryError, DeviceId};
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_batteries_async::acpi::{BmcControlFlags, BmdStatusFlags};
+use embedded_batteries_async::smart_battery::Percent;
+use embedded_services::{GlobalRawMutex, error, info, warn};
+use power_policy_interface::charger::{ChargeInhibit, ChargerError, LearnMode};
+
+use crate::acpi::check_state;
+use crate::registration::Registration;
+
+/// `_BMC`: initiate a calibration cycle, clear to end it
+const BMC_CALIBRATE: u32 = 1 << 0;
+/// `_BMC`: disable charging
+const BMC_DISABLE_CHARGING: u32 = 1 << 1;
+/// `_BMC`: discharge the battery while on AC
+const BMC_DISCHARGE_ON_AC: u32 = 1 << 2;
+
+/// `_BMD`: a calibration cycle is in progress
+const BMD_CALIBRATING: u32 = 1 << 0;
+/// `_BMD`: charging is disabled
+const BMD_CHARGING_DISABLED: u32 = 1 << 1;
+/// `_BMD`: the battery is discharging while on AC
+const BMD_DISCHARGING_ON_AC: u32 = 1 << 2;
+
+/// Learn cycle configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CalibrationConfig {
+    /// Battery discharge current limit in learn mode
+    pub discharge_current_ma: u16,
+    /// State of charge at which the discharge phase ends and the battery is charged back to full
+    pub discharge_end_soc: Percent,
+    /// State of charge below which the cycle is aborted, the battery is charged regardless of the host request
+    pub critical_soc: Percent,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            discharge_current_ma: 2000,
+            discharge_end_soc: 5,
+            critical_soc: 3,
+        }
+    }
+}
+
+/// Learn cycle phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CalibrationPhase {
+    /// No cycle in progress
+    #[default]
+    Idle,
+    /// Discharging to the configured end state of charge
+    Discharging,
+    /// Charging back to full
+    Charging,
+}
+
+/// Charger control applied by the calibrator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Control {
+    charge_disabled: bool,
+    discharging: bool,
+}
+
+struct CalibrationState {
+    /// Battery the control flags apply to
+    battery_id: DeviceId,
+    /// Control flags last requested by the host
+    requested: u32,
+    phase: CalibrationPhase,
+    /// Control currently applied to the charger
+    applied: Control,
+    /// The OEM interrupted the cycle
+    interrupted: bool,
+}
+
+/// Next phase of the cycle.
+///
+/// `adapter` indicates a consumer is attached to the charger, `abort` that the cycle must end now.
+fn next_phase(
+    config: &CalibrationConfig,
+    phase: CalibrationPhase,
+    requested: u32,
+    soc: Percent,
+    adapter: bool,
+    abort: bool,
+) -> CalibrationPhase {
+    if requested & BMC_CALIBRATE == 0 || abort || !adapter || soc < config.critical_soc {
+        return CalibrationPhase::Idle;
+    }
+
+    match phase {
+        CalibrationPhase::Idle => CalibrationPhase::Discharging,
+        CalibrationPhase::Discharging if soc <= config.discharge_end_soc => CalibrationPhase::Charging,
+        CalibrationPhase::Charging if soc >= 100 => CalibrationPhase::Idle,
+        phase => phase,
+    }
+}
+
+/// Charger control for the given phase, outside of a cycle the host flags apply directly.
+fn control(phase: CalibrationPhase, requested: u32) -> Control {
+    match phase {
+        CalibrationPhase::Idle => Control {
+            charge_disabled: requested & BMC_DISABLE_CHARGING != 0,
+            discharging: requested & BMC_DISCHARGE_ON_AC != 0,
+        },
+        CalibrationPhase::Discharging => Control {
+            charge_disabled: true,
+            discharging: true,
+        },
+        CalibrationPhase::Charging => Control::default(),
+    }
+}
+
+/// Host-requested battery maintenance state.
+pub struct Calibrator {
+    config: CalibrationConfig,
+    state: Mutex<GlobalRawMutex, RefCell<CalibrationState>>,
+}
+
+impl Calibrator {
+    /// Create a new calibrator with no maintenance requested.
+    pub const fn new(config: CalibrationConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(RefCell::new(CalibrationState {
+                battery_id: DeviceId(0),
+                requested: 0,
+                phase: CalibrationPhase::Idle,
+                applied: Control {
+                    charge_disabled: false,
+                    discharging: false,
+                },
+                interrupted: false,
+            })),
+        }
+    }
+
+    /// Record the control flags requested by the host through `_BMC`.
+    ///
+    /// Only one battery can be under maintenance at a time, a request for another battery fails until the flags of
+    /// the current one are cleared.
+    pub fn request(&self, battery_id: DeviceId, flags: BmcControlFlags) -> Result<(), BatteryError> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if state.battery_id != battery_id && (state.requested != 0 || state.phase != CalibrationPhase::Idle) {
+                warn!("Battery {} maintenance already in progress", state.battery_id.0);
+                return Err(BatteryError::UnspecifiedFailure);
+            }
+
+            state.battery_id = battery_id;
+            state.requested = flags.bits();
+            state.interrupted = false;
+            Ok(())
+        })
+    }
+
+    /// Interrupt a calibration cycle, e.g. on lid close.
+    ///
+    /// The cycle is aborted on the next update and must be requested again by the host.
+    pub fn interrupt(&self) {
+        self.state.lock(|state| state.borrow_mut().interrupted = true);
+    }
+
+    /// Returns the current phase of the learn cycle.
+    pub fn phase(&self) -> CalibrationPhase {
+        self.state.lock(|state| state.borrow().phase)
+    }
+
+    /// Returns true if charging is controlled by the calibrator for the given battery.
+    pub fn is_active(&self, battery_id: DeviceId) -> bool {
+        self.state.lock(|state| {
+            let state = state.borrow();
+            state.battery_id == battery_id
+                && (state.phase != CalibrationPhase::Idle || state.applied != Control::default())
+        })
+    }
+
+    /// Returns the `_BMD` status flags of the given battery.
+    pub fn status_flags(&self, battery_id: DeviceId) -> BmdStatusFlags {
+        self.state.lock(|state| {
+            let state = state.borrow();
+            if state.battery_id != battery_id {
+                return BmdStatusFlags::empty();
+            }
+
+            let mut bits = 0;
+            if state.phase != CalibrationPhase::Idle {
+                bits |= BMD_CALIBRATING;
+            }
+            if state.applied.charge_disabled {
+                bits |= BMD_CHARGING_DISABLED;
+            }
+            if state.applied.discharging {
+                bits |= BMD_DISCHARGING_ON_AC;
+            }
+            BmdStatusFlags::from_bits_truncate(bits)
+        })
+    }
+
+    /// Advance the cycle from the given state of charge and command the charger.
+    ///
+    /// `abort` ends the cycle regardless of the host request.
+    pub async fn update<C: LearnMode>(&self, charger: &mut C, soc: Percent, abort: bool) -> Result<(), ChargerError> {
+        let adapter = charger.state().capability().is_some();
+        let (phase, requested, applied, interrupted) = self.state.lock(|state| {
+            let state = state.borrow();
+            (state.phase, state.requested, state.applied, state.interrupted)
+        });
+
+        let new_phase = next_phase(&self.config, phase, requested, soc, adapter, abort || interrupted);
+        if new_phase != phase {
+            match new_phase {
+                CalibrationPhase::Discharging => info!("Battery calibration started at {}%", soc),
+                CalibrationPhase::Charging => info!("Battery calibration charging from {}%", soc),
+                CalibrationPhase::Idle if phase == CalibrationPhase::Charging && soc >= 100 => {
+                    info!("Battery calibration complete")
+                }
+                CalibrationPhase::Idle => warn!("Battery calibration aborted at {}%", soc),
+            }
+        }
+
+        // The host flags no longer apply once the adapter is removed or the battery is low
+        let control = if adapter && soc >= self.config.critical_soc && !abort {
+            control(new_phase, requested)
+        } else {
+            Control::default()
+        };
+
+        // Stop discharging before charge is restored, and disable charge before discharging
+        if applied.discharging && !control.discharging {
+            charger.set_learn_mode(None).await.map_err(Into::into)?;
+        }
+        if control.charge_disabled != applied.charge_disabled {
+            if control.charge_disabled {
+                crate::inhibit_charge(charger, ChargeInhibit::CALIBRATION).await?;
+            } else {
+                crate::release_charge(charger, ChargeInhibit::CALIBRATION).await?;
+            }
+        }
+        if control.discharging && !applied.discharging {
+            charger
+                .set_learn_mode(Some(self.config.discharge_current_ma))
+                .await
+                .map_err(Into::into)?;
+        }
+
+        // Only commit the new state once the charger accepted it so a failed command is retried on the next update
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.phase = new_phase;
+            state.applied = control;
+            if new_phase == CalibrationPhase::Idle && phase != CalibrationPhase::Idle {
+                // The cycle is over, completed or not
+                state.requested &= !BMC_CALIBRATE;
+                state.interrupted = false;
+            }
+        });
+        Ok(())
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Returns true if the charger is controlled by battery maintenance of the given battery.
+    pub fn is_calibrating(&self, battery_id: DeviceId) -> bool {
+        self.charge_control
+            .calibrator
+            .is_some_and(|calibrator| calibrator.is_active(battery_id))
+    }
+
+    /// Advance battery maintenance of the given battery from its cached state of charge and command the charger.
+    ///
+    /// Does nothing if no [`Calibrator`] was provided to the service or another battery is under maintenance. A
+    /// safety fault aborts the cycle.
+    pub async fn update_calibration<C: LearnMode>(
+        &self,
+        battery_id: DeviceId,
+        charger: &mut C,
+    ) -> Result<(), BatteryError> {
+        let Some(calibrator) = self.charge_control.calibrator else {
+            return Ok(());
+        };
+        if calibrator.state.lock(|state| state.borrow().battery_id) != battery_id {
+            return Ok(());
+        }
+
+        let soc = {
+            let fuel_gauge = self.lock_fuel_gauge(battery_id).await?;
+            check_state(fuel_gauge.state())?;
+            fuel_gauge.state().dynamic_cache().standard().relative_soc
+        };
+
+        calibrator
+            .update(charger, soc, self.is_faulted(battery_id))
+            .await
+            .map_err(|e| {
+                error!("Failed to update battery calibration: {:?}", e);
+                crate::charger_error(e)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn learn_cycle_phases() {
+        let config = CalibrationConfig::default();
+        let idle = CalibrationPhase::Idle;
+        let discharging = CalibrationPhase::Discharging;
+        let charging = CalibrationPhase::Charging;
+
+        assert_eq!(next_phase(&config, idle, 0, 50, true, false), idle);
+        assert_eq!(next_phase(&config, idle, BMC_CALIBRATE, 50, true, false), discharging);
+        assert_eq!(
+            next_phase(&config, discharging, BMC_CALIBRATE, 6, true, false),
+            discharging
+        );
+        assert_eq!(
+            next_phase(&config, discharging, BMC_CALIBRATE, 5, true, false),
+            charging
+        );
+        assert_eq!(next_phase(&config, charging, BMC_CALIBRATE, 99, true, false), charging);
+        assert_eq!(next_phase(&config, charging, BMC_CALIBRATE, 100, true, false), idle);
+    }
+
+    #[test]
+    fn learn_cycle_aborts() {
+        let config = CalibrationConfig::default();
+        let discharging = CalibrationPhase::Discharging;
+
+        // Host cleared the request
+        assert_eq!(
+            next_phase(&config, discharging, 0, 50, true, false),
+            CalibrationPhase::Idle
+        );
+        // Adapter removed
+        assert_eq!(
+            next_phase(&config, discharging, BMC_CALIBRATE, 50, false, false),
+            CalibrationPhase::Idle
+        );
+        // Low battery
+        assert_eq!(
+            next_phase(&config, discharging, BMC_CALIBRATE, 2, true, false),
+            CalibrationPhase::Idle
+        );
+        // Interrupted
+        assert_eq!(
+            next_phase(&config, discharging, BMC_CALIBRATE, 50, true, true),
+            CalibrationPhase::Idle
+        );
+    }
+
+    #[test]
+    fn host_flags_apply_outside_cycle() {
+        assert_eq!(control(CalibrationPhase::Idle, 0), Control::default());
+        assert_eq!(
+            control(CalibrationPhase::Idle, BMC_DISABLE_CHARGING | BMC_DISCHARGE_ON_AC),
+            Control {
+                charge_disabled: true,
+                discharging: true,
+            }
+        );
+        // The cycle overrides the host flags
+        assert_eq!(
+            control(CalibrationPhase::Charging, BMC_DISABLE_CHARGING),
+            Control::default()
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Battery-saver charge limit.
+//!
+//! [`ChargeLimiter`] holds a host or user configured state of charge ceiling and resume threshold, persisted in
+//! NVRAM so that the setting survives a reset. [`Service::enforce_charge_limit`](crate::Service::enforce_charge_limit)
+//! runs as part of [`Service::update_charge_control`](crate::Service::update_charge_control) after each fuel gauge
+//! update and commands the charger to inhibit charge above the ceiling, restoring the power policy's charge capability
+//! once the state of charge falls to the resume threshold.
+use core::cell::RefCell;
+
+use battery_service_interface::fuel_gauge::{DynamicBatteryData, FuelGauge};
+use battery_service_interface::{BatteryError, ChargeLimit, ChargeLimitStatus, DeviceId};
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_batteries_async::smart_battery::Percent;
+use embedded_mcu_hal::nvram::NvramStorage;
+use embedded_services::sync::Lockable;
+use embedded_services::{GlobalRawMutex, error, info};
+use power_policy_interface::charger::{ChargeInhibit, Charger, ChargerError};
+
+use crate::acpi::check_state;
+use crate::registration::Registration;
+
+const CEILING_MASK: u32 = 0xff;
+const RESUME_SHIFT: u32 = 8;
+const RESUME_MASK: u32 = 0xff << RESUME_SHIFT;
+const ENABLED: u32 = 1 << 16;
+
+/// Decode the stored limit, uninitialized or corrupt NVRAM is treated as no limit.
+fn decode(raw: u32) -> Option<ChargeLimit> {
+    if raw & ENABLED == 0 {
+        return None;
+    }
+    ChargeLimit::new((raw & CEILING_MASK) as u8, ((raw & RESUME_MASK) >> RESUME_SHIFT) as u8)
+}
+
+fn encode(limit: Option<ChargeLimit>) -> u32 {
+    match limit {
+        Some(limit) => {
+            ENABLED | u32::from(limit.ceiling_percent()) | (u32::from(limit.resume_percent()) << RESUME_SHIFT)
+        }
+        None => 0,
+    }
+}
+
+/// Returns true if charge should be inhibited at the given state of charge.
+///
+/// Once inhibited, charging only resumes at the resume threshold to avoid toggling the charger around the ceiling.
+fn should_inhibit(limit: Option<ChargeLimit>, inhibited: bool, soc: Percent) -> bool {
+    match limit {
+        None => false,
+        Some(limit) if inhibited => soc > limit.resume_percent(),
+        Some(limit) => soc >= limit.ceiling_percent(),
+    }
+}
+
+struct LimiterState<'hw> {
+    storage: &'hw mut dyn NvramStorage<'hw, u32>,
+    limit: Option<ChargeLimit>,
+    inhibited: bool,
+}
+
+/// Persisted charge limit and its enforcement state.
+pub struct ChargeLimiter<'hw> {
+    state: Mutex<GlobalRawMutex, RefCell<LimiterState<'hw>>>,
+}
+
+impl<'hw> ChargeLimiter<'hw> {
+    /// Create a new charge limiter, restoring any limit previously stored in `storage`.
+    pub fn new(storage: &'hw mut dyn NvramStorage<'hw, u32>) -> Self {
+        let limit = decode(storage.read());
+        Self {
+            state: Mutex::new(RefCell::new(LimiterState {
+                storage,
+                limit,
+                inhibited: false,
+            })),
+        }
+    }
+
+    /// Set and persist the charge limit, `None` removes the limit.
+    ///
+    /// The new limit takes effect on the next call to [`Self::enforce`].
+    pub fn set_limit(&self, limit: Option<ChargeLimit>) {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.limit = limit;
+            state.storage.write(encode(limit));
+        });
+    }
+
+    /// Returns the configured limit and whether charging is currently inhibited.
+    pub fn status(&self) -> ChargeLimitStatus {
+        self.state.lock(|state| {
+            let state = state.borrow();
+            ChargeLimitStatus {
+                limit: state.limit,
+                inhibited: state.inhibited,
+            }
+        })
+    }
+
+    /// Inhibit or resume charging based on the given state of charge.
+    ///
+    /// Charge is inhibited by commanding a zero charging current and marking the charger state with
+    /// [`ChargeInhibit::LIMIT`] so that power policy doesn't re-attach it. The zero current is re-sent on every update
+    /// while inhibited in case the charger restored it. Charging resumes by re-applying the consumer capability the
+    /// power policy last attached to the charger, if any, unless another charge control feature inhibits it.
+    pub async fn enforce<C: Charger>(&self, charger: &mut C, soc: Percent) -> Result<(), ChargerError> {
+        let status = self.status();
+        let inhibit = should_inhibit(status.limit, status.inhibited, soc);
+        if inhibit {
+            if !status.inhibited {
+                info!("Charge limit reached at {}%, inhibiting charge", soc);
+            }
+            crate::inhibit_charge(charger, ChargeInhibit::LIMIT).await?;
+        } else if status.inhibited {
+            info!("Charge resumed at {}%", soc);
+            crate::release_charge(charger, ChargeInhibit::LIMIT).await?;
+        } else {
+            return Ok(());
+        }
+
+        // Only commit the new state once the charger accepted it so a failed command is retried on the next update
+        self.state.lock(|state| state.borrow_mut().inhibited = inhibit);
+        Ok(())
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Enforce the charge limit against the cached state of charge of the given battery.
+    ///
+    /// Does nothing if no [`ChargeLimiter`] was provided to the service or while the battery has a safety fault, so
+    /// charging isn't resumed until the fault clears. Battery maintenance and in-bag detection also take precedence
+    /// over the limit.
+    pub async fn enforce_charge_limit<C: Charger>(
+        &self,
+        battery_id: DeviceId,
+        charger: &mut C,
+    ) -> Result<(), BatteryError> {
+        let Some(limiter) = self.charge_control.limiter else {
+            return Ok(());
+        };
+        if self.is_faulted(battery_id) || self.is_calibrating(battery_id) || self.is_in_bag() {
+            return Ok(());
+        }
+
+        let soc = {
+            let fuel_gauge = self.lock_fuel_gauge(battery_id).await?;
+            check_state(fuel_gauge.state())?;
+            fuel_gauge.state().dynamic_cache().standard().relative_soc
+        };
+
+        limiter.enforce(charger, soc).await.map_err(|e| {
+            error!("Failed to enforce charge limit: {:?}", e);
+            crate::charger_error(e)
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use embassy_sync::channel::Channel;
+    use power_policy_interface::capability::{ConsumerPowerCapability, PowerCapability};
+    use power_policy_interface::charger::EventData;
+    use power_policy_interface_test_mocks::charger::{FnCall, Mock};
+
+    const CAPABILITY: PowerCapability = PowerCapability {
+        voltage_mv: 20000,
+        current_ma: 3000,
+    };
+
+    struct TestNvram(u32);
+
+    impl<'a> NvramStorage<'a, u32> for TestNvram {
+        fn read(&self) -> u32 {
+            self.0
+        }
+
+        fn write(&mut self, value: u32) {
+            self.0 = value;
+        }
+    }
+
+    #[test]
+    fn limit_round_trips_through_storage() {
+        let limit = ChargeLimit::new(80, 75);
+        assert_eq!(decode(encode(limit)), limit);
+        assert_eq!(decode(encode(None)), None);
+        // Uninitialized flash reads as all ones, the invalid ceiling must not be restored
+        assert_eq!(decode(u32::MAX), None);
+    }
+
+    #[test]
+    fn inhibit_has_hysteresis() {
+        let limit = ChargeLimit::new(80, 75);
+        assert!(!should_inhibit(limit, false, 79));
+        assert!(should_inhibit(limit, false, 80));
+        assert!(should_inhibit(limit, true, 76));
+        assert!(!should_inhibit(limit, true, 75));
+        assert!(!should_inhibit(None, true, 100));
+    }
+
+    #[test]
+    fn set_limit_persists() {
+        let mut nvram = TestNvram(0);
+        let limiter = ChargeLimiter::new(&mut nvram);
+        assert_eq!(limiter.status(), ChargeLimitStatus::default());
+
+        limiter.set_limit(ChargeLimit::new(80, 75));
+        assert_eq!(limiter.status().limit, ChargeLimit::new(80, 75));
+        drop(limiter);
+
+        let limiter = ChargeLimiter::new(&mut nvram);
+        assert_eq!(limiter.status().limit, ChargeLimit::new(80, 75));
+    }
+
+    #[tokio::test]
+    async fn inhibit_is_reapplied_after_reattach() {
+        let channel: Channel<GlobalRawMutex, EventData, 1> = Channel::new();
+        let mut charger = Mock::new(channel.dyn_sender());
+        let capability = ConsumerPowerCapability::from(CAPABILITY);
+        charger.state_mut().on_policy_attach(capability);
+
+        let mut nvram = TestNvram(0);
+        let limiter = ChargeLimiter::new(&mut nvram);
+        limiter.set_limit(ChargeLimit::new(80, 75));
+
+        charger.next_result_charging_current.push_back(Ok(0));
+        limiter.enforce(&mut charger, 80).await.unwrap();
+        assert!(limiter.status().inhibited);
+
+        // The power policy re-attaches, restoring the charge current
+        charger.next_result_attach_handler.push_back(Ok(()));
+        charger.attach_handler(capability).await.unwrap();
+
+        // Still above the resume threshold, charge must be inhibited again
+        charger.next_result_charging_current.push_back(Ok(0));
+        limiter.enforce(&mut charger, 78).await.unwrap();
+        assert!(limiter.status().inhibited);
+
+        // Resumes at the threshold
+        charger.next_result_attach_handler.push_back(Ok(()));
+        limiter.enforce(&mut charger, 75).await.unwrap();
+        assert!(!limiter.status().inhibited);
+
+        assert_eq!(
+            charger.fn_calls,
+            [
+                FnCall::ChargingCurrent(0),
+                FnCall::AttachHandler(capability),
+                FnCall::ChargingCurrent(0),
+                FnCall::AttachHandler(capability),
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Adaptive charging schedule.
+//!
+//! [`ChargeScheduler`] lets the host request that charging completes by a target time. Using the time-alarm
+//! service's real-time clock, the battery is trickle charged until fast charging is needed to reach full charge on
+//! time. [`Service::update_charge_schedule`](crate::Service::update_charge_schedule) runs as part of
+//! [`Service::update_charge_control`](crate::Service::update_charge_control) after each fuel gauge update to command
+//! the charger for the current phase.
+use core::cell::Cell;
+
+use battery_service_interface::fuel_gauge::{DynamicBatteryData, FuelGauge};
+use battery_service_interface::{BatteryError, ChargeTarget, DeviceId};
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_batteries_async::charger::MilliAmps;
+use embedded_batteries_async::smart_battery::CapacityModeValue;
+use embedded_services::sync::Lockable;
+use embedded_services::{GlobalRawMutex, error, info};
+use power_policy_interface::charger::{Charger, ChargerError};
+use time_alarm_service_interface::TimeAlarmService;
+
+use crate::acpi::check_state;
+use crate::registration::Registration;
+
+/// Charge schedule configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChargeScheduleConfig {
+    /// Charging current used while ahead of schedule.
+    pub trickle_current: MilliAmps,
+    /// Expected charging current once fast charging, used to estimate the time to full charge.
+    pub fast_current: MilliAmps,
+    /// Extra time in seconds reserved for fast charging to absorb estimation error.
+    pub margin_secs: u32,
+}
+
+/// Charging phase selected by the schedule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChargePhase {
+    /// Ahead of schedule, charging at the trickle current.
+    Trickle,
+    /// Charging at the current set by the power policy.
+    Fast,
+}
+
+/// Estimate the time in seconds to reach full charge at the fast charge current.
+///
+/// Only possible when the fuel gauge reports capacity in mAh, returns `None` otherwise.
+fn time_to_full_secs(full: CapacityModeValue, remaining: CapacityModeValue, fast_current: MilliAmps) -> Option<u64> {
+    match (full, remaining) {
+        (CapacityModeValue::MilliAmpUnsigned(full), CapacityModeValue::MilliAmpUnsigned(remaining))
+            if fast_current > 0 =>
+        {
+            Some(u64::from(full.saturating_sub(remaining)) * 3600 / u64::from(fast_current))
+        }
+        _ => None,
+    }
+}
+
+/// Select the charging phase, fast charging whenever the time to full charge can't be estimated.
+fn plan(now: u64, target: u64, time_to_full_secs: Option<u64>, margin_secs: u32) -> ChargePhase {
+    match time_to_full_secs {
+        Some(secs) if now.saturating_add(secs).saturating_add(margin_secs.into()) < target => ChargePhase::Trickle,
+        _ => ChargePhase::Fast,
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct ScheduleState {
+    /// Unix time by which charging should complete, `None` if no schedule is active
+    target: Option<u64>,
+    /// Whether the charger is currently limited to the trickle current
+    trickling: bool,
+}
+
+/// Schedules charging to complete by a host requested time.
+pub struct ChargeScheduler<'hw> {
+    clock: &'hw dyn TimeAlarmService,
+    config: ChargeScheduleConfig,
+    state: Mutex<GlobalRawMutex, Cell<ScheduleState>>,
+}
+
+impl<'hw> ChargeScheduler<'hw> {
+    /// Create a new charge scheduler using the given time-alarm service as its clock.
+    pub fn new(clock: &'hw dyn TimeAlarmService, config: ChargeScheduleConfig) -> Self {
+        Self {
+            clock,
+            config,
+            state: Mutex::new(Cell::new(ScheduleState::default())),
+        }
+    }
+
+    fn now(&self) -> Result<u64, BatteryError> {
+        self.clock
+            .get_real_time()
+            .map(|timestamp| timestamp.datetime.unix_timestamp())
+            .map_err(|_| {
+                error!("Failed to read real time for charge schedule");
+                BatteryError::UnspecifiedFailure
+            })
+    }
+
+    /// Set the time by which charging should complete, `None` cancels the schedule.
+    ///
+    /// The new target takes effect on the next
+    /// [`Service::update_charge_schedule`](crate::Service::update_charge_schedule).
+    pub fn set_target(&self, target: Option<ChargeTarget>) -> Result<(), BatteryError> {
+        let target = match target {
+            Some(target) => Some(self.now()?.saturating_add(target.seconds_until_complete.into())),
+            None => None,
+        };
+        info!("Charge target set: {:?}", target);
+        self.state
+            .lock(|state| state.set(ScheduleState { target, ..state.get() }));
+        Ok(())
+    }
+
+    /// Returns the current charging phase for the given estimated time to full charge.
+    ///
+    /// A schedule whose target has passed is cleared.
+    fn phase(&self, time_to_full_secs: Option<u64>) -> Result<ChargePhase, BatteryError> {
+        let Some(target) = self.state.lock(|state| state.get().target) else {
+            return Ok(ChargePhase::Fast);
+        };
+
+        let now = self.now()?;
+        if now >= target {
+            self.state.lock(|state| {
+                state.set(ScheduleState {
+                    target: None,
+                    ..state.get()
+                })
+            });
+            return Ok(ChargePhase::Fast);
+        }
+
+        Ok(plan(now, target, time_to_full_secs, self.config.margin_secs))
+    }
+
+    /// Command the charger for the given phase.
+    ///
+    /// The trickle current is re-applied on every update so that it takes precedence over a capability the power
+    /// policy attached in the meantime. Leaving the trickle phase re-applies the power policy's capability unless
+    /// charging is inhibited.
+    async fn apply<C: Charger>(&self, charger: &mut C, phase: ChargePhase) -> Result<(), ChargerError> {
+        let trickling = self.state.lock(|state| state.get().trickling);
+        match phase {
+            ChargePhase::Trickle => {
+                charger
+                    .charging_current(self.config.trickle_current)
+                    .await
+                    .map_err(|_| ChargerError::BusError)?;
+            }
+            ChargePhase::Fast if trickling => {
+                if let (false, Some(capability)) =
+                    (charger.state().is_charge_inhibited(), *charger.state().capability())
+                {
+                    charger.attach_handler(capability).await.map_err(Into::into)?;
+                }
+            }
+            ChargePhase::Fast => return Ok(()),
+        }
+
+        if trickling != (phase == ChargePhase::Trickle) {
+            info!("Charge schedule entering {:?} phase", phase);
+        }
+        self.state.lock(|state| {
+            state.set(ScheduleState {
+                trickling: phase == ChargePhase::Trickle,
+                ..state.get()
+            })
+        });
+        Ok(())
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Update the charging schedule from the cached capacity of the given battery and command the charger.
+    ///
+    /// Does nothing if no [`ChargeScheduler`] was provided to the service. The charger is left untouched while a
+    /// charge limit or in-bag detection is inhibiting charge, the battery has a safety fault or is under maintenance.
+    pub async fn update_charge_schedule<C: Charger>(
+        &self,
+        battery_id: DeviceId,
+        charger: &mut C,
+    ) -> Result<ChargePhase, BatteryError> {
+        let Some(scheduler) = self.charge_control.scheduler else {
+            return Ok(ChargePhase::Fast);
+        };
+
+        let time_to_full = {
+            let fuel_gauge = self.lock_fuel_gauge(battery_id).await?;
+            check_state(fuel_gauge.state())?;
+            let dynamic = fuel_gauge.state().dynamic_cache().standard();
+            time_to_full_secs(
+                dynamic.full_charge_capacity,
+                dynamic.remaining_capacity,
+                scheduler.config.fast_current,
+            )
+        };
+
+        let phase = scheduler.phase(time_to_full)?;
+        if self.is_faulted(battery_id)
+            || self.is_calibrating(battery_id)
+            || self.is_in_bag()
+            || self
+                .charge_control
+                .limiter
+                .is_some_and(|limiter| limiter.status().inhibited)
+        {
+            return Ok(phase);
+        }
+
+        scheduler.apply(charger, phase).await.map_err(|e| {
+            error!("Failed to apply charge schedule: {:?}", e);
+            crate::charger_error(e)
+        })?;
+        Ok(phase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_to_full_requires_milliamp_capacity() {
+        assert_eq!(
+            time_to_full_secs(
+                CapacityModeValue::MilliAmpUnsigned(5000),
+                CapacityModeValue::MilliAmpUnsigned(3000),
+                2000
+            ),
+            Some(3600)
+        );
+        assert_eq!(
+            time_to_full_secs(
+                CapacityModeValue::CentiWattUnsigned(5000),
+                CapacityModeValue::CentiWattUnsigned(3000),
+                2000
+            ),
+            None
+        );
+        assert_eq!(
+            time_to_full_secs(
+                CapacityModeValue::MilliAmpUnsigned(5000),
+                CapacityModeValue::MilliAmpUnsigned(3000),
+                0
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn plan_fast_charges_when_needed_to_finish_on_time() {
+        // One hour to full with a ten minute margin, target in two hours
+        assert_eq!(plan(0, 7200, Some(3600), 600), ChargePhase::Trickle);
+        // Target in just over an hour, no time left to trickle
+        assert_eq!(plan(0, 4000, Some(3600), 600), ChargePhase::Fast);
+        // Unknown time to full always fast charges
+        assert_eq!(plan(0, 7200, None, 600), ChargePhase::Fast);
+    }
+}
@@ -0,0 +1,328 @@
+//! Fuel gauge configuration image programming.
+//!
+//! Many fuel gauges must be loaded with a configuration image (chemistry, data flash parameters, etc.) before they
+//! report accurate data, e.g. TI gauges are configured from a golden file flash stream. The image is converted to
+//! the binary format below at build time and stored in a flash partition, typically a read-only partition from
+//! partition-manager. [`program`] pushes it to a [`GaugeConfig`] fuel gauge at first boot or after a gauge reset.
+//!
+//! | Offset | Size | Field                            |
+//! |--------|------|----------------------------------|
+//! | 0      | 4    | Magic [`MAGIC`]                  |
+//! | 4      | 1    | Layout version [`VERSION`]       |
+//! | 5      | 3    | Reserved                         |
+//! | 8      | 4    | Length of the records in bytes   |
+//! | 12     | 4    | Configuration signature          |
+//! | 16     | 4    | CRC-32 (ISO-HDLC) of the records |
+//! | 20     | ...  | Records                          |
+//!
+//! Each record starts with an opcode matching the flash stream commands:
+//!
+//! | Opcode     | Fields                              | Action                                              |
+//! |------------|-------------------------------------|-----------------------------------------------------|
+//! | `W` (0x57) | address, register, length, data     | Write the data to the register                      |
+//! | `C` (0x43) | address, register, length, expected | Read back and compare, e.g. a data flash checksum   |
+//! | `X` (0x58) | delay in milliseconds (2 bytes)     | Wait, e.g. for a data flash block to be committed   |
+//!
+//! All fields are little endian. The record CRC is checked before anything is written to the fuel gauge, so a
+//! corrupted partition never leaves the gauge partially programmed. Once programmed, the fuel gauge reports the
+//! signature from the header through [`GaugeConfig::config_signature`] (usually because the image writes it) and
+//! later calls skip programming.
+//!
+//! ```ignore
+//! fuel_gauge.initialize().await?;
+//! gauge_config::program(&mut fuel_gauge, &mut partition, false, |progress| {
+//!     info!("Gauge config {}/{}", progress.written, progress.total);
+//! })
+//! .await?;
+//! fuel_gauge.update_static_data().await?;
+//! ```
+
+use battery_service_interface::fuel_gauge::{FuelGaugeError, GaugeConfig};
+use embassy_time::Timer;
+use embedded_services::{info, warn};
+use embedded_storage_async::nor_flash::ReadNorFlash;
+
+/// Magic value identifying a configuration image
+pub const MAGIC: u32 = 0x4746_4347;
+/// Version of the image layout
+pub const VERSION: u8 = 1;
+/// Size of the image header in bytes
+pub const HEADER_SIZE: usize = 20;
+/// Maximum data length of a single record
+pub const MAX_DATA_SIZE: usize = 64;
+/// Size of the flash read buffer, the largest supported flash read granularity
+const BUFFER_SIZE: usize = 64;
+
+/// Write record opcode
+const OP_WRITE: u8 = b'W';
+/// Compare record opcode
+const OP_COMPARE: u8 = b'C';
+/// Delay record opcode
+const OP_DELAY: u8 = b'X';
+
+const CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// Configuration programming error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// Flash access failed
+    Flash(E),
+    /// The flash read granularity isn't supported
+    UnsupportedFlash,
+    /// The image header is invalid or the image doesn't fit in the partition
+    InvalidImage,
+    /// The image CRC doesn't match, nothing was written to the fuel gauge
+    Checksum,
+    /// A record at the given image offset is malformed
+    InvalidRecord(u32),
+    /// Reading back the fuel gauge didn't match the compare record at the given image offset
+    Verify(u32),
+    /// Fuel gauge access failed
+    Gauge(FuelGaugeError),
+}
+
+/// Result of [`program`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Outcome {
+    /// The fuel gauge was programmed with the image
+    Programmed,
+    /// The fuel gauge already reported the image signature, nothing was written
+    AlreadyProgrammed,
+}
+
+/// Programming progress, reported after each record
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Progress {
+    /// Bytes of records processed
+    pub written: u32,
+    /// Total bytes of records in the image
+    pub total: u32,
+}
+
+/// Configuration image header
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Header {
+    /// Length of the records in bytes
+    pub length: u32,
+    /// Signature reported by the fuel gauge once programmed
+    pub signature: u32,
+    /// CRC-32 of the records
+    pub crc: u32,
+}
+
+impl Header {
+    /// Decode a header, returns `None` if the magic or version don't match
+    pub fn decode(bytes: &[u8; HEADER_SIZE]) -> Option<Self> {
+        let field = |offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .and_then(|field| field.try_into().ok())
+                .map(u32::from_le_bytes)
+        };
+
+        if field(0)? != MAGIC || bytes.get(4) != Some(&VERSION) {
+            return None;
+        }
+
+        Some(Self {
+            length: field(8)?,
+            signature: field(12)?,
+            crc: field(16)?,
+        })
+    }
+}
+
+/// Sequential reader over a flash partition that respects the flash read granularity
+struct Reader<'a, F: ReadNorFlash> {
+    flash: &'a mut F,
+    buffer: [u8; BUFFER_SIZE],
+    /// Partition offset of the buffered data
+    start: u32,
+    /// Length of the buffered data
+    len: usize,
+    /// Partition offset of the next byte
+    offset: u32,
+}
+
+impl<'a, F: ReadNorFlash> Reader<'a, F> {
+    fn new(flash: &'a mut F, offset: u32) -> Self {
+        Self {
+            flash,
+            buffer: [0; BUFFER_SIZE],
+            start: 0,
+            len: 0,
+            offset,
+        }
+    }
+
+    async fn read(&mut self, bytes: &mut [u8]) -> Result<(), Error<F::Error>> {
+        for byte in bytes {
+            *byte = self.read_byte().await?;
+        }
+        Ok(())
+    }
+
+    async fn read_byte(&mut self) -> Result<u8, Error<F::Error>> {
+        let buffered = self
+            .offset
+            .checked_sub(self.start)
+            .map(|index| index as usize)
+            .filter(|index| *index < self.len);
+
+        let index = match buffered {
+            Some(index) => index,
+            None => {
+                // Refill from the read-aligned offset at or before the next byte
+                let chunk = BUFFER_SIZE - BUFFER_SIZE % F::READ_SIZE;
+                self.start = self.offset - self.offset % F::READ_SIZE as u32;
+                self.len = chunk.min(self.flash.capacity().saturating_sub(self.start as usize));
+                let (chunk, _) = self.buffer.split_at_mut(self.len);
+                self.flash.read(self.start, chunk).await.map_err(Error::Flash)?;
+                (self.offset - self.start) as usize
+            }
+        };
+
+        let byte = self
+            .buffer
+            .get(..self.len)
+            .and_then(|buffered| buffered.get(index))
+            .copied()
+            .ok_or(Error::InvalidImage)?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    async fn read_u16(&mut self) -> Result<u16, Error<F::Error>> {
+        let mut bytes = [0; 2];
+        self.read(&mut bytes).await?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+}
+
+/// Read and validate the image header and record CRC
+pub async fn verify_image<F: ReadNorFlash>(flash: &mut F) -> Result<Header, Error<F::Error>> {
+    if F::READ_SIZE > BUFFER_SIZE {
+        return Err(Error::UnsupportedFlash);
+    }
+
+    let capacity = flash.capacity();
+    let mut reader = Reader::new(flash, 0);
+    let mut header = [0; HEADER_SIZE];
+    reader.read(&mut header).await?;
+    let header = Header::decode(&header).ok_or(Error::InvalidImage)?;
+    if HEADER_SIZE.saturating_add(header.length as usize) > capacity {
+        return Err(Error::InvalidImage);
+    }
+
+    let mut digest = CRC.digest();
+    for _ in 0..header.length {
+        digest.update(&[reader.read_byte().await?]);
+    }
+
+    if digest.finalize() != header.crc {
+        return Err(Error::Checksum);
+    }
+
+    Ok(header)
+}
+
+/// Program `fuel_gauge` with the configuration image stored in `flash`
+///
+/// Programming is skipped if the fuel gauge already reports the image signature, unless `force` is set. `progress`
+/// is called after every record. On error the fuel gauge may be partially programmed and reports no signature, so
+/// programming is attempted again on the next call.
+pub async fn program<G: GaugeConfig, F: ReadNorFlash>(
+    fuel_gauge: &mut G,
+    flash: &mut F,
+    force: bool,
+    mut progress: impl FnMut(Progress),
+) -> Result<Outcome, Error<F::Error>> {
+    let header = verify_image(flash).await?;
+    let gauge_error = |e: G::FuelGaugeError| Error::Gauge(e.into());
+
+    if !force && fuel_gauge.config_signature().await.map_err(gauge_error)? == Some(header.signature) {
+        info!("Fuel gauge config {:#x} already programmed", header.signature);
+        return Ok(Outcome::AlreadyProgrammed);
+    }
+
+    info!("Programming fuel gauge config {:#x}", header.signature);
+    let end = HEADER_SIZE as u32 + header.length;
+    let mut reader = Reader::new(flash, HEADER_SIZE as u32);
+    while reader.offset < end {
+        let record = reader.offset;
+        match reader.read_byte().await? {
+            op @ (OP_WRITE | OP_COMPARE) => {
+                let address = reader.read_byte().await?;
+                let register = reader.read_byte().await?;
+                let len = usize::from(reader.read_byte().await?);
+                let mut buffer = [0; MAX_DATA_SIZE];
+                let data = buffer.get_mut(..len).ok_or(Error::InvalidRecord(record))?;
+                reader.read(data).await?;
+
+                if op == OP_WRITE {
+                    fuel_gauge
+                        .write_config(address, register, data)
+                        .await
+                        .map_err(gauge_error)?;
+                } else {
+                    let mut actual = [0; MAX_DATA_SIZE];
+                    let actual = actual.get_mut(..len).ok_or(Error::InvalidRecord(record))?;
+                    fuel_gauge
+                        .read_config(address, register, actual)
+                        .await
+                        .map_err(gauge_error)?;
+                    if actual != data {
+                        warn!("Fuel gauge config verification failed at offset {}", record);
+                        return Err(Error::Verify(record));
+                    }
+                }
+            }
+            OP_DELAY => Timer::after_millis(u64::from(reader.read_u16().await?)).await,
+            _ => return Err(Error::InvalidRecord(record)),
+        }
+
+        if reader.offset > end {
+            return Err(Error::InvalidRecord(record));
+        }
+
+        progress(Progress {
+            written: reader.offset - HEADER_SIZE as u32,
+            total: header.length,
+        });
+    }
+
+    info!("Fuel gauge config {:#x} programmed", header.signature);
+    Ok(Outcome::Programmed)
+}
+
+#[cfg(test)]
+#[allow(clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_decode() {
+        let mut header = [0u8; HEADER_SIZE];
+        header[..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4] = VERSION;
+        header[8..12].copy_from_slice(&6u32.to_le_bytes());
+        header[12..16].copy_from_slice(&0x1234u32.to_le_bytes());
+        header[16..20].copy_from_slice(&0xDEAD_BEEFu32.to_le_bytes());
+
+        assert_eq!(
+            Header::decode(&header),
+            Some(Header {
+                length: 6,
+                signature: 0x1234,
+                crc: 0xDEAD_BEEF,
+            })
+        );
+
+        header[4] = VERSION + 1;
+        assert_eq!(Header::decode(&header), None);
+    }
+}
@@ -0,0 +1,267 @@
+//! In-bag detection.
+//!
+//! A system left running with its lid closed inside a bag can't shed heat, which shows as the battery temperature
+//! climbing quickly. [`InBagDetector`] combines the lid state, set by the OEM from the lid switch, with the rise of
+//! the cached battery temperature over a sliding window. [`Service::update_in_bag`](crate::Service::update_in_bag),
+//! called by the OEM after each fuel gauge update, then:
+//!
+//! * inhibits charging while the system is in the bag, restoring it once the lid is opened,
+//! * submits [`HeatSource::InBag`] notices so the thermal service ramps the fans,
+//! * raises an [`InBagEvent`] on each transition, which the OEM relays to the host through [`InBagDetector::wait_event`].
+use core::cell::RefCell;
+
+use battery_service_interface::fuel_gauge::{DynamicBatteryData, FuelGauge};
+use battery_service_interface::{BatteryError, DeviceId};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant};
+use embedded_batteries_async::smart_battery::DeciKelvin;
+use embedded_services::sync::Lockable;
+use embedded_services::{GlobalRawMutex, error, info};
+use power_policy_interface::charger::{ChargeInhibit, Charger, ChargerError};
+use thermal_service_interface::heat::{HeatNoticeSink, HeatSource, InBagEvent};
+
+use crate::acpi::check_state;
+use crate::registration::Registration;
+
+/// Maximum number of temperature samples kept for the detection window.
+const MAX_SAMPLES: usize = 16;
+
+/// In-bag detection configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InBagConfig {
+    /// Whether detection is enabled
+    pub enabled: bool,
+    /// Battery temperature rise within [`window`](Self::window) that indicates the system is in a bag, in dK
+    pub temp_rise: DeciKelvin,
+    /// Period over which the temperature rise is measured
+    pub window: Duration,
+}
+
+impl Default for InBagConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            temp_rise: 50,
+            window: Duration::from_secs(600),
+        }
+    }
+}
+
+struct InBagState {
+    lid_closed: bool,
+    /// Battery temperature samples within the detection window, oldest first
+    samples: heapless::Deque<(Instant, DeciKelvin), MAX_SAMPLES>,
+    detected: bool,
+    /// Charge is currently inhibited by in-bag detection
+    inhibited: bool,
+}
+
+/// In-bag detection state.
+pub struct InBagDetector<'hw> {
+    config: InBagConfig,
+    heat: Option<&'hw dyn HeatNoticeSink>,
+    state: Mutex<GlobalRawMutex, RefCell<InBagState>>,
+    event: Signal<GlobalRawMutex, InBagEvent>,
+}
+
+impl<'hw> InBagDetector<'hw> {
+    /// Create a new detector, submitting heat notices to `heat` while the system is in a bag.
+    pub fn new(config: InBagConfig, heat: Option<&'hw dyn HeatNoticeSink>) -> Self {
+        Self {
+            config,
+            heat,
+            state: Mutex::new(RefCell::new(InBagState {
+                lid_closed: false,
+                samples: heapless::Deque::new(),
+                detected: false,
+                inhibited: false,
+            })),
+            event: Signal::new(),
+        }
+    }
+
+    /// Update the lid state.
+    ///
+    /// Closing the lid starts a new detection window, opening it clears any detection. Charging is restored on the
+    /// next [`update_in_bag`](crate::Service::update_in_bag).
+    pub fn set_lid_closed(&self, closed: bool) {
+        let cleared = self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if state.lid_closed == closed {
+                return false;
+            }
+
+            state.lid_closed = closed;
+            state.samples.clear();
+            !closed && core::mem::replace(&mut state.detected, false)
+        });
+
+        if cleared {
+            info!("Lid opened, in-bag condition cleared");
+            self.event.signal(InBagEvent::Cleared);
+        }
+    }
+
+    /// Returns true if the system is currently detected in a bag.
+    pub fn is_detected(&self) -> bool {
+        self.state.lock(|state| state.borrow().detected)
+    }
+
+    /// Wait for the next in-bag detection event.
+    pub async fn wait_event(&self) -> InBagEvent {
+        self.event.wait().await
+    }
+
+    /// Record a battery temperature sample taken at `now`, returns true if the system is in a bag.
+    fn record_at(&self, temperature: DeciKelvin, now: Instant) -> bool {
+        let (detected, newly_detected) = self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if !self.config.enabled || !state.lid_closed {
+                return (false, false);
+            }
+
+            while state
+                .samples
+                .front()
+                .is_some_and(|(at, _)| now.saturating_duration_since(*at) > self.config.window)
+            {
+                state.samples.pop_front();
+            }
+            if state.samples.is_full() {
+                state.samples.pop_front();
+            }
+            // Can't fail, room was made above
+            let _ = state.samples.push_back((now, temperature));
+
+            if state.detected {
+                return (true, false);
+            }
+
+            let coolest = state.samples.iter().map(|&(_, t)| t).min().unwrap_or(temperature);
+            state.detected = temperature.saturating_sub(coolest) >= self.config.temp_rise;
+            (state.detected, state.detected)
+        });
+
+        if newly_detected {
+            info!("In-bag condition detected at {} dK", temperature);
+            self.event.signal(InBagEvent::Detected);
+        }
+        detected
+    }
+
+    /// Inhibit or restore charging according to the detection state.
+    ///
+    /// The inhibit is re-applied on every update while detected in case the charger restored the charge current.
+    async fn enforce<C: Charger>(&self, charger: &mut C, detected: bool) -> Result<(), ChargerError> {
+        let inhibited = self.state.lock(|state| state.borrow().inhibited);
+        if detected {
+            if !inhibited {
+                info!("Inhibiting charge while in bag");
+            }
+            crate::inhibit_charge(charger, ChargeInhibit::IN_BAG).await?;
+        } else if !inhibited {
+            return Ok(());
+        } else {
+            info!("Restoring charge after in-bag condition");
+            crate::release_charge(charger, ChargeInhibit::IN_BAG).await?;
+        }
+
+        // Only commit the new state once the charger accepted it so a failed command is retried on the next update
+        self.state.lock(|state| state.borrow_mut().inhibited = detected);
+        Ok(())
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Returns true if charging is inhibited by in-bag detection.
+    pub fn is_in_bag(&self) -> bool {
+        self.charge_control
+            .in_bag
+            .is_some_and(|detector| detector.state.lock(|state| state.borrow().inhibited) || detector.is_detected())
+    }
+
+    /// Run in-bag detection against the cached temperature of the given battery and command the charger.
+    ///
+    /// Does nothing if no [`InBagDetector`] was provided to the service. An active learn cycle is interrupted when
+    /// the system is detected in a bag. Once the lid is opened, charging is only restored if the charge limit doesn't
+    /// inhibit it. This should be called after the other charge control updates so the inhibit takes precedence.
+    pub async fn update_in_bag<C: Charger>(&self, battery_id: DeviceId, charger: &mut C) -> Result<(), BatteryError> {
+        let Some(detector) = self.charge_control.in_bag else {
+            return Ok(());
+        };
+
+        let temperature = {
+            let fuel_gauge = self.lock_fuel_gauge(battery_id).await?;
+            check_state(fuel_gauge.state())?;
+            fuel_gauge.state().dynamic_cache().standard().battery_temp
+        };
+
+        let detected = detector.record_at(temperature, Instant::now());
+        if detected {
+            if let Some(heat) = detector.heat {
+                heat.heat_notice(HeatSource::InBag);
+            }
+            if let Some(calibrator) = self.charge_control.calibrator
+                && self.is_calibrating(battery_id)
+            {
+                calibrator.interrupt();
+            }
+        }
+
+        detector.enforce(charger, detected).await.map_err(|e| {
+            error!("Failed to enforce in-bag charge inhibit: {:?}", e);
+            crate::charger_error(e)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> InBagDetector<'static> {
+        InBagDetector::new(
+            InBagConfig {
+                enabled: true,
+                temp_rise: 50,
+                window: Duration::from_secs(60),
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn detects_rapid_rise_with_lid_closed() {
+        let detector = detector();
+        let at = Instant::from_secs;
+
+        // Lid open, the rise is ignored
+        assert!(!detector.record_at(3000, at(0)));
+        assert!(!detector.record_at(3100, at(10)));
+
+        detector.set_lid_closed(true);
+        assert!(!detector.record_at(3000, at(20)));
+        assert!(!detector.record_at(3030, at(40)));
+        assert!(detector.record_at(3050, at(60)));
+        assert_eq!(detector.event.try_take(), Some(InBagEvent::Detected));
+
+        // Stays detected until the lid is opened
+        assert!(detector.record_at(3000, at(200)));
+        detector.set_lid_closed(false);
+        assert!(!detector.is_detected());
+        assert_eq!(detector.event.try_take(), Some(InBagEvent::Cleared));
+    }
+
+    #[test]
+    fn slow_rise_is_ignored() {
+        let detector = detector();
+        detector.set_lid_closed(true);
+
+        // 40 dK per minute never reaches the 50 dK rise within the window
+        for (i, t) in (3000..3400).step_by(40).enumerate() {
+            assert!(!detector.record_at(t, Instant::from_secs(i as u64 * 61)));
+        }
+    }
+}
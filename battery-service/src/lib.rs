@@ -1,28 +1,143 @@
 #![no_std]
 
 use battery_service_interface::{
-    BatteryError, Bct, BctReturnResult, BixFixedStrings, Bma, Bmc, Bmd, Bms, Bpc, Bps, Bpt, BstReturn, Btm,
-    BtmReturnResult, Btp, PifFixedStrings, PsrReturn, StaReturn,
+    BatteryError, BatteryInfoFormat, Bct, BctReturnResult, BixFixedStrings, Bma, Bmc, Bmd, Bms, Bpc, Bps, Bpt,
+    BstReturn, Btm, BtmReturnResult, Btp, ChargeLimit, ChargeLimitStatus, ChargeTarget, MeasurementStatus,
+    PifFixedStrings, PsrReturn, StaReturn,
 };
 use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+use embassy_time::Duration;
 use embedded_services::info;
 use embedded_services::sync::Lockable;
+use power_policy_interface::charger::{ChargeInhibit, Charger, ChargerError};
 
 mod acpi;
+pub mod calibration;
+pub mod charge_limit;
+pub mod charge_schedule;
+pub mod gauge_config;
+pub mod in_bag;
+pub mod lifecycle;
 #[cfg(feature = "mock")]
 pub mod mock;
+pub mod presence;
+mod recovery;
 pub mod registration;
+pub mod safety;
+mod snapshot;
+pub mod telemetry;
 
+pub use calibration::{CalibrationConfig, CalibrationPhase, Calibrator};
+pub use charge_limit::ChargeLimiter;
+pub use charge_schedule::{ChargePhase, ChargeScheduleConfig, ChargeScheduler};
+pub use in_bag::{InBagConfig, InBagDetector};
+pub use lifecycle::LifecycleWatch;
+pub use presence::{PresenceChange, PresenceNotification};
 pub use registration::{ArrayRegistration, Registration};
+pub use safety::BatteryFault;
 
 // Re-export the fuel gauge interface so that OEM drivers and integrators can
 // implement and use the battery service without depending on the interface crate directly.
 pub use battery_service_interface::fuel_gauge::{
-    DynamicBatteryData, DynamicBatteryMsgs, FuelGauge, FuelGaugeError, InternalState, OperationalSubstate,
-    PresentSubstate, State, StaticBatteryData, StaticBatteryMsgs,
+    AveragedMeasurements, DynamicBatteryData, DynamicBatteryMsgs, FuelGauge, FuelGaugeError, GaugeConfig,
+    InternalState, MeasurementConfig, OperationalSubstate, PresentSubstate, SafetyFaults, State, StaticBatteryData,
+    StaticBatteryMsgs,
 };
+pub use battery_service_interface::state_machine::{LifecycleEvent, LifecycleState, Transition, TransitionListener};
 pub use battery_service_interface::{BatteryService, DeviceId};
 
+/// Battery service configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Maximum time an ACPI request waits on a fuel gauge before failing and marking the fuel gauge degraded.
+    pub request_timeout: Duration,
+    /// Delay between recovery probes of degraded fuel gauges.
+    pub recovery_interval: Duration,
+    /// Source of the _BCT and _BTM time estimates.
+    pub time_estimation: TimeEstimation,
+    /// Set [`BatteryFault::shutdown_requested`] when a battery reports a safety fault.
+    ///
+    /// This only flags the request, the platform's handler on the power endpoint performs the shutdown.
+    pub shutdown_on_safety_fault: bool,
+    /// Interval of the [`BatteryTelemetry`](telemetry::BatteryTelemetry) broadcast, `None` to disable it.
+    pub telemetry_interval: Option<Duration>,
+    /// Layout of the static battery information returned to the host, _BIF for hosts that don't support _BIX.
+    pub battery_info_format: BatteryInfoFormat,
+}
+
+/// Source of the charge time (_BCT) and run time (_BTM) estimates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimeEstimation {
+    /// Use the fuel gauge's own estimates, computing them on the EC when the fuel gauge doesn't provide one.
+    #[default]
+    PreferGauge,
+    /// Always compute the estimates on the EC from the cached current and capacity.
+    Ec,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_millis(500),
+            recovery_interval: Duration::from_secs(10),
+            time_estimation: TimeEstimation::default(),
+            shutdown_on_safety_fault: false,
+            telemetry_interval: None,
+            battery_info_format: BatteryInfoFormat::default(),
+        }
+    }
+}
+
+/// Optional charge control features driven by the battery service.
+#[derive(Clone, Copy, Default)]
+pub struct ChargeControl<'hw> {
+    /// Battery-saver charge limit
+    pub limiter: Option<&'hw ChargeLimiter<'hw>>,
+    /// Scheduled charge completion
+    pub scheduler: Option<&'hw ChargeScheduler<'hw>>,
+    /// Host-requested battery maintenance and recalibration
+    pub calibrator: Option<&'hw Calibrator>,
+    /// Charge inhibit while the system is closed in a bag
+    pub in_bag: Option<&'hw InBagDetector<'hw>>,
+}
+
+/// Map a charger error onto the most specific battery error, so the host can tell transient failures apart.
+pub(crate) fn charger_error(error: ChargerError) -> BatteryError {
+    match error {
+        ChargerError::Timeout => BatteryError::Timeout { error_code: 0 },
+        ChargerError::BusError => BatteryError::BusError { error_code: 0 },
+        ChargerError::InvalidState(_) => BatteryError::InvalidState { error_code: 0 },
+        ChargerError::UnknownEvent => BatteryError::UnspecifiedFailure,
+    }
+}
+
+/// Inhibit charging for the given reason by commanding a zero charging current.
+///
+/// The reason is recorded in the charger state so that power policy doesn't re-attach the charger meanwhile.
+pub(crate) async fn inhibit_charge<C: Charger>(charger: &mut C, reason: ChargeInhibit) -> Result<(), ChargerError> {
+    charger.charging_current(0).await.map_err(|_| ChargerError::BusError)?;
+    charger.state_mut().set_charge_inhibit(reason, true);
+    Ok(())
+}
+
+/// Clear the given charge inhibit reason.
+///
+/// Charging resumes by re-applying the consumer capability the power policy last attached to the charger, if any,
+/// but only once no other charge control feature inhibits it.
+pub(crate) async fn release_charge<C: Charger>(charger: &mut C, reason: ChargeInhibit) -> Result<(), ChargerError> {
+    charger.state_mut().set_charge_inhibit(reason, false);
+    if charger.state().is_charge_inhibited() {
+        return Ok(());
+    }
+    if let Some(capability) = *charger.state().capability() {
+        charger.attach_handler(capability).await.map_err(Into::into)?;
+    }
+    Ok(())
+}
+
 /// The battery service.
 ///
 /// Owns the [`Registration`] that provides the set of fuel gauges, and answers
@@ -31,15 +146,58 @@ pub use battery_service_interface::{BatteryService, DeviceId};
 /// gauge directly through the [`FuelGauge`] trait methods.
 pub struct Service<'hw, Reg: Registration<'hw>> {
     registration: Reg,
+    config: Config,
+    /// Bitmask of fuel gauges that timed out and are awaiting recovery
+    degraded: AtomicU32,
+    /// Bitmask of inserted batteries whose fuel gauge hasn't been initialized yet
+    inserting: AtomicU32,
+    /// Bitmask of batteries last detected as not present
+    removed: AtomicU32,
+    /// Active safety faults of each battery
+    faults: safety::FaultMasks,
+    /// Optional charge limit and charge schedule
+    charge_control: ChargeControl<'hw>,
+    /// Host notification rung on battery insertion and removal
+    presence_notification: Option<PresenceNotification<'hw>>,
+    /// Lifecycle state of each fuel gauge, in registration order
+    lifecycle: &'static [LifecycleWatch],
     _phantom: PhantomData<&'hw ()>,
 }
 
 impl<'hw, Reg: Registration<'hw>> Service<'hw, Reg> {
     /// Create a new battery service that owns the provided registration.
     pub fn new(registration: Reg) -> Self {
+        Self::new_with_config(registration, Config::default())
+    }
+
+    /// Create a new battery service with the given configuration.
+    pub fn new_with_config(registration: Reg, config: Config) -> Self {
+        Self::new_with_charge_control(registration, config, ChargeControl::default())
+    }
+
+    /// Create a new battery service with the given configuration and charge control features.
+    pub fn new_with_charge_control(registration: Reg, config: Config, charge_control: ChargeControl<'hw>) -> Self {
+        Self::new_with_presence_notification(registration, config, charge_control, None)
+    }
+
+    /// Create a new battery service that notifies the host when a battery is inserted or removed.
+    pub fn new_with_presence_notification(
+        registration: Reg,
+        config: Config,
+        charge_control: ChargeControl<'hw>,
+        presence_notification: Option<PresenceNotification<'hw>>,
+    ) -> Self {
         info!("Starting battery-service");
         Self {
             registration,
+            config,
+            degraded: AtomicU32::new(0),
+            inserting: AtomicU32::new(0),
+            removed: AtomicU32::new(0),
+            faults: safety::FaultMasks::default(),
+            charge_control,
+            presence_notification,
+            lifecycle: &[],
             _phantom: PhantomData,
         }
     }
@@ -53,6 +211,23 @@ impl<'hw, Reg: Registration<'hw>> Service<'hw, Reg> {
     pub fn get_fuel_gauge(&self, id: DeviceId) -> Option<&'hw Reg::FuelGauge> {
         self.registration.get_fuel_gauge(id)
     }
+
+    /// Run the charge control features against the cached data of the given battery and command the charger.
+    ///
+    /// Called by the OEM after each fuel gauge dynamic data update. Safety faults are handled first so the other
+    /// features see the current fault state. Features then run in order of precedence, the charge limit runs after the
+    /// charge schedule so that its inhibit overrides the trickle current. A failing feature doesn't prevent the others
+    /// from running, the first error is returned.
+    pub async fn update_charge_control<C: Charger>(
+        &self,
+        battery_id: DeviceId,
+        charger: &mut C,
+    ) -> Result<(), BatteryError> {
+        let safety = self.handle_safety_faults(battery_id, charger).await.map(|_| ());
+        let schedule = self.update_charge_schedule(battery_id, charger).await.map(|_| ());
+        let limit = self.enforce_charge_limit(battery_id, charger).await;
+        safety.and(schedule).and(limit)
+    }
 }
 
 impl<'hw, Reg: Registration<'hw>> battery_service_interface::BatteryService for Service<'hw, Reg> {
@@ -61,46 +236,75 @@ impl<'hw, Reg: Registration<'hw>> battery_service_interface::BatteryService for
         battery_id: DeviceId,
         charge_level: Bct,
     ) -> Result<BctReturnResult, BatteryError> {
-        self.battery_charge_time(&mut *self.fuel_gauge(battery_id)?.lock().await, charge_level)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.battery_charge_time(fuel_gauge, charge_level)
+        })
+        .await
     }
 
     async fn battery_info(&self, battery_id: DeviceId) -> Result<BixFixedStrings, BatteryError> {
-        self.battery_info(&mut *self.fuel_gauge(battery_id)?.lock().await)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| self.battery_info(fuel_gauge))
+            .await
+    }
+
+    /// Registered batteries are reported until [`Self::update_presence`] detects them as removed, and once inserted
+    /// only after their fuel gauge is initialized. Batteries beyond device ID 31 are not represented.
+    fn battery_mask(&self) -> u32 {
+        let registered = (0..self.fuel_gauges().len().min(u32::BITS as usize)).fold(0, |mask, id| mask | (1 << id));
+        registered & !self.removed.load(Ordering::Relaxed) & !self.inserting.load(Ordering::Relaxed)
+    }
+
+    fn battery_info_format(&self) -> BatteryInfoFormat {
+        self.config.battery_info_format
     }
 
     async fn set_battery_measurement_averaging_interval(
         &self,
         battery_id: DeviceId,
         bma: Bma,
-    ) -> Result<(), BatteryError> {
-        self.set_battery_measurement_averaging_interval(&mut *self.fuel_gauge(battery_id)?.lock().await, bma)
+    ) -> Result<MeasurementStatus, BatteryError> {
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.set_battery_measurement_averaging_interval(fuel_gauge, bma).await
+        })
+        .await
     }
 
     async fn battery_maintenance_control(&self, battery_id: DeviceId, bmc: Bmc) -> Result<(), BatteryError> {
-        self.battery_maintenance_control(&mut *self.fuel_gauge(battery_id)?.lock().await, bmc)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.battery_maintenance_control(battery_id, fuel_gauge, bmc)
+        })
+        .await
     }
 
     async fn battery_maintenance_data(&self, battery_id: DeviceId) -> Result<Bmd, BatteryError> {
-        self.battery_maintenance_data(&mut *self.fuel_gauge(battery_id)?.lock().await)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.battery_maintenance_data(battery_id, fuel_gauge)
+        })
+        .await
     }
 
     async fn set_battery_measurement_sampling_time(
         &self,
         battery_id: DeviceId,
         battery_measurement_sampling: Bms,
-    ) -> Result<(), BatteryError> {
-        self.set_battery_measurement_sampling_time(
-            &mut *self.fuel_gauge(battery_id)?.lock().await,
-            battery_measurement_sampling,
-        )
+    ) -> Result<MeasurementStatus, BatteryError> {
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.set_battery_measurement_sampling_time(fuel_gauge, battery_measurement_sampling)
+                .await
+        })
+        .await
     }
 
     async fn battery_power_characteristics(&self, battery_id: DeviceId) -> Result<Bpc, BatteryError> {
-        self.battery_power_characteristics(&mut *self.fuel_gauge(battery_id)?.lock().await)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.battery_power_characteristics(fuel_gauge)
+        })
+        .await
     }
 
     async fn battery_power_state(&self, battery_id: DeviceId) -> Result<Bps, BatteryError> {
-        self.battery_power_state(&mut *self.fuel_gauge(battery_id)?.lock().await)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| self.battery_power_state(fuel_gauge))
+            .await
     }
 
     async fn set_battery_power_threshold(
@@ -108,11 +312,15 @@ impl<'hw, Reg: Registration<'hw>> battery_service_interface::BatteryService for
         battery_id: DeviceId,
         power_threshold: Bpt,
     ) -> Result<(), BatteryError> {
-        self.set_battery_power_threshold(&mut *self.fuel_gauge(battery_id)?.lock().await, power_threshold)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.set_battery_power_threshold(fuel_gauge, power_threshold)
+        })
+        .await
     }
 
     async fn battery_status(&self, battery_id: DeviceId) -> Result<BstReturn, BatteryError> {
-        self.battery_status(&mut *self.fuel_gauge(battery_id)?.lock().await)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| self.battery_status(fuel_gauge))
+            .await
     }
 
     async fn battery_time_to_empty(
@@ -120,22 +328,52 @@ impl<'hw, Reg: Registration<'hw>> battery_service_interface::BatteryService for
         battery_id: DeviceId,
         battery_discharge_rate: Btm,
     ) -> Result<BtmReturnResult, BatteryError> {
-        self.battery_time_to_empty(&mut *self.fuel_gauge(battery_id)?.lock().await, battery_discharge_rate)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.battery_time_to_empty(fuel_gauge, battery_discharge_rate)
+        })
+        .await
     }
 
     async fn set_battery_trip_point(&self, battery_id: DeviceId, btp: Btp) -> Result<(), BatteryError> {
-        self.set_battery_trip_point(&mut *self.fuel_gauge(battery_id)?.lock().await, btp)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| {
+            self.set_battery_trip_point(fuel_gauge, btp)
+        })
+        .await
     }
 
     async fn is_psu_in_use(&self, psu_id: DeviceId) -> Result<PsrReturn, BatteryError> {
-        self.is_psu_in_use(&mut *self.fuel_gauge(psu_id)?.lock().await)
+        self.with_fuel_gauge(psu_id, async |fuel_gauge| self.is_psu_in_use(fuel_gauge))
+            .await
     }
 
     async fn power_source_information(&self, power_source_id: DeviceId) -> Result<PifFixedStrings, BatteryError> {
-        self.power_source_information(&mut *self.fuel_gauge(power_source_id)?.lock().await)
+        self.with_fuel_gauge(power_source_id, async |fuel_gauge| {
+            self.power_source_information(fuel_gauge)
+        })
+        .await
     }
 
     async fn device_status(&self, battery_id: DeviceId) -> Result<StaReturn, BatteryError> {
-        self.device_status(&mut *self.fuel_gauge(battery_id)?.lock().await)
+        self.with_fuel_gauge(battery_id, async |fuel_gauge| self.device_status(fuel_gauge))
+            .await
+    }
+
+    async fn set_charge_limit(&self, limit: Option<ChargeLimit>) -> Result<(), BatteryError> {
+        self.charge_control
+            .limiter
+            .ok_or(BatteryError::Unsupported)?
+            .set_limit(limit);
+        Ok(())
+    }
+
+    async fn charge_limit_status(&self) -> Result<ChargeLimitStatus, BatteryError> {
+        Ok(self.charge_control.limiter.ok_or(BatteryError::Unsupported)?.status())
+    }
+
+    async fn set_charge_target(&self, target: Option<ChargeTarget>) -> Result<(), BatteryError> {
+        self.charge_control
+            .scheduler
+            .ok_or(BatteryError::Unsupported)?
+            .set_target(target)
     }
 }
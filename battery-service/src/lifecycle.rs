@@ -0,0 +1,98 @@
+//! Observing battery lifecycle state from other tasks.
+//!
+//! The fuel gauge [`State`](crate::State) reports lifecycle transitions to a single [`TransitionListener`].
+//! [`LifecycleWatch`] is a listener that publishes the latest state through a [`Watch`] so any number of tasks can
+//! read it or wait for it to change, without each keeping its own copy updated from transition callbacks.
+//!
+//! The battery service keeps the lifecycle state of its fuel gauges in the watches set with
+//! [`Service::set_lifecycle_watches`](crate::Service::set_lifecycle_watches), and other tasks observe a battery
+//! through [`Service::lifecycle`](crate::Service::lifecycle).
+//!
+//! ```ignore
+//! static BATTERY_STATE: [LifecycleWatch; 1] = [LifecycleWatch::new()];
+//!
+//! service.set_lifecycle_watches(&BATTERY_STATE).await;
+//! let mut watcher = service.lifecycle(DeviceId(0)).unwrap().watcher();
+//! let state = watcher.changed().await;
+//! ```
+use core::ops::Deref;
+
+use battery_service_interface::DeviceId;
+use battery_service_interface::fuel_gauge::FuelGauge;
+use battery_service_interface::state_machine::{LifecycleState, Transition, TransitionListener};
+use embedded_services::sync::{Lockable, Watch};
+
+use crate::registration::Registration;
+
+/// Publishes the lifecycle state of a fuel gauge to watchers.
+pub struct LifecycleWatch(Watch<LifecycleState>);
+
+impl LifecycleWatch {
+    /// Create a new lifecycle watch, starting in [`LifecycleState::Init`] until the first transition.
+    pub const fn new() -> Self {
+        Self(Watch::new_with(LifecycleState::Init))
+    }
+}
+
+impl Default for LifecycleWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for LifecycleWatch {
+    type Target = Watch<LifecycleState>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl TransitionListener for LifecycleWatch {
+    fn on_transition(&self, transition: Transition) {
+        self.0.send(transition.to);
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Publish the lifecycle state of each fuel gauge to `watches`, in registration order.
+    ///
+    /// Each fuel gauge's transitions are sent to its watch, replacing any previous subscriber, starting from its
+    /// current state. Fuel gauges beyond the end of `watches` aren't observed.
+    pub async fn set_lifecycle_watches(&mut self, watches: &'static [LifecycleWatch]) {
+        for (fuel_gauge, watch) in self.fuel_gauges().iter().zip(watches) {
+            let mut fuel_gauge = fuel_gauge.lock().await;
+            watch.send(fuel_gauge.state().lifecycle_state());
+            fuel_gauge.state_mut().subscribe_transitions(watch);
+        }
+        self.lifecycle = watches;
+    }
+
+    /// Lifecycle state of the battery at `device_id`, watchers are woken whenever it changes.
+    ///
+    /// Returns `None` if the battery isn't observed, see [`Self::set_lifecycle_watches`].
+    pub fn lifecycle(&self, device_id: DeviceId) -> Option<&'static Watch<LifecycleState>> {
+        self.lifecycle.get(usize::from(device_id.0)).map(|watch| &watch.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use battery_service_interface::state_machine::{LifecycleEvent, StateMachine};
+
+    use super::*;
+
+    #[test]
+    fn watcher_sees_transitions() {
+        static WATCH: LifecycleWatch = LifecycleWatch::new();
+
+        let mut machine = StateMachine::default();
+        machine.subscribe(&WATCH);
+        let mut watcher = WATCH.watcher();
+        assert_eq!(WATCH.try_get(), Some(LifecycleState::Init));
+
+        assert!(machine.handle(LifecycleEvent::Detected).is_ok());
+        assert_eq!(watcher.try_changed(), Some(LifecycleState::Present));
+        assert_eq!(watcher.try_changed(), None);
+    }
+}
@@ -64,9 +64,42 @@ fn padded<const N: usize>(bytes: &[u8]) -> [u8; N] {
     arr
 }
 
+/// A single step of a [`MockFuelGauge`] profile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfileSample {
+    /// Relative state of charge in percent.
+    pub relative_soc: smart_battery::Percent,
+    /// Pack voltage in mV.
+    pub voltage_mv: smart_battery::MilliVolts,
+    /// Pack current in mA, negative while discharging.
+    pub current_ma: smart_battery::MilliAmpsSigned,
+}
+
+/// Fault that can be injected into a [`MockFuelGauge`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Bus transactions fail as if the fuel gauge NACKed them.
+    Nack,
+    /// Bus transactions succeed but the fuel gauge keeps reporting the same data.
+    StaleData,
+    /// Measurement configuration requests never complete, as if the bus hung. Other transactions fail as if NACKed.
+    Stall,
+}
+
 /// A mock fuel gauge that manages its own state and produces static, arbitrary data.
+///
+/// The reported SoC, voltage, and current can be scripted with [`MockFuelGauge::set_profile`] and
+/// faults injected with [`MockFuelGauge::inject_fault`] to exercise error handling without hardware.
 pub struct MockFuelGauge {
     state: State,
+    /// Scripted samples, one is consumed per dynamic data update
+    profile: &'static [ProfileSample],
+    /// Index of the next profile sample
+    profile_index: usize,
+    /// Currently injected fault and the number of transactions it applies to
+    fault: Option<(Fault, u32)>,
+    /// Emulated battery presence pin
+    present: bool,
 }
 
 impl MockFuelGauge {
@@ -225,7 +258,96 @@ impl MockFuelGauge {
         d.run_time_to_empty = RUN_TIME_TO_EMPTY_MIN;
         d.average_time_to_empty = AVERAGE_TIME_TO_EMPTY_MIN;
         d.average_time_to_full = u16::MAX; // over-range: not charging
-        MockFuelGauge { state }
+        MockFuelGauge {
+            state,
+            profile: &[],
+            profile_index: 0,
+            fault: None,
+            present: true,
+        }
+    }
+
+    /// Script the SoC, voltage, and current reported by the fuel gauge.
+    ///
+    /// Each dynamic data update consumes the next sample, the last sample is held once the profile is exhausted.
+    pub fn set_profile(&mut self, profile: &'static [ProfileSample]) {
+        self.profile = profile;
+        self.profile_index = 0;
+    }
+
+    /// Inject `fault` for the next `transactions` fuel gauge operations.
+    pub fn inject_fault(&mut self, fault: Fault, transactions: u32) {
+        self.fault = (transactions > 0).then_some((fault, transactions));
+    }
+
+    /// Clear any injected fault.
+    pub fn clear_fault(&mut self) {
+        self.fault = None;
+    }
+
+    /// Emulate inserting or removing the battery, bus transactions fail while it's removed.
+    pub fn set_present(&mut self, present: bool) {
+        self.present = present;
+    }
+
+    /// Start a bus transaction, consuming one transaction of the injected fault.
+    ///
+    /// Returns the fault that applies to this transaction, if any.
+    fn transaction(&mut self) -> Option<Fault> {
+        let (fault, remaining) = self.fault?;
+        self.fault = remaining.checked_sub(1).filter(|r| *r > 0).map(|r| (fault, r));
+        Some(fault)
+    }
+
+    /// Like [`Self::bus_transaction`], but never completes while a stall is injected.
+    async fn stalling_bus_transaction(&mut self) -> Result<bool, MockBatteryError> {
+        if matches!(self.fault, Some((Fault::Stall, _))) {
+            trace!("FG: injected stall");
+            core::future::pending::<()>().await;
+        }
+        self.bus_transaction()
+    }
+
+    /// Fail the transaction if a NACK is injected, returns true if the data should be left stale.
+    fn bus_transaction(&mut self) -> Result<bool, MockBatteryError> {
+        if !self.present {
+            trace!("FG: battery removed");
+            return Err(MockBatteryError);
+        }
+
+        match self.transaction() {
+            Some(Fault::Nack | Fault::Stall) => {
+                trace!("FG: injected NACK");
+                Err(MockBatteryError)
+            }
+            Some(Fault::StaleData) => {
+                trace!("FG: injected stale data");
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Apply the next profile sample to the emulated fuel gauge registers.
+    fn advance_profile(&mut self) {
+        let Some(sample) = self
+            .profile
+            .get(self.profile_index)
+            .or_else(|| self.profile.last())
+            .copied()
+        else {
+            return;
+        };
+        self.profile_index = self.profile_index.saturating_add(1);
+
+        let d = self.state.dynamic_cache_mut();
+        d.relative_soc = sample.relative_soc;
+        d.voltage = sample.voltage_mv;
+        d.current = sample.current_ma;
+        d.average_current = sample.current_ma;
+        d.battery_status = smart_battery::BatteryStatusFields::from(d.battery_status)
+            .with_discharging(sample.current_ma < 0)
+            .into();
     }
 
     async fn set_capacity_bit(&mut self, mwh: bool) -> Result<(), MockBatteryError> {
@@ -247,7 +369,8 @@ pub struct MockBatteryError;
 
 impl From<MockBatteryError> for FuelGaugeError {
     fn from(_value: MockBatteryError) -> Self {
-        FuelGaugeError::BusError
+        // Every mock error is an injected NACK
+        FuelGaugeError::NotAcknowledged
     }
 }
 
@@ -257,6 +380,9 @@ impl FuelGauge for MockFuelGauge {
     type DynamicData = DynamicBatteryMsgs;
 
     async fn initialize(&mut self) -> Result<(), Self::FuelGaugeError> {
+        self.bus_transaction()
+            .inspect_err(|_| error!("FG: failed to initialize"))?;
+
         // Milliamps
         let mwh = false;
         self.set_capacity_bit(mwh)
@@ -269,7 +395,11 @@ impl FuelGauge for MockFuelGauge {
     }
 
     async fn ping(&mut self) -> Result<(), Self::FuelGaugeError> {
-        if let Err(e) = self.charging_voltage().await {
+        let result = match self.bus_transaction() {
+            Ok(_) => self.charging_voltage().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
             error!("FG: failed to ping");
             Err(e)
         } else {
@@ -280,6 +410,10 @@ impl FuelGauge for MockFuelGauge {
     }
 
     async fn update_dynamic_data(&mut self) -> Result<(), Self::FuelGaugeError> {
+        if !self.bus_transaction()? {
+            self.advance_profile();
+        }
+
         let average_current = self.average_current().await?;
         let battery_status: u16 = self.battery_status().await?.into();
         let battery_temp = self.temperature().await?;
@@ -332,6 +466,8 @@ impl FuelGauge for MockFuelGauge {
     }
 
     async fn update_static_data(&mut self) -> Result<(), Self::FuelGaugeError> {
+        self.bus_transaction()?;
+
         let design_capacity = self.design_capacity().await?;
         let design_capacity_value: u32 = match design_capacity {
             smart_battery::CapacityModeValue::CentiWattUnsigned(v) => v.into(),
@@ -398,6 +534,20 @@ impl FuelGauge for MockFuelGauge {
         Ok(())
     }
 
+    async fn set_sampling_time(&mut self, _sampling_time_ms: u32) -> Result<bool, Self::FuelGaugeError> {
+        self.stalling_bus_transaction().await?;
+        Ok(false)
+    }
+
+    async fn set_averaging_interval(&mut self, _averaging_interval_ms: u32) -> Result<bool, Self::FuelGaugeError> {
+        self.stalling_bus_transaction().await?;
+        Ok(false)
+    }
+
+    async fn detect_presence(&mut self) -> Result<bool, FuelGaugeError> {
+        Ok(self.present)
+    }
+
     fn state(&self) -> &State {
         &self.state
     }
@@ -0,0 +1,124 @@
+//! Battery presence detection and hot-swap handling.
+//!
+//! Removable batteries are detected through [`FuelGauge::detect_presence`], which the driver implements by reading the
+//! platform's presence pin, fixed batteries are always reported present. The platform calls
+//! [`Service::update_presence`](crate::Service::update_presence) periodically or from the presence pin interrupt. When a battery is inserted the fuel gauge and its cached static (_BIX) data are re-initialized before
+//! the battery is reported again, and the host is notified of every insertion and removal so it re-evaluates _STA.
+use core::future::Future;
+use core::sync::atomic::Ordering;
+
+use battery_service_interface::fuel_gauge::{FuelGauge, FuelGaugeError};
+use battery_service_interface::{BatteryError, DeviceId};
+use embassy_time::{Duration, with_timeout};
+use embedded_services::host_notification::{Doorbell, NotificationId};
+use embedded_services::info;
+use embedded_services::sync::Lockable;
+
+use crate::recovery::device_bit;
+use crate::registration::Registration;
+
+/// Host notification rung when a battery is inserted or removed.
+#[derive(Clone, Copy)]
+pub struct PresenceNotification<'hw> {
+    /// Doorbell shared with the host interface.
+    pub doorbell: &'hw Doorbell,
+    /// Notification the host associates with battery status changes.
+    pub id: NotificationId,
+}
+
+/// Battery presence change reported by [`Service::update_presence`](crate::Service::update_presence).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PresenceChange {
+    /// A battery was inserted and its fuel gauge re-initialized.
+    Inserted,
+    /// The battery was removed.
+    Removed,
+}
+
+/// Run a fuel gauge operation, failing with [`FuelGaugeError::Timeout`] if it doesn't complete in time.
+async fn timed<T, E: Into<FuelGaugeError>>(
+    timeout: Duration,
+    operation: impl Future<Output = Result<T, E>>,
+) -> Result<T, FuelGaugeError> {
+    match with_timeout(timeout, operation).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => Err(FuelGaugeError::Timeout),
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Check whether the battery at `device_id` is present and handle insertion or removal.
+    ///
+    /// On insertion the fuel gauge is initialized and its static data re-read, the battery is only reported as
+    /// inserted once both succeed so the host never reads _BIX data left over from the previous battery. A failed
+    /// re-initialization is retried on the next call. A fuel gauge that recovers from a communication loss without
+    /// the battery having been removed isn't reported. Returns the change, if any.
+    pub async fn update_presence(&self, device_id: DeviceId) -> Result<Option<PresenceChange>, BatteryError> {
+        let timeout = self.config.request_timeout;
+        let mut fuel_gauge = with_timeout(timeout, self.fuel_gauge(device_id)?.lock())
+            .await
+            .map_err(|_| BatteryError::from(FuelGaugeError::Timeout))?;
+
+        let present = timed(timeout, fuel_gauge.detect_presence()).await?;
+        let not_present = !fuel_gauge.state().is_present();
+        let change = match (present, not_present || self.is_inserting(device_id)) {
+            (false, _) => {
+                self.set_inserting(device_id, false);
+                if not_present {
+                    None
+                } else {
+                    info!("Battery {} removed", device_id.0);
+                    fuel_gauge.state_mut().on_removed();
+                    // A removed battery is expected to stop responding
+                    self.set_degraded(device_id, false);
+                    Some(PresenceChange::Removed)
+                }
+            }
+            (true, true) => {
+                // Tracked separately from the fuel gauge state, which can't tell an initialized battery whose static
+                // data read failed from one that just recovered
+                self.set_inserting(device_id, true);
+                if not_present {
+                    info!("Battery {} inserted, initializing", device_id.0);
+                    timed(timeout, fuel_gauge.initialize()).await?;
+                }
+                timed(timeout, fuel_gauge.update_static_data()).await?;
+                self.set_inserting(device_id, false);
+                Some(PresenceChange::Inserted)
+            }
+            (true, false) => None,
+        };
+        self.set_removed(device_id, !present);
+
+        if let (Some(_), Some(notification)) = (change, self.presence_notification) {
+            notification.doorbell.ring(notification.id);
+        }
+        Ok(change)
+    }
+
+    /// Returns true if the battery was inserted but its fuel gauge hasn't been initialized yet.
+    fn is_inserting(&self, device_id: DeviceId) -> bool {
+        device_bit(device_id).is_some_and(|bit| self.inserting.load(Ordering::Relaxed) & bit != 0)
+    }
+
+    fn set_inserting(&self, device_id: DeviceId, inserting: bool) {
+        if let Some(bit) = device_bit(device_id) {
+            if inserting {
+                self.inserting.fetch_or(bit, Ordering::Relaxed);
+            } else {
+                self.inserting.fetch_and(!bit, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn set_removed(&self, device_id: DeviceId, removed: bool) {
+        if let Some(bit) = device_bit(device_id) {
+            if removed {
+                self.removed.fetch_or(bit, Ordering::Relaxed);
+            } else {
+                self.removed.fetch_and(!bit, Ordering::Relaxed);
+            }
+        }
+    }
+}
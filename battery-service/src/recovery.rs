@@ -0,0 +1,131 @@
+//! Fuel gauge request timeouts and recovery.
+//!
+//! ACPI requests lock the fuel gauge to read its cached state or reconfigure it. If a driver is stuck in a hung bus
+//! transaction, either while holding the lock or while serving the request, requests time out and the fuel gauge is
+//! marked degraded so that following requests fail fast instead of stalling. [`Service::run_recovery`](crate::Service::run_recovery)
+//! periodically probes degraded fuel gauges and clears the degraded state once they respond again.
+use core::ops::DerefMut;
+use core::sync::atomic::Ordering;
+
+use battery_service_interface::fuel_gauge::{FuelGauge, FuelGaugeError};
+use battery_service_interface::{BatteryError, DeviceId};
+use embassy_time::{Timer, with_timeout};
+use embedded_services::sync::Lockable;
+use embedded_services::{info, warn};
+
+use crate::registration::Registration;
+
+/// Bit used to track per-device state in a bitmask, `None` if the device ID can't be tracked
+pub(crate) fn device_bit(device_id: DeviceId) -> Option<u32> {
+    1u32.checked_shl(u32::from(device_id.0))
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Returns true if the fuel gauge has been marked degraded after a request timed out.
+    pub fn is_degraded(&self, device_id: DeviceId) -> bool {
+        device_bit(device_id).is_some_and(|bit| self.degraded.load(Ordering::Relaxed) & bit != 0)
+    }
+
+    pub(crate) fn set_degraded(&self, device_id: DeviceId, degraded: bool) {
+        if let Some(bit) = device_bit(device_id) {
+            if degraded {
+                self.degraded.fetch_or(bit, Ordering::Relaxed);
+            } else {
+                self.degraded.fetch_and(!bit, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Lock the fuel gauge registered at `device_id`, enforcing the configured request timeout.
+    ///
+    /// Fails immediately with [`BatteryError::Timeout`] while the fuel gauge is degraded.
+    pub(crate) async fn lock_fuel_gauge(
+        &self,
+        device_id: DeviceId,
+    ) -> Result<impl DerefMut<Target = <Reg::FuelGauge as Lockable>::Inner>, BatteryError> {
+        let fuel_gauge = self.fuel_gauge(device_id)?;
+        if self.is_degraded(device_id) {
+            return Err(FuelGaugeError::Timeout.into());
+        }
+
+        with_timeout(self.config.request_timeout, fuel_gauge.lock())
+            .await
+            .map_err(|_| {
+                warn!("Fuel gauge {} request timed out, marking degraded", device_id.0);
+                self.set_degraded(device_id, true);
+                FuelGaugeError::Timeout.into()
+            })
+    }
+
+    /// Run a request against the fuel gauge registered at `device_id`, enforcing the configured request timeout.
+    ///
+    /// Unlike [`Self::lock_fuel_gauge`], the timeout covers the whole transaction, so a request stuck in a hung bus
+    /// transaction marks the fuel gauge degraded the same way as a driver holding the lock does. Fails immediately
+    /// with [`BatteryError::Timeout`] while the fuel gauge is degraded.
+    pub(crate) async fn with_fuel_gauge<T>(
+        &self,
+        device_id: DeviceId,
+        request: impl AsyncFnOnce(&mut <Reg::FuelGauge as Lockable>::Inner) -> Result<T, BatteryError>,
+    ) -> Result<T, BatteryError> {
+        let fuel_gauge = self.fuel_gauge(device_id)?;
+        if self.is_degraded(device_id) {
+            return Err(FuelGaugeError::Timeout.into());
+        }
+
+        with_timeout(self.config.request_timeout, async {
+            request(&mut *fuel_gauge.lock().await).await
+        })
+        .await
+        .unwrap_or_else(|_| {
+            warn!("Fuel gauge {} request timed out, marking degraded", device_id.0);
+            self.set_degraded(device_id, true);
+            Err(FuelGaugeError::Timeout.into())
+        })
+    }
+
+    /// Probe a fuel gauge, clearing its degraded state if it responds.
+    ///
+    /// A fuel gauge that can be locked but doesn't respond to a ping is transitioned to
+    /// `Present(NotOperational)` so that its stale cached data is no longer reported.
+    pub async fn probe(&self, device_id: DeviceId) -> Result<(), BatteryError> {
+        let timeout = self.config.request_timeout;
+        let mut fuel_gauge = with_timeout(timeout, self.fuel_gauge(device_id)?.lock())
+            .await
+            .map_err(|_| BatteryError::from(FuelGaugeError::Timeout))?;
+
+        let result = match with_timeout(timeout, fuel_gauge.ping()).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(FuelGaugeError::Timeout),
+        };
+
+        match result {
+            Ok(()) => {
+                fuel_gauge.state_mut().on_recovered();
+                self.set_degraded(device_id, false);
+                info!("Fuel gauge {} recovered", device_id.0);
+                Ok(())
+            }
+            Err(e) => {
+                fuel_gauge.state_mut().on_error(e);
+                self.set_degraded(device_id, true);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Periodically probe degraded fuel gauges.
+    ///
+    /// Must be run in its own task for degraded fuel gauges to recover.
+    pub async fn run_recovery(&self) -> ! {
+        loop {
+            Timer::after(self.config.recovery_interval).await;
+            for id in 0..u32::BITS {
+                let device_id = DeviceId(id as u8);
+                if self.is_degraded(device_id) && self.probe(device_id).await.is_err() {
+                    warn!("Fuel gauge {} recovery probe failed", device_id.0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Battery safety fault handling.
+//!
+//! Fuel gauges report safety faults (over-temperature, over-voltage, short circuit) through
+//! [`DynamicBatteryData::safety_faults`]. [`Service::handle_safety_faults`](crate::Service::handle_safety_faults) runs
+//! first in [`Service::update_charge_control`](crate::Service::update_charge_control) after each fuel gauge update.
+//! While a fault is active charging is suspended and the charger state is marked inhibited so that power policy doesn't
+//! resume it. Every change to the active faults, including one fault kind replacing another, is sent as a
+//! [`BatteryFault`] to the power endpoint ahead of routine traffic and rings the host's battery status notification.
+//! While the fault persists, _BST reports a critical battery, which an ACPI host acts on with a critical battery
+//! shutdown, and _STA reports the battery as not functioning. Charging resumes once the fuel gauge clears the fault.
+//!
+//! None of the services act on [`BatteryFault::shutdown_requested`]. A platform that shuts down on safety faults must
+//! register its own handler on the power endpoint that receives the [`BatteryFault`] and performs the shutdown.
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use battery_service_interface::fuel_gauge::{DynamicBatteryData, FuelGauge, SafetyFaults};
+use battery_service_interface::{BatteryError, DeviceId};
+use embedded_services::comms::{self, EndpointID, Internal, Priority};
+use embedded_services::{error, info, warn};
+use power_policy_interface::charger::{ChargeInhibit, Charger};
+
+use crate::acpi::check_state;
+use crate::registration::Registration;
+
+/// Comms message sent to the power endpoint when a battery's safety faults change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatteryFault {
+    /// Battery reporting the fault.
+    pub battery_id: DeviceId,
+    /// Active faults, all clear once the fuel gauge stops reporting the fault.
+    pub faults: SafetyFaults,
+    /// Set if the platform is configured to shut down on battery safety faults, acting on it is left to the platform.
+    pub shutdown_requested: bool,
+}
+
+/// Bit used to track the fault state of a device, `None` if the device ID can't be tracked
+fn faulted_bit(device_id: DeviceId) -> Option<u32> {
+    1u32.checked_shl(u32::from(device_id.0))
+}
+
+/// Active safety faults of each battery, one bitmask per fault kind.
+#[derive(Default)]
+pub(crate) struct FaultMasks {
+    over_temperature: AtomicU32,
+    over_voltage: AtomicU32,
+    short_circuit: AtomicU32,
+}
+
+impl FaultMasks {
+    fn get(&self, bit: u32) -> SafetyFaults {
+        SafetyFaults {
+            over_temperature: self.over_temperature.load(Ordering::Relaxed) & bit != 0,
+            over_voltage: self.over_voltage.load(Ordering::Relaxed) & bit != 0,
+            short_circuit: self.short_circuit.load(Ordering::Relaxed) & bit != 0,
+        }
+    }
+
+    /// Returns the over temperature, over voltage, and short circuit masks, in that order
+    pub(crate) fn masks(&self) -> [u32; 3] {
+        [
+            self.over_temperature.load(Ordering::Relaxed),
+            self.over_voltage.load(Ordering::Relaxed),
+            self.short_circuit.load(Ordering::Relaxed),
+        ]
+    }
+
+    fn set(&self, bit: u32, faults: SafetyFaults) {
+        for (mask, active) in [
+            (&self.over_temperature, faults.over_temperature),
+            (&self.over_voltage, faults.over_voltage),
+            (&self.short_circuit, faults.short_circuit),
+        ] {
+            if active {
+                mask.fetch_or(bit, Ordering::Relaxed);
+            } else {
+                mask.fetch_and(!bit, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Returns true if the battery has an active safety fault.
+    pub fn is_faulted(&self, device_id: DeviceId) -> bool {
+        faulted_bit(device_id).is_some_and(|bit| self.faults.get(bit).any())
+    }
+
+    /// Act on the safety faults in the cached data of the given battery.
+    ///
+    /// Charging is suspended while a fault is active and resumed once it clears, unless another charge control
+    /// feature is inhibiting charge. A [`BatteryFault`] is sent on every change. Returns the active faults.
+    pub async fn handle_safety_faults<C: Charger>(
+        &self,
+        battery_id: DeviceId,
+        charger: &mut C,
+    ) -> Result<SafetyFaults, BatteryError> {
+        let faults = {
+            let fuel_gauge = self.lock_fuel_gauge(battery_id).await?;
+            check_state(fuel_gauge.state())?;
+            fuel_gauge.state().dynamic_cache().safety_faults()
+        };
+
+        let bit = faulted_bit(battery_id).ok_or(BatteryError::UnknownDeviceId)?;
+        let previous = self.faults.get(bit);
+        if faults.any() {
+            if !previous.any() {
+                error!("Battery {} safety fault {:?}, suspending charge", battery_id.0, faults);
+            } else if faults != previous {
+                warn!("Battery {} safety fault changed to {:?}", battery_id.0, faults);
+            }
+            // Re-sent on every update in case the charger restored the charge current
+            crate::inhibit_charge(charger, ChargeInhibit::FAULT)
+                .await
+                .map_err(|e| {
+                    error!("Failed to suspend charging: {:?}", e);
+                    crate::charger_error(e)
+                })?;
+        } else if previous.any() {
+            info!("Battery {} safety fault cleared", battery_id.0);
+            crate::release_charge(charger, ChargeInhibit::FAULT)
+                .await
+                .map_err(|e| {
+                    error!("Failed to resume charging: {:?}", e);
+                    crate::charger_error(e)
+                })?;
+        }
+
+        if faults == previous {
+            return Ok(faults);
+        }
+        self.faults.set(bit, faults);
+
+        let message = BatteryFault {
+            battery_id,
+            faults,
+            shutdown_requested: faults.any() && self.config.shutdown_on_safety_fault,
+        };
+        let _ = comms::send_with_priority(
+            EndpointID::Internal(Internal::Battery),
+            EndpointID::Internal(Internal::Power),
+            &message,
+            Priority::High,
+        )
+        .await;
+        // The host re-evaluates _BST and _STA, which report the fault
+        if let Some(notification) = self.presence_notification {
+            notification.doorbell.ring(notification.id);
+        }
+        Ok(faults)
+    }
+}
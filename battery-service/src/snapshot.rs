@@ -0,0 +1,48 @@
+//! Battery service state snapshot for the debug service.
+//!
+//! The service implements [`ServiceState`], register it with a
+//! [`StateProvider`](embedded_services::service_state::StateProvider) under
+//! [`id::BATTERY`](embedded_services::service_state::id::BATTERY). The [`LifecycleState`](crate::LifecycleState) of
+//! each fuel gauge follows the fixed fields, in registration order, taken from the watches set with
+//! [`Service::set_lifecycle_watches`](crate::Service::set_lifecycle_watches). Fields are little-endian:
+//!
+//! | Offset | Size | Field                                                                     |
+//! |--------|------|---------------------------------------------------------------------------|
+//! | 0      | 1    | Number of registered fuel gauges                                          |
+//! | 1      | 4    | Bitmask of degraded fuel gauges                                           |
+//! | 5      | 4    | Bitmask of inserted batteries awaiting initialization                     |
+//! | 9      | 4    | Bitmask of batteries reporting over temperature                           |
+//! | 13     | 4    | Bitmask of batteries reporting over voltage                               |
+//! | 17     | 4    | Bitmask of batteries reporting a short circuit                            |
+//! | 21     | 1    | Lifecycle state of each fuel gauge, `0xff` if it isn't observed           |
+use core::sync::atomic::Ordering;
+
+use embedded_services::service_state::{MAX_SNAPSHOT_LEN, ServiceState, SnapshotWriter};
+
+use crate::registration::Registration;
+
+impl<'hw, Reg: Registration<'hw>> ServiceState for crate::Service<'hw, Reg>
+where
+    Self: Sync,
+{
+    fn snapshot(&self, buffer: &mut [u8; MAX_SNAPSHOT_LEN]) -> usize {
+        let [over_temperature, over_voltage, short_circuit] = self.faults.masks();
+        let writer = SnapshotWriter::new(buffer)
+            .put(&[u8::try_from(self.fuel_gauges().len()).unwrap_or(u8::MAX)])
+            .put(&self.degraded.load(Ordering::Relaxed).to_le_bytes())
+            .put(&self.inserting.load(Ordering::Relaxed).to_le_bytes())
+            .put(&over_temperature.to_le_bytes())
+            .put(&over_voltage.to_le_bytes())
+            .put(&short_circuit.to_le_bytes());
+
+        (0..self.fuel_gauges().len())
+            .map(|id| {
+                self.lifecycle
+                    .get(id)
+                    .and_then(|watch| watch.try_get())
+                    .map_or(u8::MAX, |state| state as u8)
+            })
+            .fold(writer, |writer, state| writer.put(&[state]))
+            .finish()
+    }
+}
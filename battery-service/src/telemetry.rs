@@ -0,0 +1,150 @@
+//! Battery telemetry.
+//!
+//! Reports the battery discharge power to system power (PSYS) telemetry, and optionally broadcasts a periodic
+//! [`BatteryTelemetry`] comms message so that other services don't each have to poll the fuel gauges. The broadcast
+//! is enabled by setting [`Config::telemetry_interval`](crate::Config::telemetry_interval) and running
+//! [`Service::run_telemetry`](crate::Service::run_telemetry) in its own task.
+use battery_service_interface::fuel_gauge::{DynamicBatteryData, FuelGauge};
+use battery_service_interface::{BatteryError, DeviceId};
+use embassy_time::Timer;
+use embedded_batteries_async::charger::MilliVolts;
+use embedded_batteries_async::smart_battery::{DeciKelvin, MilliAmpsSigned, Percent};
+use embedded_services::comms::{self, EndpointID, Internal};
+use embedded_services::sync::Lockable;
+use embedded_services::trace;
+use power_policy_interface::telemetry::{PowerSensor, SensorError};
+
+use crate::acpi::check_state;
+use crate::registration::Registration;
+
+/// Endpoints receiving the periodic [`BatteryTelemetry`] broadcast.
+pub const TELEMETRY_ENDPOINTS: [Internal; 3] = [Internal::Power, Internal::Thermal, Internal::Debug];
+
+/// Comms message periodically broadcast with the cached measurements of a battery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatteryTelemetry {
+    /// Battery the measurements were taken from.
+    pub battery_id: DeviceId,
+    /// Battery voltage in mV.
+    pub voltage: MilliVolts,
+    /// Battery current in mA, negative while discharging.
+    pub current: MilliAmpsSigned,
+    /// Relative state of charge in %.
+    pub relative_soc: Percent,
+    /// Battery temperature in dK.
+    pub temperature: DeciKelvin,
+}
+
+/// Power drawn from the battery in mW, zero while charging.
+fn discharge_mw(current: MilliAmpsSigned, voltage: MilliVolts) -> u32 {
+    // Smart battery current is negative while discharging
+    if current >= 0 {
+        0
+    } else {
+        u32::from(current.unsigned_abs()) * u32::from(voltage) / 1000
+    }
+}
+
+/// [`PowerSensor`] reporting the discharge power of a battery from its cached fuel gauge data.
+pub struct DischargePowerSensor<'a, 'hw, Reg: Registration<'hw>> {
+    service: &'a crate::Service<'hw, Reg>,
+    battery_id: DeviceId,
+}
+
+impl<'hw, Reg: Registration<'hw>> PowerSensor for DischargePowerSensor<'_, 'hw, Reg> {
+    async fn power_mw(&mut self) -> Result<u32, SensorError> {
+        let fuel_gauge = self
+            .service
+            .lock_fuel_gauge(self.battery_id)
+            .await
+            .map_err(sensor_error)?;
+        check_state(fuel_gauge.state()).map_err(sensor_error)?;
+        let dynamic = fuel_gauge.state().dynamic_cache().standard();
+        Ok(discharge_mw(dynamic.current, dynamic.voltage))
+    }
+}
+
+fn sensor_error(error: BatteryError) -> SensorError {
+    match error {
+        BatteryError::Timeout { .. } => SensorError::Timeout,
+        BatteryError::BusError { .. } => SensorError::BusError,
+        _ => SensorError::Unavailable,
+    }
+}
+
+impl<'hw, Reg: Registration<'hw>> crate::Service<'hw, Reg> {
+    /// Returns a power sensor reporting the discharge power of the given battery.
+    pub fn discharge_power_sensor(&self, battery_id: DeviceId) -> DischargePowerSensor<'_, 'hw, Reg> {
+        DischargePowerSensor {
+            service: self,
+            battery_id,
+        }
+    }
+
+    /// Returns the cached measurements of the given battery.
+    pub async fn telemetry(&self, battery_id: DeviceId) -> Result<BatteryTelemetry, BatteryError> {
+        let fuel_gauge = self.lock_fuel_gauge(battery_id).await?;
+        check_state(fuel_gauge.state())?;
+        let dynamic = fuel_gauge.state().dynamic_cache().standard();
+        Ok(BatteryTelemetry {
+            battery_id,
+            voltage: dynamic.voltage,
+            current: dynamic.current,
+            relative_soc: dynamic.relative_soc,
+            temperature: dynamic.battery_temp,
+        })
+    }
+
+    /// Broadcast the cached measurements of every operational battery to the [`TELEMETRY_ENDPOINTS`].
+    pub async fn publish_telemetry(&self) {
+        for id in 0..self.fuel_gauges().len() {
+            let Ok(id) = u8::try_from(id) else {
+                break;
+            };
+
+            let telemetry = match self.telemetry(DeviceId(id)).await {
+                Ok(telemetry) => telemetry,
+                Err(e) => {
+                    trace!("Skipping telemetry for battery {}: {:?}", id, e);
+                    continue;
+                }
+            };
+
+            for endpoint in TELEMETRY_ENDPOINTS {
+                let _ = comms::send(
+                    EndpointID::Internal(Internal::Battery),
+                    EndpointID::Internal(endpoint),
+                    &telemetry,
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Periodically broadcast battery telemetry at the configured interval.
+    ///
+    /// Must be run in its own task. Never returns, and does nothing if telemetry is disabled.
+    pub async fn run_telemetry(&self) -> ! {
+        let Some(interval) = self.config.telemetry_interval else {
+            core::future::pending().await
+        };
+
+        loop {
+            Timer::after(interval).await;
+            self.publish_telemetry().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::discharge_mw;
+
+    #[test]
+    fn discharge_power_ignores_charging() {
+        assert_eq!(discharge_mw(-2000, 12000), 24000);
+        assert_eq!(discharge_mw(1500, 12000), 0);
+        assert_eq!(discharge_mw(0, 12000), 0);
+    }
+}
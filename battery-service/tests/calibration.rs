@@ -0,0 +1,98 @@
+#![allow(clippy::unwrap_used)]
+use battery_service::mock::{MockFuelGauge, init_state_machine};
+use battery_service::{
+    ArrayRegistration, CalibrationConfig, CalibrationPhase, Calibrator, ChargeControl, Config, DeviceId, Service,
+};
+use battery_service_interface::fuel_gauge::FuelGauge;
+use embassy_sync::channel::Channel;
+use embassy_sync::mutex::Mutex;
+use embedded_batteries_async::acpi::BmcControlFlags;
+use embedded_services::GlobalRawMutex;
+use power_policy_interface::capability::{ConsumerPowerCapability, PowerCapability};
+use power_policy_interface::charger::{ChargeInhibit, Charger, EventData};
+use power_policy_interface_test_mocks::charger::{FnCall, Mock};
+
+type FuelGaugeType = Mutex<GlobalRawMutex, MockFuelGauge>;
+
+const CAPABILITY: PowerCapability = PowerCapability {
+    voltage_mv: 20000,
+    current_ma: 3000,
+};
+
+/// `_BMC` calibrate flag
+const BMC_CALIBRATE: u32 = 1 << 0;
+/// `BatteryStatus` OVER_TEMP_ALARM bit
+const OVER_TEMP_ALARM: u16 = 1 << 12;
+
+async fn set_battery_status(fuel_gauge: &FuelGaugeType, battery_status: u16) {
+    fuel_gauge.lock().await.state_mut().dynamic_cache_mut().battery_status = battery_status;
+}
+
+/// A safety fault aborts a learn cycle, but charging stays suspended until the fault itself clears.
+#[tokio::test]
+async fn fault_during_calibration_keeps_charge_suspended() {
+    let _time = odp_test_support::time::real_time();
+    embedded_services::init().await;
+
+    let fuel_gauge: FuelGaugeType = Mutex::new(MockFuelGauge::new());
+    init_state_machine(&fuel_gauge).await.unwrap();
+    let calibrator = Calibrator::new(CalibrationConfig::default());
+    let service = Service::new_with_charge_control(
+        ArrayRegistration {
+            fuel_gauges: [&fuel_gauge],
+        },
+        Config::default(),
+        ChargeControl {
+            calibrator: Some(&calibrator),
+            ..Default::default()
+        },
+    );
+
+    let channel: Channel<GlobalRawMutex, EventData, 1> = Channel::new();
+    let mut charger = Mock::new(channel.dyn_sender());
+    let capability = ConsumerPowerCapability::from(CAPABILITY);
+    charger.state_mut().on_policy_attach(capability);
+    let battery = DeviceId(0);
+
+    // Start the cycle, charge is disabled and the battery discharged
+    calibrator
+        .request(battery, BmcControlFlags::from_bits_truncate(BMC_CALIBRATE))
+        .unwrap();
+    charger.next_result_charging_current.push_back(Ok(0));
+    charger.next_result_set_learn_mode.push_back(Ok(()));
+    service.update_calibration(battery, &mut charger).await.unwrap();
+    assert_eq!(calibrator.phase(), CalibrationPhase::Discharging);
+    assert_eq!(charger.state().charge_inhibit(), ChargeInhibit::CALIBRATION);
+
+    // Faulted mid-cycle, the cycle is aborted but charge must not be restored
+    set_battery_status(&fuel_gauge, OVER_TEMP_ALARM).await;
+    charger.next_result_charging_current.push_back(Ok(0));
+    service.update_charge_control(battery, &mut charger).await.unwrap();
+    charger.next_result_set_learn_mode.push_back(Ok(()));
+    service.update_calibration(battery, &mut charger).await.unwrap();
+    assert_eq!(calibrator.phase(), CalibrationPhase::Idle);
+    assert_eq!(charger.state().charge_inhibit(), ChargeInhibit::FAULT);
+
+    // Still faulted, the charger stays detached
+    charger.next_result_charging_current.push_back(Ok(0));
+    service.update_charge_control(battery, &mut charger).await.unwrap();
+    service.update_calibration(battery, &mut charger).await.unwrap();
+
+    // Cleared, charging resumes
+    set_battery_status(&fuel_gauge, 0).await;
+    charger.next_result_attach_handler.push_back(Ok(()));
+    service.update_charge_control(battery, &mut charger).await.unwrap();
+    assert!(!charger.state().is_charge_inhibited());
+
+    assert_eq!(
+        charger.fn_calls,
+        [
+            FnCall::ChargingCurrent(0),
+            FnCall::SetLearnMode(Some(2000)),
+            FnCall::ChargingCurrent(0),
+            FnCall::SetLearnMode(None),
+            FnCall::ChargingCurrent(0),
+            FnCall::AttachHandler(capability),
+        ]
+    );
+}
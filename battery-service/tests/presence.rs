@@ -0,0 +1,139 @@
+#![allow(clippy::unwrap_used)]
+use battery_service::mock::{Fault, MockFuelGauge, init_state_machine};
+use battery_service::{
+    ArrayRegistration, ChargeControl, Config, DeviceId, LifecycleState, LifecycleWatch, PresenceChange,
+    PresenceNotification, Service,
+};
+use battery_service_interface::BatteryService;
+use battery_service_interface::fuel_gauge::{FuelGauge, InternalState, OperationalSubstate, PresentSubstate};
+use embassy_sync::mutex::Mutex;
+use embassy_time::Duration;
+use embedded_services::GlobalRawMutex;
+use embedded_services::host_notification::{Doorbell, NotificationId};
+use embedded_services::service_state::{MAX_SNAPSHOT_LEN, ServiceState};
+
+type FuelGaugeType = Mutex<GlobalRawMutex, MockFuelGauge>;
+
+const POLLING: InternalState = InternalState::Present(PresentSubstate::Operational(OperationalSubstate::Polling));
+
+/// Batteries are reported as they're removed and inserted, a fuel gauge recovering from a communication loss isn't.
+///
+/// The battery mask leaves out a battery while it's absent or its fuel gauge isn't initialized yet.
+#[tokio::test]
+async fn insert_remove_recover() {
+    let _time = odp_test_support::time::real_time();
+
+    let fuel_gauge: FuelGaugeType = Mutex::new(MockFuelGauge::new());
+    init_state_machine(&fuel_gauge).await.unwrap();
+    let doorbell = Doorbell::new(Duration::from_ticks(0));
+    let service = Service::new_with_presence_notification(
+        ArrayRegistration {
+            fuel_gauges: [&fuel_gauge],
+        },
+        Config::default(),
+        ChargeControl::default(),
+        Some(PresenceNotification {
+            doorbell: &doorbell,
+            id: NotificationId::new(1).unwrap(),
+        }),
+    );
+    let battery = DeviceId(0);
+
+    assert_eq!(service.update_presence(battery).await.unwrap(), None);
+    assert_eq!(service.battery_mask(), 1);
+
+    // Communication lost and recovered, the battery was never removed
+    {
+        let mut fuel_gauge = fuel_gauge.lock().await;
+        fuel_gauge.state_mut().on_timeout();
+        fuel_gauge.ping().await.unwrap();
+        assert_eq!(
+            fuel_gauge.state().internal_state(),
+            InternalState::Present(PresentSubstate::Operational(OperationalSubstate::Init))
+        );
+    }
+    assert_eq!(service.update_presence(battery).await.unwrap(), None);
+    assert!(doorbell.pending().is_empty());
+
+    // Removed
+    fuel_gauge.lock().await.set_present(false);
+    assert_eq!(
+        service.update_presence(battery).await.unwrap(),
+        Some(PresenceChange::Removed)
+    );
+    assert_eq!(
+        fuel_gauge.lock().await.state().internal_state(),
+        InternalState::NotPresent
+    );
+    assert!(!doorbell.pending().is_empty());
+    assert_eq!(service.battery_mask(), 0);
+    assert_eq!(service.update_presence(battery).await.unwrap(), None);
+    // _BST is still answered without a battery
+    assert!(BatteryService::battery_status(&service, battery).await.is_ok());
+
+    // Inserted, but the fuel gauge doesn't respond yet so the insertion is retried on the next call
+    {
+        let mut fuel_gauge = fuel_gauge.lock().await;
+        fuel_gauge.set_present(true);
+        fuel_gauge.inject_fault(Fault::Nack, 1);
+    }
+    assert!(service.update_presence(battery).await.is_err());
+    assert_eq!(service.battery_mask(), 0);
+    assert_eq!(
+        service.update_presence(battery).await.unwrap(),
+        Some(PresenceChange::Inserted)
+    );
+    assert_eq!(service.battery_mask(), 1);
+    assert_eq!(fuel_gauge.lock().await.state().internal_state(), POLLING);
+    assert_eq!(service.update_presence(battery).await.unwrap(), None);
+}
+
+/// The state snapshot reports batteries awaiting initialization, and the lifecycle state of observed fuel gauges.
+#[tokio::test]
+async fn state_snapshot() {
+    static LIFECYCLE: [LifecycleWatch; 1] = [LifecycleWatch::new()];
+    let _time = odp_test_support::time::real_time();
+
+    let fuel_gauge: FuelGaugeType = Mutex::new(MockFuelGauge::new());
+    init_state_machine(&fuel_gauge).await.unwrap();
+    let mut service = Service::new(ArrayRegistration {
+        fuel_gauges: [&fuel_gauge],
+    });
+    let battery = DeviceId(0);
+    let mut buffer = [0; MAX_SNAPSHOT_LEN];
+
+    // Fuel gauges aren't observed until their lifecycle watches are set
+    assert_eq!(service.snapshot(&mut buffer), 22);
+    assert_eq!(buffer.first(), Some(&1));
+    assert_eq!(buffer.get(1..21).unwrap(), &[0; 20]);
+    assert_eq!(buffer.get(21), Some(&u8::MAX));
+    assert!(service.lifecycle(battery).is_none());
+
+    service.set_lifecycle_watches(&LIFECYCLE).await;
+    let lifecycle_state = fuel_gauge.lock().await.state().lifecycle_state();
+    service.snapshot(&mut buffer);
+    assert_eq!(buffer.get(21), Some(&(lifecycle_state as u8)));
+    let mut watcher = service.lifecycle(battery).unwrap().watcher();
+    assert!(service.lifecycle(DeviceId(1)).is_none());
+
+    fuel_gauge.lock().await.set_present(false);
+    service.update_presence(battery).await.unwrap();
+    assert_eq!(watcher.try_changed(), Some(LifecycleState::NotPresent));
+
+    // The lifecycle state is still reported while the fuel gauge is in use
+    {
+        let _locked = fuel_gauge.lock().await;
+        service.snapshot(&mut buffer);
+        assert_eq!(buffer.get(21), Some(&(LifecycleState::NotPresent as u8)));
+    }
+
+    // Inserted, but the fuel gauge doesn't respond yet
+    {
+        let mut fuel_gauge = fuel_gauge.lock().await;
+        fuel_gauge.set_present(true);
+        fuel_gauge.inject_fault(Fault::Nack, 1);
+    }
+    assert!(service.update_presence(battery).await.is_err());
+    service.snapshot(&mut buffer);
+    assert_eq!(buffer.get(5..9).unwrap(), &1u32.to_le_bytes());
+}
@@ -0,0 +1,141 @@
+#![allow(clippy::unwrap_used)]
+use battery_service::mock::{MockFuelGauge, init_state_machine};
+use battery_service::{ArrayRegistration, BatteryFault, ChargeControl, Config, DeviceId, Service};
+use battery_service_interface::fuel_gauge::{FuelGauge, SafetyFaults};
+use embassy_sync::channel::Channel;
+use embassy_sync::mutex::Mutex;
+use embedded_services::GlobalRawMutex;
+use embedded_services::comms::{self, EndpointID, Internal, MailboxDelegate, MailboxDelegateError, Message};
+use power_policy_interface::capability::{ConsumerPowerCapability, PowerCapability};
+use power_policy_interface::charger::{Charger, EventData};
+use power_policy_interface_test_mocks::charger::{FnCall, Mock};
+
+type FuelGaugeType = Mutex<GlobalRawMutex, MockFuelGauge>;
+
+const CAPABILITY: PowerCapability = PowerCapability {
+    voltage_mv: 20000,
+    current_ma: 3000,
+};
+
+/// `BatteryStatus` OVER_TEMP_ALARM bit
+const OVER_TEMP_ALARM: u16 = 1 << 12;
+/// `BatteryStatus` OVER_CHARGED_ALARM bit
+const OVER_CHARGED_ALARM: u16 = 1 << 15;
+
+/// Stand-in for the power manager, records the battery faults it receives
+struct PowerManager {
+    tp: comms::Endpoint,
+    faults: Channel<GlobalRawMutex, BatteryFault, 4>,
+}
+
+impl MailboxDelegate for PowerManager {
+    fn receive(&self, message: &Message) -> Result<(), MailboxDelegateError> {
+        let fault = message
+            .data
+            .get::<BatteryFault>()
+            .ok_or(MailboxDelegateError::MessageNotFound)?;
+        self.faults
+            .try_send(*fault)
+            .map_err(|_| MailboxDelegateError::BufferFull)
+    }
+}
+
+static POWER_MANAGER: PowerManager = PowerManager {
+    tp: comms::Endpoint::uninit(EndpointID::Internal(Internal::Power)),
+    faults: Channel::new(),
+};
+
+async fn set_battery_status(fuel_gauge: &FuelGaugeType, battery_status: u16) {
+    fuel_gauge.lock().await.state_mut().dynamic_cache_mut().battery_status = battery_status;
+}
+
+/// Charging stays suspended while faulted, even if the power policy re-attaches the charger, every change of the
+/// active faults is broadcast and charging resumes once the fault clears.
+#[tokio::test]
+async fn fault_suspends_charge_until_cleared() {
+    let _time = odp_test_support::time::real_time();
+    embedded_services::init().await;
+    comms::register_endpoint(&POWER_MANAGER, &POWER_MANAGER.tp)
+        .await
+        .unwrap();
+
+    let fuel_gauge: FuelGaugeType = Mutex::new(MockFuelGauge::new());
+    init_state_machine(&fuel_gauge).await.unwrap();
+    let service = Service::new_with_charge_control(
+        ArrayRegistration {
+            fuel_gauges: [&fuel_gauge],
+        },
+        Config {
+            shutdown_on_safety_fault: true,
+            ..Default::default()
+        },
+        ChargeControl::default(),
+    );
+
+    let channel: Channel<GlobalRawMutex, EventData, 1> = Channel::new();
+    let mut charger = Mock::new(channel.dyn_sender());
+    let capability = ConsumerPowerCapability::from(CAPABILITY);
+    charger.state_mut().on_policy_attach(capability);
+    let battery = DeviceId(0);
+
+    set_battery_status(&fuel_gauge, OVER_TEMP_ALARM).await;
+    charger.next_result_charging_current.push_back(Ok(0));
+    service.update_charge_control(battery, &mut charger).await.unwrap();
+    assert!(service.is_faulted(battery));
+    let over_temperature = SafetyFaults {
+        over_temperature: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        POWER_MANAGER.faults.try_receive().unwrap(),
+        BatteryFault {
+            battery_id: battery,
+            faults: over_temperature,
+            shutdown_requested: true,
+        }
+    );
+
+    // The power policy re-attaches, charging must be suspended again without a new broadcast
+    charger.next_result_attach_handler.push_back(Ok(()));
+    charger.attach_handler(capability).await.unwrap();
+    charger.next_result_charging_current.push_back(Ok(0));
+    service.update_charge_control(battery, &mut charger).await.unwrap();
+    assert!(POWER_MANAGER.faults.try_receive().is_err());
+
+    // Switching fault kinds while faulted is broadcast
+    set_battery_status(&fuel_gauge, OVER_CHARGED_ALARM).await;
+    charger.next_result_charging_current.push_back(Ok(0));
+    service.update_charge_control(battery, &mut charger).await.unwrap();
+    assert_eq!(
+        POWER_MANAGER.faults.try_receive().unwrap().faults,
+        SafetyFaults {
+            over_voltage: true,
+            ..Default::default()
+        }
+    );
+
+    // Cleared, charging resumes
+    set_battery_status(&fuel_gauge, 0).await;
+    charger.next_result_attach_handler.push_back(Ok(()));
+    service.update_charge_control(battery, &mut charger).await.unwrap();
+    assert!(!service.is_faulted(battery));
+    assert_eq!(
+        POWER_MANAGER.faults.try_receive().unwrap(),
+        BatteryFault {
+            battery_id: battery,
+            faults: SafetyFaults::default(),
+            shutdown_requested: false,
+        }
+    );
+
+    assert_eq!(
+        charger.fn_calls,
+        [
+            FnCall::ChargingCurrent(0),
+            FnCall::AttachHandler(capability),
+            FnCall::ChargingCurrent(0),
+            FnCall::ChargingCurrent(0),
+            FnCall::AttachHandler(capability),
+        ]
+    );
+}
@@ -0,0 +1,77 @@
+#![allow(clippy::unwrap_used)]
+use battery_service::mock::{MockFuelGauge, init_state_machine};
+use battery_service::telemetry::BatteryTelemetry;
+use battery_service::{ArrayRegistration, Config, DeviceId, Service};
+use embassy_sync::channel::Channel;
+use embassy_sync::mutex::Mutex;
+use embassy_time::Duration;
+use embedded_services::GlobalRawMutex;
+use embedded_services::comms::{self, EndpointID, Internal, MailboxDelegate, MailboxDelegateError, Message};
+use odp_test_support::task::run_until;
+
+type FuelGaugeType = Mutex<GlobalRawMutex, MockFuelGauge>;
+
+const TELEMETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Stand-in for the thermal service, records the battery telemetry it receives
+struct ThermalListener {
+    tp: comms::Endpoint,
+    telemetry: Channel<GlobalRawMutex, (EndpointID, BatteryTelemetry), 4>,
+}
+
+impl MailboxDelegate for ThermalListener {
+    fn receive(&self, message: &Message) -> Result<(), MailboxDelegateError> {
+        let telemetry = message
+            .data
+            .get::<BatteryTelemetry>()
+            .ok_or(MailboxDelegateError::MessageNotFound)?;
+        self.telemetry
+            .try_send((message.from, *telemetry))
+            .map_err(|_| MailboxDelegateError::BufferFull)
+    }
+}
+
+static THERMAL_LISTENER: ThermalListener = ThermalListener {
+    tp: comms::Endpoint::uninit(EndpointID::Internal(Internal::Thermal)),
+    telemetry: Channel::new(),
+};
+
+/// The periodic broadcast reaches a registered endpoint once per interval with the cached battery measurements.
+#[tokio::test]
+async fn telemetry_reaches_endpoints() {
+    let time = odp_test_support::time::pause();
+    embedded_services::init().await;
+    comms::register_endpoint(&THERMAL_LISTENER, &THERMAL_LISTENER.tp)
+        .await
+        .unwrap();
+
+    let fuel_gauge: FuelGaugeType = Mutex::new(MockFuelGauge::new());
+    init_state_machine(&fuel_gauge).await.unwrap();
+    let service = Service::new_with_config(
+        ArrayRegistration {
+            fuel_gauges: [&fuel_gauge],
+        },
+        Config {
+            telemetry_interval: Some(TELEMETRY_INTERVAL),
+            ..Default::default()
+        },
+    );
+    let expected = service.telemetry(DeviceId(0)).await.unwrap();
+
+    run_until(service.run_telemetry(), async {
+        // Nothing is sent before the first interval elapses
+        time.advance(TELEMETRY_INTERVAL - Duration::from_secs(1)).await;
+        assert!(THERMAL_LISTENER.telemetry.try_receive().is_err());
+
+        time.advance(Duration::from_secs(1)).await;
+        assert_eq!(
+            THERMAL_LISTENER.telemetry.try_receive().unwrap(),
+            (EndpointID::Internal(Internal::Battery), expected)
+        );
+        assert!(THERMAL_LISTENER.telemetry.try_receive().is_err());
+
+        time.advance(TELEMETRY_INTERVAL).await;
+        assert_eq!(THERMAL_LISTENER.telemetry.try_receive().unwrap().1, expected);
+    })
+    .await;
+}
@@ -0,0 +1,56 @@
+#![allow(clippy::unwrap_used)]
+use battery_service::mock::{Fault, MockFuelGauge, init_state_machine};
+use battery_service::{ArrayRegistration, Config, DeviceId, Service};
+use battery_service_interface::fuel_gauge::FuelGaugeError;
+use battery_service_interface::{BatteryError, BatteryService, Bms, MeasurementStatus};
+use embassy_sync::mutex::Mutex;
+use embassy_time::Duration;
+use embedded_services::GlobalRawMutex;
+
+type FuelGaugeType = Mutex<GlobalRawMutex, MockFuelGauge>;
+
+const TIMEOUT: BatteryError = BatteryError::Timeout {
+    error_code: FuelGaugeError::Timeout.code(),
+};
+const BMS: Bms = Bms { sampling_time_ms: 500 };
+
+/// A request stuck in a hung bus transaction times out and marks the fuel gauge degraded until it's recovered.
+#[tokio::test]
+async fn hung_request_times_out() {
+    let _time = odp_test_support::time::real_time();
+
+    let fuel_gauge: FuelGaugeType = Mutex::new(MockFuelGauge::new());
+    init_state_machine(&fuel_gauge).await.unwrap();
+    let service = Service::new_with_config(
+        ArrayRegistration {
+            fuel_gauges: [&fuel_gauge],
+        },
+        Config {
+            request_timeout: Duration::from_millis(50),
+            ..Default::default()
+        },
+    );
+    let battery = DeviceId(0);
+
+    // The lock is free, only the request itself hangs
+    fuel_gauge.lock().await.inject_fault(Fault::Stall, 1);
+    assert_eq!(
+        BatteryService::set_battery_measurement_sampling_time(&service, battery, BMS).await,
+        Err(TIMEOUT)
+    );
+    assert!(service.is_degraded(battery));
+
+    // Degraded fuel gauges fail fast, even for requests that only read the cache
+    assert!(matches!(
+        BatteryService::battery_status(&service, battery).await,
+        Err(BatteryError::Timeout { .. })
+    ));
+
+    fuel_gauge.lock().await.clear_fault();
+    service.probe(battery).await.unwrap();
+    assert!(!service.is_degraded(battery));
+    assert_eq!(
+        BatteryService::set_battery_measurement_sampling_time(&service, battery, BMS).await,
+        Ok(MeasurementStatus::Success)
+    );
+}
@@ -6,10 +6,11 @@ use crate::{
         state::{FwUpdateState, SharedState},
     },
     component::{InternalResponseData, RequestData},
-    customization::Customization,
+    customization::{Customization, ImageVerificationError, OFFER_VALIDATION_HOOK},
 };
 use embedded_cfu_protocol::protocol_definitions::*;
-use embedded_services::{debug, error, sync::Lockable};
+use embedded_services::sync::{Lockable, cancel_safe};
+use embedded_services::{debug, error};
 use fw_update_interface::basic::FwUpdate;
 
 pub mod config;
@@ -36,6 +37,8 @@ pub struct Updater<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner
     customization: Cust,
     shared_state: &'a Shared,
     config: Config,
+    /// Number of bytes written for the update in progress
+    image_len: usize,
 }
 
 impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState>, Cust: Customization>
@@ -55,6 +58,7 @@ impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState
             component_id,
             customization,
             config,
+            image_len: 0,
         }
     }
 
@@ -141,7 +145,12 @@ impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState
             }
         };
 
-        InternalResponseData::OfferResponse(self.customization.validate(FwVersion::new(version), offer))
+        let current = FwVersion::new(version);
+        let response = match OFFER_VALIDATION_HOOK.call((current, *offer)).await {
+            Some(response) => response,
+            None => self.customization.validate(current, offer).await,
+        };
+        InternalResponseData::OfferResponse(response)
     }
 
     /// Process an AbortUpdate command
@@ -162,7 +171,22 @@ impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState
     }
 
     /// Process a GiveContent command
+    ///
+    /// DROP SAFETY: Dropping the future part way through may leave the device mid-update, so the updater enters
+    /// recovery and the update is aborted on the next recovery tick.
     pub async fn process_give_content(&mut self, content: &FwUpdateContentCommand) -> InternalResponseData {
+        let shared_state = self.shared_state;
+        cancel_safe(self.give_content(content), || {
+            error!("FW update content cancelled, entering recovery");
+            match shared_state.try_lock() {
+                Some(mut state) => state.enter_recovery(),
+                None => error!("Failed to lock shared state to enter recovery"),
+            }
+        })
+        .await
+    }
+
+    async fn give_content(&mut self, content: &FwUpdateContentCommand) -> InternalResponseData {
         let data = if let Some(data) = content.data.get(0..content.header.data_length as usize) {
             data
         } else {
@@ -195,6 +219,7 @@ impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState
                 .lock()
                 .await
                 .enter_in_progress(self.config.recovery.tick_interval);
+            self.image_len = 0;
         }
 
         let result = self
@@ -206,6 +231,8 @@ impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState
         match result {
             Ok(_) => {
                 debug!("Block written successfully");
+                let end = (content.header.firmware_address as usize).saturating_add(data.len());
+                self.image_len = self.image_len.max(end);
             }
             Err(e) => {
                 error!("Failed to write block: {:?}", e);
@@ -217,6 +244,19 @@ impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState
         }
 
         if content.header.flags & FW_UPDATE_FLAG_LAST_BLOCK != 0 {
+            if let Err(e) = self.customization.verify_image(self.component_id, self.image_len).await {
+                error!("FW image verification failed: {:?}", e);
+                self.abort_rejected_image().await;
+                let status = match e {
+                    ImageVerificationError::InvalidSignature => CfuUpdateContentResponseStatus::ErrorSignature,
+                    ImageVerificationError::ReadFailed => CfuUpdateContentResponseStatus::ErrorVerify,
+                };
+                return InternalResponseData::ContentResponse(FwUpdateContentResponse::new(
+                    content.header.sequence_num,
+                    status,
+                ));
+            }
+
             let result = self.device.lock().await.finalize_fw_update().await;
             match result {
                 Ok(_) => {
@@ -240,6 +280,18 @@ impl<'a, Device: Lockable<Inner: FwUpdate>, Shared: Lockable<Inner = SharedState
         ))
     }
 
+    /// Abort an update whose image failed verification so it's never activated
+    async fn abort_rejected_image(&mut self) {
+        let result = self.device.lock().await.abort_fw_update().await;
+        match result {
+            Ok(_) => self.shared_state.lock().await.enter_idle(),
+            Err(e) => {
+                error!("Failed to abort FW update: {:?}", e);
+                self.shared_state.lock().await.enter_recovery();
+            }
+        }
+    }
+
     /// Process a CFU recovery tick.
     pub async fn process_recovery_tick(&mut self) {
         // Update timed out, attempt to abort
@@ -9,6 +9,7 @@ use crate::{
         state::{FwUpdateState, SharedState},
     },
     component::{InternalResponseData, RequestData},
+    customization::ImageVerificationError,
 };
 use embassy_sync::{mutex::Mutex, once_lock::OnceLock};
 use embassy_time::{Duration, with_timeout};
@@ -216,10 +217,66 @@ impl Test for TestBasicFlow {
                 device.lock().await.fn_calls.pop_front().unwrap(),
                 FwFnCall::FinalizeFwUpdate
             );
+            assert_eq!(
+                cfu_basic.customization_mut().fn_calls.pop_front(),
+                Some(CustomizationFnCall::VerifyImage(
+                    DEVICE0_COMPONENT_ID,
+                    DEFAULT_DATA_LENGTH
+                ))
+            );
         }
     }
 }
 
+/// Test that an image failing verification is aborted instead of finalized.
+struct TestRejectedImage;
+
+impl Test for TestRejectedImage {
+    async fn run<'a>(&mut self, device: &'a DeviceType, cfu_basic: &'a mut UpdaterType<'a>) {
+        cfu_basic.customization_mut().verify_result = Err(ImageVerificationError::InvalidSignature);
+
+        let output = with_timeout(
+            PER_CALL_TIMEOUT,
+            cfu_basic.process_event(Event::Request(RequestData::GiveContent(FwUpdateContentCommand {
+                header: FwUpdateContentHeader {
+                    flags: FW_UPDATE_FLAG_FIRST_BLOCK | FW_UPDATE_FLAG_LAST_BLOCK,
+                    data_length: DEFAULT_DATA_LENGTH as u8,
+                    sequence_num: 0,
+                    firmware_address: 0x0,
+                },
+                data: [1; DEFAULT_DATA_LENGTH],
+            }))),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            output,
+            Output::CfuResponse(InternalResponseData::ContentResponse(FwUpdateContentResponse::new(
+                0,
+                CfuUpdateContentResponseStatus::ErrorSignature
+            )))
+        );
+        assert_eq!(cfu_basic.update_state().await, FwUpdateState::Idle);
+        assert_eq!(
+            cfu_basic.customization_mut().fn_calls.pop_front(),
+            Some(CustomizationFnCall::VerifyImage(
+                DEVICE0_COMPONENT_ID,
+                DEFAULT_DATA_LENGTH
+            ))
+        );
+
+        let mut device = device.lock().await;
+        assert_eq!(device.fn_calls.pop_front().unwrap(), FwFnCall::StartFwUpdate);
+        assert_eq!(
+            device.fn_calls.pop_front().unwrap(),
+            FwFnCall::WriteFwContents(0, vec![1; DEFAULT_DATA_LENGTH])
+        );
+        assert_eq!(device.fn_calls.pop_front().unwrap(), FwFnCall::AbortFwUpdate);
+        assert!(device.fn_calls.is_empty());
+    }
+}
+
 /// Test that the recovery flow works immediately after sending the first content block.
 struct TestStartRecoveryFlow;
 
@@ -323,6 +380,54 @@ impl Test for TestStartRecoveryFlow {
     }
 }
 
+/// Test that cancelling a content command part way through puts the updater into recovery.
+struct TestCancelledContent;
+
+impl Test for TestCancelledContent {
+    async fn run<'a>(&mut self, device: &'a DeviceType, cfu_basic: &'a mut UpdaterType<'a>) {
+        {
+            // Hold the device so the first block can't complete, then cancel it
+            let _device = device.lock().await;
+            let result = with_timeout(
+                Duration::from_millis(10),
+                cfu_basic.process_event(Event::Request(RequestData::GiveContent(FwUpdateContentCommand {
+                    header: FwUpdateContentHeader {
+                        flags: FW_UPDATE_FLAG_FIRST_BLOCK,
+                        data_length: DEFAULT_DATA_LENGTH as u8,
+                        sequence_num: 0,
+                        firmware_address: 0x0,
+                    },
+                    data: [1; DEFAULT_DATA_LENGTH],
+                }))),
+            )
+            .await;
+            assert!(result.is_err());
+        }
+        assert_eq!(cfu_basic.update_state().await, FwUpdateState::Recovery);
+
+        {
+            // Commands are rejected until the update is aborted
+            let output = with_timeout(
+                PER_CALL_TIMEOUT,
+                cfu_basic.process_event(Event::Request(RequestData::FwVersionRequest)),
+            )
+            .await
+            .unwrap();
+            assert_eq!(output, Output::CfuResponse(InternalResponseData::ComponentBusy));
+
+            let output = with_timeout(PER_CALL_TIMEOUT, cfu_basic.process_event(Event::RecoveryTick))
+                .await
+                .unwrap();
+            assert_eq!(output, Output::CfuRecovery);
+            assert_eq!(cfu_basic.update_state().await, FwUpdateState::Idle);
+            assert_eq!(
+                device.lock().await.fn_calls.pop_front().unwrap(),
+                FwFnCall::AbortFwUpdate
+            );
+        }
+    }
+}
+
 #[tokio::test]
 async fn run_test_basic_flow() {
     run_test(DEFAULT_TIMEOUT, TestBasicFlow).await;
@@ -333,6 +438,16 @@ async fn run_test_start_recovery_flow() {
     run_test(DEFAULT_TIMEOUT, TestStartRecoveryFlow).await;
 }
 
+#[tokio::test]
+async fn run_test_cancelled_content() {
+    run_test(DEFAULT_TIMEOUT, TestCancelledContent).await;
+}
+
+#[tokio::test]
+async fn run_test_rejected_image() {
+    run_test(DEFAULT_TIMEOUT, TestRejectedImage).await;
+}
+
 /// Trait for runnable tests.
 ///
 /// This exists because there are lifetime issues with being generic over FnOnce or FnMut.
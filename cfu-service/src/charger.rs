@@ -0,0 +1,458 @@
+//! CFU support for chargers registered with the power policy service.
+//!
+//! [`ChargerFwUpdate`] wraps a charger driver that also implements [`FwUpdate`] so the same device can be
+//! registered with both the power policy service and a [`crate::basic::Updater`]. Charge control from the
+//! power policy is paused while an update is in progress and the latest policy decision is replayed once the
+//! update completes or is aborted.
+use embedded_batteries_async::charger::{MilliAmps, MilliVolts};
+use embedded_cfu_protocol::protocol_definitions::{
+    ComponentId, FwUpdateOffer, FwUpdateOfferResponse, FwVersion, HostToken, OfferRejectReason, OfferStatus,
+};
+use embedded_services::hook::{ExtensionPoint, HookSlot};
+use embedded_services::named::Named;
+use embedded_services::sync::Lockable;
+use embedded_services::{debug, error, info};
+use fw_update_interface::basic::{Error as FwError, FwUpdate};
+use power_policy_interface::capability::{ConsumerPowerCapability, PsuType};
+use power_policy_interface::charger::{Charger, ChargerError, PsuState, State};
+
+use crate::basic::config::Updater as UpdaterConfig;
+use crate::basic::state::SharedState;
+use crate::customization::Customization;
+
+/// OEM extension point for charger firmware offer validation
+pub struct ChargerOfferValidation;
+
+impl ExtensionPoint for ChargerOfferValidation {
+    const NAME: &'static str = "cfu::ChargerOfferValidation";
+    type Input = (FwVersion, FwUpdateOffer);
+    type Output = FwUpdateOfferResponse;
+}
+
+/// Hook slot for [`ChargerOfferValidation`]
+pub static CHARGER_OFFER_VALIDATION_HOOK: HookSlot<ChargerOfferValidation> = HookSlot::new();
+
+/// Charger firmware offer validator
+///
+/// Defers to [`CHARGER_OFFER_VALIDATION_HOOK`]. Offers are rejected if no hook is registered since charger
+/// firmware compatibility can only be determined by the platform.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FwOfferValidator;
+
+impl Customization for FwOfferValidator {
+    async fn validate(&mut self, current: FwVersion, offer: &FwUpdateOffer) -> FwUpdateOfferResponse {
+        CHARGER_OFFER_VALIDATION_HOOK
+            .call_or_else((current, *offer), || {
+                error!("No charger offer validation hook registered, rejecting offer");
+                FwUpdateOfferResponse::new_with_failure(
+                    HostToken::Driver,
+                    OfferRejectReason::InvalidComponent,
+                    OfferStatus::Reject,
+                )
+            })
+            .await
+    }
+}
+
+/// CFU updater for a charger
+pub type Updater<'a, Device, Shared> = crate::basic::Updater<'a, Device, Shared, FwOfferValidator>;
+
+/// Create a CFU updater for a charger wrapped in [`ChargerFwUpdate`]
+///
+/// The same device should also be registered with the power policy service as a charger.
+pub fn new_updater<
+    'a,
+    C: Charger + FwUpdate,
+    Device: Lockable<Inner = ChargerFwUpdate<C>>,
+    Shared: Lockable<Inner = SharedState>,
+>(
+    device: &'a Device,
+    shared_state: &'a Shared,
+    config: UpdaterConfig,
+    component_id: ComponentId,
+) -> Updater<'a, Device, Shared> {
+    crate::basic::Updater::new(device, shared_state, config, component_id, FwOfferValidator)
+}
+
+/// Map a charger error to the equivalent FW update error
+fn fw_error(error: ChargerError) -> FwError {
+    match error {
+        ChargerError::Timeout => FwError::Timeout,
+        ChargerError::BusError => FwError::Bus,
+        _ => FwError::Failed,
+    }
+}
+
+/// Charger wrapper that pauses charge control while a firmware update is in progress
+pub struct ChargerFwUpdate<C: Charger + FwUpdate> {
+    /// Charger driver
+    inner: C,
+    /// True while a firmware update is in progress
+    update_in_progress: bool,
+    /// Latest capability from the power policy, applied once the update completes
+    capability: Option<ConsumerPowerCapability>,
+    /// Input selected by the power policy during the update, applied once the update completes
+    deferred_input: Option<PsuType>,
+}
+
+impl<C: Charger + FwUpdate> ChargerFwUpdate<C> {
+    /// Create a new instance
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            update_in_progress: false,
+            capability: None,
+            deferred_input: None,
+        }
+    }
+
+    /// Returns true if a firmware update is in progress
+    pub fn is_update_in_progress(&self) -> bool {
+        self.update_in_progress
+    }
+
+    /// Gives immutable access to the charger driver
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Gives mutable access to the charger driver
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Resume charge control by replaying the latest policy decision
+    async fn resume_charge_control(&mut self) -> Result<(), FwError> {
+        self.update_in_progress = false;
+        if self.inner.state().is_unpowered() {
+            // Power policy will re-initialize the charger on the next attach
+            return Ok(());
+        }
+
+        info!("{}: Resuming charge control", self.inner.name());
+        if let Some(psu_type) = self.deferred_input.take() {
+            // The input is routed before the capability is applied, as when the power policy switches consumers
+            self.inner
+                .select_input(psu_type)
+                .await
+                .map_err(|e| fw_error(e.into()))?;
+        }
+        match self.capability {
+            Some(capability) if self.inner.state().is_charge_inhibited() => {
+                // Battery charge control attaches the cached capability once it clears the inhibit
+                info!("{}: Charging inhibited, caching deferred capability", self.inner.name());
+                self.inner.state_mut().on_policy_attach(capability);
+                Ok(())
+            }
+            Some(capability) => self.inner.attach_handler(capability).await,
+            None => self.inner.detach_handler().await,
+        }
+        .map_err(|e| fw_error(e.into()))
+    }
+}
+
+impl<C: Charger + FwUpdate> Named for ChargerFwUpdate<C> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+impl<C: Charger + FwUpdate> FwUpdate for ChargerFwUpdate<C> {
+    async fn get_active_fw_version(&mut self) -> Result<u32, FwError> {
+        self.inner.get_active_fw_version().await
+    }
+
+    async fn start_fw_update(&mut self) -> Result<(), FwError> {
+        info!("{}: Pausing charge control for FW update", self.inner.name());
+        self.update_in_progress = true;
+        let result = self.inner.start_fw_update().await;
+        if result.is_err() {
+            self.resume_charge_control().await?;
+        }
+        result
+    }
+
+    async fn abort_fw_update(&mut self) -> Result<(), FwError> {
+        let result = self.inner.abort_fw_update().await;
+        self.resume_charge_control().await?;
+        result
+    }
+
+    async fn finalize_fw_update(&mut self) -> Result<(), FwError> {
+        let result = self.inner.finalize_fw_update().await;
+        self.resume_charge_control().await?;
+        result
+    }
+
+    async fn write_fw_contents(&mut self, offset: usize, data: &[u8]) -> Result<(), FwError> {
+        self.inner.write_fw_contents(offset, data).await
+    }
+}
+
+impl<C: Charger + FwUpdate> Charger for ChargerFwUpdate<C> {
+    type ChargerError = C::ChargerError;
+
+    async fn init_charger(&mut self) -> Result<PsuState, Self::ChargerError> {
+        self.inner.init_charger().await
+    }
+
+    async fn attach_handler(&mut self, capability: ConsumerPowerCapability) -> Result<(), Self::ChargerError> {
+        self.capability = Some(capability);
+        if self.update_in_progress {
+            debug!("{}: FW update in progress, deferring attach", self.inner.name());
+            return Ok(());
+        }
+        self.inner.attach_handler(capability).await
+    }
+
+    async fn detach_handler(&mut self) -> Result<(), Self::ChargerError> {
+        self.capability = None;
+        if self.update_in_progress {
+            debug!("{}: FW update in progress, deferring detach", self.inner.name());
+            return Ok(());
+        }
+        self.inner.detach_handler().await
+    }
+
+    async fn select_input(&mut self, psu_type: PsuType) -> Result<(), Self::ChargerError> {
+        if self.update_in_progress {
+            debug!(
+                "{}: FW update in progress, deferring input selection",
+                self.inner.name()
+            );
+            self.deferred_input = Some(psu_type);
+            return Ok(());
+        }
+        self.inner.select_input(psu_type).await
+    }
+
+    async fn is_ready(&mut self) -> Result<(), Self::ChargerError> {
+        self.inner.is_ready().await
+    }
+
+    fn state(&self) -> &State {
+        self.inner.state()
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        self.inner.state_mut()
+    }
+}
+
+impl<C: Charger + FwUpdate> embedded_batteries_async::charger::ErrorType for ChargerFwUpdate<C> {
+    type Error = C::Error;
+}
+
+impl<C: Charger + FwUpdate> embedded_batteries_async::charger::Charger for ChargerFwUpdate<C> {
+    async fn charging_current(&mut self, current: MilliAmps) -> Result<MilliAmps, Self::Error> {
+        self.inner.charging_current(current).await
+    }
+
+    async fn charging_voltage(&mut self, voltage: MilliVolts) -> Result<MilliVolts, Self::Error> {
+        self.inner.charging_voltage(voltage).await
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use fw_update_interface_mocks::basic::{FnCall as FwFnCall, Mock as FwMock};
+    use power_policy_interface::capability::PowerCapability;
+    use power_policy_interface::charger::ChargeInhibit;
+    use power_policy_interface::charger::mock::NoopCharger;
+    use std::vec::Vec;
+
+    /// Charger that records calls to its attach, detach, and input selection handlers
+    struct MockCharger {
+        charger: NoopCharger,
+        fw: FwMock,
+        /// Capability for each attach, `None` for each detach
+        policy_calls: Vec<Option<ConsumerPowerCapability>>,
+        /// Each selected input
+        inputs: Vec<PsuType>,
+    }
+
+    impl MockCharger {
+        fn new() -> Self {
+            let mut charger = NoopCharger::new();
+            charger.state_mut().on_ready_success();
+            Self {
+                charger,
+                fw: FwMock::new("charger", 0),
+                policy_calls: Vec::new(),
+                inputs: Vec::new(),
+            }
+        }
+    }
+
+    impl Named for MockCharger {
+        fn name(&self) -> &'static str {
+            self.fw.name()
+        }
+    }
+
+    impl FwUpdate for MockCharger {
+        async fn get_active_fw_version(&mut self) -> Result<u32, FwError> {
+            self.fw.get_active_fw_version().await
+        }
+
+        async fn start_fw_update(&mut self) -> Result<(), FwError> {
+            self.fw.start_fw_update().await
+        }
+
+        async fn abort_fw_update(&mut self) -> Result<(), FwError> {
+            self.fw.abort_fw_update().await
+        }
+
+        async fn finalize_fw_update(&mut self) -> Result<(), FwError> {
+            self.fw.finalize_fw_update().await
+        }
+
+        async fn write_fw_contents(&mut self, offset: usize, data: &[u8]) -> Result<(), FwError> {
+            self.fw.write_fw_contents(offset, data).await
+        }
+    }
+
+    impl Charger for MockCharger {
+        type ChargerError = core::convert::Infallible;
+
+        async fn init_charger(&mut self) -> Result<PsuState, Self::ChargerError> {
+            self.charger.init_charger().await
+        }
+
+        async fn attach_handler(&mut self, capability: ConsumerPowerCapability) -> Result<(), Self::ChargerError> {
+            self.policy_calls.push(Some(capability));
+            Ok(())
+        }
+
+        async fn detach_handler(&mut self) -> Result<(), Self::ChargerError> {
+            self.policy_calls.push(None);
+            Ok(())
+        }
+
+        async fn select_input(&mut self, psu_type: PsuType) -> Result<(), Self::ChargerError> {
+            self.inputs.push(psu_type);
+            Ok(())
+        }
+
+        fn state(&self) -> &State {
+            self.charger.state()
+        }
+
+        fn state_mut(&mut self) -> &mut State {
+            self.charger.state_mut()
+        }
+    }
+
+    impl embedded_batteries_async::charger::ErrorType for MockCharger {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_batteries_async::charger::Charger for MockCharger {
+        async fn charging_current(&mut self, current: MilliAmps) -> Result<MilliAmps, Self::Error> {
+            Ok(current)
+        }
+
+        async fn charging_voltage(&mut self, voltage: MilliVolts) -> Result<MilliVolts, Self::Error> {
+            Ok(voltage)
+        }
+    }
+
+    fn capability(current_ma: u16) -> ConsumerPowerCapability {
+        PowerCapability {
+            voltage_mv: 5000,
+            current_ma,
+        }
+        .into()
+    }
+
+    /// Test that policy decisions made during an update are deferred and replayed once it completes
+    #[tokio::test]
+    async fn test_charge_control_paused() {
+        let mut charger = ChargerFwUpdate::new(MockCharger::new());
+        charger.attach_handler(capability(1000)).await.unwrap();
+
+        charger.start_fw_update().await.unwrap();
+        assert!(charger.is_update_in_progress());
+        charger.detach_handler().await.unwrap();
+        charger.attach_handler(capability(3000)).await.unwrap();
+        assert_eq!(charger.inner().policy_calls, [Some(capability(1000))]);
+
+        charger.finalize_fw_update().await.unwrap();
+        assert!(!charger.is_update_in_progress());
+        assert_eq!(
+            charger.inner().policy_calls,
+            [Some(capability(1000)), Some(capability(3000))]
+        );
+        assert_eq!(
+            charger.inner_mut().fw.fn_calls.drain(..).collect::<Vec<_>>(),
+            [FwFnCall::StartFwUpdate, FwFnCall::FinalizeFwUpdate]
+        );
+    }
+
+    /// Test that input selection is forwarded, and deferred while an update is in progress
+    #[tokio::test]
+    async fn test_select_input() {
+        let mut charger = ChargerFwUpdate::new(MockCharger::new());
+        charger.select_input(PsuType::TypeC).await.unwrap();
+        assert_eq!(charger.inner().inputs, [PsuType::TypeC]);
+
+        charger.start_fw_update().await.unwrap();
+        charger.select_input(PsuType::DcJack).await.unwrap();
+        charger.attach_handler(capability(3000)).await.unwrap();
+        assert_eq!(charger.inner().inputs, [PsuType::TypeC]);
+        assert!(charger.inner().policy_calls.is_empty());
+
+        // Only the latest input is applied, before the capability
+        charger.finalize_fw_update().await.unwrap();
+        assert_eq!(charger.inner().inputs, [PsuType::TypeC, PsuType::DcJack]);
+        assert_eq!(charger.inner().policy_calls, [Some(capability(3000))]);
+
+        // Resuming again doesn't repeat the input selection
+        charger.start_fw_update().await.unwrap();
+        charger.abort_fw_update().await.unwrap();
+        assert_eq!(charger.inner().inputs, [PsuType::TypeC, PsuType::DcJack]);
+    }
+
+    /// Test that a deferred attach isn't replayed while battery charge control inhibits charging
+    #[tokio::test]
+    async fn test_charge_control_resumed_while_inhibited() {
+        let mut charger = ChargerFwUpdate::new(MockCharger::new());
+        charger.start_fw_update().await.unwrap();
+        charger.attach_handler(capability(3000)).await.unwrap();
+        charger.state_mut().set_charge_inhibit(ChargeInhibit::FAULT, true);
+
+        charger.finalize_fw_update().await.unwrap();
+        assert!(!charger.is_update_in_progress());
+        assert!(charger.inner().policy_calls.is_empty());
+        assert_eq!(*charger.state().capability(), Some(capability(3000)));
+    }
+
+    /// Test that charge control resumes after an aborted update
+    #[tokio::test]
+    async fn test_charge_control_resumed_on_abort() {
+        let mut charger = ChargerFwUpdate::new(MockCharger::new());
+        charger.start_fw_update().await.unwrap();
+        charger.abort_fw_update().await.unwrap();
+
+        assert!(!charger.is_update_in_progress());
+        assert_eq!(charger.inner().policy_calls, [None]);
+    }
+
+    /// Test that offers are rejected without a registered validation hook
+    #[tokio::test]
+    async fn test_offer_rejected_without_hook() {
+        let offer = FwUpdateOffer::new(HostToken::Driver, 0, FwVersion::new(1), 0, 0);
+        assert_eq!(
+            FwOfferValidator.validate(FwVersion::new(0), &offer).await,
+            FwUpdateOfferResponse::new_with_failure(
+                HostToken::Driver,
+                OfferRejectReason::InvalidComponent,
+                OfferStatus::Reject
+            )
+        );
+    }
+}
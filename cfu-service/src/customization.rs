@@ -1,8 +1,48 @@
 //! Common CFU customization trait
-use embedded_cfu_protocol::protocol_definitions::{FwUpdateOffer, FwUpdateOfferResponse, FwVersion};
+use embedded_cfu_protocol::protocol_definitions::{ComponentId, FwUpdateOffer, FwUpdateOfferResponse, FwVersion};
+use embedded_services::hook::{ExtensionPoint, HookSlot};
 
 /// Common CFU customization trait
 pub trait Customization {
     /// Determine if we are accepting the firmware update offer, returns a CFU offer response
-    fn validate(&mut self, current: FwVersion, offer: &FwUpdateOffer) -> FwUpdateOfferResponse;
+    fn validate(&mut self, current: FwVersion, offer: &FwUpdateOffer) -> impl Future<Output = FwUpdateOfferResponse>;
+
+    /// Verify the authenticity of a fully received image before the update is finalized
+    ///
+    /// Called once the last content block has been written, `image_len` is the number of bytes written to the
+    /// component's update partition. Platforms that require signed firmware should check the hash or signature of
+    /// the image in the partition here, an error aborts the update so the image is never activated. Defaults to
+    /// accepting every image.
+    fn verify_image(
+        &mut self,
+        _component_id: ComponentId,
+        _image_len: usize,
+    ) -> impl Future<Output = Result<(), ImageVerificationError>> {
+        async { Ok(()) }
+    }
+}
+
+/// Image verification failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ImageVerificationError {
+    /// The image hash or signature doesn't match
+    InvalidSignature,
+    /// The image couldn't be read back from the update partition
+    ReadFailed,
 }
+
+/// OEM extension point for offer validation
+///
+/// A registered hook decides the offer response in place of [`Customization::validate`], which is only consulted if
+/// no hook is registered.
+pub struct OfferValidation;
+
+impl ExtensionPoint for OfferValidation {
+    const NAME: &'static str = "cfu::OfferValidation";
+    type Input = (FwVersion, FwUpdateOffer);
+    type Output = FwUpdateOfferResponse;
+}
+
+/// Hook slot for [`OfferValidation`]
+pub static OFFER_VALIDATION_HOOK: HookSlot<OfferValidation> = HookSlot::new();
@@ -0,0 +1,225 @@
+//! Component update ordering
+//!
+//! Some components must be updated before others, e.g. a retimer before the PD controller behind it.
+//! [`UpdateOrder`] tracks offers and completed updates within an update session and rejects offers that would
+//! update a component out of order. It is shared between the [`Splitter`](crate::splitter::Splitter)s serving the
+//! components involved. A session starts when the host sends the start entire transaction offer information, see
+//! [`is_session_start`].
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_cfu_protocol::protocol_definitions::{
+    ComponentId, FwUpdateOfferInformation, FwUpdateOfferResponse, HostToken, OfferInformationCodeValues,
+    OfferInformationComponentInfo, OfferRejectReason, OfferStatus, SpecialComponentIds,
+};
+use embedded_services::{GlobalRawMutex, info};
+
+/// Declares that `component` may only be updated after `after`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Dependency {
+    /// Dependent component
+    pub component: ComponentId,
+    /// Component that must be updated first
+    pub after: ComponentId,
+}
+
+/// Returns true if the offer information starts a new update session
+pub fn is_session_start(info: &FwUpdateOfferInformation) -> bool {
+    let start = FwUpdateOfferInformation::new(OfferInformationComponentInfo::new(
+        HostToken::Driver,
+        SpecialComponentIds::Info,
+        OfferInformationCodeValues::StartEntireTransaction,
+    ));
+    let start: [u8; 16] = (&start).into();
+    let info: [u8; 16] = info.into();
+    // The first byte holds the information code, the others the host token that sent it
+    info.first() == start.first()
+}
+
+/// Set of component IDs
+#[derive(Clone, Copy, Default)]
+struct ComponentSet([u32; 8]);
+
+impl ComponentSet {
+    fn insert(&mut self, component: ComponentId) {
+        if let Some(word) = self.0.get_mut(usize::from(component) / 32) {
+            *word |= 1 << (component % 32);
+        }
+    }
+
+    fn contains(&self, component: ComponentId) -> bool {
+        self.0
+            .get(usize::from(component) / 32)
+            .is_some_and(|word| word & (1 << (component % 32)) != 0)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Session {
+    /// Components whose offer was accepted in this session
+    accepted: ComponentSet,
+    /// Components whose update completed in this session
+    updated: ComponentSet,
+}
+
+/// Enforces component update dependencies within an update session
+pub struct UpdateOrder<'a> {
+    dependencies: &'a [Dependency],
+    reject_reason: OfferRejectReason,
+    session: Mutex<GlobalRawMutex, Cell<Session>>,
+}
+
+impl<'a> UpdateOrder<'a> {
+    /// Create a new instance
+    ///
+    /// Offers that violate the ordering are rejected with `reject_reason`, typically a vendor-specific reason that
+    /// the host tooling recognizes.
+    pub const fn new(dependencies: &'a [Dependency], reject_reason: OfferRejectReason) -> Self {
+        Self {
+            dependencies,
+            reject_reason,
+            session: Mutex::new(Cell::new(Session {
+                accepted: ComponentSet([0; 8]),
+                updated: ComponentSet([0; 8]),
+            })),
+        }
+    }
+
+    /// Start a new update session, forgetting all offers and updates from the previous one
+    pub fn start_session(&self) {
+        info!("Starting new CFU update session");
+        self.session.lock(|session| session.set(Session::default()));
+    }
+
+    /// Check whether an offer for `component` may be accepted, returns the rejection response if not
+    ///
+    /// An offer is rejected while a component it depends on has been accepted but not finished updating, or if a
+    /// component that depends on it has already been accepted in this session.
+    pub fn check_offer(&self, component: ComponentId) -> Option<FwUpdateOfferResponse> {
+        let session = self.session.lock(|session| session.get());
+        let prerequisite_pending = self.dependencies.iter().any(|dependency| {
+            dependency.component == component
+                && session.accepted.contains(dependency.after)
+                && !session.updated.contains(dependency.after)
+        });
+        let dependent_ahead = self
+            .dependencies
+            .iter()
+            .any(|dependency| dependency.after == component && session.accepted.contains(dependency.component));
+
+        if prerequisite_pending || dependent_ahead {
+            info!("Rejecting out of order offer for component {}", component);
+            Some(FwUpdateOfferResponse::new_with_failure(
+                HostToken::Driver,
+                self.reject_reason,
+                OfferStatus::Reject,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Record that an offer for `component` was accepted
+    pub fn on_offer_accepted(&self, component: ComponentId) {
+        self.session.lock(|session| {
+            let mut state = session.get();
+            state.accepted.insert(component);
+            session.set(state);
+        });
+    }
+
+    /// Record that the update of `component` completed
+    pub fn on_update_complete(&self, component: ComponentId) {
+        self.session.lock(|session| {
+            let mut state = session.get();
+            state.updated.insert(component);
+            session.set(state);
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RETIMER: ComponentId = 0x10;
+    const PD: ComponentId = 0x20;
+    const DEPENDENCIES: [Dependency; 1] = [Dependency {
+        component: PD,
+        after: RETIMER,
+    }];
+
+    #[test]
+    fn dependent_waits_for_prerequisite() {
+        let order = UpdateOrder::new(&DEPENDENCIES, OfferRejectReason::InvalidComponent);
+
+        assert!(order.check_offer(RETIMER).is_none());
+        order.on_offer_accepted(RETIMER);
+        // Retimer update still in progress
+        assert!(order.check_offer(PD).is_some());
+
+        order.on_update_complete(RETIMER);
+        assert!(order.check_offer(PD).is_none());
+    }
+
+    #[test]
+    fn prerequisite_rejected_after_dependent() {
+        let order = UpdateOrder::new(&DEPENDENCIES, OfferRejectReason::InvalidComponent);
+
+        // Retimer not being updated this session, so the PD controller can go ahead
+        assert!(order.check_offer(PD).is_none());
+        order.on_offer_accepted(PD);
+        assert_eq!(
+            order.check_offer(RETIMER),
+            Some(FwUpdateOfferResponse::new_with_failure(
+                HostToken::Driver,
+                OfferRejectReason::InvalidComponent,
+                OfferStatus::Reject,
+            ))
+        );
+
+        order.start_session();
+        assert!(order.check_offer(RETIMER).is_none());
+    }
+
+    fn offer_information(code: OfferInformationCodeValues) -> FwUpdateOfferInformation {
+        FwUpdateOfferInformation::new(OfferInformationComponentInfo::new(
+            HostToken::Driver,
+            SpecialComponentIds::Info,
+            code,
+        ))
+    }
+
+    #[test]
+    fn session_start_detected() {
+        assert!(is_session_start(&offer_information(
+            OfferInformationCodeValues::StartEntireTransaction
+        )));
+        assert!(!is_session_start(&offer_information(
+            OfferInformationCodeValues::StartOfferList
+        )));
+        assert!(!is_session_start(&offer_information(
+            OfferInformationCodeValues::EndOfferList
+        )));
+    }
+
+    #[test]
+    fn second_session_starts_clean() {
+        let order = UpdateOrder::new(&DEPENDENCIES, OfferRejectReason::InvalidComponent);
+
+        // First session updates both in order
+        order.start_session();
+        order.on_offer_accepted(RETIMER);
+        order.on_update_complete(RETIMER);
+        order.on_offer_accepted(PD);
+        order.on_update_complete(PD);
+        assert!(order.check_offer(RETIMER).is_some());
+
+        // A new session may offer the retimer again, and the PD controller must wait for it again
+        order.start_session();
+        assert!(order.check_offer(RETIMER).is_none());
+        order.on_offer_accepted(RETIMER);
+        assert!(order.check_offer(PD).is_some());
+    }
+}
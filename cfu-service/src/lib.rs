@@ -1,5 +1,9 @@
 #![no_std]
 
+// Host tests use the embassy-time driver from the test support crate
+#[cfg(test)]
+use odp_test_support as _;
+
 use embassy_sync::channel::Channel;
 use embedded_cfu_protocol::client::CfuReceiveContent;
 use embedded_cfu_protocol::components::CfuComponentTraits;
@@ -8,8 +12,10 @@ use embedded_services::{GlobalRawMutex, comms, error, info, intrusive_list, trac
 
 pub mod basic;
 pub mod buffer;
+pub mod charger;
 pub mod component;
 pub mod customization;
+pub mod dependency;
 pub mod host;
 mod responses;
 pub mod splitter;
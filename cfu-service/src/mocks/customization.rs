@@ -3,14 +3,15 @@ extern crate std;
 
 use std::collections::VecDeque;
 
-use crate::customization::Customization;
+use crate::customization::{Customization, ImageVerificationError};
 use embedded_cfu_protocol::protocol_definitions::{
-    FwUpdateOffer, FwUpdateOfferResponse, FwVersion, HostToken, OfferRejectReason, OfferStatus,
+    ComponentId, FwUpdateOffer, FwUpdateOfferResponse, FwVersion, HostToken, OfferRejectReason, OfferStatus,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FnCall {
     Validate(FwVersion, FwUpdateOffer),
+    VerifyImage(ComponentId, usize),
 }
 
 /// Simple mock
@@ -19,6 +20,8 @@ pub struct Mock {
     pub fn_calls: VecDeque<FnCall>,
     /// Acceptable version
     acceptable_version: FwVersion,
+    /// Result returned by [`Customization::verify_image`]
+    pub verify_result: Result<(), ImageVerificationError>,
 }
 
 impl Mock {
@@ -27,6 +30,7 @@ impl Mock {
         Self {
             fn_calls: VecDeque::new(),
             acceptable_version,
+            verify_result: Ok(()),
         }
     }
 
@@ -36,7 +40,7 @@ impl Mock {
 }
 
 impl Customization for Mock {
-    fn validate(&mut self, current: FwVersion, fw_update_offer: &FwUpdateOffer) -> FwUpdateOfferResponse {
+    async fn validate(&mut self, current: FwVersion, fw_update_offer: &FwUpdateOffer) -> FwUpdateOfferResponse {
         self.record_fn_call(FnCall::Validate(current, *fw_update_offer));
         if fw_update_offer.firmware_version == self.acceptable_version {
             FwUpdateOfferResponse::new_accept(HostToken::Driver)
@@ -44,18 +48,27 @@ impl Customization for Mock {
             FwUpdateOfferResponse::new_with_failure(HostToken::Driver, OfferRejectReason::OldFw, OfferStatus::Reject)
         }
     }
+
+    async fn verify_image(
+        &mut self,
+        component_id: ComponentId,
+        image_len: usize,
+    ) -> Result<(), ImageVerificationError> {
+        self.record_fn_call(FnCall::VerifyImage(component_id, image_len));
+        self.verify_result
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    #[test]
-    fn test_validate_accept() {
+    #[tokio::test]
+    async fn test_validate_accept() {
         let acceptable_version = FwVersion::new(1);
         let mut mock = Mock::new(acceptable_version);
         let fw_update_offer = FwUpdateOffer::new(HostToken::Driver, 0, FwVersion::new(1), 0, 0);
-        let response = mock.validate(acceptable_version, &fw_update_offer);
+        let response = mock.validate(acceptable_version, &fw_update_offer).await;
         assert_eq!(response, FwUpdateOfferResponse::new_accept(HostToken::Driver));
         assert_eq!(mock.fn_calls.len(), 1);
         assert_eq!(
@@ -64,12 +77,12 @@ mod test {
         );
     }
 
-    #[test]
-    fn test_validate_reject() {
+    #[tokio::test]
+    async fn test_validate_reject() {
         let acceptable_version = FwVersion::new(1);
         let mut mock = Mock::new(acceptable_version);
         let fw_update_offer = FwUpdateOffer::new(HostToken::Driver, 0, FwVersion::new(9), 0, 0);
-        let response = mock.validate(acceptable_version, &fw_update_offer);
+        let response = mock.validate(acceptable_version, &fw_update_offer).await;
         assert_eq!(
             response,
             FwUpdateOfferResponse::new_with_failure(HostToken::Driver, OfferRejectReason::OldFw, OfferStatus::Reject)
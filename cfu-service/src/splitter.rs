@@ -4,6 +4,7 @@
 use core::{future::Future, iter::zip};
 
 use crate::component;
+use crate::dependency::{self, UpdateOrder};
 use embassy_futures::join::{join, join3, join4};
 use embedded_cfu_protocol::protocol_definitions::*;
 use embedded_services::{error, intrusive_list, trace};
@@ -28,6 +29,8 @@ pub struct Splitter<'a, C: Customization> {
     devices: &'a [ComponentId],
     /// Customization for the Splitter
     customization: C,
+    /// Update ordering shared with other components, if any
+    update_order: Option<&'a UpdateOrder<'a>>,
 }
 
 /// Maximum number of devices supported
@@ -36,6 +39,16 @@ pub const MAX_SUPPORTED_DEVICES: usize = 4;
 impl<'a, C: Customization> Splitter<'a, C> {
     /// Create a new Splitter, returns None if the devices slice is empty or too large
     pub fn new(component_id: ComponentId, devices: &'a [ComponentId], customization: C) -> Option<Self> {
+        Self::new_with_update_order(component_id, devices, customization, None)
+    }
+
+    /// Create a new Splitter that enforces the given update ordering, returns None if the devices slice is empty or too large
+    pub fn new_with_update_order(
+        component_id: ComponentId,
+        devices: &'a [ComponentId],
+        customization: C,
+        update_order: Option<&'a UpdateOrder<'a>>,
+    ) -> Option<Self> {
         if devices.is_empty() || devices.len() > MAX_SUPPORTED_DEVICES {
             None
         } else {
@@ -43,6 +56,7 @@ impl<'a, C: Customization> Splitter<'a, C> {
                 cfu_device: component::CfuDevice::new(component_id),
                 devices,
                 customization,
+                update_order,
             })
         }
     }
@@ -85,6 +99,11 @@ impl<'a, C: Customization> Splitter<'a, C> {
         offer: &FwUpdateOffer,
         cfu_client: &crate::CfuClient,
     ) -> component::InternalResponseData {
+        let component_id = self.cfu_device.component_id();
+        if let Some(rejection) = self.update_order.and_then(|order| order.check_offer(component_id)) {
+            return component::InternalResponseData::OfferResponse(rejection);
+        }
+
         let mut offer_responses = [FwUpdateOfferResponse::default(); MAX_SUPPORTED_DEVICES];
 
         let success = map_slice_join(self.devices, &mut offer_responses, |device_id| async move {
@@ -106,9 +125,13 @@ impl<'a, C: Customization> Splitter<'a, C> {
         .await;
 
         if success && let Some(offer_responses_slice) = offer_responses.get(..self.devices.len()) {
-            component::InternalResponseData::OfferResponse(
-                self.customization.resolve_offer_response(offer_responses_slice),
-            )
+            let response = self.customization.resolve_offer_response(offer_responses_slice);
+            if response.status == OfferStatus::Accept
+                && let Some(order) = self.update_order
+            {
+                order.on_offer_accepted(component_id);
+            }
+            component::InternalResponseData::OfferResponse(response)
         } else {
             crate::responses::create_invalid_fw_version_response(self.cfu_device.component_id())
         }
@@ -137,9 +160,18 @@ impl<'a, C: Customization> Splitter<'a, C> {
         .await;
 
         if success && let Some(content_responses_slice) = content_responses.get(..self.devices.len()) {
-            component::InternalResponseData::ContentResponse(
-                self.customization.resolve_content_response(content_responses_slice),
-            )
+            let response = self.customization.resolve_content_response(content_responses_slice);
+            if content.header.flags & FW_UPDATE_FLAG_LAST_BLOCK != 0
+                && response
+                    == FwUpdateContentResponse::new(
+                        content.header.sequence_num,
+                        CfuUpdateContentResponseStatus::Success,
+                    )
+                && let Some(order) = self.update_order
+            {
+                order.on_update_complete(self.cfu_device.component_id());
+            }
+            component::InternalResponseData::ContentResponse(response)
         } else {
             crate::responses::create_content_rejection(content.header.sequence_num)
         }
@@ -190,9 +222,18 @@ impl<'a, C: Customization> Splitter<'a, C> {
                     OfferStatus::Reject,
                 ))
             }
-            component::RequestData::GiveOfferInformation(_) => {
+            component::RequestData::GiveOfferInformation(info) => {
                 trace!("Got GiveOfferInformation");
-                // Offer information is not currently supported
+                if let Some(order) = self.update_order
+                    && dependency::is_session_start(&info)
+                {
+                    order.start_session();
+                    return component::InternalResponseData::OfferResponse(FwUpdateOfferResponse::new_accept(
+                        HostToken::Driver,
+                    ));
+                }
+
+                // Other offer information is not currently supported
                 component::InternalResponseData::OfferResponse(FwUpdateOfferResponse::new_with_failure(
                     HostToken::Driver,
                     OfferRejectReason::InvalidComponent,
@@ -1,9 +1,34 @@
 #![no_std]
+use embedded_services::relay::host_error::{ErrorClass, HostError};
 use embedded_services::relay::{MessageSerializationError, SerializableMessage};
 
 /// Standard Debug Service Log Buffer Size
 pub const STD_DEBUG_BUF_SIZE: usize = 128;
 
+/// Maximum size of a serialized service state snapshot
+pub const STD_SERVICE_STATE_SIZE: usize = embedded_services::service_state::MAX_SNAPSHOT_LEN;
+
+/// Maximum length of the module path in a log level request
+pub const MAX_LOG_MODULE_LEN: usize = embedded_services::fmt::filter::MAX_MODULE_LEN;
+
+/// Maximum length of a metric name, longer names are truncated
+pub const MAX_METRIC_NAME_LEN: usize = 24;
+
+/// Maximum length of a console command line
+pub const MAX_CMDLINE_LEN: usize = 64;
+
+/// Maximum length of console command output, longer output is truncated
+pub const MAX_EXEC_OUTPUT_LEN: usize = 120;
+
+/// Number of buckets in a histogram response
+pub const HISTOGRAM_BUCKETS: usize = embedded_services::metrics::HISTOGRAM_BUCKETS;
+
+/// Length of the fixed part of a serialized metric response
+const METRIC_HEADER_LEN: usize = 12;
+
+/// Length of the fixed part of a serialized histogram response
+const HISTOGRAM_HEADER_LEN: usize = 11 + 4 * HISTOGRAM_BUCKETS;
+
 #[derive(num_enum::IntoPrimitive, num_enum::TryFromPrimitive, Copy, Clone, Debug, PartialEq)]
 #[repr(u16)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -12,12 +37,27 @@ enum DebugCmd {
     /// Get buffer of debug messages, if available.
     /// Can be used to poll debug messages.
     GetMsgs = 1,
+    /// Get a snapshot of a service's internal state.
+    GetServiceState = 2,
+    /// Set the runtime log level of a module.
+    SetLogLevel = 3,
+    /// Get a queue depth or stack usage high-water mark.
+    GetMetric = 4,
+    /// Execute a console command line.
+    Exec = 5,
+    /// Get a latency histogram.
+    GetHistogram = 6,
 }
 
 impl From<&DebugRequest> for DebugCmd {
     fn from(request: &DebugRequest) -> Self {
         match request {
             DebugRequest::DebugGetMsgsRequest => DebugCmd::GetMsgs,
+            DebugRequest::DebugGetServiceStateRequest { .. } => DebugCmd::GetServiceState,
+            DebugRequest::DebugSetLogLevelRequest { .. } => DebugCmd::SetLogLevel,
+            DebugRequest::DebugGetMetricRequest { .. } => DebugCmd::GetMetric,
+            DebugRequest::DebugExecRequest { .. } => DebugCmd::Exec,
+            DebugRequest::DebugGetHistogramRequest { .. } => DebugCmd::GetHistogram,
         }
     }
 }
@@ -26,6 +66,11 @@ impl From<&DebugResponse> for DebugCmd {
     fn from(response: &DebugResponse) -> Self {
         match response {
             DebugResponse::DebugGetMsgsResponse { .. } => DebugCmd::GetMsgs,
+            DebugResponse::DebugGetServiceStateResponse { .. } => DebugCmd::GetServiceState,
+            DebugResponse::DebugSetLogLevelResponse => DebugCmd::SetLogLevel,
+            DebugResponse::DebugGetMetricResponse { .. } => DebugCmd::GetMetric,
+            DebugResponse::DebugExecResponse { .. } => DebugCmd::Exec,
+            DebugResponse::DebugGetHistogramResponse { .. } => DebugCmd::GetHistogram,
         }
     }
 }
@@ -34,21 +79,116 @@ impl From<&DebugResponse> for DebugCmd {
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DebugRequest {
     DebugGetMsgsRequest,
+    DebugGetServiceStateRequest {
+        service_id: u8,
+    },
+    /// Only the first `len` bytes of `module` are serialized, an empty module sets the default level
+    DebugSetLogLevelRequest {
+        level: u8,
+        len: u8,
+        module: [u8; MAX_LOG_MODULE_LEN],
+    },
+    /// Metrics are enumerated by index, starting from 0 until [`DebugError::UnknownMetric`] is returned
+    DebugGetMetricRequest {
+        index: u8,
+    },
+    /// Only the first `len` bytes of `cmdline` are serialized
+    DebugExecRequest {
+        len: u8,
+        cmdline: [u8; MAX_CMDLINE_LEN],
+    },
+    /// Histograms are enumerated by index, starting from 0 until [`DebugError::UnknownMetric`] is returned
+    DebugGetHistogramRequest {
+        index: u8,
+    },
 }
 
 impl SerializableMessage for DebugRequest {
-    fn serialize(self, _buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
+    fn serialize(self, buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
         match self {
             Self::DebugGetMsgsRequest => Ok(0),
+            Self::DebugGetServiceStateRequest { service_id } => {
+                *buffer.get_mut(0).ok_or(MessageSerializationError::BufferTooSmall)? = service_id;
+                Ok(1)
+            }
+            Self::DebugSetLogLevelRequest { level, len, module } => {
+                let module = module
+                    .get(..len as usize)
+                    .ok_or(MessageSerializationError::InvalidPayload("module length too large"))?;
+                let buffer = buffer
+                    .get_mut(..module.len() + 2)
+                    .ok_or(MessageSerializationError::BufferTooSmall)?;
+                let (header, payload) = buffer.split_at_mut(2);
+                header.copy_from_slice(&[level, len]);
+                payload.copy_from_slice(module);
+                Ok(module.len() + 2)
+            }
+            Self::DebugGetMetricRequest { index } | Self::DebugGetHistogramRequest { index } => {
+                *buffer.get_mut(0).ok_or(MessageSerializationError::BufferTooSmall)? = index;
+                Ok(1)
+            }
+            Self::DebugExecRequest { len, cmdline } => {
+                let cmdline = cmdline
+                    .get(..len as usize)
+                    .ok_or(MessageSerializationError::InvalidPayload(
+                        "command line length too large",
+                    ))?;
+                let buffer = buffer
+                    .get_mut(..cmdline.len() + 1)
+                    .ok_or(MessageSerializationError::BufferTooSmall)?;
+                let (header, payload) = buffer.split_at_mut(1);
+                header.copy_from_slice(&[len]);
+                payload.copy_from_slice(cmdline);
+                Ok(cmdline.len() + 1)
+            }
         }
     }
 
-    fn deserialize(discriminant: u16, _buffer: &[u8]) -> Result<Self, MessageSerializationError> {
+    fn deserialize(discriminant: u16, buffer: &[u8]) -> Result<Self, MessageSerializationError> {
         Ok(
             match DebugCmd::try_from(discriminant)
                 .map_err(|_| MessageSerializationError::UnknownMessageDiscriminant(discriminant))?
             {
                 DebugCmd::GetMsgs => Self::DebugGetMsgsRequest,
+                DebugCmd::GetServiceState => Self::DebugGetServiceStateRequest {
+                    service_id: *buffer.first().ok_or(MessageSerializationError::BufferTooSmall)?,
+                },
+                DebugCmd::SetLogLevel => {
+                    let (&[level, len], payload) = buffer
+                        .split_first_chunk::<2>()
+                        .ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let mut module = [0u8; MAX_LOG_MODULE_LEN];
+                    module
+                        .get_mut(..len as usize)
+                        .ok_or(MessageSerializationError::InvalidPayload("module length too large"))?
+                        .copy_from_slice(
+                            payload
+                                .get(..len as usize)
+                                .ok_or(MessageSerializationError::BufferTooSmall)?,
+                        );
+                    Self::DebugSetLogLevelRequest { level, len, module }
+                }
+                DebugCmd::GetMetric => Self::DebugGetMetricRequest {
+                    index: *buffer.first().ok_or(MessageSerializationError::BufferTooSmall)?,
+                },
+                DebugCmd::Exec => {
+                    let (&len, payload) = buffer.split_first().ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let mut cmdline = [0u8; MAX_CMDLINE_LEN];
+                    cmdline
+                        .get_mut(..len as usize)
+                        .ok_or(MessageSerializationError::InvalidPayload(
+                            "command line length too large",
+                        ))?
+                        .copy_from_slice(
+                            payload
+                                .get(..len as usize)
+                                .ok_or(MessageSerializationError::BufferTooSmall)?,
+                        );
+                    Self::DebugExecRequest { len, cmdline }
+                }
+                DebugCmd::GetHistogram => Self::DebugGetHistogramRequest {
+                    index: *buffer.first().ok_or(MessageSerializationError::BufferTooSmall)?,
+                },
             },
         )
     }
@@ -62,7 +202,43 @@ impl SerializableMessage for DebugRequest {
 #[derive(PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DebugResponse {
-    DebugGetMsgsResponse { debug_buf: [u8; STD_DEBUG_BUF_SIZE] },
+    DebugGetMsgsResponse {
+        debug_buf: [u8; STD_DEBUG_BUF_SIZE],
+    },
+    /// Only the first `len` bytes of `state` are serialized
+    DebugGetServiceStateResponse {
+        service_id: u8,
+        len: u8,
+        state: [u8; STD_SERVICE_STATE_SIZE],
+    },
+    DebugSetLogLevelResponse,
+    /// Only the first `len` bytes of `name` are serialized
+    DebugGetMetricResponse {
+        index: u8,
+        service_id: u8,
+        /// 0 for a queue depth, 1 for stack bytes
+        kind: u8,
+        capacity: u32,
+        high_water: u32,
+        len: u8,
+        name: [u8; MAX_METRIC_NAME_LEN],
+    },
+    /// Only the first `len` bytes of `output` are serialized
+    DebugExecResponse {
+        len: u8,
+        output: [u8; MAX_EXEC_OUTPUT_LEN],
+    },
+    /// Only the first `len` bytes of `name` are serialized
+    DebugGetHistogramResponse {
+        index: u8,
+        service_id: u8,
+        /// Upper bound of the first bucket, bucket `i` counts samples below `base << i`
+        base: u32,
+        max: u32,
+        buckets: [u32; HISTOGRAM_BUCKETS],
+        len: u8,
+        name: [u8; MAX_METRIC_NAME_LEN],
+    },
 }
 
 impl SerializableMessage for DebugResponse {
@@ -75,6 +251,88 @@ impl SerializableMessage for DebugResponse {
                     .copy_from_slice(&debug_buf);
                 Ok(debug_buf.len())
             }
+            Self::DebugGetServiceStateResponse { service_id, len, state } => {
+                let state = state
+                    .get(..len as usize)
+                    .ok_or(MessageSerializationError::InvalidPayload("state length too large"))?;
+                let buffer = buffer
+                    .get_mut(..state.len() + 2)
+                    .ok_or(MessageSerializationError::BufferTooSmall)?;
+                let (header, payload) = buffer.split_at_mut(2);
+                header.copy_from_slice(&[service_id, len]);
+                payload.copy_from_slice(state);
+                Ok(state.len() + 2)
+            }
+            Self::DebugSetLogLevelResponse => Ok(0),
+            Self::DebugGetMetricResponse {
+                index,
+                service_id,
+                kind,
+                capacity,
+                high_water,
+                len,
+                name,
+            } => {
+                let name = name
+                    .get(..len as usize)
+                    .ok_or(MessageSerializationError::InvalidPayload("name length too large"))?;
+                let buffer = buffer
+                    .get_mut(..METRIC_HEADER_LEN + name.len())
+                    .ok_or(MessageSerializationError::BufferTooSmall)?;
+                let (header, payload) = buffer.split_at_mut(METRIC_HEADER_LEN);
+                let (ids, values) = header.split_at_mut(3);
+                ids.copy_from_slice(&[index, service_id, kind]);
+                let (capacity_bytes, rest) = values.split_at_mut(4);
+                capacity_bytes.copy_from_slice(&capacity.to_le_bytes());
+                let (high_water_bytes, len_byte) = rest.split_at_mut(4);
+                high_water_bytes.copy_from_slice(&high_water.to_le_bytes());
+                len_byte.copy_from_slice(&[len]);
+                payload.copy_from_slice(name);
+                Ok(METRIC_HEADER_LEN + name.len())
+            }
+            Self::DebugExecResponse { len, output } => {
+                let output = output
+                    .get(..len as usize)
+                    .ok_or(MessageSerializationError::InvalidPayload("output length too large"))?;
+                let buffer = buffer
+                    .get_mut(..output.len() + 1)
+                    .ok_or(MessageSerializationError::BufferTooSmall)?;
+                let (header, payload) = buffer.split_at_mut(1);
+                header.copy_from_slice(&[len]);
+                payload.copy_from_slice(output);
+                Ok(output.len() + 1)
+            }
+            Self::DebugGetHistogramResponse {
+                index,
+                service_id,
+                base,
+                max,
+                buckets,
+                len,
+                name,
+            } => {
+                let name = name
+                    .get(..len as usize)
+                    .ok_or(MessageSerializationError::InvalidPayload("name length too large"))?;
+                let buffer = buffer
+                    .get_mut(..HISTOGRAM_HEADER_LEN + name.len())
+                    .ok_or(MessageSerializationError::BufferTooSmall)?;
+                let (header, payload) = buffer.split_at_mut(HISTOGRAM_HEADER_LEN);
+                let words = header
+                    .get_mut(2..HISTOGRAM_HEADER_LEN - 1)
+                    .ok_or(MessageSerializationError::BufferTooSmall)?
+                    .chunks_exact_mut(4);
+                for (word, value) in words.zip([base, max].into_iter().chain(buckets)) {
+                    word.copy_from_slice(&value.to_le_bytes());
+                }
+                header
+                    .first_chunk_mut::<2>()
+                    .ok_or(MessageSerializationError::BufferTooSmall)?
+                    .copy_from_slice(&[index, service_id]);
+                *header.last_mut().ok_or(MessageSerializationError::BufferTooSmall)? = len;
+                payload.copy_from_slice(name);
+                Ok(HISTOGRAM_HEADER_LEN + name.len())
+            }
         }
     }
 
@@ -90,6 +348,93 @@ impl SerializableMessage for DebugResponse {
                         .try_into()
                         .map_err(|_| MessageSerializationError::BufferTooSmall)?,
                 },
+                DebugCmd::GetServiceState => {
+                    let (&[service_id, len], payload) = buffer
+                        .split_first_chunk::<2>()
+                        .ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let mut state = [0u8; STD_SERVICE_STATE_SIZE];
+                    state
+                        .get_mut(..len as usize)
+                        .ok_or(MessageSerializationError::InvalidPayload("state length too large"))?
+                        .copy_from_slice(
+                            payload
+                                .get(..len as usize)
+                                .ok_or(MessageSerializationError::BufferTooSmall)?,
+                        );
+                    Self::DebugGetServiceStateResponse { service_id, len, state }
+                }
+                DebugCmd::SetLogLevel => Self::DebugSetLogLevelResponse,
+                DebugCmd::GetMetric => {
+                    let (header, payload) = buffer
+                        .split_first_chunk::<METRIC_HEADER_LEN>()
+                        .ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let [index, service_id, kind, c0, c1, c2, c3, h0, h1, h2, h3, len] = *header;
+                    let mut name = [0u8; MAX_METRIC_NAME_LEN];
+                    name.get_mut(..len as usize)
+                        .ok_or(MessageSerializationError::InvalidPayload("name length too large"))?
+                        .copy_from_slice(
+                            payload
+                                .get(..len as usize)
+                                .ok_or(MessageSerializationError::BufferTooSmall)?,
+                        );
+                    Self::DebugGetMetricResponse {
+                        index,
+                        service_id,
+                        kind,
+                        capacity: u32::from_le_bytes([c0, c1, c2, c3]),
+                        high_water: u32::from_le_bytes([h0, h1, h2, h3]),
+                        len,
+                        name,
+                    }
+                }
+                DebugCmd::Exec => {
+                    let (&len, payload) = buffer.split_first().ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let mut output = [0u8; MAX_EXEC_OUTPUT_LEN];
+                    output
+                        .get_mut(..len as usize)
+                        .ok_or(MessageSerializationError::InvalidPayload("output length too large"))?
+                        .copy_from_slice(
+                            payload
+                                .get(..len as usize)
+                                .ok_or(MessageSerializationError::BufferTooSmall)?,
+                        );
+                    Self::DebugExecResponse { len, output }
+                }
+                DebugCmd::GetHistogram => {
+                    let (header, payload) = buffer
+                        .split_first_chunk::<HISTOGRAM_HEADER_LEN>()
+                        .ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let [index, service_id, ..] = *header;
+                    let len = *header.last().ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let mut words = header
+                        .get(2..HISTOGRAM_HEADER_LEN - 1)
+                        .ok_or(MessageSerializationError::BufferTooSmall)?
+                        .chunks_exact(4)
+                        .map(|word| u32::from_le_bytes(word.try_into().unwrap_or_default()));
+                    let base = words.next().ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let max = words.next().ok_or(MessageSerializationError::BufferTooSmall)?;
+                    let mut buckets = [0u32; HISTOGRAM_BUCKETS];
+                    for (bucket, word) in buckets.iter_mut().zip(words) {
+                        *bucket = word;
+                    }
+                    let mut name = [0u8; MAX_METRIC_NAME_LEN];
+                    name.get_mut(..len as usize)
+                        .ok_or(MessageSerializationError::InvalidPayload("name length too large"))?
+                        .copy_from_slice(
+                            payload
+                                .get(..len as usize)
+                                .ok_or(MessageSerializationError::BufferTooSmall)?,
+                        );
+                    Self::DebugGetHistogramResponse {
+                        index,
+                        service_id,
+                        base,
+                        max,
+                        buckets,
+                        len,
+                        name,
+                    }
+                }
             },
         )
     }
@@ -104,12 +449,40 @@ impl SerializableMessage for DebugResponse {
 #[repr(u16)]
 pub enum DebugError {
     UnspecifiedFailure = 1,
+    /// No state provider is registered for the requested service
+    UnknownService = 2,
+    /// The log level or module in a log level request is invalid, or all module filters are in use
+    InvalidLogFilter = 3,
+    /// No metric or histogram is registered at the requested index
+    UnknownMetric = 4,
+    /// No console command is registered under the requested name
+    UnknownCommand = 5,
+    /// The console command line is malformed or its arguments are invalid
+    InvalidCommand = 6,
+}
+
+impl HostError for DebugError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::UnspecifiedFailure => ErrorClass::Unspecified,
+            Self::UnknownService
+            | Self::InvalidLogFilter
+            | Self::UnknownMetric
+            | Self::UnknownCommand
+            | Self::InvalidCommand => ErrorClass::InvalidArgument,
+        }
+    }
 }
 
 impl SerializableMessage for DebugError {
     fn serialize(self, _buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
         match self {
-            Self::UnspecifiedFailure => Ok(0),
+            Self::UnspecifiedFailure
+            | Self::UnknownService
+            | Self::InvalidLogFilter
+            | Self::UnknownMetric
+            | Self::UnknownCommand
+            | Self::InvalidCommand => Ok(0),
         }
     }
 
@@ -125,3 +498,135 @@ impl SerializableMessage for DebugError {
 }
 
 pub type DebugResult = Result<DebugResponse, DebugError>;
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_service_state_request_round_trip() {
+        let request = DebugRequest::DebugGetServiceStateRequest { service_id: 7 };
+
+        let mut buffer = [0u8; 1];
+        assert_eq!(request.serialize(&mut buffer).unwrap(), 1);
+        assert_eq!(buffer, [7]);
+        let discriminant = request.discriminant();
+        assert_eq!(discriminant, 2);
+        assert!(DebugRequest::deserialize(discriminant, &buffer).unwrap() == request);
+
+        // Missing service ID
+        assert!(request.serialize(&mut []).is_err());
+        assert!(DebugRequest::deserialize(discriminant, &[]).is_err());
+    }
+
+    #[test]
+    fn get_service_state_response_round_trip() {
+        let mut state = [0u8; STD_SERVICE_STATE_SIZE];
+        state.get_mut(..3).unwrap().copy_from_slice(&[0xa, 0xb, 0xc]);
+        let response = DebugResponse::DebugGetServiceStateResponse {
+            service_id: 7,
+            len: 3,
+            state,
+        };
+
+        let mut buffer = [0u8; STD_SERVICE_STATE_SIZE + 2];
+        assert_eq!(response.serialize(&mut buffer).unwrap(), 5);
+        assert_eq!(buffer.get(..5).unwrap(), &[7, 3, 0xa, 0xb, 0xc]);
+        let discriminant = response.discriminant();
+        assert_eq!(discriminant, 2);
+        assert!(DebugResponse::deserialize(discriminant, buffer.get(..5).unwrap()).unwrap() == response);
+
+        // An empty snapshot only carries the header
+        let response = DebugResponse::DebugGetServiceStateResponse {
+            service_id: 7,
+            len: 0,
+            state: [0; STD_SERVICE_STATE_SIZE],
+        };
+        assert_eq!(response.serialize(&mut buffer).unwrap(), 2);
+        assert!(DebugResponse::deserialize(discriminant, buffer.get(..2).unwrap()).unwrap() == response);
+    }
+
+    #[test]
+    fn get_service_state_response_invalid() {
+        // Snapshot longer than the response can hold
+        assert!(DebugResponse::deserialize(2, &[7, STD_SERVICE_STATE_SIZE as u8 + 1]).is_err());
+
+        // Truncated snapshot
+        assert!(DebugResponse::deserialize(2, &[7, 2, 0xa]).is_err());
+
+        let response = DebugResponse::DebugGetServiceStateResponse {
+            service_id: 7,
+            len: STD_SERVICE_STATE_SIZE as u8 + 1,
+            state: [0; STD_SERVICE_STATE_SIZE],
+        };
+        assert!(response.serialize(&mut [0u8; 128]).is_err());
+
+        // Buffer too small for the snapshot
+        let response = DebugResponse::DebugGetServiceStateResponse {
+            service_id: 7,
+            len: 3,
+            state: [0; STD_SERVICE_STATE_SIZE],
+        };
+        assert!(response.serialize(&mut [0u8; 4]).is_err());
+    }
+
+    #[test]
+    fn unknown_service_error() {
+        assert_eq!(DebugError::UnknownService.discriminant(), 2);
+        assert_eq!(DebugError::UnknownService.serialize(&mut []).unwrap(), 0);
+    }
+
+    #[test]
+    fn set_log_level_request_round_trip() {
+        let mut module = [0u8; MAX_LOG_MODULE_LEN];
+        module.get_mut(..14).unwrap().copy_from_slice(b"type_c_service");
+        let request = DebugRequest::DebugSetLogLevelRequest {
+            level: 4,
+            len: 14,
+            module,
+        };
+
+        let mut buffer = [0u8; MAX_LOG_MODULE_LEN + 2];
+        assert_eq!(request.serialize(&mut buffer).unwrap(), 16);
+        assert_eq!(buffer.get(..2).unwrap(), &[4, 14]);
+        assert_eq!(buffer.get(2..16).unwrap(), b"type_c_service");
+        let discriminant = request.discriminant();
+        assert_eq!(discriminant, 3);
+        assert!(DebugRequest::deserialize(discriminant, buffer.get(..16).unwrap()).unwrap() == request);
+
+        // An empty module sets the default level
+        let request = DebugRequest::DebugSetLogLevelRequest {
+            level: 2,
+            len: 0,
+            module: [0; MAX_LOG_MODULE_LEN],
+        };
+        assert_eq!(request.serialize(&mut buffer).unwrap(), 2);
+        assert!(DebugRequest::deserialize(discriminant, buffer.get(..2).unwrap()).unwrap() == request);
+    }
+
+    #[test]
+    fn set_log_level_request_invalid() {
+        // Module longer than the request can hold
+        let buffer = [0, MAX_LOG_MODULE_LEN as u8 + 1];
+        assert!(DebugRequest::deserialize(3, &buffer).is_err());
+
+        // Truncated module
+        assert!(DebugRequest::deserialize(3, &[0, 4, b'a']).is_err());
+
+        let request = DebugRequest::DebugSetLogLevelRequest {
+            level: 0,
+            len: MAX_LOG_MODULE_LEN as u8 + 1,
+            module: [0; MAX_LOG_MODULE_LEN],
+        };
+        assert!(request.serialize(&mut [0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn set_log_level_response_round_trip() {
+        let response = DebugResponse::DebugSetLogLevelResponse;
+        assert_eq!(response.discriminant(), 3);
+        assert_eq!(response.serialize(&mut []).unwrap(), 0);
+        assert!(DebugResponse::deserialize(3, &[]).unwrap() == response);
+    }
+}
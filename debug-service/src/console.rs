@@ -0,0 +1,178 @@
+//! Debug console
+//!
+//! A line-based command interpreter reachable through [`DebugRequest::DebugExecRequest`](debug_service_messages::DebugRequest::DebugExecRequest).
+//! The first word of a command line selects the command, the remaining words are passed to it as arguments.
+//!
+//! `help`, `stats`, `latency` and `loglevel` are built in. Services and the platform register a [`Command`] for everything else,
+//! e.g. `reset`, `thermal show` or `pd show`.
+use core::fmt::Write;
+
+use debug_service_messages::{DebugError, DebugResponse, DebugResult, MAX_EXEC_OUTPUT_LEN};
+use embedded_services::fmt::filter::{self, Level};
+use embedded_services::{info, intrusive_list, metrics};
+
+/// Arguments following the command name
+pub type Args<'a> = core::str::SplitAsciiWhitespace<'a>;
+
+/// Trait implemented by services that handle a console command
+pub trait CommandHandler: Sync {
+    /// Execute the command with the given arguments, writing any output to `out`
+    fn exec(&self, args: Args<'_>, out: &mut Output) -> Result<(), DebugError>;
+}
+
+/// Registration node for a console command
+pub struct Command {
+    node: intrusive_list::Node,
+    name: &'static str,
+    help: &'static str,
+    handler: &'static dyn CommandHandler,
+}
+
+impl Command {
+    /// Create a new command with the given name and one line help text, such that it could be used in a static
+    pub const fn new(name: &'static str, help: &'static str, handler: &'static dyn CommandHandler) -> Self {
+        Self {
+            node: intrusive_list::Node::uninit(),
+            name,
+            help,
+            handler,
+        }
+    }
+
+    /// Register this command, forwards any error states (such as double registration) from intrusive_list
+    pub fn register(&'static self) -> intrusive_list::Result<()> {
+        COMMANDS.push(self)
+    }
+}
+
+impl intrusive_list::NodeContainer for Command {
+    fn get_node(&self) -> &intrusive_list::Node {
+        &self.node
+    }
+}
+
+static COMMANDS: intrusive_list::IntrusiveList = intrusive_list::IntrusiveList::new();
+
+/// Command output returned to the host, output beyond [`MAX_EXEC_OUTPUT_LEN`] is silently truncated
+pub struct Output {
+    len: usize,
+    buffer: [u8; MAX_EXEC_OUTPUT_LEN],
+}
+
+impl Output {
+    const fn new() -> Self {
+        Self {
+            len: 0,
+            buffer: [0; MAX_EXEC_OUTPUT_LEN],
+        }
+    }
+
+    fn into_response(self) -> DebugResponse {
+        DebugResponse::DebugExecResponse {
+            len: self.len as u8,
+            output: self.buffer,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let remaining = self.buffer.get_mut(self.len..).unwrap_or(&mut []);
+        let len = s.len().min(remaining.len());
+        remaining[..len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+/// Built in commands, listed by `help` ahead of the registered commands
+const BUILTINS: [(&str, &str); 4] = [
+    ("help", "list commands"),
+    ("stats", "queue and stack high-water marks"),
+    ("latency", "latency histograms"),
+    ("loglevel", "<level> [module]"),
+];
+
+/// Execute a host command line
+pub(crate) fn exec(cmdline: &[u8]) -> DebugResult {
+    let cmdline = core::str::from_utf8(cmdline).map_err(|_| DebugError::InvalidCommand)?;
+    let mut args = cmdline.split_ascii_whitespace();
+    let name = args.next().ok_or(DebugError::InvalidCommand)?;
+
+    let mut out = Output::new();
+    match name {
+        "help" => help(&mut out),
+        "stats" => stats(&mut out),
+        "latency" => latency(&mut out),
+        "loglevel" => loglevel(args, &mut out)?,
+        _ => {
+            let command = COMMANDS
+                .iter_only::<Command>()
+                .find(|command| command.name == name)
+                .ok_or(DebugError::UnknownCommand)?;
+            command.handler.exec(args, &mut out)?;
+        }
+    }
+
+    Ok(out.into_response())
+}
+
+fn help(out: &mut Output) {
+    for (name, help) in BUILTINS {
+        let _ = writeln!(out, "{name}: {help}");
+    }
+    for command in COMMANDS.iter_only::<Command>() {
+        let _ = writeln!(out, "{}: {}", command.name, command.help);
+    }
+}
+
+fn stats(out: &mut Output) {
+    for metric in metrics::metrics() {
+        let _ = writeln!(out, "{} {}/{}", metric.name(), metric.high_water(), metric.capacity());
+    }
+}
+
+/// Print each histogram as its maximum followed by the non-empty buckets, keyed by their upper bound
+fn latency(out: &mut Output) {
+    for histogram in metrics::histograms() {
+        let _ = write!(out, "{} max {}", histogram.name(), histogram.max());
+        for i in 0..metrics::HISTOGRAM_BUCKETS {
+            let count = histogram.bucket(i);
+            if count == 0 {
+                continue;
+            }
+            if i + 1 < metrics::HISTOGRAM_BUCKETS {
+                let _ = write!(out, " <{}:{}", histogram.base() << i, count);
+            } else {
+                let _ = write!(out, " +:{}", count);
+            }
+        }
+        let _ = writeln!(out);
+    }
+}
+
+/// Set the log level of a module, or the default level if no module is given
+fn loglevel(mut args: Args<'_>, out: &mut Output) -> Result<(), DebugError> {
+    let level = args.next().and_then(parse_level).ok_or(DebugError::InvalidCommand)?;
+    match args.next() {
+        Some(module) => filter::set_level(module, level).map_err(|_| DebugError::InvalidLogFilter)?,
+        None => filter::set_default_level(level),
+    }
+
+    info!("Console set log level to {:?}", level);
+    let _ = writeln!(out, "ok");
+    Ok(())
+}
+
+/// Parse a log level given by name or number
+fn parse_level(arg: &str) -> Option<Level> {
+    match arg {
+        "off" => Some(Level::Off),
+        "error" => Some(Level::Error),
+        "warn" => Some(Level::Warn),
+        "info" => Some(Level::Info),
+        "debug" => Some(Level::Debug),
+        "trace" => Some(Level::Trace),
+        _ => arg.parse::<u8>().ok().and_then(|level| Level::try_from(level).ok()),
+    }
+}
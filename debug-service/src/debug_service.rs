@@ -1,8 +1,12 @@
-use debug_service_messages::{DebugRequest, DebugResult};
+use debug_service_messages::{
+    DebugError, DebugRequest, DebugResponse, DebugResult, HISTOGRAM_BUCKETS, MAX_METRIC_NAME_LEN,
+};
 use embassy_sync::{once_lock::OnceLock, signal::Signal};
 use embedded_services::GlobalRawMutex;
 use embedded_services::buffer::{OwnedRef, SharedRef};
-use embedded_services::debug;
+use embedded_services::fmt::filter::{self, Level};
+use embedded_services::metrics;
+use embedded_services::{debug, info};
 
 // Maximum number of bytes to request per defmt frame write grant.
 // This decouples the logger from any external protocol-specific size constants.
@@ -40,7 +44,22 @@ impl embedded_services::relay::mctp::RelayServiceHandlerTypes for Service {
 }
 
 impl embedded_services::relay::mctp::RelayServiceHandler for Service {
-    async fn process_request(&self, _request: Self::RequestType) -> Self::ResultType {
+    async fn process_request(&self, request: Self::RequestType) -> Self::ResultType {
+        match request {
+            DebugRequest::DebugGetServiceStateRequest { service_id } => {
+                return crate::service_state::service_state(service_id);
+            }
+            DebugRequest::DebugSetLogLevelRequest { level, len, module } => {
+                return set_log_level(level, module.get(..len as usize).unwrap_or(&[]));
+            }
+            DebugRequest::DebugGetMetricRequest { index } => return get_metric(index),
+            DebugRequest::DebugGetHistogramRequest { index } => return get_histogram(index),
+            DebugRequest::DebugExecRequest { len, cmdline } => {
+                return crate::console::exec(cmdline.get(..len as usize).unwrap_or(&[]));
+            }
+            DebugRequest::DebugGetMsgsRequest => {}
+        }
+
         // Host sent an ACPI/MCTP request (e.g. GetDebugBuffer). Treat this as the
         // trigger to send the staged debug buffer back to the host.
         // We only use the signal as a wakeup; the defmt task ignores any payload here.
@@ -54,6 +73,65 @@ impl embedded_services::relay::mctp::RelayServiceHandler for Service {
     }
 }
 
+/// Apply a host log level request, an empty module sets the default level
+fn set_log_level(level: u8, module: &[u8]) -> DebugResult {
+    let level = Level::try_from(level).map_err(|_| DebugError::InvalidLogFilter)?;
+    let module = core::str::from_utf8(module).map_err(|_| DebugError::InvalidLogFilter)?;
+    if module.is_empty() {
+        filter::set_default_level(level);
+    } else {
+        filter::set_level(module, level).map_err(|_| DebugError::InvalidLogFilter)?;
+    }
+    info!("Log level of '{}' set to {:?}", module, level);
+    Ok(DebugResponse::DebugSetLogLevelResponse)
+}
+
+/// Report the metric registered at `index`
+fn get_metric(index: u8) -> DebugResult {
+    let metric = metrics::metrics()
+        .nth(index as usize)
+        .ok_or(DebugError::UnknownMetric)?;
+
+    let bytes = metric.name().as_bytes();
+    let len = bytes.len().min(MAX_METRIC_NAME_LEN);
+    let mut name = [0u8; MAX_METRIC_NAME_LEN];
+    name[..len].copy_from_slice(&bytes[..len]);
+    Ok(DebugResponse::DebugGetMetricResponse {
+        index,
+        service_id: metric.service_id(),
+        kind: metric.kind() as u8,
+        capacity: u32::try_from(metric.capacity()).unwrap_or(u32::MAX),
+        high_water: u32::try_from(metric.high_water()).unwrap_or(u32::MAX),
+        len: len as u8,
+        name,
+    })
+}
+
+/// Report the histogram registered at `index`
+fn get_histogram(index: u8) -> DebugResult {
+    let histogram = metrics::histograms()
+        .nth(index as usize)
+        .ok_or(DebugError::UnknownMetric)?;
+
+    let bytes = histogram.name().as_bytes();
+    let len = bytes.len().min(MAX_METRIC_NAME_LEN);
+    let mut name = [0u8; MAX_METRIC_NAME_LEN];
+    name[..len].copy_from_slice(&bytes[..len]);
+    let mut buckets = [0u32; HISTOGRAM_BUCKETS];
+    for (i, bucket) in buckets.iter_mut().enumerate() {
+        *bucket = u32::try_from(histogram.bucket(i)).unwrap_or(u32::MAX);
+    }
+    Ok(DebugResponse::DebugGetHistogramResponse {
+        index,
+        service_id: histogram.service_id(),
+        base: u32::try_from(histogram.base()).unwrap_or(u32::MAX),
+        max: u32::try_from(histogram.max()).unwrap_or(u32::MAX),
+        buckets,
+        len: len as u8,
+        name,
+    })
+}
+
 static DEBUG_SERVICE: OnceLock<Service> = OnceLock::new();
 
 // Global signal used to notify tasks waiting on a Host response path (e.g., ACPI response).
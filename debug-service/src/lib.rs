@@ -27,5 +27,11 @@ mod defmt_ring_logger;
 #[cfg(not(test))]
 pub mod task;
 
+#[cfg(not(test))]
+pub mod service_state;
+
+#[cfg(not(test))]
+pub mod console;
+
 #[cfg(not(test))]
 pub use debug_service::*;
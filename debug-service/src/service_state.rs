@@ -0,0 +1,18 @@
+//! Service state snapshots
+//!
+//! Services register a [`StateProvider`] to expose a compact snapshot of their internal state
+//! (e.g. current consumer, fan duties, last event) that the host can request for bug reports.
+//! The registry lives in [`embedded_services::service_state`] so services don't depend on the debug service.
+use debug_service_messages::{DebugError, DebugResponse, STD_SERVICE_STATE_SIZE};
+pub use embedded_services::service_state::{RetainedState, ServiceState, StateProvider};
+
+/// Take a state snapshot from the provider registered for `service_id`
+pub(crate) fn service_state(service_id: u8) -> Result<DebugResponse, DebugError> {
+    let mut state = [0u8; STD_SERVICE_STATE_SIZE];
+    let len = embedded_services::service_state::snapshot(service_id, &mut state).ok_or(DebugError::UnknownService)?;
+    Ok(DebugResponse::DebugGetServiceStateResponse {
+        service_id,
+        len: len as u8,
+        state,
+    })
+}
@@ -1,11 +1,22 @@
 //! Immediate broadcaster
 //! No backpressure and unhandled messages may be lost if the subscriber's queue is full.
+//!
+//! Lost messages are counted and logged per subscriber, see [`Subscriber::dropped`] and
+//! [`Subscriber::with_lag_callback`]. State-like topics can use [`Immediate::new_retained`] so that late subscribers
+//! still see the latest value.
 
-use core::marker::PhantomData;
+use core::cell::RefCell;
 
-use embassy_sync::{mutex::Mutex, pubsub::DynImmediatePublisher};
+use embassy_sync::{
+    blocking_mutex,
+    mutex::Mutex,
+    pubsub::{DynImmediatePublisher, DynSubscriber, WaitResult},
+};
 
-use crate::{GlobalRawMutex, intrusive_list};
+use crate::{GlobalRawMutex, event, intrusive_list, warn};
+
+/// Callback invoked when a subscriber misses messages, with the subscriber's total dropped message count
+pub type LagCallback = fn(u64);
 
 /// Receiver
 pub struct Receiver<'a, T: Clone> {
@@ -38,7 +49,8 @@ impl<T: Clone> intrusive_list::NodeContainer for Receiver<'static, T> {
 /// Immediate broadcaster
 pub struct Immediate<T: Clone + 'static> {
     receivers: intrusive_list::IntrusiveList,
-    _phantom: PhantomData<T>,
+    /// Latest broadcast message, only tracked in retained mode
+    retained: Option<blocking_mutex::Mutex<GlobalRawMutex, RefCell<Option<T>>>>,
 }
 
 impl<T: Clone + 'static> Immediate<T> {
@@ -46,9 +58,27 @@ impl<T: Clone + 'static> Immediate<T> {
     pub const fn new() -> Self {
         Self {
             receivers: intrusive_list::IntrusiveList::new(),
-            _phantom: PhantomData,
+            retained: None,
+        }
+    }
+
+    /// Create a new `Immediate<T>` that retains the latest message
+    ///
+    /// Receivers registered after a broadcast are immediately sent the latest message. Intended for state-like topics
+    /// where a subscriber only cares about the current value.
+    pub const fn new_retained() -> Self {
+        Self {
+            receivers: intrusive_list::IntrusiveList::new(),
+            retained: Some(blocking_mutex::Mutex::new(RefCell::new(None))),
         }
     }
+
+    /// Returns the latest broadcast message, `None` if nothing has been broadcast or not in retained mode
+    pub fn latest(&self) -> Option<T> {
+        self.retained
+            .as_ref()
+            .and_then(|retained| retained.lock(|retained| retained.borrow().clone()))
+    }
 }
 
 impl<T: Clone + 'static> Default for Immediate<T> {
@@ -59,12 +89,26 @@ impl<T: Clone + 'static> Default for Immediate<T> {
 
 impl<T: Clone + 'static> Immediate<T> {
     /// Register a receiver
+    ///
+    /// In retained mode the receiver is immediately sent the latest message, if any.
     pub fn register_receiver(&self, receiver: &'static Receiver<'_, T>) -> intrusive_list::Result<()> {
-        self.receivers.push(receiver)
+        self.receivers.push(receiver)?;
+        if let Some(message) = self.latest() {
+            // The receiver was just created so nothing else can hold its publisher
+            match receiver.publisher.try_lock() {
+                Ok(publisher) => publisher.publish_immediate(message),
+                Err(_) => warn!("Failed to send retained message to new receiver"),
+            }
+        }
+        Ok(())
     }
 
     /// Broadcast a message to all receivers
     pub async fn broadcast(&self, message: T) {
+        if let Some(retained) = &self.retained {
+            retained.lock(|retained| *retained.borrow_mut() = Some(message.clone()));
+        }
+
         for node in &self.receivers {
             if let Some(receiver) = node.data::<Receiver<'_, T>>() {
                 receiver.publisher.lock().await.publish_immediate(message.clone());
@@ -73,11 +117,87 @@ impl<T: Clone + 'static> Immediate<T> {
     }
 }
 
+/// Subscriber to an immediate broadcaster that tracks the messages it missed
+///
+/// Each subscriber of a channel reads at its own pace, so lag is counted here rather than at the publisher.
+pub struct Subscriber<'a, T: Clone> {
+    subscriber: DynSubscriber<'a, T>,
+    dropped: u64,
+    on_lag: Option<LagCallback>,
+}
+
+impl<'a, T: Clone> Subscriber<'a, T> {
+    /// Create a new subscriber
+    pub fn new(subscriber: DynSubscriber<'a, T>) -> Self {
+        Self {
+            subscriber,
+            dropped: 0,
+            on_lag: None,
+        }
+    }
+
+    /// Create a new subscriber that calls `on_lag` whenever it misses messages
+    pub fn with_lag_callback(subscriber: DynSubscriber<'a, T>, on_lag: LagCallback) -> Self {
+        Self {
+            on_lag: Some(on_lag),
+            ..Self::new(subscriber)
+        }
+    }
+
+    /// Number of messages this subscriber has missed because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Record missed messages, returns the message if there is one
+    fn process(&mut self, result: WaitResult<T>) -> Option<T> {
+        match result {
+            WaitResult::Message(message) => Some(message),
+            WaitResult::Lagged(missed) => {
+                self.dropped = self.dropped.saturating_add(missed);
+                warn!("Broadcast subscriber lagged, {} messages dropped", self.dropped);
+                if let Some(on_lag) = self.on_lag {
+                    on_lag(self.dropped);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<'a, T: Clone> From<DynSubscriber<'a, T>> for Subscriber<'a, T> {
+    fn from(subscriber: DynSubscriber<'a, T>) -> Self {
+        Self::new(subscriber)
+    }
+}
+
+impl<T: Clone> event::Receiver<T> for Subscriber<'_, T> {
+    fn try_next(&mut self) -> Option<T> {
+        loop {
+            let result = self.subscriber.try_next_message()?;
+            if let Some(message) = self.process(result) {
+                return Some(message);
+            }
+        }
+    }
+
+    async fn wait_next(&mut self) -> T {
+        loop {
+            let result = self.subscriber.next_message().await;
+            if let Some(message) = self.process(result) {
+                return message;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod test {
     use super::*;
-    use embassy_sync::pubsub::{PubSubChannel, WaitResult};
+    use crate::event::Receiver as _;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use embassy_sync::pubsub::PubSubChannel;
     use static_cell::StaticCell;
 
     /// Test normal functionality
@@ -127,4 +247,70 @@ mod test {
         let message = subscriber.next_message().await;
         assert_eq!(message, WaitResult::Message(34));
     }
+
+    /// Test each subscriber counts its own dropped messages and calls its lag callback
+    #[tokio::test]
+    async fn test_subscriber_lag() {
+        static LAST_DROPPED: AtomicU64 = AtomicU64::new(0);
+        fn on_lag(dropped: u64) {
+            LAST_DROPPED.store(dropped, Ordering::Relaxed);
+        }
+
+        static CHANNEL: StaticCell<PubSubChannel<GlobalRawMutex, u32, 2, 2, 0>> = StaticCell::new();
+        let channel = CHANNEL.init(PubSubChannel::new());
+
+        let publisher = channel.dyn_immediate_publisher();
+        let mut slow = Subscriber::with_lag_callback(channel.dyn_subscriber().unwrap(), on_lag);
+        let mut fast = Subscriber::new(channel.dyn_subscriber().unwrap());
+
+        static RECEIVER: StaticCell<Receiver<'static, u32>> = StaticCell::new();
+        let receiver = RECEIVER.init(Receiver::new(publisher));
+
+        static BROADCASTER: StaticCell<Immediate<u32>> = StaticCell::new();
+        let immediate_broadcaster = BROADCASTER.init(Immediate::default());
+
+        immediate_broadcaster.register_receiver(receiver).unwrap();
+        for message in 0..2 {
+            immediate_broadcaster.broadcast(message).await;
+            assert_eq!(fast.wait_next().await, message);
+        }
+        for message in 2..5 {
+            immediate_broadcaster.broadcast(message).await;
+        }
+
+        // The slow subscriber missed the first three messages, skips to the oldest one still queued
+        assert_eq!(slow.wait_next().await, 3);
+        assert_eq!(slow.dropped(), 3);
+        assert_eq!(LAST_DROPPED.load(Ordering::Relaxed), 3);
+
+        // The fast subscriber only missed message 2
+        assert_eq!(fast.try_next(), Some(3));
+        assert_eq!(fast.dropped(), 1);
+        assert_eq!(fast.try_next(), Some(4));
+        assert_eq!(fast.try_next(), None);
+    }
+
+    /// Test a late receiver gets the retained message
+    #[tokio::test]
+    async fn test_immediate_broadcaster_retained() {
+        static CHANNEL: StaticCell<PubSubChannel<GlobalRawMutex, u32, 1, 1, 0>> = StaticCell::new();
+        let channel = CHANNEL.init(PubSubChannel::new());
+
+        let publisher = channel.dyn_immediate_publisher();
+        let mut subscriber = channel.dyn_subscriber().unwrap();
+
+        static RECEIVER: StaticCell<Receiver<'static, u32>> = StaticCell::new();
+        let receiver = RECEIVER.init(Receiver::new(publisher));
+
+        static BROADCASTER: StaticCell<Immediate<u32>> = StaticCell::new();
+        let immediate_broadcaster = BROADCASTER.init(Immediate::new_retained());
+
+        assert_eq!(immediate_broadcaster.latest(), None);
+        immediate_broadcaster.broadcast(42).await;
+        assert_eq!(immediate_broadcaster.latest(), Some(42));
+
+        immediate_broadcaster.register_receiver(receiver).unwrap();
+        let message = subscriber.next_message().await;
+        assert_eq!(message, WaitResult::Message(42));
+    }
 }
@@ -11,6 +11,11 @@
 //!
 //! This allows for producer code to own the buffer through a `OwnedRef`, and then allow access to consumers
 //! through any number of `SharedRef`.
+//!
+//! An `OwnedRef` can be split into two `OwnedSlice`s covering disjoint halves of the buffer, e.g. so that a receive
+//! and a transmit path can each use one half of a single static buffer concurrently. Borrows of each half are tracked
+//! separately, so borrowing one half mutably doesn't block the other, while accesses spanning both halves are still
+//! checked against both.
 use core::borrow::{Borrow, BorrowMut};
 use core::marker::PhantomData;
 use core::ops::Range;
@@ -40,11 +45,43 @@ enum Status {
     Poisoned,
 }
 
+impl Status {
+    fn borrowed(self, mutable: bool) -> Result<Self, Error> {
+        match (self, mutable) {
+            (Status::None, false) => Ok(Status::Immutable(1)),
+            (Status::None, true) => Ok(Status::Mutable),
+            (Status::Mutable, _) => Err(Error::BorrowedMutably),
+            (Status::Immutable(count), false) => Ok(Status::Immutable(count + 1)),
+            (Status::Immutable(_), true) => Err(Error::BorrowedImmutably),
+            (Status::Poisoned, _) => Err(Error::Poisoned),
+        }
+    }
+
+    fn released(self) -> Self {
+        match self {
+            // Unborrowed buffer dropped
+            Status::None => Status::Poisoned,
+            Status::Mutable => Status::None,
+            // Buffer borrow count underflow
+            Status::Immutable(0) => Status::Poisoned,
+            Status::Immutable(1) => Status::None,
+            Status::Immutable(count) => Status::Immutable(count - 1),
+            // Buffer already poisoned
+            Status::Poisoned => Status::Poisoned,
+        }
+    }
+}
+
 /// Underlying buffer storage struct
 pub struct Buffer<'a, T> {
     buffer: AtomicPtr<T>,
     len: usize,
+    /// Borrow status of the lower half, which is the whole buffer until it's split
     status: SyncCell<Status>,
+    /// Borrow status of the upper half
+    upper_status: SyncCell<Status>,
+    /// Start of the upper half, equal to `len` until the buffer is split
+    split: SyncCell<usize>,
     _lifetime: PhantomData<&'a ()>,
 }
 
@@ -57,6 +94,8 @@ impl<'a, T> Buffer<'a, T> {
             buffer: AtomicPtr::new(raw_buffer.as_mut_ptr()),
             len: raw_buffer.len(),
             status: SyncCell::new(Status::None),
+            upper_status: SyncCell::new(Status::None),
+            split: SyncCell::new(raw_buffer.len()),
             _lifetime: PhantomData,
         }
     }
@@ -80,35 +119,46 @@ impl<'a, T> Buffer<'a, T> {
         self.len == 0
     }
 
-    fn borrow(&self, mutable: bool) -> Result<(), Error> {
-        let status = match (self.status.get(), mutable) {
-            (Status::None, false) => Status::Immutable(1),
-            (Status::None, true) => Status::Mutable,
-            (Status::Mutable, _) => return Err(Error::BorrowedMutably),
-            (Status::Immutable(count), false) => Status::Immutable(count + 1),
-            (Status::Immutable(_), true) => return Err(Error::BorrowedImmutably),
-            (Status::Poisoned, _) => return Err(Error::Poisoned),
+    /// Returns which halves `range` touches, as (lower, upper)
+    fn halves(&self, range: &Range<usize>) -> (bool, bool) {
+        let split = self.split.get();
+        (range.start < split, range.end > split)
+    }
+
+    fn borrow(&self, range: &Range<usize>, mutable: bool) -> Result<(), Error> {
+        let (lower, upper) = self.halves(range);
+        // Check both halves before updating either so a failed borrow leaves no trace
+        let status = if lower {
+            Some(self.status.get().borrowed(mutable)?)
+        } else {
+            None
         };
-        self.status.set(status);
+        let upper_status = if upper {
+            Some(self.upper_status.get().borrowed(mutable)?)
+        } else {
+            None
+        };
+
+        if let Some(status) = status {
+            self.status.set(status);
+        }
+        if let Some(status) = upper_status {
+            self.upper_status.set(status);
+        }
         Ok(())
     }
 
     // In the case of invalid status, we can't return an error since this is within the drop handler,
     // but don't want to panic either.
     // Instead, mark this buffer `Poisoned` to signify it's now in a bad/unexpected state.
-    fn drop_borrow(&self) {
-        let status = match self.status.get() {
-            // Unborrowed buffer dropped
-            Status::None => Status::Poisoned,
-            Status::Mutable => Status::None,
-            // Buffer borrow count underflow
-            Status::Immutable(0) => Status::Poisoned,
-            Status::Immutable(1) => Status::None,
-            Status::Immutable(count) => Status::Immutable(count - 1),
-            // Buffer already poisoned
-            Status::Poisoned => Status::Poisoned,
-        };
-        self.status.set(status);
+    fn drop_borrow(&self, range: &Range<usize>) {
+        let (lower, upper) = self.halves(range);
+        if lower {
+            self.status.set(self.status.get().released());
+        }
+        if upper {
+            self.upper_status.set(self.upper_status.get().released());
+        }
     }
 }
 
@@ -132,7 +182,7 @@ impl<'a, T> OwnedRef<'a, T> {
     ///
     /// Returns an error if the buffer is already borrowed
     pub fn borrow_mut(&self) -> Result<AccessMut<'a, T>, Error> {
-        AccessMut::new(self.0)
+        AccessMut::new(self.0, 0..self.0.len())
     }
 
     /// Returns the length of the buffer
@@ -144,37 +194,113 @@ impl<'a, T> OwnedRef<'a, T> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Splits the buffer into two owned halves, `[0, mid)` and `[mid, len)`, that can be borrowed independently
+    ///
+    /// Returns `self` back if `mid` doesn't leave both halves non-empty, or if the buffer is currently borrowed.
+    pub fn split_at(self, mid: usize) -> Result<(OwnedSlice<'a, T>, OwnedSlice<'a, T>), Self> {
+        let buffer = self.0;
+        if mid == 0 || mid >= buffer.len() || buffer.status.get() != Status::None {
+            return Err(self);
+        }
+
+        buffer.split.set(mid);
+        Ok((
+            OwnedSlice { buffer, slice: 0..mid },
+            OwnedSlice {
+                buffer,
+                slice: mid..buffer.len(),
+            },
+        ))
+    }
+}
+
+/// A mutable, owned reference to one half of a split buffer
+pub struct OwnedSlice<'a, T> {
+    buffer: &'a Buffer<'a, T>,
+    slice: Range<usize>,
+}
+
+impl<'a, T> OwnedSlice<'a, T> {
+    /// Creates an immutable reference to this half of the buffer
+    pub fn reference(&self) -> SharedRef<'a, T> {
+        SharedRef {
+            buffer: self.buffer,
+            slice: self.slice.clone(),
+        }
+    }
+
+    /// Borrows this half of the buffer immutably
+    ///
+    /// Returns an error if it's already borrowed mutably
+    pub fn borrow(&self) -> Result<Access<'a, T>, Error> {
+        Access::new(self.buffer, self.slice.clone())
+    }
+
+    /// Borrows this half of the buffer mutably
+    ///
+    /// Returns an error if it's already borrowed
+    pub fn borrow_mut(&self) -> Result<AccessMut<'a, T>, Error> {
+        AccessMut::new(self.buffer, self.slice.clone())
+    }
+
+    /// Returns the length of this half of the buffer
+    pub fn len(&self) -> usize {
+        self.slice.len()
+    }
+
+    /// Returns true if this half of the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// Guard struct for mutable buffer access
-pub struct AccessMut<'a, T>(&'a Buffer<'a, T>);
+pub struct AccessMut<'a, T> {
+    buffer: &'a Buffer<'a, T>,
+    slice: Range<usize>,
+}
 
 impl<'a, T> AccessMut<'a, T> {
-    fn new(buffer: &'a Buffer<'a, T>) -> Result<Self, Error> {
-        buffer.borrow(true)?;
-        Ok(Self(buffer))
+    fn new(buffer: &'a Buffer<'a, T>, slice: Range<usize>) -> Result<Self, Error> {
+        buffer.borrow(&slice, true)?;
+        Ok(Self { buffer, slice })
     }
 }
 
-// SAFETY: Access to the buffer is dynamically checked
+// SAFETY: Access to the buffer is dynamically checked, and the slice is always within the buffer
 impl<T> Borrow<[T]> for AccessMut<'_, T> {
     fn borrow(&self) -> &[T] {
-        unsafe { core::slice::from_raw_parts(self.0.buffer.load(core::sync::atomic::Ordering::Acquire), self.0.len) }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.buffer
+                    .buffer
+                    .load(core::sync::atomic::Ordering::Acquire)
+                    .add(self.slice.start),
+                self.slice.len(),
+            )
+        }
     }
 }
 
-// SAFETY: Access to the buffer is dynamically checked
+// SAFETY: Access to the buffer is dynamically checked, and the slice is always within the buffer
 impl<T> BorrowMut<[T]> for AccessMut<'_, T> {
     fn borrow_mut(&mut self) -> &mut [T] {
         unsafe {
-            core::slice::from_raw_parts_mut(self.0.buffer.load(core::sync::atomic::Ordering::Acquire), self.0.len)
+            core::slice::from_raw_parts_mut(
+                self.buffer
+                    .buffer
+                    .load(core::sync::atomic::Ordering::Acquire)
+                    .add(self.slice.start),
+                self.slice.len(),
+            )
         }
     }
 }
 
 impl<T> Drop for AccessMut<'_, T> {
     fn drop(&mut self) {
-        self.0.drop_borrow();
+        self.buffer.drop_borrow(&self.slice);
     }
 }
 
@@ -248,7 +374,7 @@ impl<'a, T> Access<'a, T> {
         if slice.start >= buffer.len() || slice.end > buffer.len() {
             Err(Error::InvalidRange)
         } else {
-            buffer.borrow(false)?;
+            buffer.borrow(&slice, false)?;
             Ok(Self { buffer, slice })
         }
     }
@@ -273,7 +399,7 @@ impl<T> Borrow<[T]> for Access<'_, T> {
 
 impl<T> Drop for Access<'_, T> {
     fn drop(&mut self) {
-        self.buffer.drop_borrow();
+        self.buffer.drop_borrow(&self.slice);
     }
 }
 
@@ -403,6 +529,45 @@ mod test {
         assert_eq!(sliced.borrow(), [3, 4, 5, 6]);
     }
 
+    // Verify that the halves of a split buffer can be borrowed mutably at the same time
+    #[test]
+    fn test_split() {
+        define_static_buffer!(buffer, u8, [0; 8]);
+        let buffer = buffer::get_mut().unwrap();
+        let full = buffer.reference();
+        let (rx, tx) = buffer.split_at(4).ok().unwrap();
+
+        let mut rx_access = rx.borrow_mut().unwrap();
+        let mut tx_access = tx.borrow_mut().unwrap();
+        BorrowMut::<[u8]>::borrow_mut(&mut rx_access).copy_from_slice(&[1, 2, 3, 4]);
+        BorrowMut::<[u8]>::borrow_mut(&mut tx_access).copy_from_slice(&[5, 6, 7, 8]);
+
+        // Accesses overlapping a mutably borrowed half are rejected
+        assert_eq!(rx.borrow().err(), Some(Error::BorrowedMutably));
+        assert_eq!(full.slice(2..6).unwrap().borrow().err(), Some(Error::BorrowedMutably));
+        drop(rx_access);
+        assert_eq!(full.slice(2..6).unwrap().borrow().err(), Some(Error::BorrowedMutably));
+        assert_eq!(full.slice(0..4).unwrap().borrow().unwrap().borrow(), [1, 2, 3, 4]);
+        drop(tx_access);
+
+        let spanning = full.slice(2..6).unwrap();
+        let spanning = spanning.borrow().unwrap();
+        assert_eq!(spanning.borrow(), [3, 4, 5, 6]);
+        assert_eq!(tx.borrow_mut().err(), Some(Error::BorrowedImmutably));
+    }
+
+    // Verify that a borrowed buffer can't be split
+    #[test]
+    fn test_split_borrowed_fail() {
+        define_static_buffer!(buffer, u8, [0; 8]);
+        let buffer = buffer::get_mut().unwrap();
+        let reference = buffer.reference();
+        let access = reference.borrow().unwrap();
+        let buffer = buffer.split_at(4).err().unwrap();
+        drop(access);
+        assert!(buffer.split_at(8).is_err());
+    }
+
     // Test slice starting index out of bounds
     #[test]
     #[should_panic]
@@ -0,0 +1,60 @@
+//! Host-visible EC capabilities
+//!
+//! Services publish the capability fields they own here, and the platform reports the combined [`Capabilities`] to
+//! the host, e.g. through the system relay. Every change is signalled through [`wait_changed`] so the platform can
+//! notify the host that the capabilities must be read again.
+
+use embassy_sync::signal::Signal;
+
+use crate::{AtomicU32, GlobalRawMutex, Ordering};
+
+/// Capabilities reported to the host
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Capabilities {
+    /// Platform security state, bit layout defined by the platform service
+    pub secure_state: u32,
+}
+
+static SECURE_STATE: AtomicU32 = AtomicU32::new(0);
+static CHANGED: Signal<GlobalRawMutex, ()> = Signal::new();
+
+fn update(field: &AtomicU32, value: u32) {
+    if field.swap(value, Ordering::Relaxed) != value {
+        CHANGED.signal(());
+    }
+}
+
+/// Set the platform security state
+pub fn set_secure_state(secure_state: u32) {
+    update(&SECURE_STATE, secure_state);
+}
+
+/// Returns the current capabilities
+pub fn get() -> Capabilities {
+    Capabilities {
+        secure_state: SECURE_STATE.load(Ordering::Relaxed),
+    }
+}
+
+/// Wait for the capabilities to change, returns the new capabilities
+pub async fn wait_changed() -> Capabilities {
+    CHANGED.wait().await;
+    get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_change_signalled() {
+        set_secure_state(0x5);
+        assert_eq!(wait_changed().await.secure_state, 0x5);
+
+        // Setting the same value again isn't a change
+        set_secure_state(0x5);
+        assert!(!CHANGED.signaled());
+        assert_eq!(get().secure_state, 0x5);
+    }
+}
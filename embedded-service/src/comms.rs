@@ -4,11 +4,13 @@ use core::any::{Any, TypeId};
 use core::convert::Infallible;
 
 use embassy_sync::once_lock::OnceLock;
+use embassy_time::Instant;
 use serde::{Deserialize, Serialize};
 
 use crate::IntrusiveList;
 use crate::SyncCell;
 use crate::intrusive_list::{self, Node, NodeContainer};
+use crate::{AtomicU32, Ordering};
 
 /// key type for OEM Endpoint declarations
 pub type OemKey = isize;
@@ -94,6 +96,20 @@ impl From<External> for EndpointID {
     }
 }
 
+/// Message delivery priority
+///
+/// High priority is reserved for safety-critical messages, e.g. a thermal critical shutdown or a critically low
+/// battery, that must not wait behind routine traffic in the receiver's queue.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Priority {
+    /// Routine traffic
+    #[default]
+    Normal,
+    /// Safety-critical traffic, delivered through [`MailboxDelegate::receive_priority`]
+    High,
+}
+
 /// Data reference -- generalized such that any stack variable can be transmitted "in place" as needed
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -116,13 +132,15 @@ impl<'a> Data<'a> {
     /// Ex:
     /// ```
     /// # use core::any::TypeId;
-    /// # use embedded_services::comms::{Data, Message, EndpointID, Internal};
+    /// # use embedded_services::comms::{Data, Message, EndpointID, Internal, Metadata};
     /// struct MessageClassA;
     /// struct MessageClassB;
     /// let message = Message {
     ///     from: EndpointID::from(Internal::PlatformInfo),
     ///     to: EndpointID::from(Internal::PlatformInfo),
     ///     data: Data::new(&MessageClassA),
+    ///     priority: Default::default(),
+    ///     metadata: Metadata::next(),
     /// };
     /// if message.data.type_id() == TypeId::of::<MessageClassA>() {
     ///     // do something
@@ -145,6 +163,33 @@ impl<'a> Data<'a> {
     }
 }
 
+/// Message metadata stamped when a message is sent
+///
+/// Sequence numbers are global across all endpoints, so debug tools can order and correlate events from different
+/// services. A receiver only sees the messages addressed to it, so gaps in its sequence numbers don't indicate dropped
+/// messages.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Metadata {
+    /// time the message was sent
+    pub timestamp: Instant,
+
+    /// send order of the message, wraps on overflow
+    pub sequence: u32,
+}
+
+impl Metadata {
+    /// Stamp a message being sent now with the next sequence number
+    pub fn next() -> Self {
+        static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+        Self {
+            timestamp: Instant::now(),
+            sequence: SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
 /// Message to receive
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -157,6 +202,12 @@ pub struct Message<'a> {
 
     /// message content
     pub data: Data<'a>,
+
+    /// delivery priority
+    pub priority: Priority,
+
+    /// send timestamp and sequence number
+    pub metadata: Metadata,
 }
 
 /// Trait to receive messages
@@ -165,6 +216,14 @@ pub trait MailboxDelegate {
     fn receive(&self, _message: &Message) -> Result<(), MailboxDelegateError> {
         Ok(())
     }
+
+    /// Receive a [`Priority::High`] Message
+    ///
+    /// Delegates that queue messages should override this to act on the message immediately, or at least place it
+    /// ahead of routine traffic. Defaults to [`MailboxDelegate::receive`].
+    fn receive_priority(&self, message: &Message) -> Result<(), MailboxDelegateError> {
+        self.receive(message)
+    }
 }
 
 /// Message transmission Error
@@ -225,6 +284,16 @@ impl Endpoint {
         send(self.id, to, data).await
     }
 
+    /// Send a generic message to an endpoint with the given priority
+    pub async fn send_with_priority(
+        &self,
+        to: EndpointID,
+        data: &(impl Any + Send + Sync),
+        priority: Priority,
+    ) -> Result<(), Infallible> {
+        send_with_priority(self.id, to, data, priority).await
+    }
+
     fn init(&self, rx: &'static dyn MailboxDelegate) {
         self.delegator.set(Some(rx));
     }
@@ -232,7 +301,10 @@ impl Endpoint {
     fn process(&self, message: &Message) {
         if let Some(delegator) = self.delegator.get() {
             // REVISIT: Continue to propagate error
-            let _res = delegator.receive(message);
+            let _res = match message.priority {
+                Priority::Normal => delegator.receive(message),
+                Priority::High => delegator.receive_priority(message),
+            };
         }
     }
 }
@@ -300,10 +372,22 @@ fn get_list(target: EndpointID) -> &'static OnceLock<IntrusiveList> {
 
 /// Send a generic message to an endpoint
 pub async fn send(from: EndpointID, to: EndpointID, data: &(impl Any + Send + Sync)) -> Result<(), Infallible> {
+    send_with_priority(from, to, data, Priority::Normal).await
+}
+
+/// Send a generic message to an endpoint with the given priority
+pub async fn send_with_priority(
+    from: EndpointID,
+    to: EndpointID,
+    data: &(impl Any + Send + Sync),
+    priority: Priority,
+) -> Result<(), Infallible> {
     route(Message {
         from,
         to,
         data: Data::new(data),
+        priority,
+        metadata: Metadata::next(),
     })
     .await
 }
@@ -344,3 +428,64 @@ pub(crate) fn init() {
     get_list(External::Host.into()).get_or_init(IntrusiveList::new);
     get_list(External::Oem(0).into()).get_or_init(IntrusiveList::new);
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use embassy_sync::channel::Channel;
+
+    use super::*;
+    use crate::GlobalRawMutex;
+
+    /// Records the priority of the receive method each message was delivered through
+    struct PriorityRecorder {
+        tp: Endpoint,
+        received: Channel<GlobalRawMutex, Priority, 2>,
+    }
+
+    impl MailboxDelegate for PriorityRecorder {
+        fn receive(&self, _message: &Message) -> Result<(), MailboxDelegateError> {
+            self.received
+                .try_send(Priority::Normal)
+                .map_err(|_| MailboxDelegateError::BufferFull)
+        }
+
+        fn receive_priority(&self, _message: &Message) -> Result<(), MailboxDelegateError> {
+            self.received
+                .try_send(Priority::High)
+                .map_err(|_| MailboxDelegateError::BufferFull)
+        }
+    }
+
+    /// OEM key not used by any other test
+    const RECORDER_ID: EndpointID = EndpointID::Internal(Internal::Oem(0x3926));
+
+    static RECORDER: PriorityRecorder = PriorityRecorder {
+        tp: Endpoint::uninit(RECORDER_ID),
+        received: Channel::new(),
+    };
+
+    #[tokio::test]
+    async fn high_priority_reaches_receive_priority() {
+        init();
+        register_endpoint(&RECORDER, &RECORDER.tp).await.unwrap();
+
+        send(RECORDER_ID, RECORDER_ID, &()).await.unwrap();
+        send_with_priority(RECORDER_ID, RECORDER_ID, &(), Priority::High)
+            .await
+            .unwrap();
+
+        assert_eq!(RECORDER.received.try_receive(), Ok(Priority::Normal));
+        assert_eq!(RECORDER.received.try_receive(), Ok(Priority::High));
+    }
+
+    #[test]
+    fn metadata_is_ordered() {
+        let first = Metadata::next();
+        let second = Metadata::next();
+
+        // Other tests may send messages in between
+        assert!(second.sequence.wrapping_sub(first.sequence) as i32 > 0);
+        assert!(second.timestamp >= first.timestamp);
+    }
+}
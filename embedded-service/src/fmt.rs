@@ -1,5 +1,7 @@
 //! Logging macro implementations and other formating functions
 
+pub mod filter;
+
 // In no_std (embedded) targets, defmt and log are mutually exclusive to avoid double logging
 #[cfg(all(feature = "log", feature = "defmt", target_os = "none", not(doc)))]
 compile_error!("features `log` and `defmt` are mutually exclusive on no_std targets");
@@ -11,50 +13,60 @@ mod defmt_and_log {
     #[macro_export]
     #[collapse_debuginfo(yes)]
     macro_rules! trace {
-        ($s:literal $(, $x:expr)* $(,)?) => {{
-            ::defmt::trace!($s $(, $x)*);
-            ::log::trace!($s $(, $x)*);
-        }};
+        ($s:literal $(, $x:expr)* $(,)?) => {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Trace) {
+                ::defmt::trace!($s $(, $x)*);
+                ::log::trace!($s $(, $x)*);
+            }
+        };
     }
 
     /// Logs a debug message using both defmt and log
     #[macro_export]
     #[collapse_debuginfo(yes)]
     macro_rules! debug {
-        ($s:literal $(, $x:expr)* $(,)?) => {{
-            ::defmt::debug!($s $(, $x)*);
-            ::log::debug!($s $(, $x)*);
-        }};
+        ($s:literal $(, $x:expr)* $(,)?) => {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Debug) {
+                ::defmt::debug!($s $(, $x)*);
+                ::log::debug!($s $(, $x)*);
+            }
+        };
     }
 
     /// Logs an info message using both defmt and log
     #[macro_export]
     #[collapse_debuginfo(yes)]
     macro_rules! info {
-        ($s:literal $(, $x:expr)* $(,)?) => {{
-            ::defmt::info!($s $(, $x)*);
-            ::log::info!($s $(, $x)*);
-        }};
+        ($s:literal $(, $x:expr)* $(,)?) => {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Info) {
+                ::defmt::info!($s $(, $x)*);
+                ::log::info!($s $(, $x)*);
+            }
+        };
     }
 
     /// Logs a warning using both defmt and log
     #[macro_export]
     #[collapse_debuginfo(yes)]
     macro_rules! warn {
-        ($s:literal $(, $x:expr)* $(,)?) => {{
-            ::defmt::warn!($s $(, $x)*);
-            ::log::warn!($s $(, $x)*);
-        }};
+        ($s:literal $(, $x:expr)* $(,)?) => {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Warn) {
+                ::defmt::warn!($s $(, $x)*);
+                ::log::warn!($s $(, $x)*);
+            }
+        };
     }
 
     /// Logs an error using both defmt and log
     #[macro_export]
     #[collapse_debuginfo(yes)]
     macro_rules! error {
-        ($s:literal $(, $x:expr)* $(,)?) => {{
-            ::defmt::error!($s $(, $x)*);
-            ::log::error!($s $(, $x)*);
-        }};
+        ($s:literal $(, $x:expr)* $(,)?) => {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Error) {
+                ::defmt::error!($s $(, $x)*);
+                ::log::error!($s $(, $x)*);
+            }
+        };
     }
 }
 
@@ -65,7 +77,7 @@ mod defmt {
     #[collapse_debuginfo(yes)]
     macro_rules! trace {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Trace) {
                 let _ = $s;
                 ::defmt::trace!($s $(, $x)*);
             }
@@ -77,7 +89,7 @@ mod defmt {
     #[collapse_debuginfo(yes)]
     macro_rules! debug {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Debug) {
                 let _ = $s;
                 ::defmt::debug!($s $(, $x)*);
             }
@@ -89,7 +101,7 @@ mod defmt {
     #[collapse_debuginfo(yes)]
     macro_rules! info {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Info) {
                 let _ = $s;
                 ::defmt::info!($s $(, $x)*);
             }
@@ -101,7 +113,7 @@ mod defmt {
     #[collapse_debuginfo(yes)]
     macro_rules! warn {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Warn) {
                 let _ = $s;
                 ::defmt::warn!($s $(, $x)*);
             }
@@ -113,7 +125,7 @@ mod defmt {
     #[collapse_debuginfo(yes)]
     macro_rules! error {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Error) {
                 let _ = $s;
                 ::defmt::error!($s $(, $x)*);
             }
@@ -128,7 +140,7 @@ mod log {
     #[collapse_debuginfo(yes)]
     macro_rules! trace {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Trace) {
                 ::log::trace!($s $(, $x)*);
             }
         };
@@ -139,7 +151,7 @@ mod log {
     #[collapse_debuginfo(yes)]
     macro_rules! debug {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Debug) {
                 ::log::debug!($s $(, $x)*);
             }
         };
@@ -150,7 +162,7 @@ mod log {
     #[collapse_debuginfo(yes)]
     macro_rules! info {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Info) {
                 ::log::info!($s $(, $x)*);
             }
         };
@@ -161,7 +173,7 @@ mod log {
     #[collapse_debuginfo(yes)]
     macro_rules! warn {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Warn) {
                 ::log::warn!($s $(, $x)*);
             }
         };
@@ -172,7 +184,7 @@ mod log {
     #[collapse_debuginfo(yes)]
     macro_rules! error {
         ($s:literal $(, $x:expr)* $(,)?) => {
-            {
+            if $crate::fmt::filter::enabled(module_path!(), $crate::fmt::filter::Level::Error) {
                 ::log::error!($s $(, $x)*);
             }
         };
@@ -0,0 +1,262 @@
+//! Runtime log level filtering
+//!
+//! The logging macros check [`enabled`] before emitting a message, so the verbosity of individual modules can be
+//! changed at runtime, e.g. through a debug service command, without recompiling. Filters are keyed by module path
+//! prefix: a filter on `type_c_service::wrapper` applies to that module and everything below it, and the longest
+//! matching filter wins. Modules without a matching filter use the default level.
+//!
+//! Runtime filtering can only further restrict logging, messages removed at compile time (e.g. through `DEFMT_LOG`)
+//! stay removed.
+use core::sync::atomic::{AtomicU8, fence};
+
+use embassy_sync::blocking_mutex::Mutex;
+
+use crate::{AtomicUsize, GlobalRawMutex, Ordering};
+
+/// Maximum number of module filters
+pub const MAX_FILTERS: usize = 8;
+
+/// Maximum length of a module path in a filter
+pub const MAX_MODULE_LEN: usize = 48;
+
+/// Log level, ordered from least to most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Level {
+    /// No messages
+    Off = 0,
+    /// Errors only
+    Error = 1,
+    /// Warnings and above
+    Warn = 2,
+    /// Info and above
+    Info = 3,
+    /// Debug and above
+    Debug = 4,
+    /// All messages
+    Trace = 5,
+}
+
+impl TryFrom<u8> for Level {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Level::Off),
+            1 => Ok(Level::Error),
+            2 => Ok(Level::Warn),
+            3 => Ok(Level::Info),
+            4 => Ok(Level::Debug),
+            5 => Ok(Level::Trace),
+            other => Err(other),
+        }
+    }
+}
+
+/// Filter errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FilterError {
+    /// The module path is longer than [`MAX_MODULE_LEN`]
+    ModuleTooLong,
+    /// All [`MAX_FILTERS`] filters are in use
+    Full,
+}
+
+/// Level stored in unused filter slots
+const UNUSED: u8 = u8::MAX;
+
+/// A module filter, stored in atomics so [`enabled`] can read it without taking a lock
+struct Slot {
+    /// [`Level`] of the filter, or [`UNUSED`]
+    level: AtomicU8,
+    len: AtomicU8,
+    module: [AtomicU8; MAX_MODULE_LEN],
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            level: AtomicU8::new(UNUSED),
+            len: AtomicU8::new(0),
+            module: [const { AtomicU8::new(0) }; MAX_MODULE_LEN],
+        }
+    }
+
+    fn is_used(&self) -> bool {
+        self.level.load(Ordering::Relaxed) != UNUSED
+    }
+
+    fn starts(&self, module: &[u8]) -> bool {
+        self.module
+            .iter()
+            .zip(module)
+            .all(|(stored, byte)| stored.load(Ordering::Relaxed) == *byte)
+    }
+
+    /// Returns true if the filter is for exactly `module`
+    fn is_module(&self, module: &[u8]) -> bool {
+        self.is_used() && usize::from(self.len.load(Ordering::Relaxed)) == module.len() && self.starts(module)
+    }
+
+    /// Returns the length of the filter's module path if it matches `module`
+    fn matches(&self, module: &[u8]) -> Option<usize> {
+        let len = usize::from(self.len.load(Ordering::Relaxed));
+        let (prefix, rest) = module.split_at_checked(len)?;
+        (self.is_used() && self.starts(prefix) && (rest.is_empty() || rest.starts_with(b"::"))).then_some(len)
+    }
+
+    /// Store a filter, `module` must be at most [`MAX_MODULE_LEN`] bytes
+    fn store(&self, module: &[u8], level: Level) {
+        for (stored, byte) in self.module.iter().zip(module) {
+            stored.store(*byte, Ordering::Relaxed);
+        }
+        self.len.store(module.len() as u8, Ordering::Relaxed);
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+}
+
+static DEFAULT: AtomicU8 = AtomicU8::new(Level::Trace as u8);
+static SLOTS: [Slot; MAX_FILTERS] = [const { Slot::new() }; MAX_FILTERS];
+
+/// Incremented before and after every update, odd while an update is in progress
+///
+/// Log calls only read the filters, they check the version hasn't changed instead of locking out updates.
+static VERSION: AtomicUsize = AtomicUsize::new(0);
+
+/// Serializes updates
+static UPDATE: Mutex<GlobalRawMutex, ()> = Mutex::new(());
+
+/// Number of module filters in use plus one if the default level was changed, lets the common case skip the filters
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Apply an update to the filters
+fn update<R>(f: impl FnOnce() -> R) -> R {
+    UPDATE.lock(|_| {
+        let version = VERSION.load(Ordering::Relaxed);
+        VERSION.store(version.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        let result = f();
+
+        let count = SLOTS.iter().filter(|slot| slot.is_used()).count()
+            + usize::from(DEFAULT.load(Ordering::Relaxed) != Level::Trace as u8);
+        ACTIVE.store(count, Ordering::Relaxed);
+        VERSION.store(version.wrapping_add(2), Ordering::Release);
+        result
+    })
+}
+
+/// Set the level used by modules without a matching filter
+pub fn set_default_level(level: Level) {
+    update(|| DEFAULT.store(level as u8, Ordering::Relaxed));
+}
+
+/// Set the level of `module` and the modules below it, replacing any existing filter for the same module
+pub fn set_level(module: &str, level: Level) -> Result<(), FilterError> {
+    let module = module.as_bytes();
+    if module.len() > MAX_MODULE_LEN {
+        return Err(FilterError::ModuleTooLong);
+    }
+
+    update(|| {
+        let slot = SLOTS
+            .iter()
+            .find(|slot| slot.is_module(module))
+            .or_else(|| SLOTS.iter().find(|slot| !slot.is_used()))
+            .ok_or(FilterError::Full)?;
+        slot.store(module, level);
+        Ok(())
+    })
+}
+
+/// Remove the filter for `module`, if any
+pub fn clear_level(module: &str) {
+    update(|| {
+        for slot in SLOTS.iter().filter(|slot| slot.is_module(module.as_bytes())) {
+            slot.level.store(UNUSED, Ordering::Relaxed);
+        }
+    });
+}
+
+/// Returns true if a message at `level` from `module` should be logged
+///
+/// This never blocks, so it can be called from any context. A message logged while the filters are being updated is
+/// let through rather than waiting for the update to complete.
+pub fn enabled(module: &str, level: Level) -> bool {
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return true;
+    }
+
+    let version = VERSION.load(Ordering::Acquire);
+    if !version.is_multiple_of(2) {
+        return true;
+    }
+
+    let max_level = SLOTS
+        .iter()
+        .filter_map(|slot| {
+            let level = slot.level.load(Ordering::Relaxed);
+            slot.matches(module.as_bytes()).map(|len| (len, level))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map_or_else(|| DEFAULT.load(Ordering::Relaxed), |(_, level)| level);
+
+    fence(Ordering::Acquire);
+    if VERSION.load(Ordering::Relaxed) != version {
+        return true;
+    }
+    level as u8 <= max_level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Filters are global, so everything runs in a single test
+    #[test]
+    fn module_filters() {
+        assert!(enabled("type_c_service::wrapper", Level::Trace));
+
+        set_default_level(Level::Info);
+        assert!(!enabled("type_c_service::wrapper", Level::Debug));
+        assert!(enabled("type_c_service::wrapper", Level::Info));
+
+        // Longest prefix wins, and only whole path segments match
+        assert_eq!(set_level("type_c_service", Level::Warn), Ok(()));
+        assert_eq!(set_level("type_c_service::wrapper", Level::Trace), Ok(()));
+        assert!(enabled("type_c_service::wrapper::pd", Level::Trace));
+        assert!(!enabled("type_c_service::service", Level::Info));
+        assert!(!enabled("type_c_service_relay", Level::Debug));
+        assert!(enabled("type_c_service_relay", Level::Info));
+
+        clear_level("type_c_service::wrapper");
+        assert!(!enabled("type_c_service::wrapper", Level::Info));
+
+        assert_eq!(
+            set_level(
+                core::str::from_utf8(&[b'a'; MAX_MODULE_LEN + 1]).unwrap_or(""),
+                Level::Off
+            ),
+            Err(FilterError::ModuleTooLong)
+        );
+
+        // An update in progress lets messages through instead of blocking
+        let version = VERSION.load(Ordering::Relaxed);
+        VERSION.store(version.wrapping_add(1), Ordering::Relaxed);
+        assert!(enabled("type_c_service::service", Level::Trace));
+        VERSION.store(version, Ordering::Relaxed);
+        assert!(!enabled("type_c_service::service", Level::Trace));
+
+        // Replacing a filter doesn't use another slot
+        for _ in 0..MAX_FILTERS {
+            assert_eq!(set_level("type_c_service", Level::Error), Ok(()));
+        }
+        assert!(!enabled("type_c_service::service", Level::Warn));
+
+        clear_level("type_c_service");
+        set_default_level(Level::Trace);
+        assert!(enabled("type_c_service::service", Level::Trace));
+    }
+}
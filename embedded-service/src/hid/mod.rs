@@ -10,6 +10,8 @@ use crate::{GlobalRawMutex, IntrusiveList, Node, NodeContainer, error, intrusive
 
 mod command;
 pub use command::*;
+mod report_descriptor;
+pub use report_descriptor::*;
 
 /// HID descriptor length
 pub const DESCRIPTOR_LEN: usize = 30;
@@ -172,6 +174,21 @@ impl Default for RegisterFile {
     }
 }
 
+impl RegisterFile {
+    /// Returns true if the given address is one of the registers in this file
+    pub fn contains(&self, reg: u16) -> bool {
+        [
+            self.hid_desc_reg,
+            self.report_desc_reg,
+            self.input_reg,
+            self.output_reg,
+            self.command_reg,
+            self.data_reg,
+        ]
+        .contains(&reg)
+    }
+}
+
 /// HID device that responds to HID requests
 pub struct Device {
     node: Node,
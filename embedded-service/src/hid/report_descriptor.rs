@@ -0,0 +1,397 @@
+//! Const report descriptor builder
+//!
+//! Report descriptors are composed of short items, see section 6.2.2 of the HID spec. The builder encodes each
+//! item with the smallest data size that fits its value and validates the descriptor as it is built. Since
+//! every method is `const`, invalid descriptors are rejected at compile time when used with
+//! [`hid_report_descriptor!`](crate::hid_report_descriptor).
+
+/// Maximum length of a report descriptor produced by [`ReportDescriptorBuilder`]
+pub const REPORT_DESCRIPTOR_MAX_LEN: usize = 256;
+
+/// Short item types
+const ITEM_TYPE_MAIN: u8 = 0;
+const ITEM_TYPE_GLOBAL: u8 = 1;
+const ITEM_TYPE_LOCAL: u8 = 2;
+
+/// Main item tags
+const TAG_INPUT: u8 = 0x8;
+const TAG_OUTPUT: u8 = 0x9;
+const TAG_COLLECTION: u8 = 0xA;
+const TAG_FEATURE: u8 = 0xB;
+const TAG_END_COLLECTION: u8 = 0xC;
+
+/// Global item tags
+const TAG_USAGE_PAGE: u8 = 0x0;
+const TAG_LOGICAL_MINIMUM: u8 = 0x1;
+const TAG_LOGICAL_MAXIMUM: u8 = 0x2;
+const TAG_REPORT_SIZE: u8 = 0x7;
+const TAG_REPORT_ID: u8 = 0x8;
+const TAG_REPORT_COUNT: u8 = 0x9;
+
+/// Local item tags
+const TAG_USAGE: u8 = 0x0;
+const TAG_USAGE_MINIMUM: u8 = 0x1;
+const TAG_USAGE_MAXIMUM: u8 = 0x2;
+
+/// Collection type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Collection {
+    /// Physical collection
+    Physical = 0x00,
+    /// Application collection
+    Application = 0x01,
+    /// Logical collection
+    Logical = 0x02,
+    /// Report collection
+    Report = 0x03,
+    /// Named array collection
+    NamedArray = 0x04,
+    /// Usage switch collection
+    UsageSwitch = 0x05,
+    /// Usage modifier collection
+    UsageModifier = 0x06,
+}
+
+/// Flags for input, output, and feature items
+///
+/// The default (all bits clear) is Data, Array, Absolute.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MainItemFlags(u8);
+
+impl MainItemFlags {
+    /// Data, Array, Absolute
+    pub const DATA: Self = Self(0);
+    /// Constant instead of data
+    pub const CONSTANT: Self = Self(1 << 0);
+    /// Variable instead of array
+    pub const VARIABLE: Self = Self(1 << 1);
+    /// Relative instead of absolute
+    pub const RELATIVE: Self = Self(1 << 2);
+    /// Wraps around instead of stopping at the limits
+    pub const WRAP: Self = Self(1 << 3);
+    /// Non-linear
+    pub const NON_LINEAR: Self = Self(1 << 4);
+    /// No preferred state
+    pub const NO_PREFERRED: Self = Self(1 << 5);
+    /// Has a null state
+    pub const NULL_STATE: Self = Self(1 << 6);
+    /// Volatile, only valid for output and feature items
+    pub const VOLATILE: Self = Self(1 << 7);
+
+    /// Returns the union of self and other
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Returns the raw flags
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+/// Const report descriptor builder
+///
+/// Panics during const evaluation if the descriptor exceeds [`REPORT_DESCRIPTOR_MAX_LEN`], collections are
+/// unbalanced, or a data item is added before the report size and count are set.
+#[derive(Clone, Copy, Debug)]
+pub struct ReportDescriptorBuilder {
+    buf: [u8; REPORT_DESCRIPTOR_MAX_LEN],
+    len: usize,
+    /// Number of open collections
+    depth: usize,
+    /// Current report size in bits
+    report_size: u32,
+    /// Current report count
+    report_count: u32,
+}
+
+impl Default for ReportDescriptorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReportDescriptorBuilder {
+    /// Create a new empty descriptor
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; REPORT_DESCRIPTOR_MAX_LEN],
+            len: 0,
+            depth: 0,
+            report_size: 0,
+            report_count: 0,
+        }
+    }
+
+    /// Append a single byte
+    const fn push(mut self, byte: u8) -> Self {
+        assert!(self.len < REPORT_DESCRIPTOR_MAX_LEN, "Report descriptor too long");
+        if let Some((slot, _)) = self.buf.split_at_mut(self.len).1.split_first_mut() {
+            *slot = byte;
+        }
+        self.len += 1;
+        self
+    }
+
+    /// Append a short item with `size` bytes of little-endian data
+    const fn item(mut self, item_type: u8, tag: u8, data: u32, size: usize) -> Self {
+        let size_code = match size {
+            0 => 0,
+            1 => 1,
+            2 => 2,
+            _ => 3,
+        };
+        self = self.push((tag << 4) | (item_type << 2) | size_code);
+
+        let [b0, b1, b2, b3] = data.to_le_bytes();
+        if size >= 1 {
+            self = self.push(b0);
+        }
+        if size >= 2 {
+            self = self.push(b1);
+        }
+        if size >= 4 {
+            self = self.push(b2).push(b3);
+        }
+        self
+    }
+
+    /// Append an item with unsigned data encoded in the fewest bytes
+    const fn unsigned_item(self, item_type: u8, tag: u8, data: u32) -> Self {
+        let size = if data <= u8::MAX as u32 {
+            1
+        } else if data <= u16::MAX as u32 {
+            2
+        } else {
+            4
+        };
+        self.item(item_type, tag, data, size)
+    }
+
+    /// Append an item with signed data encoded in the fewest bytes
+    const fn signed_item(self, item_type: u8, tag: u8, data: i32) -> Self {
+        let size = if data >= i8::MIN as i32 && data <= i8::MAX as i32 {
+            1
+        } else if data >= i16::MIN as i32 && data <= i16::MAX as i32 {
+            2
+        } else {
+            4
+        };
+        self.item(item_type, tag, data as u32, size)
+    }
+
+    /// Append an input, output, or feature item
+    const fn data_item(self, tag: u8, flags: MainItemFlags) -> Self {
+        assert!(self.report_size > 0, "Report size must be set before data items");
+        assert!(self.report_count > 0, "Report count must be set before data items");
+        self.item(ITEM_TYPE_MAIN, tag, flags.0 as u32, 1)
+    }
+
+    /// Usage page
+    pub const fn usage_page(self, page: u16) -> Self {
+        self.unsigned_item(ITEM_TYPE_GLOBAL, TAG_USAGE_PAGE, page as u32)
+    }
+
+    /// Usage
+    pub const fn usage(self, usage: u16) -> Self {
+        self.unsigned_item(ITEM_TYPE_LOCAL, TAG_USAGE, usage as u32)
+    }
+
+    /// Usage minimum
+    pub const fn usage_minimum(self, usage: u16) -> Self {
+        self.unsigned_item(ITEM_TYPE_LOCAL, TAG_USAGE_MINIMUM, usage as u32)
+    }
+
+    /// Usage maximum
+    pub const fn usage_maximum(self, usage: u16) -> Self {
+        self.unsigned_item(ITEM_TYPE_LOCAL, TAG_USAGE_MAXIMUM, usage as u32)
+    }
+
+    /// Logical minimum
+    pub const fn logical_minimum(self, value: i32) -> Self {
+        self.signed_item(ITEM_TYPE_GLOBAL, TAG_LOGICAL_MINIMUM, value)
+    }
+
+    /// Logical maximum
+    pub const fn logical_maximum(self, value: i32) -> Self {
+        self.signed_item(ITEM_TYPE_GLOBAL, TAG_LOGICAL_MAXIMUM, value)
+    }
+
+    /// Report ID, must be non-zero
+    pub const fn report_id(self, id: u8) -> Self {
+        assert!(id != 0, "Report ID must be non-zero");
+        self.unsigned_item(ITEM_TYPE_GLOBAL, TAG_REPORT_ID, id as u32)
+    }
+
+    /// Report size in bits
+    pub const fn report_size(mut self, bits: u32) -> Self {
+        self.report_size = bits;
+        self.unsigned_item(ITEM_TYPE_GLOBAL, TAG_REPORT_SIZE, bits)
+    }
+
+    /// Report count
+    pub const fn report_count(mut self, count: u32) -> Self {
+        self.report_count = count;
+        self.unsigned_item(ITEM_TYPE_GLOBAL, TAG_REPORT_COUNT, count)
+    }
+
+    /// Begin a collection
+    pub const fn collection(mut self, collection: Collection) -> Self {
+        self.depth += 1;
+        self.item(ITEM_TYPE_MAIN, TAG_COLLECTION, collection as u32, 1)
+    }
+
+    /// End the current collection
+    pub const fn end_collection(mut self) -> Self {
+        assert!(self.depth > 0, "End collection without an open collection");
+        self.depth -= 1;
+        self.item(ITEM_TYPE_MAIN, TAG_END_COLLECTION, 0, 0)
+    }
+
+    /// Input item
+    pub const fn input(self, flags: MainItemFlags) -> Self {
+        self.data_item(TAG_INPUT, flags)
+    }
+
+    /// Output item
+    pub const fn output(self, flags: MainItemFlags) -> Self {
+        self.data_item(TAG_OUTPUT, flags)
+    }
+
+    /// Feature item
+    pub const fn feature(self, flags: MainItemFlags) -> Self {
+        self.data_item(TAG_FEATURE, flags)
+    }
+
+    /// Length of the encoded descriptor
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no items have been added
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the encoded descriptor, validating that all collections are closed
+    pub const fn finish<const N: usize>(&self) -> [u8; N] {
+        assert!(self.depth == 0, "Report descriptor has unclosed collections");
+        assert!(N == self.len, "Report descriptor length mismatch");
+
+        match self.buf.first_chunk::<N>() {
+            Some(descriptor) => *descriptor,
+            None => [0; N],
+        }
+    }
+}
+
+/// Build a `&'static [u8]` report descriptor from a [`ReportDescriptorBuilder`] expression at compile time
+///
+/// ```
+/// use embedded_services::hid::{Collection, MainItemFlags, ReportDescriptorBuilder};
+///
+/// const DESCRIPTOR: &[u8] = embedded_services::hid_report_descriptor!(
+///     ReportDescriptorBuilder::new()
+///         .usage_page(0x01)
+///         .usage(0x06)
+///         .collection(Collection::Application)
+///         .report_size(1)
+///         .report_count(8)
+///         .input(MainItemFlags::VARIABLE)
+///         .end_collection()
+/// );
+/// assert_eq!(DESCRIPTOR, &[0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0xC0]);
+/// ```
+#[macro_export]
+macro_rules! hid_report_descriptor {
+    ($builder:expr) => {{
+        const BUILDER: $crate::hid::ReportDescriptorBuilder = $builder;
+        const DESCRIPTOR: [u8; BUILDER.len()] = BUILDER.finish();
+        &DESCRIPTOR
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Keyboard descriptor previously hand-encoded by the keyboard service
+    #[rustfmt::skip]
+    const KEYBOARD: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x85, 0x01,
+        0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02,
+        0x19, 0x00, 0x29, 0x91, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x06, 0x81, 0x00,
+        0x05, 0x08, 0x19, 0x01, 0x29, 0x03, 0x75, 0x01, 0x95, 0x03, 0x25, 0x01, 0x91, 0x02,
+        0x95, 0x05, 0x91, 0x01,
+        0xC0,
+    ];
+
+    #[test]
+    fn test_keyboard_descriptor() {
+        const DESCRIPTOR: &[u8] = crate::hid_report_descriptor!(
+            ReportDescriptorBuilder::new()
+                .usage_page(0x01)
+                .usage(0x06)
+                .collection(Collection::Application)
+                .report_id(1)
+                .usage_page(0x07)
+                .usage_minimum(0xE0)
+                .usage_maximum(0xE7)
+                .logical_minimum(0)
+                .logical_maximum(1)
+                .report_size(1)
+                .report_count(8)
+                .input(MainItemFlags::VARIABLE)
+                .usage_minimum(0x00)
+                .usage_maximum(0x91)
+                .logical_maximum(255)
+                .report_size(8)
+                .report_count(6)
+                .input(MainItemFlags::DATA)
+                .usage_page(0x08)
+                .usage_minimum(0x01)
+                .usage_maximum(0x03)
+                .report_size(1)
+                .report_count(3)
+                .logical_maximum(1)
+                .output(MainItemFlags::VARIABLE)
+                .report_count(5)
+                .output(MainItemFlags::CONSTANT)
+                .end_collection()
+        );
+        assert_eq!(DESCRIPTOR, KEYBOARD);
+    }
+
+    #[test]
+    fn test_item_sizes() {
+        let builder = ReportDescriptorBuilder::new()
+            .usage_page(0xFF00)
+            .logical_minimum(-1)
+            .logical_minimum(-200)
+            .logical_maximum(100_000);
+        assert_eq!(
+            builder.finish::<13>(),
+            [
+                0x06, 0x00, 0xFF, 0x15, 0xFF, 0x16, 0x38, 0xFF, 0x27, 0xA0, 0x86, 0x01, 0x00
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Report size must be set before data items")]
+    fn test_missing_report_size() {
+        let _ = ReportDescriptorBuilder::new()
+            .report_count(1)
+            .input(MainItemFlags::DATA);
+    }
+
+    #[test]
+    #[should_panic(expected = "Report descriptor has unclosed collections")]
+    fn test_unclosed_collection() {
+        let builder = ReportDescriptorBuilder::new().collection(Collection::Application);
+        let _ = builder.finish::<2>();
+    }
+}
@@ -0,0 +1,226 @@
+//! OEM hook registration
+//!
+//! Services expose well-defined extension points (offer validation, consumer selection, thermal failsafe,
+//! host command filtering, etc.) as statically declared [`HookSlot`]s. OEM crates register a single hook
+//! per slot at init time, and services consult the slot at the extension point, falling back to their
+//! default behavior if nothing is registered. This allows platform specific behavior without forking the
+//! service.
+//!
+//! Hooks are async. Since async traits aren't dyn compatible and there's no allocator, a registered hook runs in an
+//! OEM task through [`HookRunner::run`] and services exchange inputs and outputs with it through the slot.
+
+use embassy_sync::channel::Channel;
+use embassy_sync::mutex::Mutex;
+use embassy_sync::once_lock::OnceLock;
+
+use crate::{GlobalRawMutex, Never};
+
+/// Hook registration error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A hook has already been registered for this extension point
+    AlreadyRegistered,
+}
+
+/// Trait describing a single extension point
+///
+/// Implemented on a marker type by the service that owns the extension point.
+pub trait ExtensionPoint: 'static {
+    /// Name of the extension point, used for logging
+    const NAME: &'static str;
+    /// Data passed to the hook
+    type Input: Send;
+    /// Value returned by the hook
+    type Output: Send;
+}
+
+/// Callback for an extension point
+pub trait Hook<P: ExtensionPoint> {
+    /// Invoke the hook
+    fn call(&mut self, input: P::Input) -> impl Future<Output = P::Output>;
+}
+
+impl<P: ExtensionPoint, F: AsyncFnMut(P::Input) -> P::Output> Hook<P> for F {
+    fn call(&mut self, input: P::Input) -> impl Future<Output = P::Output> {
+        self(input)
+    }
+}
+
+/// Statically declared slot holding the hook for a single extension point
+pub struct HookSlot<P: ExtensionPoint> {
+    registered: OnceLock<()>,
+    /// Serializes calls, holds the sequence number of the next call
+    call: Mutex<GlobalRawMutex, u32>,
+    requests: Channel<GlobalRawMutex, (u32, P::Input), 1>,
+    responses: Channel<GlobalRawMutex, (u32, P::Output), 1>,
+}
+
+impl<P: ExtensionPoint> HookSlot<P> {
+    /// Create a new empty slot
+    pub const fn new() -> Self {
+        Self {
+            registered: OnceLock::new(),
+            call: Mutex::new(0),
+            requests: Channel::new(),
+            responses: Channel::new(),
+        }
+    }
+
+    /// Register a hook for this extension point
+    ///
+    /// Returns the runner for the hook, which must be run in a task. Calls to the extension point wait for the runner
+    /// from then on.
+    pub fn register(&self) -> Result<HookRunner<'_, P>, Error> {
+        self.registered.init(()).map_err(|_| {
+            crate::error!("Hook already registered for {}", P::NAME);
+            Error::AlreadyRegistered
+        })?;
+        crate::info!("Registered hook for {}", P::NAME);
+        Ok(HookRunner { slot: self })
+    }
+
+    /// Returns true if a hook has been registered
+    pub fn is_registered(&self) -> bool {
+        self.registered.try_get().is_some()
+    }
+
+    /// Invoke the registered hook, returns `None` if no hook is registered
+    ///
+    /// DROP SAFETY: Dropping the call part way through is safe, the response to the abandoned request is discarded by
+    /// the next call.
+    pub async fn call(&self, input: P::Input) -> Option<P::Output> {
+        if !self.is_registered() {
+            return None;
+        }
+
+        let mut sequence = self.call.lock().await;
+        let current = *sequence;
+        *sequence = sequence.wrapping_add(1);
+
+        self.requests.send((current, input)).await;
+        loop {
+            let (response_sequence, output) = self.responses.receive().await;
+            if response_sequence == current {
+                return Some(output);
+            }
+            crate::trace!("Discarding stale response for {}", P::NAME);
+        }
+    }
+
+    /// Invoke the registered hook or fall back to `default` if no hook is registered
+    pub async fn call_or_else(&self, input: P::Input, default: impl FnOnce() -> P::Output) -> P::Output {
+        match self.call(input).await {
+            Some(output) => output,
+            None => default(),
+        }
+    }
+}
+
+impl<P: ExtensionPoint> Default for HookSlot<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs the hook registered for an extension point
+pub struct HookRunner<'a, P: ExtensionPoint> {
+    slot: &'a HookSlot<P>,
+}
+
+impl<P: ExtensionPoint> HookRunner<'_, P> {
+    /// Serve calls to the extension point with `hook`
+    pub async fn run(self, mut hook: impl Hook<P>) -> Never {
+        loop {
+            let (sequence, input) = self.slot.requests.receive().await;
+            let output = hook.call(input).await;
+            self.slot.responses.send((sequence, output)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use embassy_futures::select::{Either, select};
+
+    use super::*;
+
+    struct Double;
+
+    impl ExtensionPoint for Double {
+        const NAME: &'static str = "Double";
+        type Input = u32;
+        type Output = u32;
+    }
+
+    struct Tripler;
+
+    impl Hook<Double> for Tripler {
+        async fn call(&mut self, input: u32) -> u32 {
+            input * 3
+        }
+    }
+
+    /// Call the slot while the hook runs, returns the result of the calls
+    async fn call_with<const N: usize>(
+        runner: HookRunner<'_, Double>,
+        hook: impl Hook<Double>,
+        slot: &HookSlot<Double>,
+        inputs: [u32; N],
+    ) -> [u32; N] {
+        let calls = async {
+            let mut outputs = [0; N];
+            for (output, input) in outputs.iter_mut().zip(inputs) {
+                *output = slot.call_or_else(input, || input * 2).await;
+            }
+            outputs
+        };
+        match select(runner.run(hook), calls).await {
+            Either::First(never) => match never {},
+            Either::Second(outputs) => outputs,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_fallback() {
+        let slot: HookSlot<Double> = HookSlot::new();
+        assert!(!slot.is_registered());
+        assert_eq!(slot.call(2).await, None);
+        assert_eq!(slot.call_or_else(2, || 4).await, 4);
+    }
+
+    #[tokio::test]
+    async fn test_registered_hook() {
+        let slot: HookSlot<Double> = HookSlot::new();
+        let runner = slot.register().unwrap();
+        assert!(slot.is_registered());
+        assert_eq!(slot.register().err(), Some(Error::AlreadyRegistered));
+        assert_eq!(call_with(runner, Tripler, &slot, [2, 5]).await, [6, 15]);
+    }
+
+    #[tokio::test]
+    async fn test_async_closure_hook() {
+        let slot: HookSlot<Double> = HookSlot::new();
+        let runner = slot.register().unwrap();
+        let mut calls = 0;
+        let hook = async |input: u32| {
+            calls += 1;
+            input + calls
+        };
+        assert_eq!(call_with(runner, hook, &slot, [2, 2]).await, [3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_call() {
+        let slot: HookSlot<Double> = HookSlot::new();
+        let runner = slot.register().unwrap();
+
+        // The runner isn't running, so the call is abandoned once its request is queued
+        let abandoned = select(slot.call(1), core::future::ready(())).await;
+        assert!(matches!(abandoned, Either::Second(())));
+
+        // The stale response to the abandoned call isn't returned to the next one
+        assert_eq!(call_with(runner, Tripler, &slot, [2]).await, [6]);
+    }
+}
@@ -0,0 +1,460 @@
+//! Host sleep state aware notification handling
+//!
+//! Host notifications are identified by a small ID (0-31) assigned by the platform, e.g. the eSPI notification
+//! offset. While the host is in S0 every notification is delivered immediately. While the host is asleep,
+//! notifications that are configured as wake-worthy trigger the wake protocol, and all others are coalesced and
+//! delivered once the host returns to S0.
+//!
+//! Notifications rung through a [`Doorbell`] are additionally coalesced in time: every ID rung within the doorbell's
+//! window is combined into a single host interrupt, and the host reads the combined event bitmap to learn which
+//! notifications are pending. This keeps the host from being woken once per update during bursts, e.g. battery and
+//! thermal updates that land in the same second. A doorbell created with [`Doorbell::new_with_filter`] runs every
+//! ring through a [`Filter`], so notifications that aren't wake-worthy are held back while the host is asleep. The
+//! transport reports host power state changes through [`Doorbell::set_power_state`] and raises the platform's wake
+//! signal ahead of the interrupt when [`Doorbell::take_wake`] reports a wake-worthy notification.
+
+use core::cell::{Cell, RefCell};
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+
+use crate::GlobalRawMutex;
+
+/// Maximum number of distinct notification IDs
+pub const MAX_NOTIFICATION_ID: u8 = 31;
+
+/// Host power state
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HostPowerState {
+    /// Host is fully running
+    #[default]
+    S0,
+    /// Host is in modern standby
+    S0ix,
+    /// Host is suspended to RAM
+    S3,
+    /// Host is hibernating
+    S4,
+    /// Host is off
+    S5,
+}
+
+impl HostPowerState {
+    /// Returns true if the host is able to process notifications without being woken
+    pub fn is_awake(self) -> bool {
+        self == HostPowerState::S0
+    }
+}
+
+/// Notification ID
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NotificationId(u8);
+
+impl NotificationId {
+    /// Create a new notification ID, returns `None` if the ID is greater than [`MAX_NOTIFICATION_ID`]
+    pub const fn new(id: u8) -> Option<Self> {
+        if id <= MAX_NOTIFICATION_ID {
+            Some(Self(id))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the raw ID
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    const fn mask(self) -> u32 {
+        1 << self.0
+    }
+}
+
+/// Set of notification IDs
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NotificationSet(u32);
+
+impl NotificationSet {
+    /// Create an empty set
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Return a copy of this set with the given ID added
+    pub const fn with(self, id: NotificationId) -> Self {
+        Self(self.0 | id.mask())
+    }
+
+    /// Add an ID to the set
+    pub fn insert(&mut self, id: NotificationId) {
+        self.0 |= id.mask();
+    }
+
+    /// Remove an ID from the set
+    pub fn remove(&mut self, id: NotificationId) {
+        self.0 &= !id.mask();
+    }
+
+    /// Returns true if the set contains the ID
+    pub const fn contains(&self, id: NotificationId) -> bool {
+        self.0 & id.mask() != 0
+    }
+
+    /// Returns true if the set is empty
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the set as a bitmap, bit N set for notification ID N
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Return the union of this set and `other`
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Iterate over the IDs in the set, lowest first
+    pub fn iter(&self) -> impl Iterator<Item = NotificationId> {
+        let bits = self.0;
+        (0..=MAX_NOTIFICATION_ID)
+            .filter(move |id| bits & (1 << id) != 0)
+            .map(NotificationId)
+    }
+}
+
+/// Action to take for a notification
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Action {
+    /// Host is awake, deliver the notification immediately
+    Deliver,
+    /// Host is asleep and the notification is wake-worthy, wake the host then deliver the notification
+    Wake,
+    /// Host is asleep, the notification has been deferred until the host is awake
+    Deferred,
+}
+
+struct State {
+    power_state: HostPowerState,
+    deferred: NotificationSet,
+}
+
+/// Notification filter
+pub struct Filter {
+    wake_worthy: NotificationSet,
+    state: Mutex<GlobalRawMutex, RefCell<State>>,
+}
+
+impl Filter {
+    /// Create a new filter with the given set of wake-worthy notifications
+    pub const fn new(wake_worthy: NotificationSet) -> Self {
+        Self {
+            wake_worthy,
+            state: Mutex::new(RefCell::new(State {
+                power_state: HostPowerState::S0,
+                deferred: NotificationSet::new(),
+            })),
+        }
+    }
+
+    /// Returns the set of wake-worthy notifications
+    pub fn wake_worthy(&self) -> NotificationSet {
+        self.wake_worthy
+    }
+
+    /// Returns the current host power state
+    pub fn power_state(&self) -> HostPowerState {
+        self.state.lock(|state| state.borrow().power_state)
+    }
+
+    /// Update the host power state
+    ///
+    /// Returns the set of deferred notifications that must now be delivered if the host has returned to S0.
+    /// Notifications for the same ID are coalesced, so each ID is delivered at most once.
+    pub fn set_power_state(&self, power_state: HostPowerState) -> NotificationSet {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.power_state = power_state;
+            if power_state.is_awake() {
+                core::mem::take(&mut state.deferred)
+            } else {
+                NotificationSet::new()
+            }
+        })
+    }
+
+    /// Determine what to do with a notification
+    pub fn filter(&self, id: NotificationId) -> Action {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if state.power_state.is_awake() {
+                Action::Deliver
+            } else if self.wake_worthy.contains(id) {
+                // Waking the host delivers this notification, drop any older deferred copy
+                state.deferred.remove(id);
+                Action::Wake
+            } else {
+                state.deferred.insert(id);
+                Action::Deferred
+            }
+        })
+    }
+
+    /// Returns the currently deferred notifications
+    pub fn deferred(&self) -> NotificationSet {
+        self.state.lock(|state| state.borrow().deferred)
+    }
+}
+
+/// Coalesces host notification doorbells into a single interrupt per window
+///
+/// Services call [`Doorbell::ring`] when they update a host-visible section. The transport waits on
+/// [`Doorbell::wait`], which returns once the window following the first ring has elapsed, then raises one host
+/// interrupt. The combined bitmap is latched until the host reads it through [`Doorbell::acknowledge`], so IDs rung
+/// before the host gets around to reading aren't lost.
+pub struct Doorbell {
+    window: Duration,
+    filter: Option<Filter>,
+    /// Notifications rung in the current window
+    pending: Mutex<GlobalRawMutex, Cell<NotificationSet>>,
+    /// Notifications signalled to the host but not yet read
+    latched: Mutex<GlobalRawMutex, Cell<NotificationSet>>,
+    /// Set if a wake-worthy notification was rung while the host was asleep
+    wake: Mutex<GlobalRawMutex, Cell<bool>>,
+    rung: Signal<GlobalRawMutex, ()>,
+}
+
+impl Doorbell {
+    /// Create a new doorbell, coalescing notifications rung within `window` of each other
+    pub const fn new(window: Duration) -> Self {
+        Self {
+            window,
+            filter: None,
+            pending: Mutex::new(Cell::new(NotificationSet::new())),
+            latched: Mutex::new(Cell::new(NotificationSet::new())),
+            wake: Mutex::new(Cell::new(false)),
+            rung: Signal::new(),
+        }
+    }
+
+    /// Create a new doorbell that filters notifications based on the host power state
+    ///
+    /// Notifications in `wake_worthy` are always raised, waking the host if it's asleep. Others are deferred while the
+    /// host is asleep and raised once [`Doorbell::set_power_state`] reports that it's back in S0.
+    pub const fn new_with_filter(window: Duration, wake_worthy: NotificationSet) -> Self {
+        Self {
+            window,
+            filter: Some(Filter::new(wake_worthy)),
+            pending: Mutex::new(Cell::new(NotificationSet::new())),
+            latched: Mutex::new(Cell::new(NotificationSet::new())),
+            wake: Mutex::new(Cell::new(false)),
+            rung: Signal::new(),
+        }
+    }
+
+    /// Returns the coalescing window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns the notification filter, if any
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    /// Ring the doorbell for a notification
+    ///
+    /// Returns the action taken by the filter, notifications are always delivered if the doorbell has no filter.
+    pub fn ring(&self, id: NotificationId) -> Action {
+        let action = match &self.filter {
+            Some(filter) => filter.filter(id),
+            None => Action::Deliver,
+        };
+
+        if action == Action::Wake {
+            self.wake.lock(|wake| wake.set(true));
+        }
+        if action != Action::Deferred {
+            self.raise(NotificationSet::new().with(id));
+        }
+        action
+    }
+
+    /// Returns and clears whether a wake-worthy notification was rung while the host was asleep
+    ///
+    /// The transport checks this once [`Doorbell::wait`] returns and wakes the host before raising the interrupt.
+    pub fn take_wake(&self) -> bool {
+        self.wake.lock(|wake| wake.take())
+    }
+
+    /// Update the host power state
+    ///
+    /// Notifications deferred while the host was asleep are raised once it returns to S0. Does nothing if the doorbell
+    /// has no filter.
+    pub fn set_power_state(&self, power_state: HostPowerState) {
+        if let Some(filter) = &self.filter {
+            let deferred = filter.set_power_state(power_state);
+            if !deferred.is_empty() {
+                self.raise(deferred);
+            }
+        }
+    }
+
+    fn raise(&self, notifications: NotificationSet) {
+        self.pending
+            .lock(|pending| pending.set(pending.get().union(notifications)));
+        self.rung.signal(());
+    }
+
+    /// Returns the notifications rung in the current window
+    pub fn pending(&self) -> NotificationSet {
+        self.pending.lock(|pending| pending.get())
+    }
+
+    /// Wait for the doorbell to be rung and the coalescing window to elapse
+    ///
+    /// Returns the combined bitmap the host should see, including any notifications latched by a previous interrupt
+    /// that the host hasn't acknowledged yet. The caller should raise a single host interrupt.
+    pub async fn wait(&self) -> NotificationSet {
+        loop {
+            self.rung.wait().await;
+            if self.window > Duration::from_ticks(0) {
+                Timer::after(self.window).await;
+            }
+            // Rings during the window are already included in the pending set
+            self.rung.reset();
+
+            let rung = self.pending.lock(|pending| pending.take());
+            if !rung.is_empty() {
+                return self.latched.lock(|latched| {
+                    let combined = latched.get().union(rung);
+                    latched.set(combined);
+                    combined
+                });
+            }
+        }
+    }
+
+    /// Host read of the event bitmap, returns and clears the latched notifications
+    pub fn acknowledge(&self) -> NotificationSet {
+        self.latched.lock(|latched| latched.take())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const BATTERY_PERCENT: NotificationId = NotificationId(1);
+    const BATTERY_CRITICAL: NotificationId = NotificationId(2);
+    const THERMAL_CRT: NotificationId = NotificationId(3);
+
+    fn filter() -> Filter {
+        Filter::new(NotificationSet::new().with(BATTERY_CRITICAL).with(THERMAL_CRT))
+    }
+
+    #[test]
+    fn test_notification_id() {
+        assert_eq!(
+            NotificationId::new(MAX_NOTIFICATION_ID).unwrap().get(),
+            MAX_NOTIFICATION_ID
+        );
+        assert!(NotificationId::new(MAX_NOTIFICATION_ID + 1).is_none());
+    }
+
+    #[test]
+    fn test_deliver_when_awake() {
+        let filter = filter();
+        assert_eq!(filter.filter(BATTERY_PERCENT), Action::Deliver);
+        assert_eq!(filter.filter(BATTERY_CRITICAL), Action::Deliver);
+        assert!(filter.deferred().is_empty());
+    }
+
+    #[test]
+    fn test_defer_and_coalesce() {
+        let filter = filter();
+        assert!(filter.set_power_state(HostPowerState::S0ix).is_empty());
+        assert_eq!(filter.filter(BATTERY_PERCENT), Action::Deferred);
+        assert_eq!(filter.filter(BATTERY_PERCENT), Action::Deferred);
+
+        let delivered = filter.set_power_state(HostPowerState::S0);
+        let mut delivered = delivered.iter();
+        assert_eq!(delivered.next(), Some(BATTERY_PERCENT));
+        assert_eq!(delivered.next(), None);
+        assert!(filter.deferred().is_empty());
+    }
+
+    #[test]
+    fn test_wake_worthy() {
+        let filter = filter();
+        filter.set_power_state(HostPowerState::S3);
+        assert_eq!(filter.filter(BATTERY_CRITICAL), Action::Wake);
+        assert_eq!(filter.filter(THERMAL_CRT), Action::Wake);
+        assert!(filter.deferred().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_doorbell_coalesces() {
+        let doorbell = Doorbell::new(Duration::from_millis(10));
+        doorbell.ring(BATTERY_PERCENT);
+        doorbell.ring(THERMAL_CRT);
+        doorbell.ring(BATTERY_PERCENT);
+
+        let combined = doorbell.wait().await;
+        assert_eq!(combined, NotificationSet::new().with(BATTERY_PERCENT).with(THERMAL_CRT));
+        assert_eq!(combined.bits(), 0b1010);
+        assert!(doorbell.pending().is_empty());
+
+        // Unread notifications stay latched into the next interrupt
+        doorbell.ring(BATTERY_CRITICAL);
+        assert_eq!(
+            doorbell.wait().await,
+            NotificationSet::new()
+                .with(BATTERY_PERCENT)
+                .with(BATTERY_CRITICAL)
+                .with(THERMAL_CRT)
+        );
+        assert_eq!(doorbell.acknowledge().bits(), 0b1110);
+        assert!(doorbell.acknowledge().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_doorbell_filter() {
+        let doorbell = Doorbell::new_with_filter(
+            Duration::from_ticks(0),
+            NotificationSet::new().with(BATTERY_CRITICAL).with(THERMAL_CRT),
+        );
+        doorbell.set_power_state(HostPowerState::S3);
+
+        // Filtered notifications are suppressed while the host is asleep
+        assert_eq!(doorbell.ring(BATTERY_PERCENT), Action::Deferred);
+        assert!(doorbell.pending().is_empty());
+        assert_eq!(
+            doorbell.filter().unwrap().deferred(),
+            NotificationSet::new().with(BATTERY_PERCENT)
+        );
+
+        assert!(!doorbell.take_wake());
+
+        // Wake-worthy notifications go through and wake the host
+        assert_eq!(doorbell.ring(THERMAL_CRT), Action::Wake);
+        assert_eq!(doorbell.wait().await, NotificationSet::new().with(THERMAL_CRT));
+        assert!(doorbell.take_wake());
+        assert!(!doorbell.take_wake());
+        assert_eq!(doorbell.acknowledge(), NotificationSet::new().with(THERMAL_CRT));
+
+        // Deferred notifications are raised once the host is awake
+        doorbell.set_power_state(HostPowerState::S0);
+        assert_eq!(doorbell.wait().await, NotificationSet::new().with(BATTERY_PERCENT));
+        assert_eq!(doorbell.ring(BATTERY_PERCENT), Action::Deliver);
+        assert!(doorbell.filter().unwrap().deferred().is_empty());
+        assert!(!doorbell.take_wake());
+    }
+}
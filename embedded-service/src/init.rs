@@ -1,7 +1,25 @@
 //! Code related to initialization states and ordering
+//!
+//! Services declare their initialization as an [`InitNode`] along with the nodes it depends on, e.g. comms before
+//! endpoints or the power policy before its devices. [`run`] resolves the order, reporting missing dependencies and
+//! cycles before anything is initialized, then initializes each node once its dependencies are ready. Tasks await
+//! [`InitNode::wait_ready`] rather than relying on spawn order.
+//!
+//! ```ignore
+//! static COMMS: InitNode = InitNode::new("comms", &[], comms::init);
+//! static POLICY: InitNode = InitNode::new("power-policy", &[&COMMS], policy_init);
+//! // Ready once its task has registered its devices
+//! static DEVICES: InitNode = InitNode::deferred("devices", &[&POLICY]);
+//!
+//! init::run(&[&COMMS, &POLICY, &DEVICES]).await?;
+//! ```
+
+use core::ptr;
 
 use embassy_sync::once_lock::OnceLock;
 
+use crate::debug;
+
 static REGISTRATION_DONE: OnceLock<()> = OnceLock::new();
 
 /// Wait for registration to complete
@@ -13,3 +31,196 @@ pub async fn wait_for_registration() {
 pub fn registration_done() {
     REGISTRATION_DONE.get_or_init(|| ());
 }
+
+/// Initialization error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InitError {
+    /// A node depends on a node that wasn't passed to [`run`]
+    MissingDependency {
+        /// Name of the node
+        node: &'static str,
+        /// Name of the missing dependency
+        dependency: &'static str,
+    },
+    /// The named node is part of, or depends on, a dependency cycle
+    Cycle(&'static str),
+}
+
+/// Service taking part in ordered initialization
+pub struct InitNode {
+    name: &'static str,
+    dependencies: &'static [&'static InitNode],
+    init: Option<fn()>,
+    ready: OnceLock<()>,
+}
+
+impl InitNode {
+    /// Create a node initialized by `init`, it's ready as soon as `init` returns
+    pub const fn new(name: &'static str, dependencies: &'static [&'static InitNode], init: fn()) -> Self {
+        Self {
+            name,
+            dependencies,
+            init: Some(init),
+            ready: OnceLock::new(),
+        }
+    }
+
+    /// Create a node whose readiness is signalled with [`Self::mark_ready`], e.g. by its task
+    pub const fn deferred(name: &'static str, dependencies: &'static [&'static InitNode]) -> Self {
+        Self {
+            name,
+            dependencies,
+            init: None,
+            ready: OnceLock::new(),
+        }
+    }
+
+    /// Node name
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Signal that the node is ready
+    pub fn mark_ready(&self) {
+        self.ready.get_or_init(|| ());
+    }
+
+    /// Returns true if the node is ready
+    pub fn is_ready(&self) -> bool {
+        self.ready.try_get().is_some()
+    }
+
+    /// Wait for the node to be ready
+    pub async fn wait_ready(&self) {
+        self.ready.get().await;
+    }
+
+    /// Wait for all dependencies of the node to be ready
+    pub async fn wait_dependencies(&self) {
+        for dependency in self.dependencies {
+            dependency.wait_ready().await;
+        }
+    }
+}
+
+/// Returns the index of `node` in `nodes`
+fn index_of(nodes: &[&'static InitNode], node: &InitNode) -> Option<usize> {
+    nodes.iter().position(|n| ptr::eq(*n, node))
+}
+
+/// Resolve the initialization order of `nodes`, returned as indices into `nodes`
+pub fn resolve<const N: usize>(nodes: &[&'static InitNode; N]) -> Result<[usize; N], InitError> {
+    for node in nodes {
+        if let Some(dependency) = node.dependencies.iter().find(|d| index_of(nodes, d).is_none()) {
+            return Err(InitError::MissingDependency {
+                node: node.name,
+                dependency: dependency.name,
+            });
+        }
+    }
+
+    let mut order = [0; N];
+    let mut resolved = [false; N];
+    let is_resolved = |resolved: &[bool; N], node: &InitNode| {
+        index_of(nodes, node)
+            .and_then(|i| resolved.get(i).copied())
+            .unwrap_or(false)
+    };
+
+    for slot in order.iter_mut() {
+        let next = nodes.iter().position(|node| {
+            !is_resolved(&resolved, node) && node.dependencies.iter().all(|d| is_resolved(&resolved, d))
+        });
+
+        let Some(next) = next else {
+            // Every remaining node waits on another remaining node
+            let blocked = nodes
+                .iter()
+                .find(|node| !is_resolved(&resolved, node))
+                .map_or("", |node| node.name);
+            return Err(InitError::Cycle(blocked));
+        };
+
+        if let Some(resolved) = resolved.get_mut(next) {
+            *resolved = true;
+        }
+        *slot = next;
+    }
+
+    Ok(order)
+}
+
+/// Initialize `nodes` in dependency order
+///
+/// Each node is initialized once all its dependencies are ready, so this waits on any deferred dependency. Nothing is
+/// initialized if the order can't be resolved.
+pub async fn run<const N: usize>(nodes: &[&'static InitNode; N]) -> Result<(), InitError> {
+    let order = resolve(nodes)?;
+    for node in order.iter().filter_map(|&i| nodes.get(i)) {
+        node.wait_dependencies().await;
+        if let Some(init) = node.init {
+            debug!("Initializing {}", node.name);
+            init();
+            node.mark_ready();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{AtomicUsize, Ordering};
+    use embassy_futures::block_on;
+    use embassy_futures::join::join;
+
+    fn noop() {}
+
+    #[test]
+    fn resolve_orders_dependencies_first() {
+        static COMMS: InitNode = InitNode::new("comms", &[], noop);
+        static POLICY: InitNode = InitNode::new("policy", &[&COMMS], noop);
+        static DEVICE: InitNode = InitNode::new("device", &[&POLICY, &COMMS], noop);
+
+        assert_eq!(resolve(&[&DEVICE, &POLICY, &COMMS]).unwrap(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn resolve_reports_errors() {
+        static A: InitNode = InitNode::new("a", &[&B], noop);
+        static B: InitNode = InitNode::new("b", &[&A], noop);
+        static C: InitNode = InitNode::new("c", &[], noop);
+        static D: InitNode = InitNode::new("d", &[&C], noop);
+
+        assert_eq!(resolve(&[&C, &A, &B]), Err(InitError::Cycle("a")));
+        assert_eq!(
+            resolve(&[&D]),
+            Err(InitError::MissingDependency {
+                node: "d",
+                dependency: "c",
+            })
+        );
+    }
+
+    #[test]
+    fn run_waits_for_deferred_dependencies() {
+        static INITIALIZED: AtomicUsize = AtomicUsize::new(0);
+        fn init() {
+            INITIALIZED.fetch_add(1, Ordering::SeqCst);
+        }
+
+        static DEFERRED: InitNode = InitNode::deferred("deferred", &[]);
+        static DEPENDENT: InitNode = InitNode::new("dependent", &[&DEFERRED], init);
+
+        let (result, _) = block_on(join(run(&[&DEPENDENT, &DEFERRED]), async {
+            assert_eq!(INITIALIZED.load(Ordering::SeqCst), 0);
+            DEFERRED.mark_ready();
+        }));
+        result.unwrap();
+        assert_eq!(INITIALIZED.load(Ordering::SeqCst), 1);
+        assert!(DEPENDENT.is_ready());
+    }
+}
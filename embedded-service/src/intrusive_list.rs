@@ -1,4 +1,8 @@
-//! A static lifetime'd intrusive linked list, construction only (never remove/delete)
+//! A static lifetime'd intrusive linked list
+//!
+//! Nodes are statically allocated, so removing a node never frees its memory. A removed node is tombstoned: it no
+//! longer yields data, but keeps its link so that an in-flight iteration can continue past it. Each push bumps the
+//! node's generation, which lets iterators detect a node that was removed and pushed again while they held it.
 
 // Any type used for dynamic type coercion
 pub use core::any::Any;
@@ -10,6 +14,8 @@ use crate::SyncCell;
 pub enum Error {
     /// cannot push a node to any list if it's already in one
     NodeAlreadyInList,
+    /// cannot remove a node from a list it isn't in
+    NodeNotInList,
 }
 
 /// override Result type for shorthand `-> Result<T>`
@@ -22,10 +28,14 @@ pub struct IntrusiveNode {
     address_of_data: &'static dyn Any,
 
     /// unsafe iterator type
-    next: Option<&'static IntrusiveNode>,
+    next: Option<&'static Node>,
 
-    /// valid address flag: used to ensure proper initialization sequencing over address_of_data
+    /// valid address flag: used to ensure proper initialization sequencing over address_of_data.
+    /// Cleared when the node is removed from its list
     valid: bool,
+
+    /// incremented every time the node is pushed to a list
+    generation: u32,
 }
 
 /// node type for list allocation. Embed this in the "list wrapper" object, and init with Node::uninit()
@@ -33,6 +43,16 @@ pub struct Node {
     inner: SyncCell<IntrusiveNode>,
 }
 
+impl core::fmt::Debug for Node {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let inner = self.inner.get();
+        f.debug_struct("Node")
+            .field("valid", &inner.valid)
+            .field("generation", &inner.generation)
+            .finish()
+    }
+}
+
 struct Invalid {}
 
 impl Node {
@@ -43,6 +63,7 @@ impl Node {
         address_of_data: &Node::INVALID,
         next: None,
         valid: false,
+        generation: 0,
     };
 
     /// construct an uninitialized node in place
@@ -51,6 +72,21 @@ impl Node {
             inner: SyncCell::new(Node::EMPTY),
         }
     }
+
+    /// retrieve the underlying dynamic type information (vtable), `None` if the node has been removed
+    pub fn data<T: NodeContainer>(&self) -> Option<&'static T> {
+        let inner = self.inner.get();
+        if inner.valid {
+            inner.address_of_data.downcast_ref()
+        } else {
+            None
+        }
+    }
+
+    /// returns true if the node is currently in a list
+    pub fn is_linked(&self) -> bool {
+        self.inner.get().valid
+    }
 }
 
 /// implementing this trait is required for IntrusiveList construction over type T
@@ -62,19 +98,10 @@ pub trait NodeContainer: Any {
 /// List of intruded nodes of unknown type(s), must be allocated statically
 pub struct IntrusiveList {
     /// traditional head pointer on list. Static reference type is used to ensure static allocations (for safety)
-    head: SyncCell<Option<&'static IntrusiveNode>>,
+    head: SyncCell<Option<&'static Node>>,
 }
 
 impl IntrusiveNode {
-    /// generate an empty node for use within whatever type T
-    fn new<T: NodeContainer>(this_ref: &'static T) -> IntrusiveNode {
-        IntrusiveNode {
-            address_of_data: this_ref,
-            next: None,
-            valid: true,
-        }
-    }
-
     /// retrieve the underlying dynamic type information (vtable)
     pub fn data<T: NodeContainer>(&self) -> Option<&T> {
         if self.valid {
@@ -99,37 +126,74 @@ impl IntrusiveList {
         }
     }
 
-    /// only allow pushing to the head of the list
-    fn push_front(&self, node: &'static mut IntrusiveNode) {
+    /// returns true if `node` is reachable from the head of this list
+    fn contains(&self, node: &Node) -> bool {
+        self.into_iter().any(|current| core::ptr::eq(current, node))
+    }
+
+    /// generic over T: NodeContainer for list.push() proper node construction
+    pub fn push<T: NodeContainer>(&self, object: &'static T) -> Result<()> {
+        let node = object.get_node();
+
         // critical section in case of multi-threaded implementation:
         critical_section::with(|_cs| {
-            if let Some(old_head) = self.head.get() {
-                node.next = Some(old_head);
+            let inner = node.inner.get();
+
+            // check if node is in the list already. Valid flag will only be set if
+            // the element has been constructed and inserted into a linked list, so
+            // this check covers both same list and other list conditions.
+            if inner.valid {
+                return Err(Error::NodeAlreadyInList);
             }
 
+            // a node that isn't flagged valid must never be reachable, otherwise pushing it would create a cycle
+            debug_assert!(!self.contains(node), "unlinked node found in list");
+
+            // only allow pushing to the head of the list
+            node.inner.set(IntrusiveNode {
+                address_of_data: object,
+                next: self.head.get(),
+                valid: true,
+                generation: inner.generation.wrapping_add(1),
+            });
             self.head.set(Some(node));
-        });
+            Ok(())
+        })
     }
 
-    /// generic over T: NodeContainer for list.push() proper node construction
-    pub fn push<T: NodeContainer>(&self, object: &'static T) -> Result<()> {
-        // check if node is in the list already. Valid flag will only be set if
-        // the element has been constructed and inserted into a linked list, so
-        // this check covers both same list and other list conditions.
-        if object.get_node().inner.get().valid {
-            return Err(Error::NodeAlreadyInList);
-        }
+    /// Remove `object` from the list.
+    ///
+    /// The node is tombstoned rather than cleared, so iterators currently positioned on it skip its data but can
+    /// still continue to the rest of the list. Once removed, the node may be pushed to a list again.
+    pub fn remove<T: NodeContainer>(&self, object: &T) -> Result<()> {
+        let target = object.get_node();
 
-        // since this API is private to this module, this is the only place where
-        // a node can be marked as valid.
-        let node = IntrusiveNode::new(object);
-        object.get_node().inner.set(node);
+        critical_section::with(|_cs| {
+            let mut prev: Option<&'static Node> = None;
+            let mut current = self.head.get();
+
+            while let Some(node) = current {
+                let inner = node.inner.get();
+                if core::ptr::eq(node, target) {
+                    match prev {
+                        Some(prev) => prev.inner.set(IntrusiveNode {
+                            next: inner.next,
+                            ..prev.inner.get()
+                        }),
+                        None => self.head.set(inner.next),
+                    }
+
+                    node.inner.set(IntrusiveNode { valid: false, ..inner });
+                    debug_assert!(!self.contains(node), "removed node still reachable");
+                    return Ok(());
+                }
+
+                prev = Some(node);
+                current = inner.next;
+            }
 
-        self.push_front(
-            // SAFETY: known safe operation due to valid flag and static lifetime
-            unsafe { &mut *object.get_node().inner.as_ptr() },
-        );
-        Ok(())
+            Err(Error::NodeNotInList)
+        })
     }
 
     /// Iterate over the list as if it were items of type `T`, skipping any nodes that are of a different type.
@@ -139,39 +203,49 @@ impl IntrusiveList {
 }
 
 /// iterator wrapper type for IntrusiveNode
+///
+/// Tolerates nodes being removed while iterating. Removed nodes are still yielded, but return no data. If the next
+/// node was removed and pushed again since it was reached, iteration ends rather than following it into its new list.
 pub struct IntrusiveIterator {
-    current: Option<&'static IntrusiveNode>,
+    /// next node to yield and its generation when it was reached
+    current: Option<(&'static Node, u32)>,
+}
+
+impl IntrusiveIterator {
+    fn new(node: Option<&'static Node>) -> Self {
+        Self {
+            current: node.map(|node| (node, node.inner.get().generation)),
+        }
+    }
 }
 
 impl IntoIterator for &IntrusiveList {
     type IntoIter = IntrusiveIterator;
-    type Item = &'static IntrusiveNode;
+    type Item = &'static Node;
 
     fn into_iter(self) -> Self::IntoIter {
-        IntrusiveIterator {
-            current: self.head.get(),
-        }
+        IntrusiveIterator::new(self.head.get())
     }
 }
 
 impl Iterator for IntrusiveIterator {
-    type Item = &'static IntrusiveNode;
+    type Item = &'static Node;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut iter = None;
-
-        if let Some(current) = self.current {
-            self.current = current.next;
-            iter = Some(current);
+        let (current, generation) = self.current.take()?;
+        let inner = current.inner.get();
+        if inner.generation != generation {
+            return None;
         }
 
-        iter
+        *self = Self::new(inner.next);
+        Some(current)
     }
 }
 
 /// Iterator wrapper type for [`IntrusiveList`] that returns only nodes of type `T`.
 pub struct OnlyT<'a, T> {
-    iter: core::iter::FilterMap<IntrusiveIterator, fn(&'static IntrusiveNode) -> Option<&'a T>>,
+    iter: core::iter::FilterMap<IntrusiveIterator, fn(&'static Node) -> Option<&'a T>>,
     _marker: core::marker::PhantomData<&'a T>,
 }
 
@@ -605,4 +679,67 @@ mod test {
     fn test_static_alloc() {
         static _LIST: IntrusiveList = IntrusiveList::new();
     }
+
+    #[test]
+    fn test_remove() {
+        static NODES: [OnceLock<RegistrationOnly>; 3] = [const { OnceLock::new() }; 3];
+        let nodes = NODES
+            .each_ref()
+            .map(|node| node.get_or_init(|| RegistrationOnly { node: Node::uninit() }));
+        let list = IntrusiveList::new();
+        for node in nodes {
+            assert!(list.push(node).is_ok());
+        }
+
+        // remove from the middle, then the head
+        assert!(list.remove(nodes[1]).is_ok());
+        assert_eq!(2, list.iter_only::<RegistrationOnly>().count());
+        assert!(list.remove(nodes[2]).is_ok());
+        assert_eq!(1, list.iter_only::<RegistrationOnly>().count());
+        assert!(!nodes[2].get_node().is_linked());
+
+        // can't remove twice or from a list the node isn't in
+        assert!(matches!(list.remove(nodes[2]), Err(Error::NodeNotInList)));
+        let list2 = IntrusiveList::new();
+        assert!(matches!(list2.remove(nodes[0]), Err(Error::NodeNotInList)));
+
+        // removed nodes can be pushed again
+        assert!(list2.push(nodes[1]).is_ok());
+        assert!(list.push(nodes[2]).is_ok());
+        assert_eq!(2, list.into_iter().count());
+        assert_eq!(1, list2.into_iter().count());
+    }
+
+    #[test]
+    #[allow(clippy::unwrap_used)]
+    fn test_remove_while_iterating() {
+        static NODES: [OnceLock<RegistrationOnly>; 3] = [const { OnceLock::new() }; 3];
+        let nodes = NODES
+            .each_ref()
+            .map(|node| node.get_or_init(|| RegistrationOnly { node: Node::uninit() }));
+        let list = IntrusiveList::new();
+        for node in nodes {
+            assert!(list.push(node).is_ok());
+        }
+
+        // list order is [2, 1, 0], remove the node the iterator is positioned on
+        let mut iter = list.into_iter();
+        assert!(core::ptr::eq(iter.next().unwrap(), nodes[2].get_node()));
+        assert!(list.remove(nodes[1]).is_ok());
+
+        // tombstoned node is still traversed but yields no data
+        let removed = iter.next().unwrap();
+        assert!(core::ptr::eq(removed, nodes[1].get_node()));
+        assert!(removed.data::<RegistrationOnly>().is_none());
+        assert!(core::ptr::eq(iter.next().unwrap(), nodes[0].get_node()));
+        assert!(iter.next().is_none());
+
+        // a node removed and pushed to another list ends the iteration instead of crossing lists
+        let mut iter = list.into_iter();
+        assert!(iter.next().is_some());
+        assert!(list.remove(nodes[0]).is_ok());
+        let list2 = IntrusiveList::new();
+        assert!(list2.push(nodes[0]).is_ok());
+        assert!(iter.next().is_none());
+    }
 }
@@ -3,6 +3,10 @@
 #![no_std]
 #![warn(missing_docs)]
 
+// Host tests use the embassy-time driver from the test support crate
+#[cfg(test)]
+use odp_test_support as _;
+
 pub mod intrusive_list;
 pub use intrusive_list::*;
 
@@ -14,15 +18,20 @@ pub mod thread_mode_cell;
 pub mod activity;
 pub mod broadcaster;
 pub mod buffer;
+pub mod caps;
 pub mod comms;
 pub mod event;
 pub mod fmt;
 pub mod hid;
+pub mod hook;
+pub mod host_notification;
 pub mod init;
 pub mod ipc;
 pub mod keyboard;
+pub mod metrics;
 pub mod named;
 pub mod relay;
+pub mod service_state;
 pub mod sync;
 
 /// Hidden re-exports used by macros defined in this crate.
@@ -48,12 +57,16 @@ pub type GlobalRawMutex = embassy_sync::blocking_mutex::raw::CriticalSectionRawM
 #[cfg(all(not(test), target_os = "none", target_arch = "arm"))]
 pub type GlobalRawMutex = embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
 
-/// AtomicUsize and Ordering re-exports. Uses core::sync::atomic if the target supports atomic operations,
+/// AtomicUsize, AtomicU32 and Ordering re-exports. Uses core::sync::atomic if the target supports atomic operations,
 /// otherwise falls back to portable-atomic crate.
+#[cfg(target_has_atomic = "32")]
+pub use core::sync::atomic::AtomicU32;
 #[cfg(target_has_atomic = "ptr")]
 pub use core::sync::atomic::AtomicUsize;
 #[cfg(target_has_atomic = "ptr")]
 pub use core::sync::atomic::Ordering;
+#[cfg(not(target_has_atomic = "32"))]
+pub use portable_atomic::AtomicU32;
 #[cfg(not(target_has_atomic = "ptr"))]
 pub use portable_atomic::AtomicUsize;
 #[cfg(not(target_has_atomic = "ptr"))]
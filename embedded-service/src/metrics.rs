@@ -0,0 +1,271 @@
+//! Sizing metrics
+//!
+//! Service queues and task stacks are sized statically, usually by guesswork. Services register a [`Metric`] for a
+//! queue and record its depth whenever something is queued, and platforms can do the same for task stacks where the
+//! high-water mark can be measured (see [`paint_stack`]). The recorded high-water marks are read back through the
+//! debug service to guide sizing.
+//!
+//! Latencies are recorded in a [`Histogram`] so regressions show up as a shift between buckets rather than only as a
+//! new maximum.
+use crate::{AtomicUsize, Ordering, intrusive_list};
+
+/// Pattern written to unused stack by [`paint_stack`]
+pub const STACK_PAINT: u32 = 0xc0de_5a5a;
+
+/// What a metric measures
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Kind {
+    /// Number of items waiting in a queue or channel
+    QueueDepth = 0,
+    /// Bytes of task stack used
+    StackBytes = 1,
+}
+
+/// High-water mark metric
+pub struct Metric {
+    node: intrusive_list::Node,
+    service_id: u8,
+    name: &'static str,
+    kind: Kind,
+    capacity: usize,
+    high_water: AtomicUsize,
+}
+
+impl Metric {
+    /// Create a new metric for the given service ID, such that it could be used in a static
+    pub const fn new(service_id: u8, name: &'static str, kind: Kind, capacity: usize) -> Self {
+        Self {
+            node: intrusive_list::Node::uninit(),
+            service_id,
+            name,
+            kind,
+            capacity,
+            high_water: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register this metric, forwards any error states (such as double registration) from intrusive_list
+    pub fn register(&'static self) -> intrusive_list::Result<()> {
+        METRICS.push(self)
+    }
+
+    /// Record a sample, the high-water mark is updated if the sample exceeds it
+    pub fn record(&self, value: usize) {
+        self.high_water.fetch_max(value, Ordering::Relaxed);
+    }
+
+    /// Highest sample recorded since the last reset
+    pub fn high_water(&self) -> usize {
+        self.high_water.load(Ordering::Relaxed)
+    }
+
+    /// Reset the high-water mark
+    pub fn reset(&self) {
+        self.high_water.store(0, Ordering::Relaxed);
+    }
+
+    /// ID of the service this metric belongs to
+    pub fn service_id(&self) -> u8 {
+        self.service_id
+    }
+
+    /// Name of the queue or task
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// What this metric measures
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Capacity of the queue or stack, in the same unit as the samples
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl intrusive_list::NodeContainer for Metric {
+    fn get_node(&self) -> &intrusive_list::Node {
+        &self.node
+    }
+}
+
+static METRICS: intrusive_list::IntrusiveList = intrusive_list::IntrusiveList::new();
+
+/// Iterate over the registered metrics, most recently registered first
+pub fn metrics() -> impl Iterator<Item = &'static Metric> {
+    METRICS.iter_only::<Metric>()
+}
+
+/// Number of buckets of a [`Histogram`]
+pub const HISTOGRAM_BUCKETS: usize = 8;
+
+/// Latency histogram with power of two buckets
+///
+/// Bucket `i` counts samples below `base << i`, the last bucket counts every sample above the others.
+pub struct Histogram {
+    node: intrusive_list::Node,
+    service_id: u8,
+    name: &'static str,
+    base: usize,
+    buckets: [AtomicUsize; HISTOGRAM_BUCKETS],
+    max: AtomicUsize,
+}
+
+impl Histogram {
+    /// Create a new histogram for the given service ID, such that it could be used in a static
+    ///
+    /// `base` is the upper bound of the first bucket, in the unit of the samples.
+    pub const fn new(service_id: u8, name: &'static str, base: usize) -> Self {
+        Self {
+            node: intrusive_list::Node::uninit(),
+            service_id,
+            name,
+            base: if base == 0 { 1 } else { base },
+            buckets: [const { AtomicUsize::new(0) }; HISTOGRAM_BUCKETS],
+            max: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register this histogram, forwards any error states (such as double registration) from intrusive_list
+    pub fn register(&'static self) -> intrusive_list::Result<()> {
+        HISTOGRAMS.push(self)
+    }
+
+    /// Record a sample
+    pub fn record(&self, value: usize) {
+        let index = (0..HISTOGRAM_BUCKETS - 1)
+            .find(|index| value < self.base.saturating_mul(1 << index))
+            .unwrap_or(HISTOGRAM_BUCKETS - 1);
+        if let Some(bucket) = self.buckets.get(index) {
+            bucket.fetch_add(1, Ordering::Relaxed);
+        }
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    /// Number of samples in the bucket at `index`
+    pub fn bucket(&self, index: usize) -> usize {
+        self.buckets
+            .get(index)
+            .map_or(0, |bucket| bucket.load(Ordering::Relaxed))
+    }
+
+    /// Upper bound of the first bucket
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Largest sample recorded since the last reset
+    pub fn max(&self) -> usize {
+        self.max.load(Ordering::Relaxed)
+    }
+
+    /// Reset all buckets and the maximum
+    pub fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.max.store(0, Ordering::Relaxed);
+    }
+
+    /// ID of the service this histogram belongs to
+    pub fn service_id(&self) -> u8 {
+        self.service_id
+    }
+
+    /// Name of the measured operation
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl intrusive_list::NodeContainer for Histogram {
+    fn get_node(&self) -> &intrusive_list::Node {
+        &self.node
+    }
+}
+
+static HISTOGRAMS: intrusive_list::IntrusiveList = intrusive_list::IntrusiveList::new();
+
+/// Iterate over the registered histograms, most recently registered first
+pub fn histograms() -> impl Iterator<Item = &'static Histogram> {
+    HISTOGRAMS.iter_only::<Histogram>()
+}
+
+/// Fill a stack region with [`STACK_PAINT`] so its high-water mark can later be measured with [`stack_used_bytes`]
+///
+/// The region must not be in use yet, e.g. painted before the task owning it is started.
+pub fn paint_stack(region: &mut [u32]) {
+    region.fill(STACK_PAINT);
+}
+
+/// Returns the number of bytes of a painted stack region that have been used
+///
+/// `region` is ordered from the lowest address, and the stack is assumed to grow downwards, so any paint left at the
+/// start of the region has never been touched.
+pub fn stack_used_bytes(region: &[u32]) -> usize {
+    let unused = region.iter().take_while(|word| **word == STACK_PAINT).count();
+    (region.len() - unused) * size_of::<u32>()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_high_water() {
+        static METRIC: Metric = Metric::new(1, "host_tx_queue", Kind::QueueDepth, 5);
+        METRIC.register().unwrap();
+
+        METRIC.record(2);
+        METRIC.record(4);
+        METRIC.record(1);
+        assert_eq!(METRIC.high_water(), 4);
+
+        let registered = metrics().find(|metric| metric.name() == "host_tx_queue").unwrap();
+        assert_eq!(registered.capacity(), 5);
+        assert_eq!(registered.high_water(), 4);
+
+        METRIC.reset();
+        assert_eq!(METRIC.high_water(), 0);
+    }
+
+    #[test]
+    fn test_histogram() {
+        static HISTOGRAM: Histogram = Histogram::new(1, "mctp_latency", 100);
+        HISTOGRAM.register().unwrap();
+
+        HISTOGRAM.record(50);
+        HISTOGRAM.record(100);
+        HISTOGRAM.record(399);
+        HISTOGRAM.record(1_000_000);
+        assert_eq!(HISTOGRAM.bucket(0), 1);
+        assert_eq!(HISTOGRAM.bucket(1), 1);
+        assert_eq!(HISTOGRAM.bucket(2), 1);
+        assert_eq!(HISTOGRAM.bucket(HISTOGRAM_BUCKETS - 1), 1);
+        assert_eq!(HISTOGRAM.max(), 1_000_000);
+
+        assert!(histograms().any(|histogram| histogram.name() == "mctp_latency"));
+
+        HISTOGRAM.reset();
+        assert_eq!(HISTOGRAM.bucket(0), 0);
+        assert_eq!(HISTOGRAM.max(), 0);
+    }
+
+    #[test]
+    fn test_stack_used() {
+        let mut stack = [0u32; 16];
+        paint_stack(&mut stack);
+        assert_eq!(stack_used_bytes(&stack), 0);
+
+        // Top four words used
+        for word in stack.iter_mut().skip(12) {
+            *word = 0;
+        }
+        assert_eq!(stack_used_bytes(&stack), 16);
+    }
+}
@@ -0,0 +1,249 @@
+//! Request/response correlation for relays.
+//!
+//! A relay registers each host request in a [`Table`] along with the context needed to reply to it, e.g. the service
+//! the request is addressed to and its MCTP message tag, and carries the returned [`Cookie`] alongside the request.
+//! When the response is ready it is matched back to its request with [`Table::complete`]. A request that isn't
+//! answered before its deadline is freed by [`Table::expire`], and a late response for it is rejected rather than
+//! being sent with the context of whichever request reuses the slot.
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{Mutex, raw::RawMutex};
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::{debug, warn};
+
+/// Default time a relay waits for the response to a host request
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Identifies a single in-flight request
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Cookie(pub u16);
+
+/// Correlation errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// All slots are occupied by in-flight requests
+    TableFull,
+    /// No in-flight request matches the cookie, most likely because it timed out
+    UnknownCookie(Cookie),
+}
+
+#[derive(Copy, Clone)]
+struct Entry<C> {
+    cookie: Cookie,
+    deadline: Instant,
+    context: C,
+}
+
+struct State<C, const N: usize> {
+    /// Request occupying each slot
+    slots: [Option<Entry<C>>; N],
+    /// Next cookie to hand out
+    next_cookie: u16,
+}
+
+/// Table of up to `N` in-flight requests, each with the context `C` needed to reply to it
+pub struct Table<M: RawMutex, C: Copy, const N: usize> {
+    state: Mutex<M, RefCell<State<C, N>>>,
+    timeout: Duration,
+}
+
+impl<M: RawMutex, C: Copy, const N: usize> Table<M, C, N> {
+    /// Create a new, empty table where requests time out after `timeout`
+    pub const fn new(timeout: Duration) -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(State {
+                slots: [const { None }; N],
+                next_cookie: 0,
+            })),
+            timeout,
+        }
+    }
+
+    /// Register a new in-flight request
+    ///
+    /// The returned cookie must be carried with the request and handed back to [`Self::complete`] with the response.
+    pub fn register(&self, context: C) -> Result<Cookie, Error> {
+        let deadline = Instant::now() + self.timeout;
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+
+            // Skip any cookie still in use, there are at most N of them so this terminates
+            let mut cookie = Cookie(state.next_cookie);
+            while state.slots.iter().flatten().any(|entry| entry.cookie == cookie) {
+                cookie = Cookie(cookie.0.wrapping_add(1));
+            }
+
+            let slot = state
+                .slots
+                .iter_mut()
+                .find(|slot| slot.is_none())
+                .ok_or(Error::TableFull)?;
+            *slot = Some(Entry {
+                cookie,
+                deadline,
+                context,
+            });
+            state.next_cookie = cookie.0.wrapping_add(1);
+            Ok(cookie)
+        })
+    }
+
+    /// Match a response to the in-flight request identified by `cookie`, freeing its slot
+    ///
+    /// Returns the context the request was registered with. A request past its deadline is freed and rejected even if
+    /// [`Self::expire`] hasn't run yet.
+    pub fn complete(&self, cookie: Cookie) -> Result<C, Error> {
+        let now = Instant::now();
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            let entry = state
+                .slots
+                .iter_mut()
+                .find(|slot| slot.is_some_and(|entry| entry.cookie == cookie))
+                .and_then(Option::take)
+                .filter(|entry| entry.deadline > now)
+                .ok_or_else(|| {
+                    // Not an error on our side, the host gave up on the request before the response was ready
+                    debug!("Response for unknown or expired cookie: {}", cookie.0);
+                    Error::UnknownCookie(cookie)
+                })?;
+            Ok(entry.context)
+        })
+    }
+
+    /// Free every request whose deadline is at or before `now`, returns the number of requests freed
+    pub fn expire(&self, now: Instant) -> usize {
+        self.state.lock(|state| {
+            let mut expired = 0;
+            for slot in state.borrow_mut().slots.iter_mut() {
+                if let Some(entry) = slot
+                    && entry.deadline <= now
+                {
+                    warn!("Host request {} timed out", entry.cookie.0);
+                    *slot = None;
+                    expired += 1;
+                }
+            }
+            expired
+        })
+    }
+
+    /// Wait for the next in-flight request to time out and free it, never completes while the table is empty
+    ///
+    /// DROP SAFETY: Only waits on a timer, the table is updated after the wait completes
+    pub async fn wait_expire(&self) -> usize {
+        match self.next_deadline() {
+            Some(deadline) => {
+                Timer::at(deadline).await;
+                self.expire(Instant::now())
+            }
+            None => core::future::pending().await,
+        }
+    }
+
+    /// Returns the earliest deadline of the in-flight requests
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.state
+            .lock(|state| state.borrow().slots.iter().flatten().map(|entry| entry.deadline).min())
+    }
+
+    /// Returns the number of in-flight requests
+    pub fn in_flight(&self) -> usize {
+        self.state
+            .lock(|state| state.borrow().slots.iter().filter(|slot| slot.is_some()).count())
+    }
+}
+
+impl<M: RawMutex, C: Copy, const N: usize> Default for Table<M, C, N> {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::GlobalRawMutex;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    type TestTable = Table<GlobalRawMutex, u32, 2>;
+
+    #[test]
+    fn test_table_full() {
+        let table = TestTable::new(TIMEOUT);
+        let first = table.register(1).unwrap();
+        let second = table.register(2).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(table.in_flight(), 2);
+        assert_eq!(table.register(3), Err(Error::TableFull));
+
+        // Completing a request frees its slot
+        assert_eq!(table.complete(first), Ok(1));
+        assert_eq!(table.in_flight(), 1);
+        let third = table.register(3).unwrap();
+        assert_ne!(third, second);
+    }
+
+    /// Responses arriving out of order must be matched to their own request
+    #[test]
+    fn test_out_of_order_responses() {
+        let table = TestTable::new(TIMEOUT);
+        let first = table.register(1).unwrap();
+        let second = table.register(2).unwrap();
+
+        assert_eq!(table.complete(second), Ok(2));
+        assert_eq!(table.complete(first), Ok(1));
+        assert_eq!(table.in_flight(), 0);
+
+        // Each request is only answered once
+        assert_eq!(table.complete(first), Err(Error::UnknownCookie(first)));
+    }
+
+    /// A response that arrives after its request timed out must not be matched to the request reusing the slot
+    #[tokio::test]
+    async fn test_timeout_frees_slot() {
+        let time = odp_test_support::time::pause();
+        let table = TestTable::new(TIMEOUT);
+        let stale = table.register(1).unwrap();
+        assert_eq!(table.next_deadline(), Some(Instant::now() + TIMEOUT));
+
+        time.advance(TIMEOUT - Duration::from_millis(1)).await;
+        assert_eq!(table.expire(Instant::now()), 0);
+        time.advance(Duration::from_millis(1)).await;
+        assert_eq!(table.expire(Instant::now()), 1);
+        assert_eq!(table.in_flight(), 0);
+        assert_eq!(table.next_deadline(), None);
+
+        let pending = table.register(2).unwrap();
+        assert_eq!(table.complete(stale), Err(Error::UnknownCookie(stale)));
+        assert_eq!(table.complete(pending), Ok(2));
+    }
+
+    /// A late response is rejected even before the expired request is swept
+    #[tokio::test]
+    async fn test_late_response_rejected() {
+        let time = odp_test_support::time::pause();
+        let table = TestTable::new(TIMEOUT);
+        let cookie = table.register(1).unwrap();
+
+        time.advance(TIMEOUT).await;
+        assert_eq!(table.complete(cookie), Err(Error::UnknownCookie(cookie)));
+        assert_eq!(table.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_wait_expire() {
+        let time = odp_test_support::time::pause();
+        let table = TestTable::new(TIMEOUT);
+        table.register(1).unwrap();
+
+        let (expired, _) = embassy_futures::join::join(table.wait_expire(), time.advance(TIMEOUT)).await;
+        assert_eq!(expired, 1);
+        assert_eq!(table.in_flight(), 0);
+    }
+}
@@ -0,0 +1,66 @@
+//! Classification of errors returned to the host.
+//!
+//! Each relayed service defines its own wire error codes, so the host can't tell from the code alone whether a
+//! request is worth retrying. Host error types implement [`HostError`] to place each of their codes in a common
+//! [`ErrorClass`]. Services should map internal errors onto the most specific host error available rather than an
+//! unspecified failure, following these conventions:
+//!
+//! * [`ErrorClass::InvalidArgument`]: the request itself is wrong (unknown instance, out of range value, malformed
+//!   payload). Retrying the same request will fail the same way.
+//! * [`ErrorClass::Unsupported`]: the request is well formed but this device doesn't implement it.
+//! * [`ErrorClass::NotReady`]: the device can't service the request yet, e.g. a battery that hasn't been identified.
+//!   The request may succeed later.
+//! * [`ErrorClass::Timeout`]: the device didn't respond in time. The request may succeed if retried.
+//! * [`ErrorClass::Hardware`]: the device reported a failure. Retrying is unlikely to help.
+//! * [`ErrorClass::Unspecified`]: the cause is unknown, treated as fatal.
+
+/// Category of an error returned to the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ErrorClass {
+    /// The request is invalid
+    InvalidArgument,
+    /// The request isn't supported
+    Unsupported,
+    /// The device isn't ready to service the request
+    NotReady,
+    /// The device didn't respond in time
+    Timeout,
+    /// The device reported a hardware failure
+    Hardware,
+    /// The cause of the failure is unknown
+    Unspecified,
+}
+
+impl ErrorClass {
+    /// Returns true if the host may retry a request that failed with an error of this class.
+    pub const fn is_retryable(self) -> bool {
+        matches!(self, ErrorClass::NotReady | ErrorClass::Timeout)
+    }
+}
+
+/// An error returned to the host.
+pub trait HostError {
+    /// Returns the class of this error.
+    fn class(&self) -> ErrorClass;
+
+    /// Returns true if the host may retry the request that failed with this error.
+    fn is_retryable(&self) -> bool {
+        self.class().is_retryable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_errors_are_retryable() {
+        assert!(ErrorClass::NotReady.is_retryable());
+        assert!(ErrorClass::Timeout.is_retryable());
+        assert!(!ErrorClass::InvalidArgument.is_retryable());
+        assert!(!ErrorClass::Unsupported.is_retryable());
+        assert!(!ErrorClass::Hardware.is_retryable());
+        assert!(!ErrorClass::Unspecified.is_retryable());
+    }
+}
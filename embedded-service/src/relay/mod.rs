@@ -1,5 +1,8 @@
 //! Helper code for serialization/deserialization of arbitrary messages to/from the embedded controller via a relay service, e.g. the eSPI service.
 
+pub mod correlation;
+pub mod host_error;
+
 /// Error type for serializing/deserializing messages
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -128,6 +131,38 @@ pub mod mctp {
         ) -> impl core::future::Future<Output = Self::ResultType> + 'a;
     }
 
+    /// Identifies a host request presented to the host request filter
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct HostRequestInfo {
+        /// ID of the service the request is addressed to
+        pub service_id: u8,
+        /// ID of the message within the service
+        pub message_id: u16,
+    }
+
+    /// Decision of the host request filter
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum FilterAction {
+        /// Process the request
+        Allow,
+        /// Reject the request, the host receives an error result with an empty body
+        Drop,
+    }
+
+    /// Extension point consulted by relay services before a host request is processed
+    pub struct HostRequestFilter;
+
+    impl crate::hook::ExtensionPoint for HostRequestFilter {
+        const NAME: &'static str = "relay::HostRequestFilter";
+        type Input = HostRequestInfo;
+        type Output = FilterAction;
+    }
+
+    /// Host request filter hook, all requests are allowed if nothing is registered
+    pub static HOST_REQUEST_FILTER_HOOK: crate::hook::HookSlot<HostRequestFilter> = crate::hook::HookSlot::new();
+
     // Traits below this point are intended for consumption by relay services (e.g. the eSPI service), not individual services that want their messages relayed.
     // In general, you should not implement these yourself; rather, you should leverage the `impl_odp_mctp_relay_handler` macro to do that for you.
 
@@ -137,6 +172,49 @@ pub mod mctp {
     pub trait RelayHeader<ServiceIdType> {
         /// Return the ID of the service associated with the request
         fn get_service_id(&self) -> ServiceIdType;
+
+        /// Return the ID of the message within the service
+        fn get_message_id(&self) -> u16;
+
+        /// Construct the header of an error result answering the request with this header
+        fn create_rejection_header(&self) -> Self
+        where
+            Self: Sized;
+    }
+
+    /// Error result with an empty body, sent to the host when a relay service rejects a request without processing it,
+    /// e.g. because the host request filter dropped it
+    ///
+    /// `R` is the result type of the relay handler, whose header and MCTP message type are reused.
+    pub struct RejectedRequest<R>(core::marker::PhantomData<R>);
+
+    impl<R> RejectedRequest<R> {
+        /// Create a new rejection
+        pub const fn new() -> Self {
+            Self(core::marker::PhantomData)
+        }
+    }
+
+    impl<R> Default for RejectedRequest<R> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<'buf, R: mctp_rs::MctpMessageTrait<'buf>> mctp_rs::MctpMessageTrait<'buf> for RejectedRequest<R> {
+        const MESSAGE_TYPE: u8 = R::MESSAGE_TYPE;
+        type Header = R::Header;
+
+        fn serialize<M: mctp_rs::MctpMedium>(self, _buffer: &mut [u8]) -> mctp_rs::MctpPacketResult<usize, M> {
+            Ok(0)
+        }
+
+        fn deserialize<M: mctp_rs::MctpMedium>(
+            _header: &Self::Header,
+            _buffer: &'buf [u8],
+        ) -> mctp_rs::MctpPacketResult<Self, M> {
+            Ok(Self::new())
+        }
     }
 
     /// Contains additional methods that must be implemented on the relay response type.
@@ -391,6 +469,18 @@ pub mod mctp {
                         fn get_service_id(&self) -> OdpService {
                             self.service
                         }
+
+                        fn get_message_id(&self) -> u16 {
+                            self.message_id
+                        }
+
+                        fn create_rejection_header(&self) -> OdpHeader {
+                            OdpHeader {
+                                message_type: OdpMessageType::Result { is_error: true },
+                                service: self.service,
+                                message_id: self.message_id,
+                            }
+                        }
                     }
 
                     #[derive(Clone)]
@@ -0,0 +1,180 @@
+//! Service state snapshots
+//!
+//! Services expose a compact snapshot of their internal state (e.g. current consumer, battery state, port status)
+//! that the host can request through the debug service, so a single tool can take a full EC state dump for bug
+//! reports. A [`StateProvider`] associates a service ID with a [`ServiceState`] and is registered once at init time,
+//! [`snapshot`] then takes the snapshot for a service ID.
+//!
+//! Snapshots are taken synchronously, so services whose state sits behind an async lock publish their snapshot to a
+//! [`RetainedState`] whenever it changes instead of implementing [`ServiceState`] themselves.
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+
+use crate::{GlobalRawMutex, intrusive_list};
+
+/// Maximum size of a state snapshot in bytes
+pub const MAX_SNAPSHOT_LEN: usize = 64;
+
+/// Service IDs of the snapshots provided by services in this repository
+///
+/// Platform specific services should use IDs starting at [`PLATFORM`](id::PLATFORM).
+pub mod id {
+    /// Power policy service
+    pub const POWER_POLICY: u8 = 0x10;
+    /// Battery service
+    pub const BATTERY: u8 = 0x11;
+    /// Type-C service
+    pub const TYPE_C: u8 = 0x12;
+    /// First service ID available to platform specific services
+    pub const PLATFORM: u8 = 0x80;
+}
+
+/// Trait implemented by services that can provide a state snapshot
+pub trait ServiceState: Sync {
+    /// Serialize a snapshot of the current state into `buffer`, returning the number of bytes written
+    fn snapshot(&self, buffer: &mut [u8; MAX_SNAPSHOT_LEN]) -> usize;
+}
+
+/// Registration node for a service state snapshot
+pub struct StateProvider {
+    node: intrusive_list::Node,
+    service_id: u8,
+    state: &'static dyn ServiceState,
+}
+
+impl StateProvider {
+    /// Create a new state provider for the given service ID, such that it could be used in a static
+    pub const fn new(service_id: u8, state: &'static dyn ServiceState) -> Self {
+        Self {
+            node: intrusive_list::Node::uninit(),
+            service_id,
+            state,
+        }
+    }
+
+    /// Register this provider, forwards any error states (such as double registration) from intrusive_list
+    pub fn register(&'static self) -> intrusive_list::Result<()> {
+        STATE_PROVIDERS.push(self)
+    }
+}
+
+impl intrusive_list::NodeContainer for StateProvider {
+    fn get_node(&self) -> &intrusive_list::Node {
+        &self.node
+    }
+}
+
+static STATE_PROVIDERS: intrusive_list::IntrusiveList = intrusive_list::IntrusiveList::new();
+
+/// Take a snapshot from the provider registered for `service_id`
+///
+/// Returns the number of bytes written to `buffer`, or `None` if no provider is registered for `service_id`.
+pub fn snapshot(service_id: u8, buffer: &mut [u8; MAX_SNAPSHOT_LEN]) -> Option<usize> {
+    let provider = STATE_PROVIDERS
+        .iter_only::<StateProvider>()
+        .find(|provider| provider.service_id == service_id)?;
+    Some(provider.state.snapshot(buffer).min(MAX_SNAPSHOT_LEN))
+}
+
+/// Latest snapshot published by a service
+pub struct RetainedState {
+    state: Mutex<GlobalRawMutex, RefCell<([u8; MAX_SNAPSHOT_LEN], usize)>>,
+}
+
+impl RetainedState {
+    /// Create a new, empty snapshot
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(([0; MAX_SNAPSHOT_LEN], 0))),
+        }
+    }
+
+    /// Replace the snapshot with the one written by `f`, which returns the number of bytes written
+    pub fn publish(&self, f: impl FnOnce(SnapshotWriter) -> usize) {
+        let mut buffer = [0; MAX_SNAPSHOT_LEN];
+        let len = f(SnapshotWriter::new(&mut buffer)).min(MAX_SNAPSHOT_LEN);
+        self.state.lock(|state| *state.borrow_mut() = (buffer, len));
+    }
+}
+
+impl Default for RetainedState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServiceState for RetainedState {
+    fn snapshot(&self, buffer: &mut [u8; MAX_SNAPSHOT_LEN]) -> usize {
+        self.state.lock(|state| {
+            let (snapshot, len) = *state.borrow();
+            *buffer = snapshot;
+            len
+        })
+    }
+}
+
+/// Writes the fields of a snapshot in order, a field that doesn't fit in the remaining space is dropped
+pub struct SnapshotWriter<'a> {
+    buffer: &'a mut [u8; MAX_SNAPSHOT_LEN],
+    len: usize,
+}
+
+impl<'a> SnapshotWriter<'a> {
+    /// Create a writer starting at the beginning of `buffer`
+    pub fn new(buffer: &'a mut [u8; MAX_SNAPSHOT_LEN]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Append `bytes` to the snapshot
+    pub fn put(mut self, bytes: &[u8]) -> Self {
+        let end = self.len.saturating_add(bytes.len());
+        if let Some(field) = self.buffer.get_mut(self.len..end) {
+            field.copy_from_slice(bytes);
+            self.len = end;
+        }
+        self
+    }
+
+    /// Returns the number of bytes written
+    pub fn finish(self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retained_state() {
+        static STATE: RetainedState = RetainedState::new();
+        static PROVIDER: StateProvider = StateProvider::new(id::PLATFORM, &STATE);
+        PROVIDER.register().unwrap();
+
+        let mut buffer = [0xff; MAX_SNAPSHOT_LEN];
+        assert_eq!(snapshot(id::PLATFORM, &mut buffer), Some(0));
+
+        STATE.publish(|writer| writer.put(&[1]).put(&2u16.to_le_bytes()).finish());
+        assert_eq!(snapshot(id::PLATFORM, &mut buffer), Some(3));
+        assert_eq!(buffer.get(..3).unwrap(), &[1, 2, 0]);
+
+        // Unregistered services have no snapshot
+        assert_eq!(snapshot(id::PLATFORM + 1, &mut buffer), None);
+    }
+
+    #[test]
+    fn test_snapshot_writer_overflow() {
+        let mut buffer = [0; MAX_SNAPSHOT_LEN];
+        let len = SnapshotWriter::new(&mut buffer)
+            .put(&[1; MAX_SNAPSHOT_LEN - 1])
+            .put(&[2, 2])
+            .put(&[3])
+            .finish();
+
+        // The field that doesn't fit is dropped, later fields still fill the remaining space
+        assert_eq!(len, MAX_SNAPSHOT_LEN);
+        assert_eq!(buffer.last(), Some(&3));
+    }
+}
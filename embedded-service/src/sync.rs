@@ -1,9 +1,16 @@
 //! Synchronization utilities
 
+use core::cell::RefCell;
+use core::future::poll_fn;
 use core::ops::DerefMut;
+use core::task::Poll;
 
+use embassy_sync::blocking_mutex;
+use embassy_sync::waitqueue::MultiWakerRegistration;
 use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
 
+use crate::GlobalRawMutex;
+
 /// General trait for types that allow locking to access an inner object
 ///
 /// This trait allows code to be generic over multiple types that provide
@@ -30,3 +37,259 @@ impl<M: RawMutex, T> Lockable for Mutex<M, T> {
         self.lock()
     }
 }
+
+/// Runs a closure when dropped, unless disarmed
+///
+/// Used to restore state when a future is dropped part way through an operation, e.g. when a long-running controller
+/// command is cancelled by a `select`. The closure runs from `drop` so it must not block.
+pub struct CancelGuard<F: FnOnce()> {
+    on_cancel: Option<F>,
+}
+
+impl<F: FnOnce()> CancelGuard<F> {
+    /// Create a new armed guard
+    pub fn new(on_cancel: F) -> Self {
+        Self {
+            on_cancel: Some(on_cancel),
+        }
+    }
+
+    /// Disarm the guard, the operation completed so there's nothing to restore
+    pub fn disarm(mut self) {
+        self.on_cancel = None;
+    }
+}
+
+impl<F: FnOnce()> Drop for CancelGuard<F> {
+    fn drop(&mut self) {
+        if let Some(on_cancel) = self.on_cancel.take() {
+            on_cancel();
+        }
+    }
+}
+
+/// Run `future` to completion, calling `on_cancel` if it's dropped before completing
+///
+/// DROP SAFETY: `on_cancel` runs if the returned future is dropped, see [`CancelGuard`]
+pub async fn cancel_safe<F: Future>(future: F, on_cancel: impl FnOnce()) -> F::Output {
+    let guard = CancelGuard::new(on_cancel);
+    let output = future.await;
+    guard.disarm();
+    output
+}
+
+struct WatchState<T, const WAITERS: usize> {
+    value: Option<T>,
+    /// Incremented on every send so watchers can tell which values they've already seen
+    version: u32,
+    wakers: MultiWakerRegistration<WAITERS>,
+}
+
+/// Holds the latest value of some state and lets any number of tasks wait for it to change
+///
+/// Replaces the pattern of a `Signal` paired with a separately cached copy of the value: the watch is the single
+/// source of truth, readers get the current value with [`Watch::try_get`] and wait for updates through a
+/// [`Watcher`]. Watchers only ever see the latest value, intermediate values sent while a watcher isn't polling are
+/// coalesced. `new` is `const` so a watch can be placed in a `static`.
+///
+/// `WAITERS` is the number of tasks that can wait concurrently, exceeding it wakes all registered waiters early so
+/// they re-register, which is correct but less efficient.
+pub struct Watch<T: Copy, const WAITERS: usize = 4> {
+    state: blocking_mutex::Mutex<GlobalRawMutex, RefCell<WatchState<T, WAITERS>>>,
+}
+
+impl<T: Copy, const WAITERS: usize> Watch<T, WAITERS> {
+    /// Create a new watch with no value
+    pub const fn new() -> Self {
+        Self::new_inner(None)
+    }
+
+    /// Create a new watch with an initial value
+    pub const fn new_with(value: T) -> Self {
+        Self::new_inner(Some(value))
+    }
+
+    const fn new_inner(value: Option<T>) -> Self {
+        Self {
+            state: blocking_mutex::Mutex::new(RefCell::new(WatchState {
+                value,
+                version: 0,
+                wakers: MultiWakerRegistration::new(),
+            })),
+        }
+    }
+
+    /// Set a new value and wake all watchers
+    pub fn send(&self, value: T) {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.value = Some(value);
+            state.version = state.version.wrapping_add(1);
+            state.wakers.wake();
+        });
+    }
+
+    /// Set a new value if it differs from the current one, returns true if watchers were woken
+    pub fn send_if_modified(&self, value: T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if state.value == Some(value) {
+                return false;
+            }
+            state.value = Some(value);
+            state.version = state.version.wrapping_add(1);
+            state.wakers.wake();
+            true
+        })
+    }
+
+    /// Return the current value, if one has been sent
+    pub fn try_get(&self) -> Option<T> {
+        self.state.lock(|state| state.borrow().value)
+    }
+
+    /// Return the current value, waiting for one to be sent if there isn't one yet
+    pub async fn get(&self) -> T {
+        poll_fn(|cx| {
+            self.state.lock(|state| {
+                let mut state = state.borrow_mut();
+                match state.value {
+                    Some(value) => Poll::Ready(value),
+                    None => {
+                        state.wakers.register(cx.waker());
+                        Poll::Pending
+                    }
+                }
+            })
+        })
+        .await
+    }
+
+    /// Create a watcher that waits for values sent after this call
+    pub fn watcher(&self) -> Watcher<'_, T, WAITERS> {
+        Watcher {
+            watch: self,
+            version: self.state.lock(|state| state.borrow().version),
+        }
+    }
+}
+
+impl<T: Copy, const WAITERS: usize> Default for Watch<T, WAITERS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tracks which values of a [`Watch`] a single task has seen
+pub struct Watcher<'a, T: Copy, const WAITERS: usize = 4> {
+    watch: &'a Watch<T, WAITERS>,
+    version: u32,
+}
+
+impl<T: Copy, const WAITERS: usize> Watcher<'_, T, WAITERS> {
+    /// Wait until a value this watcher hasn't seen is sent and return it
+    pub async fn changed(&mut self) -> T {
+        poll_fn(|cx| match self.try_changed() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                self.watch
+                    .state
+                    .lock(|state| state.borrow_mut().wakers.register(cx.waker()));
+                // A send between `try_changed` and registering would otherwise be missed
+                match self.try_changed() {
+                    Some(value) => Poll::Ready(value),
+                    None => Poll::Pending,
+                }
+            }
+        })
+        .await
+    }
+
+    /// Return the current value if this watcher hasn't seen it yet
+    pub fn try_changed(&mut self) -> Option<T> {
+        self.watch.state.lock(|state| {
+            let state = state.borrow();
+            if state.version == self.version {
+                return None;
+            }
+            self.version = state.version;
+            state.value
+        })
+    }
+
+    /// Return the current value without marking it as seen
+    pub fn get(&self) -> Option<T> {
+        self.watch.try_get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use embassy_futures::block_on;
+    use embassy_futures::join::join;
+    use embassy_futures::select::{Either, select};
+
+    use super::*;
+
+    #[test]
+    fn test_cancel_safe_completed() {
+        let cancelled = Cell::new(false);
+        assert_eq!(block_on(cancel_safe(async { 5 }, || cancelled.set(true))), 5);
+        assert!(!cancelled.get());
+    }
+
+    #[test]
+    fn test_cancel_safe_dropped() {
+        let cancelled = Cell::new(false);
+        let result = block_on(select(
+            cancel_safe(core::future::pending::<()>(), || cancelled.set(true)),
+            async {},
+        ));
+        assert!(matches!(result, Either::Second(())));
+        assert!(cancelled.get());
+    }
+
+    #[test]
+    fn test_watch_get() {
+        let watch: Watch<u32> = Watch::new();
+        assert_eq!(watch.try_get(), None);
+        watch.send(3);
+        assert_eq!(watch.try_get(), Some(3));
+        assert_eq!(block_on(watch.get()), 3);
+    }
+
+    #[test]
+    fn test_watcher_sees_latest_value_once() {
+        let watch: Watch<u32> = Watch::new_with(1);
+        let mut watcher = watch.watcher();
+        assert_eq!(watcher.try_changed(), None);
+
+        watch.send(2);
+        watch.send(3);
+        assert_eq!(block_on(watcher.changed()), 3);
+        assert_eq!(watcher.try_changed(), None);
+    }
+
+    #[test]
+    fn test_watch_send_if_modified() {
+        let watch: Watch<u32> = Watch::new_with(1);
+        let mut watcher = watch.watcher();
+        assert!(!watch.send_if_modified(1));
+        assert_eq!(watcher.try_changed(), None);
+        assert!(watch.send_if_modified(2));
+        assert_eq!(watcher.try_changed(), Some(2));
+    }
+
+    #[test]
+    fn test_watcher_wakes_on_send() {
+        let watch: Watch<u32> = Watch::new();
+        let mut watcher = watch.watcher();
+        let result = block_on(join(watcher.changed(), async { watch.send(5) }));
+        assert_eq!(result, (5, ()));
+    }
+}
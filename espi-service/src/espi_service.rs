@@ -1,14 +1,25 @@
 use core::slice;
 
-use embassy_futures::select::select;
+use embassy_futures::select::{Either4, select4};
 use embassy_imxrt::espi;
 use embassy_sync::channel::Channel;
 use embassy_sync::mutex::Mutex;
-use embedded_services::{GlobalRawMutex, error, info, trace};
+use embassy_time::Instant;
+use embedded_services::hook::{ExtensionPoint, HookSlot};
+use embedded_services::host_notification::{Doorbell, HostPowerState, NotificationSet};
+use embedded_services::metrics::Metric;
+use embedded_services::relay::correlation::{self, Cookie};
+use embedded_services::relay::mctp::RejectedRequest;
+use embedded_services::{GlobalRawMutex, error, info, trace, warn};
+use mctp_rs::MctpMessageTag;
 use mctp_rs::smbus_espi::SmbusEspiMedium;
 use mctp_rs::smbus_espi::SmbusEspiReplyContext;
 
-const HOST_TX_QUEUE_SIZE: usize = 5;
+use crate::latency::{LatencyConfig, Transaction};
+use crate::memory_map::{MemoryMap, RegionAccess};
+
+/// Default number of host responses that can be queued
+pub const HOST_TX_QUEUE_SIZE: usize = 5;
 
 // OOB port number for NXP IMXRT
 // REVISIT: When adding support for other platforms, refactor this as they don't have a notion of port IDs
@@ -20,8 +31,28 @@ const ASSEMBLY_BUF_SIZE: usize = 256;
 #[derive(Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 struct HostResultMessage<RelayHandler: embedded_services::relay::mctp::RelayHandler> {
-    pub handler_service_id: RelayHandler::ServiceIdType,
-    pub message: RelayHandler::ResultEnumType,
+    /// Identifies the request being answered in the correlation table
+    pub cookie: Cookie,
+    pub message: HostResultPayload<RelayHandler>,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum HostResultPayload<RelayHandler: embedded_services::relay::mctp::RelayHandler> {
+    /// Result produced by the relay handler
+    Result(RelayHandler::ResultEnumType),
+    /// The request was rejected without being processed, the header of the error result to send
+    Rejected(RelayHandler::HeaderType),
+}
+
+/// Context needed to reply to a host request, kept in the correlation table while the request is in flight
+#[derive(Clone, Copy)]
+struct RequestContext<ServiceId> {
+    service_id: ServiceId,
+    /// Message tag of the request, the response must carry the same tag
+    message_tag: MctpMessageTag,
+    /// When the request was received from the host
+    received_at: Instant,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -32,23 +63,33 @@ pub enum Error {
 }
 
 /// The memory required by the eSPI service to run
-pub struct Resources<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> {
-    inner: Option<ServiceInner<'hw, RelayHandler>>,
+pub struct Resources<
+    'hw,
+    RelayHandler: embedded_services::relay::mctp::RelayHandler,
+    const HOST_TX_QUEUE: usize = HOST_TX_QUEUE_SIZE,
+> {
+    inner: Option<ServiceInner<'hw, RelayHandler, HOST_TX_QUEUE>>,
 }
 
-impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> Default for Resources<'hw, RelayHandler> {
+impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler, const HOST_TX_QUEUE: usize> Default
+    for Resources<'hw, RelayHandler, HOST_TX_QUEUE>
+{
     fn default() -> Self {
         Self { inner: None }
     }
 }
 
 /// Service runner for the eSPI service.  Users must call the run() method on the runner for the service to start processing events.
-pub struct Runner<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> {
-    inner: &'hw ServiceInner<'hw, RelayHandler>,
+pub struct Runner<
+    'hw,
+    RelayHandler: embedded_services::relay::mctp::RelayHandler,
+    const HOST_TX_QUEUE: usize = HOST_TX_QUEUE_SIZE,
+> {
+    inner: &'hw ServiceInner<'hw, RelayHandler, HOST_TX_QUEUE>,
 }
 
-impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler>
-    odp_service_common::runnable_service::ServiceRunner<'hw> for Runner<'hw, RelayHandler>
+impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler, const HOST_TX_QUEUE: usize>
+    odp_service_common::runnable_service::ServiceRunner<'hw> for Runner<'hw, RelayHandler, HOST_TX_QUEUE>
 {
     /// Run the service event loop.
     async fn run(self) -> embedded_services::Never {
@@ -56,22 +97,31 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler>
     }
 }
 
-pub struct Service<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> {
-    _inner: &'hw ServiceInner<'hw, RelayHandler>,
+/// eSPI service
+///
+/// `HOST_TX_QUEUE` is the number of host responses that can be queued, defaulting to [`HOST_TX_QUEUE_SIZE`].
+pub struct Service<
+    'hw,
+    RelayHandler: embedded_services::relay::mctp::RelayHandler,
+    const HOST_TX_QUEUE: usize = HOST_TX_QUEUE_SIZE,
+> {
+    _inner: &'hw ServiceInner<'hw, RelayHandler, HOST_TX_QUEUE>,
 }
 
-impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> odp_service_common::runnable_service::Service<'hw>
-    for Service<'hw, RelayHandler>
+impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler, const HOST_TX_QUEUE: usize>
+    odp_service_common::runnable_service::Service<'hw> for Service<'hw, RelayHandler, HOST_TX_QUEUE>
 {
-    type Resources = Resources<'hw, RelayHandler>;
-    type Runner = Runner<'hw, RelayHandler>;
+    type Resources = Resources<'hw, RelayHandler, HOST_TX_QUEUE>;
+    type Runner = Runner<'hw, RelayHandler, HOST_TX_QUEUE>;
 }
 
-impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> Service<'hw, RelayHandler> {
+impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler, const HOST_TX_QUEUE: usize>
+    Service<'hw, RelayHandler, HOST_TX_QUEUE>
+{
     pub async fn new(
-        resources: &'hw mut Resources<'hw, RelayHandler>,
+        resources: &'hw mut Resources<'hw, RelayHandler, HOST_TX_QUEUE>,
         params: InitParams<'hw, RelayHandler>,
-    ) -> Result<(Self, Runner<'hw, RelayHandler>), core::convert::Infallible> {
+    ) -> Result<(Self, Runner<'hw, RelayHandler, HOST_TX_QUEUE>), core::convert::Infallible> {
         let inner = resources.inner.insert(ServiceInner::new(params).await);
         Ok((Self { _inner: inner }, Runner { inner }))
     }
@@ -80,52 +130,129 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> Service<'h
 pub struct InitParams<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> {
     pub espi: espi::Espi<'hw>,
     pub relay_handler: RelayHandler,
+    /// Host memory-mapped regions served through the peripheral channel ports
+    pub memory_map: MemoryMap<'hw>,
+    /// Coalesced host notification doorbell, if any
+    pub doorbell: Option<HostDoorbell<'hw>>,
+    /// Records the high-water mark of the host response queue, which holds `HOST_TX_QUEUE` responses
+    pub host_tx_queue_metric: Option<&'hw Metric>,
+    /// Host transaction latency budgets and histograms
+    pub latency: LatencyConfig<'hw>,
+}
+
+/// Doorbell raising coalesced host notifications
+///
+/// The combined event bitmap is exposed to the host through the memory map, the handler of the region containing it
+/// should call [`Doorbell::acknowledge`] when the host reads it, e.g. through a
+/// [`DoorbellRegion`](crate::memory_map::DoorbellRegion).
+#[derive(Clone, Copy)]
+pub struct HostDoorbell<'hw> {
+    /// Doorbell rung by services updating host-visible sections
+    pub doorbell: &'hw Doorbell,
+    /// eSPI notification offset used for the combined interrupt
+    pub irq_offset: u8,
 }
 
-struct ServiceInner<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> {
+/// Extension point decoding the host power state from the eSPI virtual wires
+///
+/// Which wires report the sleep state is platform specific, so the hook reads them and returns the new host power
+/// state, or `None` if the wire change didn't affect it.
+pub struct HostPowerStateChange;
+
+impl ExtensionPoint for HostPowerStateChange {
+    const NAME: &'static str = "espi::HostPowerStateChange";
+    type Input = ();
+    type Output = Option<HostPowerState>;
+}
+
+/// Host power state hook, consulted on every virtual wire change. The host is assumed to stay in S0 if nothing is
+/// registered.
+pub static HOST_POWER_STATE_HOOK: HookSlot<HostPowerStateChange> = HookSlot::new();
+
+/// Extension point raising the platform's wake signal, e.g. the PME# virtual wire or a wake GPIO
+pub struct HostWake;
+
+impl ExtensionPoint for HostWake {
+    const NAME: &'static str = "espi::HostWake";
+    type Input = ();
+    type Output = ();
+}
+
+/// Host wake hook, called before the doorbell interrupt when a wake-worthy notification was rung while the host was
+/// asleep
+pub static HOST_WAKE_HOOK: HookSlot<HostWake> = HookSlot::new();
+
+struct ServiceInner<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler, const HOST_TX_QUEUE: usize> {
     espi: Mutex<GlobalRawMutex, espi::Espi<'hw>>,
-    host_tx_queue: Channel<GlobalRawMutex, HostResultMessage<RelayHandler>, HOST_TX_QUEUE_SIZE>,
+    host_tx_queue: Channel<GlobalRawMutex, HostResultMessage<RelayHandler>, HOST_TX_QUEUE>,
+    /// Host requests awaiting a response
+    pending: correlation::Table<GlobalRawMutex, RequestContext<RelayHandler::ServiceIdType>, HOST_TX_QUEUE>,
     relay_handler: RelayHandler,
+    memory_map: MemoryMap<'hw>,
+    doorbell: Option<HostDoorbell<'hw>>,
+    host_tx_queue_metric: Option<&'hw Metric>,
+    latency: LatencyConfig<'hw>,
 }
 
-impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> ServiceInner<'hw, RelayHandler> {
+impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler, const HOST_TX_QUEUE: usize>
+    ServiceInner<'hw, RelayHandler, HOST_TX_QUEUE>
+{
     async fn new(mut init_params: InitParams<'hw, RelayHandler>) -> Self {
         init_params.espi.wait_for_plat_reset().await;
 
         Self {
             espi: Mutex::new(init_params.espi),
             host_tx_queue: Channel::new(),
+            pending: correlation::Table::default(),
             relay_handler: init_params.relay_handler,
+            memory_map: init_params.memory_map,
+            doorbell: init_params.doorbell,
+            host_tx_queue_metric: init_params.host_tx_queue_metric,
+            latency: init_params.latency,
         }
     }
 
     async fn run(&self) -> embedded_services::Never {
         let mut espi = self.espi.lock().await;
         loop {
-            let event = select(espi.wait_for_event(), self.host_tx_queue.receive()).await;
+            let event = select4(
+                espi.wait_for_event(),
+                self.host_tx_queue.receive(),
+                self.wait_for_doorbell(),
+                self.pending.wait_expire(),
+            )
+            .await;
 
             match event {
-                embassy_futures::select::Either::First(controller_event) => {
+                Either4::First(controller_event) => {
                     self.process_controller_event(&mut espi, controller_event)
                         .await
                         .unwrap_or_else(|e| {
                             error!("Critical error processing eSPI controller event: {:?}", e);
                         });
                 }
-                embassy_futures::select::Either::Second(host_msg) => {
-                    self.process_response_to_host(&mut espi, host_msg).await
+                Either4::Second(host_msg) => self.process_response_to_host(&mut espi, host_msg).await,
+                Either4::Third((doorbell, notifications)) => {
+                    if doorbell.doorbell.take_wake() && HOST_WAKE_HOOK.call(()).await.is_none() {
+                        warn!("espi: No host wake hook registered, notifying without waking the host");
+                    }
+                    espi.irq_push(doorbell.irq_offset).await;
+                    trace!("espi: Coalesced notifications {:#x} sent to Host", notifications.bits());
+                }
+                Either4::Fourth(expired) => {
+                    warn!("espi: {} host requests timed out without a response", expired);
                 }
             }
         }
     }
 
-    // TODO The notification system was not actually used, so this is currently dead code.
-    //      We need to implement some interface for triggering notifications from other subsystems, and it may do something like this:
-    //
-    // async fn process_notification_to_host(&self, espi: &mut espi::Espi<'_>, notification: &NotificationMsg) {
-    //     espi.irq_push(notification.offset).await;
-    //     info!("espi: Notification id {} sent to Host!", notification.offset);
-    // }
+    /// Wait for the next coalesced doorbell, never completes if no doorbell is configured
+    async fn wait_for_doorbell(&self) -> (HostDoorbell<'hw>, NotificationSet) {
+        match self.doorbell {
+            Some(doorbell) => (doorbell, doorbell.doorbell.wait().await),
+            None => core::future::pending().await,
+        }
+    }
 
     fn write_to_hw(&self, espi: &mut espi::Espi<'hw>, packet: &[u8]) -> Result<(), embassy_imxrt::espi::Error> {
         // Send packet via your transport medium
@@ -144,16 +271,18 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> ServiceInn
     ) -> Result<(), Error> {
         match event {
             Ok(espi::Event::PeripheralEvent(port_event)) => {
+                let received_at = Instant::now();
                 info!(
                     "eSPI PeripheralEvent Port: {}, direction: {}, address: {}, offset: {}, length: {}",
                     port_event.port, port_event.direction, port_event.offset, port_event.base_addr, port_event.length,
                 );
 
-                // We're not handling these - communication is all through OOB
-
+                self.process_region_access(&port_event);
                 espi.complete_port(port_event.port);
+                self.latency.record(Transaction::MemoryMap, received_at.elapsed());
             }
             Ok(espi::Event::OOBEvent(port_event)) => {
+                let received_at = Instant::now();
                 info!(
                     "eSPI OOBEvent Port: {}, direction: {}, address: {}, offset: {}, length: {}",
                     port_event.port, port_event.direction, port_event.offset, port_event.base_addr, port_event.length,
@@ -175,9 +304,17 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> ServiceInn
                     match mctp_ctx.deserialize_packet(src_slice) {
                         Ok(Some(message)) => {
                             trace!("MCTP packet successfully deserialized");
+                            let message_tag = message.reply_context.message_tag;
                             match message.parse_as::<RelayHandler::RequestEnumType>() {
                                 Ok((header, body)) => {
-                                    self.process_request_to_ec((header, body), espi, &port_event).await?;
+                                    self.process_request_to_ec(
+                                        (header, body),
+                                        espi,
+                                        &port_event,
+                                        message_tag,
+                                        received_at,
+                                    )
+                                    .await?;
                                 }
                                 Err(e) => {
                                     error!("MCTP ODP type malformed: {:?}", e);
@@ -212,6 +349,7 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> ServiceInn
             }
             Ok(espi::Event::WireChange(_)) => {
                 info!("eSPI WireChange");
+                self.process_wire_change().await;
             }
             Err(e) => {
                 error!("eSPI Failed with error: {:?}", e);
@@ -220,6 +358,52 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> ServiceInn
         Ok(())
     }
 
+    /// Report host power state changes signalled through the virtual wires to the doorbell
+    async fn process_wire_change(&self) {
+        let Some(doorbell) = self.doorbell else {
+            return;
+        };
+
+        if let Some(Some(power_state)) = HOST_POWER_STATE_HOOK.call(()).await {
+            info!("espi: Host power state {:?}", power_state);
+            // Raises any notifications deferred while the host was asleep
+            doorbell.doorbell.set_power_state(power_state);
+        }
+    }
+
+    /// Dispatch a completed host access to the handler of the region mapped through the port
+    fn process_region_access(&self, port_event: &espi::PortEvent) {
+        let Some(region) = self.memory_map.region(port_event.port) else {
+            trace!("No memory region mapped through port {}", port_event.port);
+            return;
+        };
+
+        let offset = port_event.offset as u32;
+        let Some(range) = region.address_range(port_event.base_addr as usize, offset, port_event.length) else {
+            error!(
+                "Access at offset {} length {} is outside the region mapped through port {}",
+                port_event.offset, port_event.length, port_event.port
+            );
+            return;
+        };
+
+        let access = if port_event.direction {
+            // SAFETY: The HAL reports the port's memory window, which stays valid until the port is completed, and the
+            // range was checked to be within the region mapped through it.
+            let data = unsafe { slice::from_raw_parts(range.start as *const u8, range.len()) };
+            RegionAccess::Write { offset, data }
+        } else {
+            RegionAccess::Read {
+                offset,
+                length: port_event.length,
+            }
+        };
+
+        if let Err(e) = region.dispatch(access) {
+            error!("Failed to dispatch access through port {}: {:?}", port_event.port, e);
+        }
+    }
+
     async fn process_request_to_ec(
         &self,
         (header, body): (
@@ -228,27 +412,85 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> ServiceInn
         ),
         espi: &mut espi::Espi<'hw>,
         port_event: &espi::PortEvent,
+        message_tag: MctpMessageTag,
+        received_at: Instant,
     ) -> Result<(), Error> {
-        use embedded_services::relay::mctp::RelayHeader;
+        use embedded_services::relay::mctp::{FilterAction, HOST_REQUEST_FILTER_HOOK, HostRequestInfo, RelayHeader};
         info!("Host Request received");
 
         espi.complete_port(port_event.port);
 
-        let response = self.relay_handler.process_request(body).await;
-        self.host_tx_queue
-            .try_send(HostResultMessage {
-                handler_service_id: header.get_service_id(),
-                message: response,
-            })
-            .map_err(|_| Error::Serialize)?;
+        let request_info = HostRequestInfo {
+            service_id: header.get_service_id().into(),
+            message_id: header.get_message_id(),
+        };
+        let action = HOST_REQUEST_FILTER_HOOK
+            .call_or_else(request_info, || FilterAction::Allow)
+            .await;
+
+        let cookie = match self.pending.register(RequestContext {
+            service_id: header.get_service_id(),
+            message_tag,
+            received_at,
+        }) {
+            Ok(cookie) => cookie,
+            Err(e) => {
+                warn!("Host request {:?} rejected: {:?}", request_info, e);
+                return Ok(());
+            }
+        };
+
+        let message = if action == FilterAction::Drop {
+            // Answer with an error so the host doesn't wait for a response that never comes
+            warn!("Host request {:?} dropped by filter", request_info);
+            HostResultPayload::Rejected(header.create_rejection_header())
+        } else {
+            HostResultPayload::Result(self.relay_handler.process_request(body).await)
+        };
+        if self
+            .host_tx_queue
+            .try_send(HostResultMessage { cookie, message })
+            .is_err()
+        {
+            // Free the slot, the response is lost
+            let _ = self.pending.complete(cookie);
+            return Err(Error::Serialize);
+        }
+        if let Some(metric) = self.host_tx_queue_metric {
+            metric.record(self.host_tx_queue.len());
+        }
 
         Ok(())
     }
 
     async fn process_response_to_host(&self, espi: &mut espi::Espi<'hw>, response: HostResultMessage<RelayHandler>) {
-        match self.serialize_packet_from_subsystem(espi, response).await {
+        // The host has given up on a request that timed out, sending the response now could be mistaken for the
+        // response to a later request
+        let context = match self.pending.complete(response.cookie) {
+            Ok(context) => context,
+            Err(e) => {
+                warn!("Dropping response to a timed out host request: {:?}", e);
+                return;
+            }
+        };
+
+        let result = match response.message {
+            HostResultPayload::Result(message) => {
+                use embedded_services::relay::mctp::RelayResponse;
+                let header = message.create_header(&context.service_id);
+                self.serialize_packet_from_subsystem(espi, context, header, message)
+                    .await
+            }
+            HostResultPayload::Rejected(header) => {
+                let message = RejectedRequest::<RelayHandler::ResultEnumType>::new();
+                self.serialize_packet_from_subsystem(espi, context, header, message)
+                    .await
+            }
+        };
+        match result {
             Ok(()) => {
-                trace!("Full packet successfully sent to host!")
+                trace!("Full packet successfully sent to host!");
+                self.latency.record(Transaction::Mctp, context.received_at.elapsed());
             }
             Err(e) => {
                 // TODO we may want to consider sending a failure message to the debug service or something, but that'll require
@@ -258,33 +500,33 @@ impl<'hw, RelayHandler: embedded_services::relay::mctp::RelayHandler> ServiceInn
         }
     }
 
-    async fn serialize_packet_from_subsystem(
+    async fn serialize_packet_from_subsystem<Message>(
         &self,
         espi: &mut espi::Espi<'hw>,
-        result: HostResultMessage<RelayHandler>,
-    ) -> Result<(), Error> {
-        use embedded_services::relay::mctp::RelayResponse;
+        context: RequestContext<RelayHandler::ServiceIdType>,
+        header: RelayHandler::HeaderType,
+        message: Message,
+    ) -> Result<(), Error>
+    where
+        Message: for<'buf> mctp_rs::MctpMessageTrait<'buf, Header = RelayHandler::HeaderType>,
+    {
         let mut assembly_buf = [0u8; ASSEMBLY_BUF_SIZE];
         let mut mctp_ctx =
             mctp_rs::MctpPacketContext::new(mctp_rs::smbus_espi::SmbusEspiMedium, assembly_buf.as_mut_slice());
 
         let reply_context: mctp_rs::MctpReplyContext<SmbusEspiMedium> = mctp_rs::MctpReplyContext {
             source_endpoint_id: mctp_rs::EndpointId::Id(0x80),
-            destination_endpoint_id: mctp_rs::EndpointId::Id(result.handler_service_id.into()), // TODO We're currently using this incorrectly - it should be the bus address of the host. Revisit once we have assigned a bus address to the host.
+            destination_endpoint_id: mctp_rs::EndpointId::Id(context.service_id.into()), // TODO We're currently using this incorrectly - it should be the bus address of the host. Revisit once we have assigned a bus address to the host.
             packet_sequence_number: mctp_rs::MctpSequenceNumber::new(0),
-            message_tag: mctp_rs::MctpMessageTag::try_from(3).map_err(|e| {
-                error!("serialize_packet_from_subsystem: {:?}", e);
-                Error::Serialize
-            })?,
+            message_tag: context.message_tag,
             medium_context: SmbusEspiReplyContext {
                 destination_slave_address: 1,
                 source_slave_address: 0,
             }, // Medium-specific context
         };
 
-        let header = result.message.create_header(&result.handler_service_id);
         let mut packet_state = mctp_ctx
-            .serialize_packet(reply_context, (header, result.message))
+            .serialize_packet(reply_context, (header, message))
             .map_err(|e| {
                 error!("serialize_packet_from_subsystem: {:?}", e);
                 Error::Serialize
@@ -0,0 +1,333 @@
+//! eSPI flash access channel passthrough.
+//!
+//! Lets the host read, write and erase regions of the EC-attached SPI flash. Each host-visible region is backed by a
+//! partition-manager [`Partition`], whose read-only or read/write marker decides what the host may do with it; flash
+//! outside of a mapped region is never accessible. Requests received on the flash channel are queued with
+//! [`FlashChannel::submit`] and serviced by [`FlashChannel::process`], which splits reads into completions no larger
+//! than the channel's maximum payload.
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Channel;
+use embedded_services::{GlobalRawMutex, error, trace};
+use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
+use partition_manager::{Partition, RO, RW};
+
+/// Maximum payload of a single flash channel transaction in bytes
+pub const MAX_PAYLOAD: usize = 64;
+
+/// Flash access errors reported to the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FlashError {
+    /// The access isn't fully contained in a single host-visible region
+    NoRegion,
+    /// The region doesn't permit the requested operation
+    AccessDenied,
+    /// The access isn't aligned to the flash's requirements
+    NotAligned,
+    /// Another user currently has exclusive write access to the region
+    Busy,
+    /// The flash device returned an error
+    Device,
+    /// The request queue is full
+    QueueFull,
+}
+
+impl<E> From<partition_manager::Error<E>> for FlashError {
+    fn from(value: partition_manager::Error<E>) -> Self {
+        match value {
+            partition_manager::Error::OutOfBounds => FlashError::NoRegion,
+            partition_manager::Error::NotAligned => FlashError::NotAligned,
+            partition_manager::Error::ReadOnly => FlashError::AccessDenied,
+            partition_manager::Error::Busy => FlashError::Busy,
+            partition_manager::Error::Inner(_) => FlashError::Device,
+        }
+    }
+}
+
+/// Flash access request from the host, addresses are in the host's view of the flash
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FlashRequest {
+    /// Read `length` bytes starting at `address`
+    Read { address: u32, length: u32 },
+    /// Write `data` starting at `address`
+    Write {
+        address: u32,
+        data: heapless::Vec<u8, MAX_PAYLOAD>,
+    },
+    /// Erase `length` bytes starting at `address`
+    Erase { address: u32, length: u32 },
+}
+
+/// Partition backing a host-visible region
+pub enum RegionPartition<'a, F, M: RawMutex> {
+    /// The host may only read this region
+    ReadOnly(&'a Partition<'a, F, RO, M>),
+    /// The host may read, write and erase this region
+    ReadWrite(&'a Partition<'a, F, RW, M>),
+}
+
+/// A region of flash visible to the host
+pub struct HostRegion<'a, F, M: RawMutex> {
+    /// Host address of the start of the region
+    pub base: u32,
+    /// Partition the region is mapped onto
+    pub partition: RegionPartition<'a, F, M>,
+}
+
+impl<F: ReadNorFlash, M: RawMutex> HostRegion<'_, F, M> {
+    fn size(&self) -> usize {
+        match self.partition {
+            RegionPartition::ReadOnly(partition) => partition.capacity(),
+            RegionPartition::ReadWrite(partition) => partition.capacity(),
+        }
+    }
+
+    /// Returns the partition offset of the given access if it lies within this region
+    fn offset_of(&self, address: u32, length: u32) -> Option<u32> {
+        let offset = address.checked_sub(self.base)?;
+        let end = usize::try_from(offset.checked_add(length)?).ok()?;
+        (end <= self.size()).then_some(offset)
+    }
+}
+
+/// Split a read into chunks that don't cross a [`MAX_PAYLOAD`] aligned boundary, returns `(address, length)` pairs
+fn read_chunks(address: u32, length: u32) -> impl Iterator<Item = (u32, u32)> {
+    let end = address.saturating_add(length);
+    let mut next = address;
+    core::iter::from_fn(move || {
+        if next >= end {
+            return None;
+        }
+        let boundary = (next / MAX_PAYLOAD as u32)
+            .saturating_add(1)
+            .saturating_mul(MAX_PAYLOAD as u32);
+        let chunk = (next, boundary.min(end) - next);
+        next = boundary;
+        Some(chunk)
+    })
+}
+
+/// Host flash access channel
+pub struct FlashChannel<'a, F, M: RawMutex, const REGIONS: usize, const QUEUE: usize> {
+    regions: [HostRegion<'a, F, M>; REGIONS],
+    queue: Channel<GlobalRawMutex, FlashRequest, QUEUE>,
+}
+
+impl<'a, F: NorFlash, M: RawMutex, const REGIONS: usize, const QUEUE: usize> FlashChannel<'a, F, M, REGIONS, QUEUE> {
+    /// Create a new flash channel exposing the given regions to the host
+    pub fn new(regions: [HostRegion<'a, F, M>; REGIONS]) -> Self {
+        Self {
+            regions,
+            queue: Channel::new(),
+        }
+    }
+
+    /// Queue a request received from the host
+    pub fn submit(&self, request: FlashRequest) -> Result<(), FlashError> {
+        self.queue.try_send(request).map_err(|_| {
+            error!("Flash channel request queue full");
+            FlashError::QueueFull
+        })
+    }
+
+    /// Wait for the next queued request
+    pub async fn receive(&self) -> FlashRequest {
+        self.queue.receive().await
+    }
+
+    /// Look up the region containing the whole access and the access offset within its partition
+    fn resolve(&self, address: u32, length: u32) -> Result<(&HostRegion<'a, F, M>, u32), FlashError> {
+        self.regions
+            .iter()
+            .find_map(|region| region.offset_of(address, length).map(|offset| (region, offset)))
+            .ok_or(FlashError::NoRegion)
+    }
+
+    /// Service a request
+    ///
+    /// `complete` is called with the data of each read completion, and once with an empty slice when a write or erase
+    /// completes.
+    pub async fn process(&self, request: &FlashRequest, mut complete: impl FnMut(&[u8])) -> Result<(), FlashError> {
+        trace!("Flash channel request: {:?}", request);
+        match request {
+            FlashRequest::Read { address, length } => {
+                let (region, offset) = self.resolve(*address, *length)?;
+                let mut buf = [0u8; MAX_PAYLOAD];
+                for (chunk_address, chunk_length) in read_chunks(*address, *length) {
+                    let chunk_offset = offset + (chunk_address - address);
+                    let chunk = buf.get_mut(..chunk_length as usize).ok_or(FlashError::Device)?;
+                    match region.partition {
+                        RegionPartition::ReadOnly(partition) => partition.lock().await.read(chunk_offset, chunk).await,
+                        RegionPartition::ReadWrite(partition) => partition.lock().await.read(chunk_offset, chunk).await,
+                    }?;
+                    complete(chunk);
+                }
+            }
+            FlashRequest::Write { address, data } => {
+                let (region, offset) = self.resolve(*address, data.len() as u32)?;
+                let RegionPartition::ReadWrite(partition) = region.partition else {
+                    return Err(FlashError::AccessDenied);
+                };
+                partition.lock().await.write(offset, data).await?;
+                complete(&[]);
+            }
+            FlashRequest::Erase { address, length } => {
+                let (region, offset) = self.resolve(*address, *length)?;
+                let RegionPartition::ReadWrite(partition) = region.partition else {
+                    return Err(FlashError::AccessDenied);
+                };
+                partition.lock().await.erase(offset, offset + length).await?;
+                complete(&[]);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::mutex::Mutex;
+    use embedded_storage_async::nor_flash::{ErrorType, NorFlashErrorKind};
+
+    const SIZE: usize = 0x200;
+
+    /// RAM backed flash where erased bytes read as 0xff
+    struct RamFlash([u8; SIZE]);
+
+    impl ErrorType for RamFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 1;
+
+        async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let start = offset as usize;
+            let src = self
+                .0
+                .get(start..start + bytes.len())
+                .ok_or(NorFlashErrorKind::OutOfBounds)?;
+            bytes.copy_from_slice(src);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            SIZE
+        }
+    }
+
+    impl NorFlash for RamFlash {
+        const WRITE_SIZE: usize = 1;
+        const ERASE_SIZE: usize = 1;
+
+        async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.0
+                .get_mut(from as usize..to as usize)
+                .ok_or(NorFlashErrorKind::OutOfBounds)?
+                .fill(0xff);
+            Ok(())
+        }
+
+        async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let start = offset as usize;
+            self.0
+                .get_mut(start..start + bytes.len())
+                .ok_or(NorFlashErrorKind::OutOfBounds)?
+                .copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_chunks_split_at_payload_boundaries() {
+        let mut chunks = read_chunks(0x30, 0x60);
+        assert_eq!(chunks.next(), Some((0x30, 0x10)));
+        assert_eq!(chunks.next(), Some((0x40, 0x40)));
+        assert_eq!(chunks.next(), Some((0x80, 0x10)));
+        assert_eq!(chunks.next(), None);
+    }
+
+    #[test]
+    fn access_control() {
+        let mut flash = RamFlash([0u8; SIZE]);
+        for (i, byte) in flash.0.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let storage = Mutex::<NoopRawMutex, _>::new(flash);
+        let boot = Partition::<_, RO, _>::new(&storage, 0x000, 0x100);
+        let settings = Partition::<_, RW, _>::new(&storage, 0x100, 0x100);
+
+        let channel: FlashChannel<'_, _, _, 2, 1> = FlashChannel::new([
+            HostRegion {
+                base: 0x1000,
+                partition: RegionPartition::ReadOnly(&boot),
+            },
+            HostRegion {
+                base: 0x2000,
+                partition: RegionPartition::ReadWrite(&settings),
+            },
+        ]);
+
+        block_on(async {
+            // Reads are split into completions of at most the maximum payload
+            let mut completions = 0;
+            let request = FlashRequest::Read {
+                address: 0x1010,
+                length: 0x50,
+            };
+            channel
+                .process(&request, |data| {
+                    assert_eq!(data.first().copied(), Some(0x10 + completions * 0x30));
+                    completions += 1;
+                })
+                .await
+                .unwrap();
+            assert_eq!(completions, 2);
+
+            // Writes to a read-only region and accesses spanning regions are rejected
+            let write = |address| FlashRequest::Write {
+                address,
+                data: heapless::Vec::from_slice(&[0xaa; 4]).unwrap(),
+            };
+            assert_eq!(
+                channel.process(&write(0x1000), |_| {}).await,
+                Err(FlashError::AccessDenied)
+            );
+            assert_eq!(channel.process(&write(0x10fe), |_| {}).await, Err(FlashError::NoRegion));
+
+            channel.process(&write(0x2000), |_| {}).await.unwrap();
+            let mut read_back = [0u8; 4];
+            let request = FlashRequest::Read {
+                address: 0x2000,
+                length: 4,
+            };
+            channel
+                .process(&request, |data| read_back.copy_from_slice(data))
+                .await
+                .unwrap();
+            assert_eq!(read_back, [0xaa; 4]);
+        });
+    }
+
+    #[test]
+    fn queue_full() {
+        let storage = Mutex::<NoopRawMutex, _>::new(RamFlash([0u8; SIZE]));
+        let settings = Partition::<_, RW, _>::new(&storage, 0, 0x100);
+        let channel: FlashChannel<'_, _, _, 1, 1> = FlashChannel::new([HostRegion {
+            base: 0,
+            partition: RegionPartition::ReadWrite(&settings),
+        }]);
+
+        let request = FlashRequest::Erase {
+            address: 0,
+            length: 0x10,
+        };
+        channel.submit(request.clone()).unwrap();
+        assert_eq!(channel.submit(request.clone()), Err(FlashError::QueueFull));
+        assert_eq!(block_on(channel.receive()), request);
+    }
+}
@@ -0,0 +1,89 @@
+//! Host transaction latency instrumentation.
+//!
+//! The host gives up on a transaction that takes longer than its eSPI timeout, so slow handlers show up as sporadic
+//! host errors rather than EC failures. The service measures the time from receiving each request to completing it,
+//! logs a warning when the configured budget is exceeded and records the latency in an optional
+//! [`Histogram`](embedded_services::metrics::Histogram), read back through the debug service.
+use embassy_time::Duration;
+use embedded_services::metrics::Histogram;
+use embedded_services::warn;
+
+/// Kind of host transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Transaction {
+    /// Memory-mapped access through a peripheral channel port, from the port event until the port is completed
+    MemoryMap,
+    /// MCTP request, from reception of the request until the last response packet is sent
+    Mctp,
+}
+
+/// Latency budgets and histograms
+#[derive(Clone, Copy)]
+pub struct LatencyConfig<'hw> {
+    /// Budget of a memory-mapped access
+    pub memory_map_budget: Duration,
+    /// Budget of an MCTP transaction
+    pub mctp_budget: Duration,
+    /// Records memory-mapped access latencies in microseconds
+    pub memory_map_histogram: Option<&'hw Histogram>,
+    /// Records MCTP transaction latencies in microseconds
+    pub mctp_histogram: Option<&'hw Histogram>,
+}
+
+impl Default for LatencyConfig<'_> {
+    fn default() -> Self {
+        Self {
+            memory_map_budget: Duration::from_millis(1),
+            mctp_budget: Duration::from_millis(50),
+            memory_map_histogram: None,
+            mctp_histogram: None,
+        }
+    }
+}
+
+impl LatencyConfig<'_> {
+    /// Record the latency of a completed transaction, returns true if it exceeded its budget
+    pub fn record(&self, transaction: Transaction, elapsed: Duration) -> bool {
+        let (budget, histogram) = match transaction {
+            Transaction::MemoryMap => (self.memory_map_budget, self.memory_map_histogram),
+            Transaction::Mctp => (self.mctp_budget, self.mctp_histogram),
+        };
+
+        if let Some(histogram) = histogram {
+            histogram.record(usize::try_from(elapsed.as_micros()).unwrap_or(usize::MAX));
+        }
+
+        let exceeded = elapsed > budget;
+        if exceeded {
+            warn!(
+                "eSPI {:?} transaction took {} us, budget {} us",
+                transaction,
+                elapsed.as_micros(),
+                budget.as_micros()
+            );
+        }
+        exceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_checks_budget() {
+        static HISTOGRAM: Histogram = Histogram::new(0, "espi_mctp", 1000);
+        let config = LatencyConfig {
+            mctp_histogram: Some(&HISTOGRAM),
+            ..Default::default()
+        };
+
+        assert!(!config.record(Transaction::Mctp, Duration::from_micros(500)));
+        assert!(config.record(Transaction::Mctp, Duration::from_millis(60)));
+        assert!(!config.record(Transaction::MemoryMap, Duration::from_micros(10)));
+
+        assert_eq!(HISTOGRAM.bucket(0), 1);
+        assert_eq!(HISTOGRAM.max(), 60_000);
+    }
+}
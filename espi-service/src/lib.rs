@@ -22,5 +22,10 @@
 #[cfg(not(test))]
 mod espi_service;
 
+// These modules don't touch the eSPI peripheral directly, so they can be tested on desktop
+pub mod flash;
+pub mod latency;
+pub mod memory_map;
+
 #[cfg(not(test))]
 pub use espi_service::*;
@@ -0,0 +1,355 @@
+//! Host memory-mapped regions.
+//!
+//! Each eSPI peripheral channel port exposes a window of EC memory to the host, e.g. ACPI shared memory on one port and
+//! a debug log window on another. A [`MemoryMap`] assigns each port to the service that owns its region so host
+//! accesses are dispatched to that service rather than to a single global handler.
+//!
+//! Within a region, the sections of a particular product, e.g. the battery and thermal blocks of the ACPI shared memory,
+//! are described by a TOML manifest. With the `macros` feature, `create_memory_layout!` generates the region
+//! structure, the host-facing offset constants and the offset to section conversion from the manifest at build time:
+//!
+//! ```toml
+//! [region]
+//! size = 0x100
+//!
+//! [sections]
+//! battery = { offset = 0x00, size = 0x40 }
+//! thermal = { offset = 0x80, size = 0x20 }
+//! ```
+//!
+//! ```ignore
+//! espi_service::memory_map::create_memory_layout!(name: AcpiMemory, section_name: AcpiSection, manifest: "acpi.toml");
+//!
+//! match AcpiSection::resolve(offset) {
+//!     Some((AcpiSection::Battery, offset)) => { /* battery section access */ }
+//!     _ => {}
+//! }
+//! ```
+
+use core::ops::Range;
+
+use embedded_services::host_notification::Doorbell;
+
+#[cfg(feature = "macros")]
+pub use memory_map_macros::create_memory_layout;
+
+/// A completed host access to a memory-mapped region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RegionAccess<'a> {
+    /// The host wrote `data` at `offset` within the region
+    Write { offset: u32, data: &'a [u8] },
+    /// The host read `length` bytes at `offset` within the region
+    Read { offset: u32, length: usize },
+}
+
+impl RegionAccess<'_> {
+    /// Offset of the access within the region
+    pub fn offset(&self) -> u32 {
+        match self {
+            Self::Write { offset, .. } | Self::Read { offset, .. } => *offset,
+        }
+    }
+
+    /// Number of bytes accessed
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Write { data, .. } => data.len(),
+            Self::Read { length, .. } => *length,
+        }
+    }
+
+    /// Returns true if the access is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the access covers the byte at `offset`
+    pub fn covers(&self, offset: u32) -> bool {
+        offset
+            .checked_sub(self.offset())
+            .is_some_and(|relative| (relative as usize) < self.len())
+    }
+}
+
+/// Handler for host accesses to a memory-mapped region
+pub trait RegionHandler {
+    /// Called from the eSPI event loop once the host has completed an access, must not block
+    fn on_access(&self, access: RegionAccess<'_>);
+}
+
+/// Dispatch error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DispatchError {
+    /// No region is mapped through the port
+    Unmapped,
+    /// The access extends past the end of the region
+    OutOfBounds,
+}
+
+/// A memory-mapped region and the handler that owns it
+#[derive(Clone, Copy)]
+pub struct MemoryRegion<'a> {
+    /// Peripheral channel port the region is mapped through
+    pub port: usize,
+    /// Size of the region in bytes
+    pub size: u32,
+    /// Handler notified of host accesses to the region
+    pub handler: &'a dyn RegionHandler,
+}
+
+impl MemoryRegion<'_> {
+    /// Returns true if `length` bytes at `offset` are within the region
+    pub fn contains(&self, offset: u32, length: usize) -> bool {
+        (offset as usize)
+            .checked_add(length)
+            .is_some_and(|end| end <= self.size as usize)
+    }
+
+    /// Address range of an access of `length` bytes at `offset` within a region whose window starts at `base_addr`
+    ///
+    /// Returns `None` if the access isn't within the region.
+    pub fn address_range(&self, base_addr: usize, offset: u32, length: usize) -> Option<Range<usize>> {
+        if !self.contains(offset, length) {
+            return None;
+        }
+
+        let start = base_addr.checked_add(offset as usize)?;
+        Some(start..start.checked_add(length)?)
+    }
+
+    /// Dispatch an access to the region handler, checking that it's within the region
+    pub fn dispatch(&self, access: RegionAccess<'_>) -> Result<(), DispatchError> {
+        if !self.contains(access.offset(), access.len()) {
+            return Err(DispatchError::OutOfBounds);
+        }
+
+        self.handler.on_access(access);
+        Ok(())
+    }
+}
+
+/// Memory-mapped regions exposed to the host
+#[derive(Clone, Copy, Default)]
+pub struct MemoryMap<'a> {
+    regions: &'a [MemoryRegion<'a>],
+}
+
+impl<'a> MemoryMap<'a> {
+    /// Create a new memory map, returns `None` if more than one region uses the same port
+    pub fn new(regions: &'a [MemoryRegion<'a>]) -> Option<Self> {
+        let duplicate = regions
+            .iter()
+            .enumerate()
+            .any(|(i, region)| regions.iter().skip(i + 1).any(|other| other.port == region.port));
+        (!duplicate).then_some(Self { regions })
+    }
+
+    /// Returns the region mapped through `port`
+    pub fn region(&self, port: usize) -> Option<&'a MemoryRegion<'a>> {
+        self.regions.iter().find(|region| region.port == port)
+    }
+
+    /// Dispatch an access to the handler of the region mapped through `port`
+    pub fn dispatch(&self, port: usize, access: RegionAccess<'_>) -> Result<(), DispatchError> {
+        self.region(port).ok_or(DispatchError::Unmapped)?.dispatch(access)
+    }
+}
+
+/// Region handler acknowledging a [`Doorbell`] when the host reads the combined event bitmap
+///
+/// Intended for the region containing the bitmap signalled by
+/// [`HostDoorbell`](crate::HostDoorbell), accesses that don't read the bitmap are ignored.
+pub struct DoorbellRegion<'a> {
+    doorbell: &'a Doorbell,
+    bitmap_offset: u32,
+}
+
+impl<'a> DoorbellRegion<'a> {
+    /// Create a new handler for a bitmap at `bitmap_offset` within the region
+    pub const fn new(doorbell: &'a Doorbell, bitmap_offset: u32) -> Self {
+        Self {
+            doorbell,
+            bitmap_offset,
+        }
+    }
+}
+
+impl RegionHandler for DoorbellRegion<'_> {
+    fn on_access(&self, access: RegionAccess<'_>) {
+        if matches!(access, RegionAccess::Read { .. }) && access.covers(self.bitmap_offset) {
+            self.doorbell.acknowledge();
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    fn write(offset: u32, data: &[u8]) -> RegionAccess<'_> {
+        RegionAccess::Write { offset, data }
+    }
+
+    fn read(offset: u32, length: usize) -> RegionAccess<'static> {
+        RegionAccess::Read { offset, length }
+    }
+
+    #[derive(Default)]
+    struct CountingHandler {
+        writes: Cell<usize>,
+        reads: Cell<usize>,
+    }
+
+    impl RegionHandler for CountingHandler {
+        fn on_access(&self, access: RegionAccess<'_>) {
+            match access {
+                RegionAccess::Write { .. } => self.writes.set(self.writes.get() + 1),
+                RegionAccess::Read { .. } => self.reads.set(self.reads.get() + 1),
+            }
+        }
+    }
+
+    #[test]
+    fn dispatch_by_port() {
+        let acpi = CountingHandler::default();
+        let debug_log = CountingHandler::default();
+        let regions = [
+            MemoryRegion {
+                port: 0,
+                size: 0x100,
+                handler: &acpi,
+            },
+            MemoryRegion {
+                port: 2,
+                size: 0x40,
+                handler: &debug_log,
+            },
+        ];
+        let map = MemoryMap::new(&regions).unwrap();
+
+        assert_eq!(map.dispatch(0, write(4, &[1])), Ok(()));
+        assert_eq!(map.dispatch(2, read(0, 16)), Ok(()));
+        assert_eq!(map.dispatch(1, read(0, 1)), Err(DispatchError::Unmapped));
+
+        assert_eq!((acpi.writes.get(), acpi.reads.get()), (1, 0));
+        assert_eq!((debug_log.writes.get(), debug_log.reads.get()), (0, 1));
+    }
+
+    #[test]
+    fn dispatch_checks_bounds() {
+        let handler = CountingHandler::default();
+        let regions = [MemoryRegion {
+            port: 0,
+            size: 0x40,
+            handler: &handler,
+        }];
+        let map = MemoryMap::new(&regions).unwrap();
+
+        // Last byte of the region
+        assert_eq!(map.dispatch(0, write(0x3f, &[1])), Ok(()));
+        assert_eq!(map.dispatch(0, write(0x3f, &[1, 2])), Err(DispatchError::OutOfBounds));
+        assert_eq!(
+            map.dispatch(0, read(u32::MAX, usize::MAX)),
+            Err(DispatchError::OutOfBounds)
+        );
+        assert_eq!((handler.writes.get(), handler.reads.get()), (1, 0));
+    }
+
+    #[test]
+    fn address_range_includes_offset() {
+        let handler = CountingHandler::default();
+        let region = MemoryRegion {
+            port: 0,
+            size: 0x40,
+            handler: &handler,
+        };
+
+        assert_eq!(
+            region.address_range(0x2000_0000, 0x10, 4),
+            Some(0x2000_0010..0x2000_0014)
+        );
+        assert_eq!(
+            region.address_range(0x2000_0000, 0x3c, 4),
+            Some(0x2000_003c..0x2000_0040)
+        );
+        assert_eq!(region.address_range(0x2000_0000, 0x3d, 4), None);
+        assert_eq!(region.address_range(usize::MAX, 0x10, 4), None);
+    }
+
+    #[test]
+    fn doorbell_acknowledged_on_bitmap_read() {
+        const BITMAP_OFFSET: u32 = 0x10;
+        let doorbell = Doorbell::new(embassy_time::Duration::from_ticks(0));
+        let handler = DoorbellRegion::new(&doorbell, BITMAP_OFFSET);
+        let regions = [MemoryRegion {
+            port: 0,
+            size: 0x40,
+            handler: &handler,
+        }];
+        let map = MemoryMap::new(&regions).unwrap();
+        let id = embedded_services::host_notification::NotificationId::new(1).unwrap();
+
+        doorbell.ring(id);
+        let signalled = embassy_futures::block_on(doorbell.wait());
+
+        // Neither a read elsewhere in the region nor a write to the bitmap acknowledge it
+        map.dispatch(0, read(0, 0x10)).unwrap();
+        map.dispatch(0, write(BITMAP_OFFSET, &[0])).unwrap();
+        assert_eq!(doorbell.acknowledge(), signalled);
+
+        doorbell.ring(id);
+        embassy_futures::block_on(doorbell.wait());
+        map.dispatch(0, read(0x0c, 8)).unwrap();
+        assert!(doorbell.acknowledge().is_empty());
+    }
+
+    #[test]
+    fn duplicate_ports_rejected() {
+        let handler = CountingHandler::default();
+        let regions = [
+            MemoryRegion {
+                port: 2,
+                size: 0x40,
+                handler: &handler,
+            },
+            MemoryRegion {
+                port: 2,
+                size: 0x40,
+                handler: &handler,
+            },
+        ];
+        assert!(MemoryMap::new(&regions).is_none());
+    }
+
+    #[cfg(feature = "macros")]
+    #[allow(dead_code)]
+    mod layout {
+        use crate::memory_map::create_memory_layout;
+
+        create_memory_layout!(
+            name: TestMemory,
+            section_name: TestSection,
+            manifest: "src/memory_map_test.toml"
+        );
+
+        #[test]
+        fn generated_layout() {
+            assert_eq!(TestSection::resolve(0x10), Some((TestSection::Battery, 0x10)));
+            assert_eq!(TestSection::resolve(0x9f), Some((TestSection::Thermal, 0x1f)));
+            // Gap between sections and past the last section
+            assert_eq!(TestSection::resolve(0x40), None);
+            assert_eq!(TestSection::resolve(0xa0), None);
+
+            assert_eq!(TestSection::ALL, [TestSection::Battery, TestSection::Thermal]);
+            assert_eq!(TestSection::Thermal.name(), "thermal");
+            assert_eq!(TestSection::Thermal.size(), 0x20);
+            assert_eq!(TestMemory::THERMAL_OFFSET, 0x80);
+            assert_eq!(TestMemory::SIZE, 0x100);
+            assert_eq!(core::mem::offset_of!(TestMemory, thermal), 0x80);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+#![no_std]
+#![no_main]
+
+use embassy_time::Duration;
+use embedded_mcu_hal::nvram::{Nvram, NvramStorage};
+use embedded_services::info;
+use embedded_services::relay::mctp::RelayServiceHandler;
+use platform_service::relay::{SystemRelayHandler, SystemRequest};
+use platform_service::reset::{self, ResetReason};
+use static_cell::StaticCell;
+use {defmt_rtt as _, panic_probe as _};
+
+/// Time the blockers get to quiesce before the EC resets anyway
+const RESET_TIMEOUT: Duration = Duration::from_millis(500);
+
+static FAN_BLOCKER: reset::Blocker = reset::Blocker::uninit();
+static CHARGER_BLOCKER: reset::Blocker = reset::Blocker::uninit();
+static NVRAM_BLOCKER: reset::Blocker = reset::Blocker::uninit();
+
+#[embassy_executor::task]
+async fn fan_blocker_task() {
+    FAN_BLOCKER
+        .wait_for_reset(async || {
+            // Nothing controls the fans while the EC restarts, so leave them at full speed
+            info!("Fans set to full speed for reset");
+        })
+        .await;
+}
+
+#[embassy_executor::task]
+async fn charger_blocker_task() {
+    CHARGER_BLOCKER
+        .wait_for_reset(async || {
+            // Fall back to the charger's safe default input current until the power policy is running again
+            info!("Charger input limited for reset");
+        })
+        .await;
+}
+
+#[embassy_executor::task]
+async fn nvram_blocker_task(reason_storage: &'static mut dyn NvramStorage<'static, u32>) {
+    NVRAM_BLOCKER
+        .wait_for_reset_with_reason(async |reason| {
+            // Persist the reason so it can be reported to the host after the reset
+            if let Some(reason) = reason {
+                reason_storage.write(reason.0);
+                info!("Reset reason {:#x} saved to NVRAM", reason.0);
+            }
+        })
+        .await;
+}
+
+#[embassy_executor::task]
+async fn reset_task() {
+    reset::reset_task(RESET_TIMEOUT).await
+}
+
+#[embassy_executor::main]
+async fn main(spawner: embassy_executor::Spawner) {
+    let p = embassy_imxrt::init(Default::default());
+
+    static RTC: StaticCell<embassy_imxrt::rtc::Rtc> = StaticCell::new();
+    let rtc = RTC.init(embassy_imxrt::rtc::Rtc::new(p.RTC));
+    let (_dt_clock, rtc_nvram) = rtc.split();
+    let [.., reason_storage] = rtc_nvram.storage();
+
+    embedded_services::init().await;
+    info!("services initialized");
+
+    // Register before spawning the blocker tasks so none of them can miss a reset requested right away
+    for blocker in [&FAN_BLOCKER, &CHARGER_BLOCKER, &NVRAM_BLOCKER] {
+        blocker.register().expect("Reset blocker registered twice");
+    }
+    spawner.spawn(fan_blocker_task().expect("Failed to create fan blocker task"));
+    spawner.spawn(charger_blocker_task().expect("Failed to create charger blocker task"));
+    spawner.spawn(nvram_blocker_task(reason_storage).expect("Failed to create NVRAM blocker task"));
+    spawner.spawn(reset_task().expect("Failed to create reset task"));
+
+    use embedded_services::relay::mctp::impl_odp_mctp_relay_handler;
+    impl_odp_mctp_relay_handler!(
+        EspiRelayHandler;
+        System, 0x01, platform_service::relay::SystemRelayHandler<'static>;
+    );
+
+    let _relay_handler = EspiRelayHandler::new(SystemRelayHandler::new());
+
+    // Here, you'd normally pass _relay_handler to your relay service (e.g. eSPI service).
+    // In this example, we're not leveraging a relay service, so we'll simulate the host reset request directly.
+    //
+    let response = SystemRelayHandler::new()
+        .process_request(SystemRequest::Reset(ResetReason(0x1)))
+        .await;
+    info!("Reset response: {:?}", response);
+
+    // reset_task quiesces the blockers and resets the EC
+    core::future::pending::<()>().await;
+}
@@ -26,7 +26,16 @@ async fn main(spawner: embassy_executor::Spawner) {
     let rtc = RTC.init(embassy_imxrt::rtc::Rtc::new(p.RTC));
     let (dt_clock, rtc_nvram) = rtc.split();
 
-    let [tz, ac_expiration, ac_policy, dc_expiration, dc_policy, ..] = rtc_nvram.storage();
+    let [
+        tz,
+        ac_expiration,
+        ac_policy,
+        ac_wake_status,
+        dc_expiration,
+        dc_policy,
+        dc_wake_status,
+        ..,
+    ] = rtc_nvram.storage();
 
     embedded_services::init().await;
     info!("services initialized");
@@ -38,8 +47,10 @@ async fn main(spawner: embassy_executor::Spawner) {
             tz,
             ac_expiration,
             ac_policy,
+            ac_wake_status,
             dc_expiration,
             dc_policy,
+            dc_wake_status,
         )
     })
     .expect("Failed to spawn time alarm service");
@@ -48,7 +48,7 @@ bind_interrupts!(struct Irqs {
 struct CfuCustomization;
 
 impl cfu_service::customization::Customization for CfuCustomization {
-    fn validate(&mut self, _current: FwVersion, _offer: &FwUpdateOffer) -> FwUpdateOfferResponse {
+    async fn validate(&mut self, _current: FwVersion, _offer: &FwUpdateOffer) -> FwUpdateOfferResponse {
         // For this example, we always accept the offer
         FwUpdateOfferResponse::new_accept(HostToken::Driver)
     }
@@ -5,15 +5,28 @@
 //! trait methods, while the battery service holds the registration and answers
 //! ACPI queries by reading the fuel gauge's cached state.
 //!
+//! ACPI queries are sent through the battery service relay handler, the same path
+//! taken by requests from the host. The mock fuel gauge replays a discharge profile
+//! and periodically has NACK and stale data faults injected. After each update the
+//! battery service's charge control runs against a no-op charger, enforcing an 80%
+//! charge limit. A stand-in power manager receives battery safety faults and shuts
+//! the system down when requested. A recovery task probes the fuel gauge whenever
+//! an ACPI request times out and marks it degraded.
+//!
 //! The example can be run simply by typing `cargo run --bin battery`
 
 use battery_service as bs;
+use battery_service_relay::{AcpiBatteryRequest, AcpiBatteryResponse, BatteryServiceRelayHandler};
 use bs::FuelGauge as _;
-use bs::mock::MockFuelGauge;
+use bs::mock::{Fault, MockFuelGauge, ProfileSample};
 use embassy_executor::{Executor, Spawner};
 use embassy_sync::mutex::Mutex;
 use embassy_time::{Duration, Timer};
+use embedded_mcu_hal::nvram::NvramStorage;
 use embedded_services::GlobalRawMutex;
+use embedded_services::comms::{self, EndpointID, Internal};
+use embedded_services::relay::mctp::RelayServiceHandler;
+use power_policy_interface::charger::mock::{ChargerType, NoopCharger};
 use static_cell::StaticCell;
 
 /// The fuel gauge, wrapped in a mutex so it can be shared between the OEM driving
@@ -21,26 +34,133 @@ use static_cell::StaticCell;
 type FuelGauge = Mutex<GlobalRawMutex, MockFuelGauge>;
 /// The registration: a single fuel gauge, which becomes battery `0`.
 type Reg = bs::ArrayRegistration<'static, FuelGauge, 1>;
+/// Relay handler that answers ACPI requests using the battery service.
+type Relay = BatteryServiceRelayHandler<bs::Service<'static, Reg>>;
+
+/// Discharge profile of a 3S pack under load, one sample is consumed per dynamic data update.
+const DISCHARGE_PROFILE: &[ProfileSample] = &[
+    ProfileSample {
+        relative_soc: 80,
+        voltage_mv: 11_850,
+        current_ma: -1_500,
+    },
+    ProfileSample {
+        relative_soc: 79,
+        voltage_mv: 11_820,
+        current_ma: -1_650,
+    },
+    ProfileSample {
+        relative_soc: 78,
+        voltage_mv: 11_790,
+        current_ma: -2_100,
+    },
+    ProfileSample {
+        relative_soc: 77,
+        voltage_mv: 11_760,
+        current_ma: -1_800,
+    },
+    ProfileSample {
+        relative_soc: 77,
+        voltage_mv: 12_100,
+        current_ma: 1_500,
+    },
+];
+
+/// Number of updates between injected faults.
+const FAULT_INTERVAL: usize = 10;
+
+/// RAM backed stand-in for the NVRAM register holding the charge limit.
+struct RamNvram(u32);
+
+impl<'a> NvramStorage<'a, u32> for RamNvram {
+    fn read(&self) -> u32 {
+        self.0
+    }
+
+    fn write(&mut self, value: u32) {
+        self.0 = value;
+    }
+}
+
+/// Stand-in for the OEM power manager, acting on battery safety faults.
+struct PowerManager {
+    tp: comms::Endpoint,
+}
+
+impl comms::MailboxDelegate for PowerManager {
+    fn receive(&self, message: &comms::Message) -> Result<(), comms::MailboxDelegateError> {
+        // The power endpoint also receives battery telemetry, which isn't used here
+        let Some(fault) = message.data.get::<bs::BatteryFault>() else {
+            return Ok(());
+        };
+
+        if fault.shutdown_requested {
+            embedded_services::error!(
+                "Battery {} safety fault {:?}, shutting down",
+                fault.battery_id.0,
+                fault.faults
+            );
+            std::process::exit(1);
+        }
+        embedded_services::warn!("Battery {} safety faults: {:?}", fault.battery_id.0, fault.faults);
+        Ok(())
+    }
+}
 
 #[embassy_executor::task]
 async fn embassy_main(spawner: Spawner) {
     embedded_services::debug!("Initializing battery service");
     embedded_services::init().await;
 
+    static POWER_MANAGER: PowerManager = PowerManager {
+        tp: comms::Endpoint::uninit(EndpointID::Internal(Internal::Power)),
+    };
+    comms::register_endpoint(&POWER_MANAGER, &POWER_MANAGER.tp)
+        .await
+        .expect("Failed to register power manager endpoint");
+
     // The OEM owns the fuel gauge. A shared reference is handed both to the
     // service (via registration) and to the task that drives it.
     static FUEL_GAUGE: StaticCell<FuelGauge> = StaticCell::new();
     let fuel_gauge: &'static FuelGauge = FUEL_GAUGE.init(Mutex::new(MockFuelGauge::new()));
 
-    let battery_service = bs::Service::new(bs::ArrayRegistration {
-        fuel_gauges: [fuel_gauge],
-    });
+    static NVRAM: StaticCell<RamNvram> = StaticCell::new();
+    static LIMITER: StaticCell<bs::ChargeLimiter<'static>> = StaticCell::new();
+    let limiter = LIMITER.init(bs::ChargeLimiter::new(NVRAM.init(RamNvram(0))));
+    limiter.set_limit(battery_service_interface::ChargeLimit::new(80, 75));
+
+    let battery_service = bs::Service::new_with_charge_control(
+        bs::ArrayRegistration {
+            fuel_gauges: [fuel_gauge],
+        },
+        bs::Config {
+            shutdown_on_safety_fault: true,
+            ..Default::default()
+        },
+        bs::ChargeControl {
+            limiter: Some(limiter),
+            ..Default::default()
+        },
+    );
 
-    spawner.spawn(run_app(fuel_gauge, battery_service).expect("Failed to create run_app task"));
+    static RELAY: StaticCell<Relay> = StaticCell::new();
+    let relay: &'static Relay = RELAY.init(BatteryServiceRelayHandler::new(battery_service));
+
+    static CHARGER: StaticCell<ChargerType> = StaticCell::new();
+    let charger: &'static ChargerType = CHARGER.init(Mutex::new(NoopCharger::new()));
+
+    spawner.spawn(run_app(fuel_gauge, charger, relay).expect("Failed to create run_app task"));
+    spawner.spawn(recovery_task(relay).expect("Failed to create recovery task"));
 }
 
+/// Probes fuel gauges marked degraded after an ACPI request timed out.
 #[embassy_executor::task]
-pub async fn run_app(fuel_gauge: &'static FuelGauge, battery_service: bs::Service<'static, Reg>) {
+async fn recovery_task(relay: &'static Relay) {
+    relay.service().run_recovery().await
+}
+
+#[embassy_executor::task]
+pub async fn run_app(fuel_gauge: &'static FuelGauge, charger: &'static ChargerType, relay: &'static Relay) {
     // Initialize the fuel gauge by driving it directly.
     let mut retries = 5;
     while let Err(e) = bs::mock::init_state_machine(fuel_gauge).await {
@@ -51,6 +171,7 @@ pub async fn run_app(fuel_gauge: &'static FuelGauge, battery_service: bs::Servic
         }
         Timer::after(Duration::from_secs(1)).await;
     }
+    fuel_gauge.lock().await.set_profile(DISCHARGE_PROFILE);
 
     let mut failures: u32 = 0;
     let mut count: usize = 1;
@@ -67,14 +188,39 @@ pub async fn run_app(fuel_gauge: &'static FuelGauge, battery_service: bs::Servic
             embedded_services::error!("Fuel gauge dynamic data error: {:?}", e);
         }
 
+        // Charge control commands the charger from the freshly updated cache.
+        if let Err(e) = relay
+            .service()
+            .update_charge_control(bs::DeviceId(0), &mut *charger.lock().await)
+            .await
+        {
+            embedded_services::warn!("Charge control failed: {:?}", e);
+        }
+
+        // Alternate between a burst of NACKs and a period of stale data.
+        if count.is_multiple_of(FAULT_INTERVAL) {
+            let fault = if count.is_multiple_of(2 * FAULT_INTERVAL) {
+                Fault::StaleData
+            } else {
+                Fault::Nack
+            };
+            embedded_services::info!("FG: injecting {:?}", fault);
+            fuel_gauge.lock().await.inject_fault(fault, 2);
+        }
+
         // The battery service answers ACPI queries by reading the fuel gauge's
-        // cached state. The caller hands the service exclusive access to the
-        // fuel gauge for the duration of the query.
+        // cached state, locking the fuel gauge for the duration of the query.
+        match relay
+            .process_request(AcpiBatteryRequest::GetBst { battery_id: 0 })
+            .await
         {
-            let mut fg = fuel_gauge.lock().await;
-            if battery_service.battery_status(&mut *fg).is_ok() {
-                embedded_services::debug!("Queried battery status via the battery service");
-            }
+            Ok(AcpiBatteryResponse::GetBst { bst }) => embedded_services::info!(
+                "BST: rate {} mA, voltage {} mV",
+                bst.battery_present_rate,
+                bst.battery_present_voltage
+            ),
+            Ok(_) => embedded_services::error!("Unexpected BST response"),
+            Err(e) => embedded_services::warn!("BST failed: {:?}", e),
         }
 
         if failures > 10 {
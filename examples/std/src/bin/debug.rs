@@ -90,6 +90,14 @@ mod espi_service {
                         trace!("mock eSPI staged {copy_len} response bytes for host");
                         self.resp_len.signal(copy_len);
                     }
+                    DebugResponse::DebugGetServiceStateResponse { service_id, len, state } => {
+                        let state = &state[..usize::from(len).min(state.len())];
+                        let copy_len = core::cmp::min(state.len(), buf.len());
+                        buf[..copy_len].copy_from_slice(&state[..copy_len]);
+                        trace!("mock eSPI staged {copy_len} state bytes of service {service_id} for host");
+                        self.resp_len.signal(copy_len);
+                    }
+                    _ => trace!("mock eSPI has nothing to stage for this response"),
                 }
 
                 Ok(())
@@ -178,12 +186,42 @@ mod espi_service {
     }
 }
 
+// State snapshot of the defmt frames task, which the host can request from the debug service
+mod frames_state {
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use debug_service::service_state::{ServiceState, StateProvider};
+    use debug_service_messages::STD_SERVICE_STATE_SIZE;
+
+    /// Service ID the host requests the snapshot with
+    pub const SERVICE_ID: u8 = embedded_services::service_state::id::PLATFORM;
+
+    pub struct FramesState {
+        /// Number of frames logged so far
+        pub frames: AtomicU32,
+    }
+
+    impl ServiceState for FramesState {
+        fn snapshot(&self, buffer: &mut [u8; STD_SERVICE_STATE_SIZE]) -> usize {
+            let frames = self.frames.load(Ordering::Relaxed).to_le_bytes();
+            buffer[..frames.len()].copy_from_slice(&frames);
+            frames.len()
+        }
+    }
+
+    pub static STATE: FramesState = FramesState {
+        frames: AtomicU32::new(0),
+    };
+    pub static PROVIDER: StateProvider = StateProvider::new(SERVICE_ID, &STATE);
+}
+
 #[embassy_executor::task]
 async fn defmt_frames_task() {
+    use core::sync::atomic::Ordering;
     use embassy_time::{Duration, Timer};
     info!("Hello from defmt frames task");
     loop {
         defmt::info!("Hello from defmt frames task");
+        frames_state::STATE.frames.fetch_add(1, Ordering::Relaxed);
         Timer::after(Duration::from_secs(5)).await;
     }
 }
@@ -204,6 +242,9 @@ async fn init_task(spawner: Spawner) {
     info!("spawn defmt_to_host_task");
     spawner.spawn(defmt_to_host_task().expect("Failed to create defmt_to_host task"));
 
+    frames_state::PROVIDER
+        .register()
+        .expect("Failed to register frames state provider");
     spawner.spawn(defmt_frames_task().expect("Failed to create defmt_frames task"));
 }
 
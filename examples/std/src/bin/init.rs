@@ -1,9 +1,22 @@
 use embassy_executor::Executor;
 use embassy_time::{self as _, Timer};
-use embedded_services::init;
+use embedded_services::init::{self, InitNode};
 use log::*;
 use static_cell::StaticCell;
 
+fn comms_init() {
+    info!("Comms initialized");
+}
+
+fn policy_init() {
+    info!("Policy initialized");
+}
+
+static COMMS: InitNode = InitNode::new("comms", &[], comms_init);
+static POLICY: InitNode = InitNode::new("policy", &[&COMMS], policy_init);
+/// Ready once the device task has registered its devices
+static DEVICES: InitNode = InitNode::deferred("devices", &[&POLICY]);
+
 #[embassy_executor::task]
 async fn registration_waiter() {
     info!("Waiting for registration");
@@ -19,6 +32,24 @@ async fn registration_task() {
     info!("Registration task finished");
 }
 
+#[embassy_executor::task]
+async fn init_task() {
+    if let Err(e) = init::run(&[&DEVICES, &POLICY, &COMMS]).await {
+        error!("Failed to initialize services: {e:?}");
+        return;
+    }
+    DEVICES.wait_ready().await;
+    info!("All services initialized");
+}
+
+#[embassy_executor::task]
+async fn device_task() {
+    DEVICES.wait_dependencies().await;
+    info!("Registering devices");
+    Timer::after(embassy_time::Duration::from_millis(500)).await;
+    DEVICES.mark_ready();
+}
+
 fn main() {
     env_logger::builder().filter_level(log::LevelFilter::Info).init();
 
@@ -27,5 +58,7 @@ fn main() {
     executor.run(|spawner| {
         spawner.spawn(registration_waiter().expect("Failed to create registration_waiter task"));
         spawner.spawn(registration_task().expect("Failed to create registration task"));
+        spawner.spawn(init_task().expect("Failed to create init task"));
+        spawner.spawn(device_task().expect("Failed to create device task"));
     });
 }
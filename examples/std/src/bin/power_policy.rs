@@ -3,9 +3,11 @@ use embassy_sync::{
     blocking_mutex::raw::NoopRawMutex,
     channel::{self, Channel},
     mutex::Mutex,
+    pubsub::{DynImmediatePublisher, DynSubscriber, PubSubChannel},
 };
 use embassy_time::{self as _, Timer};
 use embedded_batteries_async::charger::{MilliAmps, MilliVolts};
+use embedded_services::service_state::{self, RetainedState, StateProvider};
 use embedded_services::{GlobalRawMutex, event::NoopSender, named::Named};
 use log::*;
 use power_policy_interface::{
@@ -15,9 +17,12 @@ use power_policy_interface::{
 use power_policy_interface::{
     charger::Charger,
     psu::{Error, Psu},
+    telemetry::{PowerSensor, SensorError, SharedSystemPower, SystemPower},
 };
 use power_policy_service::{
-    charger::ChargerEventReceivers, psu::PsuEventReceivers, service::registration::ArrayRegistration,
+    charger::ChargerEventReceivers,
+    psu::PsuEventReceivers,
+    service::{registration::ArrayRegistration, telemetry::PowerSensors},
 };
 use static_cell::StaticCell;
 
@@ -217,6 +222,15 @@ impl<'a> charger::Charger for ExampleCharger<'a> {
 
 type ChargerType = Mutex<GlobalRawMutex, ExampleCharger<'static>>;
 
+/// Power sensor returning a fixed reading
+struct ExampleSensor(u32);
+
+impl PowerSensor for ExampleSensor {
+    async fn power_mw(&mut self) -> Result<u32, SensorError> {
+        Ok(self.0)
+    }
+}
+
 #[embassy_executor::task]
 async fn run(spawner: Spawner) {
     embedded_services::init().await;
@@ -261,6 +275,14 @@ async fn run(spawner: Spawner) {
         power_policy_service::service::config::Config::default(),
     )));
 
+    // Expose the policy state to host state dumps
+    static STATE_SNAPSHOT: RetainedState = RetainedState::new();
+    static STATE_PROVIDER: StateProvider = StateProvider::new(service_state::id::POWER_POLICY, &STATE_SNAPSHOT);
+    STATE_PROVIDER
+        .register()
+        .expect("Failed to register power policy state provider");
+    service.lock().await.set_state_snapshot(&STATE_SNAPSHOT);
+
     spawner.spawn(
         power_policy_task(
             PsuEventReceivers::new(
@@ -276,6 +298,23 @@ async fn run(spawner: Spawner) {
         .expect("Failed to create power policy task"),
     );
 
+    info!("Creating system power telemetry");
+    static SYSTEM_POWER: SharedSystemPower = SharedSystemPower::new();
+    static TELEMETRY_CHANNEL: StaticCell<PubSubChannel<GlobalRawMutex, SystemPower, 1, 1, 1>> = StaticCell::new();
+    let telemetry_channel = TELEMETRY_CHANNEL.init(PubSubChannel::new());
+    spawner.spawn(
+        telemetry_task(&SYSTEM_POWER, telemetry_channel.dyn_immediate_publisher(), service)
+            .expect("Failed to create telemetry task"),
+    );
+    spawner.spawn(
+        telemetry_listener_task(
+            telemetry_channel
+                .dyn_subscriber()
+                .expect("Failed to create telemetry subscriber"),
+        )
+        .expect("Failed to create telemetry listener task"),
+    );
+
     // Check ready charger 0, should transition to Powered(Init)
     info!("Charger 0 check ready");
     {
@@ -541,6 +580,28 @@ async fn power_policy_task(
     power_policy_service::service::task::task(psu_events, charger_events, power_policy).await;
 }
 
+#[embassy_executor::task]
+async fn telemetry_task(
+    shared: &'static SharedSystemPower,
+    publisher: DynImmediatePublisher<'static, SystemPower>,
+    power_policy: &'static ServiceType,
+) {
+    let sensors = PowerSensors {
+        adapter: ExampleSensor(15000),
+        battery: ExampleSensor(0),
+        rails: [ExampleSensor(4000), ExampleSensor(2500)],
+    };
+    power_policy_service::service::task::telemetry_task(sensors, shared, publisher, power_policy).await;
+}
+
+#[embassy_executor::task]
+async fn telemetry_listener_task(mut subscriber: DynSubscriber<'static, SystemPower>) {
+    loop {
+        let power = subscriber.next_message_pure().await;
+        info!("System power: {} mW", power.psys_mw());
+    }
+}
+
 fn main() {
     env_logger::builder().filter_level(log::LevelFilter::Trace).init();
 
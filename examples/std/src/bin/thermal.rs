@@ -43,6 +43,8 @@ async fn run(spawner: Spawner) {
                 driver: ts::mock::sensor::MockSensor::new(),
                 config: ts::mock::sensor::MockSensor::config(),
                 event_senders,
+                threshold_store: None,
+                threshold_notification: None,
             },
         ))
         .expect("Failed to spawn sensor service");
@@ -56,6 +58,7 @@ async fn run(spawner: Spawner) {
             config: ts::mock::fan::MockFan::config(),
             sensor_service,
             event_senders: &mut [],
+            heat_notices: None,
         },
     ))
     .expect("Failed to spawn fan service");
@@ -73,7 +76,16 @@ async fn run(spawner: Spawner) {
     // However, we can still use the thermal service handle to access registered sensors and fans by id
     static RESOURCES: StaticCell<ts::Resources<MockSensorService, MockFanService>> = StaticCell::new();
     let resources = RESOURCES.init(ts::Resources::default());
-    let thermal_service = ts::Service::init(resources, ts::InitParams { sensors, fans });
+    let thermal_service = ts::Service::init(
+        resources,
+        ts::InitParams {
+            sensors,
+            fans,
+            tmp_slots: &[],
+            fan_slots: &[],
+            system_power: None,
+        },
+    );
 
     spawner.spawn(monitor(thermal_service).expect("Failed to create monitor task"));
     spawner.spawn(
@@ -12,12 +12,12 @@ use log::{debug, info};
 
 use power_policy_interface::capability::PowerCapability;
 use type_c_interface::control::dp::{DpConfig, DpPinConfig, DpStatus};
-use type_c_interface::control::pd::{PdStateMachineConfig, PortStatus};
+use type_c_interface::control::pd::{PdStateMachineConfig, Pdos, PortStatus};
 use type_c_interface::control::power::SystemPowerState;
 use type_c_interface::control::retimer::RetimerFwUpdateState;
 use type_c_interface::control::svid::DiscoveredSvids;
 use type_c_interface::control::tbt::TbtConfig;
-use type_c_interface::control::type_c::TypeCStateMachineState;
+use type_c_interface::control::type_c::{TryRole, TypeCStateMachineState};
 use type_c_interface::control::usb::UsbControlConfig;
 use type_c_interface::control::vdm::{AttnVdm, OtherVdm, SendVdm};
 use type_c_interface::port::event::PortEventBitfield;
@@ -236,6 +236,11 @@ impl type_c_interface::controller::pd::Pd for Controller<'_> {
         Ok(())
     }
 
+    async fn execute_dr_swap(&mut self, port: LocalPortId) -> Result<(), PdError> {
+        debug!("Execute DR_Swap for port {port:?}");
+        Ok(())
+    }
+
     async fn get_discovered_svids(&mut self, port: LocalPortId) -> Result<DiscoveredSvids, PdError> {
         debug!("Get discovered SVIDs for port {port:?}");
         Ok(DiscoveredSvids::default())
@@ -253,6 +258,11 @@ impl type_c_interface::controller::pd::Pd for Controller<'_> {
         debug!("Get Discover Identity SOP' response for port {port:?}");
         Err(PdError::Failed)
     }
+
+    async fn get_partner_pdos(&mut self, port: LocalPortId, role: PowerRole) -> Result<Pdos, PdError> {
+        debug!("Get partner {role:?} PDOs for port {port:?}");
+        Err(PdError::Failed)
+    }
 }
 
 impl type_c_interface::controller::max_sink_voltage::MaxSinkVoltage for Controller<'_> {
@@ -262,6 +272,13 @@ impl type_c_interface::controller::max_sink_voltage::MaxSinkVoltage for Controll
     }
 }
 
+impl type_c_interface::controller::current_limit::CurrentLimit for Controller<'_> {
+    async fn set_current_limit(&mut self, port: LocalPortId, current_ma: Option<u16>) -> Result<(), PdError> {
+        debug!("Set current limit for port {}: {:?}", port.0, current_ma);
+        Ok(())
+    }
+}
+
 impl type_c_interface::controller::pd::StateMachine for Controller<'_> {
     async fn set_pd_state_machine_config(
         &mut self,
@@ -282,6 +299,11 @@ impl type_c_interface::controller::type_c::StateMachine for Controller<'_> {
         debug!("Set Type-C State Machine state for port {port:?}: {state:?}");
         Ok(())
     }
+
+    async fn set_try_role(&mut self, port: LocalPortId, role: TryRole) -> Result<(), PdError> {
+        debug!("Set try role for port {port:?}: {role:?}");
+        Ok(())
+    }
 }
 
 impl type_c_interface::ucsi::Lpm for Controller<'_> {
@@ -307,6 +329,13 @@ impl type_c_interface::controller::electrical_disconnect::ElectricalDisconnect f
     }
 }
 
+impl type_c_interface::controller::port_enable::PortEnable for Controller<'_> {
+    async fn set_port_enable(&mut self, port: LocalPortId, enabled: bool) -> Result<(), PdError> {
+        debug!("Set port enable for port {port:?}: {enabled}");
+        Ok(())
+    }
+}
+
 impl type_c_interface::controller::power::SystemPowerStateStatus for Controller<'_> {
     async fn set_system_power_state_status(
         &mut self,
@@ -1,6 +1,12 @@
 //! I2C<->HID bridge
+//!
+//! The bridge can expose multiple HID devices over a single I2C target. Requests are routed to a device by the
+//! target address accessed, if the target reports it, and the device's register file. Devices that share an
+//! interrupt line to the host use a [`SharedInterrupt`] to decide which device services each input report read.
 use core::borrow::{Borrow, BorrowMut};
+use core::cell::Cell;
 
+use embassy_sync::blocking_mutex;
 use embassy_sync::mutex::Mutex;
 use embassy_sync::signal::Signal;
 use embassy_time::{Duration, with_timeout};
@@ -10,6 +16,7 @@ use embedded_services::comms::{self, Endpoint, EndpointID, External, MailboxDele
 use embedded_services::hid::{self, DeviceId, InvalidSizeError, Opcode};
 use embedded_services::{error, trace};
 
+use super::passthrough::SharedInterrupt;
 use super::{Command as I2cCommand, I2cSlaveAsync};
 use crate::Error;
 
@@ -36,8 +43,23 @@ pub enum Access {
     Write,
 }
 
-pub struct Host<B: I2cSlaveAsync> {
-    id: DeviceId,
+/// HID device exposed to the host
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HostDevice {
+    /// Device ID
+    pub id: DeviceId,
+    /// Target address of the device, `None` if the device responds on any address
+    pub address: Option<u8>,
+}
+
+pub struct Host<B: I2cSlaveAsync, const N: usize = 1> {
+    devices: [HostDevice; N],
+    interrupt: Option<&'static SharedInterrupt<N>>,
+    /// Target address of the current transaction
+    address: blocking_mutex::Mutex<GlobalRawMutex, Cell<Option<u8>>>,
+    /// Index of the device whose input report is being sent
+    input_device: blocking_mutex::Mutex<GlobalRawMutex, Cell<Option<usize>>>,
     pub tp: Endpoint,
     response: Signal<GlobalRawMutex, Option<hid::Response<'static>>>,
     buffer: OwnedRef<'static, u8>,
@@ -47,8 +69,27 @@ pub struct Host<B: I2cSlaveAsync> {
 
 impl<B: I2cSlaveAsync> Host<B> {
     pub fn new(id: DeviceId, bus: B, buffer: OwnedRef<'static, u8>, timeout_config: Config) -> Self {
+        Self::new_with_devices([HostDevice { id, address: None }], bus, buffer, timeout_config, None)
+    }
+}
+
+impl<B: I2cSlaveAsync, const N: usize> Host<B, N> {
+    /// Create a host exposing multiple devices
+    ///
+    /// Devices that respond on the same address must use disjoint register files.
+    /// Input report reads are routed to the owner of `interrupt` if the devices share an interrupt line.
+    pub fn new_with_devices(
+        devices: [HostDevice; N],
+        bus: B,
+        buffer: OwnedRef<'static, u8>,
+        timeout_config: Config,
+        interrupt: Option<&'static SharedInterrupt<N>>,
+    ) -> Self {
         Host {
-            id,
+            devices,
+            interrupt,
+            address: blocking_mutex::Mutex::new(Cell::new(None)),
+            input_device: blocking_mutex::Mutex::new(Cell::new(None)),
             tp: Endpoint::uninit(EndpointID::External(External::Host)),
             response: Signal::new(),
             buffer,
@@ -57,6 +98,26 @@ impl<B: I2cSlaveAsync> Host<B> {
         }
     }
 
+    /// Returns true if the device responds on the address of the current transaction
+    fn matches_address(&self, device: &HostDevice) -> bool {
+        let address = self.address.lock(|address| address.get());
+        device.address.is_none() || address.is_none() || device.address == address
+    }
+
+    /// Returns the index of the device that services an input report read
+    fn input_device_index(&self) -> Option<usize> {
+        let address = self.address.lock(|address| address.get());
+        if address.is_some()
+            && let Some(index) = self.devices.iter().position(|device| device.address == address)
+        {
+            return Some(index);
+        }
+
+        self.interrupt
+            .and_then(SharedInterrupt::owner)
+            .or_else(|| self.devices.iter().position(|device| self.matches_address(device)))
+    }
+
     async fn read_bus(&self, timeout: Duration, buffer: &mut [u8]) -> Result<(), Error<B::Error>> {
         let mut bus = self.bus.lock().await;
         with_timeout(timeout, bus.respond_to_write(buffer))
@@ -245,7 +306,12 @@ impl<B: I2cSlaveAsync> Host<B> {
 
         let reg = u16::from_le_bytes(reg);
         trace!("Register address {:#x}", reg);
-        if let Some(device) = hid::get_device(self.id) {
+        let target = self
+            .devices
+            .iter()
+            .filter(|device| self.matches_address(device))
+            .find_map(|device| hid::get_device(device.id).filter(|device| device.regs.contains(reg)));
+        if let Some(device) = target {
             let request = if reg == device.regs.hid_desc_reg {
                 hid::Request::Descriptor
             } else if reg == device.regs.report_desc_reg {
@@ -261,21 +327,25 @@ impl<B: I2cSlaveAsync> Host<B> {
                 return Err(Error::Hid(hid::Error::InvalidRegisterAddress));
             };
 
-            hid::send_request(&self.tp, self.id, request)
+            hid::send_request(&self.tp, device.id, request)
                 .await
                 .map_err(|_| Error::Hid(hid::Error::Transport))?;
 
             trace!("Request processed");
             Ok(())
         } else {
-            error!("Invalid device id {}", self.id.0);
-            Err(Error::Hid(hid::Error::InvalidDevice))
+            error!("No device for register address {:#x}", reg);
+            Err(Error::Hid(hid::Error::InvalidRegisterAddress))
         }
     }
 
     async fn process_read(&self) -> Result<(), Error<B::Error>> {
         trace!("Got input report read request");
-        hid::send_request(&self.tp, self.id, hid::Request::InputReport)
+        let index = self.input_device_index().ok_or(Error::Hid(hid::Error::InvalidDevice))?;
+        let id = self.devices.get(index).ok_or(Error::Hid(hid::Error::InvalidDevice))?.id;
+        self.input_device.lock(|device| device.set(Some(index)));
+        trace!("Input report from device {}", id.0);
+        hid::send_request(&self.tp, id, hid::Request::InputReport)
             .await
             .map_err(|_| Error::Hid(hid::Error::Transport))
     }
@@ -293,8 +363,14 @@ impl<B: I2cSlaveAsync> Host<B> {
                 }
                 Ok(cmd) => match cmd {
                     I2cCommand::Probe => continue,
-                    I2cCommand::Read => return Ok(Access::Read),
-                    I2cCommand::Write => return Ok(Access::Write),
+                    I2cCommand::Read => {
+                        self.address.lock(|address| address.set(bus.address()));
+                        return Ok(Access::Read);
+                    }
+                    I2cCommand::Write => {
+                        self.address.lock(|address| address.set(bus.address()));
+                        return Ok(Access::Write);
+                    }
                 },
             }
         }
@@ -335,10 +411,25 @@ impl<B: I2cSlaveAsync> Host<B> {
                 }
             }
 
+            if let hid::Response::InputReport(data) = response {
+                let bytes = data.borrow().map_err(Error::Buffer)?;
+                let result = self
+                    .write_bus(self.timeout_config.device_response_timeout, bytes.borrow())
+                    .await;
+
+                // Release the device's share of the interrupt line even if the host didn't read the report
+                if let Some(index) = self.input_device.lock(|device| device.take())
+                    && let Some(interrupt) = self.interrupt
+                {
+                    interrupt.complete(index);
+                }
+                return result;
+            }
+
             match response {
+                hid::Response::InputReport(_) => Ok(()),
                 hid::Response::Descriptor(data)
                 | hid::Response::ReportDescriptor(data)
-                | hid::Response::InputReport(data)
                 | hid::Response::FeatureReport(data) => {
                     let bytes = data.borrow().map_err(Error::Buffer)?;
                     self.write_bus(self.timeout_config.device_response_timeout, bytes.borrow())
@@ -374,7 +465,7 @@ impl<B: I2cSlaveAsync> Host<B> {
     }
 }
 
-impl<B: I2cSlaveAsync> MailboxDelegate for Host<B> {
+impl<B: I2cSlaveAsync, const N: usize> MailboxDelegate for Host<B, N> {
     fn receive(&self, message: &comms::Message) -> Result<(), comms::MailboxDelegateError> {
         let hid_msg = message
             .data
@@ -389,7 +480,9 @@ impl<B: I2cSlaveAsync> MailboxDelegate for Host<B> {
             _ if message.to != EndpointID::External(External::Host) => {
                 Err(comms::MailboxDelegateError::InvalidDestination)
             }
-            _ if hid_msg.id != self.id => Err(comms::MailboxDelegateError::InvalidData),
+            _ if !self.devices.iter().any(|device| device.id == hid_msg.id) => {
+                Err(comms::MailboxDelegateError::InvalidData)
+            }
             _ => Err(comms::MailboxDelegateError::Other),
         }
     }
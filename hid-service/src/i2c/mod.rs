@@ -3,7 +3,7 @@ mod host;
 pub mod passthrough;
 
 pub use device::{Config as DeviceConfig, Device};
-pub use host::{Access, Config as HostConfig, Host};
+pub use host::{Access, Config as HostConfig, Host, HostDevice};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -21,4 +21,12 @@ pub trait I2cSlaveAsync {
     async fn listen(&mut self) -> Result<Command, Self::Error>;
     async fn respond_to_write(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
     async fn respond_to_read(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Target address of the most recent transaction
+    ///
+    /// Targets that respond to a range of addresses return the address the host accessed,
+    /// `None` if the target only responds to a single address.
+    fn address(&self) -> Option<u8> {
+        None
+    }
 }
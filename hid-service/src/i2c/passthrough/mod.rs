@@ -1,6 +1,8 @@
 mod interrupt;
+mod shared;
 
 pub use interrupt::*;
+pub use shared::*;
 
 #[macro_export]
 macro_rules! define_i2c_passthrough_device_task {
@@ -0,0 +1,147 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::digital::Wait;
+use embedded_services::{GlobalRawMutex, trace};
+
+use super::Error;
+
+/// Maximum number of devices sharing an interrupt line
+pub const MAX_SHARED_DEVICES: usize = 32;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct State {
+    /// Bitmask of devices with a pending interrupt
+    pending: u32,
+    /// Device whose input report the host is currently reading
+    owner: Option<usize>,
+    /// Most recently serviced device, used for round robin arbitration
+    last: usize,
+}
+
+/// This struct arbitrates a single interrupt line to the host shared by `N` devices
+/// The line is asserted while any device has a pending interrupt
+/// Host input report reads are assigned to one pending device at a time in round robin order
+/// A device is released once its input report has been sent to the host
+pub struct SharedInterrupt<const N: usize> {
+    state: Mutex<GlobalRawMutex, RefCell<State>>,
+    line: Signal<GlobalRawMutex, ()>,
+    serviced: [Signal<GlobalRawMutex, ()>; N],
+}
+
+impl<const N: usize> SharedInterrupt<N> {
+    pub const fn new() -> Self {
+        const { assert!(N <= MAX_SHARED_DEVICES, "Too many devices sharing an interrupt line") };
+        Self {
+            state: Mutex::new(RefCell::new(State {
+                pending: 0,
+                owner: None,
+                last: 0,
+            })),
+            line: Signal::new(),
+            serviced: [const { Signal::new() }; N],
+        }
+    }
+
+    fn mask(index: usize) -> u32 {
+        u32::try_from(index)
+            .ok()
+            .and_then(|index| 1u32.checked_shl(index))
+            .unwrap_or(0)
+    }
+
+    /// Mark an interrupt as pending for the given device
+    pub fn assert(&self, index: usize) {
+        self.state.lock(|state| state.borrow_mut().pending |= Self::mask(index));
+        self.line.signal(());
+    }
+
+    /// Returns true if any device has a pending interrupt
+    pub fn is_asserted(&self) -> bool {
+        self.state.lock(|state| state.borrow().pending != 0)
+    }
+
+    /// Returns the device that should service the next host input report read
+    ///
+    /// Returns `None` if no device has a pending interrupt.
+    pub fn owner(&self) -> Option<usize> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if let Some(owner) = state.owner
+                && state.pending & Self::mask(owner) != 0
+            {
+                return Some(owner);
+            }
+
+            let owner = (1..=N)
+                .map(|offset| (state.last + offset) % N)
+                .find(|index| state.pending & Self::mask(*index) != 0);
+            state.owner = owner;
+            owner
+        })
+    }
+
+    /// Release the given device after its input report was sent to the host
+    pub fn complete(&self, index: usize) {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.pending &= !Self::mask(index);
+            if state.owner == Some(index) {
+                state.owner = None;
+                state.last = index;
+            }
+        });
+        self.line.signal(());
+        if let Some(serviced) = self.serviced.get(index) {
+            serviced.signal(());
+        }
+    }
+
+    /// Release all devices and deassert the line
+    pub fn reset(&self) {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.pending = 0;
+            state.owner = None;
+        });
+        self.line.signal(());
+        for serviced in &self.serviced {
+            serviced.signal(());
+        }
+    }
+
+    /// Wait for an interrupt from the given device and hold it pending until the device is serviced
+    pub async fn process_input<IN: Wait>(&self, index: usize, int_in: &mut IN) -> Result<(), Error> {
+        let serviced = self.serviced.get(index).ok_or(Error::IoRead)?;
+
+        trace!("Waiting for interrupt from device {}", index);
+        int_in.wait_for_low().await.map_err(|_| Error::IoRead)?;
+
+        serviced.reset();
+        self.assert(index);
+        trace!("Interrupt received from device {}", index);
+
+        serviced.wait().await;
+        trace!("Device {} serviced", index);
+        Ok(())
+    }
+
+    /// Drive the shared interrupt line to the host whenever the pending state changes
+    pub async fn process_output<OUT: OutputPin>(&self, int_out: &mut OUT) -> Result<(), Error> {
+        self.line.wait().await;
+        if self.is_asserted() {
+            int_out.set_low().map_err(|_| Error::IoSet)
+        } else {
+            trace!("Shared interrupt deasserted");
+            int_out.set_high().map_err(|_| Error::IoSet)
+        }
+    }
+}
+
+impl<const N: usize> Default for SharedInterrupt<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,361 @@
+//! An analog (e.g. hall-effect) keyboard which can be used for the keyboard service.
+//!
+//! Each key is sampled through an ADC and compared against per-key press/release thresholds,
+//! which may be overridden at runtime to support configurable actuation points.
+//! The resulting key states go through the same debouncing and keyberon layout as the `GpioKeyboard`,
+//! producing identical HID reports.
+use super::HidKeyboard;
+use super::gpio_kb::{
+    HidConfig, HidReport, INPUT_MAX_LEN, LedConfig, LedFlags, OUTPUT_MAX_LEN, REPORT_DESCRIPTOR, REPORT_ID, set_led,
+};
+use core::borrow::Borrow;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+use embedded_hal::digital::OutputPin;
+use embedded_services::GlobalRawMutex;
+use embedded_services::error;
+use embedded_services::hid;
+use keyberon::debounce::Debouncer;
+use keyberon::key_code::KbHidReport;
+use keyberon::layout::{Layers, Layout};
+
+/// An ADC-sampled key matrix.
+///
+/// Implementations are responsible for any multiplexing (e.g. selecting a column before sampling its rows)
+/// and for normalizing readings so that larger values mean the key is pressed further.
+pub trait AnalogMatrix {
+    /// Error type
+    type Error;
+
+    /// Samples the key at the given column and row, returning the raw ADC reading.
+    fn sample(&mut self, col: usize, row: usize) -> Result<u16, Self::Error>;
+}
+
+/// Key press and release thresholds, in raw ADC units.
+///
+/// `release` should be lower than `press` to provide hysteresis around the actuation point.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct KeyThreshold {
+    /// A released key is reported pressed once its reading reaches this value.
+    pub press: u16,
+    /// A pressed key is reported released once its reading drops below this value.
+    pub release: u16,
+}
+
+impl KeyThreshold {
+    // Returns the new pressed state of a key given its previous state and latest reading
+    fn is_pressed(&self, was_pressed: bool, sample: u16) -> bool {
+        if was_pressed {
+            sample >= self.release
+        } else {
+            sample >= self.press
+        }
+    }
+}
+
+/// Analog keyboard configuration.
+pub struct AnalogKeyboardConfig<const NCOLS: usize, const NROWS: usize, const NLAYERS: usize, MATRIX: AnalogMatrix> {
+    /// The ADC-sampled key matrix.
+    pub matrix: MATRIX,
+    /// Press/release thresholds of each key, indexed by column then row.
+    ///
+    /// Usually determined by factory calibration to account for variation between sensors and magnets.
+    pub thresholds: [[KeyThreshold; NROWS]; NCOLS],
+    /// A keyberon layers implementation which maps coordinates to keys.
+    pub layers: &'static Layers<NCOLS, NROWS, NLAYERS>,
+    /// The interval in milliseconds between each scan.
+    pub poll_ms: u64,
+    /// The number of times an event (e.g. a key press) needs to be seen to actually register.
+    pub nb_bounce: u16,
+}
+
+/// A HID-aware analog keyboard ready to be used by the Keyboard Service.
+pub struct AnalogKeyboard<
+    const NCOLS: usize,
+    const NROWS: usize,
+    const NLAYERS: usize,
+    MATRIX: AnalogMatrix,
+    LED: OutputPin,
+> {
+    matrix: MATRIX,
+    thresholds: [[KeyThreshold; NROWS]; NCOLS],
+    actuation: [[Option<KeyThreshold>; NROWS]; NCOLS],
+    pressed: [[bool; NROWS]; NCOLS],
+    debouncer: Debouncer<[[bool; NROWS]; NCOLS]>,
+    layout: Layout<NCOLS, NROWS, NLAYERS>,
+    poll_ms: u64,
+    hid_cfg: HidConfig,
+    led_cfg: LedConfig<LED>,
+    report: HidReport,
+    power_state: hid::PowerState,
+    scan_signal: Signal<GlobalRawMutex, ()>,
+    report_freq: hid::ReportFreq,
+}
+
+impl<const NCOLS: usize, const NROWS: usize, const NLAYERS: usize, MATRIX: AnalogMatrix, LED: OutputPin>
+    AnalogKeyboard<NCOLS, NROWS, NLAYERS, MATRIX, LED>
+{
+    /// Create a new instance of an analog keyboard with given configuration.
+    pub fn new(
+        kb_cfg: AnalogKeyboardConfig<NCOLS, NROWS, NLAYERS, MATRIX>,
+        hid_cfg: HidConfig,
+        led_cfg: LedConfig<LED>,
+    ) -> Self {
+        Self {
+            matrix: kb_cfg.matrix,
+            thresholds: kb_cfg.thresholds,
+            actuation: [[None; NROWS]; NCOLS],
+            pressed: [[false; NROWS]; NCOLS],
+            // Keyberon expects columns as input and rows as output, so like the `GpioKeyboard`
+            // state is indexed by column and coordinates are reversed during scan.
+            debouncer: Debouncer::new([[false; NROWS]; NCOLS], [[false; NROWS]; NCOLS], kb_cfg.nb_bounce),
+            layout: Layout::new(kb_cfg.layers),
+            poll_ms: kb_cfg.poll_ms,
+            hid_cfg,
+            led_cfg,
+            report: HidReport::default(),
+            power_state: hid::PowerState::Sleep,
+            scan_signal: Signal::new(),
+            report_freq: hid::ReportFreq::Infinite,
+        }
+    }
+
+    /// Overrides the actuation point of a key.
+    ///
+    /// Passing `None` restores the configured threshold. Returns false if the coordinate is out of range.
+    pub fn set_actuation_point(&mut self, col: usize, row: usize, threshold: Option<KeyThreshold>) -> bool {
+        match self.actuation.get_mut(col).and_then(|col| col.get_mut(row)) {
+            Some(actuation) => {
+                *actuation = threshold;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the threshold currently in effect for a key, if the coordinate is in range.
+    pub fn actuation_point(&self, col: usize, row: usize) -> Option<KeyThreshold> {
+        let configured = *self.thresholds.get(col)?.get(row)?;
+        Some(self.actuation[col][row].unwrap_or(configured))
+    }
+
+    // Samples every key and returns their pressed state, indexed by column then row
+    fn sample(&mut self) -> Result<[[bool; NROWS]; NCOLS], MATRIX::Error> {
+        for col in 0..NCOLS {
+            for row in 0..NROWS {
+                let sample = self.matrix.sample(col, row)?;
+                let threshold = self.actuation[col][row].unwrap_or(self.thresholds[col][row]);
+                self.pressed[col][row] = threshold.is_pressed(self.pressed[col][row], sample);
+            }
+        }
+
+        Ok(self.pressed)
+    }
+}
+
+impl<const NCOLS: usize, const NROWS: usize, const NLAYERS: usize, MATRIX: AnalogMatrix, LED: OutputPin> HidKeyboard
+    for AnalogKeyboard<NCOLS, NROWS, NLAYERS, MATRIX, LED>
+{
+    fn register_file(&self) -> hid::RegisterFile {
+        // Don't need anything special so use the default
+        hid::RegisterFile::default()
+    }
+
+    fn hid_descriptor(&self) -> hid::Descriptor {
+        const VERSION: u16 = 0x0100;
+
+        hid::Descriptor {
+            w_hid_desc_length: hid::DESCRIPTOR_LEN as u16,
+            bcd_version: VERSION,
+            w_report_desc_length: REPORT_DESCRIPTOR.len() as u16,
+            w_report_desc_register: self.register_file().report_desc_reg,
+            w_input_register: self.register_file().input_reg,
+            w_max_input_length: INPUT_MAX_LEN as u16,
+            w_output_register: self.register_file().output_reg,
+            w_max_output_length: OUTPUT_MAX_LEN as u16,
+            w_command_register: self.register_file().command_reg,
+            w_data_register: self.register_file().data_reg,
+            w_vendor_id: self.hid_cfg.vid,
+            w_product_id: self.hid_cfg.pid,
+            w_version_id: VERSION,
+        }
+    }
+
+    fn report_descriptor(&self) -> &'static [u8] {
+        REPORT_DESCRIPTOR
+    }
+
+    async fn scan(&mut self) -> Result<super::HidReportSlice<'_>, super::KeyboardError> {
+        // Wait until we are told to power on before scanning
+        if self.power_state == hid::PowerState::Sleep {
+            self.scan_signal.wait().await;
+        }
+
+        // Determine the idle rate
+        let idle = if let hid::ReportFreq::Msecs(ms) = self.report_freq {
+            Timer::after_millis(ms as u64)
+        } else {
+            // If set to 'infinite', set a timer very far in the future (effectively infinite)
+            Timer::after_secs(1_000_000)
+        };
+
+        // Polling scan loop
+        // Note: Every key is sensed independently, so unlike a GPIO matrix there is no ghosting to detect
+        let scan = async {
+            loop {
+                if let Ok(pressed) = self.sample() {
+                    // Keyberon expects cols as input and rows as output, but we are the opposite so swap them
+                    let events = self.debouncer.events(pressed).map(|e| e.transform(|x, y| (y, x)));
+
+                    let mut changed = false;
+                    for event in events {
+                        self.layout.event(event);
+                        self.layout.tick();
+                        changed = true;
+                    }
+
+                    // Only send a report once on press, and once on release
+                    if changed {
+                        self.report = self.layout.keycodes().collect::<KbHidReport>().into();
+                        break Ok(());
+                    }
+                } else {
+                    error!("Failed to sample analog keyboard!");
+                    break Err(super::KeyboardError::Scan);
+                }
+
+                Timer::after_millis(self.poll_ms).await;
+            }
+        };
+
+        match embassy_futures::select::select(idle, scan).await {
+            // Hit the idle limit? Return the most recent report
+            embassy_futures::select::Either::First(_) => Ok(self.report.as_slice()),
+
+            // Have a fresh report? Return it
+            embassy_futures::select::Either::Second(Ok(())) => Ok(self.report.as_slice()),
+
+            // Error? Let the HID backend convert it to report for us
+            embassy_futures::select::Either::Second(Err(e)) => Err(e),
+        }
+    }
+
+    async fn wait_wake(&mut self) -> Result<(), super::KeyboardError> {
+        // Revisit: Some hall-effect sensors support a low power threshold interrupt, but there is no generic
+        // way to arm it, so sample the matrix at the poll interval instead.
+        loop {
+            match self.sample() {
+                Ok(pressed) if pressed.iter().flatten().any(|&key| key) => return Ok(()),
+                Ok(_) => Timer::after_millis(self.poll_ms).await,
+                Err(_) => {
+                    error!("Failed to sample analog keyboard!");
+                    return Err(super::KeyboardError::Scan);
+                }
+            }
+        }
+    }
+
+    async fn reset(&mut self) -> Result<(), super::KeyboardError> {
+        self.report_freq = hid::ReportFreq::Infinite;
+        Ok(())
+    }
+
+    async fn set_power_state(&mut self, power_state: hid::PowerState) -> Result<(), super::KeyboardError> {
+        self.power_state = power_state;
+
+        // Signal to scanner it can start now
+        if power_state == hid::PowerState::On {
+            self.scan_signal.signal(());
+        }
+
+        Ok(())
+    }
+
+    async fn set_idle(
+        &mut self,
+        _report_id: hid::ReportId,
+        report_freq: hid::ReportFreq,
+    ) -> Result<(), super::KeyboardError> {
+        self.report_freq = report_freq;
+        Ok(())
+    }
+
+    fn get_idle(&self, _report_id: hid::ReportId) -> hid::ReportFreq {
+        self.report_freq
+    }
+
+    async fn set_protocol(&mut self, _protocol: hid::Protocol) -> Result<(), super::KeyboardError> {
+        // NOP
+        // Only support Report protocol
+        Ok(())
+    }
+
+    fn get_protocol(&self) -> hid::Protocol {
+        hid::Protocol::Report
+    }
+
+    async fn vendor_cmd(&mut self) -> Result<(), super::KeyboardError> {
+        // NOP
+        // No vendor-defined commands for this implementation
+        Ok(())
+    }
+
+    async fn set_report(
+        &mut self,
+        report_type: hid::ReportType,
+        report_id: hid::ReportId,
+        buf: &embedded_services::buffer::SharedRef<'static, u8>,
+    ) -> Result<(), super::KeyboardError> {
+        match report_type {
+            // Received a set output report for LEDs
+            hid::ReportType::Output if report_id.0 == REPORT_ID => {
+                let buf = buf.borrow().map_err(super::KeyboardError::Buffer)?;
+                let leds: &[u8] = buf.borrow();
+                let flags = LedFlags::from_bits_retain(leds[0]);
+
+                set_led(&mut self.led_cfg.num_lock, flags.contains(LedFlags::NumLock))?;
+                set_led(&mut self.led_cfg.caps_lock, flags.contains(LedFlags::CapsLock))?;
+                set_led(&mut self.led_cfg.scroll_lock, flags.contains(LedFlags::ScrollLock))?;
+            }
+            // Not currently supported so treat as NOP
+            hid::ReportType::Feature => (),
+            // Should never receive a set input report command
+            hid::ReportType::Input => Err(super::KeyboardError::Command)?,
+            // Received set output for unknown report ID, also treat as NOP
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn get_report(&self, report_type: hid::ReportType, _report_id: hid::ReportId) -> super::HidReportSlice<'_> {
+        match report_type {
+            hid::ReportType::Input => self.report.as_slice(),
+            // We don't currently support feature reports
+            _ => super::HidReportSlice(&[0x00]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_hysteresis() {
+        let threshold = KeyThreshold {
+            press: 600,
+            release: 400,
+        };
+
+        // Released key only presses at the press threshold
+        assert!(!threshold.is_pressed(false, 500));
+        assert!(threshold.is_pressed(false, 600));
+
+        // Pressed key stays pressed until it drops below the release threshold
+        assert!(threshold.is_pressed(true, 500));
+        assert!(threshold.is_pressed(true, 400));
+        assert!(!threshold.is_pressed(true, 399));
+    }
+}
@@ -8,12 +8,12 @@ use embassy_time::Timer;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_services::GlobalRawMutex;
 use embedded_services::hid;
+use embedded_services::hid::{Collection, MainItemFlags, ReportDescriptorBuilder};
 use embedded_services::{error, warn};
 use keyberon::debounce::Debouncer;
 use keyberon::key_code::KbHidReport;
 use keyberon::layout::Layout;
 pub use keyberon::layout::{Layers, layout};
-use keyberon::matrix::Matrix;
 
 // Currently hard cap this to 6 since Keyberon only supports 6 keys
 // If move away from Keyberon this can be changed and allow user to configure
@@ -24,80 +24,58 @@ const KEYMOD_SZ: usize = 1;
 
 // Don't like how this still needs knowledge of i2c representation
 // May need to consider letting hid back end create the hid descriptor
-const INPUT_MAX_LEN: usize = super::hid_kb::I2C_REPORT_HEADER_SZ + KEYMOD_SZ + KRO;
+pub(crate) const INPUT_MAX_LEN: usize = super::hid_kb::I2C_REPORT_HEADER_SZ + KEYMOD_SZ + KRO;
 
 // Output reports are the I2C header plus a single byte for LED on/off status
-const OUTPUT_MAX_LEN: usize = super::hid_kb::I2C_REPORT_HEADER_SZ + 1;
+pub(crate) const OUTPUT_MAX_LEN: usize = super::hid_kb::I2C_REPORT_HEADER_SZ + 1;
 
 // An input/output report
-const REPORT_ID: u8 = 1;
+pub(crate) const REPORT_ID: u8 = 1;
 
 // This is a basic report descriptor that defines a single keyboard report with 6 keys
 // Revisit: Could also allow user to pass in a custom report descriptor
-// Revisit: Investigate a struct representation of report descriptors,
-// but may prove challenging due to the fact that a strict ordering and length is not defined.
-#[rustfmt::skip]
-const REPORT_DESCRIPTOR: &[u8] = &[
-    // Usage Page (Generic Desktop Ctrls)
-    0x05, 0x01,
-    // Usage (Keyboard)
-    0x09, 0x06,
-    // Collection (Application)
-    0xA1, 0x01,
-    // Report ID (1)
-    0x85, REPORT_ID,
-    // Usage Page (Keypad)
-    0x05, 0x07,
-    // Usage Minimum (0xE0)
-    0x19, 0xE0,
-    // Usage Maximum (0xE7)
-    0x29, 0xE7,
-    // Logical Minimum (0)
-    0x15, 0x00,
-    // Logical Maximum (1)
-    0x25, 0x01,
-    // Report Size (1)
-    0x75, 0x01,
-    // Report Count (8) (8 modifier keys represented by single bit)
-    0x95, 0x08,
-    // Input (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
-    0x81, 0x02,
-    // Usage Minimum (0x00)
-    0x19, 0x00,
-    // Usage Maximum (0x91)
-    0x29, 0x91,
-    // Logical Maximum (255)
-    0x26, 0xFF, 0x00,
-    // Report Size (8)
-    0x75, 0x08,
-    // Report Count (6) (Keyberon only supports 6 keys)
-    0x95, 0x06,
-    // Input (Data,Array,Abs,No Wrap,Linear,Preferred State,No Null Position)
-    0x81, 0x00,
-    // LED report
-    // Usage Page (LEDs)
-    0x05, 0x08,
-    // Usage Minimum (Num Lock)
-    0x19, 0x01,
-    // Usage Maximum (Scroll Lock)
-    0x29, 0x03,
-    // Report Size (1)
-    0x75, 0x01,
-    // Report Count (3)
-    0x95, 0x03,
-    // Logical Maximum (1)
-    0x25, 0x01,
-    // Output (Data,Var,Abs,No Wrap,Linear,Preferred State,No Null Position)
-    0x91, 0x02,
-    // Report Count (5)
-    0x95, 0x05,
-    // Output (Const,Array,Abs,No Wrap,Linear,Preferred State,No Null Position)
-    0x91, 0x01,
-    // End LED report
-    // Revisit: Consumer reports... but can we make that generic?
-    // End Collection
-    0xC0,
-];
+pub(crate) const REPORT_DESCRIPTOR: &[u8] = embedded_services::hid_report_descriptor!(
+    ReportDescriptorBuilder::new()
+        // Generic Desktop Ctrls
+        .usage_page(0x01)
+        // Keyboard
+        .usage(0x06)
+        .collection(Collection::Application)
+        .report_id(REPORT_ID)
+        // Keypad
+        .usage_page(0x07)
+        .usage_minimum(0xE0)
+        .usage_maximum(0xE7)
+        .logical_minimum(0)
+        .logical_maximum(1)
+        // 8 modifier keys represented by single bit
+        .report_size(1)
+        .report_count(8)
+        .input(MainItemFlags::VARIABLE)
+        .usage_minimum(0x00)
+        .usage_maximum(0x91)
+        .logical_maximum(255)
+        // Keyberon only supports 6 keys
+        .report_size(8)
+        .report_count(6)
+        .input(MainItemFlags::DATA)
+        // LED report
+        // LEDs
+        .usage_page(0x08)
+        // Num Lock to Scroll Lock
+        .usage_minimum(0x01)
+        .usage_maximum(0x03)
+        .report_size(1)
+        .report_count(3)
+        .logical_maximum(1)
+        .output(MainItemFlags::VARIABLE)
+        // Padding
+        .report_count(5)
+        .output(MainItemFlags::CONSTANT)
+        // End LED report
+        // Revisit: Consumer reports... but can we make that generic?
+        .end_collection()
+);
 
 // Matches the format described by report descriptor
 // As in, each LED on/off status represented by single-bit
@@ -111,7 +89,7 @@ bitflags::bitflags! {
     }
 }
 
-fn set_led(led: &mut Option<impl OutputPin>, cond: bool) -> Result<(), super::KeyboardError> {
+pub(crate) fn set_led(led: &mut Option<impl OutputPin>, cond: bool) -> Result<(), super::KeyboardError> {
     if let Some(led) = led {
         if cond {
             led.set_high().map_err(|_| super::KeyboardError::Scan)?;
@@ -126,10 +104,10 @@ fn set_led(led: &mut Option<impl OutputPin>, cond: bool) -> Result<(), super::Ke
 // Note: This is not defined at top-level because operations on const generics is not yet stable
 // E.g. `struct HidReport<const KRO: usize>([u8; KRO + 1])` is not currently possible
 #[derive(Default)]
-struct HidReport([u8; KRO + KEYMOD_SZ]);
+pub(crate) struct HidReport([u8; KRO + KEYMOD_SZ]);
 
 impl HidReport {
-    fn as_slice(&self) -> super::HidReportSlice<'_> {
+    pub(crate) fn as_slice(&self) -> super::HidReportSlice<'_> {
         super::HidReportSlice(&self.0)
     }
 }
@@ -147,6 +125,80 @@ impl From<KbHidReport> for HidReport {
     }
 }
 
+// Key matrix which drives one column low at a time and reads back the rows.
+//
+// Keyberon's matrix owns the pins without exposing them, so this is used instead to also allow
+// driving all columns at once for any-key wake detection.
+struct KeyMatrix<INPUT, OUTPUT, const NROWS: usize, const NCOLS: usize> {
+    rows: [INPUT; NROWS],
+    cols: [OUTPUT; NCOLS],
+    wake_armed: bool,
+}
+
+impl<E, INPUT: InputPin<Error = E>, OUTPUT: OutputPin<Error = E>, const NROWS: usize, const NCOLS: usize>
+    KeyMatrix<INPUT, OUTPUT, NROWS, NCOLS>
+{
+    fn new(rows: [INPUT; NROWS], mut cols: [OUTPUT; NCOLS]) -> Result<Self, E> {
+        for col in cols.iter_mut() {
+            col.set_high()?;
+        }
+
+        Ok(Self {
+            rows,
+            cols,
+            wake_armed: false,
+        })
+    }
+
+    // Returns the pressed state of each key, indexed by column then row
+    fn get_with_delay(&mut self, delay: &mut impl FnMut()) -> Result<[[bool; NROWS]; NCOLS], E> {
+        // Wake detection may have been cancelled, release the columns before scanning
+        if self.wake_armed {
+            self.disarm_wake()?;
+        }
+
+        let mut pressed = [[false; NROWS]; NCOLS];
+        for (col, col_pressed) in self.cols.iter_mut().zip(pressed.iter_mut()) {
+            col.set_low()?;
+            delay();
+            for (row, key) in self.rows.iter_mut().zip(col_pressed.iter_mut()) {
+                *key = row.is_low()?;
+            }
+            col.set_high()?;
+        }
+
+        Ok(pressed)
+    }
+
+    // Drive all columns so a press of any key pulls its row low
+    fn arm_wake(&mut self) -> Result<(), E> {
+        self.wake_armed = true;
+        for col in self.cols.iter_mut() {
+            col.set_low()?;
+        }
+        Ok(())
+    }
+
+    // Release all columns to return to normal scanning
+    fn disarm_wake(&mut self) -> Result<(), E> {
+        for col in self.cols.iter_mut() {
+            col.set_high()?;
+        }
+        self.wake_armed = false;
+        Ok(())
+    }
+
+    // Returns true if any key is pressed while wake detection is armed
+    fn any_pressed(&mut self) -> Result<bool, E> {
+        for row in self.rows.iter_mut() {
+            if row.is_low()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
 /// GPIO keyboard configuration.
 pub struct KeyboardConfig<
     const NCOLS: usize,
@@ -188,7 +240,7 @@ struct KeyberonConfig<
     OUTPUT: OutputPin<Error = E>,
     DELAY: FnMut(),
 > {
-    matrix: Matrix<INPUT, OUTPUT, NROWS, NCOLS>,
+    matrix: KeyMatrix<INPUT, OUTPUT, NROWS, NCOLS>,
     debouncer: Debouncer<[[bool; NROWS]; NCOLS]>,
     layout: Layout<NCOLS, NROWS, NLAYERS>,
     poll_ms: u64,
@@ -212,10 +264,10 @@ impl<
     fn try_from(cfg: KeyboardConfig<NCOLS, NROWS, NLAYERS, E, INPUT, OUTPUT, DELAY>) -> Result<Self, E> {
         Ok(Self {
             // Keyberon expects colums as input and rows as output, but most platforms seem opposite?
-            // So we swap them, and during scan perform a transform to reverse coordinates.
+            // So the matrix is indexed by column, and during scan we perform a transform to reverse coordinates.
             //
             // Revisit: See if there is an easy way to support both formats generically
-            matrix: Matrix::new(cfg.rows, cfg.cols)?,
+            matrix: KeyMatrix::new(cfg.rows, cfg.cols)?,
             debouncer: keyberon::debounce::Debouncer::new(
                 [[false; NROWS]; NCOLS],
                 [[false; NROWS]; NCOLS],
@@ -472,6 +524,29 @@ impl<
         }
     }
 
+    async fn wait_wake(&mut self) -> Result<(), super::KeyboardError> {
+        self.kb_cfg.matrix.arm_wake().map_err(|_| super::KeyboardError::Scan)?;
+
+        // Revisit: Rows are generic input pins, so poll them rather than waiting on an interrupt.
+        // This is still far cheaper than a full matrix scan since all columns are driven at once.
+        let result = loop {
+            match self.kb_cfg.matrix.any_pressed() {
+                Ok(true) => break Ok(()),
+                Ok(false) => Timer::after_millis(self.kb_cfg.poll_ms).await,
+                Err(_) => {
+                    error!("Failed to read keyboard rows!");
+                    break Err(super::KeyboardError::Scan);
+                }
+            }
+        };
+
+        self.kb_cfg
+            .matrix
+            .disarm_wake()
+            .map_err(|_| super::KeyboardError::Scan)?;
+        result
+    }
+
     async fn reset(&mut self) -> Result<(), super::KeyboardError> {
         self.report_freq = hid::ReportFreq::Infinite;
         Ok(())
@@ -1,16 +1,18 @@
 //! Handles the backend HID communication with host for the keyboard
-use super::HidKeyboard;
-use core::borrow::BorrowMut;
+use super::remap::{self, Remapper};
+use super::{HidKeyboard, HidReportSlice, SleepMessage, WakeRequest};
+use core::borrow::{Borrow, BorrowMut};
+use embassy_futures::select::{Either, Either3, select, select3};
 use embassy_sync::channel::Channel;
 use embassy_sync::once_lock::OnceLock;
 use embassy_sync::signal::Signal;
 use embedded_hal::digital::OutputPin;
 use embedded_services::GlobalRawMutex;
 use embedded_services::buffer::SharedRef;
-use embedded_services::comms;
-use embedded_services::error;
+use embedded_services::comms::{self, EndpointID, Internal};
 use embedded_services::hid;
 use embedded_services::ipc::deferred as ipc;
+use embedded_services::{error, info};
 use hid_service::i2c::I2cSlaveAsync;
 use static_cell::StaticCell;
 
@@ -95,6 +97,8 @@ pub(crate) struct Context {
     pub(crate) report_ipc: ReportIpc,
     pub(crate) cmd_ipc: CmdIpc,
     send_complete: Signal<GlobalRawMutex, ()>,
+    sleep: Signal<GlobalRawMutex, SleepMessage>,
+    pub(crate) tp: comms::Endpoint,
 }
 pub(crate) static CONTEXT: OnceLock<Context> = OnceLock::new();
 
@@ -106,6 +110,8 @@ pub(crate) fn init(reg_file: hid::RegisterFile) -> &'static hid::Device {
         report_ipc: ReportIpc::new(),
         cmd_ipc: CmdIpc::new(),
         send_complete: Signal::new(),
+        sleep: Signal::new(),
+        tp: comms::Endpoint::uninit(EndpointID::Internal(Internal::Keyboard)),
     };
     CONTEXT
         .init(context)
@@ -118,11 +124,67 @@ pub(crate) fn init(reg_file: hid::RegisterFile) -> &'static hid::Device {
     DEVICE.init(device)
 }
 
+// Receives system sleep notifications
+impl comms::MailboxDelegate for Context {
+    fn receive(&self, message: &comms::Message) -> Result<(), comms::MailboxDelegateError> {
+        let sleep = message
+            .data
+            .get::<SleepMessage>()
+            .ok_or(comms::MailboxDelegateError::MessageNotFound)?;
+
+        self.sleep.signal(*sleep);
+        Ok(())
+    }
+}
+
+// Waits until the system resumes
+async fn wait_resume(context: &Context) {
+    while context.sleep.wait().await != SleepMessage::Resume {}
+}
+
+// Arms the keyboard to wake on key press while suspended, returning when the system resumes.
+// If a key press is detected first, a wake request is sent to the power manager.
+async fn suspend<T: HidKeyboard>(hid_kb: &mut T, context: &Context) {
+    info!("Keyboard armed for wake");
+    match select(hid_kb.wait_wake(), wait_resume(context)).await {
+        Either::First(Ok(())) => {
+            info!("Key press detected, requesting wake");
+            let request = WakeRequest {
+                source: super::HID_KB_ID,
+            };
+            if context
+                .tp
+                .send(EndpointID::Internal(Internal::Power), &request)
+                .await
+                .is_err()
+            {
+                error!("Failed to send keyboard wake request");
+            }
+        }
+        Either::First(Err(_)) => error!("Keyboard wake detection failed"),
+        Either::Second(()) => info!("System resumed, keyboard wake disarmed"),
+    }
+}
+
 /// This task handles calling the keyboard `scan` in a loop, while also listening for commands
 /// from the HID request handler task. To minimize delay between scan loops, we quickly process commands
 /// and let the HID request handler task handle forwarding the response to the host.
-pub async fn handle_keyboard<T: HidKeyboard>(mut hid_kb: T) -> Result<embedded_services::Never, super::KeyboardError> {
+pub async fn handle_keyboard<T: HidKeyboard>(hid_kb: T) -> Result<embedded_services::Never, super::KeyboardError> {
+    handle_keyboard_with_remap(hid_kb, None).await
+}
+
+/// Same as [`handle_keyboard`], but applies the host-editable remap table to every input report.
+///
+/// SET_REPORT(Feature) requests for the configured report ID edit the table rather than being passed to the keyboard.
+pub async fn handle_keyboard_with_remap<T: HidKeyboard>(
+    mut hid_kb: T,
+    remap: Option<remap::Config<'_>>,
+) -> Result<embedded_services::Never, super::KeyboardError> {
     let context = CONTEXT.get().await;
+    let (remap_report_id, mut remapper) = match remap {
+        Some(config) => (Some(config.report_id), Remapper::new(config.storage)),
+        None => (None, Remapper::new(None)),
+    };
 
     // Buffer holding immediate report requests
     embedded_services::define_static_buffer!(report_buf, u8, [0u8; INPUT_MAX]);
@@ -130,16 +192,25 @@ pub async fn handle_keyboard<T: HidKeyboard>(mut hid_kb: T) -> Result<embedded_s
     let max_input_len = hid_kb.hid_descriptor().w_max_input_length;
 
     loop {
-        // Wait for either a command request or input report to become available
-        match embassy_futures::select::select(hid_kb.scan(), context.cmd_ipc.receive()).await {
+        // Wait for either a command request, input report, or sleep notification to become available
+        match select3(hid_kb.scan(), context.cmd_ipc.receive(), context.sleep.wait()).await {
             // If we got a keyboard report, queue it up to be sent out
-            embassy_futures::select::Either::First(report) => {
+            Either3::First(report) => {
                 let i2c_report = match report {
                     Ok(report) => {
                         // Revisit: Look into ways to avoid multiple copies (even if reports are small)
                         // But, difficult to store slices/references in queue with all the lifetime management that entails
                         // May need some form of ring buffer if really need to squeeze performance?
-                        HidI2cReport::from_report_slice(report, max_input_len).to_bytes()
+                        let mut remapped = [0u8; REPORT_MAX_SZ];
+                        match remapper.apply(report.as_bytes(), &mut remapped) {
+                            Ok(len) => {
+                                HidI2cReport::from_report_slice(HidReportSlice::new(&remapped[..len]), max_input_len)
+                                    .to_bytes()
+                            }
+                            Err(_) => {
+                                HidI2cReport::from_error(super::KeyboardError::Rollover, max_input_len).to_bytes()
+                            }
+                        }
                     }
                     Err(e) => HidI2cReport::from_error(e, max_input_len).to_bytes(),
                 };
@@ -149,7 +220,7 @@ pub async fn handle_keyboard<T: HidKeyboard>(mut hid_kb: T) -> Result<embedded_s
 
             // Otherwise if we are instructed to perform a command, do it quickly then respond
             // Revisit: For commands that are fallible, realistically what can we do other than print an error?
-            embassy_futures::select::Either::Second(request) => match request.command {
+            Either3::Second(request) => match request.command {
                 // A reset is handled similarly to an input report.
                 // When we receive a reset command, we must place reset sentinel value ([0x00, 0x00])
                 // into report buffer, then assert interrupt so host can read it after we've reset the keyboard.
@@ -183,6 +254,21 @@ pub async fn handle_keyboard<T: HidKeyboard>(mut hid_kb: T) -> Result<embedded_s
                     request.respond(Some(hid::Response::InputReport(report_buf::get())));
                 }
 
+                // Edits the remap table
+                hid::Command::SetReport(hid::ReportType::Feature, report_id, ref buf)
+                    if Some(report_id) == remap_report_id =>
+                {
+                    let result = buf.borrow().map_err(super::KeyboardError::Buffer).and_then(|payload| {
+                        remapper
+                            .set_report(payload.borrow())
+                            .map_err(|_| super::KeyboardError::Command)
+                    });
+                    match result {
+                        Ok(()) => request.respond(None),
+                        Err(_) => error!("Failed to update keyboard remap table"),
+                    }
+                }
+
                 // Instructs the keyboard to immediately set the output/feature report
                 hid::Command::SetReport(report_type, report_id, ref buf) => {
                     if hid_kb.set_report(report_type, report_id, buf).await.is_ok() {
@@ -244,6 +330,13 @@ pub async fn handle_keyboard<T: HidKeyboard>(mut hid_kb: T) -> Result<embedded_s
                     }
                 }
             },
+
+            // System is suspending, stop scanning and wait for a key press to wake it
+            // Resumes normal scanning once the system resumes or wake is requested
+            Either3::Third(SleepMessage::Suspend) => suspend(&mut hid_kb, context).await,
+
+            // Not suspended, nothing to do
+            Either3::Third(SleepMessage::Resume) => (),
         }
     }
 }
@@ -1,6 +1,7 @@
 //! Keyboard Service
 //!
 //! For users with basic GPIO key matrix needs, consider using the provided `GpioKeyboard`.
+//! For analog (e.g. hall-effect) key matrices, consider using the provided `AnalogKeyboard`.
 //!
 //! Otherwise, users may manually implement the `HidKeyboard` trait for custom scanning logic
 //! or hardware-implemented key scanners.
@@ -10,8 +11,10 @@
 #![allow(clippy::panic_in_result_fn)]
 #![allow(clippy::unwrap_used)]
 
+pub mod analog_kb;
 pub mod gpio_kb;
 pub mod hid_kb;
+pub mod remap;
 pub mod task;
 
 use embedded_services::buffer::SharedRef;
@@ -34,6 +37,28 @@ pub enum KeyboardError {
     Buffer(embedded_services::buffer::Error),
 }
 
+/// System sleep notification, sent to the keyboard service endpoint ([`comms::Internal::Keyboard`]).
+///
+/// [`comms::Internal::Keyboard`]: embedded_services::comms::Internal::Keyboard
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SleepMessage {
+    /// The system is suspending, arm the keyboard to wake on key press
+    Suspend,
+    /// The system has resumed, resume normal scanning
+    Resume,
+}
+
+/// Wake request sent to the power manager ([`comms::Internal::Power`]) when a key is pressed while suspended.
+///
+/// [`comms::Internal::Power`]: embedded_services::comms::Internal::Power
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeRequest {
+    /// HID device that detected the key press
+    pub source: hid::DeviceId,
+}
+
 /// A slice of a HID report.
 ///
 /// This should only contain a single key modifiers byte followed by KRO usage codes.
@@ -57,7 +82,7 @@ impl<'a> HidReportSlice<'a> {
 /// Represents a HID-aware keyboard.
 ///
 /// This should be implemented on a struct and passed to the keyboard service initialization
-/// if not using the provided `GpioKeyboard`.
+/// if not using the provided `GpioKeyboard` or `AnalogKeyboard`.
 pub trait HidKeyboard {
     /// Returns the HID descriptor for the keyboard.
     fn hid_descriptor(&self) -> hid::Descriptor;
@@ -84,6 +109,17 @@ pub trait HidKeyboard {
     /// The implementation MUST be cancel safe as the HID backend may cancel to service an incoming command.
     fn scan(&mut self) -> impl core::future::Future<Output = Result<HidReportSlice<'_>, KeyboardError>>;
 
+    /// Waits for any key press while the system is suspended.
+    ///
+    /// Called when the system suspends. The keyboard should be placed in its lowest power wake configuration
+    /// (e.g. all columns driven so any key press is detected on a row), returning once a key press is detected.
+    /// Normal scanning resumes after this returns.
+    ///
+    /// # Cancel Safety
+    ///
+    /// The implementation MUST be cancel safe as the keyboard service cancels it if the system resumes.
+    fn wait_wake(&mut self) -> impl core::future::Future<Output = Result<(), KeyboardError>>;
+
     /// Resets the keyboard to initial state.
     fn reset(&mut self) -> impl core::future::Future<Output = Result<(), KeyboardError>>;
 
@@ -0,0 +1,457 @@
+//! Key remapping
+//!
+//! A [`RemapTable`] sits between the keyboard scan and HID report generation. Each entry rewrites one key usage:
+//! it can remap it to another usage, disable it, or expand it into a chord of up to [`MAX_MACRO_KEYS`] usages
+//! pressed together. The host edits the table through a vendor feature report (see [`RemapCommand`]), and the table is
+//! saved through an optional [`Storage`] backend so it survives EC resets.
+//!
+//! Only key usages following the modifier byte are looked up. Remapping a key to a modifier usage
+//! (`0xe0`-`0xe7`) sets the corresponding modifier bit instead of occupying a key slot.
+
+use embedded_services::hid;
+
+/// Maximum number of remap entries
+pub const MAX_ENTRIES: usize = 16;
+
+/// Maximum number of usages a single key may expand to
+pub const MAX_MACRO_KEYS: usize = 4;
+
+/// Length of the table as encoded by [`RemapTable::encode`]
+pub const ENCODED_LEN: usize = 2 + MAX_ENTRIES * ENTRY_LEN;
+
+/// Encoded length of a single entry: usage, action kind, key count and keys
+const ENTRY_LEN: usize = 3 + MAX_MACRO_KEYS;
+
+/// Marks a valid encoding so uninitialized storage isn't mistaken for a saved table
+const VALID: u8 = 0xa5;
+
+const KIND_REMAP: u8 = 0;
+const KIND_DISABLE: u8 = 1;
+const KIND_MACRO: u8 = 2;
+
+const OP_CLEAR: u8 = 0;
+const OP_REMAP: u8 = 1;
+const OP_DISABLE: u8 = 2;
+const OP_MACRO: u8 = 3;
+const OP_REMOVE: u8 = 4;
+
+const USAGE_NONE: u8 = 0x00;
+const USAGE_LEFT_CONTROL: u8 = 0xe0;
+const USAGE_RIGHT_GUI: u8 = 0xe7;
+
+/// Remap table errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RemapError {
+    /// The table has no room for another entry
+    TableFull,
+    /// The command is malformed
+    InvalidCommand,
+    /// The remapped keys don't fit in the report
+    Rollover,
+}
+
+/// What to do with a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Action {
+    /// Report the key as a different usage
+    Remap(u8),
+    /// Don't report the key
+    Disable,
+    /// Report the first `len` usages of `keys` pressed together
+    Macro { keys: [u8; MAX_MACRO_KEYS], len: u8 },
+}
+
+impl Action {
+    /// Create a macro action, returns `None` if `keys` is empty or longer than [`MAX_MACRO_KEYS`]
+    pub fn new_macro(keys: &[u8]) -> Option<Self> {
+        if keys.is_empty() || keys.len() > MAX_MACRO_KEYS {
+            return None;
+        }
+
+        let mut buf = [USAGE_NONE; MAX_MACRO_KEYS];
+        buf[..keys.len()].copy_from_slice(keys);
+        Some(Action::Macro {
+            keys: buf,
+            len: keys.len() as u8,
+        })
+    }
+
+    /// Usages reported in place of the original key
+    fn usages(&self) -> &[u8] {
+        match self {
+            Action::Remap(usage) => core::slice::from_ref(usage),
+            Action::Disable => &[],
+            Action::Macro { keys, len } => keys.get(..usize::from(*len)).unwrap_or(keys),
+        }
+    }
+}
+
+/// A remap entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Entry {
+    /// Key usage as produced by the keyboard scan
+    pub usage: u8,
+    /// Action applied to the key
+    pub action: Action,
+}
+
+/// Host command to edit the remap table
+///
+/// Encoded in the vendor feature report as an opcode byte followed by its arguments:
+///
+/// | Opcode | Command                      | Arguments              |
+/// |--------|------------------------------|------------------------|
+/// | `0x00` | Clear the table              |                        |
+/// | `0x01` | Remap a key                  | usage, target usage    |
+/// | `0x02` | Disable a key                | usage                  |
+/// | `0x03` | Expand a key into a chord    | usage, count, usages.. |
+/// | `0x04` | Restore a key's default      | usage                  |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RemapCommand {
+    /// Remove all entries
+    Clear,
+    /// Add or replace an entry
+    Set(Entry),
+    /// Remove the entry for a usage
+    Remove(u8),
+}
+
+impl RemapCommand {
+    /// Decode a command from a feature report payload
+    pub fn decode(bytes: &[u8]) -> Result<Self, RemapError> {
+        let (&op, args) = bytes.split_first().ok_or(RemapError::InvalidCommand)?;
+        let arg = |index: usize| args.get(index).copied().ok_or(RemapError::InvalidCommand);
+        let entry = |action| -> Result<Self, RemapError> { Ok(RemapCommand::Set(Entry { usage: arg(0)?, action })) };
+
+        match op {
+            OP_CLEAR => Ok(RemapCommand::Clear),
+            OP_REMAP => entry(Action::Remap(arg(1)?)),
+            OP_DISABLE => entry(Action::Disable),
+            OP_MACRO => {
+                let len = usize::from(arg(1)?);
+                let keys = args.get(2..2 + len).ok_or(RemapError::InvalidCommand)?;
+                entry(Action::new_macro(keys).ok_or(RemapError::InvalidCommand)?)
+            }
+            OP_REMOVE => Ok(RemapCommand::Remove(arg(0)?)),
+            _ => Err(RemapError::InvalidCommand),
+        }
+    }
+}
+
+/// Storage backend for the remap table
+pub trait Storage {
+    /// Load the saved table, `None` if no table was saved or it couldn't be read
+    fn load(&mut self) -> Option<[u8; ENCODED_LEN]>;
+    /// Save the table, must not block
+    fn store(&mut self, table: &[u8; ENCODED_LEN]);
+}
+
+/// Key remap table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemapTable {
+    entries: [Option<Entry>; MAX_ENTRIES],
+}
+
+impl Default for RemapTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RemapTable {
+    /// Create an empty table
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; MAX_ENTRIES],
+        }
+    }
+
+    /// Returns the entry for a usage, if any
+    pub fn get(&self, usage: u8) -> Option<Entry> {
+        self.entries
+            .iter()
+            .flatten()
+            .find(|entry| entry.usage == usage)
+            .copied()
+    }
+
+    /// Returns true if the table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+
+    /// Add an entry, replacing any existing entry for the same usage
+    pub fn set(&mut self, entry: Entry) -> Result<(), RemapError> {
+        let slot = match self
+            .entries
+            .iter()
+            .position(|e| e.is_some_and(|e| e.usage == entry.usage))
+        {
+            Some(index) => Some(index),
+            None => self.entries.iter().position(Option::is_none),
+        };
+        let slot = slot
+            .and_then(|index| self.entries.get_mut(index))
+            .ok_or(RemapError::TableFull)?;
+        *slot = Some(entry);
+        Ok(())
+    }
+
+    /// Remove the entry for a usage
+    pub fn remove(&mut self, usage: u8) {
+        for slot in self.entries.iter_mut() {
+            if slot.is_some_and(|entry| entry.usage == usage) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.entries = [None; MAX_ENTRIES];
+    }
+
+    /// Apply a host command
+    pub fn execute(&mut self, command: RemapCommand) -> Result<(), RemapError> {
+        match command {
+            RemapCommand::Clear => self.clear(),
+            RemapCommand::Set(entry) => self.set(entry)?,
+            RemapCommand::Remove(usage) => self.remove(usage),
+        }
+        Ok(())
+    }
+
+    /// Remap a report consisting of a modifier byte followed by key usages
+    ///
+    /// The remapped report is written to `out` with the same layout and length as `report` (truncated to the length
+    /// of `out`), returning that length. Returns [`RemapError::Rollover`] if the remapped keys don't fit.
+    pub fn apply(&self, report: &[u8], out: &mut [u8]) -> Result<usize, RemapError> {
+        let len = report.len().min(out.len());
+        let Some((&modifiers, keys)) = report.get(..len).and_then(|report| report.split_first()) else {
+            return Ok(0);
+        };
+        let Some((out_modifiers, out_keys)) = out.get_mut(..len).and_then(|out| out.split_first_mut()) else {
+            return Ok(0);
+        };
+
+        *out_modifiers = modifiers;
+        out_keys.fill(USAGE_NONE);
+        let mut count = 0;
+        for &key in keys.iter().filter(|&&key| key != USAGE_NONE) {
+            let action = self.get(key).map(|entry| entry.action).unwrap_or(Action::Remap(key));
+            for &usage in action.usages() {
+                if (USAGE_LEFT_CONTROL..=USAGE_RIGHT_GUI).contains(&usage) {
+                    *out_modifiers |= 1 << (usage - USAGE_LEFT_CONTROL);
+                } else if usage != USAGE_NONE && !out_keys[..count].contains(&usage) {
+                    *out_keys.get_mut(count).ok_or(RemapError::Rollover)? = usage;
+                    count += 1;
+                }
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Encode the table for storage
+    pub fn encode(&self) -> [u8; ENCODED_LEN] {
+        let mut buf = [0u8; ENCODED_LEN];
+        buf[0] = VALID;
+        let mut count = 0;
+        for (entry, chunk) in self.entries.iter().flatten().zip(buf[2..].chunks_exact_mut(ENTRY_LEN)) {
+            let (kind, usages) = match entry.action {
+                Action::Remap(_) => (KIND_REMAP, entry.action.usages()),
+                Action::Disable => (KIND_DISABLE, entry.action.usages()),
+                Action::Macro { .. } => (KIND_MACRO, entry.action.usages()),
+            };
+            chunk[0] = entry.usage;
+            chunk[1] = kind;
+            chunk[2] = usages.len() as u8;
+            chunk[3..3 + usages.len()].copy_from_slice(usages);
+            count += 1;
+        }
+        buf[1] = count;
+        buf
+    }
+
+    /// Decode a table produced by [`Self::encode`], returns `None` if the buffer doesn't contain a saved table
+    pub fn decode(buf: &[u8; ENCODED_LEN]) -> Option<Self> {
+        if buf[0] != VALID || usize::from(buf[1]) > MAX_ENTRIES {
+            return None;
+        }
+
+        let mut table = Self::new();
+        for chunk in buf[2..].chunks_exact(ENTRY_LEN).take(usize::from(buf[1])) {
+            let usages = chunk.get(3..3 + usize::from(chunk[2]))?;
+            let action = match chunk[1] {
+                KIND_REMAP => Action::Remap(*usages.first()?),
+                KIND_DISABLE => Action::Disable,
+                KIND_MACRO => Action::new_macro(usages)?,
+                _ => return None,
+            };
+            table
+                .set(Entry {
+                    usage: chunk[0],
+                    action,
+                })
+                .ok()?;
+        }
+        Some(table)
+    }
+}
+
+/// Remap configuration for the keyboard service
+pub struct Config<'a> {
+    /// Vendor feature report ID the host uses to edit the table
+    pub report_id: hid::ReportId,
+    /// Backend the table is saved to, if any
+    pub storage: Option<&'a mut dyn Storage>,
+}
+
+/// Host-editable remap table with optional persistence
+pub struct Remapper<'a> {
+    table: RemapTable,
+    storage: Option<&'a mut dyn Storage>,
+}
+
+impl<'a> Remapper<'a> {
+    /// Create a new remapper, restoring the saved table from `storage` if there is one
+    pub fn new(mut storage: Option<&'a mut dyn Storage>) -> Self {
+        let table = storage
+            .as_mut()
+            .and_then(|storage| storage.load())
+            .and_then(|buf| RemapTable::decode(&buf))
+            .unwrap_or_default();
+        Self { table, storage }
+    }
+
+    /// Returns the current table
+    pub fn table(&self) -> &RemapTable {
+        &self.table
+    }
+
+    /// Apply a host command from a feature report payload, saving the table if it changed
+    pub fn set_report(&mut self, payload: &[u8]) -> Result<(), RemapError> {
+        let command = RemapCommand::decode(payload)?;
+        let previous = self.table;
+        self.table.execute(command)?;
+        if self.table != previous
+            && let Some(storage) = self.storage.as_mut()
+        {
+            storage.store(&self.table.encode());
+        }
+        Ok(())
+    }
+
+    /// Remap a report, see [`RemapTable::apply`]
+    pub fn apply(&self, report: &[u8], out: &mut [u8]) -> Result<usize, RemapError> {
+        self.table.apply(report, out)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const USAGE_A: u8 = 0x04;
+    const USAGE_B: u8 = 0x05;
+    const USAGE_C: u8 = 0x06;
+    const USAGE_CAPS_LOCK: u8 = 0x39;
+    const USAGE_INSERT: u8 = 0x49;
+
+    #[derive(Default)]
+    struct RamStorage(Option<[u8; ENCODED_LEN]>);
+
+    impl Storage for RamStorage {
+        fn load(&mut self) -> Option<[u8; ENCODED_LEN]> {
+            self.0
+        }
+
+        fn store(&mut self, table: &[u8; ENCODED_LEN]) {
+            self.0 = Some(*table);
+        }
+    }
+
+    #[test]
+    fn remap_disable_and_macro() {
+        let mut table = RemapTable::new();
+        // Caps lock becomes left control, insert is disabled, A types B+C
+        table
+            .execute(RemapCommand::decode(&[OP_REMAP, USAGE_CAPS_LOCK, USAGE_LEFT_CONTROL]).unwrap())
+            .unwrap();
+        table
+            .execute(RemapCommand::decode(&[OP_DISABLE, USAGE_INSERT]).unwrap())
+            .unwrap();
+        table
+            .execute(RemapCommand::decode(&[OP_MACRO, USAGE_A, 2, USAGE_B, USAGE_C]).unwrap())
+            .unwrap();
+
+        let mut out = [0xffu8; 7];
+        let report = [0x00, USAGE_CAPS_LOCK, USAGE_INSERT, USAGE_A, 0, 0, 0];
+        assert_eq!(table.apply(&report, &mut out), Ok(7));
+        assert_eq!(out, [0x01, USAGE_B, USAGE_C, 0, 0, 0, 0]);
+
+        // Expansion that doesn't fit is reported as rollover
+        let report = [0x00, USAGE_A, USAGE_CAPS_LOCK];
+        assert_eq!(table.apply(&report, &mut out), Err(RemapError::Rollover));
+
+        table.execute(RemapCommand::Remove(USAGE_A)).unwrap();
+        assert_eq!(table.apply(&[0x00, USAGE_A, 0], &mut out), Ok(3));
+        assert_eq!(out[..3], [0x00, USAGE_A, 0]);
+    }
+
+    #[test]
+    fn invalid_commands() {
+        assert_eq!(RemapCommand::decode(&[]), Err(RemapError::InvalidCommand));
+        assert_eq!(
+            RemapCommand::decode(&[OP_REMAP, USAGE_A]),
+            Err(RemapError::InvalidCommand)
+        );
+        assert_eq!(
+            RemapCommand::decode(&[OP_MACRO, USAGE_A, MAX_MACRO_KEYS as u8 + 1, 1, 2, 3, 4, 5]),
+            Err(RemapError::InvalidCommand)
+        );
+        assert_eq!(RemapCommand::decode(&[0xff]), Err(RemapError::InvalidCommand));
+
+        let mut table = RemapTable::new();
+        for usage in 0..MAX_ENTRIES as u8 {
+            table
+                .set(Entry {
+                    usage,
+                    action: Action::Disable,
+                })
+                .unwrap();
+        }
+        assert_eq!(
+            table.set(Entry {
+                usage: 0x80,
+                action: Action::Disable,
+            }),
+            Err(RemapError::TableFull)
+        );
+    }
+
+    #[test]
+    fn persisted_across_restart() {
+        let mut storage = RamStorage::default();
+        assert!(Remapper::new(Some(&mut storage)).table().is_empty());
+
+        let mut remapper = Remapper::new(Some(&mut storage));
+        remapper
+            .set_report(&[OP_REMAP, USAGE_CAPS_LOCK, USAGE_LEFT_CONTROL])
+            .unwrap();
+        remapper.set_report(&[OP_MACRO, USAGE_A, 2, USAGE_B, USAGE_C]).unwrap();
+        let table = *remapper.table();
+
+        let restored = Remapper::new(Some(&mut storage));
+        assert_eq!(*restored.table(), table);
+
+        // Uninitialized storage is ignored
+        let mut blank = RamStorage(Some([0xff; ENCODED_LEN]));
+        assert!(Remapper::new(Some(&mut blank)).table().is_empty());
+    }
+}
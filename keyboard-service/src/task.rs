@@ -1,5 +1,6 @@
 use core::borrow::BorrowMut;
 
+use embedded_services::comms;
 use embedded_services::hid;
 
 use crate::hid_kb::{self, CONTEXT};
@@ -10,6 +11,13 @@ pub async fn keyboard_task<T: crate::HidKeyboard>(
     crate::hid_kb::handle_keyboard(keyboard).await
 }
 
+pub async fn keyboard_task_with_remap<T: crate::HidKeyboard>(
+    keyboard: T,
+    remap: crate::remap::Config<'_>,
+) -> Result<embedded_services::Never, super::KeyboardError> {
+    crate::hid_kb::handle_keyboard_with_remap(keyboard, Some(remap)).await
+}
+
 pub async fn reports_task<T: embedded_hal::digital::OutputPin>(
     keyboard_interrupt: T,
 ) -> Result<embedded_services::Never, super::KeyboardError> {
@@ -64,6 +72,11 @@ pub async fn init_and_recv_device_requests_task(
         .expect("Device must not already be registered");
     let context = CONTEXT.get().await;
 
+    // Register to receive system sleep notifications
+    comms::register_endpoint(context, &context.tp)
+        .await
+        .expect("Keyboard endpoint must not already be registered");
+
     // Buffer holding hid descriptor
     embedded_services::define_static_buffer!(hid_desc_buf, u8, [0u8; hid::DESCRIPTOR_LEN]);
     {
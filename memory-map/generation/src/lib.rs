@@ -0,0 +1,304 @@
+#![no_std]
+
+#[cfg(not(target_os = "none"))]
+pub use internal::*;
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests;
+
+#[cfg(not(target_os = "none"))]
+pub(crate) mod internal {
+    extern crate std;
+
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        string::String,
+        vec::Vec,
+    };
+
+    use anyhow::anyhow;
+    use quote::{format_ident, quote};
+    use serde::Deserialize;
+    use syn::Ident;
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    pub struct Region {
+        pub size: u32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    pub struct Section {
+        pub offset: u32,
+        pub size: u32,
+    }
+
+    impl Section {
+        fn end(&self) -> Option<u32> {
+            self.offset.checked_add(self.size)
+        }
+
+        pub fn overlaps(&self, other: &Section) -> bool {
+            self.offset < other.end().unwrap_or(u32::MAX) && other.offset < self.end().unwrap_or(u32::MAX)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub(crate) struct GeneratedSection {
+        pub name: String,
+        pub offset: u32,
+        pub size: u32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    pub struct Manifest {
+        pub region: Region,
+        pub sections: BTreeMap<String, Section>,
+    }
+
+    /// Converts a snake_case section name to the CamelCase name of its variant
+    fn variant_name(name: &str) -> String {
+        name.split('_')
+            .flat_map(|part| {
+                let mut chars = part.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase())
+                    .into_iter()
+                    .chain(chars)
+            })
+            .collect()
+    }
+
+    impl Manifest {
+        fn validate_names(&self) -> anyhow::Result<()> {
+            if self.sections.is_empty() {
+                return Err(anyhow!("Manifest has no sections"));
+            }
+
+            let mut variants = BTreeSet::new();
+            for name in self.sections.keys() {
+                let snake_case = name.starts_with(|c: char| c.is_ascii_lowercase())
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+                if !snake_case || syn::parse_str::<Ident>(name).is_err() {
+                    return Err(anyhow!("Section {} is not a snake_case identifier", name));
+                }
+
+                if !variants.insert(variant_name(name)) {
+                    return Err(anyhow!("Section {} has the same variant name as another section", name));
+                }
+            }
+            Ok(())
+        }
+
+        fn validate_size(&self) -> anyhow::Result<()> {
+            for (name, section) in self.sections.iter() {
+                if section.size == 0 {
+                    return Err(anyhow!("Section {} is empty", name));
+                }
+
+                if section.end().is_none_or(|end| end > self.region.size) {
+                    return Err(anyhow!("Section {} goes over the region edge", name));
+                }
+            }
+            Ok(())
+        }
+
+        fn validate_overlap(&self) -> anyhow::Result<()> {
+            let mut sections = Vec::from_iter(self.sections.iter());
+            sections.sort_by_key(|(_, section)| section.offset);
+
+            for (i, (section_name_x, section_x)) in sections.iter().enumerate() {
+                for (section_name_y, section_y) in sections.iter().skip(i + 1) {
+                    if section_x.overlaps(section_y) {
+                        return Err(anyhow!("Sections {} and {} overlap", section_name_x, section_name_y));
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        pub fn check_consistency(&self) -> anyhow::Result<()> {
+            self.validate_names()?;
+            self.validate_size()?;
+            self.validate_overlap()?;
+            Ok(())
+        }
+
+        /// Returns the sections ordered by offset
+        pub(crate) fn generate(self) -> anyhow::Result<Vec<GeneratedSection>> {
+            self.check_consistency()?;
+
+            let mut sections = Vec::from_iter(
+                self.sections
+                    .into_iter()
+                    .map(|(name, Section { offset, size })| GeneratedSection { name, offset, size }),
+            );
+            sections.sort_by_key(|section| section.offset);
+            Ok(sections)
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn transform_toml(name: Ident, section_name: Ident, manifest: &str) -> proc_macro2::TokenStream {
+        let manifest = match transform_toml_manifest(manifest) {
+            Ok(manifest) => manifest,
+            Err(e) => return anyhow_error_to_compile_error(e),
+        };
+
+        transform_manifest(name, section_name, manifest)
+    }
+
+    #[cfg(feature = "toml")]
+    pub(crate) fn transform_toml_manifest(manifest: &str) -> anyhow::Result<Manifest> {
+        Ok(toml::from_str(manifest)?)
+    }
+
+    pub fn transform_manifest(name: Ident, section_name: Ident, manifest: Manifest) -> proc_macro2::TokenStream {
+        let region_size = manifest.region.size;
+        let sections = match manifest.generate() {
+            Ok(sections) => sections,
+            Err(e) => return anyhow_error_to_compile_error(e),
+        };
+
+        // Fields of the region structure, with reserved fields filling the gaps between sections
+        let mut fields = Vec::new();
+        let mut position = 0;
+        let gaps = sections
+            .iter()
+            .map(|section| (section.offset, Some(section)))
+            .chain([(region_size, None)]);
+        for (offset, section) in gaps {
+            if offset > position {
+                let reserved = format_ident!("_reserved_{:x}", position);
+                fields.push((reserved, (offset - position) as usize, false));
+            }
+
+            if let Some(section) = section {
+                fields.push((format_ident!("{}", section.name), section.size as usize, true));
+                position = offset + section.size;
+            }
+        }
+
+        let fields_def = fields.iter().map(|(field, size, public)| {
+            if *public {
+                quote! { pub #field: [u8; #size], }
+            } else {
+                quote! { #field: [u8; #size], }
+            }
+        });
+
+        let fields_constr = fields.iter().map(|(field, size, _)| quote! { #field: [0; #size], });
+
+        let constants = sections.iter().map(|section| {
+            let upper = section.name.to_ascii_uppercase();
+            let offset_name = format_ident!("{}_OFFSET", upper);
+            let size_name = format_ident!("{}_SIZE", upper);
+            let offset = section.offset;
+            let size = section.size;
+
+            quote! {
+                pub const #offset_name: u32 = #offset;
+                pub const #size_name: u32 = #size;
+            }
+        });
+
+        let variants = Vec::from_iter(
+            sections
+                .iter()
+                .map(|section| format_ident!("{}", variant_name(&section.name))),
+        );
+        let count = variants.len();
+        let offsets = sections.iter().map(|section| section.offset);
+        let sizes = sections.iter().map(|section| section.size);
+        let names = sections.iter().map(|section| section.name.as_str());
+
+        let resolve_arms = sections.iter().zip(&variants).map(|(section, variant)| {
+            let first = section.offset;
+            let last = section.offset + (section.size - 1);
+            let relative = if first == 0 {
+                quote! { offset }
+            } else {
+                quote! { offset - #first }
+            };
+
+            quote! { #first..=#last => Some((Self::#variant, #relative)), }
+        });
+
+        quote! {
+            /// Sections of a memory-mapped region, generated from the layout manifest
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub enum #section_name {
+                #(#variants,)*
+            }
+
+            impl #section_name {
+                /// All sections, ordered by offset
+                pub const ALL: [Self; #count] = [#(Self::#variants,)*];
+
+                /// Offset of the section within the region
+                pub const fn offset(self) -> u32 {
+                    match self {
+                        #(Self::#variants => #offsets,)*
+                    }
+                }
+
+                /// Size of the section in bytes
+                pub const fn size(self) -> u32 {
+                    match self {
+                        #(Self::#variants => #sizes,)*
+                    }
+                }
+
+                /// Name of the section in the manifest
+                pub const fn name(self) -> &'static str {
+                    match self {
+                        #(Self::#variants => #names,)*
+                    }
+                }
+
+                /// Returns the section containing `offset` and the offset relative to the start of that section
+                pub const fn resolve(offset: u32) -> Option<(Self, u32)> {
+                    match offset {
+                        #(#resolve_arms)*
+                        _ => None,
+                    }
+                }
+            }
+
+            /// Host-facing layout of a memory-mapped region, generated from the layout manifest
+            #[repr(C)]
+            pub struct #name {
+                #(#fields_def)*
+            }
+
+            impl #name {
+                /// Size of the region in bytes
+                pub const SIZE: u32 = #region_size;
+
+                #(#constants)*
+
+                /// Create a zeroed region
+                pub const fn new() -> Self {
+                    Self {
+                        #(#fields_constr)*
+                    }
+                }
+            }
+
+            impl Default for #name {
+                fn default() -> Self {
+                    Self::new()
+                }
+            }
+
+            const _: () = assert!(core::mem::size_of::<#name>() == #region_size as usize);
+        }
+    }
+
+    fn anyhow_error_to_compile_error(error: anyhow::Error) -> proc_macro2::TokenStream {
+        syn::Error::new(proc_macro2::Span::call_site(), std::format!("{error:#}")).into_compile_error()
+    }
+}
@@ -0,0 +1,124 @@
+#![allow(clippy::unwrap_used)]
+extern crate std;
+
+use std::{format, string::ToString, vec};
+
+use quote::format_ident;
+
+use crate::{GeneratedSection, Manifest, Region, Section, transform_manifest, transform_toml_manifest};
+
+fn create_manifest(sections: &[(&str, u32, u32)]) -> Manifest {
+    Manifest {
+        region: Region { size: 0x100 },
+        sections: sections
+            .iter()
+            .map(|(name, offset, size)| {
+                (
+                    name.to_string(),
+                    Section {
+                        offset: *offset,
+                        size: *size,
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn generate_orders_by_offset() {
+    let manifest = transform_toml_manifest(include_str!("layout.toml")).unwrap();
+    let result = manifest.generate().unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            GeneratedSection {
+                name: "battery".to_string(),
+                offset: 0x00,
+                size: 0x40,
+            },
+            GeneratedSection {
+                name: "usb_c".to_string(),
+                offset: 0x40,
+                size: 0x10,
+            },
+            GeneratedSection {
+                name: "thermal".to_string(),
+                offset: 0x80,
+                size: 0x20,
+            },
+        ]
+    );
+}
+
+#[test]
+fn overlap() {
+    let result = create_manifest(&[("battery", 0x00, 0x40), ("thermal", 0x3f, 0x10)]).check_consistency();
+    assert_eq!(format!("{result:?}"), "Err(Sections battery and thermal overlap)");
+}
+
+#[test]
+fn overflow() {
+    let result = create_manifest(&[("battery", 0xf0, 0x20)]).check_consistency();
+    assert_eq!(format!("{result:?}"), "Err(Section battery goes over the region edge)");
+
+    let result = create_manifest(&[("battery", u32::MAX, 0x20)]).check_consistency();
+    assert_eq!(format!("{result:?}"), "Err(Section battery goes over the region edge)");
+}
+
+#[test]
+fn empty() {
+    let result = create_manifest(&[("battery", 0x00, 0)]).check_consistency();
+    assert_eq!(format!("{result:?}"), "Err(Section battery is empty)");
+
+    let result = create_manifest(&[]).check_consistency();
+    assert_eq!(format!("{result:?}"), "Err(Manifest has no sections)");
+}
+
+#[test]
+fn names() {
+    let result = create_manifest(&[("Battery", 0x00, 0x10)]).check_consistency();
+    assert_eq!(
+        format!("{result:?}"),
+        "Err(Section Battery is not a snake_case identifier)"
+    );
+
+    let result = create_manifest(&[("type", 0x00, 0x10)]).check_consistency();
+    assert_eq!(
+        format!("{result:?}"),
+        "Err(Section type is not a snake_case identifier)"
+    );
+
+    let result = create_manifest(&[("usb_c", 0x00, 0x10), ("usb__c", 0x10, 0x10)]).check_consistency();
+    assert_eq!(
+        format!("{result:?}"),
+        "Err(Section usb_c has the same variant name as another section)"
+    );
+}
+
+#[test]
+fn transform() {
+    let manifest = transform_toml_manifest(include_str!("layout.toml")).unwrap();
+    let tokens = transform_manifest(format_ident!("AcpiMemory"), format_ident!("AcpiSection"), manifest).to_string();
+
+    assert!(!tokens.contains("compile_error"));
+    for expected in [
+        "pub enum AcpiSection",
+        "UsbC",
+        "pub struct AcpiMemory",
+        "_reserved_50",
+        "_reserved_a0",
+        "pub const THERMAL_OFFSET",
+    ] {
+        assert!(tokens.contains(expected), "missing {expected}");
+    }
+
+    let tokens = transform_manifest(
+        format_ident!("AcpiMemory"),
+        format_ident!("AcpiSection"),
+        create_manifest(&[]),
+    )
+    .to_string();
+    assert!(tokens.contains("compile_error"));
+}
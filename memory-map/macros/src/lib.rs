@@ -0,0 +1,153 @@
+use std::{fs::File, io::Read, ops::Deref, path::PathBuf};
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use syn::{Ident, LitStr, parse::Lookahead1};
+
+fn transform(input: Input) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let mut path = PathBuf::from(input.manifest.value());
+    if path.is_relative() {
+        #[allow(clippy::unwrap_used)]
+        //panic safety: CARGO_MANIFEST_DIR is always set by cargo when invoking proc-macros, and this is not deployed code.
+        let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+        path = manifest_dir.join(path);
+    }
+
+    let mut file_contents = String::new();
+    File::open(&path)
+        .and_then(|mut file| file.read_to_string(&mut file_contents))
+        .map_err(|e| {
+            syn::Error::new(
+                Span::call_site(),
+                format!("Could not read the manifest file at '{}': {e}", path.display()),
+            )
+        })?;
+
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy())
+        .ok_or(syn::Error::new(
+            Span::call_site(),
+            "Manifest file has no file extension",
+        ))?;
+
+    match extension.deref() {
+        #[cfg(feature = "toml")]
+        "toml" => Ok(memory_map_generation::transform_toml(
+            input.name,
+            input.section_name,
+            &file_contents,
+        )),
+        #[cfg(not(feature = "toml"))]
+        "toml" => Err(syn::Error::new(Span::call_site(), "The toml feature is not enabled")),
+        unknown => Err(syn::Error::new(
+            Span::call_site(),
+            format!("Unknown manifest file extension: '{unknown}'"),
+        )),
+    }
+}
+
+/// Generate the layout of a memory-mapped region from a manifest
+///
+/// ```ignore
+/// create_memory_layout!(name: AcpiMemory, section_name: AcpiSection, manifest: "acpi.toml");
+/// ```
+///
+/// Generates `name`, a `#[repr(C)]` structure of the region with a field per section and the host-facing
+/// `<SECTION>_OFFSET` and `<SECTION>_SIZE` constants, and `section_name`, an enum of the sections with
+/// `resolve` to convert an access offset to its section. Relative manifest paths are resolved from the crate root.
+#[proc_macro]
+pub fn create_memory_layout(item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as Input);
+
+    match transform(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct Preinput {
+    name: Option<Ident>,
+    section_name: Option<Ident>,
+    manifest: Option<LitStr>,
+}
+
+struct Input {
+    name: Ident,
+    section_name: Ident,
+    manifest: LitStr,
+}
+
+impl TryFrom<Preinput> for Input {
+    type Error = syn::Error;
+
+    fn try_from(value: Preinput) -> Result<Self, Self::Error> {
+        match value {
+            Preinput {
+                name: Some(name),
+                section_name: Some(section_name),
+                manifest: Some(manifest),
+            } => Ok(Input {
+                name,
+                section_name,
+                manifest,
+            }),
+            _ => Err(syn::Error::new(
+                Span::call_site(),
+                "Missing fields in macro invocation: name, section_name or manifest",
+            )),
+        }
+    }
+}
+
+/// Write a value to an option, but only if it has not yet been set before.
+///
+/// Emit an error with the span indicated by the look-token if it is not unique.
+fn set_unique<T>(target: &mut Option<T>, value: T, look: Lookahead1<'_>) -> Result<(), syn::Error> {
+    if target.replace(value).is_none() {
+        Ok(())
+    } else {
+        Err(syn::Error::new(look.error().span(), "Duplicate field"))
+    }
+}
+
+impl syn::parse::Parse for Input {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut result = Preinput::default();
+
+        loop {
+            let look = input.lookahead1();
+
+            if look.peek(kw::name) {
+                input.parse::<kw::name>()?;
+                input.parse::<syn::Token![:]>()?;
+                set_unique(&mut result.name, input.parse()?, look)?;
+            } else if look.peek(kw::section_name) {
+                input.parse::<kw::section_name>()?;
+                input.parse::<syn::Token![:]>()?;
+                set_unique(&mut result.section_name, input.parse()?, look)?;
+            } else if look.peek(kw::manifest) {
+                input.parse::<kw::manifest>()?;
+                input.parse::<syn::Token![:]>()?;
+                set_unique(&mut result.manifest, input.parse()?, look)?;
+            } else {
+                return Err(look.error());
+            }
+
+            if input.is_empty() {
+                break;
+            }
+
+            input.parse::<syn::Token![,]>()?;
+        }
+
+        result.try_into()
+    }
+}
+
+mod kw {
+    syn::custom_keyword!(name);
+    syn::custom_keyword!(section_name);
+    syn::custom_keyword!(manifest);
+}
@@ -48,7 +48,7 @@ pub trait ServiceRunner<'hw> {
 ///     time_alarm_service::Service<'static>,
 ///     |resources| time_alarm_service::Service::new(
 ///         resources,
-///         dt_clock, tz, ac_expiration, ac_policy, dc_expiration, dc_policy
+///         dt_clock, tz, ac_expiration, ac_policy, ac_wake_status, dc_expiration, dc_policy, dc_wake_status
 ///     )
 /// ).expect("failed to initialize time_alarm service");
 /// ```
@@ -0,0 +1,151 @@
+//! Host `embassy-time` driver with support for pausing and advancing time
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::Waker;
+use std::thread;
+use std::time::{Duration as StdDuration, Instant as StdInstant};
+
+use embassy_time_driver::Driver;
+
+struct State {
+    /// Host instant corresponding to `offset`, set on first use and whenever time resumes
+    origin: Option<StdInstant>,
+    /// Ticks at `origin`
+    offset: u64,
+    /// Frozen tick count while time is paused
+    paused: Option<u64>,
+    /// Pending wakers and their expiration in ticks
+    timers: Vec<(u64, Waker)>,
+    /// Whether the timer thread has been started
+    thread_started: bool,
+}
+
+impl State {
+    fn now(&mut self) -> u64 {
+        if let Some(paused) = self.paused {
+            return paused;
+        }
+
+        let origin = *self.origin.get_or_insert_with(StdInstant::now);
+        self.offset + origin.elapsed().as_micros() as u64
+    }
+
+    /// Wake all expired timers, returning the expiration of the next pending timer
+    fn wake_expired(&mut self) -> Option<u64> {
+        let now = self.now();
+        self.timers.retain(|(at, waker)| {
+            if *at <= now {
+                waker.wake_by_ref();
+                false
+            } else {
+                true
+            }
+        });
+        self.timers.iter().map(|(at, _)| *at).min()
+    }
+}
+
+pub(crate) struct TestDriver {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl TestDriver {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A panicking test shouldn't take down every other test in the binary
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Freeze time at its current value
+    pub(crate) fn pause(&self) {
+        let mut state = self.lock();
+        let now = state.now();
+        state.paused = Some(now);
+    }
+
+    /// Resume following the host clock from the current tick count
+    pub(crate) fn resume(&self) {
+        let mut state = self.lock();
+        if let Some(paused) = state.paused.take() {
+            state.origin = Some(StdInstant::now());
+            state.offset = paused;
+        }
+        self.changed.notify_one();
+    }
+
+    /// Move time forward by the given number of ticks and wake any expired timers
+    pub(crate) fn advance(&self, ticks: u64) {
+        let mut state = self.lock();
+        match state.paused.as_mut() {
+            Some(paused) => *paused += ticks,
+            None => state.offset += ticks,
+        }
+        state.wake_expired();
+        self.changed.notify_one();
+    }
+
+    /// Expiration of the next pending timer
+    pub(crate) fn next_expiration(&self) -> Option<u64> {
+        self.lock().timers.iter().map(|(at, _)| *at).min()
+    }
+
+    /// Wake expired timers once they are due in host time
+    fn run(&self) -> ! {
+        let mut state = self.lock();
+        loop {
+            let next = state.wake_expired();
+            state = match (next, state.paused) {
+                (Some(at), None) => {
+                    let timeout = StdDuration::from_micros(at.saturating_sub(state.now()));
+                    self.changed
+                        .wait_timeout(state, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                // Paused timers only expire through `advance`
+                _ => self.changed.wait(state).unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+}
+
+impl Driver for TestDriver {
+    fn now(&self) -> u64 {
+        self.lock().now()
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        let mut state = self.lock();
+        if at <= state.now() {
+            waker.wake_by_ref();
+            return;
+        }
+
+        if let Some((expiration, _)) = state.timers.iter_mut().find(|(_, pending)| pending.will_wake(waker)) {
+            *expiration = (*expiration).min(at);
+        } else {
+            state.timers.push((at, waker.clone()));
+        }
+
+        if !state.thread_started {
+            state.thread_started = true;
+            thread::spawn(|| DRIVER.run());
+        }
+        self.changed.notify_one();
+    }
+}
+
+embassy_time_driver::time_driver_impl!(static DRIVER: TestDriver = TestDriver {
+    state: Mutex::new(State {
+        origin: None,
+        offset: 0,
+        paused: None,
+        timers: Vec::new(),
+        thread_started: false,
+    }),
+    changed: Condvar::new(),
+});
+
+/// The driver instance registered with `embassy-time`
+pub(crate) fn driver() -> &'static TestDriver {
+    &DRIVER
+}
@@ -0,0 +1,15 @@
+//! Test support for services
+//!
+//! This crate provides the `embassy-time` driver used by host tests in place of the `std` driver, along with helpers
+//! to run service event loops under tokio. Time follows the host clock by default, so existing tests that rely on
+//! real timers keep working. Tests of timeouts (sink-ready timeouts, CFU ticks, debounce, etc.) can instead
+//! [`pause`](time::pause) time and advance it explicitly, which makes them deterministic and removes real sleeps.
+//!
+//! Crates that use this as a dev-dependency must link it into every test binary, e.g. with
+//! `use odp_test_support as _;`, otherwise the time driver is missing at link time.
+#![allow(clippy::expect_used)]
+#![allow(clippy::panic)]
+
+mod driver;
+pub mod task;
+pub mod time;
@@ -0,0 +1,70 @@
+//! Executor helpers for running services under tokio
+use core::future::Future;
+use core::pin::pin;
+
+use embassy_futures::select::{Either, select};
+use embassy_time::Duration;
+
+/// Number of times [`settle`] yields to other tasks
+const SETTLE_YIELDS: usize = 32;
+
+/// Yield repeatedly so that other tasks and joined futures can process pending events
+///
+/// This lets a service event loop react to an event sent by the test before the test asserts on the result, without
+/// relying on a real sleep.
+pub async fn settle() {
+    for _ in 0..SETTLE_YIELDS {
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Run `test` alongside a service event loop, returning the output of `test`
+///
+/// Services are expected to run forever, so this panics if `service` completes first.
+pub async fn run_until<T>(service: impl Future, test: impl Future<Output = T>) -> T {
+    match select(pin!(service), pin!(test)).await {
+        Either::First(_) => panic!("Service loop exited"),
+        Either::Second(output) => output,
+    }
+}
+
+/// Await `future` with a timeout in host time, panicking if it elapses
+///
+/// Unlike `embassy_time::with_timeout`, this timeout still elapses while time is [`paused`](crate::time::pause).
+pub async fn with_real_timeout<T>(timeout: Duration, future: impl Future<Output = T>) -> T {
+    tokio::time::timeout(core::time::Duration::from_micros(timeout.as_micros()), future)
+        .await
+        .expect("Test timed out")
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::future::pending;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn settle_runs_joined_futures() {
+        let processed = Cell::new(false);
+        let output = run_until(
+            async {
+                tokio::task::yield_now().await;
+                processed.set(true);
+                pending::<()>().await;
+            },
+            async {
+                settle().await;
+                processed.get()
+            },
+        )
+        .await;
+        assert!(output);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "Service loop exited")]
+    async fn service_exit_panics() {
+        run_until(async {}, pending::<()>()).await;
+    }
+}
@@ -0,0 +1,123 @@
+//! Virtual time control
+//!
+//! `embassy-time` is global to a test binary, while tests within a binary run in parallel. Tests that pause time
+//! therefore hold an exclusive lock on the clock, and tests that rely on time passing normally (e.g. through
+//! `embassy_time::with_timeout`) can hold a shared lock with [`real_time`] so that they never observe a paused or
+//! advanced clock.
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use embassy_time::{Duration, Instant};
+
+use crate::driver::driver;
+use crate::task::settle;
+
+static CLOCK: RwLock<()> = RwLock::new(());
+
+/// Guard for a test that requires time to follow the host clock
+pub struct RealTime {
+    _guard: RwLockReadGuard<'static, ()>,
+}
+
+/// Wait until no test has time paused and prevent any test from pausing it while the returned guard is held
+pub fn real_time() -> RealTime {
+    RealTime {
+        _guard: CLOCK.read().unwrap_or_else(PoisonError::into_inner),
+    }
+}
+
+/// Guard for a test that controls time, time resumes following the host clock when dropped
+pub struct PausedTime {
+    _guard: RwLockWriteGuard<'static, ()>,
+}
+
+/// Wait for exclusive control of time, then pause it
+///
+/// While paused, [`Instant::now`] only changes through [`PausedTime::advance`].
+pub fn pause() -> PausedTime {
+    let guard = CLOCK.write().unwrap_or_else(PoisonError::into_inner);
+    driver().pause();
+    PausedTime { _guard: guard }
+}
+
+impl PausedTime {
+    /// Advance time by `duration`
+    ///
+    /// Time is advanced one timer expiration at a time, letting other tasks [`settle`] after each step. A task that
+    /// arms a new timer when woken, such as a periodic tick, therefore sees every expiration within `duration` in
+    /// order rather than a single jump to the end.
+    pub async fn advance(&self, duration: Duration) {
+        let target = Instant::now() + duration;
+        loop {
+            settle().await;
+            match driver().next_expiration() {
+                Some(at) if at <= target.as_ticks() => driver().advance(at.saturating_sub(Instant::now().as_ticks())),
+                _ => break,
+            }
+        }
+
+        driver().advance(target.as_ticks().saturating_sub(Instant::now().as_ticks()));
+        settle().await;
+    }
+
+    /// Advance time to the next timer expiration, returning the instant advanced to or `None` if no timer is pending
+    pub async fn advance_to_next(&self) -> Option<Instant> {
+        settle().await;
+        let at = driver().next_expiration()?;
+        driver().advance(at.saturating_sub(Instant::now().as_ticks()));
+        settle().await;
+        Some(Instant::from_ticks(at))
+    }
+}
+
+impl Drop for PausedTime {
+    fn drop(&mut self) {
+        driver().resume();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::join::join;
+    use embassy_time::Timer;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn advance_wakes_timers_in_order() {
+        let time = pause();
+        let start = Instant::now();
+
+        let mut expirations = Vec::new();
+        join(
+            async {
+                for _ in 0..3 {
+                    Timer::after_secs(10).await;
+                    expirations.push(start.elapsed());
+                }
+            },
+            time.advance(Duration::from_secs(35)),
+        )
+        .await;
+
+        assert_eq!(
+            expirations,
+            [
+                Duration::from_secs(10),
+                Duration::from_secs(20),
+                Duration::from_secs(30)
+            ]
+        );
+        assert_eq!(start.elapsed(), Duration::from_secs(35));
+    }
+
+    #[tokio::test]
+    async fn advance_to_next_expiration() {
+        let time = pause();
+        let start = Instant::now();
+
+        let timer = Timer::after_millis(500);
+        let (_, next) = join(timer, time.advance_to_next()).await;
+        assert_eq!(next, Some(start + Duration::from_millis(500)));
+        assert_eq!(time.advance_to_next().await, None);
+    }
+}
@@ -153,6 +153,10 @@ impl<const SIZE: usize, F: BlockDevice<SIZE>, M: RawMutex> BlockDevice<SIZE> for
         block_address: u32,
         data: &[Aligned<Self::Align, [u8; SIZE]>],
     ) -> Result<(), Self::Error> {
+        if self.write_blocked() {
+            return Err(Error::Busy);
+        }
+
         self.check_access(block_address, data)?;
         let start_block = self.start_block(SIZE as u32).ok_or(Error::NotAligned)?;
 
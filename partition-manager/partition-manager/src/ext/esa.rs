@@ -13,6 +13,7 @@ impl<E: NorFlashError + Debug> NorFlashError for Error<E> {
             Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
             Error::NotAligned => NorFlashErrorKind::NotAligned,
             Error::ReadOnly => NorFlashErrorKind::Other, // Note: actually unreachable, only thrown by other impls.
+            Error::Busy => NorFlashErrorKind::Other,
             Error::Inner(e) => e.kind(),
         }
     }
@@ -93,6 +94,10 @@ impl<F: NorFlash, M: RawMutex> NorFlash for PartitionGuard<'_, F, RW, M> {
     const ERASE_SIZE: usize = F::ERASE_SIZE;
 
     async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if self.write_blocked() {
+            return Err(Error::Busy);
+        }
+
         if !self.within_bounds(from, to.checked_sub(from).ok_or(Error::OutOfBounds)? as usize) {
             return Err(Error::OutOfBounds);
         }
@@ -107,6 +112,10 @@ impl<F: NorFlash, M: RawMutex> NorFlash for PartitionGuard<'_, F, RW, M> {
     }
 
     async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        if self.write_blocked() {
+            return Err(Error::Busy);
+        }
+
         if !self.within_bounds(offset, bytes.len()) {
             return Err(Error::OutOfBounds);
         }
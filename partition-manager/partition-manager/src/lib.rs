@@ -4,15 +4,22 @@
 #[cfg(feature = "macros")]
 pub use partition_manager_macros as macros;
 
-use core::{fmt::Debug, marker::PhantomData};
+use core::{cell::Cell, fmt::Debug, marker::PhantomData};
 use embassy_sync::{
-    blocking_mutex::raw::{NoopRawMutex, RawMutex},
+    blocking_mutex::{
+        self,
+        raw::{NoopRawMutex, RawMutex},
+    },
     mutex::{Mutex, MutexGuard},
 };
 
 pub use embassy_sync::mutex::TryLockError;
 
 mod ext;
+#[cfg(feature = "nvram")]
+pub mod nvram;
+#[cfg(feature = "esa")]
+pub mod rmw;
 
 #[cfg(test)]
 mod test;
@@ -33,6 +40,7 @@ pub struct Partition<'a, F, MARKER, M: RawMutex = NoopRawMutex> {
     storage: &'a Mutex<M, F>,
     offset: u32,
     size: u32,
+    writer: blocking_mutex::Mutex<M, Cell<bool>>,
     _marker: PhantomData<MARKER>,
 }
 
@@ -42,16 +50,20 @@ impl<'a, F, MARKER, M: RawMutex> Partition<'a, F, MARKER, M> {
             storage,
             offset,
             size,
+            writer: blocking_mutex::Mutex::const_new(M::INIT, Cell::new(false)),
             _marker: PhantomData,
         }
     }
 
     /// Lock the underlying storage and return a guard that allows direct operations.
+    ///
+    /// Write and erase operations on the guard fail with [`Error::Busy`] while a [`WriterToken`] is held.
     pub async fn lock(&self) -> PartitionGuard<'_, F, MARKER, M> {
         PartitionGuard {
             guard: self.storage.lock().await,
             offset: self.offset,
             size: self.size,
+            writer: Some(&self.writer),
             _marker: PhantomData,
         }
     }
@@ -62,9 +74,57 @@ impl<'a, F, MARKER, M: RawMutex> Partition<'a, F, MARKER, M> {
             guard: self.storage.try_lock()?,
             offset: self.offset,
             size: self.size,
+            writer: Some(&self.writer),
             _marker: PhantomData,
         })
     }
+
+    /// Returns true if a [`WriterToken`] is currently held for this partition.
+    pub fn is_write_locked(&self) -> bool {
+        self.writer.lock(|writer| writer.get())
+    }
+}
+
+impl<'a, F, M: RawMutex> Partition<'a, F, RW, M> {
+    /// Attempt to acquire exclusive write access to this partition.
+    ///
+    /// While the returned token is alive, writes and erases through any other path fail with [`Error::Busy`],
+    /// reads are unaffected. Returns `None` if another token is already held.
+    pub fn try_acquire_writer(&self) -> Option<WriterToken<'_, 'a, F, M>> {
+        self.writer.lock(|writer| {
+            if writer.replace(true) {
+                None
+            } else {
+                Some(WriterToken { partition: self })
+            }
+        })
+    }
+}
+
+/// Exclusive write access to a partition.
+///
+/// Obtained via [`Partition::try_acquire_writer`], released on drop.
+pub struct WriterToken<'p, 'a, F, M: RawMutex = NoopRawMutex> {
+    partition: &'p Partition<'a, F, RW, M>,
+}
+
+impl<F, M: RawMutex> WriterToken<'_, '_, F, M> {
+    /// Lock the underlying storage and return a guard that is allowed to write to the partition.
+    pub async fn lock(&self) -> PartitionGuard<'_, F, RW, M> {
+        PartitionGuard {
+            guard: self.partition.storage.lock().await,
+            offset: self.partition.offset,
+            size: self.partition.size,
+            writer: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, M: RawMutex> Drop for WriterToken<'_, '_, F, M> {
+    fn drop(&mut self) {
+        self.partition.writer.lock(|writer| writer.set(false));
+    }
 }
 
 /// A guard that provides exclusive access to a partition's underlying storage.
@@ -76,6 +136,8 @@ pub struct PartitionGuard<'a, F, MARKER, M: RawMutex = NoopRawMutex> {
     guard: MutexGuard<'a, M, F>,
     offset: u32,
     size: u32,
+    /// Writer token state of the partition, `None` if this guard was obtained through a [`WriterToken`].
+    writer: Option<&'a blocking_mutex::Mutex<M, Cell<bool>>>,
     _marker: PhantomData<MARKER>,
 }
 
@@ -125,6 +187,12 @@ impl<F, MARKER, M: RawMutex> PartitionGuard<'_, F, MARKER, M> {
             false
         }
     }
+
+    /// Checks whether another holder of a [`WriterToken`] has exclusive write access.
+    #[cfg(any(feature = "esa", feature = "bdd"))]
+    fn write_blocked(&self) -> bool {
+        self.writer.is_some_and(|writer| writer.lock(|writer| writer.get()))
+    }
 }
 
 /// Marker type for read-only partitions.
@@ -143,6 +211,8 @@ pub enum Error<E> {
     NotAligned,
     /// Tried to perform an Write or Erase operation on a read-only partition.
     ReadOnly,
+    /// Tried to perform an Write or Erase operation while another user holds the [`WriterToken`].
+    Busy,
     /// Underlying device returned an error.
     Inner(E),
 }
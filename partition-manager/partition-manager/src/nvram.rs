@@ -0,0 +1,257 @@
+//! [`NvramStorage`] implementation backed by a flash partition.
+//!
+//! Platforms without battery-backed registers can use this to persist small values (e.g. timers, timezone) in
+//! flash. A [`FlashNvram`] holds up to `N` `u32` cells in RAM and hands out [`NvramCell`]s that implement
+//! [`NvramStorage`]. Writes update the RAM copy immediately and are persisted to flash by [`FlashNvram::run`]
+//! or an explicit call to [`FlashNvram::persist`].
+//!
+//! Every persist appends a record containing all cells to the next slot of the partition. Slots are used in
+//! rotation so each erase sector is only erased once per pass over the partition. On load the valid record with the
+//! highest sequence number wins. The partition should span at least two erase sectors, otherwise a power loss
+//! between the erase and the write of a new record loses the stored values.
+
+use core::cell::RefCell;
+
+use embassy_sync::{
+    blocking_mutex::{self, raw::RawMutex},
+    mutex::Mutex,
+    signal::Signal,
+};
+use embedded_mcu_hal::nvram::NvramStorage;
+use embedded_storage_async::nor_flash::NorFlash;
+
+use crate::Error;
+
+/// Largest supported slot size in bytes
+const MAX_SLOT_SIZE: usize = 256;
+/// Size of a single field in a record
+const FIELD_SIZE: usize = core::mem::size_of::<u32>();
+/// Value XOR'd into the record check to distinguish records from erased flash
+const CHECK_SEED: u32 = 0x4E56_524D;
+
+struct State<const N: usize> {
+    values: [u32; N],
+    /// Sequence number of the most recently persisted record
+    sequence: u32,
+    /// Slot of the most recently persisted record, `None` if nothing has been persisted yet
+    slot: Option<u32>,
+    dirty: bool,
+}
+
+/// Collection of `N` flash backed `u32` cells
+pub struct FlashNvram<F: NorFlash, M: RawMutex, const N: usize> {
+    flash: Mutex<M, F>,
+    state: blocking_mutex::Mutex<M, RefCell<State<N>>>,
+    dirty: Signal<M, ()>,
+    slot_size: u32,
+    slot_count: u32,
+}
+
+impl<F: NorFlash, M: RawMutex, const N: usize> FlashNvram<F, M, N> {
+    /// Create a new instance with all cells set to `default`
+    ///
+    /// Returns `None` if a record doesn't fit in [`MAX_SLOT_SIZE`] bytes or the partition can't hold at least one record.
+    pub fn new(flash: F, default: u32) -> Option<Self> {
+        let record_size = (N + 2) * FIELD_SIZE;
+        let slot_size = record_size.div_ceil(F::WRITE_SIZE) * F::WRITE_SIZE;
+        if slot_size > MAX_SLOT_SIZE || !F::ERASE_SIZE.is_multiple_of(slot_size) {
+            return None;
+        }
+
+        // Only use whole erase sectors
+        let sectors = flash.capacity() / F::ERASE_SIZE;
+        let slot_count = u32::try_from(sectors * (F::ERASE_SIZE / slot_size)).ok()?;
+        if slot_count == 0 {
+            return None;
+        }
+
+        Some(Self {
+            flash: Mutex::new(flash),
+            state: blocking_mutex::Mutex::new(RefCell::new(State {
+                values: [default; N],
+                sequence: 0,
+                slot: None,
+                dirty: false,
+            })),
+            dirty: Signal::new(),
+            slot_size: slot_size as u32,
+            slot_count,
+        })
+    }
+
+    /// Returns the cell at the given index, `None` if the index is out of range
+    pub fn cell(&self, index: usize) -> Option<NvramCell<'_, F, M, N>> {
+        (index < N).then_some(NvramCell { nvram: self, index })
+    }
+
+    /// Load the most recent record from flash
+    ///
+    /// Cells keep their default value if no valid record is found.
+    pub async fn load(&self) -> Result<(), Error<F::Error>> {
+        let mut flash = self.flash.lock().await;
+        let mut newest: Option<(u32, u32, [u32; N])> = None;
+
+        for slot in 0..self.slot_count {
+            let Some((sequence, values)) = self.read_record(&mut flash, slot).await? else {
+                continue;
+            };
+
+            if newest.is_none_or(|(newest_sequence, _, _)| sequence.wrapping_sub(newest_sequence) as i32 > 0) {
+                newest = Some((sequence, slot, values));
+            }
+        }
+
+        if let Some((sequence, slot, values)) = newest {
+            self.state.lock(|state| {
+                let mut state = state.borrow_mut();
+                state.values = values;
+                state.sequence = sequence;
+                state.slot = Some(slot);
+                state.dirty = false;
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Write the current values to flash if any cell has changed since the last persist
+    pub async fn persist(&self) -> Result<(), Error<F::Error>> {
+        let mut flash = self.flash.lock().await;
+        let Some((values, sequence, slot)) = self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if !state.dirty {
+                return None;
+            }
+
+            state.dirty = false;
+            let slot = state.slot.map_or(0, |slot| (slot + 1) % self.slot_count);
+            Some((state.values, state.sequence.wrapping_add(1), slot))
+        }) else {
+            return Ok(());
+        };
+
+        if let Err(e) = self.write_record(&mut flash, slot, sequence, values).await {
+            // Retry on the next persist
+            self.state.lock(|state| state.borrow_mut().dirty = true);
+            return Err(e);
+        }
+
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.sequence = sequence;
+            state.slot = Some(slot);
+        });
+        Ok(())
+    }
+
+    /// Persist changes as they are made
+    pub async fn run(&self) -> ! {
+        loop {
+            self.dirty.wait().await;
+            // On failure the values stay dirty and the next write triggers a retry
+            let _ = self.persist().await;
+        }
+    }
+
+    async fn write_record(
+        &self,
+        flash: &mut F,
+        slot: u32,
+        sequence: u32,
+        values: [u32; N],
+    ) -> Result<(), Error<F::Error>> {
+        let offset = slot * self.slot_size;
+        if offset.is_multiple_of(F::ERASE_SIZE as u32) {
+            // Entering a new sector, the previous sector still holds the latest record
+            flash
+                .erase(offset, offset + F::ERASE_SIZE as u32)
+                .await
+                .map_err(Error::Inner)?;
+        }
+
+        let mut buffer = [0xFFu8; MAX_SLOT_SIZE];
+        let record = buffer.get_mut(..self.slot_size as usize).ok_or(Error::OutOfBounds)?;
+        let mut fields = record.chunks_exact_mut(FIELD_SIZE);
+        let mut check = CHECK_SEED ^ sequence;
+        for (value, field) in core::iter::once(sequence).chain(values).zip(fields.by_ref()) {
+            field.copy_from_slice(&value.to_le_bytes());
+            check ^= value.rotate_left(7);
+        }
+        fields
+            .next()
+            .ok_or(Error::OutOfBounds)?
+            .copy_from_slice(&check.to_le_bytes());
+
+        flash.write(offset, record).await.map_err(Error::Inner)
+    }
+
+    async fn read_record(&self, flash: &mut F, slot: u32) -> Result<Option<(u32, [u32; N])>, Error<F::Error>> {
+        let read_size = self.slot_size.div_ceil(F::READ_SIZE as u32) * F::READ_SIZE as u32;
+        let mut buffer = [0u8; MAX_SLOT_SIZE];
+        let record = buffer.get_mut(..read_size as usize).ok_or(Error::OutOfBounds)?;
+        flash.read(slot * self.slot_size, record).await.map_err(Error::Inner)?;
+
+        let mut fields = record
+            .chunks_exact(FIELD_SIZE)
+            .map(|field| field.try_into().map(u32::from_le_bytes).unwrap_or(u32::MAX));
+
+        let Some(sequence) = fields.next() else {
+            return Ok(None);
+        };
+
+        let mut check = CHECK_SEED ^ sequence ^ sequence.rotate_left(7);
+        let mut values = [0u32; N];
+        for value in values.iter_mut() {
+            let Some(field) = fields.next() else {
+                return Ok(None);
+            };
+            *value = field;
+            check ^= field.rotate_left(7);
+        }
+
+        if fields.next() == Some(check) {
+            Ok(Some((sequence, values)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_value(&self, index: usize) -> u32 {
+        self.state
+            .lock(|state| state.borrow().values.get(index).copied().unwrap_or_default())
+    }
+
+    fn write_value(&self, index: usize, value: u32) {
+        let changed = self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            match state.values.get_mut(index) {
+                Some(current) if *current != value => {
+                    *current = value;
+                    state.dirty = true;
+                    true
+                }
+                _ => false,
+            }
+        });
+
+        if changed {
+            self.dirty.signal(());
+        }
+    }
+}
+
+/// A single `u32` cell in a [`FlashNvram`]
+pub struct NvramCell<'a, F: NorFlash, M: RawMutex, const N: usize> {
+    nvram: &'a FlashNvram<F, M, N>,
+    index: usize,
+}
+
+impl<'a, F: NorFlash, M: RawMutex, const N: usize> NvramStorage<'a, u32> for NvramCell<'a, F, M, N> {
+    fn read(&self) -> u32 {
+        self.nvram.read_value(self.index)
+    }
+
+    fn write(&mut self, value: u32) {
+        self.nvram.write_value(self.index, value);
+    }
+}
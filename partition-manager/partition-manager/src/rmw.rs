@@ -0,0 +1,160 @@
+//! Read-modify-write helper for writes smaller than the erase granularity.
+
+use embedded_storage_async::nor_flash::NorFlash;
+
+use crate::Error;
+
+/// Read-modify-write writer over a [`NorFlash`] device, typically a [`crate::Partition`].
+///
+/// Writes of any size and alignment are supported by reading the affected erase sector into a RAM buffer,
+/// patching it, then erasing and writing the sector back.
+///
+/// When caching is enabled, the most recently touched sector is kept in the buffer and only written back
+/// once a different sector is accessed or [`RmwWriter::flush`] is called. This reduces wear when updating
+/// several small records in the same sector, but requires the user to flush before the data is persisted.
+pub struct RmwWriter<'b, F: NorFlash> {
+    flash: F,
+    buffer: &'b mut [u8],
+    cache: bool,
+    /// Offset of the sector currently held in the buffer
+    sector: Option<u32>,
+    dirty: bool,
+}
+
+impl<'b, F: NorFlash> RmwWriter<'b, F> {
+    /// Create a new writer that writes every change back immediately.
+    ///
+    /// `buffer` must be at least `F::ERASE_SIZE` bytes, otherwise `None` is returned.
+    pub fn new(flash: F, buffer: &'b mut [u8]) -> Option<Self> {
+        Self::new_inner(flash, buffer, false)
+    }
+
+    /// Create a new writer that caches the most recently written sector in RAM.
+    ///
+    /// `buffer` must be at least `F::ERASE_SIZE` bytes, otherwise `None` is returned.
+    pub fn new_cached(flash: F, buffer: &'b mut [u8]) -> Option<Self> {
+        Self::new_inner(flash, buffer, true)
+    }
+
+    fn new_inner(flash: F, buffer: &'b mut [u8], cache: bool) -> Option<Self> {
+        let buffer = buffer.get_mut(..F::ERASE_SIZE)?;
+        Some(Self {
+            flash,
+            buffer,
+            cache,
+            sector: None,
+            dirty: false,
+        })
+    }
+
+    /// Returns the wrapped device, the caller must call [`RmwWriter::flush`] first to persist any cached data.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Returns true if there is cached data that hasn't been written back yet.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Read data at any offset and length, cached data that hasn't been written back is returned.
+    pub async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error<F::Error>> {
+        self.check_bounds(offset, bytes.len())?;
+
+        let mut offset = offset;
+        let mut remaining = bytes;
+        while !remaining.is_empty() {
+            let (sector, start) = self.split(offset);
+            let len = remaining.len().min(F::ERASE_SIZE - start);
+            let (chunk, rest) = remaining.split_at_mut(len);
+
+            self.load(sector).await?;
+            chunk.copy_from_slice(self.buffer.get(start..start + len).ok_or(Error::OutOfBounds)?);
+
+            offset = offset.checked_add(len as u32).ok_or(Error::OutOfBounds)?;
+            remaining = rest;
+        }
+
+        Ok(())
+    }
+
+    /// Write data at any offset and length.
+    pub async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error<F::Error>> {
+        self.check_bounds(offset, bytes.len())?;
+
+        let mut offset = offset;
+        let mut remaining = bytes;
+        while !remaining.is_empty() {
+            let (sector, start) = self.split(offset);
+            let len = remaining.len().min(F::ERASE_SIZE - start);
+            let (chunk, rest) = remaining.split_at(len);
+
+            self.load(sector).await?;
+            self.buffer
+                .get_mut(start..start + len)
+                .ok_or(Error::OutOfBounds)?
+                .copy_from_slice(chunk);
+            self.dirty = true;
+
+            if !self.cache {
+                self.flush().await?;
+            }
+
+            offset = offset.checked_add(len as u32).ok_or(Error::OutOfBounds)?;
+            remaining = rest;
+        }
+
+        Ok(())
+    }
+
+    /// Write back the cached sector if it has been modified.
+    pub async fn flush(&mut self) -> Result<(), Error<F::Error>> {
+        let Some(sector) = self.sector else {
+            return Ok(());
+        };
+
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let end = sector.checked_add(F::ERASE_SIZE as u32).ok_or(Error::OutOfBounds)?;
+        self.flash.erase(sector, end).await.map_err(Error::Inner)?;
+        self.flash.write(sector, self.buffer).await.map_err(Error::Inner)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Load the given sector into the buffer, writing back the current sector first if required.
+    async fn load(&mut self, sector: u32) -> Result<(), Error<F::Error>> {
+        // Without caching, always reload to pick up changes made through other paths
+        if self.cache && self.sector == Some(sector) {
+            return Ok(());
+        }
+
+        self.flush().await?;
+        // Invalidate first so a failed read doesn't leave stale data marked as valid
+        self.sector = None;
+        self.flash.read(sector, self.buffer).await.map_err(Error::Inner)?;
+        self.sector = Some(sector);
+        Ok(())
+    }
+
+    /// Split an offset into the sector offset and the offset within the sector.
+    fn split(&self, offset: u32) -> (u32, usize) {
+        let start = offset % F::ERASE_SIZE as u32;
+        (offset - start, start as usize)
+    }
+
+    fn check_bounds(&self, offset: u32, len: usize) -> Result<(), Error<F::Error>> {
+        let end = u32::try_from(len)
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .ok_or(Error::OutOfBounds)?;
+
+        if end as usize > self.flash.capacity() {
+            Err(Error::OutOfBounds)
+        } else {
+            Ok(())
+        }
+    }
+}
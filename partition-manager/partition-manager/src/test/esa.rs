@@ -127,3 +127,60 @@ fn esa() {
         disk.check();
     })
 }
+
+#[test]
+fn esa_writer_token() {
+    embassy_futures::block_on(async {
+        use std::collections::VecDeque;
+
+        let mut disk = MockDisk {
+            size: 0x4000,
+            actions: VecDeque::from([
+                ActionWrite {
+                    offset: 0x100,
+                    bytes: Vec::from([0xAA; 128]),
+                }
+                .into(),
+                ActionRead {
+                    offset: 0x100,
+                    bytes: Vec::from([0xAA; 8]),
+                }
+                .into(),
+                ActionWrite {
+                    offset: 0x180,
+                    bytes: Vec::from([0xBB; 128]),
+                }
+                .into(),
+            ]),
+        };
+
+        {
+            let mut pm: PartitionManager<_> = PartitionManager::new(&mut disk);
+            let TestMap { mut settings, .. } = pm.map(TestConfig);
+
+            use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
+
+            {
+                let token = settings.try_acquire_writer().unwrap();
+                assert!(settings.is_write_locked());
+                assert!(settings.try_acquire_writer().is_none());
+
+                // Token holder can write
+                token.lock().await.write(0x00, &[0xAA; 128]).await.unwrap();
+
+                // Other writers are rejected, reads proceed
+                let mut guard = settings.lock().await;
+                assert_eq!(guard.write(0x00, &[0xCC; 128]).await, Err(Error::Busy));
+                assert_eq!(guard.erase(0x00, 0x100).await, Err(Error::Busy));
+                let mut buf = [0u8; 8];
+                guard.read(0x00, &mut buf).await.unwrap();
+            }
+
+            // Token released on drop
+            assert!(!settings.is_write_locked());
+            settings.write(0x80, &[0xBB; 128]).await.unwrap();
+        }
+
+        disk.check();
+    })
+}
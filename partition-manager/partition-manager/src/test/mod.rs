@@ -9,8 +9,10 @@ mod bdd;
 mod esa;
 #[cfg(feature = "macros")]
 mod macros;
-
-use core::marker::PhantomData;
+#[cfg(feature = "nvram")]
+mod nvram;
+#[cfg(feature = "esa")]
+mod rmw;
 
 use crate::{Partition, PartitionConfig, PartitionMap, RW};
 use embassy_sync::blocking_mutex::raw::{NoopRawMutex, RawMutex};
@@ -39,30 +41,10 @@ impl PartitionConfig for TestConfig {
         storage: &embassy_sync::mutex::Mutex<M, F>,
     ) -> Self::Map<'_, F, M> {
         TestMap {
-            factory: Partition {
-                storage,
-                offset: 0x0000,
-                size: 0x0100,
-                _marker: PhantomData,
-            },
-            settings: Partition {
-                storage,
-                offset: 0x0100,
-                size: 0x0200,
-                _marker: PhantomData,
-            },
-            slot_a: Partition {
-                storage,
-                offset: 0x1000,
-                size: 0x1000,
-                _marker: PhantomData,
-            },
-            slot_b: Partition {
-                storage,
-                offset: 0x2000,
-                size: 0x1000,
-                _marker: PhantomData,
-            },
+            factory: Partition::new(storage, 0x0000, 0x0100),
+            settings: Partition::new(storage, 0x0100, 0x0200),
+            slot_a: Partition::new(storage, 0x1000, 0x1000),
+            slot_b: Partition::new(storage, 0x2000, 0x1000),
         }
     }
 }
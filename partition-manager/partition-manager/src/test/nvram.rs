@@ -0,0 +1,102 @@
+#![allow(clippy::indexing_slicing)]
+
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_mcu_hal::nvram::NvramStorage;
+use embedded_storage_async::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+
+use crate::nvram::FlashNvram;
+
+/// Simple RAM backed flash that enforces erase before write
+struct RamFlash {
+    data: [u8; 256],
+    writes: usize,
+}
+
+impl RamFlash {
+    fn new() -> Self {
+        Self {
+            data: [0xFF; 256],
+            writes: 0,
+        }
+    }
+}
+
+impl ErrorType for &mut RamFlash {
+    type Error = core::convert::Infallible;
+}
+
+impl ReadNorFlash for &mut RamFlash {
+    const READ_SIZE: usize = 4;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for &mut RamFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = 128;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.data[from as usize..to as usize].fill(0xFF);
+        Ok(())
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        let target = &mut self.data[offset..offset + bytes.len()];
+        assert!(target.iter().all(|b| *b == 0xFF), "write to non-erased flash");
+        target.copy_from_slice(bytes);
+        self.writes += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn nvram_persist_and_load() {
+    embassy_futures::block_on(async {
+        let mut flash = RamFlash::new();
+
+        {
+            let nvram: FlashNvram<_, NoopRawMutex, 2> = FlashNvram::new(&mut flash, u32::MAX).unwrap();
+            nvram.load().await.unwrap();
+
+            let mut cell0 = nvram.cell(0).unwrap();
+            let cell1 = nvram.cell(1).unwrap();
+            assert!(nvram.cell(2).is_none());
+            assert_eq!(cell0.read(), u32::MAX);
+            assert_eq!(cell1.read(), u32::MAX);
+
+            // Each persist lands in a new slot, wrapping around both sectors
+            for i in 0..20 {
+                cell0.write(i);
+                nvram.persist().await.unwrap();
+            }
+
+            // Nothing changed, nothing written
+            cell0.write(19);
+            nvram.persist().await.unwrap();
+        }
+
+        assert_eq!(flash.writes, 20);
+
+        {
+            let nvram: FlashNvram<_, NoopRawMutex, 2> = FlashNvram::new(&mut flash, u32::MAX).unwrap();
+            nvram.load().await.unwrap();
+            assert_eq!(nvram.cell(0).unwrap().read(), 19);
+            assert_eq!(nvram.cell(1).unwrap().read(), u32::MAX);
+        }
+    })
+}
+
+#[test]
+fn nvram_too_small() {
+    let mut flash = RamFlash::new();
+    assert!(FlashNvram::<_, NoopRawMutex, 64>::new(&mut flash, 0).is_none());
+}
@@ -0,0 +1,160 @@
+use crate::{
+    PartitionManager,
+    rmw::RmwWriter,
+    test::{
+        TestConfig, TestMap,
+        mock::{ActionErase, ActionRead, ActionWrite, MockDisk},
+    },
+};
+
+/// Build the expected contents of a 128 byte sector filled with `base` and the given patches applied
+fn patched(base: u8, patches: &[(usize, &[u8])]) -> Vec<u8> {
+    (0..128)
+        .map(|i| {
+            patches
+                .iter()
+                .find_map(|(offset, bytes)| i.checked_sub(*offset).and_then(|i| bytes.get(i)))
+                .copied()
+                .unwrap_or(base)
+        })
+        .collect()
+}
+
+#[test]
+fn rmw_uncached() {
+    embassy_futures::block_on(async {
+        use std::collections::VecDeque;
+
+        let mut disk = MockDisk {
+            size: 0x4000,
+            actions: VecDeque::from([
+                ActionRead {
+                    offset: 0x100,
+                    bytes: Vec::from([0xFF; 128]),
+                }
+                .into(),
+                ActionErase {
+                    offset: 0x100,
+                    len: 128,
+                }
+                .into(),
+                ActionWrite {
+                    offset: 0x100,
+                    bytes: patched(0xFF, &[(4, &[1, 2, 3][..])]),
+                }
+                .into(),
+                // Write spanning two sectors
+                ActionRead {
+                    offset: 0x100,
+                    bytes: patched(0xFF, &[(4, &[1, 2, 3][..])]),
+                }
+                .into(),
+                ActionErase {
+                    offset: 0x100,
+                    len: 128,
+                }
+                .into(),
+                ActionWrite {
+                    offset: 0x100,
+                    bytes: patched(0xFF, &[(4, &[1, 2, 3][..]), (127, &[4][..])]),
+                }
+                .into(),
+                ActionRead {
+                    offset: 0x180,
+                    bytes: Vec::from([0xFF; 128]),
+                }
+                .into(),
+                ActionErase {
+                    offset: 0x180,
+                    len: 128,
+                }
+                .into(),
+                ActionWrite {
+                    offset: 0x180,
+                    bytes: patched(0xFF, &[(0, &[5][..])]),
+                }
+                .into(),
+            ]),
+        };
+
+        {
+            let mut pm: PartitionManager<_> = PartitionManager::new(&mut disk);
+            let TestMap { settings, .. } = pm.map(TestConfig);
+
+            let mut buffer = [0u8; 128];
+            let mut writer = RmwWriter::new(settings, &mut buffer).unwrap();
+            writer.write(4, &[1, 2, 3][..]).await.unwrap();
+            assert!(!writer.is_dirty());
+            writer.write(0x7F, &[4, 5]).await.unwrap();
+        }
+
+        disk.check();
+    })
+}
+
+#[test]
+fn rmw_cached() {
+    embassy_futures::block_on(async {
+        use std::collections::VecDeque;
+
+        let mut disk = MockDisk {
+            size: 0x4000,
+            actions: VecDeque::from([
+                ActionRead {
+                    offset: 0x100,
+                    bytes: Vec::from([0x00; 128]),
+                }
+                .into(),
+                ActionErase {
+                    offset: 0x100,
+                    len: 128,
+                }
+                .into(),
+                ActionWrite {
+                    offset: 0x100,
+                    bytes: patched(0x00, &[(8, &[1, 2, 3, 4][..])]),
+                }
+                .into(),
+            ]),
+        };
+
+        {
+            let mut pm: PartitionManager<_> = PartitionManager::new(&mut disk);
+            let TestMap { settings, .. } = pm.map(TestConfig);
+
+            let mut buffer = [0u8; 256];
+            let mut writer = RmwWriter::new_cached(settings, &mut buffer).unwrap();
+            writer.write(8, &[1, 2][..]).await.unwrap();
+            writer.write(10, &[3, 4][..]).await.unwrap();
+            assert!(writer.is_dirty());
+
+            // Reads are served from the cache
+            let mut buf = [0u8; 4];
+            writer.read(8, &mut buf).await.unwrap();
+            assert_eq!(buf, [1, 2, 3, 4]);
+
+            writer.flush().await.unwrap();
+            assert!(!writer.is_dirty());
+        }
+
+        disk.check();
+    })
+}
+
+#[test]
+fn rmw_buffer_too_small() {
+    let mut disk = MockDisk {
+        size: 0x4000,
+        actions: Default::default(),
+    };
+
+    {
+        let mut pm: PartitionManager<_> = PartitionManager::new(&mut disk);
+        let TestMap { settings, .. } = pm.map(TestConfig);
+
+        let mut buffer = [0u8; 64];
+        assert!(RmwWriter::new(settings, &mut buffer).is_none());
+    }
+
+    disk.check();
+}
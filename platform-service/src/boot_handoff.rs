@@ -0,0 +1,373 @@
+//! Bootloader handoff info block
+//!
+//! The EC application and the bootloader exchange a small [`HandoffInfo`] block through a dedicated flash partition,
+//! typically a read/write partition from partition-manager. The bootloader records why it booted the application and
+//! the application requests updates, trial boots of the other image slot and tracks consecutive crashes so the
+//! bootloader can roll back to a known good image.
+//!
+//! The partition must span at least two erase sectors. Every update writes a new record to the sector that doesn't
+//! hold the current record, so a power loss during an update leaves the previous record intact. On read the valid
+//! record with the highest sequence number wins. Both sides of the handoff must use the record layout below:
+//!
+//! | Offset | Size | Field                            |
+//! |--------|------|----------------------------------|
+//! | 0      | 4    | Magic [`MAGIC`]                  |
+//! | 4      | 4    | Sequence number                  |
+//! | 8      | 1    | Layout version [`VERSION`]       |
+//! | 9      | 1    | [`BootReason`]                   |
+//! | 10     | 1    | [`UpdateFlags`]                  |
+//! | 11     | 1    | Crash counter                    |
+//! | 12     | 4    | CRC-32 (ISO-HDLC) of bytes 0..12 |
+//!
+//! All fields are little endian.
+
+use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
+use embedded_storage_async::nor_flash::NorFlash;
+
+/// Magic value identifying a handoff record
+pub const MAGIC: u32 = 0x4846_4F42;
+/// Version of the record layout
+pub const VERSION: u8 = 1;
+/// Size of a serialized record in bytes
+pub const RECORD_SIZE: usize = 16;
+/// Largest supported flash write or read granularity
+const MAX_RECORD_SIZE: usize = 64;
+/// Offset of the CRC in a record
+const CRC_OFFSET: usize = 12;
+/// Number of erase sectors used for records
+const SECTOR_COUNT: u32 = 2;
+
+const CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// Handoff error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// Flash access failed
+    Flash(E),
+}
+
+/// Reason for the most recent boot of the application, recorded by the bootloader
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum BootReason {
+    /// Unknown or not recorded
+    #[default]
+    Unknown = 0,
+    /// Cold boot
+    PowerOn = 1,
+    /// Reset requested by the application
+    Software = 2,
+    /// Watchdog reset
+    Watchdog = 3,
+    /// Fault or panic in the application
+    Crash = 4,
+    /// First boot of a newly installed image
+    UpdateApplied = 5,
+    /// The bootloader rolled back to the previous image
+    Rollback = 6,
+}
+
+impl From<u8> for BootReason {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::PowerOn,
+            2 => Self::Software,
+            3 => Self::Watchdog,
+            4 => Self::Crash,
+            5 => Self::UpdateApplied,
+            6 => Self::Rollback,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Update state shared between the application and the bootloader
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+    pub struct UpdateFlags: u8 {
+        /// An image has been staged and should be installed on the next boot
+        const UPDATE_PENDING = 1 << 0;
+        /// The update targets image slot B, slot A otherwise
+        const TARGET_SLOT_B = 1 << 1;
+        /// The running image is on trial and must be confirmed by the application
+        const TRIAL_BOOT = 1 << 2;
+        /// The application requests a rollback to the previous image
+        const ROLLBACK_REQUESTED = 1 << 3;
+        // Preserve flags defined by newer bootloaders
+        const _ = !0;
+    }
+}
+
+/// Information shared between the application and the bootloader
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HandoffInfo {
+    /// Reason for the most recent boot
+    pub boot_reason: BootReason,
+    /// Update state
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    pub update_flags: UpdateFlags,
+    /// Number of consecutive boots that ended in a crash
+    pub crash_count: u8,
+}
+
+impl HandoffInfo {
+    /// Record a crash of the running image
+    pub fn record_crash(&mut self) {
+        self.crash_count = self.crash_count.saturating_add(1);
+    }
+
+    /// Mark the running image as good, clearing the trial state and crash counter
+    pub fn confirm_boot(&mut self) {
+        self.update_flags.remove(UpdateFlags::TRIAL_BOOT);
+        self.crash_count = 0;
+    }
+
+    /// Returns true if an update is staged for the next boot
+    pub fn update_pending(&self) -> bool {
+        self.update_flags.contains(UpdateFlags::UPDATE_PENDING)
+    }
+
+    fn encode(&self, sequence: u32) -> [u8; RECORD_SIZE] {
+        let mut record = [0u8; RECORD_SIZE];
+        let (body, crc) = record.split_at_mut(CRC_OFFSET);
+        let (magic, rest) = body.split_at_mut(4);
+        let (seq, fields) = rest.split_at_mut(4);
+        magic.copy_from_slice(&MAGIC.to_le_bytes());
+        seq.copy_from_slice(&sequence.to_le_bytes());
+        fields.copy_from_slice(&[
+            VERSION,
+            self.boot_reason as u8,
+            self.update_flags.bits(),
+            self.crash_count,
+        ]);
+        crc.copy_from_slice(&CRC.checksum(body).to_le_bytes());
+        record
+    }
+
+    /// Decode a record, returns the sequence number and info or `None` if the record isn't valid
+    fn decode(record: &[u8; RECORD_SIZE]) -> Option<(u32, Self)> {
+        let (body, crc) = record.split_at(CRC_OFFSET);
+        if u32::from_le_bytes(crc.try_into().ok()?) != CRC.checksum(body) {
+            return None;
+        }
+
+        let (header, fields) = body.split_at(8);
+        let (magic, sequence) = header.split_at(4);
+        let [version, boot_reason, update_flags, crash_count] = <[u8; 4]>::try_from(fields).ok()?;
+        if u32::from_le_bytes(magic.try_into().ok()?) != MAGIC || version != VERSION {
+            return None;
+        }
+
+        Some((
+            u32::from_le_bytes(sequence.try_into().ok()?),
+            Self {
+                boot_reason: boot_reason.into(),
+                update_flags: UpdateFlags::from_bits_retain(update_flags),
+                crash_count,
+            },
+        ))
+    }
+}
+
+/// Flash backed handoff block
+pub struct BootHandoff<F: NorFlash, M: RawMutex> {
+    flash: Mutex<M, F>,
+}
+
+impl<F: NorFlash, M: RawMutex> BootHandoff<F, M> {
+    /// Create a new instance
+    ///
+    /// Returns `None` if the partition doesn't span two erase sectors or the flash granularity isn't supported.
+    pub fn new(flash: F) -> Option<Self> {
+        let write_size = RECORD_SIZE.div_ceil(F::WRITE_SIZE) * F::WRITE_SIZE;
+        let read_size = RECORD_SIZE.div_ceil(F::READ_SIZE) * F::READ_SIZE;
+        if write_size.max(read_size) > MAX_RECORD_SIZE
+            || F::ERASE_SIZE < write_size
+            || flash.capacity() < SECTOR_COUNT as usize * F::ERASE_SIZE
+        {
+            return None;
+        }
+
+        Some(Self {
+            flash: Mutex::new(flash),
+        })
+    }
+
+    /// Read the current handoff info
+    ///
+    /// Returns the default info if no valid record exists.
+    pub async fn read(&self) -> Result<HandoffInfo, Error<F::Error>> {
+        let mut flash = self.flash.lock().await;
+        Ok(Self::load(&mut flash)
+            .await?
+            .map(|(_, _, info)| info)
+            .unwrap_or_default())
+    }
+
+    /// Atomically update the handoff info
+    ///
+    /// `f` is applied to the current info and the result is written to flash if it changed.
+    /// Returns the updated info.
+    pub async fn update(&self, f: impl FnOnce(&mut HandoffInfo)) -> Result<HandoffInfo, Error<F::Error>> {
+        let mut flash = self.flash.lock().await;
+        let current = Self::load(&mut flash).await?;
+        let mut info = current.map(|(_, _, info)| info).unwrap_or_default();
+        let previous = info;
+        f(&mut info);
+
+        if current.is_some() && info == previous {
+            return Ok(info);
+        }
+
+        // Write to the sector that doesn't hold the current record
+        let (sequence, sector) = current.map_or((0, 0), |(sequence, sector, _)| {
+            (sequence.wrapping_add(1), (sector + 1) % SECTOR_COUNT)
+        });
+        let offset = sector * F::ERASE_SIZE as u32;
+        flash
+            .erase(offset, offset + F::ERASE_SIZE as u32)
+            .await
+            .map_err(Error::Flash)?;
+
+        let write_size = RECORD_SIZE.div_ceil(F::WRITE_SIZE) * F::WRITE_SIZE;
+        let mut buffer = [0xFFu8; MAX_RECORD_SIZE];
+        let (record, _) = buffer.split_at_mut(RECORD_SIZE);
+        record.copy_from_slice(&info.encode(sequence));
+        let (record, _) = buffer.split_at(write_size);
+        flash.write(offset, record).await.map_err(Error::Flash)?;
+
+        Ok(info)
+    }
+
+    /// Confirm the running image, see [`HandoffInfo::confirm_boot`]
+    pub async fn confirm_boot(&self) -> Result<HandoffInfo, Error<F::Error>> {
+        self.update(HandoffInfo::confirm_boot).await
+    }
+
+    /// Load the newest valid record, returns its sequence number, sector and info
+    async fn load(flash: &mut F) -> Result<Option<(u32, u32, HandoffInfo)>, Error<F::Error>> {
+        let read_size = RECORD_SIZE.div_ceil(F::READ_SIZE) * F::READ_SIZE;
+        let mut newest: Option<(u32, u32, HandoffInfo)> = None;
+
+        for sector in 0..SECTOR_COUNT {
+            let mut buffer = [0u8; MAX_RECORD_SIZE];
+            let (record, _) = buffer.split_at_mut(read_size);
+            flash
+                .read(sector * F::ERASE_SIZE as u32, record)
+                .await
+                .map_err(Error::Flash)?;
+
+            let Some((sequence, info)) = <[u8; RECORD_SIZE]>::try_from(buffer.split_at(RECORD_SIZE).0)
+                .ok()
+                .and_then(|record| HandoffInfo::decode(&record))
+            else {
+                continue;
+            };
+
+            if newest.is_none_or(|(newest_sequence, _, _)| sequence.wrapping_sub(newest_sequence) as i32 > 0) {
+                newest = Some((sequence, sector, info));
+            }
+        }
+
+        Ok(newest)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+#[allow(clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embedded_storage_async::nor_flash::{ErrorType, ReadNorFlash};
+
+    /// Simple RAM backed flash with two 64 byte sectors
+    struct RamFlash {
+        data: [u8; 128],
+    }
+
+    impl ErrorType for &mut RamFlash {
+        type Error = core::convert::Infallible;
+    }
+
+    impl ReadNorFlash for &mut RamFlash {
+        const READ_SIZE: usize = 4;
+
+        async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl NorFlash for &mut RamFlash {
+        const WRITE_SIZE: usize = 8;
+        const ERASE_SIZE: usize = 64;
+
+        async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.data[from as usize..to as usize].fill(0xFF);
+            Ok(())
+        }
+
+        async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            let target = &mut self.data[offset..offset + bytes.len()];
+            assert!(target.iter().all(|b| *b == 0xFF), "write to non-erased flash");
+            target.copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let info = HandoffInfo {
+            boot_reason: BootReason::Watchdog,
+            update_flags: UpdateFlags::UPDATE_PENDING | UpdateFlags::TARGET_SLOT_B,
+            crash_count: 3,
+        };
+
+        let mut record = info.encode(7);
+        assert_eq!(HandoffInfo::decode(&record), Some((7, info)));
+
+        // Any corruption invalidates the record
+        record[11] ^= 1;
+        assert_eq!(HandoffInfo::decode(&record), None);
+        assert_eq!(HandoffInfo::decode(&[0xFF; RECORD_SIZE]), None);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_recover() {
+        let mut flash = RamFlash { data: [0xFF; 128] };
+
+        {
+            let handoff: BootHandoff<_, NoopRawMutex> = BootHandoff::new(&mut flash).unwrap();
+            assert_eq!(handoff.read().await.unwrap(), HandoffInfo::default());
+
+            handoff
+                .update(|info| {
+                    info.update_flags |= UpdateFlags::UPDATE_PENDING | UpdateFlags::TRIAL_BOOT;
+                    info.record_crash();
+                })
+                .await
+                .unwrap();
+            let info = handoff.confirm_boot().await.unwrap();
+            assert!(info.update_pending());
+            assert_eq!(info.crash_count, 0);
+            assert_eq!(handoff.read().await.unwrap(), info);
+        }
+
+        // Records alternate between sectors, a torn write falls back to the previous record
+        flash.data[64 + 10] = 0;
+        let handoff: BootHandoff<_, NoopRawMutex> = BootHandoff::new(&mut flash).unwrap();
+        let info = handoff.read().await.unwrap();
+        assert_eq!(info.crash_count, 1);
+        assert!(info.update_flags.contains(UpdateFlags::TRIAL_BOOT));
+    }
+}
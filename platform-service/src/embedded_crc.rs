@@ -1,4 +1,16 @@
 use crc::Algorithm;
+use embassy_futures::yield_now;
+use embedded_services::GlobalRawMutex;
+use embedded_services::ipc::deferred;
+
+/// Default number of bytes processed per call into the CRC engine by the streaming API.
+///
+/// Hardware engines are shared and locked for the duration of a calculation, bounding the
+/// chunk size keeps other users from being blocked while large images are checksummed.
+pub const DEFAULT_CHUNK_SIZE: usize = 512;
+
+/// Maximum number of buffers in a single [`CrcQueue`] calculation
+pub const MAX_QUEUED_BUFFERS: usize = 8;
 
 pub struct EmbeddedCrc<W: crc::Width> {
     algorithm: &'static Algorithm<W>,
@@ -14,6 +26,7 @@ pub enum EmbeddedCrcError {
     CrcErrorPolynomial,
     CrcErrorXorOut,
     CrcErrorMutexGet,
+    CrcErrorBatchSize,
 }
 
 impl EmbeddedCrc<u32> {
@@ -51,6 +64,39 @@ impl EmbeddedCrc<u32> {
         self.current_crc.unwrap_or(self.algorithm.init)
     }
 
+    /// Feed data in chunks of at most `chunk_size` bytes, yielding to other tasks between chunks.
+    ///
+    /// Can be called repeatedly to stream data, use [`Self::finalize`] to get the result.
+    pub async fn feed(&mut self, bytes: &[u8], chunk_size: usize) -> Result<u32, EmbeddedCrcError> {
+        for chunk in bytes.chunks(chunk_size.max(1)) {
+            self.calculate(chunk).await?;
+            yield_now().await;
+        }
+
+        Ok(self.read_crc())
+    }
+
+    /// Feed a batch of non-contiguous buffers, equivalent to feeding them one after another.
+    pub async fn feed_batch(&mut self, buffers: &[&[u8]], chunk_size: usize) -> Result<u32, EmbeddedCrcError> {
+        for buffer in buffers {
+            self.feed(buffer, chunk_size).await?;
+        }
+
+        Ok(self.read_crc())
+    }
+
+    /// Return the CRC of all data fed so far and reset for a new calculation.
+    pub fn finalize(&mut self) -> u32 {
+        let crc = self.read_crc();
+        self.reset();
+        crc
+    }
+
+    /// Discard any data fed so far.
+    pub fn reset(&mut self) {
+        self.current_crc = None;
+    }
+
     // Reverses the digest finalize operation to use as another CRC input
     fn un_finalize(&self, crc: u32) -> u32 {
         let mut out: u32 = crc ^ self.algorithm.xorout;
@@ -101,6 +147,39 @@ impl EmbeddedCrc<u16> {
         self.current_crc.unwrap_or(self.algorithm.init)
     }
 
+    /// Feed data in chunks of at most `chunk_size` bytes, yielding to other tasks between chunks.
+    ///
+    /// Can be called repeatedly to stream data, use [`Self::finalize`] to get the result.
+    pub async fn feed(&mut self, bytes: &[u8], chunk_size: usize) -> Result<u16, EmbeddedCrcError> {
+        for chunk in bytes.chunks(chunk_size.max(1)) {
+            self.calculate(chunk).await?;
+            yield_now().await;
+        }
+
+        Ok(self.read_crc())
+    }
+
+    /// Feed a batch of non-contiguous buffers, equivalent to feeding them one after another.
+    pub async fn feed_batch(&mut self, buffers: &[&[u8]], chunk_size: usize) -> Result<u16, EmbeddedCrcError> {
+        for buffer in buffers {
+            self.feed(buffer, chunk_size).await?;
+        }
+
+        Ok(self.read_crc())
+    }
+
+    /// Return the CRC of all data fed so far and reset for a new calculation.
+    pub fn finalize(&mut self) -> u16 {
+        let crc = self.read_crc();
+        self.reset();
+        crc
+    }
+
+    /// Discard any data fed so far.
+    pub fn reset(&mut self) {
+        self.current_crc = None;
+    }
+
     // Reverses the digest finalize operation to use as another CRC input
     fn un_finalize(&self, crc: u16) -> u16 {
         let mut out: u16 = crc ^ self.algorithm.xorout;
@@ -115,3 +194,135 @@ impl EmbeddedCrc<u16> {
         out
     }
 }
+
+/// CRC calculation queued on a [`CrcQueue`]
+pub struct CrcJob<'a> {
+    algorithm: &'static Algorithm<u32>,
+    buffers: heapless::Vec<&'a [u8], MAX_QUEUED_BUFFERS>,
+}
+
+/// Queue of CRC calculations processed by a single task
+///
+/// Calculations are processed one at a time in [`Self::run`], so large buffers are checksummed in the background
+/// without each caller holding the CRC engine. Callers wait for their result in the order they were queued.
+/// 16-bit algorithms can be used through their `Algorithm<u32>` form.
+pub struct CrcQueue<'a> {
+    channel: deferred::Channel<GlobalRawMutex, CrcJob<'a>, Result<u32, EmbeddedCrcError>>,
+    chunk_size: usize,
+}
+
+impl<'a> CrcQueue<'a> {
+    /// Create a new queue, feeding the CRC engine at most `chunk_size` bytes at a time
+    pub const fn new(chunk_size: usize) -> Self {
+        Self {
+            channel: deferred::Channel::new(),
+            chunk_size,
+        }
+    }
+
+    /// Queue the CRC calculation of `buffers`, fed one after another, and wait for the result
+    ///
+    /// Only the data must outlive the queue, the list of buffers is copied into the job. Fails with
+    /// [`EmbeddedCrcError::CrcErrorBatchSize`] if there are more than [`MAX_QUEUED_BUFFERS`] buffers.
+    pub async fn calculate(
+        &self,
+        algorithm: &'static Algorithm<u32>,
+        buffers: &[&'a [u8]],
+    ) -> Result<u32, EmbeddedCrcError> {
+        let buffers = heapless::Vec::from_slice(buffers).map_err(|_| EmbeddedCrcError::CrcErrorBatchSize)?;
+        self.channel.execute(CrcJob { algorithm, buffers }).await
+    }
+
+    /// Process queued calculations
+    pub async fn run(&self) -> ! {
+        loop {
+            let request = self.channel.receive().await;
+            let mut crc = EmbeddedCrc::<u32>::new(request.command.algorithm);
+            let result = crc.feed_batch(&request.command.buffers, self.chunk_size).await;
+            request.respond(result);
+        }
+    }
+}
+
+impl Default for CrcQueue<'_> {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+#[cfg(all(test, not(any(feature = "imxrt", feature = "imxrt685"))))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use embassy_futures::join::join;
+    use embassy_futures::select::{Either, select};
+
+    const DATA: &[u8] = b"123456789abcdefghijklmnopqrstuvwxyz";
+
+    #[test]
+    fn test_feed_matches_single_calculation() {
+        embassy_futures::block_on(async {
+            let mut single = EmbeddedCrc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+            let expected = single.calculate(DATA).await.unwrap();
+
+            let mut streamed = EmbeddedCrc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+            assert_eq!(streamed.feed(DATA, 4).await.unwrap(), expected);
+            assert_eq!(streamed.finalize(), expected);
+
+            // Finalize resets the calculation
+            let (first, second) = DATA.split_at(10);
+            streamed.feed_batch(&[first, second], 3).await.unwrap();
+            assert_eq!(streamed.finalize(), expected);
+        });
+    }
+
+    #[test]
+    fn test_feed_u16() {
+        embassy_futures::block_on(async {
+            let mut single = EmbeddedCrc::<u16>::new(&crc::CRC_16_IBM_3740);
+            let expected = single.calculate(DATA).await.unwrap();
+
+            let mut streamed = EmbeddedCrc::<u16>::new(&crc::CRC_16_IBM_3740);
+            streamed.feed(DATA, DEFAULT_CHUNK_SIZE).await.unwrap();
+            assert_eq!(streamed.finalize(), expected);
+        });
+    }
+
+    #[test]
+    fn test_queue() {
+        embassy_futures::block_on(async {
+            let mut single = EmbeddedCrc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+            let expected = single.calculate(DATA).await.unwrap();
+            let (first, second) = DATA.split_at(10);
+
+            let queue = CrcQueue::default();
+            // The buffer lists are declared after the queue, only the data has to outlive it
+            let split = [first, second];
+            let whole = [DATA];
+            let calculations = join(
+                queue.calculate(&crc::CRC_32_ISO_HDLC, &split),
+                queue.calculate(&crc::CRC_32_ISO_HDLC, &whole),
+            );
+            match select(queue.run(), calculations).await {
+                Either::First(never) => never,
+                Either::Second((first, second)) => {
+                    assert_eq!(first.unwrap(), expected);
+                    assert_eq!(second.unwrap(), expected);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_queue_batch_size() {
+        embassy_futures::block_on(async {
+            let queue = CrcQueue::default();
+            let buffers = [DATA; MAX_QUEUED_BUFFERS + 1];
+            // Rejected before being queued, so no runner is needed
+            assert!(matches!(
+                queue.calculate(&crc::CRC_32_ISO_HDLC, &buffers).await,
+                Err(EmbeddedCrcError::CrcErrorBatchSize)
+            ));
+        });
+    }
+}
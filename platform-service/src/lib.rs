@@ -1,11 +1,24 @@
 #![no_std]
 
+// Host tests use the embassy-time driver from the test support crate
+#[cfg(test)]
+use odp_test_support as _;
+
+/// Bootloader handoff info block
+pub mod boot_handoff;
+
 /// CRC service abstraction
 pub mod embedded_crc;
 
+/// Secure boot measurement reporting
+pub mod measurement;
+
 /// Initiate a delayed MCU Reset
 pub mod reset;
 
+/// System commands relayed from the host
+pub mod relay;
+
 #[cfg(any(feature = "imxrt", feature = "imxrt685"))]
 pub mod imxrt;
 
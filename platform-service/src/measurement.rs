@@ -0,0 +1,411 @@
+//! Secure boot measurement reporting
+//!
+//! Boot stages record measurements (image digests, keystore/ROM derived security state) into a [`MeasurementLog`]
+//! while the platform boots. Once boot completes the log is sealed and no further measurements are accepted.
+//! The security state is summarized in [`SecureState`], which is published to the host through the capabilities
+//! `secure_state` field ([`embedded_services::caps`]). Hosts can request an attestation through the system relay,
+//! which returns a blob containing every measurement, the security state and a host supplied nonce, signed with a
+//! platform key through the [`Signer`] trait. [`PlatformAttester`] pairs the log with that key.
+//!
+//! Digests are computed by the platform, e.g. using the hashing engine of the MCU, this module only stores and
+//! reports them.
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_services::GlobalRawMutex;
+
+/// Size of a measurement digest in bytes (SHA-256)
+pub const DIGEST_SIZE: usize = 32;
+/// Size of the host supplied attestation nonce in bytes
+pub const NONCE_SIZE: usize = 32;
+/// Version of the attestation blob format
+pub const BLOB_VERSION: u8 = 1;
+/// Size of the attestation blob header in bytes
+pub const HEADER_SIZE: usize = 8 + NONCE_SIZE;
+/// Size of a single serialized measurement in bytes
+pub const ENTRY_SIZE: usize = 4 + DIGEST_SIZE;
+
+/// Measurement digest
+pub type Digest = [u8; DIGEST_SIZE];
+
+/// Measurement error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The log is full
+    LogFull,
+    /// The log has been sealed, no more measurements can be recorded
+    Sealed,
+    /// The log hasn't been sealed yet, measurements can't be attested
+    NotSealed,
+    /// The output buffer is too small
+    BufferTooSmall,
+    /// The platform signer failed
+    Signer,
+}
+
+/// Measured component
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Component {
+    /// ROM bootloader or its configuration
+    Rom = 0,
+    /// Second stage bootloader
+    Bootloader = 1,
+    /// EC application image
+    Application = 2,
+    /// Keystore region
+    Keystore = 3,
+    /// Platform configuration data
+    Configuration = 4,
+    /// Platform specific component
+    Other = 0xFF,
+}
+
+/// A single boot measurement
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Measurement {
+    /// Measured component
+    pub component: Component,
+    /// Instance of the component, e.g. image slot
+    pub index: u8,
+    /// Security version of the component
+    pub version: u16,
+    /// Digest of the component
+    pub digest: Digest,
+}
+
+impl Measurement {
+    fn serialize(&self, out: &mut [u8]) -> Result<(), Error> {
+        let (header, digest) = out.get_mut(..ENTRY_SIZE).ok_or(Error::BufferTooSmall)?.split_at_mut(4);
+        let [version_lo, version_hi] = self.version.to_le_bytes();
+        header.copy_from_slice(&[self.component as u8, self.index, version_lo, version_hi]);
+        digest.copy_from_slice(&self.digest);
+        Ok(())
+    }
+}
+
+/// Platform security state
+///
+/// Bit layout as reported through the capabilities `secure_state` field:
+/// - bit 0: secure boot enforced
+/// - bit 1: debug port locked
+/// - bit 2: keystore provisioned
+/// - bit 3: measurement log sealed
+/// - bits 16-31: anti-rollback version
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SecureState {
+    /// Secure boot is enforced by the ROM
+    pub secure_boot: bool,
+    /// Debug access is locked
+    pub debug_locked: bool,
+    /// The keystore contains a valid key set
+    pub keystore_provisioned: bool,
+    /// Anti-rollback version
+    pub rollback_version: u16,
+}
+
+impl SecureState {
+    const SECURE_BOOT: u32 = 1 << 0;
+    const DEBUG_LOCKED: u32 = 1 << 1;
+    const KEYSTORE_PROVISIONED: u32 = 1 << 2;
+    const SEALED: u32 = 1 << 3;
+    const ROLLBACK_SHIFT: u32 = 16;
+
+    fn to_bits(self, sealed: bool) -> u32 {
+        let mut bits = u32::from(self.rollback_version) << Self::ROLLBACK_SHIFT;
+        if self.secure_boot {
+            bits |= Self::SECURE_BOOT;
+        }
+        if self.debug_locked {
+            bits |= Self::DEBUG_LOCKED;
+        }
+        if self.keystore_provisioned {
+            bits |= Self::KEYSTORE_PROVISIONED;
+        }
+        if sealed {
+            bits |= Self::SEALED;
+        }
+        bits
+    }
+}
+
+/// Platform attestation key
+pub trait Signer {
+    /// Size of a signature in bytes
+    const SIGNATURE_SIZE: usize;
+
+    /// Sign `message`, writing the signature to `signature`, returns the length of the signature
+    ///
+    /// `signature` is exactly [`Self::SIGNATURE_SIZE`] bytes long, and the whole of it must be written.
+    fn sign(&self, message: &[u8], signature: &mut [u8]) -> Result<usize, Error>;
+}
+
+struct State<const N: usize> {
+    secure_state: SecureState,
+    measurements: heapless::Vec<Measurement, N>,
+    sealed: bool,
+}
+
+/// Log of up to `N` boot measurements
+pub struct MeasurementLog<const N: usize> {
+    state: Mutex<GlobalRawMutex, RefCell<State<N>>>,
+}
+
+impl<const N: usize> MeasurementLog<N> {
+    /// Create a new empty log
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(State {
+                secure_state: SecureState {
+                    secure_boot: false,
+                    debug_locked: false,
+                    keystore_provisioned: false,
+                    rollback_version: 0,
+                },
+                measurements: heapless::Vec::new(),
+                sealed: false,
+            })),
+        }
+    }
+
+    /// Record a measurement
+    pub fn record(&self, measurement: Measurement) -> Result<(), Error> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if state.sealed {
+                return Err(Error::Sealed);
+            }
+
+            state.measurements.push(measurement).map_err(|_| Error::LogFull)
+        })
+    }
+
+    /// Set the platform security state, typically derived from the ROM and keystore configuration
+    pub fn set_secure_state(&self, secure_state: SecureState) -> Result<(), Error> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            if state.sealed {
+                return Err(Error::Sealed);
+            }
+
+            state.secure_state = secure_state;
+            Ok(())
+        })?;
+        embedded_services::caps::set_secure_state(self.secure_state());
+        Ok(())
+    }
+
+    /// Seal the log once boot has completed, no further changes are accepted
+    pub fn seal(&self) {
+        self.state.lock(|state| state.borrow_mut().sealed = true);
+        embedded_services::caps::set_secure_state(self.secure_state());
+    }
+
+    /// Returns true if the log has been sealed
+    pub fn is_sealed(&self) -> bool {
+        self.state.lock(|state| state.borrow().sealed)
+    }
+
+    /// Returns the value of the capabilities `secure_state` field
+    pub fn secure_state(&self) -> u32 {
+        self.state.lock(|state| {
+            let state = state.borrow();
+            state.secure_state.to_bits(state.sealed)
+        })
+    }
+
+    /// Returns the number of recorded measurements
+    pub fn len(&self) -> usize {
+        self.state.lock(|state| state.borrow().measurements.len())
+    }
+
+    /// Returns true if no measurements have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the measurement at the given index
+    pub fn get(&self, index: usize) -> Option<Measurement> {
+        self.state.lock(|state| state.borrow().measurements.get(index).copied())
+    }
+
+    /// Build a signed attestation blob for the host, returns the length of the blob
+    ///
+    /// Blob layout, all integers little-endian:
+    /// - version (u8), measurement count (u8), signature length (u16), secure state (u32), nonce
+    /// - per measurement: component (u8), index (u8), version (u16), digest
+    /// - signature over everything above
+    pub fn attest<S: Signer>(&self, nonce: &[u8; NONCE_SIZE], signer: &S, out: &mut [u8]) -> Result<usize, Error> {
+        let (sealed, count, secure_state) = self.state.lock(|state| {
+            let state = state.borrow();
+            (
+                state.sealed,
+                state.measurements.len(),
+                state.secure_state.to_bits(state.sealed),
+            )
+        });
+        if !sealed {
+            return Err(Error::NotSealed);
+        }
+
+        let count = u8::try_from(count).map_err(|_| Error::BufferTooSmall)?;
+        let body_len = HEADER_SIZE + usize::from(count) * ENTRY_SIZE;
+        if out.len() < body_len {
+            return Err(Error::BufferTooSmall);
+        }
+        let (body, signature) = out.split_at_mut(body_len);
+
+        // Signature length is filled in once known
+        let (header, entries) = body.split_at_mut(HEADER_SIZE);
+        let (fixed, nonce_out) = header.split_at_mut(8);
+        fixed.copy_from_slice(&[BLOB_VERSION, count, 0, 0, 0, 0, 0, 0]);
+        fixed
+            .get_mut(4..8)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(&secure_state.to_le_bytes());
+        nonce_out.copy_from_slice(nonce);
+
+        // The log is sealed so measurements can't change under us
+        for (index, entry) in entries.chunks_exact_mut(ENTRY_SIZE).enumerate() {
+            self.get(index).ok_or(Error::BufferTooSmall)?.serialize(entry)?;
+        }
+
+        let signature_len = u16::try_from(S::SIGNATURE_SIZE).map_err(|_| Error::Signer)?;
+        body.get_mut(2..4)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(&signature_len.to_le_bytes());
+
+        let signature = signature.get_mut(..S::SIGNATURE_SIZE).ok_or(Error::BufferTooSmall)?;
+        let written = signer.sign(body, signature)?;
+        if written != S::SIGNATURE_SIZE {
+            return Err(Error::Signer);
+        }
+
+        Ok(body_len + written)
+    }
+}
+
+impl<const N: usize> Default for MeasurementLog<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Source of host attestation blobs
+pub trait Attester {
+    /// Build a signed attestation blob for the given nonce, returns the length of the blob
+    fn attest(&self, nonce: &[u8; NONCE_SIZE], out: &mut [u8]) -> Result<usize, Error>;
+}
+
+/// Attests a measurement log with the platform key
+pub struct PlatformAttester<'a, const N: usize, S: Signer> {
+    log: &'a MeasurementLog<N>,
+    signer: S,
+}
+
+impl<'a, const N: usize, S: Signer> PlatformAttester<'a, N, S> {
+    /// Create a new attester for the given log and key
+    pub const fn new(log: &'a MeasurementLog<N>, signer: S) -> Self {
+        Self { log, signer }
+    }
+}
+
+impl<const N: usize, S: Signer> Attester for PlatformAttester<'_, N, S> {
+    fn attest(&self, nonce: &[u8; NONCE_SIZE], out: &mut [u8]) -> Result<usize, Error> {
+        self.log.attest(nonce, &self.signer, out)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+#[allow(clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    /// Toy signer that XORs the message into the signature
+    struct XorSigner;
+
+    impl Signer for XorSigner {
+        const SIGNATURE_SIZE: usize = 4;
+
+        fn sign(&self, message: &[u8], signature: &mut [u8]) -> Result<usize, Error> {
+            signature.fill(0);
+            for (i, byte) in message.iter().enumerate() {
+                signature[i % 4] ^= byte;
+            }
+            Ok(4)
+        }
+    }
+
+    fn measurement(component: Component, fill: u8) -> Measurement {
+        Measurement {
+            component,
+            index: 0,
+            version: 0x0102,
+            digest: [fill; DIGEST_SIZE],
+        }
+    }
+
+    #[test]
+    fn test_record_and_seal() {
+        let log: MeasurementLog<2> = MeasurementLog::new();
+        log.record(measurement(Component::Bootloader, 1)).unwrap();
+        log.record(measurement(Component::Application, 2)).unwrap();
+        assert_eq!(log.record(measurement(Component::Keystore, 3)), Err(Error::LogFull));
+
+        log.seal();
+        assert_eq!(log.record(measurement(Component::Keystore, 3)), Err(Error::Sealed));
+        assert_eq!(log.set_secure_state(SecureState::default()), Err(Error::Sealed));
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.get(1), Some(measurement(Component::Application, 2)));
+    }
+
+    #[test]
+    fn test_secure_state() {
+        let log: MeasurementLog<1> = MeasurementLog::new();
+        log.set_secure_state(SecureState {
+            secure_boot: true,
+            debug_locked: false,
+            keystore_provisioned: true,
+            rollback_version: 3,
+        })
+        .unwrap();
+        assert_eq!(log.secure_state(), 0x0003_0005);
+
+        log.seal();
+        assert_eq!(log.secure_state(), 0x0003_000D);
+    }
+
+    #[test]
+    fn test_attest() {
+        let log: MeasurementLog<2> = MeasurementLog::new();
+        log.record(measurement(Component::Application, 0xAA)).unwrap();
+
+        let nonce = [0x55; NONCE_SIZE];
+        let mut out = [0u8; 128];
+        assert_eq!(log.attest(&nonce, &XorSigner, &mut out), Err(Error::NotSealed));
+
+        log.seal();
+        let len = log.attest(&nonce, &XorSigner, &mut out).unwrap();
+        assert_eq!(len, HEADER_SIZE + ENTRY_SIZE + 4);
+        assert_eq!(&out[..4], &[BLOB_VERSION, 1, 4, 0]);
+        assert_eq!(&out[4..8], &0x8u32.to_le_bytes());
+        assert_eq!(&out[8..HEADER_SIZE], &nonce);
+        assert_eq!(
+            &out[HEADER_SIZE..HEADER_SIZE + 4],
+            &[Component::Application as u8, 0, 0x02, 0x01]
+        );
+
+        let mut signature = [0u8; 4];
+        XorSigner.sign(&out[..len - 4], &mut signature).unwrap();
+        assert_eq!(&out[len - 4..len], &signature);
+
+        let mut small = [0u8; HEADER_SIZE + ENTRY_SIZE];
+        assert_eq!(log.attest(&nonce, &XorSigner, &mut small), Err(Error::BufferTooSmall));
+    }
+}
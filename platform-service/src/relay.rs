@@ -0,0 +1,332 @@
+//! System commands relayed from the host
+
+use embedded_services::caps::{self, Capabilities};
+use embedded_services::error;
+use embedded_services::relay::{MessageSerializationError, SerializableMessage};
+
+use crate::measurement::{Attester, NONCE_SIZE};
+use crate::reset::{self, ResetReason};
+
+/// System command discriminants
+const RESET_DISCRIMINANT: u16 = 1;
+const GET_CAPABILITIES_DISCRIMINANT: u16 = 2;
+const ATTEST_DISCRIMINANT: u16 = 3;
+
+/// Result and error discriminants
+const OK_NO_DATA_DISCRIMINANT: u16 = 1;
+const CAPABILITIES_DISCRIMINANT: u16 = 2;
+const ATTESTATION_DISCRIMINANT: u16 = 3;
+const UNSPECIFIED_ERROR_DISCRIMINANT: u16 = 1;
+const NOT_SUPPORTED_ERROR_DISCRIMINANT: u16 = 2;
+
+/// Maximum size of an attestation blob returned to the host
+pub const MAX_ATTESTATION_SIZE: usize = 512;
+
+/// Size of the serialized capabilities in bytes
+const CAPABILITIES_SIZE: usize = 4;
+
+fn write_bytes(buffer: &mut [u8], bytes: &[u8]) -> Result<usize, MessageSerializationError> {
+    buffer
+        .get_mut(..bytes.len())
+        .ok_or(MessageSerializationError::BufferTooSmall)?
+        .copy_from_slice(bytes);
+    Ok(bytes.len())
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> Option<u32> {
+    buffer
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+}
+
+/// Requests handled by the system relay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SystemRequest {
+    /// Reset the EC once services have quiesced
+    Reset(ResetReason),
+    /// Read the EC capabilities
+    GetCapabilities,
+    /// Request a signed boot measurement blob, including the host supplied nonce
+    Attest([u8; NONCE_SIZE]),
+}
+
+impl SerializableMessage for SystemRequest {
+    fn serialize(self, buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
+        match self {
+            Self::Reset(reason) => write_bytes(buffer, &reason.0.to_le_bytes()),
+            Self::GetCapabilities => Ok(0),
+            Self::Attest(nonce) => write_bytes(buffer, &nonce),
+        }
+    }
+
+    fn discriminant(&self) -> u16 {
+        match self {
+            Self::Reset(_) => RESET_DISCRIMINANT,
+            Self::GetCapabilities => GET_CAPABILITIES_DISCRIMINANT,
+            Self::Attest(_) => ATTEST_DISCRIMINANT,
+        }
+    }
+
+    fn deserialize(discriminant: u16, buffer: &[u8]) -> Result<Self, MessageSerializationError> {
+        match discriminant {
+            RESET_DISCRIMINANT => {
+                let reason = read_u32(buffer, 0).ok_or(MessageSerializationError::InvalidPayload(
+                    "Could not deserialize reset reason",
+                ))?;
+                Ok(Self::Reset(ResetReason(reason)))
+            }
+            GET_CAPABILITIES_DISCRIMINANT => Ok(Self::GetCapabilities),
+            ATTEST_DISCRIMINANT => {
+                let nonce = buffer.get(..NONCE_SIZE).and_then(|bytes| bytes.try_into().ok()).ok_or(
+                    MessageSerializationError::InvalidPayload("Could not deserialize attestation nonce"),
+                )?;
+                Ok(Self::Attest(nonce))
+            }
+            other => Err(MessageSerializationError::UnknownMessageDiscriminant(other)),
+        }
+    }
+}
+
+/// Successful system command responses
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SystemResponse {
+    /// The command was accepted, there's no data to return.
+    /// For a reset, the EC resets shortly after this response is sent
+    OkNoData,
+    /// Current EC capabilities
+    Capabilities(Capabilities),
+    /// Signed boot measurement blob, see [`MeasurementLog::attest`](crate::measurement::MeasurementLog::attest) for
+    /// the layout
+    Attestation(heapless::Vec<u8, MAX_ATTESTATION_SIZE>),
+}
+
+impl SerializableMessage for SystemResponse {
+    fn serialize(self, buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
+        match self {
+            Self::OkNoData => Ok(0),
+            Self::Capabilities(capabilities) => {
+                let mut bytes = [0u8; CAPABILITIES_SIZE];
+                bytes.copy_from_slice(&capabilities.secure_state.to_le_bytes());
+                write_bytes(buffer, &bytes)
+            }
+            Self::Attestation(blob) => write_bytes(buffer, &blob),
+        }
+    }
+
+    fn discriminant(&self) -> u16 {
+        match self {
+            Self::OkNoData => OK_NO_DATA_DISCRIMINANT,
+            Self::Capabilities(_) => CAPABILITIES_DISCRIMINANT,
+            Self::Attestation(_) => ATTESTATION_DISCRIMINANT,
+        }
+    }
+
+    fn deserialize(discriminant: u16, buffer: &[u8]) -> Result<Self, MessageSerializationError> {
+        match discriminant {
+            OK_NO_DATA_DISCRIMINANT => Ok(Self::OkNoData),
+            CAPABILITIES_DISCRIMINANT => {
+                let secure_state = read_u32(buffer, 0).ok_or(MessageSerializationError::InvalidPayload(
+                    "Could not deserialize capabilities",
+                ))?;
+                Ok(Self::Capabilities(Capabilities { secure_state }))
+            }
+            ATTESTATION_DISCRIMINANT => heapless::Vec::from_slice(buffer)
+                .map(Self::Attestation)
+                .map_err(|_| MessageSerializationError::InvalidPayload("Attestation blob too large")),
+            other => Err(MessageSerializationError::UnknownMessageDiscriminant(other)),
+        }
+    }
+}
+
+/// System command errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SystemError {
+    /// Unspecified error
+    UnspecifiedFailure,
+    /// The command isn't supported by this platform
+    NotSupported,
+}
+
+impl SerializableMessage for SystemError {
+    fn serialize(self, _buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
+        match self {
+            Self::UnspecifiedFailure | Self::NotSupported => Ok(0),
+        }
+    }
+
+    fn discriminant(&self) -> u16 {
+        match self {
+            Self::UnspecifiedFailure => UNSPECIFIED_ERROR_DISCRIMINANT,
+            Self::NotSupported => NOT_SUPPORTED_ERROR_DISCRIMINANT,
+        }
+    }
+
+    fn deserialize(discriminant: u16, _buffer: &[u8]) -> Result<Self, MessageSerializationError> {
+        match discriminant {
+            UNSPECIFIED_ERROR_DISCRIMINANT => Ok(Self::UnspecifiedFailure),
+            NOT_SUPPORTED_ERROR_DISCRIMINANT => Ok(Self::NotSupported),
+            other => Err(MessageSerializationError::UnknownMessageDiscriminant(other)),
+        }
+    }
+}
+
+/// System command result
+pub type SystemResult = Result<SystemResponse, SystemError>;
+
+/// A relay handler that converts MCTP messages into system commands.
+///
+/// A reset is only requested here; it is carried out by [`reset::reset_task`], which must be running, so that the
+/// response reaches the host before services start quiescing. Attestation requests are only supported if an
+/// [`Attester`] was provided.
+#[derive(Default)]
+pub struct SystemRelayHandler<'hw> {
+    attester: Option<&'hw (dyn Attester + Sync)>,
+}
+
+impl<'hw> SystemRelayHandler<'hw> {
+    /// Construct a new relay handler
+    pub fn new() -> Self {
+        Self { attester: None }
+    }
+
+    /// Construct a new relay handler that answers attestation requests with the given attester
+    pub fn with_attester(attester: &'hw (dyn Attester + Sync)) -> Self {
+        Self {
+            attester: Some(attester),
+        }
+    }
+
+    fn attest(&self, nonce: &[u8; NONCE_SIZE]) -> SystemResult {
+        let attester = self.attester.ok_or(SystemError::NotSupported)?;
+        let mut blob = heapless::Vec::new();
+        blob.resize_default(MAX_ATTESTATION_SIZE)
+            .map_err(|_| SystemError::UnspecifiedFailure)?;
+        let len = attester.attest(nonce, &mut blob).map_err(|e| {
+            error!("Attestation failed: {:?}", e);
+            SystemError::UnspecifiedFailure
+        })?;
+        blob.truncate(len);
+        Ok(SystemResponse::Attestation(blob))
+    }
+}
+
+impl embedded_services::relay::mctp::RelayServiceHandlerTypes for SystemRelayHandler<'_> {
+    type RequestType = SystemRequest;
+    type ResultType = SystemResult;
+}
+
+impl embedded_services::relay::mctp::RelayServiceHandler for SystemRelayHandler<'_> {
+    async fn process_request(&self, request: Self::RequestType) -> Self::ResultType {
+        match request {
+            SystemRequest::Reset(reason) => {
+                reset::request_reset(reason);
+                Ok(SystemResponse::OkNoData)
+            }
+            SystemRequest::GetCapabilities => Ok(SystemResponse::Capabilities(caps::get())),
+            SystemRequest::Attest(nonce) => self.attest(&nonce),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::measurement;
+
+    #[test]
+    fn reset_request_round_trip() {
+        let request = SystemRequest::Reset(ResetReason(0xdead_beef));
+        let mut buffer = [0u8; 8];
+        let len = request.serialize(&mut buffer).unwrap();
+        assert_eq!(len, 4);
+        assert_eq!(
+            SystemRequest::deserialize(request.discriminant(), buffer.get(..len).unwrap()).unwrap(),
+            request
+        );
+
+        assert!(matches!(
+            SystemRequest::deserialize(RESET_DISCRIMINANT, &[0; 2]),
+            Err(MessageSerializationError::InvalidPayload(_))
+        ));
+    }
+
+    #[test]
+    fn capabilities_round_trip() {
+        let response = SystemResponse::Capabilities(Capabilities {
+            secure_state: 0x0003_000D,
+        });
+        let discriminant = response.discriminant();
+        let mut buffer = [0u8; 8];
+        let len = response.clone().serialize(&mut buffer).unwrap();
+        assert_eq!(buffer.get(..len).unwrap(), &[0x0D, 0x00, 0x03, 0x00]);
+        assert_eq!(
+            SystemResponse::deserialize(discriminant, buffer.get(..len).unwrap()).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn attest_request_round_trip() {
+        let request = SystemRequest::Attest([0x55; NONCE_SIZE]);
+        let mut buffer = [0u8; NONCE_SIZE];
+        let len = request.serialize(&mut buffer).unwrap();
+        assert_eq!(
+            SystemRequest::deserialize(request.discriminant(), buffer.get(..len).unwrap()).unwrap(),
+            request
+        );
+        assert!(matches!(
+            SystemRequest::deserialize(ATTEST_DISCRIMINANT, &[0; 4]),
+            Err(MessageSerializationError::InvalidPayload(_))
+        ));
+    }
+
+    struct FixedSigner;
+
+    impl measurement::Signer for FixedSigner {
+        const SIGNATURE_SIZE: usize = 2;
+
+        fn sign(&self, _message: &[u8], signature: &mut [u8]) -> Result<usize, measurement::Error> {
+            signature.copy_from_slice(&[0xAB, 0xCD]);
+            Ok(2)
+        }
+    }
+
+    #[tokio::test]
+    async fn attest_request() {
+        use embedded_services::relay::mctp::RelayServiceHandler;
+
+        assert_eq!(
+            SystemRelayHandler::new()
+                .process_request(SystemRequest::Attest([0; NONCE_SIZE]))
+                .await,
+            Err(SystemError::NotSupported)
+        );
+
+        static LOG: measurement::MeasurementLog<1> = measurement::MeasurementLog::new();
+        static ATTESTER: measurement::PlatformAttester<'static, 1, FixedSigner> =
+            measurement::PlatformAttester::new(&LOG, FixedSigner);
+        let handler = SystemRelayHandler::with_attester(&ATTESTER);
+        // Measurements can't be attested until the log is sealed
+        assert_eq!(
+            handler.process_request(SystemRequest::Attest([0; NONCE_SIZE])).await,
+            Err(SystemError::UnspecifiedFailure)
+        );
+
+        LOG.seal();
+        let nonce = [0x11; NONCE_SIZE];
+        let mut expected = [0u8; measurement::HEADER_SIZE + 2];
+        assert_eq!(LOG.attest(&nonce, &FixedSigner, &mut expected), Ok(expected.len()));
+        assert_eq!(expected.get(expected.len() - 2..).unwrap(), &[0xAB, 0xCD]);
+        assert_eq!(
+            handler.process_request(SystemRequest::Attest(nonce)).await,
+            Ok(SystemResponse::Attestation(
+                heapless::Vec::from_slice(&expected).unwrap()
+            ))
+        );
+    }
+}
@@ -1,13 +1,26 @@
 //! API for managing software controlled CPU/MCU resets
 
+use core::cell::Cell;
 use core::future::Future;
 
-use embassy_sync::{lazy_lock::LazyLock, signal::Signal};
+use embassy_sync::{blocking_mutex::Mutex, lazy_lock::LazyLock, signal::Signal};
+use embassy_time::{Duration, with_timeout};
 
-use embedded_services::{GlobalRawMutex, IntrusiveList, Node, NodeContainer, intrusive_list};
+use embedded_services::{GlobalRawMutex, IntrusiveList, Node, NodeContainer, info, intrusive_list, warn};
 
 static BLOCKERS: LazyLock<IntrusiveList> = LazyLock::new(IntrusiveList::new);
 
+/// Reason of the pending reset, set before blockers are signaled
+static PENDING_REASON: Mutex<GlobalRawMutex, Cell<Option<ResetReason>>> = Mutex::new(Cell::new(None));
+
+/// Reset requested through [`request_reset`], consumed by [`reset_task`]
+static REQUESTED: Signal<GlobalRawMutex, ResetReason> = Signal::new();
+
+/// Reason code for a reset, opaque to the EC and reported by whoever requested the reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ResetReason(pub u32);
+
 pub struct Blocker {
     node: Node,
     reset_pending: Signal<GlobalRawMutex, ()>,
@@ -40,18 +53,40 @@ impl Blocker {
     where
         F: FnOnce() -> Fut,
         Fut: Future<Output = ()>,
+    {
+        self.wait_for_reset_with_reason(|_| before_reset()).await;
+    }
+
+    /// waitable reset indicator, for handling resets that need to know why the reset was requested.
+    /// The reason is `None` for resets initiated through [`system_reset`]
+    pub async fn wait_for_reset_with_reason<F, Fut>(&self, before_reset: F)
+    where
+        F: FnOnce(Option<ResetReason>) -> Fut,
+        Fut: Future<Output = ()>,
     {
         self.reset_pending.wait().await;
-        before_reset().await;
+        before_reset(pending_reason()).await;
         self.unblocked.signal(());
     }
 }
 
-/// Signals and waits for all registered blockers to complete their async operations before performing a platform-specific reset, typically NVIC_RESET
-#[cfg(feature = "cortex-m")]
-pub async fn system_reset() -> ! {
+/// Reason of the reset currently in progress, if any
+pub fn pending_reason() -> Option<ResetReason> {
+    PENDING_REASON.lock(|reason| reason.get())
+}
+
+/// Request a staged reset, to be performed by [`reset_task`]. A later request before the reset starts replaces the reason
+pub fn request_reset(reason: ResetReason) {
+    info!("Reset requested, reason {:#x}", reason.0);
+    REQUESTED.signal(reason);
+}
+
+/// Signal all registered blockers and wait for each of them to acknowledge, or until `timeout` expires.
+/// Returns false if the timeout expired before all blockers acknowledged
+async fn quiesce(reason: Option<ResetReason>, timeout: Option<Duration>) -> bool {
     // signal and wait for completion as two separate events to allow for alternative scheduling algorithms to take effect
     let blockers = BLOCKERS.get();
+    PENDING_REASON.lock(|pending| pending.set(reason));
 
     // 1. signal all events
     for blocker in blockers.iter_only::<Blocker>() {
@@ -59,10 +94,86 @@ pub async fn system_reset() -> ! {
     }
 
     // 2. wait for all events
-    for blocker in blockers.iter_only::<Blocker>() {
-        blocker.unblocked.wait().await;
+    let acknowledged = async {
+        for blocker in blockers.iter_only::<Blocker>() {
+            blocker.unblocked.wait().await;
+        }
+    };
+
+    match timeout {
+        Some(timeout) => {
+            let result = with_timeout(timeout, acknowledged).await;
+            if result.is_err() {
+                warn!("Reset blockers did not acknowledge within {} ms", timeout.as_millis());
+            }
+            result.is_ok()
+        }
+        None => {
+            acknowledged.await;
+            true
+        }
     }
+}
+
+/// Signals and waits for all registered blockers to complete their async operations before performing a platform-specific reset, typically NVIC_RESET
+#[cfg(feature = "cortex-m")]
+pub async fn system_reset() -> ! {
+    quiesce(None, None).await;
 
     // 3. perform platform reset
     cortex_m::peripheral::SCB::sys_reset();
 }
+
+/// Like [`system_reset`], but gives blockers at most `timeout` to acknowledge so a stuck blocker cannot prevent the reset
+#[cfg(feature = "cortex-m")]
+pub async fn staged_reset(reason: ResetReason, timeout: Duration) -> ! {
+    info!("Staged reset, reason {:#x}", reason.0);
+    quiesce(Some(reason), Some(timeout)).await;
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+/// Waits for a reset requested through [`request_reset`] and performs it as a [`staged_reset`]
+#[cfg(feature = "cortex-m")]
+pub async fn reset_task(timeout: Duration) -> ! {
+    let reason = REQUESTED.wait().await;
+    staged_reset(reason, timeout).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::join::join;
+
+    static BLOCKER: Blocker = Blocker::uninit();
+    static STUCK: Blocker = Blocker::uninit();
+
+    // Both scenarios share the global blocker list, so they run sequentially in one test
+    #[tokio::test]
+    async fn quiesce_waits_for_acknowledgement_or_timeout() {
+        assert!(BLOCKER.register().is_ok());
+
+        let reason = ResetReason(0x42);
+        let (seen, acknowledged) = join(
+            async {
+                let seen = Cell::new(None);
+                BLOCKER
+                    .wait_for_reset_with_reason(|reason| async { seen.set(reason) })
+                    .await;
+                seen.get()
+            },
+            quiesce(Some(reason), Some(Duration::from_millis(100))),
+        )
+        .await;
+        assert_eq!(seen, Some(reason));
+        assert!(acknowledged);
+
+        // A blocker that never acknowledges doesn't hold the reset up past the timeout
+        assert!(STUCK.register().is_ok());
+        let (_, acknowledged) = join(
+            BLOCKER.wait_for_reset(|| async {}),
+            quiesce(Some(reason), Some(Duration::from_millis(10))),
+        )
+        .await;
+        assert!(!acknowledged);
+    }
+}
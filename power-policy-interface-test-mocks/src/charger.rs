@@ -16,6 +16,7 @@ pub enum FnCall {
     IsReady,
     ChargingCurrent(MilliAmps),
     ChargingVoltage(MilliVolts),
+    SetLearnMode(Option<u16>),
 }
 
 /// Mock charger for use in tests
@@ -36,6 +37,8 @@ pub struct Mock<S: NonBlockingSender<charger::event::EventData>> {
     pub next_result_charging_current: VecDeque<Result<MilliAmps, core::convert::Infallible>>,
     /// Next results to return for [`embedded_batteries_async::charger::Charger::charging_voltage`]
     pub next_result_charging_voltage: VecDeque<Result<MilliVolts, core::convert::Infallible>>,
+    /// Next results to return for [`charger::LearnMode::set_learn_mode`]
+    pub next_result_set_learn_mode: VecDeque<Result<(), core::convert::Infallible>>,
 }
 
 impl<S: NonBlockingSender<charger::event::EventData>> Mock<S> {
@@ -50,6 +53,7 @@ impl<S: NonBlockingSender<charger::event::EventData>> Mock<S> {
             next_result_is_ready: VecDeque::new(),
             next_result_charging_current: VecDeque::new(),
             next_result_charging_voltage: VecDeque::new(),
+            next_result_set_learn_mode: VecDeque::new(),
         }
     }
 
@@ -132,4 +136,13 @@ impl<S: NonBlockingSender<charger::event::EventData>> charger::Charger for Mock<
     }
 }
 
+impl<S: NonBlockingSender<charger::event::EventData>> charger::LearnMode for Mock<S> {
+    async fn set_learn_mode(&mut self, discharge_current_ma: Option<u16>) -> Result<(), Self::ChargerError> {
+        self.fn_calls.push_back(FnCall::SetLearnMode(discharge_current_ma));
+        self.next_result_set_learn_mode
+            .pop_front()
+            .expect("next_result_set_learn_mode not set")
+    }
+}
+
 pub type ChargerType<S> = Mutex<GlobalRawMutex, Mock<S>>;
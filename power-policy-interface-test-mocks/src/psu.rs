@@ -7,6 +7,7 @@ use power_policy_interface::{
     capability::{
         ConsumerDisconnect, ConsumerPowerCapability, PowerCapability, ProviderFlags, ProviderPowerCapability,
     },
+    dock::DockInfo,
     psu::{Error, Psu, State, event::EventData},
 };
 
@@ -91,6 +92,17 @@ impl<S: NonBlockingSender<EventData>> Mock<S> {
             .unwrap();
     }
 
+    /// Simulate a provider that failed to maintain its contract
+    pub async fn simulate_provider_contract_failure(&mut self) {
+        self.sender.try_send(EventData::ProviderContractFailed).unwrap();
+    }
+
+    /// Simulate a dock being identified on, or removed from, the device
+    pub async fn simulate_dock_update(&mut self, dock: Option<DockInfo>) {
+        self.state.update_dock(dock);
+        self.sender.try_send(EventData::UpdatedDock(dock)).unwrap();
+    }
+
     pub async fn simulate_update_requested_provider_power_capability(
         &mut self,
         capability: Option<ProviderPowerCapability>,
@@ -117,6 +117,8 @@ bitfield! {
     impl Debug;
     /// Unconstrained power, indicates that we are drawing power from something like an outlet and not a limited source like a battery
     pub bool, unconstrained_power, set_unconstrained_power: 0;
+    /// Thermally limited, indicates that thermal policy is capping input power below what the consumer can provide
+    pub bool, thermally_limited, set_thermally_limited: 1;
     /// PSU type
     pub u8, psu_type, set_psu_type: 11, 8;
 }
@@ -148,6 +150,22 @@ impl ConsumerFlags {
         self.0.set_unconstrained_power(value);
     }
 
+    /// Builder method to set the thermally limited flag
+    pub fn with_thermally_limited(mut self) -> Self {
+        self.0.set_thermally_limited(true);
+        self
+    }
+
+    /// Check if the thermally limited flag is set
+    pub fn thermally_limited(&self) -> bool {
+        self.0.thermally_limited()
+    }
+
+    /// Set the thermally limited flag
+    pub fn set_thermally_limited(&mut self, value: bool) {
+        self.0.set_thermally_limited(value);
+    }
+
     /// Builder method to set the PSU type
     pub fn with_psu_type(mut self, value: PsuType) -> Self {
         self.set_psu_type(value);
@@ -307,6 +325,16 @@ mod tests {
         assert_eq!(consumer.0.0, 0x0);
     }
 
+    #[test]
+    fn test_consumer_flags_thermally_limited() {
+        let mut consumer = ConsumerFlags::none().with_thermally_limited();
+        assert_eq!(consumer.0.0, 0x2);
+        assert!(consumer.thermally_limited());
+        assert!(!consumer.unconstrained_power());
+        consumer.set_thermally_limited(false);
+        assert_eq!(consumer.0.0, 0x0);
+    }
+
     #[test]
     fn test_consumer_flags_psu_type() {
         let mut consumer = ConsumerFlags::none().with_psu_type(PsuType::TypeC);
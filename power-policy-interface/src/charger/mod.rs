@@ -1,6 +1,6 @@
 //! Charger events, state machine, and trait
 
-use crate::capability::ConsumerPowerCapability;
+use crate::capability::{ConsumerPowerCapability, PsuType};
 use core::{convert::Infallible, future::Future};
 
 pub mod event;
@@ -65,6 +65,21 @@ pub enum PoweredSubstate {
     PsuDetached,
 }
 
+bitflags::bitflags! {
+    /// Reasons battery charge control holds charging off
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+    pub struct ChargeInhibit: u8 {
+        /// Battery safety fault
+        const FAULT = 1 << 0;
+        /// Charge limit reached
+        const LIMIT = 1 << 1;
+        /// System closed in a bag
+        const IN_BAG = 1 << 2;
+        /// Battery calibration or host maintenance request
+        const CALIBRATION = 1 << 3;
+    }
+}
+
 /// Current state of the charger
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -73,6 +88,9 @@ pub struct State {
     state: InternalState,
     /// Current charger capability
     capability: Option<ConsumerPowerCapability>,
+    /// Reasons charging is inhibited by battery charge control
+    #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+    charge_inhibit: ChargeInhibit,
 }
 
 impl Default for State {
@@ -80,6 +98,7 @@ impl Default for State {
         Self {
             state: InternalState::Unpowered,
             capability: None,
+            charge_inhibit: ChargeInhibit::empty(),
         }
     }
 }
@@ -177,6 +196,26 @@ impl State {
     pub fn is_unpowered(&self) -> bool {
         self.state == InternalState::Unpowered
     }
+
+    /// Set or clear one reason battery charge control holds charging off.
+    ///
+    /// Persists across charger state changes. While any reason is set, power policy only caches new capabilities with
+    /// [`Self::on_policy_attach`] instead of calling [`Charger::attach_handler`], which would restore the charge
+    /// current. Each charge control feature only clears its own reason, and attaches the cached capability once no
+    /// reason is left.
+    pub fn set_charge_inhibit(&mut self, reason: ChargeInhibit, inhibited: bool) {
+        self.charge_inhibit.set(reason, inhibited);
+    }
+
+    /// Returns the reasons charging is inhibited by battery charge control.
+    pub fn charge_inhibit(&self) -> ChargeInhibit {
+        self.charge_inhibit
+    }
+
+    /// Returns `true` if charging is inhibited by battery charge control for any reason.
+    pub fn is_charge_inhibited(&self) -> bool {
+        !self.charge_inhibit.is_empty()
+    }
 }
 
 /// Charger controller trait that devices must implement to use the power policy service.
@@ -194,6 +233,13 @@ pub trait Charger: embedded_batteries_async::charger::Charger {
     /// Called after power policy detaches from a power port, either to switch consumers,
     /// or because PSU was disconnected.
     fn detach_handler(&mut self) -> impl Future<Output = Result<(), Self::ChargerError>>;
+    /// Called while switching consumers to route the charger input to the new source.
+    ///
+    /// Power policy calls this after the previous consumer is disconnected and before the new one is connected, so
+    /// that both sources are never connected to the input at the same time.
+    fn select_input(&mut self, _psu_type: PsuType) -> impl Future<Output = Result<(), Self::ChargerError>> {
+        core::future::ready(Ok(()))
+    }
     /// Upon successful return of this method, the charger is assumed to be powered and ready to communicate,
     /// transitioning state from unpowered to powered.
     fn is_ready(&mut self) -> impl Future<Output = Result<(), Self::ChargerError>> {
@@ -204,3 +250,14 @@ pub trait Charger: embedded_batteries_async::charger::Charger {
     /// Return a mutable reference to the current charger state
     fn state_mut(&mut self) -> &mut State;
 }
+
+/// Chargers that support battery learn mode.
+///
+/// In learn mode the system runs from the battery while the adapter stays connected, used by battery recalibration.
+pub trait LearnMode: Charger {
+    /// Enter learn mode limiting the battery discharge current to the given value in mA, or exit with `None`
+    fn set_learn_mode(
+        &mut self,
+        discharge_current_ma: Option<u16>,
+    ) -> impl Future<Output = Result<(), Self::ChargerError>>;
+}
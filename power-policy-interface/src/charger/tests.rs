@@ -12,6 +12,7 @@ fn state_init() -> State {
     State {
         state: InternalState::Powered(PoweredSubstate::Init),
         capability: None,
+        charge_inhibit: ChargeInhibit::empty(),
     }
 }
 
@@ -19,6 +20,7 @@ fn state_psu_attached() -> State {
     State {
         state: InternalState::Powered(PoweredSubstate::PsuAttached),
         capability: None,
+        charge_inhibit: ChargeInhibit::empty(),
     }
 }
 
@@ -26,6 +28,7 @@ fn state_psu_detached() -> State {
     State {
         state: InternalState::Powered(PoweredSubstate::PsuDetached),
         capability: None,
+        charge_inhibit: ChargeInhibit::empty(),
     }
 }
 
@@ -283,3 +286,34 @@ fn full_lifecycle_unpowered_to_charging_and_back() {
     s.on_timeout();
     assert_eq!(s.state, InternalState::Unpowered);
 }
+
+// set_charge_inhibit
+
+#[test]
+fn charge_inhibit_persists_across_state_changes() {
+    let mut s = state_psu_attached();
+    assert!(!s.is_charge_inhibited());
+
+    s.set_charge_inhibit(ChargeInhibit::FAULT, true);
+    s.on_policy_attach(cap(5000, 3000));
+    s.on_timeout();
+    s.on_ready_success();
+    assert!(s.is_charge_inhibited());
+
+    s.set_charge_inhibit(ChargeInhibit::FAULT, false);
+    assert!(!s.is_charge_inhibited());
+}
+
+#[test]
+fn charge_inhibit_reasons_clear_independently() {
+    let mut s = state_psu_attached();
+    s.set_charge_inhibit(ChargeInhibit::FAULT, true);
+    s.set_charge_inhibit(ChargeInhibit::CALIBRATION, true);
+
+    s.set_charge_inhibit(ChargeInhibit::CALIBRATION, false);
+    assert_eq!(s.charge_inhibit(), ChargeInhibit::FAULT);
+    assert!(s.is_charge_inhibited());
+
+    s.set_charge_inhibit(ChargeInhibit::FAULT, false);
+    assert!(!s.is_charge_inhibited());
+}
@@ -0,0 +1,26 @@
+//! Dock detection types
+
+/// Dock identified on a PSU
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DockInfo {
+    /// Power the dock reserves for its downstream ports, in mW
+    pub downstream_power_mw: u32,
+}
+
+/// Composite docked state across all PSUs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DockedState {
+    /// Number of docks currently attached
+    pub docks: u8,
+    /// Total power reserved for the downstream ports of all docks, in mW
+    pub downstream_power_mw: u32,
+}
+
+impl DockedState {
+    /// Returns true if any dock is attached
+    pub fn is_docked(&self) -> bool {
+        self.docks > 0
+    }
+}
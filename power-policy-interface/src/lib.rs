@@ -2,5 +2,8 @@
 
 pub mod capability;
 pub mod charger;
+pub mod dock;
 pub mod psu;
 pub mod service;
+pub mod telemetry;
+pub mod thermal;
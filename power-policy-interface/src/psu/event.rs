@@ -3,6 +3,7 @@ use embedded_services::sync::Lockable;
 
 use crate::{
     capability::{ConsumerDisconnect, ConsumerPowerCapability, ProviderPowerCapability},
+    dock::DockInfo,
     psu,
 };
 
@@ -21,6 +22,10 @@ pub enum EventData {
     Disconnected(ConsumerDisconnect),
     /// Notify that a device has detached
     Detached,
+    /// Notify that a device failed to maintain the provider contract it was connected with
+    ProviderContractFailed,
+    /// Notify that a dock was identified on the device, or is no longer present
+    UpdatedDock(Option<DockInfo>),
 }
 
 /// Event broadcast from a PSU.
@@ -2,6 +2,7 @@
 use embedded_services::named::Named;
 
 use crate::capability::{ConsumerPowerCapability, PowerCapability, ProviderPowerCapability};
+use crate::dock::DockInfo;
 
 pub mod event;
 
@@ -88,6 +89,8 @@ pub struct State {
     pub consumer_capability: Option<ConsumerPowerCapability>,
     /// Current requested provider capability
     pub requested_provider_capability: Option<ProviderPowerCapability>,
+    /// Dock identified on this device, if any
+    pub dock: Option<DockInfo>,
 }
 
 impl Default for State {
@@ -96,6 +99,7 @@ impl Default for State {
             psu_state: PsuState::Detached,
             consumer_capability: None,
             requested_provider_capability: None,
+            dock: None,
         }
     }
 }
@@ -119,6 +123,7 @@ impl State {
         self.psu_state = PsuState::Detached;
         self.consumer_capability = None;
         self.requested_provider_capability = None;
+        self.dock = None;
     }
 
     /// Disconnect this device
@@ -166,6 +171,11 @@ impl State {
         result
     }
 
+    /// Update the identified dock, returns true if it changed
+    pub fn update_dock(&mut self, dock: Option<DockInfo>) -> bool {
+        core::mem::replace(&mut self.dock, dock) != dock
+    }
+
     /// Update the requested provider capability
     pub fn update_requested_provider_power_capability(
         &mut self,
@@ -1,7 +1,8 @@
 use embedded_services::sync::Lockable;
 
 use crate::{
-    capability::{ConsumerDisconnect, ConsumerPowerCapability, ProviderPowerCapability},
+    capability::{ConsumerDisconnect, ConsumerPowerCapability, ProviderPowerCapability, PsuType},
+    dock::DockedState,
     psu::Psu,
     service::UnconstrainedState,
 };
@@ -23,8 +24,14 @@ pub enum EventData {
     ProviderDisconnected,
     /// Provider connected
     ProviderConnected(ProviderPowerCapability),
+    /// Provider demoted to the fallback capability after repeated failures
+    ProviderDemoted,
+    /// Input source switched to a consumer of a different PSU type
+    InputSourceSwitched(PsuType),
     /// Unconstrained state changed
     Unconstrained(UnconstrainedState),
+    /// Composite docked state changed
+    DockedStateChanged(DockedState),
 }
 
 impl<'device, PSU: Lockable> From<Event<'device, PSU>> for EventData
@@ -37,7 +44,10 @@ where
             Event::ConsumerConnected(_, capability) => EventData::ConsumerConnected(capability),
             Event::ProviderDisconnected(_) => EventData::ProviderDisconnected,
             Event::ProviderConnected(_, capability) => EventData::ProviderConnected(capability),
+            Event::ProviderDemoted(_) => EventData::ProviderDemoted,
+            Event::InputSourceSwitched(_, psu_type) => EventData::InputSourceSwitched(psu_type),
             Event::Unconstrained(unconstrained) => EventData::Unconstrained(unconstrained),
+            Event::DockedStateChanged(state) => EventData::DockedStateChanged(state),
         }
     }
 }
@@ -58,8 +68,14 @@ where
     ProviderDisconnected(&'device PSU),
     /// Provider connected
     ProviderConnected(&'device PSU, ProviderPowerCapability),
+    /// Provider demoted to the fallback capability after repeated failures
+    ProviderDemoted(&'device PSU),
+    /// Input source switched to a consumer of a different PSU type, e.g. from a DC jack to a type-C port
+    InputSourceSwitched(&'device PSU, PsuType),
     /// Unconstrained state changed
     Unconstrained(UnconstrainedState),
+    /// Composite docked state changed, e.g. so thermal and port policies can switch to their docked profiles
+    DockedStateChanged(DockedState),
 }
 
 impl<'device, PSU> Clone for Event<'device, PSU>
@@ -1,5 +1,10 @@
 pub mod event;
 
+use crate::capability::{ConsumerPowerCapability, ProviderPowerCapability};
+
+/// Maximum number of connected providers reported in a [`PolicyStateResponse`]
+pub const MAX_REPORTED_PROVIDERS: usize = 4;
+
 /// Unconstrained state information
 #[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -19,3 +24,23 @@ impl UnconstrainedState {
         }
     }
 }
+
+/// Comms request for the current power policy state, answered with a [`PolicyStateResponse`]
+///
+/// Sent to [`Internal::Power`](embedded_services::comms::Internal::Power), the response is sent back to the
+/// requesting endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PolicyStateRequest;
+
+/// Power policy state sent in response to a [`PolicyStateRequest`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PolicyStateResponse {
+    /// Capability of the connected consumer, if any
+    pub consumer: Option<ConsumerPowerCapability>,
+    /// Capabilities of the connected providers
+    pub providers: heapless::Vec<ProviderPowerCapability, MAX_REPORTED_PROVIDERS>,
+    /// System unconstrained power
+    pub unconstrained: UnconstrainedState,
+}
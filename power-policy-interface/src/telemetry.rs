@@ -0,0 +1,78 @@
+//! System power (PSYS) telemetry
+use core::cell::Cell;
+use core::future::Future;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_services::GlobalRawMutex;
+
+/// Power sensor errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SensorError {
+    /// Sensor timed out responding
+    Timeout,
+    /// Sensor underlying bus error
+    BusError,
+    /// The measured quantity isn't currently available
+    Unavailable,
+}
+
+/// A sensor that measures power drawn through one point of the system, e.g. the adapter input or a rail.
+pub trait PowerSensor {
+    /// Returns the measured power in mW
+    fn power_mw(&mut self) -> impl Future<Output = Result<u32, SensorError>>;
+}
+
+/// Aggregated system power telemetry
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SystemPower {
+    /// Power drawn from the adapter in mW
+    pub adapter_mw: u32,
+    /// Power drawn from the battery in mW, zero while charging
+    pub battery_discharge_mw: u32,
+    /// Sum of the power measured on individual rails in mW
+    pub rails_mw: u32,
+}
+
+impl SystemPower {
+    /// Total system power in mW
+    ///
+    /// System power is everything drawn from the adapter and battery. Rail measurements only cover part of the
+    /// system, so they're used as a lower bound when the input measurements are missing or lag behind.
+    pub fn psys_mw(&self) -> u32 {
+        self.adapter_mw
+            .saturating_add(self.battery_discharge_mw)
+            .max(self.rails_mw)
+    }
+}
+
+/// Latest system power telemetry, shared between the aggregator and the services that report it to the host
+pub struct SharedSystemPower {
+    value: Mutex<GlobalRawMutex, Cell<Option<SystemPower>>>,
+}
+
+impl SharedSystemPower {
+    /// Create a new instance with no telemetry
+    pub const fn new() -> Self {
+        Self {
+            value: Mutex::new(Cell::new(None)),
+        }
+    }
+
+    /// Returns the latest telemetry, if any has been published
+    pub fn get(&self) -> Option<SystemPower> {
+        self.value.lock(|value| value.get())
+    }
+
+    /// Publish new telemetry
+    pub fn set(&self, power: SystemPower) {
+        self.value.lock(|value| value.set(Some(power)));
+    }
+}
+
+impl Default for SharedSystemPower {
+    fn default() -> Self {
+        Self::new()
+    }
+}
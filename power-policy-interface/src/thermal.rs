@@ -0,0 +1,35 @@
+//! Consumer input power limit imposed by thermal policy
+use embassy_sync::signal::Signal;
+use embedded_services::GlobalRawMutex;
+
+/// Latest consumer input power limit requested by thermal policy, handed to the power policy
+pub struct ConsumerThermalLimit {
+    limit_mw: Signal<GlobalRawMutex, Option<u32>>,
+}
+
+impl ConsumerThermalLimit {
+    /// Create a new instance with no pending limit
+    pub const fn new() -> Self {
+        Self {
+            limit_mw: Signal::new(),
+        }
+    }
+
+    /// Request a new limit in mW, `None` lifts the limit
+    ///
+    /// Only the latest request is kept if the power policy hasn't applied the previous one yet.
+    pub fn set(&self, limit_mw: Option<u32>) {
+        self.limit_mw.signal(limit_mw);
+    }
+
+    /// Wait for the next limit request
+    pub async fn wait(&self) -> Option<u32> {
+        self.limit_mw.wait().await
+    }
+}
+
+impl Default for ConsumerThermalLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
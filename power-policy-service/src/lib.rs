@@ -1,4 +1,9 @@
 #![no_std]
+
+// Host tests use the embassy-time driver from the test support crate
+#[cfg(test)]
+use odp_test_support as _;
+
 pub mod charger;
 pub mod psu;
 pub mod service;
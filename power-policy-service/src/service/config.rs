@@ -1,6 +1,69 @@
 //! Configuration types for the power policy service
 
-use power_policy_interface::capability::PowerCapability;
+use core::cmp::Ordering;
+
+use power_policy_interface::capability::{ConsumerPowerCapability, PowerCapability, PsuType};
+
+/// Priority between input sources of different types
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InputSourcePriority {
+    /// Select the consumer with the best power capability regardless of its type
+    #[default]
+    Capability,
+    /// Prefer a DC jack adapter over any type-C consumer
+    PreferDcJack,
+    /// Prefer a type-C consumer over a DC jack adapter
+    PreferTypeC,
+}
+
+impl InputSourcePriority {
+    /// Rank of a PSU type, consumers with a higher rank are selected regardless of their capability
+    pub fn rank(self, psu_type: PsuType) -> u8 {
+        match (self, psu_type) {
+            (InputSourcePriority::PreferDcJack, PsuType::DcJack)
+            | (InputSourcePriority::PreferTypeC, PsuType::TypeC) => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Preference given to consumers identified as docks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DockPriority {
+    /// Compare docks by capability like any other consumer
+    #[default]
+    None,
+    /// Add the given power in mW to the capability of a dock when comparing it against other consumers
+    BonusMw(u32),
+    /// Prefer a dock over any other consumer of the same input source rank
+    Prefer,
+}
+
+impl DockPriority {
+    /// Compare two consumers by dock preference alone, [`Ordering::Equal`] defers to the capability comparison
+    pub fn cmp_consumers(
+        self,
+        a: &ConsumerPowerCapability,
+        a_is_dock: bool,
+        b: &ConsumerPowerCapability,
+        b_is_dock: bool,
+    ) -> Ordering {
+        match self {
+            _ if a_is_dock == b_is_dock => Ordering::Equal,
+            DockPriority::None => Ordering::Equal,
+            DockPriority::Prefer => a_is_dock.cmp(&b_is_dock),
+            DockPriority::BonusMw(bonus_mw) => {
+                let power = |capability: &ConsumerPowerCapability, is_dock: bool| {
+                    let bonus_mw = if is_dock { bonus_mw } else { 0 };
+                    capability.capability.max_power_mw().saturating_add(bonus_mw)
+                };
+                power(a, a_is_dock).cmp(&power(b, b_is_dock))
+            }
+        }
+    }
+}
 
 #[derive(Clone, Copy)]
 #[non_exhaustive]
@@ -15,6 +78,18 @@ pub struct Config {
     ///
     /// If [`None`], the service will consume from providers, regardless of how much power they provide.
     pub min_consumer_threshold_mw: Option<u32>,
+    /// Interval between system power telemetry updates
+    pub telemetry_interval_ms: u32,
+    /// Number of provider failures after which a provider is demoted to [`provider_fallback`](Self::provider_fallback)
+    ///
+    /// Failures are counted until the device detaches, zero disables demotion.
+    pub provider_failure_threshold: u8,
+    /// Power capability of demoted providers
+    pub provider_fallback: PowerCapability,
+    /// Priority between a DC jack adapter and type-C consumers when both are available
+    pub input_source_priority: InputSourcePriority,
+    /// Preference given to docks when selecting a consumer
+    pub dock_priority: DockPriority,
 }
 
 impl Default for Config {
@@ -34,6 +109,15 @@ impl Default for Config {
             },
             // No minimum threshold
             min_consumer_threshold_mw: None,
+            telemetry_interval_ms: 1000,
+            provider_failure_threshold: 3,
+            // Type-C USB default power
+            provider_fallback: PowerCapability {
+                voltage_mv: 5000,
+                current_ma: 500,
+            },
+            input_source_priority: InputSourcePriority::Capability,
+            dock_priority: DockPriority::None,
         }
     }
 }
@@ -3,16 +3,37 @@ use embedded_services::error;
 use embedded_services::named::Named;
 
 use crate::service::config::Config;
+use crate::service::customization::{CONSUMER_SELECTION_HOOK, ConsumerCandidate, ConsumerCandidates};
 
 use super::*;
 
 use power_policy_interface::psu;
 use power_policy_interface::service::event::Event as ServiceEvent;
 use power_policy_interface::{
-    capability::{ConsumerDisconnect, ConsumerPowerCapability},
+    capability::{ConsumerDisconnect, ConsumerPowerCapability, PsuType},
     psu::PsuState,
 };
 
+/// Pass a new capability to a charger
+///
+/// While battery charge control inhibits charging, only the cached capability is updated so that the inhibit isn't
+/// undone. Battery charge control attaches the cached capability once it clears the inhibit.
+pub(super) async fn attach_charger<C: Charger>(
+    charger: &mut C,
+    capability: ConsumerPowerCapability,
+) -> Result<(), Error> {
+    if charger.state().is_charge_inhibited() {
+        info!("Charging inhibited, caching new charger capability");
+        charger.state_mut().on_policy_attach(capability);
+        return Ok(());
+    }
+
+    charger
+        .attach_handler(capability)
+        .await
+        .map_err(|e| Error::Charger(e.into()))
+}
+
 /// State of the current consumer
 #[derive(Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -57,11 +78,23 @@ pub async fn find_best_consumer_default<
     cmp: Cmp,
 ) -> Result<Option<AvailableConsumer<'device, Reg::Psu>>, Error> {
     let mut best_consumer = None;
-    let current_consumer = state.current_consumer_state.as_ref().map(|f| f.psu);
-
-    for psu in registration.psus() {
+    let mut best_is_dock = false;
+    // Stick with the consumer from before an EC reset until a consumer is connected
+    let current_consumer = state.current_consumer_state.as_ref().map(|f| f.psu).or_else(|| {
+        state
+            .preferred_consumer
+            .and_then(|index| registration.psus().get(index).copied())
+    });
+
+    for (index, psu) in registration.psus().iter().enumerate() {
         let locked_psu = psu.lock().await;
+        if state.is_psu_disabled(index) {
+            info!("({}): Not considering consumer, disabled", locked_psu.name());
+            continue;
+        }
+
         let consumer_capability = locked_psu.state().consumer_capability;
+        let is_dock = locked_psu.state().dock.is_some();
         // Don't consider consumers below minimum threshold
         if consumer_capability
             .zip(config.min_consumer_threshold_mw)
@@ -79,21 +112,39 @@ pub async fn find_best_consumer_default<
             // Nothing available
             (None, None) => None,
             // No existing consumer
-            (None, Some(power_capability)) => Some(AvailableConsumer {
-                psu: *psu,
-                consumer_power_capability: power_capability,
-            }),
+            (None, Some(power_capability)) => {
+                best_is_dock = is_dock;
+                Some(AvailableConsumer {
+                    psu: *psu,
+                    consumer_power_capability: power_capability,
+                })
+            }
             // Existing consumer, no new consumer
             (Some(_), None) => best_consumer,
             // Existing consumer, new available consumer
             (Some(best), Some(available)) => {
-                if cmp(
-                    &available,
-                    current_consumer.is_some_and(|current_consumer| ptr::eq(current_consumer, *psu)),
-                    &best.consumer_power_capability,
-                    current_consumer.is_some_and(|current_consumer| ptr::eq(current_consumer, best.psu)),
-                ) == core::cmp::Ordering::Greater
-                {
+                let priority = config.input_source_priority;
+                let ordering = priority
+                    .rank(available.flags.psu_type())
+                    .cmp(&priority.rank(best.consumer_power_capability.flags.psu_type()))
+                    .then_with(|| {
+                        config.dock_priority.cmp_consumers(
+                            &available,
+                            is_dock,
+                            &best.consumer_power_capability,
+                            best_is_dock,
+                        )
+                    })
+                    .then_with(|| {
+                        cmp(
+                            &available,
+                            current_consumer.is_some_and(|current_consumer| ptr::eq(current_consumer, *psu)),
+                            &best.consumer_power_capability,
+                            current_consumer.is_some_and(|current_consumer| ptr::eq(current_consumer, best.psu)),
+                        )
+                    });
+                if ordering == core::cmp::Ordering::Greater {
+                    best_is_dock = is_dock;
                     Some(AvailableConsumer {
                         psu,
                         consumer_power_capability: available,
@@ -130,10 +181,12 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
             .as_ref()
             .is_some_and(|current| current.consumer_power_capability.flags.unconstrained_power());
 
-        if unconstrained_new != self.state.unconstrained {
+        if self.unconstrained.send_if_modified(unconstrained_new) {
             info!("Unconstrained state changed: {:?}", unconstrained_new);
-            self.state.unconstrained = unconstrained_new;
-            self.broadcast_event(ServiceEvent::Unconstrained(self.state.unconstrained));
+            self.broadcast_event(ServiceEvent::Unconstrained(unconstrained_new));
+            if let Some(broadcaster) = self.unconstrained_broadcaster {
+                broadcaster.broadcast(unconstrained_new).await;
+            }
         }
         Ok(())
     }
@@ -144,10 +197,13 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
         connected_consumer: AvailableConsumer<'device, Reg::Psu>,
     ) -> Result<(), Error> {
         self.state.current_consumer_state = Some(connected_consumer);
+        self.state.preferred_consumer = None;
+        self.persist();
         // todo: review the delay time
         embassy_time::Timer::after_millis(800).await;
 
         // If no chargers are registered, they won't receive the new power capability.
+        let charger_capability = self.charger_capability(connected_consumer.consumer_power_capability);
         for node in self.registration.chargers() {
             let mut locked_charger = node.lock().await;
             // Chargers should be powered at this point, but in case they are not...
@@ -164,10 +220,7 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
             }
 
             // Attach and update state to new capability
-            locked_charger
-                .attach_handler(connected_consumer.consumer_power_capability)
-                .await
-                .map_err(|e| Error::Charger(e.into()))?;
+            attach_charger(&mut *locked_charger, charger_capability).await?;
         }
         self.broadcast_event(ServiceEvent::ConsumerConnected(
             connected_consumer.psu,
@@ -192,8 +245,65 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
         Ok(())
     }
 
+    /// Set the input power limit imposed by thermal policy
+    ///
+    /// The charger input is capped to the limit. While the limit is below the current consumer's capability, the
+    /// consumer is flagged as [thermally limited](power_policy_interface::capability::ConsumerFlags::thermally_limited).
+    /// A change to the flag is broadcast as a new [`ServiceEvent::ConsumerConnected`] carrying the updated capability.
+    pub async fn set_consumer_thermal_limit(&mut self, limit_mw: Option<u32>) -> Result<(), Error> {
+        if limit_mw == self.state.consumer_thermal_limit_mw {
+            return Ok(());
+        }
+
+        info!("Consumer thermal limit: {:?} mW", limit_mw);
+        self.state.consumer_thermal_limit_mw = limit_mw;
+        self.persist();
+        self.publish_state_snapshot();
+
+        let Some(mut current_consumer) = self.state.current_consumer_state else {
+            return Ok(());
+        };
+
+        let capability = self.charger_capability(current_consumer.consumer_power_capability);
+        for charger in self.registration.chargers() {
+            let mut locked_charger = charger.lock().await;
+            if !locked_charger.state().is_unpowered() {
+                attach_charger(&mut *locked_charger, capability).await?;
+            }
+        }
+
+        let was_limited = current_consumer.consumer_power_capability.flags.thermally_limited();
+        self.apply_thermal_limit(&mut current_consumer.consumer_power_capability);
+        if current_consumer.consumer_power_capability.flags.thermally_limited() != was_limited {
+            self.state.current_consumer_state = Some(current_consumer);
+            self.broadcast_event(ServiceEvent::ConsumerConnected(
+                current_consumer.psu,
+                current_consumer.consumer_power_capability,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Update the thermally limited flag of `capability` based on the current thermal limit
+    fn apply_thermal_limit(&self, capability: &mut ConsumerPowerCapability) {
+        let limited = self
+            .state
+            .consumer_thermal_limit_mw
+            .is_some_and(|limit_mw| limit_mw < capability.capability.max_power_mw());
+        capability.flags.set_thermally_limited(limited);
+    }
+
     /// Connect to a new consumer
-    async fn connect_new_consumer(&mut self, new_consumer: AvailableConsumer<'device, Reg::Psu>) -> Result<(), Error> {
+    async fn connect_new_consumer(
+        &mut self,
+        mut new_consumer: AvailableConsumer<'device, Reg::Psu>,
+    ) -> Result<(), Error> {
+        // Apply before comparing so an unchanged consumer isn't treated as a new capability
+        self.apply_thermal_limit(&mut new_consumer.consumer_power_capability);
+
+        let new_psu_type = new_consumer.consumer_power_capability.flags.psu_type();
+        let mut switched_source = false;
+
         // Handle our current consumer
         if let Some(current_consumer) = self.state.current_consumer_state {
             if ptr::eq(current_consumer.psu, new_consumer.psu)
@@ -228,10 +338,17 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
                 ConsumerDisconnect::none().with_switching(true)
             };
             self.broadcast_event(ServiceEvent::ConsumerDisconnected(current_consumer.psu, flags));
+            switched_source = current_consumer.consumer_power_capability.flags.psu_type() != new_psu_type;
 
             // Don't update the unconstrained here because this is a transitional state
         }
 
+        // Break before make, the previous consumer is disconnected before the charger input is switched
+        if switched_source {
+            info!("Switching charger input to {:?}", new_psu_type);
+            self.select_charger_input(new_psu_type).await?;
+        }
+
         let mut psu = new_consumer.psu.lock().await;
         info!("({}): Connecting new consumer", psu.name());
 
@@ -244,7 +361,76 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
             e
         } else {
             psu.connect_consumer(new_consumer.consumer_power_capability).await?;
-            self.post_consumer_connected(new_consumer).await
+            self.post_consumer_connected(new_consumer).await?;
+            if switched_source {
+                self.broadcast_event(ServiceEvent::InputSourceSwitched(new_consumer.psu, new_psu_type));
+            }
+            Ok(())
+        }
+    }
+
+    /// Route the input of all powered chargers to a consumer of the given type
+    async fn select_charger_input(&self, psu_type: PsuType) -> Result<(), Error> {
+        for charger in self.registration.chargers() {
+            let mut locked_charger = charger.lock().await;
+            if !locked_charger.state().is_unpowered() {
+                locked_charger
+                    .select_input(psu_type)
+                    .await
+                    .map_err(|e| Error::Charger(e.into()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Let the [`CONSUMER_SELECTION_HOOK`] override the consumer selected by the customization
+    async fn select_consumer_with_hook(
+        &self,
+        selected: Option<AvailableConsumer<'device, Reg::Psu>>,
+    ) -> Option<AvailableConsumer<'device, Reg::Psu>> {
+        if !CONSUMER_SELECTION_HOOK.is_registered() {
+            return selected;
+        }
+
+        let mut candidates = ConsumerCandidates {
+            available: heapless::Vec::new(),
+            selected: None,
+        };
+        for (psu_index, psu) in self.registration.psus().iter().enumerate() {
+            if selected.is_some_and(|selected| ptr::eq(selected.psu, *psu)) {
+                candidates.selected = Some(psu_index);
+            }
+
+            if self.state.is_psu_disabled(psu_index) {
+                continue;
+            }
+
+            let Some(capability) = psu.lock().await.state().consumer_capability else {
+                continue;
+            };
+            if candidates
+                .available
+                .push(ConsumerCandidate { psu_index, capability })
+                .is_err()
+            {
+                error!("Too many consumer candidates, ignoring the rest");
+                break;
+            }
+        }
+
+        let available = candidates.available.clone();
+        let psu_index = CONSUMER_SELECTION_HOOK.call(candidates).await.flatten()?;
+        let candidate = available.iter().find(|candidate| candidate.psu_index == psu_index);
+        match candidate.zip(self.registration.psus().get(psu_index).copied()) {
+            Some((candidate, psu)) => Some(AvailableConsumer {
+                psu,
+                consumer_power_capability: candidate.capability,
+            }),
+            None => {
+                error!("Consumer selection hook selected unavailable PSU {}", psu_index);
+                selected
+            }
         }
     }
 
@@ -266,6 +452,7 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
             .customization
             .find_best_consumer(&self.config, &self.state, &self.registration)
             .await?;
+        let best_consumer = self.select_consumer_with_hook(best_consumer).await;
         let best_consumer_name = if let Some(best_consumer) = best_consumer {
             best_consumer.psu.lock().await.name()
         } else {
@@ -285,9 +472,12 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
             }
             // No new consumer available
             self.state.current_consumer_state = None;
+            self.persist();
         }
 
-        self.update_unconstrained_state().await
+        let result = self.update_unconstrained_state().await;
+        self.publish_state_snapshot();
+        result
     }
 }
 
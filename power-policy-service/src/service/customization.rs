@@ -1,3 +1,5 @@
+use embedded_services::hook::{ExtensionPoint, HookSlot};
+use power_policy_interface::capability::ConsumerPowerCapability;
 use power_policy_interface::psu::Error;
 
 use crate::service::{
@@ -25,3 +27,41 @@ pub trait Customization {
 pub struct DefaultCustomization;
 
 impl Customization for DefaultCustomization {}
+
+/// Maximum number of consumers passed to the [`ConsumerSelection`] hook
+pub const MAX_CONSUMER_CANDIDATES: usize = 8;
+
+/// Consumer available for selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConsumerCandidate {
+    /// Index of the PSU in the registration
+    pub psu_index: usize,
+    /// Power capability offered by the PSU
+    pub capability: ConsumerPowerCapability,
+}
+
+/// Consumers passed to the [`ConsumerSelection`] hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerCandidates {
+    /// Enabled PSUs that can supply power
+    pub available: heapless::Vec<ConsumerCandidate, MAX_CONSUMER_CANDIDATES>,
+    /// Index of the PSU selected by [`Customization::find_best_consumer`], if any
+    pub selected: Option<usize>,
+}
+
+/// OEM extension point for consumer selection
+///
+/// A registered hook runs after [`Customization::find_best_consumer`] and returns the index of the PSU to consume
+/// from, which must be one of the available candidates, or `None` to not consume from any PSU. Returning `selected`
+/// keeps the service's choice.
+pub struct ConsumerSelection;
+
+impl ExtensionPoint for ConsumerSelection {
+    const NAME: &'static str = "power_policy::ConsumerSelection";
+    type Input = ConsumerCandidates;
+    type Output = Option<usize>;
+}
+
+/// Hook slot for [`ConsumerSelection`]
+pub static CONSUMER_SELECTION_HOOK: HookSlot<ConsumerSelection> = HookSlot::new();
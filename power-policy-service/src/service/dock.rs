@@ -0,0 +1,46 @@
+//! Dock detection
+//!
+//! A PSU reports a dock identified on its port with [`PsuEventData::UpdatedDock`]. Docks can be given priority as
+//! consumers through [`dock_priority`](super::config::Config::dock_priority). The docked state of the system,
+//! aggregated across all PSUs, is broadcast with [`ServiceEvent::DockedStateChanged`] so that other services, e.g.
+//! thermal or port policy, can switch to their docked profiles.
+use power_policy_interface::dock::DockInfo;
+
+use super::*;
+
+impl<'device, Reg: Registration<'device>, Customization: customization::Customization>
+    Service<'device, Reg, Customization>
+{
+    /// Current composite docked state
+    pub fn docked_state(&self) -> DockedState {
+        self.state.docked
+    }
+
+    /// Recompute the docked state from all PSUs, broadcasting it if it changed
+    pub(super) async fn update_docked_state(&mut self) {
+        let mut docked = DockedState::default();
+        for psu in self.registration.psus() {
+            if let Some(dock) = psu.lock().await.state().dock {
+                docked.docks = docked.docks.saturating_add(1);
+                docked.downstream_power_mw = docked.downstream_power_mw.saturating_add(dock.downstream_power_mw);
+            }
+        }
+
+        if docked != self.state.docked {
+            info!("Docked state changed: {:?}", docked);
+            self.state.docked = docked;
+            self.broadcast_event(ServiceEvent::DockedStateChanged(docked));
+        }
+    }
+
+    pub(super) async fn process_notify_dock(
+        &mut self,
+        device: &'device Reg::Psu,
+        dock: Option<DockInfo>,
+    ) -> Result<(), Error> {
+        info!("({}): Received dock update: {:?}", device.lock().await.name(), dock);
+        self.update_docked_state().await;
+        // Dock priority can change the best consumer
+        self.update_current_consumer(ConsumerDisconnect::none()).await
+    }
+}
@@ -4,28 +4,40 @@ use core::ptr;
 pub mod config;
 pub mod consumer;
 pub mod customization;
+pub mod dock;
+pub mod persistence;
 pub mod provider;
+pub mod query;
 pub mod registration;
+pub mod reservation;
+pub mod snapshot;
 pub mod task;
+pub mod telemetry;
 
+use embedded_services::broadcaster::immediate::Immediate;
 use embedded_services::error;
 use embedded_services::named::Named;
+use embedded_services::service_state::RetainedState;
+use embedded_services::sync::Watch;
 use embedded_services::{event::NonBlockingSender, info, sync::Lockable, trace};
 
 use power_policy_interface::charger::{Charger, PsuState};
 use power_policy_interface::{
     capability::{ConsumerDisconnect, ConsumerPowerCapability, ProviderPowerCapability},
     charger::{Event as ChargerEvent, EventData as ChargerEventData},
+    dock::DockedState,
     psu::{
         Error, Psu,
         event::{Event as PsuEvent, EventData as PsuEventData},
     },
-    service::{UnconstrainedState, event::Event as ServiceEvent},
+    service::{MAX_REPORTED_PROVIDERS, PolicyStateResponse, UnconstrainedState, event::Event as ServiceEvent},
 };
 
 use crate::service::registration::Registration;
 
 const MAX_CONNECTED_PROVIDERS: usize = 4;
+/// Maximum number of PSUs with tracked provider failures
+const MAX_FAILING_PROVIDERS: usize = 8;
 
 #[derive(Clone)]
 pub struct InternalState<'device, PSU: Lockable>
@@ -36,10 +48,33 @@ where
     pub current_consumer_state: Option<consumer::AvailableConsumer<'device, PSU>>,
     /// Current provider global state
     pub current_provider_state: provider::State,
-    /// System unconstrained power
-    pub unconstrained: UnconstrainedState,
     /// Connected providers
     pub connected_providers: heapless::index_set::FnvIndexSet<usize, MAX_CONNECTED_PROVIDERS>,
+    /// Input power limit imposed by thermal policy, if any
+    pub consumer_thermal_limit_mw: Option<u32>,
+    /// Power reserved for temporary loads, see [`reservation`]
+    pub reserved_power_mw: u32,
+    /// Registration index of the consumer restored from before an EC reset, preferred until a consumer connects
+    pub preferred_consumer: Option<usize>,
+    /// Bitmask of PSUs that may not be used as consumers, indexed by registration index
+    pub disabled_psus: u32,
+    /// Provider failures since attach, keyed by PSU address
+    pub provider_failures: heapless::index_map::FnvIndexMap<usize, u8, MAX_FAILING_PROVIDERS>,
+    /// Composite docked state across all PSUs
+    pub docked: DockedState,
+}
+
+impl<PSU: Lockable> InternalState<'_, PSU>
+where
+    PSU::Inner: Psu,
+{
+    /// Returns true if the PSU at the given registration index may not be used as a consumer
+    pub fn is_psu_disabled(&self, index: usize) -> bool {
+        u32::try_from(index)
+            .ok()
+            .and_then(|index| self.disabled_psus.checked_shr(index))
+            .is_some_and(|bits| bits & 1 != 0)
+    }
 }
 
 impl<PSU: Lockable> Default for InternalState<'_, PSU>
@@ -50,8 +85,68 @@ where
         Self {
             current_consumer_state: None,
             current_provider_state: provider::State::default(),
-            unconstrained: UnconstrainedState::default(),
             connected_providers: heapless::index_set::FnvIndexSet::new(),
+            consumer_thermal_limit_mw: None,
+            reserved_power_mw: 0,
+            preferred_consumer: None,
+            disabled_psus: 0,
+            provider_failures: heapless::index_map::FnvIndexMap::new(),
+            docked: DockedState::default(),
+        }
+    }
+}
+
+/// Provider connected by the power policy
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConnectedProvider<'device, PSU: Lockable<Inner: Psu>> {
+    /// Device reference
+    pub psu: &'device PSU,
+    /// The power capability provided to the device
+    pub capability: ProviderPowerCapability,
+}
+
+impl<'device, PSU: Lockable<Inner: Psu>> Clone for ConnectedProvider<'device, PSU> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'device, PSU: Lockable<Inner: Psu>> Copy for ConnectedProvider<'device, PSU> {}
+
+/// Snapshot of the power policy state, see [`Service::policy_state`]
+#[derive(Debug)]
+pub struct PolicyState<'device, PSU: Lockable<Inner: Psu>> {
+    /// Currently connected consumer, if any
+    pub consumer: Option<consumer::AvailableConsumer<'device, PSU>>,
+    /// Currently connected providers
+    pub providers: heapless::Vec<ConnectedProvider<'device, PSU>, MAX_CONNECTED_PROVIDERS>,
+    /// System unconstrained power
+    pub unconstrained: UnconstrainedState,
+}
+
+impl<PSU: Lockable<Inner: Psu>> PolicyState<'_, PSU> {
+    /// Returns the state as sent in response to a comms request, see [`query`]
+    pub fn response(&self) -> PolicyStateResponse {
+        PolicyStateResponse {
+            consumer: self.consumer.map(|consumer| consumer.consumer_power_capability),
+            providers: self
+                .providers
+                .iter()
+                .take(MAX_REPORTED_PROVIDERS)
+                .map(|provider| provider.capability)
+                .collect(),
+            unconstrained: self.unconstrained,
+        }
+    }
+}
+
+impl<'device, PSU: Lockable<Inner: Psu>> Clone for PolicyState<'device, PSU> {
+    fn clone(&self) -> Self {
+        Self {
+            consumer: self.consumer,
+            providers: self.providers.clone(),
+            unconstrained: self.unconstrained,
         }
     }
 }
@@ -70,6 +165,16 @@ pub struct Service<
     config: config::Config,
     /// Customization
     customization: Customization,
+    /// System unconstrained power
+    unconstrained: Watch<UnconstrainedState>,
+    /// Retained broadcaster for the unconstrained state, if any
+    unconstrained_broadcaster: Option<&'device Immediate<UnconstrainedState>>,
+    /// Storage for state persisted across EC resets, if any
+    storage: Option<&'device mut (dyn persistence::Storage + Send)>,
+    /// Last persisted state
+    persisted: persistence::PersistentState,
+    /// Snapshot of the policy state for the debug service, if any
+    state_snapshot: Option<&'device RetainedState>,
 }
 
 impl<'device, Reg: Registration<'device>, Customization: customization::Customization + Default>
@@ -86,12 +191,31 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
 {
     /// Create a new power policy with customization
     pub fn new_with_customization(registration: Reg, config: config::Config, customization: Customization) -> Self {
-        Self {
+        Self::new_with_storage(registration, config, customization, None)
+    }
+
+    /// Create a new power policy with customization and persistent storage
+    ///
+    /// State saved in `storage` before an EC reset is restored immediately.
+    pub fn new_with_storage(
+        registration: Reg,
+        config: config::Config,
+        customization: Customization,
+        storage: Option<&'device mut (dyn persistence::Storage + Send)>,
+    ) -> Self {
+        let mut service = Self {
             registration,
             state: InternalState::default(),
             config,
             customization,
-        }
+            unconstrained: Watch::new_with(UnconstrainedState::default()),
+            unconstrained_broadcaster: None,
+            storage,
+            persisted: persistence::PersistentState::default(),
+            state_snapshot: None,
+        };
+        service.restore();
+        service
     }
 
     /// Returns the total amount of power that is being supplied to external devices
@@ -110,13 +234,65 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
         total
     }
 
+    /// System unconstrained power state, watchers are woken whenever it changes
+    pub fn unconstrained(&self) -> &Watch<UnconstrainedState> {
+        &self.unconstrained
+    }
+
+    /// Broadcast unconstrained state changes through `broadcaster`
+    ///
+    /// The current state is broadcast immediately, so with a retained broadcaster (see [`Immediate::new_retained`])
+    /// subscribers always start from the latest state.
+    pub async fn set_unconstrained_broadcaster(&mut self, broadcaster: &'device Immediate<UnconstrainedState>) {
+        self.unconstrained_broadcaster = Some(broadcaster);
+        broadcaster
+            .broadcast(self.unconstrained.try_get().unwrap_or_default())
+            .await;
+    }
+
+    /// Returns a snapshot of the current consumer, connected providers, and unconstrained state
+    ///
+    /// Allows other services to pull the policy state instead of caching broadcast events.
+    pub async fn policy_state(&self) -> PolicyState<'device, Reg::Psu> {
+        let mut providers = heapless::Vec::new();
+        for psu in self.registration.psus() {
+            if !self
+                .state
+                .connected_providers
+                .contains(&(*psu as *const Reg::Psu as usize))
+            {
+                continue;
+            }
+
+            let Some(capability) = psu.lock().await.state().connected_provider_capability() else {
+                continue;
+            };
+
+            if providers.push(ConnectedProvider { psu: *psu, capability }).is_err() {
+                error!("Connected providers list is full");
+                break;
+            }
+        }
+
+        PolicyState {
+            consumer: self.state.current_consumer_state,
+            providers,
+            unconstrained: self.unconstrained.try_get().unwrap_or_default(),
+        }
+    }
+
     async fn process_notify_attach(&self, device: &'device Reg::Psu) {
         info!("({}): Received notify attached", device.lock().await.name());
     }
 
     async fn process_notify_detach(&mut self, device: &'device Reg::Psu) -> Result<(), Error> {
         info!("({}): Received notify detached", device.lock().await.name());
+        self.state
+            .provider_failures
+            .remove(&(device as *const Reg::Psu as usize));
         self.post_provider_removed(device).await;
+        // Detaching clears any dock identified on the device
+        self.update_docked_state().await;
         self.update_current_consumer(ConsumerDisconnect::none()).await?;
         Ok(())
     }
@@ -171,7 +347,7 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
 
     pub async fn process_psu_event(&mut self, event: PsuEvent<'device, Reg::Psu>) -> Result<(), Error> {
         let device = event.psu;
-        match event.event {
+        let result = match event.event {
             PsuEventData::Attached => {
                 self.process_notify_attach(device).await;
                 Ok(())
@@ -185,6 +361,8 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
                     .await
             }
             PsuEventData::Disconnected(flags) => self.process_notify_disconnect(device, flags).await,
+            PsuEventData::ProviderContractFailed => self.process_provider_contract_failed(device).await,
+            PsuEventData::UpdatedDock(dock) => self.process_notify_dock(device, dock).await,
             _ => {
                 info!(
                     "Received unknown PSU event from ({}): {:?}",
@@ -193,7 +371,9 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
                 );
                 Ok(())
             }
-        }
+        };
+        self.publish_state_snapshot();
+        result
     }
 
     async fn process_psu_state_change(
@@ -0,0 +1,170 @@
+//! Persistence of power policy state across EC resets
+//!
+//! An EC reset while the system sleeps would otherwise start the power policy from scratch, possibly switching to a
+//! different consumer or re-enabling a disabled port while the ports report in again. The state saved here is
+//! restored when the service is created, before it processes any events.
+use power_policy_interface::psu::PsuState;
+
+use super::*;
+
+/// Power policy state persisted across EC resets
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PersistentState {
+    /// Registration index of the last connected consumer, if any
+    pub last_consumer: Option<u8>,
+    /// Consumer input power limit imposed by thermal policy, if any
+    pub consumer_limit_mw: Option<u32>,
+    /// Bitmask of PSUs that may not be used as consumers, indexed by registration index
+    pub disabled_psus: u32,
+}
+
+/// Marks a valid [`PersistentState`] encoding so uninitialized NVRAM isn't mistaken for saved state
+const VALID: u32 = 0xa5 << 24;
+const VALID_MASK: u32 = 0xff << 24;
+const HAS_CONSUMER: u32 = 1 << 8;
+const HAS_LIMIT: u32 = 1 << 9;
+const CONSUMER_MASK: u32 = 0xff;
+
+impl PersistentState {
+    /// Encode as words suitable for NVRAM registers
+    pub fn to_words(&self) -> [u32; 3] {
+        let mut flags = VALID;
+        if let Some(consumer) = self.last_consumer {
+            flags |= HAS_CONSUMER | u32::from(consumer);
+        }
+        if self.consumer_limit_mw.is_some() {
+            flags |= HAS_LIMIT;
+        }
+        [flags, self.consumer_limit_mw.unwrap_or(0), self.disabled_psus]
+    }
+
+    /// Decode from words produced by [`Self::to_words`], returns `None` if the words don't contain saved state
+    pub fn from_words(words: [u32; 3]) -> Option<Self> {
+        let [flags, limit_mw, disabled_psus] = words;
+        if flags & VALID_MASK != VALID {
+            return None;
+        }
+
+        Some(Self {
+            last_consumer: (flags & HAS_CONSUMER != 0).then_some((flags & CONSUMER_MASK) as u8),
+            consumer_limit_mw: (flags & HAS_LIMIT != 0).then_some(limit_mw),
+            disabled_psus,
+        })
+    }
+}
+
+/// Storage backend for [`PersistentState`]
+pub trait Storage {
+    /// Load the saved state, `None` if no state was saved or it couldn't be read
+    fn load(&mut self) -> Option<PersistentState>;
+    /// Save the state, must not block
+    fn store(&mut self, state: &PersistentState);
+}
+
+impl<'device, Reg: Registration<'device>, Customization: customization::Customization>
+    Service<'device, Reg, Customization>
+{
+    /// Restore the persisted state, if any
+    pub(super) fn restore(&mut self) {
+        let Some(state) = self.storage.as_mut().and_then(|storage| storage.load()) else {
+            return;
+        };
+
+        info!("Restoring power policy state: {:?}", state);
+        self.state.preferred_consumer = state
+            .last_consumer
+            .map(usize::from)
+            .filter(|index| *index < self.registration.psus().len());
+        self.state.consumer_thermal_limit_mw = state.consumer_limit_mw;
+        self.state.disabled_psus = state.disabled_psus;
+        self.persisted = state;
+    }
+
+    /// Save the current state if it changed since it was last saved
+    pub(super) fn persist(&mut self) {
+        let state = PersistentState {
+            last_consumer: self
+                .state
+                .current_consumer_state
+                .and_then(|consumer| self.psu_index(consumer.psu))
+                .or(self.state.preferred_consumer)
+                .and_then(|index| u8::try_from(index).ok()),
+            consumer_limit_mw: self.state.consumer_thermal_limit_mw,
+            disabled_psus: self.state.disabled_psus,
+        };
+
+        if state == self.persisted {
+            return;
+        }
+
+        if let Some(storage) = self.storage.as_mut() {
+            trace!("Saving power policy state: {:?}", state);
+            storage.store(&state);
+        }
+        self.persisted = state;
+    }
+
+    /// Returns the registration index of `psu`
+    pub(super) fn psu_index(&self, psu: &Reg::Psu) -> Option<usize> {
+        self.registration
+            .psus()
+            .iter()
+            .position(|registered| ptr::eq(*registered, psu))
+    }
+
+    /// Allow or prevent the PSU at the given registration index from being used as a consumer
+    ///
+    /// Disabling the current consumer switches to the next best consumer, if any.
+    pub async fn set_psu_disabled(&mut self, index: usize, disabled: bool) -> Result<(), Error> {
+        if index >= self.registration.psus().len() || index >= u32::BITS as usize {
+            return Err(Error::InvalidDevice);
+        }
+
+        info!("PSU {} consumer disabled: {}", index, disabled);
+        if disabled {
+            self.state.disabled_psus |= 1 << index;
+        } else {
+            self.state.disabled_psus &= !(1 << index);
+        }
+        self.persist();
+
+        if let Some(current_consumer) = self.state.current_consumer_state
+            && disabled
+            && self.psu_index(current_consumer.psu) == Some(index)
+        {
+            // Disconnect now in case there's no other consumer to switch to
+            let mut psu = current_consumer.psu.lock().await;
+            if matches!(psu.state().psu_state, PsuState::ConnectedConsumer(_)) {
+                info!("({}): Disconnecting disabled consumer", psu.name());
+                psu.disconnect().await?;
+            }
+        }
+
+        self.update_current_consumer(ConsumerDisconnect::none()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_round_trip() {
+        let state = PersistentState {
+            last_consumer: Some(2),
+            consumer_limit_mw: Some(45000),
+            disabled_psus: 0b100,
+        };
+        assert_eq!(PersistentState::from_words(state.to_words()), Some(state));
+
+        let state = PersistentState::default();
+        assert_eq!(PersistentState::from_words(state.to_words()), Some(state));
+    }
+
+    #[test]
+    fn uninitialized_words_rejected() {
+        assert_eq!(PersistentState::from_words([0; 3]), None);
+        assert_eq!(PersistentState::from_words([u32::MAX; 3]), None);
+    }
+}
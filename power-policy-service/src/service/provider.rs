@@ -8,6 +8,7 @@ use core::ptr;
 use embedded_services::debug;
 use embedded_services::error;
 use embedded_services::named::Named;
+use embedded_services::warn;
 
 use super::*;
 
@@ -34,73 +35,151 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
 {
     /// Attempt to connect the requester as a provider
     pub(super) async fn connect_provider(&mut self, requester: &'device Reg::Psu) -> Result<(), Error> {
-        let requested_power_capability = {
-            let requester = requester.lock().await;
-            debug!("({}): Attempting to connect as provider", requester.name());
-            match requester.state().requested_provider_capability {
-                Some(cap) => cap,
-                // Requester is no longer requesting power
-                _ => {
-                    info!("({}): No-longer requesting power", requester.name());
-                    return Ok(());
+        loop {
+            let requested_power_capability = {
+                let requester = requester.lock().await;
+                debug!("({}): Attempting to connect as provider", requester.name());
+                match requester.state().requested_provider_capability {
+                    Some(cap) => cap,
+                    // Requester is no longer requesting power
+                    _ => {
+                        info!("({}): No-longer requesting power", requester.name());
+                        return Ok(());
+                    }
                 }
+            };
+
+            let demoted = self.is_provider_demoted(requester);
+            let requested_power_capability = if demoted {
+                ProviderPowerCapability {
+                    capability: self.config.provider_fallback,
+                    flags: requested_power_capability.flags,
+                }
+            } else {
+                requested_power_capability
+            };
+
+            // Determine total requested power draw
+            // Power reserved for temporary loads counts against the provider budget
+            let mut total_power_mw = self.state.reserved_power_mw;
+            for psu in self.registration.psus() {
+                let target_provider_cap = if ptr::eq(*psu, requester) {
+                    // Use the requester's requested power capability
+                    // this handles both new connections and upgrade requests
+                    Some(requested_power_capability)
+                } else {
+                    // Use the device's current working provider capability
+                    psu.lock().await.state().connected_provider_capability()
+                };
+                total_power_mw += target_provider_cap.map_or(0, |cap| cap.capability.max_power_mw());
             }
-        };
 
-        // Determine total requested power draw
-        let mut total_power_mw = 0;
-        for psu in self.registration.psus() {
-            let target_provider_cap = if ptr::eq(*psu, requester) {
-                // Use the requester's requested power capability
-                // this handles both new connections and upgrade requests
-                Some(requested_power_capability)
+            if total_power_mw > self.config.limited_power_threshold_mw {
+                self.state.current_provider_state.state = PowerState::Limited;
             } else {
-                // Use the device's current working provider capability
-                psu.lock().await.state().connected_provider_capability()
+                self.state.current_provider_state.state = PowerState::Unlimited;
+            }
+
+            debug!("New power state: {:?}", self.state.current_provider_state.state);
+
+            let target_power = match self.state.current_provider_state.state {
+                PowerState::Limited if !demoted => ProviderPowerCapability {
+                    capability: self.config.provider_limited,
+                    flags: requested_power_capability.flags,
+                },
+                // Demoted providers never exceed the fallback capability
+                PowerState::Limited => requested_power_capability,
+                PowerState::Unlimited => {
+                    if requested_power_capability.capability.max_power_mw()
+                        < self.config.provider_unlimited.max_power_mw()
+                    {
+                        // Don't auto upgrade to a higher contract
+                        requested_power_capability
+                    } else {
+                        ProviderPowerCapability {
+                            capability: self.config.provider_unlimited,
+                            flags: requested_power_capability.flags,
+                        }
+                    }
+                }
             };
-            total_power_mw += target_provider_cap.map_or(0, |cap| cap.capability.max_power_mw());
-        }
 
-        if total_power_mw > self.config.limited_power_threshold_mw {
-            self.state.current_provider_state.state = PowerState::Limited;
-        } else {
-            self.state.current_provider_state.state = PowerState::Unlimited;
+            let mut locked_requester = requester.lock().await;
+            if let e @ Err(_) = locked_requester.state().can_connect_provider() {
+                error!(
+                    "({}): Cannot provide, device is in state {:#?}",
+                    locked_requester.name(),
+                    locked_requester.state().psu_state
+                );
+                return e;
+            }
+
+            if let Err(e) = locked_requester.connect_provider(target_power).await {
+                let name = locked_requester.name();
+                drop(locked_requester);
+                error!("({}): Failed to connect as provider: {:?}", name, e);
+                if self.record_provider_failure(requester, name) {
+                    // Retry once at the fallback capability instead of repeating the full capability
+                    continue;
+                }
+                return Err(e);
+            }
+
+            drop(locked_requester);
+            self.post_provider_connected(requester, target_power);
+            return Ok(());
         }
+    }
 
-        debug!("New power state: {:?}", self.state.current_provider_state.state);
-
-        let target_power = match self.state.current_provider_state.state {
-            PowerState::Limited => ProviderPowerCapability {
-                capability: self.config.provider_limited,
-                flags: requested_power_capability.flags,
-            },
-            PowerState::Unlimited => {
-                if requested_power_capability.capability.max_power_mw() < self.config.provider_unlimited.max_power_mw()
-                {
-                    // Don't auto upgrade to a higher contract
-                    requested_power_capability
-                } else {
-                    ProviderPowerCapability {
-                        capability: self.config.provider_unlimited,
-                        flags: requested_power_capability.flags,
-                    }
+    /// Process a provider that failed to maintain its contract
+    pub(super) async fn process_provider_contract_failed(&mut self, requester: &'device Reg::Psu) -> Result<(), Error> {
+        let name = requester.lock().await.name();
+        info!("({}): Received provider contract failed", name);
+        self.record_provider_failure(requester, name);
+        self.connect_provider(requester).await
+    }
+
+    /// Returns true if the provider has been demoted to the fallback capability
+    fn is_provider_demoted(&self, psu: &'device Reg::Psu) -> bool {
+        let threshold = self.config.provider_failure_threshold;
+        threshold != 0
+            && self
+                .state
+                .provider_failures
+                .get(&(psu as *const Reg::Psu as usize))
+                .is_some_and(|failures| *failures >= threshold)
+    }
+
+    /// Count a provider failure
+    ///
+    /// Returns true if this failure demoted the provider to the fallback capability.
+    fn record_provider_failure(&mut self, psu: &'device Reg::Psu, name: &str) -> bool {
+        let key = psu as *const Reg::Psu as usize;
+        let failures = match self.state.provider_failures.get_mut(&key) {
+            Some(failures) => {
+                *failures = failures.saturating_add(1);
+                *failures
+            }
+            None => {
+                if self.state.provider_failures.insert(key, 1).is_err() {
+                    error!("Tracked provider failures map is full");
+                    return false;
                 }
+                1
             }
         };
 
-        let mut locked_requester = requester.lock().await;
-        if let e @ Err(_) = locked_requester.state().can_connect_provider() {
-            error!(
-                "({}): Cannot provide, device is in state {:#?}",
-                locked_requester.name(),
-                locked_requester.state().psu_state
-            );
-            e
-        } else {
-            locked_requester.connect_provider(target_power).await?;
-            self.post_provider_connected(requester, target_power);
-            Ok(())
+        if failures != self.config.provider_failure_threshold {
+            debug!("({}): Provider failure count {}", name, failures);
+            return false;
         }
+
+        warn!(
+            "({}): Demoting provider to {:?} after {} failures",
+            name, self.config.provider_fallback, failures
+        );
+        self.broadcast_event(ServiceEvent::ProviderDemoted(psu));
+        true
     }
 
     /// Common logic for after a provider has successfully connected
@@ -126,7 +205,7 @@ impl<'device, Reg: Registration<'device>, Customization: customization::Customiz
             .remove(&(psu as *const Reg::Psu as usize))
         {
             // Determine total requested power draw
-            let mut total_power_mw = 0;
+            let mut total_power_mw = self.state.reserved_power_mw;
             for psu in self.registration.psus() {
                 let target_provider_cap = psu.lock().await.state().connected_provider_capability();
                 total_power_mw += target_provider_cap.map_or(0, |cap| cap.capability.max_power_mw());
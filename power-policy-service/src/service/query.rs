@@ -0,0 +1,117 @@
+//! Power policy state queries over comms
+//!
+//! Services that don't share the power policy lock request the state by sending a [`PolicyStateRequest`] to
+//! [`Internal::Power`]. [`query_task`](super::task::query_task) answers each request with a [`PolicyStateResponse`]
+//! sent back to the requesting endpoint. Requests sent with [`Priority::High`] are answered ahead of queued routine
+//! requests.
+use embassy_futures::select::{Either, select};
+use embassy_sync::channel::Channel;
+use embedded_services::comms::{self, EndpointID, Internal, MailboxDelegate, MailboxDelegateError, Message, Priority};
+use embedded_services::{GlobalRawMutex, intrusive_list};
+use power_policy_interface::service::{PolicyStateRequest, PolicyStateResponse};
+
+/// Number of requests that can be waiting for a response
+const REQUEST_QUEUE_SIZE: usize = 4;
+
+/// Comms endpoint receiving power policy state requests
+pub struct PolicyStateQueries {
+    tp: comms::Endpoint,
+    /// Endpoints waiting for a response
+    requests: Channel<GlobalRawMutex, EndpointID, REQUEST_QUEUE_SIZE>,
+    /// Endpoints waiting for a response to a high priority request
+    priority_requests: Channel<GlobalRawMutex, EndpointID, REQUEST_QUEUE_SIZE>,
+}
+
+impl PolicyStateQueries {
+    /// Create a new, unregistered endpoint
+    pub const fn new() -> Self {
+        Self {
+            tp: comms::Endpoint::uninit(EndpointID::Internal(Internal::Power)),
+            requests: Channel::new(),
+            priority_requests: Channel::new(),
+        }
+    }
+
+    /// Register the endpoint with comms
+    pub async fn register(&'static self) -> Result<(), intrusive_list::Error> {
+        comms::register_endpoint(self, &self.tp).await
+    }
+
+    /// Wait for a request, returns the endpoint to respond to
+    pub(crate) async fn wait_request(&self) -> EndpointID {
+        // select polls the high priority queue first
+        match select(self.priority_requests.receive(), self.requests.receive()).await {
+            Either::First(from) | Either::Second(from) => from,
+        }
+    }
+
+    /// Send a response to a requesting endpoint
+    pub(crate) async fn respond(&self, to: EndpointID, response: &PolicyStateResponse) {
+        let _ = self.tp.send(to, response).await;
+    }
+}
+
+impl Default for PolicyStateQueries {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolicyStateQueries {
+    fn queue(
+        queue: &Channel<GlobalRawMutex, EndpointID, REQUEST_QUEUE_SIZE>,
+        message: &Message,
+    ) -> Result<(), MailboxDelegateError> {
+        if !message.data.is_a::<PolicyStateRequest>() {
+            return Err(MailboxDelegateError::MessageNotFound);
+        }
+
+        queue
+            .try_send(message.from)
+            .map_err(|_| MailboxDelegateError::BufferFull)
+    }
+}
+
+impl MailboxDelegate for PolicyStateQueries {
+    fn receive(&self, message: &Message) -> Result<(), MailboxDelegateError> {
+        Self::queue(&self.requests, message)
+    }
+
+    fn receive_priority(&self, message: &Message) -> Result<(), MailboxDelegateError> {
+        Self::queue(&self.priority_requests, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_services::comms::{Data, Metadata};
+
+    use super::*;
+
+    fn request(from: Internal, priority: Priority) -> Message<'static> {
+        Message {
+            from: from.into(),
+            to: Internal::Power.into(),
+            data: Data::new(&PolicyStateRequest),
+            priority,
+            metadata: Metadata::next(),
+        }
+    }
+
+    #[tokio::test]
+    async fn high_priority_request_answered_first() {
+        let queries = PolicyStateQueries::new();
+
+        assert!(queries.receive(&request(Internal::Battery, Priority::Normal)).is_ok());
+        assert!(queries.receive(&request(Internal::Usbc, Priority::Normal)).is_ok());
+        assert!(
+            queries
+                .receive_priority(&request(Internal::Thermal, Priority::High))
+                .is_ok()
+        );
+
+        assert_eq!(queries.wait_request().await, Internal::Thermal.into());
+        assert_eq!(queries.wait_request().await, Internal::Battery.into());
+        assert_eq!(queries.wait_request().await, Internal::Usbc.into());
+    }
+}
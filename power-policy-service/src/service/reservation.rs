@@ -0,0 +1,291 @@
+//! Temporary system power reservations.
+//!
+//! Subsystems with short peak loads (camera flash, LTE transmit bursts, etc.) reserve power for a limited time through
+//! [`PowerReservations`]. [`reservation_task`](super::task::reservation_task) forwards the total reserved power to
+//! [`Service::set_reserved_power`], which lowers the charger input by the reservation and counts it against
+//! [`limited_power_threshold_mw`](super::config::Config::limited_power_threshold_mw) so that providers are limited
+//! while it's active. This keeps the system within the budget of small adapters. Reservations are released
+//! explicitly or when they expire.
+//!
+//! ```ignore
+//! static RESERVATIONS: PowerReservations = PowerReservations::new();
+//!
+//! spawner.must_spawn(reservation_task(&RESERVATIONS, &POLICY));
+//!
+//! let id = RESERVATIONS.reserve(3000, Duration::from_millis(500))?;
+//! // Burst
+//! RESERVATIONS.release(id);
+//! ```
+use core::cell::RefCell;
+
+use embassy_futures::select::select;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_services::GlobalRawMutex;
+use power_policy_interface::capability::ConsumerPowerCapability;
+
+use super::*;
+
+/// Maximum number of simultaneous reservations
+pub const MAX_RESERVATIONS: usize = 8;
+
+/// Reservation identifier, used to release a reservation early
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReservationId(u16);
+
+/// Reservation error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReservationError {
+    /// [`MAX_RESERVATIONS`] reservations are already active
+    Full,
+}
+
+#[derive(Copy, Clone)]
+struct Reservation {
+    id: ReservationId,
+    power_mw: u32,
+    expires_at: Instant,
+}
+
+struct ReservationState {
+    reservations: heapless::Vec<Reservation, MAX_RESERVATIONS>,
+    next_id: u16,
+}
+
+/// Active power reservations
+pub struct PowerReservations {
+    state: Mutex<GlobalRawMutex, RefCell<ReservationState>>,
+    changed: Signal<GlobalRawMutex, ()>,
+}
+
+impl PowerReservations {
+    /// Create a new instance with no reservations
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(ReservationState {
+                reservations: heapless::Vec::new(),
+                next_id: 0,
+            })),
+            changed: Signal::new(),
+        }
+    }
+
+    /// Reserve `power_mw` for `duration`
+    pub fn reserve(&self, power_mw: u32, duration: Duration) -> Result<ReservationId, ReservationError> {
+        let now = Instant::now();
+        let id = self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.reservations.retain(|reservation| reservation.expires_at > now);
+
+            let id = ReservationId(state.next_id);
+            state
+                .reservations
+                .push(Reservation {
+                    id,
+                    power_mw,
+                    expires_at: now.checked_add(duration).unwrap_or(Instant::MAX),
+                })
+                .map_err(|_| ReservationError::Full)?;
+            state.next_id = state.next_id.wrapping_add(1);
+            Ok(id)
+        })?;
+
+        info!("Reserved {} mW for {} ms: {:?}", power_mw, duration.as_millis(), id);
+        self.changed.signal(());
+        Ok(id)
+    }
+
+    /// Release a reservation before it expires
+    ///
+    /// Returns false if the reservation already expired or doesn't exist.
+    pub fn release(&self, id: ReservationId) -> bool {
+        let released = self.state.lock(|state| {
+            let reservations = &mut state.borrow_mut().reservations;
+            let len = reservations.len();
+            reservations.retain(|reservation| reservation.id != id);
+            reservations.len() != len
+        });
+
+        if released {
+            info!("Released reservation {:?}", id);
+            self.changed.signal(());
+        }
+        released
+    }
+
+    /// Returns the total power reserved at the current time
+    pub fn total_mw(&self) -> u32 {
+        self.expire(Instant::now()).0
+    }
+
+    /// Drop reservations expired at `now`, returns the total reserved power and the next expiry, if any
+    fn expire(&self, now: Instant) -> (u32, Option<Instant>) {
+        self.state.lock(|state| {
+            let reservations = &mut state.borrow_mut().reservations;
+            reservations.retain(|reservation| reservation.expires_at > now);
+            let total_mw = reservations
+                .iter()
+                .fold(0u32, |total, reservation| total.saturating_add(reservation.power_mw));
+            let next_expiry = reservations.iter().map(|reservation| reservation.expires_at).min();
+            (total_mw, next_expiry)
+        })
+    }
+
+    /// Wait until a reservation is added, released or expires, returns the new total reserved power
+    pub async fn wait_total_mw(&self) -> u32 {
+        match self.expire(Instant::now()).1 {
+            Some(expires_at) => {
+                select(self.changed.wait(), Timer::at(expires_at)).await;
+            }
+            None => self.changed.wait().await,
+        }
+        self.total_mw()
+    }
+}
+
+impl Default for PowerReservations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'device, Reg: Registration<'device>, Customization: customization::Customization>
+    Service<'device, Reg, Customization>
+{
+    /// Set the total power reserved for temporary loads
+    ///
+    /// The reservation is subtracted from the charger input and counted against the provider power budget, connected
+    /// chargers and providers are updated immediately. Chargers inhibited by battery charge control only cache the
+    /// new capability.
+    pub async fn set_reserved_power(&mut self, reserved_mw: u32) -> Result<(), Error> {
+        if reserved_mw == self.state.reserved_power_mw {
+            return Ok(());
+        }
+
+        info!("Reserved power: {} mW", reserved_mw);
+        self.state.reserved_power_mw = reserved_mw;
+        self.publish_state_snapshot();
+
+        if let Some(current_consumer) = self.state.current_consumer_state {
+            let capability = self.charger_capability(current_consumer.consumer_power_capability);
+            for charger in self.registration.chargers() {
+                let mut locked_charger = charger.lock().await;
+                if !locked_charger.state().is_unpowered() {
+                    consumer::attach_charger(&mut *locked_charger, capability).await?;
+                }
+            }
+        }
+
+        let mut providers: heapless::Vec<&'device Reg::Psu, MAX_CONNECTED_PROVIDERS> = heapless::Vec::new();
+        for psu in self.registration.psus() {
+            if self
+                .state
+                .connected_providers
+                .contains(&(*psu as *const Reg::Psu as usize))
+            {
+                // Can't fail, the vec is as large as the connected provider set
+                let _ = providers.push(*psu);
+            }
+        }
+
+        for psu in providers {
+            self.connect_provider(psu).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the consumer capability passed to chargers, reduced by the reserved power and capped by the thermal limit
+    pub(super) fn charger_capability(&self, capability: ConsumerPowerCapability) -> ConsumerPowerCapability {
+        let mut available_mw = capability
+            .capability
+            .max_power_mw()
+            .saturating_sub(self.state.reserved_power_mw);
+        if let Some(limit_mw) = self.state.consumer_thermal_limit_mw {
+            available_mw = available_mw.min(limit_mw);
+        }
+        limit_capability(capability, available_mw)
+    }
+}
+
+/// Reduce the current of `capability` so that it provides at most `max_mw`
+fn limit_capability(mut capability: ConsumerPowerCapability, max_mw: u32) -> ConsumerPowerCapability {
+    if max_mw >= capability.capability.max_power_mw() {
+        return capability;
+    }
+
+    capability.capability.current_ma = (max_mw * 1000)
+        .checked_div(u32::from(capability.capability.voltage_mv))
+        .and_then(|current_ma| u16::try_from(current_ma).ok())
+        .unwrap_or(0);
+    capability
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use power_policy_interface::capability::{ConsumerFlags, PowerCapability};
+
+    #[test]
+    fn test_expire() {
+        let reservations = PowerReservations::new();
+        let start = Instant::now();
+        let first = reservations.reserve(1000, Duration::from_secs(10)).unwrap();
+        reservations.reserve(2000, Duration::from_secs(20)).unwrap();
+
+        assert_eq!(reservations.expire(start).0, 3000);
+        let (total_mw, next_expiry) = reservations.expire(start + Duration::from_secs(15));
+        assert_eq!(total_mw, 2000);
+        assert!(next_expiry.unwrap() >= start + Duration::from_secs(20));
+
+        assert!(!reservations.release(first));
+        assert_eq!(reservations.expire(start + Duration::from_secs(30)), (0, None));
+    }
+
+    #[test]
+    fn test_release() {
+        let reservations = PowerReservations::new();
+        let id = reservations.reserve(1000, Duration::from_secs(60)).unwrap();
+        assert_eq!(reservations.total_mw(), 1000);
+        assert!(reservations.release(id));
+        assert!(!reservations.release(id));
+        assert_eq!(reservations.total_mw(), 0);
+    }
+
+    #[test]
+    fn test_full() {
+        let reservations = PowerReservations::new();
+        for _ in 0..MAX_RESERVATIONS {
+            reservations.reserve(100, Duration::from_secs(60)).unwrap();
+        }
+        assert_eq!(
+            reservations.reserve(100, Duration::from_secs(60)),
+            Err(ReservationError::Full)
+        );
+    }
+
+    #[test]
+    fn test_limit_capability() {
+        let capability = ConsumerPowerCapability {
+            capability: PowerCapability {
+                voltage_mv: 20000,
+                current_ma: 3000,
+            },
+            flags: ConsumerFlags::none(),
+        };
+
+        // Limits at or above the capability leave it untouched
+        assert_eq!(limit_capability(capability, 60000), capability);
+        assert_eq!(limit_capability(capability, 80000), capability);
+
+        let limited = limit_capability(capability, 30000);
+        assert_eq!(limited.capability.voltage_mv, 20000);
+        assert_eq!(limited.capability.current_ma, 1500);
+        assert_eq!(limited.flags, capability.flags);
+        assert_eq!(limit_capability(capability, 0).capability.current_ma, 0);
+    }
+}
@@ -0,0 +1,59 @@
+//! Power policy state snapshot for the debug service
+//!
+//! The snapshot is published to a [`RetainedState`] whenever the policy state changes, register it with a
+//! [`StateProvider`](embedded_services::service_state::StateProvider) under
+//! [`id::POWER_POLICY`](embedded_services::service_state::id::POWER_POLICY). Fields are little-endian:
+//!
+//! | Offset | Size | Field                                                       |
+//! |--------|------|-------------------------------------------------------------|
+//! | 0      | 1    | Registration index of the current consumer, `0xff` if none  |
+//! | 1      | 4    | Maximum power of the current consumer in mW                 |
+//! | 5      | 1    | Number of connected providers                               |
+//! | 6      | 1    | Unconstrained, `1` if true                                  |
+//! | 7      | 1    | Number of available unconstrained devices                   |
+//! | 8      | 4    | Thermal input power limit in mW, `0xffffffff` if none       |
+//! | 12     | 4    | Reserved power in mW                                        |
+//! | 16     | 1    | Number of attached docks                                    |
+//! | 17     | 4    | Bitmask of PSUs disabled as consumers                       |
+use super::*;
+
+impl<'device, Reg: Registration<'device>, Customization: customization::Customization>
+    Service<'device, Reg, Customization>
+{
+    /// Publish state snapshots to `snapshot`, the current state is published immediately
+    pub fn set_state_snapshot(&mut self, snapshot: &'device RetainedState) {
+        self.state_snapshot = Some(snapshot);
+        self.publish_state_snapshot();
+    }
+
+    /// Publish the current state to the snapshot, if any
+    pub(super) fn publish_state_snapshot(&self) {
+        let Some(snapshot) = self.state_snapshot else {
+            return;
+        };
+
+        let consumer = self.state.current_consumer_state;
+        let consumer_index = consumer
+            .and_then(|consumer| self.psu_index(consumer.psu))
+            .and_then(|index| u8::try_from(index).ok())
+            .unwrap_or(u8::MAX);
+        let consumer_power_mw = consumer.map_or(0, |consumer| {
+            consumer.consumer_power_capability.capability.max_power_mw()
+        });
+        let unconstrained = self.unconstrained.try_get().unwrap_or_default();
+
+        snapshot.publish(|writer| {
+            writer
+                .put(&[consumer_index])
+                .put(&consumer_power_mw.to_le_bytes())
+                .put(&[u8::try_from(self.state.connected_providers.len()).unwrap_or(u8::MAX)])
+                .put(&[u8::from(unconstrained.unconstrained)])
+                .put(&[u8::try_from(unconstrained.available).unwrap_or(u8::MAX)])
+                .put(&self.state.consumer_thermal_limit_mw.unwrap_or(u32::MAX).to_le_bytes())
+                .put(&self.state.reserved_power_mw.to_le_bytes())
+                .put(&[self.state.docked.docks])
+                .put(&self.state.disabled_psus.to_le_bytes())
+                .finish()
+        });
+    }
+}
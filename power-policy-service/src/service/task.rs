@@ -1,11 +1,17 @@
-use embedded_services::{error, info, sync::Lockable};
+use embedded_services::{error, info, sync::Lockable, trace};
 
-use embedded_services::event::Receiver;
+use embassy_time::{Duration, Timer};
+use embedded_services::event::{NonBlockingSender, Receiver};
 use power_policy_interface::charger;
 use power_policy_interface::psu::event::EventData;
+use power_policy_interface::telemetry::{PowerSensor, SharedSystemPower, SystemPower};
+use power_policy_interface::thermal::ConsumerThermalLimit;
 
 use crate::service::customization;
+use crate::service::query::PolicyStateQueries;
 use crate::service::registration::Registration;
+use crate::service::reservation::PowerReservations;
+use crate::service::telemetry::PowerSensors;
 
 use super::Service;
 
@@ -84,3 +90,104 @@ pub async fn task<
         }
     }
 }
+
+/// Runs the system power telemetry task.
+///
+/// Aggregates the sensors every
+/// [`Config::telemetry_interval_ms`](crate::service::config::Config::telemetry_interval_ms), stores the result in
+/// `shared` for host reporting and publishes it through `publisher`. Telemetry is periodic and only interests a few
+/// listeners, so it's published on a dedicated channel rather than broadcast to every power policy listener.
+pub async fn telemetry_task<
+    'device,
+    S: Lockable<Inner = Service<'device, Reg, Customization>>,
+    Reg: Registration<'device>,
+    Customization: customization::Customization,
+    Adapter: PowerSensor,
+    Battery: PowerSensor,
+    Rail: PowerSensor,
+    const RAIL_COUNT: usize,
+    Publisher: NonBlockingSender<SystemPower>,
+>(
+    mut sensors: PowerSensors<Adapter, Battery, Rail, RAIL_COUNT>,
+    shared: &SharedSystemPower,
+    mut publisher: Publisher,
+    policy: &'device S,
+) -> ! {
+    info!("Starting power policy telemetry task");
+    let interval = Duration::from_millis(policy.lock().await.config.telemetry_interval_ms.into());
+    loop {
+        Timer::after(interval).await;
+
+        let power = sensors.read().await;
+        trace!("System power: {} mW", power.psys_mw());
+        shared.set(power);
+        if publisher.try_send(power).is_none() {
+            error!("Failed to publish system power telemetry");
+        }
+    }
+}
+
+/// Runs the power reservation task.
+///
+/// Applies the total reserved power to the policy whenever a reservation is added, released or expires.
+pub async fn reservation_task<
+    'device,
+    S: Lockable<Inner = Service<'device, Reg, Customization>>,
+    Reg: Registration<'device>,
+    Customization: customization::Customization,
+>(
+    reservations: &'device PowerReservations,
+    policy: &'device S,
+) -> ! {
+    info!("Starting power reservation task");
+    loop {
+        let reserved_mw = reservations.wait_total_mw().await;
+
+        if let Err(e) = policy.lock().await.set_reserved_power(reserved_mw).await {
+            error!("Error applying power reservation: {:?}", e);
+        }
+    }
+}
+
+/// Runs the consumer thermal limit task.
+///
+/// Applies the consumer input power limit requested by thermal policy.
+pub async fn thermal_limit_task<
+    'device,
+    S: Lockable<Inner = Service<'device, Reg, Customization>>,
+    Reg: Registration<'device>,
+    Customization: customization::Customization,
+>(
+    limit: &'device ConsumerThermalLimit,
+    policy: &'device S,
+) -> ! {
+    info!("Starting consumer thermal limit task");
+    loop {
+        let limit_mw = limit.wait().await;
+
+        if let Err(e) = policy.lock().await.set_consumer_thermal_limit(limit_mw).await {
+            error!("Error applying consumer thermal limit: {:?}", e);
+        }
+    }
+}
+
+/// Runs the power policy state query task.
+///
+/// Answers the [`PolicyStateRequest`](power_policy_interface::service::PolicyStateRequest)s received by `queries`.
+pub async fn query_task<
+    'device,
+    S: Lockable<Inner = Service<'device, Reg, Customization>>,
+    Reg: Registration<'device>,
+    Customization: customization::Customization,
+>(
+    queries: &'device PolicyStateQueries,
+    policy: &'device S,
+) -> ! {
+    info!("Starting power policy query task");
+    loop {
+        let from = queries.wait_request().await;
+
+        let state = policy.lock().await.policy_state().await;
+        queries.respond(from, &state.response()).await;
+    }
+}
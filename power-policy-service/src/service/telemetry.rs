@@ -0,0 +1,39 @@
+//! System power (PSYS) telemetry aggregation
+use embedded_services::error;
+use power_policy_interface::telemetry::{PowerSensor, SystemPower};
+
+/// Sensors combined into the system power figure
+pub struct PowerSensors<Adapter: PowerSensor, Battery: PowerSensor, Rail: PowerSensor, const RAIL_COUNT: usize> {
+    /// Adapter input power
+    pub adapter: Adapter,
+    /// Battery discharge power
+    pub battery: Battery,
+    /// Individual rail power
+    pub rails: [Rail; RAIL_COUNT],
+}
+
+/// Read a sensor, a failed sensor contributes nothing to the total
+async fn read_or_zero<S: PowerSensor>(sensor: &mut S, name: &str) -> u32 {
+    sensor.power_mw().await.unwrap_or_else(|e| {
+        error!("Failed to read {} power: {:?}", name, e);
+        0
+    })
+}
+
+impl<Adapter: PowerSensor, Battery: PowerSensor, Rail: PowerSensor, const RAIL_COUNT: usize>
+    PowerSensors<Adapter, Battery, Rail, RAIL_COUNT>
+{
+    /// Read all sensors
+    pub async fn read(&mut self) -> SystemPower {
+        let mut rails_mw: u32 = 0;
+        for rail in self.rails.iter_mut() {
+            rails_mw = rails_mw.saturating_add(read_or_zero(rail, "rail").await);
+        }
+
+        SystemPower {
+            adapter_mw: read_or_zero(&mut self.adapter, "adapter").await,
+            battery_discharge_mw: read_or_zero(&mut self.battery, "battery").await,
+            rails_mw,
+        }
+    }
+}
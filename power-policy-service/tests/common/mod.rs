@@ -14,9 +14,10 @@ use embassy_sync::{
 };
 use embassy_time::{Duration, with_timeout};
 use embedded_services::GlobalRawMutex;
+use odp_test_support as _;
 use power_policy_interface::psu::event::EventData;
 use power_policy_interface::{
-    capability::{ConsumerDisconnect, ConsumerPowerCapability, PowerCapability, ProviderPowerCapability},
+    capability::{ConsumerDisconnect, ConsumerPowerCapability, PowerCapability, ProviderPowerCapability, PsuType},
     service::{UnconstrainedState, event::Event as ServiceEvent},
 };
 use power_policy_interface_test_mocks::charger::ChargerType;
@@ -185,6 +186,18 @@ pub async fn assert_consumer_connected<'a>(
     assert_eq!(capability, expected_capability);
 }
 
+pub async fn assert_input_source_switched<'a>(
+    receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+    expected_device: &DeviceType<'a>,
+    expected_psu_type: PsuType,
+) {
+    let ServiceEvent::InputSourceSwitched(device, psu_type) = receiver.receive().await else {
+        panic!("Expected InputSourceSwitched event");
+    };
+    assert_eq!(device as *const _, expected_device as *const _);
+    assert_eq!(psu_type, expected_psu_type);
+}
+
 pub async fn assert_provider_disconnected<'a>(
     receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
     expected_device: &DeviceType<'a>,
@@ -207,6 +220,16 @@ pub async fn assert_provider_connected<'a>(
     assert_eq!(capability, expected_capability);
 }
 
+pub async fn assert_provider_demoted<'a>(
+    receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+    expected_device: &DeviceType<'a>,
+) {
+    let ServiceEvent::ProviderDemoted(device) = receiver.receive().await else {
+        panic!("Expected ProviderDemoted event");
+    };
+    assert_eq!(device as *const _, expected_device as *const _);
+}
+
 pub async fn assert_unconstrained<'a>(
     receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
     expected_state: UnconstrainedState,
@@ -4,15 +4,16 @@ use embedded_services::info;
 use embedded_services::sync::Lockable;
 use power_policy_interface::capability::ProviderFlags;
 use power_policy_interface::capability::ProviderPowerCapability;
-use power_policy_interface::capability::{ConsumerDisconnect, ConsumerFlags, ConsumerPowerCapability};
+use power_policy_interface::capability::{ConsumerDisconnect, ConsumerFlags, ConsumerPowerCapability, PsuType};
 
 mod common;
 
 use common::{LOW_POWER, ServiceMutex};
+use power_policy_interface::dock::{DockInfo, DockedState};
 use power_policy_interface::psu::Psu;
 use power_policy_interface::service::event::Event as ServiceEvent;
 use power_policy_service::service::InternalState;
-use power_policy_service::service::config::Config;
+use power_policy_service::service::config::{Config, DockPriority, InputSourcePriority};
 use power_policy_service::service::consumer::AvailableConsumer;
 use power_policy_service::service::consumer::cmp_consumer_capability_default;
 use power_policy_service::service::consumer::find_best_consumer_default;
@@ -29,11 +30,12 @@ use crate::common::assert_provider_connected;
 use crate::common::assert_provider_disconnected;
 use crate::common::{
     DEFAULT_TIMEOUT, HIGH_POWER, assert_consumer_connected, assert_consumer_disconnected,
-    assert_consumer_disconnected_with_flags, run_test,
+    assert_consumer_disconnected_with_flags, assert_input_source_switched, run_test,
 };
 use power_policy_interface_test_mocks::psu::FnCall;
 
 const MIN_CONSUMER_THRESHOLD_MW: u32 = 7500;
+const DOCK_DOWNSTREAM_POWER_MW: u32 = 15000;
 
 /// Test the basic consumer flow with a single device.
 struct TestSingle;
@@ -82,6 +84,15 @@ impl Test for TestSingle {
 
             // Ensure consumer change doesn't affect provider power computation
             assert_eq!(service.lock().await.compute_total_provider_power_mw().await, 0);
+
+            let state = service.lock().await.policy_state().await;
+            assert!(state.providers.is_empty());
+            assert!(
+                state
+                    .consumer
+                    .is_some_and(|consumer| std::ptr::eq(consumer.psu, device0)
+                        && consumer.consumer_power_capability.capability == LOW_POWER)
+            );
         }
         // Test detach
         {
@@ -94,6 +105,7 @@ impl Test for TestSingle {
 
             // Ensure consumer change doesn't affect provider power computation
             assert_eq!(service.lock().await.compute_total_provider_power_mw().await, 0);
+            assert!(service.lock().await.policy_state().await.consumer.is_none());
         }
 
         assert_no_event(service_receiver);
@@ -945,6 +957,235 @@ impl Test for TestConsumerDisconnectRenegotiationFlag {
     }
 }
 
+/// Test that a thermal limit below the consumer's capability sets the thermally limited flag and broadcasts the
+/// updated capability.
+struct TestThermalLimit;
+
+impl Test for TestThermalLimit {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        _device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_thermal_limit");
+        device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+        device0
+            .lock()
+            .await
+            .simulate_consumer_connection(HIGH_POWER.into())
+            .await;
+
+        assert_consumer_connected(
+            service_receiver,
+            device0,
+            ConsumerPowerCapability {
+                capability: HIGH_POWER,
+                flags: ConsumerFlags::none(),
+            },
+        )
+        .await;
+        device0.lock().await.fn_calls.clear();
+
+        // Limit below the consumer's capability
+        service
+            .lock()
+            .await
+            .set_consumer_thermal_limit(Some(LOW_POWER.max_power_mw()))
+            .await
+            .unwrap();
+        assert_consumer_connected(
+            service_receiver,
+            device0,
+            ConsumerPowerCapability {
+                capability: HIGH_POWER,
+                flags: ConsumerFlags::none().with_thermally_limited(),
+            },
+        )
+        .await;
+
+        // Unchanged flag, nothing to broadcast
+        service
+            .lock()
+            .await
+            .set_consumer_thermal_limit(Some(LOW_POWER.max_power_mw()))
+            .await
+            .unwrap();
+        assert_no_event(service_receiver);
+
+        // Limit lifted
+        service.lock().await.set_consumer_thermal_limit(None).await.unwrap();
+        assert_consumer_connected(
+            service_receiver,
+            device0,
+            ConsumerPowerCapability {
+                capability: HIGH_POWER,
+                flags: ConsumerFlags::none(),
+            },
+        )
+        .await;
+
+        // The flag only affects broadcasts, the consumer stays connected
+        assert!(device0.lock().await.fn_calls.is_empty());
+        assert_no_event(service_receiver);
+    }
+}
+
+/// Test that a preferred DC jack adapter is selected over a higher powered type-C consumer.
+struct TestInputSourcePriority;
+
+impl Test for TestInputSourcePriority {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        _service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_input_source_priority");
+        let type_c = ConsumerPowerCapability {
+            capability: HIGH_POWER,
+            flags: ConsumerFlags::none().with_psu_type(PsuType::TypeC),
+        };
+        let dc_jack = ConsumerPowerCapability {
+            capability: LOW_POWER,
+            flags: ConsumerFlags::none().with_psu_type(PsuType::DcJack),
+        };
+
+        // Type-C consumer connects first
+        {
+            device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device0.lock().await.simulate_consumer_connection(type_c).await;
+
+            assert_consumer_connected(service_receiver, device0, type_c).await;
+            device0.lock().await.fn_calls.clear();
+        }
+        // Lower powered DC jack adapter takes over
+        {
+            device0.lock().await.next_result_disconnect.push_back(Ok(()));
+            device1.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device1.lock().await.simulate_consumer_connection(dc_jack).await;
+
+            assert_consumer_disconnected_with_flags(
+                service_receiver,
+                device0,
+                ConsumerDisconnect::none().with_switching(true),
+            )
+            .await;
+            assert_consumer_connected(service_receiver, device1, dc_jack).await;
+
+            assert_input_source_switched(service_receiver, device1, PsuType::DcJack).await;
+
+            assert_eq!(device0.lock().await.fn_calls.pop_front().unwrap(), FnCall::Disconnect);
+            assert_eq!(
+                device1.lock().await.fn_calls.pop_front().unwrap(),
+                FnCall::ConnectConsumer(dc_jack)
+            );
+        }
+        // Type-C consumer is restored when the adapter is removed
+        {
+            device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device1.lock().await.simulate_detach().await;
+
+            assert_consumer_disconnected(service_receiver, device1).await;
+            assert_consumer_connected(service_receiver, device0, type_c).await;
+            assert_input_source_switched(service_receiver, device0, PsuType::TypeC).await;
+        }
+
+        assert_no_event(service_receiver);
+    }
+}
+
+/// Test that a preferred dock is selected over a higher powered consumer and the docked state is broadcast.
+struct TestDockPriority;
+
+impl Test for TestDockPriority {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_dock_priority");
+        let high_power = ConsumerPowerCapability {
+            capability: HIGH_POWER,
+            flags: ConsumerFlags::none(),
+        };
+        let low_power = ConsumerPowerCapability {
+            capability: LOW_POWER,
+            flags: ConsumerFlags::none(),
+        };
+        let dock = DockInfo {
+            downstream_power_mw: DOCK_DOWNSTREAM_POWER_MW,
+        };
+
+        // Higher powered consumer connects first
+        {
+            device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device0.lock().await.simulate_consumer_connection(high_power).await;
+
+            assert_consumer_connected(service_receiver, device0, high_power).await;
+            device0.lock().await.fn_calls.clear();
+        }
+        // Lower powered consumer doesn't take over until it's identified as a dock
+        {
+            device1.lock().await.simulate_consumer_connection(low_power).await;
+            embassy_time::Timer::after(DEFAULT_PER_CALL_TIMEOUT).await;
+            assert!(device0.lock().await.fn_calls.is_empty());
+            assert!(device1.lock().await.fn_calls.is_empty());
+
+            device0.lock().await.next_result_disconnect.push_back(Ok(()));
+            device1.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device1.lock().await.simulate_dock_update(Some(dock)).await;
+
+            let docked = DockedState {
+                docks: 1,
+                downstream_power_mw: DOCK_DOWNSTREAM_POWER_MW,
+            };
+            assert_eq!(
+                service_receiver.receive().await,
+                ServiceEvent::DockedStateChanged(docked)
+            );
+            assert_consumer_disconnected_with_flags(
+                service_receiver,
+                device0,
+                ConsumerDisconnect::none().with_switching(true),
+            )
+            .await;
+            assert_consumer_connected(service_receiver, device1, low_power).await;
+
+            assert_eq!(device0.lock().await.fn_calls.pop_front().unwrap(), FnCall::Disconnect);
+            assert_eq!(
+                device1.lock().await.fn_calls.pop_front().unwrap(),
+                FnCall::ConnectConsumer(low_power)
+            );
+            assert_eq!(service.lock().await.docked_state(), docked);
+        }
+        // Undocking restores the higher powered consumer
+        {
+            device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device1.lock().await.simulate_detach().await;
+
+            assert_eq!(
+                service_receiver.receive().await,
+                ServiceEvent::DockedStateChanged(DockedState::default())
+            );
+            assert_consumer_disconnected(service_receiver, device1).await;
+            assert_consumer_connected(service_receiver, device0, high_power).await;
+        }
+
+        assert_no_event(service_receiver);
+    }
+}
+
 #[tokio::test]
 async fn run_test_swap_higher() {
     run_test(
@@ -1039,3 +1280,30 @@ async fn run_test_consumer_disconnect_renegotiation_flag() {
     )
     .await;
 }
+
+#[tokio::test]
+async fn run_test_thermal_limit() {
+    run_test(
+        DEFAULT_TIMEOUT,
+        TestThermalLimit,
+        Default::default(),
+        DefaultCustomization,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn run_test_input_source_priority() {
+    let mut config = Config::default();
+    config.input_source_priority = InputSourcePriority::PreferDcJack;
+
+    run_test(DEFAULT_TIMEOUT, TestInputSourcePriority, config, DefaultCustomization).await;
+}
+
+#[tokio::test]
+async fn run_test_dock_priority() {
+    let mut config = Config::default();
+    config.dock_priority = DockPriority::Prefer;
+
+    run_test(DEFAULT_TIMEOUT, TestDockPriority, config, DefaultCustomization).await;
+}
@@ -0,0 +1,91 @@
+#![allow(clippy::unwrap_used)]
+use embassy_futures::select::{Either, select};
+use embassy_sync::channel::DynamicReceiver;
+use embedded_services::info;
+use power_policy_interface::capability::{ConsumerFlags, ConsumerPowerCapability};
+use power_policy_interface::service::event::Event as ServiceEvent;
+use power_policy_service::service::customization::{CONSUMER_SELECTION_HOOK, ConsumerCandidates, DefaultCustomization};
+
+mod common;
+
+use common::{
+    DEFAULT_TIMEOUT, DeviceType, HIGH_POWER, LOW_POWER, ServiceMutex, Test, assert_consumer_connected,
+    assert_consumer_disconnected, run_test,
+};
+
+/// Hook that always consumes from the available PSU with the lowest index
+async fn lowest_index(candidates: ConsumerCandidates) -> Option<usize> {
+    candidates.available.iter().map(|candidate| candidate.psu_index).min()
+}
+
+/// Verify that the consumer selection hook overrides the consumer selected by the service
+struct TestConsumerSelectionHook;
+
+impl Test for TestConsumerSelectionHook {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        _service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        device1: &DeviceType<'a>,
+    ) {
+        info!("Running TestConsumerSelectionHook");
+        let runner = CONSUMER_SELECTION_HOOK.register().unwrap();
+
+        let test = async {
+            // Device1 connection at high power, the only consumer available
+            device1.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device1
+                .lock()
+                .await
+                .simulate_consumer_connection(HIGH_POWER.into())
+                .await;
+            assert_consumer_connected(
+                service_receiver,
+                device1,
+                ConsumerPowerCapability {
+                    capability: HIGH_POWER,
+                    flags: ConsumerFlags::none(),
+                },
+            )
+            .await;
+
+            // Device0 connection at low power, the service would stay on device1 but the hook switches to device0
+            device1.lock().await.next_result_disconnect.push_back(Ok(()));
+            device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+            device0
+                .lock()
+                .await
+                .simulate_consumer_connection(LOW_POWER.into())
+                .await;
+            assert_consumer_disconnected(service_receiver, device1).await;
+            assert_consumer_connected(
+                service_receiver,
+                device0,
+                ConsumerPowerCapability {
+                    capability: LOW_POWER,
+                    flags: ConsumerFlags::none(),
+                },
+            )
+            .await;
+        };
+
+        match select(runner.run(lowest_index), test).await {
+            Either::First(never) => match never {},
+            Either::Second(()) => {}
+        }
+    }
+}
+
+#[tokio::test]
+async fn run_test_consumer_selection_hook() {
+    run_test(
+        DEFAULT_TIMEOUT,
+        TestConsumerSelectionHook,
+        Default::default(),
+        DefaultCustomization,
+    )
+    .await;
+}
@@ -1,4 +1,6 @@
 #![allow(clippy::unwrap_used)]
+use std::ptr;
+
 use embassy_sync::channel::DynamicReceiver;
 use embedded_services::info;
 use power_policy_interface::capability::ProviderFlags;
@@ -6,7 +8,7 @@ use power_policy_interface::capability::ProviderPowerCapability;
 
 mod common;
 
-use common::{LOW_POWER, ServiceMutex};
+use common::{LOW_POWER, MINIMAL_POWER, ServiceMutex};
 use power_policy_interface::service::event::Event as ServiceEvent;
 use power_policy_service::service::customization::DefaultCustomization;
 
@@ -14,6 +16,7 @@ use crate::common::DeviceType;
 use crate::common::HIGH_POWER;
 use crate::common::Test;
 use crate::common::assert_no_event;
+use crate::common::assert_provider_demoted;
 use crate::common::{DEFAULT_TIMEOUT, assert_provider_connected, assert_provider_disconnected, run_test};
 use power_policy_interface_test_mocks::psu::FnCall;
 
@@ -144,6 +147,16 @@ impl Test for TestUpgrade {
             }
 
             assert_eq!(service.lock().await.compute_total_provider_power_mw().await, 22500);
+
+            let state = service.lock().await.policy_state().await;
+            assert!(state.consumer.is_none());
+            assert!(matches!(
+                state.providers.as_slice(),
+                [p0, p1] if ptr::eq(p0.psu, device0)
+                    && p0.capability.capability == HIGH_POWER
+                    && ptr::eq(p1.psu, device1)
+                    && p1.capability.capability == LOW_POWER
+            ));
         }
 
         {
@@ -191,6 +204,12 @@ impl Test for TestUpgrade {
             assert!(device0.lock().await.fn_calls.is_empty());
 
             assert_eq!(service.lock().await.compute_total_provider_power_mw().await, 7500);
+
+            let state = service.lock().await.policy_state().await;
+            assert!(matches!(
+                state.providers.as_slice(),
+                [p1] if ptr::eq(p1.psu, device1) && p1.capability.capability == LOW_POWER
+            ));
         }
 
         {
@@ -290,6 +309,128 @@ impl Test for TestDisconnect {
     }
 }
 
+/// Test demoting a provider to the fallback capability after repeated contract failures
+struct TestDemote;
+
+impl Test for TestDemote {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        _device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_demote");
+        let high_power = ProviderPowerCapability {
+            capability: HIGH_POWER,
+            flags: ProviderFlags::none(),
+        };
+        let fallback_power = ProviderPowerCapability {
+            capability: MINIMAL_POWER,
+            flags: ProviderFlags::none(),
+        };
+
+        device0.lock().await.next_result_connect_provider.push_back(Ok(()));
+        device0.lock().await.simulate_provider_connection(HIGH_POWER).await;
+        assert_provider_connected(service_receiver, device0, high_power).await;
+
+        // Failures below the threshold retry the full capability
+        for _ in 0..2 {
+            device0.lock().await.next_result_connect_provider.push_back(Ok(()));
+            device0.lock().await.simulate_provider_contract_failure().await;
+            assert_provider_connected(service_receiver, device0, high_power).await;
+        }
+
+        // Reaching the threshold demotes the provider
+        device0.lock().await.next_result_connect_provider.push_back(Ok(()));
+        device0.lock().await.simulate_provider_contract_failure().await;
+        assert_provider_demoted(service_receiver, device0).await;
+        assert_provider_connected(service_receiver, device0, fallback_power).await;
+        assert_eq!(service.lock().await.compute_total_provider_power_mw().await, 2500);
+
+        {
+            let mut device = device0.lock().await;
+            for _ in 0..3 {
+                assert_eq!(
+                    device.fn_calls.pop_front().unwrap(),
+                    FnCall::ConnectProvider(high_power)
+                );
+            }
+            assert_eq!(
+                device.fn_calls.pop_front().unwrap(),
+                FnCall::ConnectProvider(fallback_power)
+            );
+            assert!(device.fn_calls.is_empty());
+        }
+
+        // Failures are forgotten on detach
+        device0.lock().await.simulate_detach().await;
+        assert_provider_disconnected(service_receiver, device0).await;
+
+        device0.lock().await.next_result_connect_provider.push_back(Ok(()));
+        device0.lock().await.simulate_provider_connection(HIGH_POWER).await;
+        assert_provider_connected(service_receiver, device0, high_power).await;
+
+        assert_no_event(service_receiver);
+    }
+}
+
+/// Test that reserved power limits connected providers until it's released.
+struct TestReservation;
+
+impl Test for TestReservation {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        _device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_reservation");
+        let high_power = ProviderPowerCapability {
+            capability: HIGH_POWER,
+            flags: ProviderFlags::none(),
+        };
+        let low_power = ProviderPowerCapability {
+            capability: LOW_POWER,
+            flags: ProviderFlags::none(),
+        };
+
+        device0.lock().await.next_result_connect_provider.push_back(Ok(()));
+        device0.lock().await.simulate_provider_connection(HIGH_POWER).await;
+        assert_provider_connected(service_receiver, device0, high_power).await;
+        device0.lock().await.fn_calls.clear();
+
+        // Reservation pushes the total above the limited power threshold
+        device0.lock().await.next_result_connect_provider.push_back(Ok(()));
+        service.lock().await.set_reserved_power(2500).await.unwrap();
+        assert_provider_connected(service_receiver, device0, low_power).await;
+        assert_eq!(
+            device0.lock().await.fn_calls.pop_front().unwrap(),
+            FnCall::ConnectProvider(low_power)
+        );
+
+        // Unchanged reservation doesn't renegotiate
+        service.lock().await.set_reserved_power(2500).await.unwrap();
+        assert!(device0.lock().await.fn_calls.is_empty());
+
+        // Releasing the reservation restores the full capability
+        device0.lock().await.next_result_connect_provider.push_back(Ok(()));
+        service.lock().await.set_reserved_power(0).await.unwrap();
+        assert_provider_connected(service_receiver, device0, high_power).await;
+        assert_eq!(
+            device0.lock().await.fn_calls.pop_front().unwrap(),
+            FnCall::ConnectProvider(high_power)
+        );
+
+        assert_no_event(service_receiver);
+    }
+}
+
 #[tokio::test]
 async fn run_test_single() {
     run_test(DEFAULT_TIMEOUT, TestSingle, Default::default(), DefaultCustomization).await;
@@ -310,3 +451,19 @@ async fn run_test_disconnect() {
     )
     .await;
 }
+
+#[tokio::test]
+async fn run_test_demote() {
+    run_test(DEFAULT_TIMEOUT, TestDemote, Default::default(), DefaultCustomization).await;
+}
+
+#[tokio::test]
+async fn run_test_reservation() {
+    run_test(
+        DEFAULT_TIMEOUT,
+        TestReservation,
+        Default::default(),
+        DefaultCustomization,
+    )
+    .await;
+}
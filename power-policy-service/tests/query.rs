@@ -0,0 +1,102 @@
+#![allow(clippy::unwrap_used)]
+use embassy_futures::select::{Either, select};
+use embassy_sync::channel::{Channel, DynamicReceiver};
+use embedded_services::comms::{self, EndpointID, Internal, MailboxDelegate, MailboxDelegateError, Message};
+use embedded_services::{GlobalRawMutex, info};
+
+mod common;
+
+use power_policy_interface::capability::{ConsumerFlags, ConsumerPowerCapability};
+use power_policy_interface::service::event::Event as ServiceEvent;
+use power_policy_interface::service::{PolicyStateRequest, PolicyStateResponse, UnconstrainedState};
+use power_policy_service::service::config::Config;
+use power_policy_service::service::customization::DefaultCustomization;
+use power_policy_service::service::query::PolicyStateQueries;
+use power_policy_service::service::task::query_task;
+
+use crate::common::{DEFAULT_TIMEOUT, LOW_POWER, assert_consumer_connected, run_test};
+use crate::common::{DeviceType, ServiceMutex, Test};
+
+/// Stand-in for a service querying the power policy, records the responses it receives
+struct Requester {
+    tp: comms::Endpoint,
+    responses: Channel<GlobalRawMutex, PolicyStateResponse, 1>,
+}
+
+impl MailboxDelegate for Requester {
+    fn receive(&self, message: &Message) -> Result<(), MailboxDelegateError> {
+        let response = message
+            .data
+            .get::<PolicyStateResponse>()
+            .ok_or(MailboxDelegateError::MessageNotFound)?;
+        self.responses
+            .try_send(response.clone())
+            .map_err(|_| MailboxDelegateError::BufferFull)
+    }
+}
+
+static REQUESTER: Requester = Requester {
+    tp: comms::Endpoint::uninit(EndpointID::Internal(Internal::Thermal)),
+    responses: Channel::new(),
+};
+
+static QUERIES: PolicyStateQueries = PolicyStateQueries::new();
+
+/// Test that a comms request is answered with the current policy state.
+struct TestQuery;
+
+impl Test for TestQuery {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        service: &'a ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        _device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_query");
+        QUERIES.register().await.unwrap();
+        comms::register_endpoint(&REQUESTER, &REQUESTER.tp).await.unwrap();
+
+        let consumer = ConsumerPowerCapability {
+            capability: LOW_POWER,
+            flags: ConsumerFlags::none(),
+        };
+        device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+        device0
+            .lock()
+            .await
+            .simulate_consumer_connection(LOW_POWER.into())
+            .await;
+        assert_consumer_connected(service_receiver, device0, consumer).await;
+
+        let response = match select(query_task(&QUERIES, service), async {
+            REQUESTER
+                .tp
+                .send(Internal::Power.into(), &PolicyStateRequest)
+                .await
+                .unwrap();
+            REQUESTER.responses.receive().await
+        })
+        .await
+        {
+            Either::First(never) => never,
+            Either::Second(response) => response,
+        };
+
+        assert_eq!(
+            response,
+            PolicyStateResponse {
+                consumer: Some(consumer),
+                providers: heapless::Vec::new(),
+                unconstrained: UnconstrainedState::default(),
+            }
+        );
+    }
+}
+
+#[tokio::test]
+async fn run_test_query() {
+    run_test(DEFAULT_TIMEOUT, TestQuery, Config::default(), DefaultCustomization).await;
+}
@@ -0,0 +1,75 @@
+#![allow(clippy::unwrap_used)]
+use embassy_sync::channel::DynamicReceiver;
+use embedded_services::info;
+use embedded_services::service_state::{MAX_SNAPSHOT_LEN, RetainedState, ServiceState};
+use power_policy_interface::capability::{ConsumerFlags, ConsumerPowerCapability};
+use power_policy_interface::service::event::Event as ServiceEvent;
+use power_policy_service::service::customization::DefaultCustomization;
+
+mod common;
+
+use crate::common::{DEFAULT_TIMEOUT, DeviceType, LOW_POWER, ServiceMutex, Test, assert_consumer_connected, run_test};
+
+/// Power policy state snapshot
+static SNAPSHOT: RetainedState = RetainedState::new();
+
+fn snapshot() -> Vec<u8> {
+    let mut buffer = [0; MAX_SNAPSHOT_LEN];
+    let len = SNAPSHOT.snapshot(&mut buffer);
+    buffer.get(..len).unwrap().to_vec()
+}
+
+/// Test that the state snapshot follows the current consumer.
+struct TestSnapshot;
+
+impl Test for TestSnapshot {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        device0: &DeviceType<'a>,
+        _device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_snapshot");
+
+        // The current state is published as soon as the snapshot is set
+        service.lock().await.set_state_snapshot(&SNAPSHOT);
+        let initial: [&[u8]; 7] = [
+            &[0xff],
+            &0u32.to_le_bytes(),
+            &[0, 0, 0],
+            &u32::MAX.to_le_bytes(),
+            &0u32.to_le_bytes(),
+            &[0],
+            &0u32.to_le_bytes(),
+        ];
+        assert_eq!(snapshot(), initial.concat());
+
+        device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
+        device0
+            .lock()
+            .await
+            .simulate_consumer_connection(LOW_POWER.into())
+            .await;
+        assert_consumer_connected(
+            service_receiver,
+            device0,
+            ConsumerPowerCapability {
+                capability: LOW_POWER,
+                flags: ConsumerFlags::none(),
+            },
+        )
+        .await;
+
+        let snapshot = snapshot();
+        assert_eq!(snapshot.first(), Some(&0));
+        assert_eq!(snapshot.get(1..5).unwrap(), &LOW_POWER.max_power_mw().to_le_bytes());
+    }
+}
+
+#[tokio::test]
+async fn run_test_snapshot() {
+    run_test(DEFAULT_TIMEOUT, TestSnapshot, Default::default(), Default::default()).await;
+}
@@ -0,0 +1,88 @@
+#![allow(clippy::unwrap_used)]
+use embassy_futures::select::{Either, select};
+use embassy_sync::channel::DynamicReceiver;
+use embassy_sync::pubsub::PubSubChannel;
+use embedded_services::{GlobalRawMutex, info};
+
+mod common;
+
+use power_policy_interface::service::event::Event as ServiceEvent;
+use power_policy_interface::telemetry::{PowerSensor, SensorError, SharedSystemPower, SystemPower};
+use power_policy_service::service::config::Config;
+use power_policy_service::service::customization::DefaultCustomization;
+use power_policy_service::service::task::telemetry_task;
+use power_policy_service::service::telemetry::PowerSensors;
+
+use crate::common::{DEFAULT_TIMEOUT, assert_no_event, run_test};
+use crate::common::{DeviceType, ServiceMutex, Test};
+
+/// Power sensor returning a fixed reading
+struct FixedSensor(Result<u32, SensorError>);
+
+impl PowerSensor for FixedSensor {
+    async fn power_mw(&mut self) -> Result<u32, SensorError> {
+        self.0
+    }
+}
+
+/// Test that aggregated telemetry is published on its own channel and that a failed sensor doesn't block the others.
+struct TestSystemPower;
+
+impl Test for TestSystemPower {
+    type Customization = DefaultCustomization;
+
+    async fn run<'a>(
+        &mut self,
+        service: &'a ServiceMutex<'a, 'a, Self::Customization>,
+        service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
+        _device0: &DeviceType<'a>,
+        _device1: &DeviceType<'a>,
+    ) {
+        info!("Running test_system_power");
+        let sensors = PowerSensors {
+            adapter: FixedSensor(Ok(30000)),
+            battery: FixedSensor(Err(SensorError::BusError)),
+            rails: [FixedSensor(Ok(8000)), FixedSensor(Ok(4000))],
+        };
+
+        let expected = SystemPower {
+            adapter_mw: 30000,
+            battery_discharge_mw: 0,
+            rails_mw: 12000,
+        };
+        assert_eq!(expected.psys_mw(), 30000);
+
+        let shared = SharedSystemPower::new();
+        let channel: PubSubChannel<GlobalRawMutex, SystemPower, 1, 1, 1> = PubSubChannel::new();
+        let mut subscriber = channel.dyn_subscriber().unwrap();
+
+        let power = match select(
+            telemetry_task(sensors, &shared, channel.dyn_immediate_publisher(), service),
+            subscriber.next_message_pure(),
+        )
+        .await
+        {
+            Either::First(never) => never,
+            Either::Second(power) => power,
+        };
+        assert_eq!(power, expected);
+        assert_eq!(shared.get(), Some(expected));
+
+        // Telemetry isn't broadcast to the power policy listeners
+        assert_no_event(service_receiver);
+    }
+}
+
+#[tokio::test]
+async fn run_test_system_power() {
+    run_test(
+        DEFAULT_TIMEOUT,
+        TestSystemPower,
+        Config {
+            telemetry_interval_ms: 10,
+            ..Default::default()
+        },
+        DefaultCustomization,
+    )
+    .await;
+}
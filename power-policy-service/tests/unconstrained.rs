@@ -1,6 +1,9 @@
 #![allow(clippy::unwrap_used)]
 use embassy_sync::channel::DynamicReceiver;
-use embedded_services::info;
+use embassy_sync::pubsub::PubSubChannel;
+use embedded_services::broadcaster::immediate::{Immediate, Receiver, Subscriber};
+use embedded_services::event::Receiver as _;
+use embedded_services::{GlobalRawMutex, info};
 use power_policy_interface::capability::{ConsumerFlags, ConsumerPowerCapability};
 
 mod common;
@@ -18,6 +21,9 @@ use crate::common::{
 use crate::common::{DeviceType, ServiceMutex, Test};
 use power_policy_interface_test_mocks::psu::FnCall;
 
+/// Retained unconstrained state broadcaster
+static UNCONSTRAINED: Immediate<UnconstrainedState> = Immediate::new_retained();
+
 /// Test unconstrained consumer flow with multiple devices.
 struct TestUnconstrained;
 
@@ -26,12 +32,15 @@ impl Test for TestUnconstrained {
 
     async fn run<'a>(
         &mut self,
-        _service: &ServiceMutex<'a, 'a, Self::Customization>,
+        service: &ServiceMutex<'a, 'a, Self::Customization>,
         service_receiver: DynamicReceiver<'a, ServiceEvent<'a, DeviceType<'a>>>,
         device0: &DeviceType<'a>,
         device1: &DeviceType<'a>,
     ) {
         info!("Running test_unconstrained");
+        service.lock().await.set_unconstrained_broadcaster(&UNCONSTRAINED).await;
+        assert_eq!(UNCONSTRAINED.latest(), Some(UnconstrainedState::default()));
+
         {
             // Connect device0, without unconstrained,
             device0.lock().await.next_result_connect_consumer.push_back(Ok(()));
@@ -99,6 +108,13 @@ impl Test for TestUnconstrained {
                 },
             )
             .await;
+            assert_eq!(
+                UNCONSTRAINED.latest(),
+                Some(UnconstrainedState {
+                    unconstrained: true,
+                    available: 1,
+                })
+            );
 
             {
                 let mut device0 = device0.lock().await;
@@ -160,6 +176,20 @@ impl Test for TestUnconstrained {
         }
 
         assert_no_event(service_receiver);
+
+        // A late subscriber starts from the latest state
+        let channel: &'static PubSubChannel<GlobalRawMutex, UnconstrainedState, 1, 1, 0> =
+            Box::leak(Box::new(PubSubChannel::new()));
+        let mut subscriber = Subscriber::new(channel.dyn_subscriber().unwrap());
+        let receiver = Box::leak(Box::new(Receiver::new(channel.dyn_immediate_publisher())));
+        UNCONSTRAINED.register_receiver(receiver).unwrap();
+        assert_eq!(
+            subscriber.try_next(),
+            Some(UnconstrainedState {
+                unconstrained: false,
+                available: 0,
+            })
+        );
     }
 }
 
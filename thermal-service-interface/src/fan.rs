@@ -22,6 +22,8 @@ pub enum Error {
 pub enum Event {
     /// Fan encountered a failure.
     Failure(Error),
+    /// The host stopped refreshing its manual override and automatic control was restored.
+    OverrideExpired,
 }
 
 /// Fan on (running) state.
@@ -46,6 +48,29 @@ pub enum State {
     On(OnState),
 }
 
+/// Entry of a platform acoustic limit table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AcousticStep {
+    /// Acoustic limit as set by the host (MPTF `SetScp`).
+    pub acoustic_limit: u32,
+    /// Maximum RPM the fan may run at under this acoustic limit.
+    pub max_rpm: u16,
+}
+
+/// Returns the RPM ceiling for the given acoustic limit.
+///
+/// `table` must be sorted by ascending acoustic limit. The ceiling is taken from the loudest entry that doesn't exceed
+/// `acoustic_limit`, or from the quietest entry if the limit is below every entry. An empty table imposes no ceiling.
+pub fn acoustic_rpm_ceiling(table: &[AcousticStep], acoustic_limit: u32) -> Option<u16> {
+    table
+        .iter()
+        .take_while(|step| step.acoustic_limit <= acoustic_limit)
+        .last()
+        .or(table.first())
+        .map(|step| step.max_rpm)
+}
+
 /// Fan service interface trait.
 pub trait FanService {
     /// Enable automatic fan control.
@@ -76,6 +101,19 @@ pub trait FanService {
     fn state_temp(&self, state: OnState) -> impl Future<Output = DegreesCelsius>;
     /// Sets the temperature at which the fan will change to the specified [`OnState`] when in automatic control mode.
     fn set_state_temp(&self, state: OnState, temp: DegreesCelsius) -> impl Future<Output = ()>;
+    /// Caps the fan speed according to the host acoustic limit, `None` removes the cap.
+    fn set_acoustic_limit(&self, acoustic_limit: Option<u32>) -> impl Future<Output = Result<(), Error>>;
+    /// Returns the RPM ceiling imposed by the current acoustic limit, if any.
+    fn rpm_ceiling(&self) -> impl Future<Output = Option<u16>>;
+    /// Sets or refreshes a host override running the fan at the specified duty cycle percentage.
+    ///
+    /// Automatic control is disabled while the override is active. The override must be refreshed within the
+    /// configured timeout or the fan reverts to automatic control.
+    fn set_host_override(&self, duty: u8) -> impl Future<Output = Result<(), Error>>;
+    /// Releases the host override, if any, and re-enables automatic control.
+    fn clear_host_override(&self) -> impl Future<Output = Result<(), Error>>;
+    /// Returns the duty cycle percentage of the active host override, if any.
+    fn host_override(&self) -> impl Future<Output = Option<u8>>;
 }
 
 impl<T: FanService> FanService for &T {
@@ -130,4 +168,53 @@ impl<T: FanService> FanService for &T {
     fn set_state_temp(&self, state: OnState, temp: DegreesCelsius) -> impl Future<Output = ()> {
         T::set_state_temp(self, state, temp)
     }
+
+    fn set_acoustic_limit(&self, acoustic_limit: Option<u32>) -> impl Future<Output = Result<(), Error>> {
+        T::set_acoustic_limit(self, acoustic_limit)
+    }
+
+    fn rpm_ceiling(&self) -> impl Future<Output = Option<u16>> {
+        T::rpm_ceiling(self)
+    }
+
+    fn set_host_override(&self, duty: u8) -> impl Future<Output = Result<(), Error>> {
+        T::set_host_override(self, duty)
+    }
+
+    fn clear_host_override(&self) -> impl Future<Output = Result<(), Error>> {
+        T::clear_host_override(self)
+    }
+
+    fn host_override(&self) -> impl Future<Output = Option<u8>> {
+        T::host_override(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: [AcousticStep; 3] = [
+        AcousticStep {
+            acoustic_limit: 25,
+            max_rpm: 2000,
+        },
+        AcousticStep {
+            acoustic_limit: 35,
+            max_rpm: 3500,
+        },
+        AcousticStep {
+            acoustic_limit: 45,
+            max_rpm: 5000,
+        },
+    ];
+
+    #[test]
+    fn acoustic_ceiling_lookup() {
+        assert_eq!(acoustic_rpm_ceiling(&TABLE, 20), Some(2000));
+        assert_eq!(acoustic_rpm_ceiling(&TABLE, 25), Some(2000));
+        assert_eq!(acoustic_rpm_ceiling(&TABLE, 40), Some(3500));
+        assert_eq!(acoustic_rpm_ceiling(&TABLE, 60), Some(5000));
+        assert_eq!(acoustic_rpm_ceiling(&[], 40), None);
+    }
 }
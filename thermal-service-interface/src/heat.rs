@@ -0,0 +1,35 @@
+//! Heat notices from non-thermal services.
+//!
+//! Some components heat up faster than the sensors feeding automatic fan control can respond, e.g. a charger at high
+//! current or a hot voltage regulator. The services that know about these conditions submit a notice so the thermal
+//! service can get ahead of the temperature rise.
+
+/// Source of a heat notice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HeatSource {
+    /// The charger is running at high current.
+    Charger,
+    /// A voltage regulator hotspot.
+    VoltageRegulator,
+    /// The system is running with its lid closed inside a bag.
+    InBag,
+}
+
+/// In-bag detection event, reported to the host as a thermal event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InBagEvent {
+    /// The system was detected running with its lid closed inside a bag, charging is inhibited.
+    Detected,
+    /// The lid was opened, charging is restored.
+    Cleared,
+}
+
+/// Receiver of heat notices.
+pub trait HeatNoticeSink {
+    /// Notify the thermal service that `source` is producing extra heat, must not block.
+    ///
+    /// Notices expire on their own, sources that remain hot should keep resubmitting them.
+    fn heat_notice(&self, source: HeatSource);
+}
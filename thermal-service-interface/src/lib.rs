@@ -1,6 +1,7 @@
 #![no_std]
 
 pub mod fan;
+pub mod heat;
 pub mod sensor;
 
 /// Thermal service interface trait.
@@ -14,4 +15,44 @@ pub trait ThermalService {
     fn sensor(&self, id: u8) -> Option<Self::Sensor>;
     /// Retrieve a handle to the fan service with the specified instance ID, if it exists.
     fn fan(&self, id: u8) -> Option<Self::Fan>;
+
+    /// Retrieve a handle to the sensor service reported in the specified host temperature slot, if it exists.
+    ///
+    /// Hosts address temperatures by slot (e.g. one slot per ACPI `_TMP` object), which need not line up with sensor
+    /// instance IDs. By default, slots map directly onto sensor instance IDs.
+    fn tmp_slot_sensor(&self, slot: u8) -> Option<Self::Sensor> {
+        self.sensor(slot)
+    }
+
+    /// Retrieve a handle to the fan bound to the specified MPTF fan instance, if it exists.
+    ///
+    /// By default, MPTF instances map directly onto fan instance IDs.
+    fn instance_fan(&self, instance: u8) -> Option<Self::Fan> {
+        self.fan(instance)
+    }
+
+    /// Bitmask of host temperature slots backed by a sensor, with bit N set if slot N reports a temperature.
+    ///
+    /// Reported to the host as the temperature capabilities. By default, derived from [`Self::tmp_slot_sensor`].
+    fn temp_mask(&self) -> u32 {
+        (0..u32::BITS as u8)
+            .filter(|slot| self.tmp_slot_sensor(*slot).is_some())
+            .fold(0, |mask, slot| mask | (1 << slot))
+    }
+
+    /// Bitmask of MPTF fan instances bound to a fan, with bit N set if MPTF fan instance N is bound.
+    ///
+    /// Reported to the host as the fan capabilities. By default, derived from [`Self::instance_fan`].
+    fn fan_mask(&self) -> u32 {
+        (0..u32::BITS as u8)
+            .filter(|instance| self.instance_fan(*instance).is_some())
+            .fold(0, |mask, instance| mask | (1 << instance))
+    }
+
+    /// Latest aggregated system power (PSYS) in mW, if available.
+    ///
+    /// Reported to the host alongside thermal telemetry for performance management.
+    fn system_power_mw(&self) -> Option<u32> {
+        None
+    }
 }
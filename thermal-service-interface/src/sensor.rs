@@ -27,6 +27,8 @@ pub enum Event {
     ThresholdCleared(Threshold),
     /// Sensor encountered a failure.
     Failure(Error),
+    /// The warn threshold timeout expired and the warn thresholds were reverted to their defaults.
+    ThresholdTimeout,
 }
 
 /// Sensor threshold types.
@@ -55,6 +57,10 @@ pub trait SensorService {
     fn set_threshold(&self, threshold: Threshold, value: DegreesCelsius) -> impl Future<Output = ()>;
     /// Returns the temperature threshold value for the specified threshold type in degrees Celsius.
     fn threshold(&self, threshold: Threshold) -> impl Future<Output = DegreesCelsius>;
+    /// Sets the period after which the warn thresholds revert to their defaults unless set again, `None` disables the timeout.
+    fn set_threshold_timeout(&self, timeout: Option<Duration>) -> impl Future<Output = ()>;
+    /// Returns the time remaining before the warn thresholds revert to their defaults, if a timeout is active.
+    fn threshold_timeout(&self) -> impl Future<Output = Option<Duration>>;
     /// Sets the rate at which temperature measurements are sampled.
     fn set_sample_period(&self, period: Duration) -> impl Future<Output = ()>;
     /// Enable periodic temperature sampling.
@@ -84,6 +90,14 @@ impl<T: SensorService> SensorService for &T {
         T::threshold(self, threshold).await
     }
 
+    async fn set_threshold_timeout(&self, timeout: Option<Duration>) {
+        T::set_threshold_timeout(self, timeout).await
+    }
+
+    async fn threshold_timeout(&self) -> Option<Duration> {
+        T::threshold_timeout(self).await
+    }
+
     async fn set_sample_period(&self, period: Duration) {
         T::set_sample_period(self, period).await
     }
@@ -2,6 +2,7 @@
 
 mod serialization;
 
+use embassy_time::Duration;
 pub use serialization::{ThermalError, ThermalRequest, ThermalResponse, ThermalResult};
 use thermal_service_interface::ThermalService;
 use thermal_service_interface::fan::{self, FanService};
@@ -47,6 +48,26 @@ pub mod uuid_standard {
     pub const FAN_CURRENT_RPM: uuid::Bytes = uuid::uuid!("adf95492-0776-4ffc-84f3-b6c8b5269683").to_bytes_le();
 }
 
+/// Platform-specific MPTF UUIDs which the thermal service understands.
+pub mod uuid_platform {
+    /// The aggregated system power (PSYS) in mW. Independent of the instance ID.
+    pub const SYSTEM_POWER: uuid::Bytes = uuid::uuid!("6c80898d-d16d-42fa-ad97-e5c41549f408").to_bytes_le();
+    /// Host manual fan override as a duty cycle percentage (0-100).
+    ///
+    /// Setting a duty cycle takes manual control of the fan, the host must set it again before the fan's override
+    /// timeout expires or the fan reverts to automatic control. Setting [`FAN_OVERRIDE_RELEASE`] releases the
+    /// override immediately. Reads return the active duty cycle or [`FAN_OVERRIDE_RELEASE`] if there's no override.
+    pub const FAN_OVERRIDE_DUTY: uuid::Bytes = uuid::uuid!("0f4bd3a6-9b7e-4c25-8d61-2e9a7c15b3f0").to_bytes_le();
+
+    /// [`FAN_OVERRIDE_DUTY`] value meaning no override.
+    pub const FAN_OVERRIDE_RELEASE: u32 = u32::MAX;
+
+    /// Temperature capabilities, bit N is set if temperature slot N is backed by a sensor. Independent of the instance ID.
+    pub const TMP_CAPS: uuid::Bytes = uuid::uuid!("c31f9a02-6e4b-4d8a-9f57-1b8e2c7d04a6").to_bytes_le();
+    /// Fan capabilities, bit N is set if MPTF fan instance N is present. Independent of the instance ID.
+    pub const FAN_CAPS: uuid::Bytes = uuid::uuid!("7e2d41c8-5a93-4f06-b1e7-93c0d6a28f4b").to_bytes_le();
+}
+
 /// Thermal service relay handler which wraps a thermal service instance.
 pub struct ThermalServiceRelayHandler<T: ThermalService> {
     service: T,
@@ -59,7 +80,10 @@ impl<T: ThermalService> ThermalServiceRelayHandler<T> {
     }
 
     async fn sensor_get_tmp(&self, instance_id: u8) -> ThermalResult {
-        let sensor = self.service.sensor(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let sensor = self
+            .service
+            .tmp_slot_sensor(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         let temp = sensor.temperature().await;
         Ok(ThermalResponse::ThermalGetTmpResponse {
             temperature: DeciKelvin::from_celsius(temp),
@@ -69,15 +93,21 @@ impl<T: ThermalService> ThermalServiceRelayHandler<T> {
     async fn sensor_set_warn_thrs(
         &self,
         instance_id: u8,
-        _timeout: u32,
+        timeout: u32,
         low: DeciKelvin,
         high: DeciKelvin,
     ) -> ThermalResult {
-        let sensor = self.service.sensor(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let sensor = self
+            .service
+            .tmp_slot_sensor(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         sensor.set_threshold(sensor::Threshold::WarnLow, low.to_celsius()).await;
         sensor
             .set_threshold(sensor::Threshold::WarnHigh, high.to_celsius())
             .await;
+        // A timeout of 0 means the thresholds never revert
+        let timeout = (timeout != 0).then(|| Duration::from_millis(timeout.into()));
+        sensor.set_threshold_timeout(timeout).await;
         Ok(ThermalResponse::ThermalSetThrsResponse)
     }
 
@@ -91,6 +121,14 @@ impl<T: ThermalService> ThermalServiceRelayHandler<T> {
             uuid_standard::FAN_MIN_RPM => self.fan_get_min_rpm(instance_id).await,
             uuid_standard::FAN_MAX_RPM => self.fan_get_max_rpm(instance_id).await,
             uuid_standard::FAN_CURRENT_RPM => self.fan_get_rpm(instance_id).await,
+            uuid_platform::SYSTEM_POWER => self.get_system_power(),
+            uuid_platform::TMP_CAPS => Ok(ThermalResponse::ThermalGetVarResponse {
+                val: self.service.temp_mask(),
+            }),
+            uuid_platform::FAN_CAPS => Ok(ThermalResponse::ThermalGetVarResponse {
+                val: self.service.fan_mask(),
+            }),
+            uuid_platform::FAN_OVERRIDE_DUTY => self.fan_get_override(instance_id).await,
             _ => Err(ThermalError::InvalidParameter),
         }
     }
@@ -121,12 +159,16 @@ impl<T: ThermalService> ThermalServiceRelayHandler<T> {
                 let rpm = u16::try_from(set_var).map_err(|_| ThermalError::InvalidParameter)?;
                 self.fan_set_rpm(instance_id, rpm).await
             }
+            uuid_platform::FAN_OVERRIDE_DUTY => self.fan_set_override(instance_id, set_var).await,
             _ => Err(ThermalError::InvalidParameter),
         }
     }
 
     async fn fan_get_state_temp(&self, instance_id: u8, state: fan::OnState) -> ThermalResult {
-        let fan = self.service.fan(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         let temp = fan.state_temp(state).await;
         Ok(ThermalResponse::ThermalGetVarResponse {
             val: DeciKelvin::from_celsius(temp).0,
@@ -134,25 +176,90 @@ impl<T: ThermalService> ThermalServiceRelayHandler<T> {
     }
 
     async fn fan_get_rpm(&self, instance_id: u8) -> ThermalResult {
-        let fan = self.service.fan(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         let rpm = fan.rpm().await;
         Ok(ThermalResponse::ThermalGetVarResponse { val: rpm.into() })
     }
 
+    fn get_system_power(&self) -> ThermalResult {
+        // Unsupported if the platform doesn't aggregate system power telemetry
+        let val = self.service.system_power_mw().ok_or(ThermalError::InvalidParameter)?;
+        Ok(ThermalResponse::ThermalGetVarResponse { val })
+    }
+
     async fn fan_get_min_rpm(&self, instance_id: u8) -> ThermalResult {
-        let fan = self.service.fan(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         let rpm = fan.min_rpm().await;
         Ok(ThermalResponse::ThermalGetVarResponse { val: rpm.into() })
     }
 
     async fn fan_get_max_rpm(&self, instance_id: u8) -> ThermalResult {
-        let fan = self.service.fan(instance_id).ok_or(ThermalError::InvalidParameter)?;
-        let rpm = fan.max_rpm().await;
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
+        // Report the maximum the fan is currently allowed to run at
+        let rpm = match fan.rpm_ceiling().await {
+            Some(ceiling) => fan.max_rpm().await.min(ceiling),
+            None => fan.max_rpm().await,
+        };
         Ok(ThermalResponse::ThermalGetVarResponse { val: rpm.into() })
     }
 
+    async fn fan_get_override(&self, instance_id: u8) -> ThermalResult {
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
+        let val = fan
+            .host_override()
+            .await
+            .map_or(uuid_platform::FAN_OVERRIDE_RELEASE, u32::from);
+        Ok(ThermalResponse::ThermalGetVarResponse { val })
+    }
+
+    async fn fan_set_override(&self, instance_id: u8, duty: u32) -> ThermalResult {
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
+        let result = if duty == uuid_platform::FAN_OVERRIDE_RELEASE {
+            fan.clear_host_override().await
+        } else {
+            let duty = u8::try_from(duty)
+                .ok()
+                .filter(|duty| *duty <= 100)
+                .ok_or(ThermalError::InvalidParameter)?;
+            fan.set_host_override(duty).await
+        };
+        result.map_err(|_| ThermalError::HardwareError)?;
+        Ok(ThermalResponse::ThermalSetVarResponse)
+    }
+
+    async fn fan_set_acoustic_limit(&self, instance_id: u8, acoustic_lim: u32) -> ThermalResult {
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
+        // An acoustic limit of 0 removes the limit
+        let acoustic_lim = (acoustic_lim != 0).then_some(acoustic_lim);
+        fan.set_acoustic_limit(acoustic_lim)
+            .await
+            .map_err(|_| ThermalError::HardwareError)?;
+        Ok(ThermalResponse::ThermalSetScpResponse)
+    }
+
     async fn sensor_set_thrs(&self, instance_id: u8, threshold: sensor::Threshold, threshold_dk: u32) -> ThermalResult {
-        let sensor = self.service.sensor(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let sensor = self
+            .service
+            .tmp_slot_sensor(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         sensor
             .set_threshold(threshold, DeciKelvin(threshold_dk).to_celsius())
             .await;
@@ -160,7 +267,10 @@ impl<T: ThermalService> ThermalServiceRelayHandler<T> {
     }
 
     async fn sensor_get_thrs(&self, instance_id: u8, threshold: sensor::Threshold) -> ThermalResult {
-        let sensor = self.service.sensor(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let sensor = self
+            .service
+            .tmp_slot_sensor(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         let temp = sensor.threshold(threshold).await;
         Ok(ThermalResponse::ThermalGetVarResponse {
             val: DeciKelvin::from_celsius(temp).0,
@@ -168,24 +278,37 @@ impl<T: ThermalService> ThermalServiceRelayHandler<T> {
     }
 
     async fn sensor_get_warn_thrs(&self, instance_id: u8) -> ThermalResult {
-        let sensor = self.service.sensor(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let sensor = self
+            .service
+            .tmp_slot_sensor(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         let low = sensor.threshold(sensor::Threshold::WarnLow).await;
         let high = sensor.threshold(sensor::Threshold::WarnHigh).await;
+        let timeout = sensor
+            .threshold_timeout()
+            .await
+            .map_or(0, |timeout| u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX));
         Ok(ThermalResponse::ThermalGetThrsResponse {
-            timeout: 0,
+            timeout,
             low: DeciKelvin::from_celsius(low),
             high: DeciKelvin::from_celsius(high),
         })
     }
 
     async fn fan_set_state_temp(&self, instance_id: u8, state: fan::OnState, temp: DeciKelvin) -> ThermalResult {
-        let fan = self.service.fan(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         fan.set_state_temp(state, temp.to_celsius()).await;
         Ok(ThermalResponse::ThermalSetVarResponse)
     }
 
     async fn fan_set_rpm(&self, instance_id: u8, rpm: u16) -> ThermalResult {
-        let fan = self.service.fan(instance_id).ok_or(ThermalError::InvalidParameter)?;
+        let fan = self
+            .service
+            .instance_fan(instance_id)
+            .ok_or(ThermalError::InvalidParameter)?;
         fan.set_rpm(rpm).await.map_err(|_| ThermalError::HardwareError)?;
         Ok(ThermalResponse::ThermalSetVarResponse)
     }
@@ -207,8 +330,12 @@ impl<T: ThermalService> embedded_services::relay::mctp::RelayServiceHandler for
                 high,
             } => self.sensor_set_warn_thrs(instance_id, timeout, low, high).await,
             ThermalRequest::ThermalGetThrsRequest { instance_id } => self.sensor_get_warn_thrs(instance_id).await,
-            // Revisit: Don't currently have a good strategy for handling this request
-            ThermalRequest::ThermalSetScpRequest { .. } => Err(ThermalError::InvalidParameter),
+            // Revisit: The power limit isn't enforced yet
+            ThermalRequest::ThermalSetScpRequest {
+                instance_id,
+                acoustic_lim,
+                ..
+            } => self.fan_set_acoustic_limit(instance_id, acoustic_lim).await,
             ThermalRequest::ThermalGetVarRequest {
                 instance_id, var_uuid, ..
             } => self.get_var_handler(instance_id, var_uuid).await,
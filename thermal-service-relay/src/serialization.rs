@@ -1,4 +1,5 @@
 use crate::DeciKelvin;
+use embedded_services::relay::host_error::{ErrorClass, HostError};
 use embedded_services::relay::{MessageSerializationError, SerializableMessage};
 
 // Standard MPTF requests expected by the thermal subsystem
@@ -234,6 +235,16 @@ pub enum ThermalError {
     HardwareError = 3,
 }
 
+impl HostError for ThermalError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::InvalidParameter => ErrorClass::InvalidArgument,
+            Self::UnsupportedRevision => ErrorClass::Unsupported,
+            Self::HardwareError => ErrorClass::Hardware,
+        }
+    }
+}
+
 impl SerializableMessage for ThermalError {
     fn serialize(self, _buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
         match self {
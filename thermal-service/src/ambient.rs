@@ -0,0 +1,257 @@
+//! Virtual ambient temperature sensor.
+//!
+//! Skin sensors read above ambient because of heat from inside the device, and by more the harder the system is
+//! working. Fan speed is used as a proxy for that internal heat load, so each skin sensor is modeled as
+//!
+//! `ambient = skin - offset - fan_rise * fan_load`
+//!
+//! where `fan_load` is the average RPM of all fans over their sampling history as a fraction of their maximum RPM.
+//! The lowest per-sensor estimate is taken, since local hot spots only ever bias an estimate upwards, and smoothed
+//! over time.
+//!
+//! [`Service`] implements [`sensor::SensorService`], so it can drive a fan curve or be registered with the thermal
+//! service and reported to the host like any physical sensor. It doesn't support thresholds.
+use embassy_futures::select::select;
+use embassy_sync::{mutex::Mutex, signal::Signal};
+use embassy_time::{Duration, Timer};
+use embedded_sensors_hal_async::temperature::DegreesCelsius;
+use embedded_services::{GlobalRawMutex, trace};
+use thermal_service_interface::{fan, sensor};
+
+/// A skin sensor and its model parameters.
+#[derive(Clone, Copy, Debug)]
+pub struct SkinSensor<S: sensor::SensorService> {
+    /// The skin sensor.
+    pub sensor: S,
+    /// Skin temperature rise above ambient with the fans stopped.
+    pub offset: DegreesCelsius,
+    /// Additional skin temperature rise above ambient with the fans at maximum RPM.
+    pub fan_rise: DegreesCelsius,
+}
+
+/// Ambient estimation configuration parameters.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Rate at which to update the estimate.
+    pub update_period: Duration,
+    /// Weight of each new estimate in the smoothed estimate, between 0 (exclusive) and 1, where 1 disables smoothing.
+    pub smoothing: f32,
+    /// Whether periodic updates are enabled.
+    pub sampling_enabled: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            update_period: Duration::from_secs(1),
+            smoothing: 0.2,
+            sampling_enabled: true,
+        }
+    }
+}
+
+/// Estimate ambient temperature from a single skin sensor's temperature and the fan load.
+fn estimate(skin: DegreesCelsius, offset: DegreesCelsius, fan_rise: DegreesCelsius, fan_load: f32) -> DegreesCelsius {
+    skin - offset - fan_rise * fan_load
+}
+
+/// Blend a new estimate into the previous smoothed estimate.
+fn smooth(previous: Option<DegreesCelsius>, estimate: DegreesCelsius, smoothing: f32) -> DegreesCelsius {
+    match previous {
+        Some(previous) => previous + (estimate - previous) * smoothing.clamp(f32::EPSILON, 1.0),
+        None => estimate,
+    }
+}
+
+struct ServiceInner<'hw, S: sensor::SensorService, F: fan::FanService> {
+    skin: &'hw [SkinSensor<S>],
+    fans: &'hw [F],
+    config: Mutex<GlobalRawMutex, Config>,
+    estimate: Mutex<GlobalRawMutex, Option<DegreesCelsius>>,
+    en_signal: Signal<GlobalRawMutex, ()>,
+}
+
+impl<S: sensor::SensorService, F: fan::FanService> ServiceInner<'_, S, F> {
+    /// Average fan RPM as a fraction of maximum RPM, 0 if there are no fans.
+    async fn fan_load(&self) -> f32 {
+        let mut load = 0.0;
+        for fan in self.fans {
+            let max_rpm = fan.max_rpm().await;
+            if max_rpm > 0 {
+                load += (f32::from(fan.rpm_average().await) / f32::from(max_rpm)).min(1.0);
+            }
+        }
+        if self.fans.is_empty() {
+            0.0
+        } else {
+            load / self.fans.len() as f32
+        }
+    }
+
+    /// Compute a new unsmoothed estimate from the latest skin sensor samples, `None` if there are no skin sensors.
+    async fn sample(&self) -> Option<DegreesCelsius> {
+        let fan_load = self.fan_load().await;
+        let mut ambient: Option<DegreesCelsius> = None;
+        for skin in self.skin {
+            let temp = skin.sensor.temperature().await;
+            let sensor_estimate = estimate(temp, skin.offset, skin.fan_rise, fan_load);
+            ambient = Some(ambient.map_or(sensor_estimate, |ambient| ambient.min(sensor_estimate)));
+        }
+        ambient
+    }
+
+    async fn update(&self) {
+        let Some(sample) = self.sample().await else {
+            return;
+        };
+        let smoothing = self.config.lock().await.smoothing;
+        let mut estimate = self.estimate.lock().await;
+        let smoothed = smooth(*estimate, sample, smoothing);
+        trace!("Ambient estimate: {} (sample {})", smoothed, sample);
+        *estimate = Some(smoothed);
+    }
+}
+
+/// Virtual ambient sensor control handle.
+pub struct Service<'hw, S: sensor::SensorService, F: fan::FanService> {
+    inner: &'hw ServiceInner<'hw, S, F>,
+}
+
+// Note: We can't derive these traits because the compiler thinks our generics then need to be Copy + Clone,
+// but we only hold a reference and don't actually need to be that strict
+impl<S: sensor::SensorService, F: fan::FanService> Clone for Service<'_, S, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: sensor::SensorService, F: fan::FanService> Copy for Service<'_, S, F> {}
+
+impl<S: sensor::SensorService, F: fan::FanService> sensor::SensorService for Service<'_, S, F> {
+    async fn temperature(&self) -> DegreesCelsius {
+        self.inner.estimate.lock().await.unwrap_or_default()
+    }
+
+    async fn temperature_average(&self) -> DegreesCelsius {
+        // The estimate is already smoothed over time
+        self.temperature().await
+    }
+
+    async fn temperature_immediate(&self) -> Result<DegreesCelsius, sensor::Error> {
+        self.inner.sample().await.ok_or(sensor::Error::Hardware)
+    }
+
+    async fn set_threshold(&self, _threshold: sensor::Threshold, _value: DegreesCelsius) {}
+
+    async fn threshold(&self, _threshold: sensor::Threshold) -> DegreesCelsius {
+        DegreesCelsius::MAX
+    }
+
+    async fn set_threshold_timeout(&self, _timeout: Option<Duration>) {}
+
+    async fn threshold_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    async fn set_sample_period(&self, period: Duration) {
+        self.inner.config.lock().await.update_period = period;
+    }
+
+    async fn enable_sampling(&self) {
+        self.inner.config.lock().await.sampling_enabled = true;
+        self.inner.en_signal.signal(());
+    }
+
+    async fn disable_sampling(&self) {
+        self.inner.config.lock().await.sampling_enabled = false;
+    }
+}
+
+/// Parameters required to initialize a virtual ambient sensor.
+pub struct InitParams<'hw, S: sensor::SensorService, F: fan::FanService> {
+    /// Skin sensors the estimate is derived from.
+    pub skin: &'hw [SkinSensor<S>],
+    /// Fans whose speed indicates the internal heat load.
+    pub fans: &'hw [F],
+    /// Initial configuration.
+    pub config: Config,
+}
+
+/// The memory resources required by the virtual ambient sensor.
+pub struct Resources<'hw, S: sensor::SensorService, F: fan::FanService> {
+    inner: Option<ServiceInner<'hw, S, F>>,
+}
+
+// Note: We can't derive Default because the compiler requires S: Default + F: Default bounds,
+// but we don't need that since the default is just the None case
+impl<S: sensor::SensorService, F: fan::FanService> Default for Resources<'_, S, F> {
+    fn default() -> Self {
+        Self { inner: None }
+    }
+}
+
+/// A task runner for the virtual ambient sensor. Users must run this in an embassy task or similar async execution context.
+pub struct Runner<'hw, S: sensor::SensorService, F: fan::FanService> {
+    service: &'hw ServiceInner<'hw, S, F>,
+}
+
+impl<'hw, S: sensor::SensorService, F: fan::FanService> odp_service_common::runnable_service::ServiceRunner<'hw>
+    for Runner<'hw, S, F>
+{
+    async fn run(self) -> embedded_services::Never {
+        loop {
+            let config = *self.service.config.lock().await;
+            if config.sampling_enabled {
+                self.service.update().await;
+                // Being re-enabled while already sampling just triggers an early update
+                let _ = select(Timer::after(config.update_period), self.service.en_signal.wait()).await;
+            } else {
+                self.service.en_signal.wait().await;
+            }
+        }
+    }
+}
+
+impl<'hw, S: sensor::SensorService + 'hw, F: fan::FanService + 'hw> odp_service_common::runnable_service::Service<'hw>
+    for Service<'hw, S, F>
+{
+    type Runner = Runner<'hw, S, F>;
+    type Resources = Resources<'hw, S, F>;
+}
+
+impl<'hw, S: sensor::SensorService, F: fan::FanService> Service<'hw, S, F> {
+    /// Create a new virtual ambient sensor.
+    pub fn new(
+        resources: &'hw mut Resources<'hw, S, F>,
+        init_params: InitParams<'hw, S, F>,
+    ) -> (Self, Runner<'hw, S, F>) {
+        let inner = resources.inner.insert(ServiceInner {
+            skin: init_params.skin,
+            fans: init_params.fans,
+            config: Mutex::new(init_params.config),
+            estimate: Mutex::new(None),
+            en_signal: Signal::new(),
+        });
+        (Self { inner }, Runner { service: inner })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fan_load_raises_skin_offset() {
+        assert_eq!(estimate(45.0, 10.0, 8.0, 0.0), 35.0);
+        assert_eq!(estimate(45.0, 10.0, 8.0, 0.5), 31.0);
+        assert_eq!(estimate(45.0, 10.0, 8.0, 1.0), 27.0);
+    }
+
+    #[test]
+    fn smoothing() {
+        assert_eq!(smooth(None, 30.0, 0.25), 30.0);
+        assert_eq!(smooth(Some(30.0), 34.0, 0.25), 31.0);
+        assert_eq!(smooth(Some(30.0), 34.0, 1.0), 34.0);
+    }
+}
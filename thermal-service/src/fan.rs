@@ -1,12 +1,14 @@
+use crate::heat::HeatNotices;
 use crate::utils::SampleBuf;
 use core::marker::PhantomData;
+use embassy_futures::select::{Either, select};
 use embassy_sync::mutex::Mutex;
 use embassy_sync::signal::Signal;
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 use embedded_fans_async::Error as _;
 use embedded_sensors_hal_async::temperature::DegreesCelsius;
 use embedded_services::event::NonBlockingSender;
-use embedded_services::{GlobalRawMutex, error, trace};
+use embedded_services::{GlobalRawMutex, error, trace, warn};
 use thermal_service_interface::{fan, sensor};
 
 /// Fan service configuration parameters.
@@ -27,6 +29,10 @@ pub struct Config {
     pub ramp_temp: DegreesCelsius,
     /// Temperature at which the fan will run at its maximum RPM.
     pub max_temp: DegreesCelsius,
+    /// Maps host acoustic limits to RPM ceilings, sorted by ascending acoustic limit.
+    pub acoustic_table: &'static [fan::AcousticStep],
+    /// Time within which the host must refresh a manual override before automatic control is restored.
+    pub override_timeout: Duration,
 }
 
 impl Default for Config {
@@ -39,6 +45,8 @@ impl Default for Config {
             min_temp: 25.0,
             ramp_temp: 35.0,
             max_temp: 45.0,
+            acoustic_table: &[],
+            override_timeout: Duration::from_secs(10),
         }
     }
 }
@@ -49,6 +57,15 @@ struct ServiceInner<T: fan::Driver, const SAMPLE_BUF_LEN: usize> {
     en_signal: Signal<GlobalRawMutex, ()>,
     config: Mutex<GlobalRawMutex, Config>,
     samples: Mutex<GlobalRawMutex, SampleBuf<u16, SAMPLE_BUF_LEN>>,
+    rpm_ceiling: Mutex<GlobalRawMutex, Option<u16>>,
+    host_override: Mutex<GlobalRawMutex, Option<HostOverride>>,
+}
+
+/// Manual fan control requested by the host.
+#[derive(Clone, Copy)]
+struct HostOverride {
+    duty: u8,
+    deadline: Instant,
 }
 
 impl<T: fan::Driver, const SAMPLE_BUF_LEN: usize> ServiceInner<T, SAMPLE_BUF_LEN> {
@@ -59,6 +76,8 @@ impl<T: fan::Driver, const SAMPLE_BUF_LEN: usize> ServiceInner<T, SAMPLE_BUF_LEN
             en_signal: Signal::new(),
             config: Mutex::new(config),
             samples: Mutex::new(SampleBuf::create()),
+            rpm_ceiling: Mutex::new(None),
+            host_override: Mutex::new(None),
         }
     }
 
@@ -74,6 +93,19 @@ impl<T: fan::Driver, const SAMPLE_BUF_LEN: usize> ServiceInner<T, SAMPLE_BUF_LEN
         }
     }
 
+    /// Limit `rpm` to the acoustic ceiling, if any
+    async fn cap_rpm(&self, rpm: u16) -> u16 {
+        self.rpm_ceiling.lock().await.map_or(rpm, |ceiling| rpm.min(ceiling))
+    }
+
+    /// Return the fan to automatic control, starting from the off state
+    async fn enable_auto_control(&self) -> Result<(), fan::Error> {
+        self.change_state(fan::State::Off).await?;
+        self.config.lock().await.auto_control = true;
+        self.en_signal.signal(());
+        Ok(())
+    }
+
     async fn change_state(&self, to: fan::State) -> Result<(), fan::Error> {
         let mut driver = self.driver.lock().await;
         match to {
@@ -87,7 +119,7 @@ impl<T: fan::Driver, const SAMPLE_BUF_LEN: usize> ServiceInner<T, SAMPLE_BUF_LEN
                 // Ramp state will continuously update RPM according to its ramp response function
             }
             fan::State::On(fan::OnState::Max) => {
-                let max_rpm = driver.max_rpm();
+                let max_rpm = self.cap_rpm(driver.max_rpm()).await;
                 let _ = driver.set_speed_rpm(max_rpm).await.map_err(|_| fan::Error::Hardware)?;
             }
         }
@@ -132,10 +164,8 @@ impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Ev
     fan::FanService for Service<'hw, T, S, E, SAMPLE_BUF_LEN>
 {
     async fn enable_auto_control(&self) -> Result<(), fan::Error> {
-        self.inner.change_state(fan::State::Off).await?;
-        self.inner.config.lock().await.auto_control = true;
-        self.inner.en_signal.signal(());
-        Ok(())
+        *self.inner.host_override.lock().await = None;
+        self.inner.enable_auto_control().await
     }
 
     async fn rpm(&self) -> u16 {
@@ -165,6 +195,7 @@ impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Ev
     }
 
     async fn set_rpm(&self, rpm: u16) -> Result<(), fan::Error> {
+        let rpm = self.inner.cap_rpm(rpm).await;
         self.inner
             .driver
             .lock()
@@ -177,13 +208,17 @@ impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Ev
     }
 
     async fn set_duty_percent(&self, duty: u8) -> Result<(), fan::Error> {
-        self.inner
-            .driver
-            .lock()
-            .await
-            .set_speed_percent(duty)
-            .await
-            .map_err(|_| fan::Error::Hardware)?;
+        let mut driver = self.inner.driver.lock().await;
+        let duty = match *self.inner.rpm_ceiling.lock().await {
+            // Scale the ceiling to a duty cycle assuming speed is roughly proportional to duty
+            Some(ceiling) if driver.max_rpm() > 0 => {
+                let max_duty = u32::from(ceiling) * 100 / u32::from(driver.max_rpm());
+                duty.min(u8::try_from(max_duty).unwrap_or(u8::MAX))
+            }
+            _ => duty,
+        };
+        driver.set_speed_percent(duty).await.map_err(|_| fan::Error::Hardware)?;
+        drop(driver);
         self.inner.config.lock().await.auto_control = false;
         Ok(())
     }
@@ -225,6 +260,50 @@ impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Ev
             fan::OnState::Max => config.max_temp = temp,
         }
     }
+
+    async fn set_acoustic_limit(&self, acoustic_limit: Option<u32>) -> Result<(), fan::Error> {
+        let table = self.inner.config.lock().await.acoustic_table;
+        let ceiling = acoustic_limit.and_then(|limit| fan::acoustic_rpm_ceiling(table, limit));
+        trace!(
+            "Fan RPM ceiling set to {:?} for acoustic limit {:?}",
+            ceiling, acoustic_limit
+        );
+        *self.inner.rpm_ceiling.lock().await = ceiling;
+
+        // Apply a lowered ceiling right away, the ramp response picks it up on its next update
+        if *self.inner.state.lock().await == fan::State::On(fan::OnState::Max) {
+            self.inner.change_state(fan::State::On(fan::OnState::Max)).await?;
+        }
+        Ok(())
+    }
+
+    async fn rpm_ceiling(&self) -> Option<u16> {
+        *self.inner.rpm_ceiling.lock().await
+    }
+
+    async fn set_host_override(&self, duty: u8) -> Result<(), fan::Error> {
+        self.set_duty_percent(duty).await?;
+        let deadline = Instant::now() + self.inner.config.lock().await.override_timeout;
+        *self.inner.host_override.lock().await = Some(HostOverride { duty, deadline });
+        // Wake the control loop so it watches the new deadline
+        self.inner.en_signal.signal(());
+        Ok(())
+    }
+
+    async fn clear_host_override(&self) -> Result<(), fan::Error> {
+        if self.inner.host_override.lock().await.take().is_some() {
+            self.inner.enable_auto_control().await?;
+        }
+        Ok(())
+    }
+
+    async fn host_override(&self) -> Option<u8> {
+        self.inner
+            .host_override
+            .lock()
+            .await
+            .map(|host_override| host_override.duty)
+    }
 }
 
 /// Parameters required to initialize a fan service.
@@ -237,6 +316,8 @@ pub struct InitParams<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlock
     pub sensor_service: S,
     /// Event senders for fan events.
     pub event_senders: &'hw mut [E],
+    /// Heat notices that bias automatic fan control, if any.
+    pub heat_notices: Option<&'hw HeatNotices>,
 }
 
 /// The memory resources required by the fan.
@@ -263,6 +344,7 @@ pub struct Runner<
     service: &'hw ServiceInner<T, SAMPLE_BUF_LEN>,
     sensor: S,
     event_senders: &'hw mut [E],
+    heat_notices: Option<&'hw HeatNotices>,
 }
 
 impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Event>, const SAMPLE_BUF_LEN: usize>
@@ -281,7 +363,8 @@ impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Ev
 
         let mut driver = self.service.driver.lock().await;
         let min_rpm = driver.min_start_rpm();
-        let max_rpm = driver.max_rpm();
+        // The curve is compressed under the acoustic ceiling rather than clipped so the fan still responds to temperature
+        let max_rpm = self.service.cap_rpm(driver.max_rpm()).await.max(min_rpm);
 
         // Provide a linear fan response between its min and max RPM relative to temperature between ramp start and max temp
         let rpm = if temp <= config.ramp_temp {
@@ -360,7 +443,9 @@ impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Ev
     async fn handle_auto_control(&mut self) {
         loop {
             if self.service.config.lock().await.auto_control {
-                let temp = self.sensor.temperature().await;
+                // Heat notices shift the fan curve ahead of the sensor catching up with the hotspot
+                let bias = self.heat_notices.map_or(0.0, HeatNotices::temp_bias);
+                let temp = self.sensor.temperature().await + bias;
                 if let Err(e) = self.handle_fan_state(temp).await {
                     error!("Error handling fan state transition, disabling auto control: {:?}", e);
                     self.service.config.lock().await.auto_control = false;
@@ -370,11 +455,37 @@ impl<'hw, T: fan::Driver, S: sensor::SensorService, E: NonBlockingSender<fan::Ev
                 let sleep_duration = self.service.config.lock().await.update_period;
                 Timer::after(sleep_duration).await;
 
-            // Sleep until auto control is re-enabled
+            // Sleep until auto control is re-enabled or a host override expires
             } else {
-                self.service.en_signal.wait().await;
+                let deadline = self.service.host_override.lock().await.map(|o| o.deadline);
+                match deadline {
+                    Some(deadline) => {
+                        if let Either::Second(()) = select(self.service.en_signal.wait(), Timer::at(deadline)).await {
+                            self.expire_host_override(deadline).await;
+                        }
+                    }
+                    None => self.service.en_signal.wait().await,
+                }
+            }
+        }
+    }
+
+    /// Restore automatic control if the host override wasn't refreshed since `deadline` was read
+    async fn expire_host_override(&mut self, deadline: Instant) {
+        {
+            let mut host_override = self.service.host_override.lock().await;
+            if host_override.is_none_or(|o| o.deadline != deadline) {
+                return;
             }
+            *host_override = None;
         }
+
+        warn!("Host fan override expired, restoring automatic control");
+        if let Err(e) = self.service.enable_auto_control().await {
+            error!("Error restoring automatic fan control: {:?}", e);
+            self.broadcast_event(fan::Event::Failure(e));
+        }
+        self.broadcast_event(fan::Event::OverrideExpired);
     }
 }
 
@@ -431,7 +542,233 @@ impl<
                 service,
                 sensor: init_params.sensor_service,
                 event_senders: init_params.event_senders,
+                heat_notices: init_params.heat_notices,
             },
         ))
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use embassy_sync::channel::{Channel, Sender};
+    use embedded_sensors_hal_async::temperature::TemperatureSensor;
+    use odp_service_common::runnable_service::ServiceRunner;
+    use odp_test_support::task::run_until;
+    use odp_test_support::time;
+    use thermal_service_interface::fan::FanService;
+
+    use super::*;
+    use crate::mock::fan::{FAN_COMMAND_LOG_LEN, FanCommandLog, MockFan};
+    use crate::mock::sensor::MockSensor;
+
+    const OVERRIDE_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Sensor holding a temperature below the fan's minimum temperature
+    struct FixedSensor;
+
+    impl sensor::SensorService for FixedSensor {
+        async fn temperature(&self) -> DegreesCelsius {
+            20.0
+        }
+
+        async fn temperature_average(&self) -> DegreesCelsius {
+            20.0
+        }
+
+        async fn temperature_immediate(&self) -> Result<DegreesCelsius, sensor::Error> {
+            Ok(20.0)
+        }
+
+        async fn set_threshold(&self, _threshold: sensor::Threshold, _value: DegreesCelsius) {}
+
+        async fn threshold(&self, _threshold: sensor::Threshold) -> DegreesCelsius {
+            0.0
+        }
+
+        async fn set_threshold_timeout(&self, _timeout: Option<Duration>) {}
+
+        async fn threshold_timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        async fn set_sample_period(&self, _period: Duration) {}
+
+        async fn enable_sampling(&self) {}
+
+        async fn disable_sampling(&self) {}
+    }
+
+    /// Sensor playing back the temperature trace of a [`MockSensor`]
+    struct TraceSensor<'a>(&'a Mutex<GlobalRawMutex, MockSensor>);
+
+    impl sensor::SensorService for TraceSensor<'_> {
+        async fn temperature(&self) -> DegreesCelsius {
+            self.0.lock().await.temperature().await.unwrap()
+        }
+
+        async fn temperature_average(&self) -> DegreesCelsius {
+            self.temperature().await
+        }
+
+        async fn temperature_immediate(&self) -> Result<DegreesCelsius, sensor::Error> {
+            Ok(self.temperature().await)
+        }
+
+        async fn set_threshold(&self, _threshold: sensor::Threshold, _value: DegreesCelsius) {}
+
+        async fn threshold(&self, _threshold: sensor::Threshold) -> DegreesCelsius {
+            0.0
+        }
+
+        async fn set_threshold_timeout(&self, _timeout: Option<Duration>) {}
+
+        async fn threshold_timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        async fn set_sample_period(&self, _period: Duration) {}
+
+        async fn enable_sampling(&self) {}
+
+        async fn disable_sampling(&self) {}
+    }
+
+    type Events = Channel<GlobalRawMutex, fan::Event, 4>;
+    type EventSender<'a> = Sender<'a, GlobalRawMutex, fan::Event, 4>;
+
+    async fn create<'hw>(
+        resources: &'hw mut Resources<MockFan, 4>,
+        senders: &'hw mut [EventSender<'hw>],
+    ) -> (
+        Service<'hw, MockFan, FixedSensor, EventSender<'hw>, 4>,
+        Runner<'hw, MockFan, FixedSensor, EventSender<'hw>, 4>,
+    ) {
+        Service::new(
+            resources,
+            InitParams {
+                driver: MockFan::new(),
+                config: Config {
+                    override_timeout: OVERRIDE_TIMEOUT,
+                    ..MockFan::config()
+                },
+                sensor_service: FixedSensor,
+                event_senders: senders,
+                heat_notices: None,
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_host_override_refresh_and_expiry() {
+        let time = time::pause();
+        let events = Events::new();
+        let mut senders = [events.sender()];
+        let mut resources = Resources::default();
+        let (service, runner) = create(&mut resources, &mut senders).await;
+
+        run_until(runner.run(), async {
+            service.set_host_override(50).await.unwrap();
+            assert_eq!(service.host_override().await, Some(50));
+            assert!(!service.inner.config.lock().await.auto_control);
+
+            // Refreshing before the deadline keeps manual control past the original timeout
+            time.advance(Duration::from_secs(8)).await;
+            service.set_host_override(60).await.unwrap();
+            time.advance(Duration::from_secs(8)).await;
+            assert_eq!(service.host_override().await, Some(60));
+            assert!(!service.inner.config.lock().await.auto_control);
+            assert!(events.try_receive().is_err());
+
+            // Without a refresh, automatic control is restored once the timeout passes
+            time.advance(Duration::from_secs(2)).await;
+            assert_eq!(service.host_override().await, None);
+            assert!(service.inner.config.lock().await.auto_control);
+            assert_eq!(events.try_receive(), Ok(fan::Event::OverrideExpired));
+            assert!(events.try_receive().is_err());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_host_override_release() {
+        let time = time::pause();
+        let events = Events::new();
+        let mut senders = [events.sender()];
+        let mut resources = Resources::default();
+        let (service, runner) = create(&mut resources, &mut senders).await;
+
+        run_until(runner.run(), async {
+            service.set_host_override(50).await.unwrap();
+            time.advance(Duration::from_secs(5)).await;
+
+            service.clear_host_override().await.unwrap();
+            assert_eq!(service.host_override().await, None);
+            assert!(service.inner.config.lock().await.auto_control);
+
+            // A released override doesn't expire later
+            time.advance(OVERRIDE_TIMEOUT).await;
+            assert!(service.inner.config.lock().await.auto_control);
+            assert!(events.try_receive().is_err());
+        })
+        .await;
+    }
+
+    /// Temperatures read once per update period, walking the fan through each of its states and back off
+    const TRACE: &[DegreesCelsius] = &[20.0, 26.0, 31.0, 32.5, 36.0, 32.0, 32.5, 20.0, 20.0];
+
+    /// RPMs commanded while playing back [`TRACE`] with the mock fan config
+    ///
+    /// The fan is started going from off to min. Going from min to ramping doesn't command the fan until the next
+    /// update, which is halfway up the curve. It then goes to max, and back to ramping halfway up the curve. Below the
+    /// ramp temperature minus hysteresis it drops back to min, then off.
+    const TRACE_COMMANDS: &[u16] = &[1000, 3500, 6000, 3500, 1000, 0];
+
+    #[tokio::test]
+    async fn test_trace_playback() {
+        static LOG: FanCommandLog = FanCommandLog::new();
+
+        let time = time::pause();
+        let sensor = Mutex::new(MockSensor::with_trace(TRACE));
+        let mut senders: [EventSender<'_>; 0] = [];
+        let mut resources = Resources::<MockFan, 4>::default();
+        let (_service, runner) = Service::new(
+            &mut resources,
+            InitParams {
+                driver: MockFan::with_log(&LOG),
+                config: MockFan::config(),
+                sensor_service: TraceSensor(&sensor),
+                event_senders: &mut senders,
+                heat_notices: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let period = MockFan::config().update_period;
+        run_until(runner.run(), async {
+            for _ in 0..2 {
+                // The first sample is read right away, then one per update period
+                time.advance(period * (TRACE.len() as u32 - 1)).await;
+                assert_eq!(sensor.lock().await.samples_read(), TRACE.len());
+
+                let mut commands = heapless::Vec::<u16, FAN_COMMAND_LOG_LEN>::new();
+                while let Ok(rpm) = LOG.try_receive() {
+                    commands.push(rpm).unwrap();
+                }
+                assert_eq!(commands.as_slice(), TRACE_COMMANDS);
+
+                // Holding the last sample leaves the fan off
+                time.advance(period).await;
+                assert!(LOG.try_receive().is_err());
+
+                // Replaying the trace from the off state commands the fan the same way
+                sensor.lock().await.restart();
+                time.advance(period).await;
+            }
+        })
+        .await;
+    }
+}
@@ -0,0 +1,144 @@
+//! Fan boost on heat notices.
+//!
+//! [`HeatNotices`] receives [`HeatSource`] notices from other services and raises the temperature used for automatic
+//! fan control by a configured bias until the notice expires, shifting the fan curve so the fan spins up before the
+//! sensors catch up with the hotspot.
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant};
+use embedded_sensors_hal_async::temperature::DegreesCelsius;
+use embedded_services::{GlobalRawMutex, trace};
+use thermal_service_interface::heat::{HeatNoticeSink, HeatSource};
+
+/// Fan curve bias applied while a notice is active.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Boost {
+    /// Amount added to the measured temperature when evaluating the fan curve.
+    pub temp_bias: DegreesCelsius,
+    /// How long a notice stays active after it was last submitted.
+    pub duration: Duration,
+}
+
+impl Default for Boost {
+    fn default() -> Self {
+        Self {
+            temp_bias: 5.0,
+            duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Heat notice configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Boost applied on charger notices.
+    pub charger: Boost,
+    /// Boost applied on voltage regulator notices.
+    pub voltage_regulator: Boost,
+    /// Boost applied on in-bag notices.
+    pub in_bag: Boost,
+}
+
+/// Active heat notices, shared between the services submitting them and the fans they boost.
+pub struct HeatNotices {
+    config: Config,
+    /// Expiry of the latest charger, voltage regulator and in-bag notices
+    expiry: Mutex<GlobalRawMutex, Cell<[Option<Instant>; 3]>>,
+}
+
+impl HeatNotices {
+    /// Create a new instance with no active notices.
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            expiry: Mutex::new(Cell::new([None; 3])),
+        }
+    }
+
+    fn index(source: HeatSource) -> usize {
+        match source {
+            HeatSource::Charger => 0,
+            HeatSource::VoltageRegulator => 1,
+            HeatSource::InBag => 2,
+        }
+    }
+
+    fn boost(&self, source: HeatSource) -> Boost {
+        match source {
+            HeatSource::Charger => self.config.charger,
+            HeatSource::VoltageRegulator => self.config.voltage_regulator,
+            HeatSource::InBag => self.config.in_bag,
+        }
+    }
+
+    /// Record a notice from `source` submitted at `now`.
+    pub fn notify_at(&self, source: HeatSource, now: Instant) {
+        let expires = now + self.boost(source).duration;
+        self.expiry.lock(|expiry| {
+            let mut entries = expiry.get();
+            if let Some(entry) = entries.get_mut(Self::index(source)) {
+                *entry = Some(expires);
+            }
+            expiry.set(entries);
+        });
+    }
+
+    /// Temperature bias at `now`, the largest bias of all active notices.
+    pub fn temp_bias_at(&self, now: Instant) -> DegreesCelsius {
+        let entries = self.expiry.lock(|expiry| expiry.get());
+        [HeatSource::Charger, HeatSource::VoltageRegulator, HeatSource::InBag]
+            .into_iter()
+            .zip(entries)
+            .filter(|(_, expires)| expires.is_some_and(|expires| now < expires))
+            .map(|(source, _)| self.boost(source).temp_bias)
+            .fold(0.0, DegreesCelsius::max)
+    }
+
+    /// Current temperature bias.
+    pub fn temp_bias(&self) -> DegreesCelsius {
+        self.temp_bias_at(Instant::now())
+    }
+}
+
+impl HeatNoticeSink for HeatNotices {
+    fn heat_notice(&self, source: HeatSource) {
+        trace!("Heat notice from {:?}", source);
+        self.notify_at(source, Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn largest_active_bias_applies_until_expiry() {
+        let notices = HeatNotices::new(Config {
+            charger: Boost {
+                temp_bias: 5.0,
+                duration: Duration::from_secs(10),
+            },
+            voltage_regulator: Boost {
+                temp_bias: 8.0,
+                duration: Duration::from_secs(5),
+            },
+            ..Default::default()
+        });
+        let at = Instant::from_secs;
+
+        assert_eq!(notices.temp_bias_at(at(100)), 0.0);
+
+        notices.notify_at(HeatSource::Charger, at(100));
+        notices.notify_at(HeatSource::VoltageRegulator, at(100));
+        assert_eq!(notices.temp_bias_at(at(101)), 8.0);
+        assert_eq!(notices.temp_bias_at(at(106)), 5.0);
+        assert_eq!(notices.temp_bias_at(at(110)), 0.0);
+
+        // Resubmitting extends the notice
+        notices.notify_at(HeatSource::Charger, at(108));
+        assert_eq!(notices.temp_bias_at(at(110)), 5.0);
+    }
+}
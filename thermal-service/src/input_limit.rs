@@ -0,0 +1,135 @@
+//! Consumer input power limiting.
+//!
+//! Charging is a significant heat source, so thermal policy caps the power drawn from the current consumer while a
+//! sensor is above its warn high or prochot threshold. [`InputPowerLimit`] is fed the events of the sensors it covers
+//! and requests the resulting limit from the power policy through a [`ConsumerThermalLimit`], which the power policy
+//! applies to the charger input.
+use embedded_services::info;
+use power_policy_interface::thermal::ConsumerThermalLimit;
+use thermal_service_interface::sensor::{self, Threshold};
+
+/// Input power limit configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Limit in mW applied while any sensor is above its warn high threshold.
+    pub warn_high_limit_mw: Option<u32>,
+    /// Limit in mW applied while any sensor is above its prochot threshold.
+    pub prochot_limit_mw: Option<u32>,
+}
+
+/// Thermal policy limiting the consumer input power.
+pub struct InputPowerLimit<'hw> {
+    config: Config,
+    limit: &'hw ConsumerThermalLimit,
+    /// Number of sensors above their warn high threshold
+    warn_high: u8,
+    /// Number of sensors above their prochot threshold
+    prochot: u8,
+    limit_mw: Option<u32>,
+}
+
+impl<'hw> InputPowerLimit<'hw> {
+    /// Create a new input power limit, no limit is applied until a threshold is exceeded.
+    pub fn new(config: Config, limit: &'hw ConsumerThermalLimit) -> Self {
+        Self {
+            config,
+            limit,
+            warn_high: 0,
+            prochot: 0,
+            limit_mw: None,
+        }
+    }
+
+    /// Currently requested limit in mW.
+    pub fn limit_mw(&self) -> Option<u32> {
+        self.limit_mw
+    }
+
+    /// Update the limit from an event of one of the covered sensors.
+    ///
+    /// Sensors report each threshold crossing once, so the limit stays applied until every sensor that exceeded a
+    /// threshold has cleared it.
+    pub fn process_sensor_event(&mut self, event: sensor::Event) {
+        match event {
+            sensor::Event::ThresholdExceeded(Threshold::WarnHigh) => self.warn_high = self.warn_high.saturating_add(1),
+            sensor::Event::ThresholdCleared(Threshold::WarnHigh) => self.warn_high = self.warn_high.saturating_sub(1),
+            sensor::Event::ThresholdExceeded(Threshold::Prochot) => self.prochot = self.prochot.saturating_add(1),
+            sensor::Event::ThresholdCleared(Threshold::Prochot) => self.prochot = self.prochot.saturating_sub(1),
+            _ => return,
+        }
+
+        let warn_high_limit = self.config.warn_high_limit_mw.filter(|_| self.warn_high > 0);
+        let prochot_limit = self.config.prochot_limit_mw.filter(|_| self.prochot > 0);
+        let limit_mw = match (warn_high_limit, prochot_limit) {
+            (Some(warn_high), Some(prochot)) => Some(warn_high.min(prochot)),
+            (limit, None) | (None, limit) => limit,
+        };
+
+        if limit_mw != self.limit_mw {
+            info!("Consumer input power limit: {:?} mW", limit_mw);
+            self.limit_mw = limit_mw;
+            self.limit.set(limit_mw);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: Config = Config {
+        warn_high_limit_mw: Some(45000),
+        prochot_limit_mw: Some(15000),
+    };
+
+    #[test]
+    fn test_limit_follows_thresholds() {
+        let shared = ConsumerThermalLimit::new();
+        let mut limit = InputPowerLimit::new(CONFIG, &shared);
+
+        limit.process_sensor_event(sensor::Event::ThresholdExceeded(Threshold::WarnHigh));
+        assert_eq!(limit.limit_mw(), Some(45000));
+
+        // The lowest active limit applies
+        limit.process_sensor_event(sensor::Event::ThresholdExceeded(Threshold::Prochot));
+        assert_eq!(limit.limit_mw(), Some(15000));
+        limit.process_sensor_event(sensor::Event::ThresholdCleared(Threshold::Prochot));
+        assert_eq!(limit.limit_mw(), Some(45000));
+
+        // Other events don't affect the limit
+        limit.process_sensor_event(sensor::Event::ThresholdExceeded(Threshold::Critical));
+        assert_eq!(limit.limit_mw(), Some(45000));
+
+        limit.process_sensor_event(sensor::Event::ThresholdCleared(Threshold::WarnHigh));
+        assert_eq!(limit.limit_mw(), None);
+    }
+
+    #[test]
+    fn test_limit_held_until_all_sensors_clear() {
+        let shared = ConsumerThermalLimit::new();
+        let mut limit = InputPowerLimit::new(CONFIG, &shared);
+
+        limit.process_sensor_event(sensor::Event::ThresholdExceeded(Threshold::WarnHigh));
+        limit.process_sensor_event(sensor::Event::ThresholdExceeded(Threshold::WarnHigh));
+        limit.process_sensor_event(sensor::Event::ThresholdCleared(Threshold::WarnHigh));
+        assert_eq!(limit.limit_mw(), Some(45000));
+        limit.process_sensor_event(sensor::Event::ThresholdCleared(Threshold::WarnHigh));
+        assert_eq!(limit.limit_mw(), None);
+    }
+
+    #[test]
+    fn test_unconfigured_threshold() {
+        let shared = ConsumerThermalLimit::new();
+        let mut limit = InputPowerLimit::new(
+            Config {
+                warn_high_limit_mw: None,
+                ..CONFIG
+            },
+            &shared,
+        );
+
+        limit.process_sensor_event(sensor::Event::ThresholdExceeded(Threshold::WarnHigh));
+        assert_eq!(limit.limit_mw(), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! MPTF instance mapping.
+//!
+//! The host addresses sensors and fans by MPTF instance ID, which need not line up with the order sensors and fans
+//! are registered in. The mapping is set at init and can be changed at runtime, e.g. when a platform variant is
+//! detected after boot. Requests for an unmapped instance are rejected.
+
+/// Maximum number of MPTF sensor instances and fan instances.
+pub const MAX_INSTANCES: usize = 32;
+
+/// Error returned when binding an MPTF instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The instance ID is beyond [`MAX_INSTANCES`].
+    InvalidInstance,
+    /// The sensor or fan is not registered with the service.
+    InvalidDevice,
+}
+
+/// Device IDs bound to each MPTF instance.
+pub(crate) struct InstanceMap {
+    sensors: [Option<u8>; MAX_INSTANCES],
+    fans: [Option<u8>; MAX_INSTANCES],
+}
+
+impl InstanceMap {
+    /// Create a map from the initial bindings, an empty binding list maps instances directly onto device IDs.
+    pub(crate) fn new(sensor_slots: &[u8], sensor_count: usize, fan_slots: &[u8], fan_count: usize) -> Self {
+        Self {
+            sensors: bindings(sensor_slots, sensor_count),
+            fans: bindings(fan_slots, fan_count),
+        }
+    }
+
+    pub(crate) fn sensor(&self, instance: u8) -> Option<u8> {
+        self.sensors.get(usize::from(instance)).copied().flatten()
+    }
+
+    pub(crate) fn fan(&self, instance: u8) -> Option<u8> {
+        self.fans.get(usize::from(instance)).copied().flatten()
+    }
+
+    pub(crate) fn bind_sensor(&mut self, instance: u8, sensor: Option<u8>, sensor_count: usize) -> Result<(), Error> {
+        bind(&mut self.sensors, instance, sensor, sensor_count)
+    }
+
+    pub(crate) fn bind_fan(&mut self, instance: u8, fan: Option<u8>, fan_count: usize) -> Result<(), Error> {
+        bind(&mut self.fans, instance, fan, fan_count)
+    }
+
+    /// Bitmask of mapped sensor instances.
+    pub(crate) fn sensor_mask(&self) -> u32 {
+        mask(&self.sensors)
+    }
+
+    /// Bitmask of mapped fan instances.
+    pub(crate) fn fan_mask(&self) -> u32 {
+        mask(&self.fans)
+    }
+}
+
+fn bindings(slots: &[u8], count: usize) -> [Option<u8>; MAX_INSTANCES] {
+    let mut bindings = [None; MAX_INSTANCES];
+    if slots.is_empty() {
+        for (id, binding) in bindings.iter_mut().take(count).enumerate() {
+            *binding = Some(id as u8);
+        }
+    } else {
+        for (binding, id) in bindings.iter_mut().zip(slots) {
+            *binding = (usize::from(*id) < count).then_some(*id);
+        }
+    }
+    bindings
+}
+
+fn bind(bindings: &mut [Option<u8>], instance: u8, id: Option<u8>, count: usize) -> Result<(), Error> {
+    if id.is_some_and(|id| usize::from(id) >= count) {
+        return Err(Error::InvalidDevice);
+    }
+    *bindings.get_mut(usize::from(instance)).ok_or(Error::InvalidInstance)? = id;
+    Ok(())
+}
+
+fn mask(bindings: &[Option<u8>]) -> u32 {
+    bindings
+        .iter()
+        .enumerate()
+        .filter(|(_, id)| id.is_some())
+        .fold(0, |mask, (instance, _)| mask | (1 << instance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_are_identity() {
+        let map = InstanceMap::new(&[], 2, &[], 1);
+        assert_eq!(map.sensor(1), Some(1));
+        assert_eq!(map.sensor(2), None);
+        assert_eq!(map.fan(0), Some(0));
+        assert_eq!(map.sensor_mask(), 0b11);
+        assert_eq!(map.fan_mask(), 0b1);
+    }
+
+    #[test]
+    fn bindings_are_validated() {
+        let mut map = InstanceMap::new(&[1, 5, 0], 2, &[], 1);
+        assert_eq!(map.sensor(0), Some(1));
+        // Unregistered sensors are left unmapped
+        assert_eq!(map.sensor(1), None);
+        assert_eq!(map.sensor_mask(), 0b101);
+
+        assert_eq!(map.bind_sensor(1, Some(2), 2), Err(Error::InvalidDevice));
+        assert_eq!(
+            map.bind_fan(MAX_INSTANCES as u8, Some(0), 1),
+            Err(Error::InvalidInstance)
+        );
+        assert_eq!(map.bind_fan(3, Some(0), 1), Ok(()));
+        assert_eq!(map.bind_fan(0, None, 1), Ok(()));
+        assert_eq!(map.fan(0), None);
+        assert_eq!(map.fan_mask(), 0b1000);
+    }
+
+    #[test]
+    fn tmp_slots_mapping() {
+        // Three sensors, only two of them reported to the host and in a different order
+        let mut map = InstanceMap::new(&[2, 0], 3, &[], 0);
+        assert_eq!(map.sensor(0), Some(2));
+        assert_eq!(map.sensor(1), Some(0));
+        // Slots beyond the list are unmapped even if a sensor with that ID is registered
+        assert_eq!(map.sensor(2), None);
+        assert_eq!(map.sensor(MAX_INSTANCES as u8), None);
+        assert_eq!(map.sensor_mask(), 0b11);
+
+        // Unmapped slots are cleared from the mask and newly mapped slots are added
+        assert_eq!(map.bind_sensor(1, None, 3), Ok(()));
+        assert_eq!(map.sensor(1), None);
+        assert_eq!(map.bind_sensor(4, Some(1), 3), Ok(()));
+        assert_eq!(map.sensor(4), Some(1));
+        assert_eq!(map.sensor_mask(), 0b10001);
+        assert_eq!(map.fan_mask(), 0);
+    }
+}
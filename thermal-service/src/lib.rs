@@ -1,17 +1,37 @@
 //! Thermal service
 #![no_std]
 
+// Host tests use the embassy-time driver from the test support crate
+#[cfg(test)]
+use odp_test_support as _;
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_services::GlobalRawMutex;
+use power_policy_interface::telemetry::SharedSystemPower;
 use thermal_service_interface::{fan::FanService, sensor::SensorService};
 
+use crate::instance::InstanceMap;
+
+pub mod ambient;
 pub mod fan;
+pub mod heat;
+pub mod input_limit;
+pub mod instance;
 #[cfg(feature = "mock")]
 pub mod mock;
+pub mod persist;
+pub mod pwm_fan;
 pub mod sensor;
+pub mod thermistor;
 mod utils;
 
 struct ServiceInner<'hw, S: SensorService, F: FanService> {
     sensors: &'hw [S],
     fans: &'hw [F],
+    instances: Mutex<GlobalRawMutex, RefCell<InstanceMap>>,
+    system_power: Option<&'hw SharedSystemPower>,
 }
 
 /// Thermal service handle.
@@ -32,6 +52,18 @@ pub struct InitParams<'hw, S: SensorService, F: FanService> {
     pub sensors: &'hw [S],
     /// Registered fans.
     pub fans: &'hw [F],
+    /// Sensor instance ID reported in each host temperature slot, indexed by slot.
+    ///
+    /// If empty, slots map directly onto sensor instance IDs. Can be changed at runtime with
+    /// [`Service::map_sensor_instance`].
+    pub tmp_slots: &'hw [u8],
+    /// Fan instance ID bound to each MPTF fan instance, indexed by MPTF instance.
+    ///
+    /// If empty, MPTF instances map directly onto fan instance IDs. Can be changed at runtime with
+    /// [`Service::map_fan_instance`].
+    pub fan_slots: &'hw [u8],
+    /// System power telemetry reported to the host, if any.
+    pub system_power: Option<&'hw SharedSystemPower>,
 }
 
 /// The memory resources required by the thermal service.
@@ -53,9 +85,32 @@ impl<'hw, S: SensorService, F: FanService> Service<'hw, S, F> {
         let inner = resources.inner.insert(ServiceInner {
             sensors: init_params.sensors,
             fans: init_params.fans,
+            instances: Mutex::new(RefCell::new(InstanceMap::new(
+                init_params.tmp_slots,
+                init_params.sensors.len(),
+                init_params.fan_slots,
+                init_params.fans.len(),
+            ))),
+            system_power: init_params.system_power,
         });
         Self { inner }
     }
+
+    /// Bind a host temperature slot to a registered sensor, or unbind it if `sensor` is `None`.
+    pub fn map_sensor_instance(&self, slot: u8, sensor: Option<u8>) -> Result<(), instance::Error> {
+        let sensor_count = self.inner.sensors.len();
+        self.inner
+            .instances
+            .lock(|instances| instances.borrow_mut().bind_sensor(slot, sensor, sensor_count))
+    }
+
+    /// Bind an MPTF fan instance to a registered fan, or unbind it if `fan` is `None`.
+    pub fn map_fan_instance(&self, instance: u8, fan: Option<u8>) -> Result<(), instance::Error> {
+        let fan_count = self.inner.fans.len();
+        self.inner
+            .instances
+            .lock(|instances| instances.borrow_mut().bind_fan(instance, fan, fan_count))
+    }
 }
 
 impl<'hw, S: SensorService + Copy, F: FanService + Copy> thermal_service_interface::ThermalService
@@ -71,4 +126,28 @@ impl<'hw, S: SensorService + Copy, F: FanService + Copy> thermal_service_interfa
     fn fan(&self, id: u8) -> Option<Self::Fan> {
         self.inner.fans.get(id as usize).copied()
     }
+
+    fn tmp_slot_sensor(&self, slot: u8) -> Option<Self::Sensor> {
+        self.sensor(self.inner.instances.lock(|instances| instances.borrow().sensor(slot))?)
+    }
+
+    fn instance_fan(&self, instance: u8) -> Option<Self::Fan> {
+        self.fan(
+            self.inner
+                .instances
+                .lock(|instances| instances.borrow().fan(instance))?,
+        )
+    }
+
+    fn temp_mask(&self) -> u32 {
+        self.inner.instances.lock(|instances| instances.borrow().sensor_mask())
+    }
+
+    fn fan_mask(&self) -> u32 {
+        self.inner.instances.lock(|instances| instances.borrow().fan_mask())
+    }
+
+    fn system_power_mw(&self) -> Option<u32> {
+        self.inner.system_power?.get().map(|power| power.psys_mw())
+    }
 }
@@ -1,5 +1,7 @@
 use crate::fan::Config;
+use embassy_sync::channel::Channel;
 use embedded_fans_async::{Error, ErrorKind, ErrorType, Fan, RpmSense};
+use embedded_services::{GlobalRawMutex, warn};
 use thermal_service_interface::fan as fan_interface;
 
 /// `MockFan` error.
@@ -11,10 +13,29 @@ impl Error for MockFanError {
     }
 }
 
+/// Number of fan commands a [`FanCommandLog`] can hold.
+pub const FAN_COMMAND_LOG_LEN: usize = 32;
+
+/// Log of the RPMs commanded to a [`MockFan`], in order.
+///
+/// Commands are dropped once the log is full, so it should be drained by the test as it runs.
+pub type FanCommandLog = Channel<GlobalRawMutex, u16, FAN_COMMAND_LOG_LEN>;
+
 /// Mock fan.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Default)]
 pub struct MockFan {
     rpm: u16,
+    log: Option<&'static FanCommandLog>,
+}
+
+// The command log is a channel, which doesn't implement `Debug`, so only report whether one is attached
+impl core::fmt::Debug for MockFan {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MockFan")
+            .field("rpm", &self.rpm)
+            .field("logged", &self.log.is_some())
+            .finish()
+    }
 }
 
 impl MockFan {
@@ -23,6 +44,11 @@ impl MockFan {
         Self::default()
     }
 
+    /// Create a new `MockFan` that records every commanded RPM to `log`.
+    pub fn with_log(log: &'static FanCommandLog) -> Self {
+        Self { rpm: 0, log: Some(log) }
+    }
+
     /// Returns a suitable `Config` for a mock fan service.
     pub fn config() -> Config {
         Config {
@@ -53,6 +79,11 @@ impl Fan for MockFan {
 
     async fn set_speed_rpm(&mut self, rpm: u16) -> Result<u16, Self::Error> {
         self.rpm = rpm;
+        if let Some(log) = self.log
+            && log.try_send(rpm).is_err()
+        {
+            warn!("Mock fan command log full, dropping {} RPM", rpm);
+        }
         Ok(rpm)
     }
 }
@@ -66,3 +97,29 @@ impl RpmSense for MockFan {
 }
 
 impl fan_interface::Driver for MockFan {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_command_log() {
+        static LOG: FanCommandLog = FanCommandLog::new();
+        let mut fan = MockFan::with_log(&LOG);
+
+        fan.set_speed_rpm(1000).await.unwrap();
+        fan.set_speed_rpm(2500).await.unwrap();
+        assert_eq!(fan.rpm().await.unwrap(), 2500);
+        assert_eq!(LOG.try_receive(), Ok(1000));
+        assert_eq!(LOG.try_receive(), Ok(2500));
+        assert!(LOG.try_receive().is_err());
+
+        // Commands past the capacity of the log are dropped rather than blocking the fan
+        for rpm in 0..=FAN_COMMAND_LOG_LEN as u16 {
+            fan.set_speed_rpm(rpm).await.unwrap();
+        }
+        assert_eq!(fan.rpm().await.unwrap(), FAN_COMMAND_LOG_LEN as u16);
+        assert_eq!(LOG.len(), FAN_COMMAND_LOG_LEN);
+    }
+}
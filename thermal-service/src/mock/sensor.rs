@@ -17,10 +17,15 @@ impl sensor_traits::ErrorType for MockSensor {
 }
 
 /// Mock sensor.
+///
+/// By default the sensor reports a sawtooth between the mock temperature limits. A sensor created with
+/// [`MockSensor::with_trace`] instead replays a temperature trace, one sample per reading.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct MockSensor {
     temp: DegreesCelsius,
     falling: bool,
+    trace: &'static [DegreesCelsius],
+    trace_index: usize,
 }
 
 impl MockSensor {
@@ -29,9 +34,35 @@ impl MockSensor {
         Self {
             temp: super::MIN_TEMP,
             falling: false,
+            trace: &[],
+            trace_index: 0,
         }
     }
 
+    /// Create a new `MockSensor` that replays `trace`.
+    ///
+    /// The last sample is held once the trace is exhausted.
+    pub fn with_trace(trace: &'static [DegreesCelsius]) -> Self {
+        Self { trace, ..Self::new() }
+    }
+
+    /// Number of trace samples that have been read.
+    pub fn samples_read(&self) -> usize {
+        self.trace_index.min(self.trace.len())
+    }
+
+    /// Restart trace playback from the first sample.
+    pub fn restart(&mut self) {
+        self.trace_index = 0;
+    }
+
+    /// Returns the next trace sample, `None` if no trace is set.
+    fn next_trace_sample(&mut self) -> Option<DegreesCelsius> {
+        let sample = self.trace.get(self.trace_index).or(self.trace.last()).copied()?;
+        self.trace_index = self.trace_index.saturating_add(1);
+        Some(sample)
+    }
+
     /// Returns a suitable `Config` for a mock sensor service.
     pub fn config() -> Config {
         Config {
@@ -45,6 +76,10 @@ impl MockSensor {
 
 impl TemperatureSensor for MockSensor {
     async fn temperature(&mut self) -> Result<DegreesCelsius, Self::Error> {
+        if let Some(t) = self.next_trace_sample() {
+            return Ok(t);
+        }
+
         let t = self.temp;
 
         // Creates a sawtooth pattern
@@ -78,3 +113,39 @@ impl TemperatureThresholdSet for MockSensor {
 }
 
 impl sensor::Driver for MockSensor {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::mock::MIN_TEMP;
+
+    #[tokio::test]
+    async fn test_trace_playback() {
+        let mut sensor = MockSensor::with_trace(&[30.0, 35.0, 25.0]);
+        assert_eq!(sensor.samples_read(), 0);
+
+        assert_eq!(sensor.temperature().await.unwrap(), 30.0);
+        assert_eq!(sensor.temperature().await.unwrap(), 35.0);
+        assert_eq!(sensor.samples_read(), 2);
+        assert_eq!(sensor.temperature().await.unwrap(), 25.0);
+
+        // The last sample is held once the trace is exhausted
+        assert_eq!(sensor.temperature().await.unwrap(), 25.0);
+        assert_eq!(sensor.samples_read(), 3);
+
+        sensor.restart();
+        assert_eq!(sensor.samples_read(), 0);
+        assert_eq!(sensor.temperature().await.unwrap(), 30.0);
+    }
+
+    #[tokio::test]
+    async fn test_sawtooth() {
+        let mut sensor = MockSensor::new();
+        assert_eq!(sensor.temperature().await.unwrap(), MIN_TEMP);
+        assert_eq!(sensor.temperature().await.unwrap(), MIN_TEMP + 1.0);
+
+        // Without a trace no samples are ever read
+        assert_eq!(sensor.samples_read(), 0);
+    }
+}
@@ -0,0 +1,220 @@
+//! Sensor threshold persistence.
+//!
+//! Thresholds set by the host (e.g. through MPTF SetThrs) are otherwise lost on an EC reset, and the host is not
+//! notified of threshold crossings until it reprograms them. A [`ThresholdStore`] keeps the active thresholds of a
+//! sensor in NVRAM so that the sensor service restores them at init.
+//!
+//! Restored thresholds are considered stale once they have been restored `max_restores` times in a row without the
+//! host setting a threshold, in which case they are discarded and the configured thresholds are used instead. Warn
+//! thresholds set with a timeout are restored with their full timeout re-armed, and are cleared from the store once
+//! the timeout expires.
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Duration;
+use embedded_mcu_hal::nvram::NvramStorage;
+use embedded_sensors_hal_async::temperature::DegreesCelsius;
+use embedded_services::{GlobalRawMutex, info};
+
+const MAGIC: u32 = 0xa5;
+const MAGIC_MASK: u32 = 0xff;
+const RESTORES_SHIFT: u32 = 8;
+const RESTORES_MASK: u32 = 0xff << RESTORES_SHIFT;
+const TIMEOUT_SHIFT: u32 = 16;
+
+/// Encoded value of a threshold that was never set
+const UNSET: u16 = 0;
+/// Encoded value of a disabled threshold
+const DISABLED: u16 = u16::MAX;
+
+/// Encode a threshold in deciKelvin
+fn encode_threshold(value: Option<DegreesCelsius>) -> u16 {
+    match value {
+        None => UNSET,
+        Some(value) if value >= DegreesCelsius::MAX => DISABLED,
+        // Clamp so that valid thresholds never collide with the reserved values
+        Some(value) => ((value + 273.15) * 10.0).clamp(1.0, f32::from(DISABLED - 1)) as u16,
+    }
+}
+
+fn decode_threshold(raw: u16) -> Option<DegreesCelsius> {
+    match raw {
+        UNSET => None,
+        DISABLED => Some(DegreesCelsius::MAX),
+        raw => Some(f32::from(raw) / 10.0 - 273.15),
+    }
+}
+
+fn encode_pair(low: Option<DegreesCelsius>, high: Option<DegreesCelsius>) -> u32 {
+    u32::from(encode_threshold(low)) | (u32::from(encode_threshold(high)) << 16)
+}
+
+fn decode_pair(raw: u32) -> (Option<DegreesCelsius>, Option<DegreesCelsius>) {
+    (
+        decode_threshold((raw & 0xffff) as u16),
+        decode_threshold((raw >> 16) as u16),
+    )
+}
+
+/// Thresholds persisted for a sensor, `None` for thresholds the host has not set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Thresholds {
+    /// Warn low threshold.
+    pub warn_low: Option<DegreesCelsius>,
+    /// Warn high threshold.
+    pub warn_high: Option<DegreesCelsius>,
+    /// Prochot threshold.
+    pub prochot: Option<DegreesCelsius>,
+    /// Critical threshold.
+    pub critical: Option<DegreesCelsius>,
+    /// Timeout after which the warn thresholds revert, stored with a resolution of one second.
+    pub warn_timeout: Option<Duration>,
+}
+
+/// NVRAM registers backing a [`ThresholdStore`].
+pub struct ThresholdStorage<'hw> {
+    /// Validity marker, restore count and warn threshold timeout.
+    pub header: &'hw mut dyn NvramStorage<'hw, u32>,
+    /// Warn low and warn high thresholds.
+    pub warn: &'hw mut dyn NvramStorage<'hw, u32>,
+    /// Prochot and critical thresholds.
+    pub limits: &'hw mut dyn NvramStorage<'hw, u32>,
+}
+
+struct StoreState<'hw> {
+    storage: ThresholdStorage<'hw>,
+    thresholds: Thresholds,
+}
+
+/// Persisted thresholds of a single sensor.
+pub struct ThresholdStore<'hw> {
+    max_restores: u8,
+    state: Mutex<GlobalRawMutex, RefCell<StoreState<'hw>>>,
+}
+
+impl<'hw> ThresholdStore<'hw> {
+    /// Create a new threshold store, discarding thresholds once restored `max_restores` times without being set.
+    pub fn new(storage: ThresholdStorage<'hw>, max_restores: u8) -> Self {
+        Self {
+            max_restores,
+            state: Mutex::new(RefCell::new(StoreState {
+                storage,
+                thresholds: Thresholds::default(),
+            })),
+        }
+    }
+
+    /// Load the persisted thresholds, returning `None` if there are none or they are stale.
+    ///
+    /// Must be called once per EC reset since each call counts towards the staleness limit.
+    pub(crate) fn restore(&self) -> Option<Thresholds> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            let header = state.storage.header.read();
+            if header & MAGIC_MASK != MAGIC {
+                return None;
+            }
+
+            let restores = ((header & RESTORES_MASK) >> RESTORES_SHIFT) as u8;
+            if restores >= self.max_restores {
+                info!("Discarding stale persisted sensor thresholds");
+                state.storage.header.write(0);
+                return None;
+            }
+
+            let (warn_low, warn_high) = decode_pair(state.storage.warn.read());
+            let (prochot, critical) = decode_pair(state.storage.limits.read());
+            let timeout_secs = header >> TIMEOUT_SHIFT;
+            state.thresholds = Thresholds {
+                warn_low,
+                warn_high,
+                prochot,
+                critical,
+                warn_timeout: (timeout_secs != 0).then(|| Duration::from_secs(timeout_secs.into())),
+            };
+            state
+                .storage
+                .header
+                .write((header & !RESTORES_MASK) | (u32::from(restores + 1) << RESTORES_SHIFT));
+            Some(state.thresholds)
+        })
+    }
+
+    /// Update and persist the thresholds, resetting the staleness count.
+    pub(crate) fn update(&self, f: impl FnOnce(&mut Thresholds)) {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            f(&mut state.thresholds);
+
+            let thresholds = state.thresholds;
+            let timeout_secs = thresholds
+                .warn_timeout
+                .map_or(0, |timeout| timeout.as_secs().clamp(1, u16::MAX.into()) as u32);
+            state
+                .storage
+                .warn
+                .write(encode_pair(thresholds.warn_low, thresholds.warn_high));
+            state
+                .storage
+                .limits
+                .write(encode_pair(thresholds.prochot, thresholds.critical));
+            state.storage.header.write(MAGIC | (timeout_secs << TIMEOUT_SHIFT));
+        });
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    struct TestNvram(u32);
+
+    impl<'a> NvramStorage<'a, u32> for TestNvram {
+        fn read(&self) -> u32 {
+            self.0
+        }
+
+        fn write(&mut self, value: u32) {
+            self.0 = value;
+        }
+    }
+
+    #[test]
+    fn thresholds_round_trip() {
+        let (low, high) = decode_pair(encode_pair(Some(-10.0), Some(85.5)));
+        assert!((low.unwrap() + 10.0).abs() < 0.1);
+        assert!((high.unwrap() - 85.5).abs() < 0.1);
+        assert_eq!(
+            decode_pair(encode_pair(None, Some(DegreesCelsius::MAX))),
+            (None, Some(DegreesCelsius::MAX))
+        );
+    }
+
+    #[test]
+    fn stale_thresholds_are_discarded() {
+        let (mut header, mut warn, mut limits) = (TestNvram(0), TestNvram(0), TestNvram(0));
+        let storage = ThresholdStorage {
+            header: &mut header,
+            warn: &mut warn,
+            limits: &mut limits,
+        };
+        let store = ThresholdStore::new(storage, 2);
+        assert_eq!(store.restore(), None);
+
+        store.update(|thresholds| {
+            thresholds.critical = Some(100.0);
+            thresholds.warn_timeout = Some(Duration::from_secs(30));
+        });
+        let restored = store.restore().unwrap();
+        assert_eq!(restored.warn_timeout, Some(Duration::from_secs(30)));
+        assert!((restored.critical.unwrap() - 100.0).abs() < 0.1);
+        assert!(store.restore().is_some());
+        assert_eq!(store.restore(), None);
+
+        // Setting a threshold resets the staleness count
+        store.update(|thresholds| thresholds.prochot = Some(90.0));
+        assert!(store.restore().is_some());
+    }
+}
@@ -0,0 +1,244 @@
+//! Generic PWM fan driver.
+//!
+//! [`PwmFan`] drives a fan from any embedded-hal PWM channel and measures its speed by timing pulses on a tachometer
+//! input. It implements [`fan::Driver`] so it can be registered with the fan service directly.
+//!
+//! ```ignore
+//! let fan = PwmFan::new(pwm, tach, PwmFanConfig::default())?;
+//! ```
+use embassy_time::{Duration, Instant, Timer, with_timeout};
+use embedded_fans_async::{Error, ErrorKind, ErrorType, Fan, RpmSense};
+use embedded_hal::pwm::SetDutyCycle;
+use embedded_hal_async::digital::Wait;
+use embedded_services::trace;
+use thermal_service_interface::fan;
+
+/// A PWM output whose frequency can be configured.
+///
+/// embedded-hal leaves PWM frequency to the HAL, so this is implemented by the platform, typically as a thin wrapper
+/// over its PWM channel. Platforms with a fixed fan PWM frequency can implement this as a no-op.
+pub trait PwmFrequency: SetDutyCycle {
+    /// Sets the PWM frequency in Hz.
+    fn set_frequency_hz(&mut self, frequency_hz: u32) -> Result<(), Self::Error>;
+}
+
+/// Spin-up kick applied when starting a stopped fan.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SpinUp {
+    /// Duty cycle percentage driven during the kick.
+    pub duty_percent: u8,
+    /// How long the kick is driven before settling to the requested speed.
+    pub duration: Duration,
+}
+
+/// PWM fan configuration.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PwmFanConfig {
+    /// PWM frequency in Hz, most 4-wire fans expect 25 kHz.
+    pub pwm_frequency_hz: u32,
+    /// Lowest duty cycle percentage at which the fan keeps spinning.
+    pub min_duty_percent: u8,
+    /// Speed of the fan at the minimum duty cycle.
+    pub min_rpm: u16,
+    /// Speed of the fan at 100% duty cycle.
+    pub max_rpm: u16,
+    /// Minimum speed needed to reliably start the fan.
+    pub min_start_rpm: u16,
+    /// Kick applied when starting a stopped fan, if any.
+    pub spin_up: Option<SpinUp>,
+    /// Number of tachometer pulses per revolution, usually 2.
+    pub tach_pulses_per_rev: u8,
+    /// Time without a tachometer pulse after which the fan is considered stopped.
+    pub tach_timeout: Duration,
+}
+
+impl Default for PwmFanConfig {
+    fn default() -> Self {
+        Self {
+            pwm_frequency_hz: 25_000,
+            min_duty_percent: 20,
+            min_rpm: 1000,
+            max_rpm: 5000,
+            min_start_rpm: 1500,
+            spin_up: Some(SpinUp {
+                duty_percent: 100,
+                duration: Duration::from_millis(500),
+            }),
+            tach_pulses_per_rev: 2,
+            tach_timeout: Duration::from_millis(200),
+        }
+    }
+}
+
+/// `PwmFan` error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PwmFanError {
+    /// Failed to drive the PWM output
+    Pwm,
+    /// Failed to read the tachometer input
+    Tach,
+}
+
+impl Error for PwmFanError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Fan driven by a PWM output with a tachometer input.
+pub struct PwmFan<PWM: PwmFrequency, TACH: Wait> {
+    pwm: PWM,
+    tach: TACH,
+    config: PwmFanConfig,
+    spinning: bool,
+}
+
+impl<PWM: PwmFrequency, TACH: Wait> PwmFan<PWM, TACH> {
+    /// Create a new PWM fan, configuring the PWM frequency and leaving the fan stopped.
+    pub fn new(mut pwm: PWM, tach: TACH, config: PwmFanConfig) -> Result<Self, PwmFanError> {
+        pwm.set_frequency_hz(config.pwm_frequency_hz)
+            .map_err(|_| PwmFanError::Pwm)?;
+        pwm.set_duty_cycle_fully_off().map_err(|_| PwmFanError::Pwm)?;
+
+        Ok(Self {
+            pwm,
+            tach,
+            config,
+            spinning: false,
+        })
+    }
+
+    fn set_duty_percent(&mut self, percent: u8) -> Result<(), PwmFanError> {
+        self.pwm
+            .set_duty_cycle_percent(percent.min(100))
+            .map_err(|_| PwmFanError::Pwm)
+    }
+}
+
+/// Returns the duty cycle percentage needed for `rpm` and the RPM actually targeted
+///
+/// Speed is assumed to scale linearly between the minimum and full duty cycle. Non-zero speeds are clamped to the
+/// fan's operating range.
+fn duty_for_rpm(config: &PwmFanConfig, rpm: u16) -> (u8, u16) {
+    if rpm == 0 {
+        return (0, 0);
+    }
+
+    let rpm = rpm.clamp(config.min_rpm, config.max_rpm.max(config.min_rpm));
+    let min_duty = u32::from(config.min_duty_percent.min(100));
+    let duty = (u32::from(rpm - config.min_rpm) * (100 - min_duty))
+        .checked_div(u32::from(config.max_rpm.saturating_sub(config.min_rpm)))
+        .map_or(100, |duty| min_duty + duty);
+    (u8::try_from(duty).unwrap_or(100), rpm)
+}
+
+/// Returns the fan speed for a tachometer period spanning `pulses_per_rev` pulses, i.e. one revolution
+fn rpm_for_period(period: Duration) -> u16 {
+    60_000_000u64
+        .checked_div(period.as_micros())
+        .map_or(u16::MAX, |rpm| u16::try_from(rpm).unwrap_or(u16::MAX))
+}
+
+impl<PWM: PwmFrequency, TACH: Wait> ErrorType for PwmFan<PWM, TACH> {
+    type Error = PwmFanError;
+}
+
+impl<PWM: PwmFrequency, TACH: Wait> Fan for PwmFan<PWM, TACH> {
+    fn min_rpm(&self) -> u16 {
+        self.config.min_rpm
+    }
+
+    fn max_rpm(&self) -> u16 {
+        self.config.max_rpm
+    }
+
+    fn min_start_rpm(&self) -> u16 {
+        self.config.min_start_rpm
+    }
+
+    async fn set_speed_rpm(&mut self, rpm: u16) -> Result<u16, Self::Error> {
+        let (duty, rpm) = duty_for_rpm(&self.config, rpm);
+
+        // A stopped fan may not start at low duty cycles, so kick it first
+        if duty > 0
+            && !self.spinning
+            && let Some(spin_up) = self.config.spin_up
+        {
+            trace!(
+                "Fan spin-up at {}% for {} ms",
+                spin_up.duty_percent,
+                spin_up.duration.as_millis()
+            );
+            self.set_duty_percent(spin_up.duty_percent)?;
+            Timer::after(spin_up.duration).await;
+        }
+
+        self.set_duty_percent(duty)?;
+        self.spinning = duty > 0;
+        Ok(rpm)
+    }
+}
+
+impl<PWM: PwmFrequency, TACH: Wait> RpmSense for PwmFan<PWM, TACH> {
+    async fn rpm(&mut self) -> Result<u16, Self::Error> {
+        let timeout = self.config.tach_timeout;
+
+        // Time a full revolution from the first falling edge, a missing pulse means the fan is stopped
+        let Ok(result) = with_timeout(timeout, self.tach.wait_for_falling_edge()).await else {
+            return Ok(0);
+        };
+        result.map_err(|_| PwmFanError::Tach)?;
+        let start = Instant::now();
+
+        for _ in 0..self.config.tach_pulses_per_rev.max(1) {
+            let Ok(result) = with_timeout(timeout, self.tach.wait_for_falling_edge()).await else {
+                return Ok(0);
+            };
+            result.map_err(|_| PwmFanError::Tach)?;
+        }
+
+        Ok(rpm_for_period(start.elapsed()))
+    }
+}
+
+impl<PWM: PwmFrequency, TACH: Wait> fan::Driver for PwmFan<PWM, TACH> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duty_for_rpm() {
+        let config = PwmFanConfig::default();
+
+        assert_eq!(duty_for_rpm(&config, 0), (0, 0));
+        assert_eq!(duty_for_rpm(&config, 1000), (20, 1000));
+        assert_eq!(duty_for_rpm(&config, 3000), (60, 3000));
+        assert_eq!(duty_for_rpm(&config, 5000), (100, 5000));
+
+        // Clamped to the operating range
+        assert_eq!(duty_for_rpm(&config, 500), (20, 1000));
+        assert_eq!(duty_for_rpm(&config, 9000), (100, 5000));
+    }
+
+    #[test]
+    fn test_duty_for_rpm_fixed_speed() {
+        let config = PwmFanConfig {
+            min_rpm: 3000,
+            max_rpm: 3000,
+            ..Default::default()
+        };
+
+        assert_eq!(duty_for_rpm(&config, 2000), (100, 3000));
+    }
+
+    #[test]
+    fn test_rpm_for_period() {
+        assert_eq!(rpm_for_period(Duration::from_millis(20)), 3000);
+        assert_eq!(rpm_for_period(Duration::from_millis(60)), 1000);
+        assert_eq!(rpm_for_period(Duration::from_ticks(0)), u16::MAX);
+    }
+}
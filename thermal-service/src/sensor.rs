@@ -1,15 +1,22 @@
+use crate::persist::{ThresholdStore, Thresholds};
 use crate::utils::SampleBuf;
 use core::marker::PhantomData;
+use embassy_futures::select::{Either, select};
 use embassy_sync::{mutex::Mutex, signal::Signal};
-use embassy_time::{Duration, Timer, with_timeout};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
 use embedded_sensors_hal_async::temperature::DegreesCelsius;
 use embedded_services::event::NonBlockingSender;
-use embedded_services::{GlobalRawMutex, error};
+use embedded_services::hook::{ExtensionPoint, HookSlot};
+use embedded_services::host_notification::{Doorbell, NotificationId};
+use embedded_services::{GlobalRawMutex, error, info};
 use thermal_service_interface::sensor;
 
 // Timeout period for physical bus access
 const BUS_TIMEOUT: Duration = Duration::from_millis(200);
 
+// Time the failsafe hook may hold up sampling
+const FAILSAFE_HOOK_TIMEOUT: Duration = Duration::from_millis(100);
+
 /* Helper macro for calling a bus function with automatic retry after timeout or failure.
  *
  * Necessary since often the sensor bus is shared and occasionally the underlying bus driver
@@ -37,6 +44,43 @@ macro_rules! with_retry {
     }};
 }
 
+/// Condition that triggers the thermal failsafe
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FailsafeTrigger {
+    /// Temperature crossed the critical threshold
+    Critical(DegreesCelsius),
+    /// Sensor could no longer be sampled
+    SensorFailure(sensor::Error),
+}
+
+/// Extension point invoked when a sensor enters a failsafe condition, before the event is broadcast
+///
+/// Allows the platform to take immediate action (e.g. force fans to full speed or shut down) without waiting for
+/// the thermal policy to process the event. The hook runs in the sampling loop, so it's abandoned if it doesn't
+/// complete within 100 ms; longer actions should be started by the hook and completed elsewhere.
+pub struct ThermalFailsafe;
+
+impl ExtensionPoint for ThermalFailsafe {
+    const NAME: &'static str = "thermal::Failsafe";
+    type Input = FailsafeTrigger;
+    type Output = ();
+}
+
+/// Thermal failsafe hook, shared by all sensors
+pub static THERMAL_FAILSAFE_HOOK: HookSlot<ThermalFailsafe> = HookSlot::new();
+
+// Call the failsafe hook without letting a stalled hook stop sampling
+async fn call_failsafe_hook(trigger: FailsafeTrigger) {
+    // Dropping the call is safe, the hook's late response is discarded by the next call
+    if with_timeout(FAILSAFE_HOOK_TIMEOUT, THERMAL_FAILSAFE_HOOK.call(trigger))
+        .await
+        .is_err()
+    {
+        error!("Thermal failsafe hook timed out");
+    }
+}
+
 /// Sensor service configuration parameters.
 #[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -88,15 +132,34 @@ struct ServiceInner<T: sensor::Driver, const SAMPLE_BUF_LEN: usize> {
     en_signal: Signal<GlobalRawMutex, ()>,
     config: Mutex<GlobalRawMutex, Config>,
     samples: Mutex<GlobalRawMutex, SampleBuf<DegreesCelsius, SAMPLE_BUF_LEN>>,
+    // Warn thresholds to revert to when the threshold timeout expires
+    default_warn_thresholds: (DegreesCelsius, DegreesCelsius),
+    threshold_deadline: Mutex<GlobalRawMutex, Option<Instant>>,
+    threshold_signal: Signal<GlobalRawMutex, ()>,
 }
 
 impl<T: sensor::Driver, const SAMPLE_BUF_LEN: usize> ServiceInner<T, SAMPLE_BUF_LEN> {
-    fn new(driver: T, config: Config) -> Self {
+    fn new(driver: T, config: Config, restored: Option<Thresholds>) -> Self {
+        let default_warn_thresholds = (config.warn_low_threshold, config.warn_high_threshold);
+        let mut config = config;
+        let mut threshold_deadline = None;
+        if let Some(restored) = restored {
+            info!("Restoring persisted sensor thresholds");
+            config.warn_low_threshold = restored.warn_low.unwrap_or(config.warn_low_threshold);
+            config.warn_high_threshold = restored.warn_high.unwrap_or(config.warn_high_threshold);
+            config.prochot_threshold = restored.prochot.unwrap_or(config.prochot_threshold);
+            config.critical_threshold = restored.critical.unwrap_or(config.critical_threshold);
+            threshold_deadline = restored.warn_timeout.map(|timeout| Instant::now() + timeout);
+        }
+
         Self {
             driver: Mutex::new(driver),
             en_signal: Signal::new(),
+            default_warn_thresholds,
             config: Mutex::new(config),
             samples: Mutex::new(SampleBuf::create()),
+            threshold_deadline: Mutex::new(threshold_deadline),
+            threshold_signal: Signal::new(),
         }
     }
 }
@@ -104,6 +167,7 @@ impl<T: sensor::Driver, const SAMPLE_BUF_LEN: usize> ServiceInner<T, SAMPLE_BUF_
 /// Sensor service control handle.
 pub struct Service<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_BUF_LEN: usize> {
     inner: &'hw ServiceInner<T, SAMPLE_BUF_LEN>,
+    threshold_store: Option<&'hw ThresholdStore<'hw>>,
     _phantom: PhantomData<E>,
 }
 
@@ -145,6 +209,15 @@ impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_B
             sensor::Threshold::Prochot => config.prochot_threshold = value,
             sensor::Threshold::Critical => config.critical_threshold = value,
         }
+
+        if let Some(store) = self.threshold_store {
+            store.update(|thresholds| match threshold {
+                sensor::Threshold::WarnLow => thresholds.warn_low = Some(value),
+                sensor::Threshold::WarnHigh => thresholds.warn_high = Some(value),
+                sensor::Threshold::Prochot => thresholds.prochot = Some(value),
+                sensor::Threshold::Critical => thresholds.critical = Some(value),
+            });
+        }
     }
 
     async fn threshold(&self, threshold: sensor::Threshold) -> DegreesCelsius {
@@ -157,6 +230,22 @@ impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_B
         }
     }
 
+    async fn set_threshold_timeout(&self, timeout: Option<Duration>) {
+        *self.inner.threshold_deadline.lock().await = timeout.map(|timeout| Instant::now() + timeout);
+        self.inner.threshold_signal.signal(());
+        if let Some(store) = self.threshold_store {
+            store.update(|thresholds| thresholds.warn_timeout = timeout);
+        }
+    }
+
+    async fn threshold_timeout(&self) -> Option<Duration> {
+        self.inner
+            .threshold_deadline
+            .lock()
+            .await
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
     async fn set_sample_period(&self, period: Duration) {
         self.inner.config.lock().await.sample_period = period;
     }
@@ -171,6 +260,15 @@ impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_B
     }
 }
 
+/// Host notification rung when host set warn thresholds time out and revert to their defaults.
+#[derive(Clone, Copy)]
+pub struct ThresholdNotification<'hw> {
+    /// Doorbell shared with the host interface.
+    pub doorbell: &'hw Doorbell,
+    /// Notification the host associates with MPTF threshold events.
+    pub id: NotificationId,
+}
+
 /// Parameters required to initialize a sensor service.
 pub struct InitParams<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>> {
     /// The underlying sensor driver this service will control.
@@ -179,6 +277,10 @@ pub struct InitParams<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event
     pub config: Config,
     /// Event senders for sensor events.
     pub event_senders: &'hw mut [E],
+    /// NVRAM store used to persist host set thresholds across EC resets, if any.
+    pub threshold_store: Option<&'hw ThresholdStore<'hw>>,
+    /// Host notification rung when the threshold timeout expires, if any.
+    pub threshold_notification: Option<ThresholdNotification<'hw>>,
 }
 
 /// The memory resources required by the sensor.
@@ -208,6 +310,8 @@ struct State {
 pub struct Runner<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_BUF_LEN: usize> {
     service: &'hw ServiceInner<T, SAMPLE_BUF_LEN>,
     event_senders: &'hw mut [E],
+    threshold_store: Option<&'hw ThresholdStore<'hw>>,
+    threshold_notification: Option<ThresholdNotification<'hw>>,
     state: State,
 }
 
@@ -251,12 +355,50 @@ impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_B
 
         if temp >= config.critical_threshold && !self.state.is_critical {
             self.state.is_critical = true;
+            call_failsafe_hook(FailsafeTrigger::Critical(temp)).await;
             self.broadcast_event(sensor::Event::ThresholdExceeded(sensor::Threshold::Critical));
         } else if temp < (config.critical_threshold - config.hysteresis) && self.state.is_critical {
             self.state.is_critical = false;
             self.broadcast_event(sensor::Event::ThresholdCleared(sensor::Threshold::Critical));
         }
     }
+
+    // Wait for the threshold timeout to expire, restarting whenever a new timeout is set
+    async fn wait_threshold_timeout(&self) {
+        loop {
+            let deadline = *self.service.threshold_deadline.lock().await;
+            match deadline {
+                Some(deadline) => {
+                    if let Either::First(()) = select(Timer::at(deadline), self.service.threshold_signal.wait()).await {
+                        return;
+                    }
+                }
+                None => self.service.threshold_signal.wait().await,
+            }
+        }
+    }
+
+    async fn revert_warn_thresholds(&mut self) {
+        info!("Sensor threshold timeout expired, reverting to default warn thresholds");
+        *self.service.threshold_deadline.lock().await = None;
+        {
+            let mut config = self.service.config.lock().await;
+            (config.warn_low_threshold, config.warn_high_threshold) = self.service.default_warn_thresholds;
+        }
+        if let Some(store) = self.threshold_store {
+            store.update(|thresholds| {
+                thresholds.warn_low = None;
+                thresholds.warn_high = None;
+                thresholds.warn_timeout = None;
+            });
+        }
+        self.broadcast_event(sensor::Event::ThresholdTimeout);
+
+        // The host must re-read the thresholds, otherwise it keeps acting on the ones it set
+        if let Some(notification) = self.threshold_notification {
+            notification.doorbell.ring(notification.id);
+        }
+    }
 }
 
 impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_BUF_LEN: usize>
@@ -272,6 +414,7 @@ impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_B
                     Ok(temp) => temp,
                     Err(e) => {
                         self.service.config.lock().await.sampling_enabled = false;
+                        call_failsafe_hook(FailsafeTrigger::SensorFailure(e)).await;
                         self.broadcast_event(sensor::Event::Failure(e));
                         error!("Error sampling sensor, disabling sampling");
                         continue;
@@ -295,11 +438,15 @@ impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event>, const SAMPLE_B
                 };
 
                 // Sleep in-between sampling periods
-                Timer::after(sleep_duration).await;
+                if let Either::Second(()) = select(Timer::after(sleep_duration), self.wait_threshold_timeout()).await {
+                    self.revert_warn_thresholds().await;
+                }
 
             // Otherwise sleep and wait to be re-enabled
-            } else {
-                self.service.en_signal.wait().await;
+            } else if let Either::Second(()) =
+                select(self.service.en_signal.wait(), self.wait_threshold_timeout()).await
+            {
+                self.revert_warn_thresholds().await;
             }
         }
     }
@@ -319,19 +466,166 @@ impl<'hw, T: sensor::Driver, E: NonBlockingSender<sensor::Event> + 'hw, const SA
         service_storage: &'hw mut Resources<T, SAMPLE_BUF_LEN>,
         init_params: InitParams<'hw, T, E>,
     ) -> Result<(Self, Runner<'hw, T, E, SAMPLE_BUF_LEN>), sensor::Error> {
+        let restored = init_params.threshold_store.and_then(ThresholdStore::restore);
         let service = service_storage
             .inner
-            .insert(ServiceInner::new(init_params.driver, init_params.config));
+            .insert(ServiceInner::new(init_params.driver, init_params.config, restored));
         Ok((
             Self {
                 inner: service,
+                threshold_store: init_params.threshold_store,
                 _phantom: PhantomData,
             },
             Runner {
                 service,
                 event_senders: init_params.event_senders,
+                threshold_store: init_params.threshold_store,
+                threshold_notification: init_params.threshold_notification,
                 state: State::default(),
             },
         ))
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use embassy_sync::channel::{Channel, Sender};
+    use odp_service_common::runnable_service::ServiceRunner;
+    use odp_test_support::task::run_until;
+    use odp_test_support::time;
+    use sensor::SensorService;
+
+    use super::*;
+    use crate::mock::sensor::MockSensor;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+    const HOST_WARN_LOW: DegreesCelsius = 10.0;
+    const HOST_WARN_HIGH: DegreesCelsius = 40.0;
+
+    // Held between the host set warn thresholds so sampling raises no threshold events
+    const TRACE: &[DegreesCelsius] = &[25.0];
+
+    type Events = Channel<GlobalRawMutex, sensor::Event, 4>;
+    type EventSender<'a> = Sender<'a, GlobalRawMutex, sensor::Event, 4>;
+
+    async fn create<'hw>(
+        resources: &'hw mut Resources<MockSensor, 4>,
+        senders: &'hw mut [EventSender<'hw>],
+        doorbell: &'hw Doorbell,
+    ) -> (
+        Service<'hw, MockSensor, EventSender<'hw>, 4>,
+        Runner<'hw, MockSensor, EventSender<'hw>, 4>,
+    ) {
+        Service::new(
+            resources,
+            InitParams {
+                driver: MockSensor::with_trace(TRACE),
+                config: Config::default(),
+                event_senders: senders,
+                threshold_store: None,
+                threshold_notification: Some(ThresholdNotification {
+                    doorbell,
+                    id: NotificationId::new(1).unwrap(),
+                }),
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn set_host_thresholds(service: &impl SensorService, timeout: Option<Duration>) {
+        service.set_threshold(sensor::Threshold::WarnLow, HOST_WARN_LOW).await;
+        service.set_threshold(sensor::Threshold::WarnHigh, HOST_WARN_HIGH).await;
+        service.set_threshold_timeout(timeout).await;
+    }
+
+    #[tokio::test]
+    async fn test_threshold_timeout_reverts_warn_thresholds() {
+        let time = time::pause();
+        let events = Events::new();
+        let mut senders = [events.sender()];
+        let doorbell = Doorbell::new(Duration::from_ticks(0));
+        let mut resources = Resources::default();
+        let (service, runner) = create(&mut resources, &mut senders, &doorbell).await;
+
+        run_until(runner.run(), async {
+            set_host_thresholds(&service, Some(TIMEOUT)).await;
+            assert_eq!(service.threshold_timeout().await, Some(TIMEOUT));
+
+            time.advance(Duration::from_secs(4)).await;
+            assert_eq!(service.threshold_timeout().await, Some(Duration::from_secs(6)));
+            assert_eq!(service.threshold(sensor::Threshold::WarnHigh).await, HOST_WARN_HIGH);
+            assert!(events.try_receive().is_err());
+            assert!(doorbell.pending().is_empty());
+
+            // Once the timeout expires the defaults are restored and the host is told to re-read them
+            time.advance(Duration::from_secs(6)).await;
+            assert_eq!(service.threshold_timeout().await, None);
+            assert_eq!(service.threshold(sensor::Threshold::WarnLow).await, DegreesCelsius::MIN);
+            assert_eq!(
+                service.threshold(sensor::Threshold::WarnHigh).await,
+                DegreesCelsius::MAX
+            );
+            assert_eq!(events.try_receive(), Ok(sensor::Event::ThresholdTimeout));
+            assert!(events.try_receive().is_err());
+            assert!(doorbell.pending().contains(NotificationId::new(1).unwrap()));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_threshold_timeout_restart() {
+        let time = time::pause();
+        let events = Events::new();
+        let mut senders = [events.sender()];
+        let doorbell = Doorbell::new(Duration::from_ticks(0));
+        let mut resources = Resources::default();
+        let (service, runner) = create(&mut resources, &mut senders, &doorbell).await;
+
+        run_until(runner.run(), async {
+            set_host_thresholds(&service, Some(TIMEOUT)).await;
+
+            // Setting a new timeout restarts the wait from now
+            time.advance(Duration::from_secs(5)).await;
+            service.set_threshold_timeout(Some(TIMEOUT)).await;
+            time.advance(Duration::from_secs(6)).await;
+            assert_eq!(service.threshold_timeout().await, Some(Duration::from_secs(4)));
+            assert_eq!(service.threshold(sensor::Threshold::WarnHigh).await, HOST_WARN_HIGH);
+            assert!(events.try_receive().is_err());
+
+            time.advance(Duration::from_secs(4)).await;
+            assert_eq!(
+                service.threshold(sensor::Threshold::WarnHigh).await,
+                DegreesCelsius::MAX
+            );
+            assert_eq!(events.try_receive(), Ok(sensor::Event::ThresholdTimeout));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_threshold_timeout_cleared() {
+        let time = time::pause();
+        let events = Events::new();
+        let mut senders = [events.sender()];
+        let doorbell = Doorbell::new(Duration::from_ticks(0));
+        let mut resources = Resources::default();
+        let (service, runner) = create(&mut resources, &mut senders, &doorbell).await;
+
+        run_until(runner.run(), async {
+            set_host_thresholds(&service, Some(TIMEOUT)).await;
+
+            // Clearing the timeout keeps the host set thresholds indefinitely
+            time.advance(Duration::from_secs(5)).await;
+            service.set_threshold_timeout(None).await;
+            assert_eq!(service.threshold_timeout().await, None);
+            time.advance(TIMEOUT * 2).await;
+            assert_eq!(service.threshold(sensor::Threshold::WarnLow).await, HOST_WARN_LOW);
+            assert_eq!(service.threshold(sensor::Threshold::WarnHigh).await, HOST_WARN_HIGH);
+            assert!(events.try_receive().is_err());
+            assert!(doorbell.pending().is_empty());
+        })
+        .await;
+    }
+}
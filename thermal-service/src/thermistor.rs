@@ -0,0 +1,228 @@
+//! Generic NTC thermistor temperature sensor driver.
+//!
+//! [`Thermistor`] converts ADC readings of a thermistor voltage divider into [`DegreesCelsius`], using either the
+//! Steinhart-Hart equation or a resistance table from the thermistor datasheet. It implements [`sensor::Driver`] so
+//! boards without digital temperature sensors can register it with the sensor service directly.
+//!
+//! ```ignore
+//! let config = ThermistorConfig {
+//!     conversion: Conversion::SteinhartHart { a: 1.129e-3, b: 2.341e-4, c: 8.775e-8 },
+//!     ..Default::default()
+//! };
+//! let sensor = Thermistor::new(adc_channel, config);
+//! ```
+use crate::utils::ln;
+use core::future::Future;
+use embedded_sensors_hal_async::sensor as sensor_traits;
+use embedded_sensors_hal_async::temperature::{DegreesCelsius, TemperatureSensor};
+use thermal_service_interface::sensor;
+
+/// Offset between degrees Celsius and Kelvin
+const KELVIN_OFFSET: f32 = 273.15;
+
+/// An ADC channel connected to the thermistor divider.
+///
+/// embedded-hal doesn't define an ADC trait, so this is implemented by the platform over its ADC driver.
+pub trait AdcChannel {
+    /// Error type
+    type Error;
+
+    /// Performs a single conversion, returning the raw reading.
+    fn read(&mut self) -> impl Future<Output = Result<u16, Self::Error>>;
+}
+
+/// Side of the voltage divider the thermistor is placed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Placement {
+    /// Thermistor between the ADC input and ground, fixed resistor to the reference voltage.
+    LowSide,
+    /// Thermistor between the reference voltage and the ADC input, fixed resistor to ground.
+    HighSide,
+}
+
+/// Resistance to temperature conversion.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Conversion {
+    /// Steinhart-Hart equation `1/T = a + b * ln(R) + c * ln(R)^3`, with `T` in Kelvin and `R` in ohms.
+    SteinhartHart { a: f32, b: f32, c: f32 },
+    /// Resistance in ohms and the matching temperature, sorted by ascending temperature.
+    ///
+    /// Temperatures between entries are linearly interpolated, readings beyond the table are clamped to its ends.
+    Table(&'static [(f32, DegreesCelsius)]),
+}
+
+impl Conversion {
+    /// Converts a thermistor resistance in ohms to a temperature
+    fn temperature(&self, resistance: f32) -> DegreesCelsius {
+        match *self {
+            Conversion::SteinhartHart { a, b, c } => {
+                let ln_r = ln(resistance);
+                1.0 / (a + b * ln_r + c * ln_r * ln_r * ln_r) - KELVIN_OFFSET
+            }
+            Conversion::Table(table) => {
+                // NTC resistance falls as temperature rises, so find the first entry below the resistance
+                let Some(upper) = table.iter().position(|&(r, _)| r <= resistance) else {
+                    return table.last().map_or(f32::NAN, |&(_, t)| t);
+                };
+                let Some(&(r_lo, t_lo)) = upper.checked_sub(1).and_then(|i| table.get(i)) else {
+                    return table.first().map_or(f32::NAN, |&(_, t)| t);
+                };
+                let Some(&(r_hi, t_hi)) = table.get(upper) else {
+                    return t_lo;
+                };
+
+                t_lo + (t_hi - t_lo) * (r_lo - resistance) / (r_lo - r_hi)
+            }
+        }
+    }
+}
+
+/// Thermistor sensor configuration.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ThermistorConfig {
+    /// Resistance to temperature conversion.
+    pub conversion: Conversion,
+    /// Placement of the thermistor in the voltage divider.
+    pub placement: Placement,
+    /// Resistance of the fixed divider resistor in ohms.
+    pub divider_ohms: f32,
+    /// Full scale ADC reading, e.g. 4095 for a 12-bit ADC.
+    pub adc_max: u16,
+    /// Number of ADC readings averaged for each temperature sample.
+    pub oversample: u8,
+}
+
+impl Default for ThermistorConfig {
+    fn default() -> Self {
+        // Common 10k NTC with a beta of 3950 and a 10k divider
+        Self {
+            conversion: Conversion::SteinhartHart {
+                a: 1.125_308_8e-3,
+                b: 2.347_118_9e-4,
+                c: 8.566_893_6e-8,
+            },
+            placement: Placement::LowSide,
+            divider_ohms: 10_000.0,
+            adc_max: 4095,
+            oversample: 8,
+        }
+    }
+}
+
+/// `Thermistor` error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ThermistorError {
+    /// Failed to read the ADC
+    Adc,
+    /// Reading at the ADC rail, the thermistor is open or shorted
+    OutOfRange,
+}
+
+impl sensor_traits::Error for ThermistorError {
+    fn kind(&self) -> sensor_traits::ErrorKind {
+        sensor_traits::ErrorKind::Other
+    }
+}
+
+/// NTC thermistor sensor read through an ADC.
+pub struct Thermistor<ADC: AdcChannel> {
+    adc: ADC,
+    config: ThermistorConfig,
+}
+
+impl<ADC: AdcChannel> Thermistor<ADC> {
+    /// Create a new thermistor sensor.
+    pub fn new(adc: ADC, config: ThermistorConfig) -> Self {
+        Self { adc, config }
+    }
+
+    /// Returns the thermistor resistance in ohms for an averaged ADC reading.
+    fn resistance(&self, reading: f32) -> Result<f32, ThermistorError> {
+        let adc_max = f32::from(self.config.adc_max);
+        if reading <= 0.0 || reading >= adc_max {
+            return Err(ThermistorError::OutOfRange);
+        }
+
+        Ok(match self.config.placement {
+            Placement::LowSide => self.config.divider_ohms * reading / (adc_max - reading),
+            Placement::HighSide => self.config.divider_ohms * (adc_max - reading) / reading,
+        })
+    }
+}
+
+impl<ADC: AdcChannel> sensor_traits::ErrorType for Thermistor<ADC> {
+    type Error = ThermistorError;
+}
+
+impl<ADC: AdcChannel> TemperatureSensor for Thermistor<ADC> {
+    async fn temperature(&mut self) -> Result<DegreesCelsius, Self::Error> {
+        let samples = self.config.oversample.max(1);
+        let mut sum = 0u32;
+        for _ in 0..samples {
+            sum += u32::from(self.adc.read().await.map_err(|_| ThermistorError::Adc)?);
+        }
+
+        let resistance = self.resistance(sum as f32 / f32::from(samples))?;
+        Ok(self.config.conversion.temperature(resistance))
+    }
+}
+
+impl<ADC: AdcChannel> sensor::Driver for Thermistor<ADC> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.1, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn steinhart_hart() {
+        let conversion = ThermistorConfig::default().conversion;
+        assert_close(conversion.temperature(32_650.0), 0.0);
+        assert_close(conversion.temperature(10_000.0), 25.0);
+        assert_close(conversion.temperature(3_603.0), 50.0);
+    }
+
+    #[test]
+    fn table() {
+        let conversion = Conversion::Table(&[(32_650.0, 0.0), (10_000.0, 25.0), (3_603.0, 50.0)]);
+        assert_close(conversion.temperature(10_000.0), 25.0);
+        assert_close(conversion.temperature(6_801.5), 37.5);
+
+        // Clamped to the table ends
+        assert_close(conversion.temperature(100_000.0), 0.0);
+        assert_close(conversion.temperature(1_000.0), 50.0);
+    }
+
+    #[test]
+    fn divider() {
+        struct NoAdc;
+        impl AdcChannel for NoAdc {
+            type Error = ();
+            async fn read(&mut self) -> Result<u16, ()> {
+                Err(())
+            }
+        }
+
+        let low = Thermistor::new(NoAdc, ThermistorConfig::default());
+        assert_close(low.resistance(2047.5).unwrap_or_default(), 10_000.0);
+        assert_close(low.resistance(1365.0).unwrap_or_default(), 5_000.0);
+        assert_eq!(low.resistance(0.0), Err(ThermistorError::OutOfRange));
+        assert_eq!(low.resistance(4095.0), Err(ThermistorError::OutOfRange));
+
+        let high = Thermistor::new(
+            NoAdc,
+            ThermistorConfig {
+                placement: Placement::HighSide,
+                ..Default::default()
+            },
+        );
+        assert_close(high.resistance(1365.0).unwrap_or_default(), 20_000.0);
+    }
+}
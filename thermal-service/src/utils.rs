@@ -46,3 +46,23 @@ impl<const N: usize> SampleBuf<u16, N> {
         sum.checked_div(self.deque.len() as u32).unwrap_or(0) as u16
     }
 }
+
+/// Natural logarithm, since `f32::ln` isn't available in `no_std`
+///
+/// Splits `x` into `m * 2^e` with `m` in [1, 2) and evaluates `ln(m)` with the `atanh` series, which is accurate to
+/// well within the precision of an `f32` temperature. Returns NaN for non-positive or subnormal inputs.
+pub fn ln(x: f32) -> f32 {
+    if !x.is_normal() || x < 0.0 {
+        return f32::NAN;
+    }
+
+    let bits = x.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127;
+    let mantissa = f32::from_bits((bits & 0x007f_ffff) | 0x3f80_0000);
+
+    // ln(m) = 2 * atanh(s) where s = (m - 1) / (m + 1) is at most 1/3
+    let s = (mantissa - 1.0) / (mantissa + 1.0);
+    let s2 = s * s;
+    let series = s * (1.0 + s2 * (1.0 / 3.0 + s2 * (1.0 / 5.0 + s2 * (1.0 / 7.0 + s2 * (1.0 / 9.0 + s2 / 11.0)))));
+    exponent as f32 * core::f32::consts::LN_2 + 2.0 * series
+}
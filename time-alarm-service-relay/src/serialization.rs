@@ -1,4 +1,5 @@
 use core::array::TryFromSliceError;
+use embedded_services::relay::host_error::{ErrorClass, HostError};
 use embedded_services::relay::{MessageSerializationError, SerializableMessage};
 use time_alarm_service_interface::{
     AcpiDaylightSavingsTimeStatus, AcpiTimerId, AcpiTimestamp, AlarmExpiredWakePolicy, AlarmTimerSeconds,
@@ -247,12 +248,16 @@ impl SerializableMessage for AcpiTimeAlarmResponse {
 #[repr(u16)]
 pub enum AcpiTimeAlarmError {
     UnspecifiedFailure = 1,
+    /// The request payload or the time it carries is invalid
+    InvalidParameter = 2,
+    /// The real-time clock reported a failure
+    HardwareError = 3,
 }
 
 impl SerializableMessage for AcpiTimeAlarmError {
     fn serialize(self, _buffer: &mut [u8]) -> Result<usize, MessageSerializationError> {
         match self {
-            Self::UnspecifiedFailure => Ok(0),
+            Self::UnspecifiedFailure | Self::InvalidParameter | Self::HardwareError => Ok(0),
         }
     }
 
@@ -266,31 +271,47 @@ impl SerializableMessage for AcpiTimeAlarmError {
 
         match discriminant {
             AcpiTimeAlarmError::UnspecifiedFailure => Ok(AcpiTimeAlarmError::UnspecifiedFailure),
+            AcpiTimeAlarmError::InvalidParameter => Ok(AcpiTimeAlarmError::InvalidParameter),
+            AcpiTimeAlarmError::HardwareError => Ok(AcpiTimeAlarmError::HardwareError),
+        }
+    }
+}
+
+impl HostError for AcpiTimeAlarmError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::UnspecifiedFailure => ErrorClass::Unspecified,
+            Self::InvalidParameter => ErrorClass::InvalidArgument,
+            Self::HardwareError => ErrorClass::Hardware,
         }
     }
 }
 
 impl From<embedded_mcu_hal::time::DatetimeError> for AcpiTimeAlarmError {
     fn from(_error: embedded_mcu_hal::time::DatetimeError) -> Self {
-        AcpiTimeAlarmError::UnspecifiedFailure
+        AcpiTimeAlarmError::InvalidParameter
     }
 }
 
 impl From<num_enum::TryFromPrimitiveError<AcpiDaylightSavingsTimeStatus>> for AcpiTimeAlarmError {
     fn from(_error: num_enum::TryFromPrimitiveError<AcpiDaylightSavingsTimeStatus>) -> Self {
-        AcpiTimeAlarmError::UnspecifiedFailure
+        AcpiTimeAlarmError::InvalidParameter
     }
 }
 
 impl From<TryFromSliceError> for AcpiTimeAlarmError {
     fn from(_error: TryFromSliceError) -> Self {
-        AcpiTimeAlarmError::UnspecifiedFailure
+        AcpiTimeAlarmError::InvalidParameter
     }
 }
 
 impl From<embedded_mcu_hal::time::DatetimeClockError> for AcpiTimeAlarmError {
-    fn from(_error: embedded_mcu_hal::time::DatetimeClockError) -> Self {
-        AcpiTimeAlarmError::UnspecifiedFailure
+    fn from(error: embedded_mcu_hal::time::DatetimeClockError) -> Self {
+        match error {
+            // The host asked to set a time the clock can't represent
+            embedded_mcu_hal::time::DatetimeClockError::UnsupportedDatetime => AcpiTimeAlarmError::InvalidParameter,
+            _ => AcpiTimeAlarmError::HardwareError,
+        }
     }
 }
 
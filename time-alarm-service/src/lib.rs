@@ -1,5 +1,9 @@
 #![cfg_attr(not(test), no_std)]
 
+// Host tests use the embassy-time driver from the test support crate
+#[cfg(test)]
+use odp_test_support as _;
+
 use core::cell::RefCell;
 use embassy_sync::blocking_mutex::Mutex;
 use embassy_sync::signal::Signal;
@@ -92,12 +96,14 @@ impl<'hw> Timers<'hw> {
     fn new(
         ac_expiration_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         ac_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+        ac_wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         dc_expiration_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         dc_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+        dc_wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
     ) -> Self {
         Self {
-            ac_timer: Timer::new(ac_expiration_storage, ac_policy_storage),
-            dc_timer: Timer::new(dc_expiration_storage, dc_policy_storage),
+            ac_timer: Timer::new(ac_expiration_storage, ac_policy_storage, ac_wake_status_storage),
+            dc_timer: Timer::new(dc_expiration_storage, dc_policy_storage, dc_wake_status_storage),
         }
     }
 }
@@ -123,8 +129,10 @@ impl<'hw> ServiceInner<'hw> {
         tz_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         ac_expiration_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         ac_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+        ac_wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         dc_expiration_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         dc_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+        dc_wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
     ) -> Self {
         Self {
             clock_state: Mutex::new(RefCell::new(ClockState {
@@ -135,8 +143,10 @@ impl<'hw> ServiceInner<'hw> {
             timers: Timers::new(
                 ac_expiration_storage,
                 ac_policy_storage,
+                ac_wake_status_storage,
                 dc_expiration_storage,
                 dc_policy_storage,
+                dc_wake_status_storage,
             ),
             capabilities: {
                 // TODO [CONFIG] We could consider making some of these user-configurable, e.g. if we want to support devices that don't have a battery
@@ -382,16 +392,20 @@ impl<'hw> Service<'hw> {
         tz_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         ac_expiration_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         ac_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+        ac_wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         dc_expiration_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         dc_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+        dc_wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
     ) -> Result<(Self, Runner<'hw>), DatetimeClockError> {
         let service = service_storage.inner.insert(ServiceInner::new(
             backing_clock,
             tz_storage,
             ac_expiration_storage,
             ac_policy_storage,
+            ac_wake_status_storage,
             dc_expiration_storage,
             dc_policy_storage,
+            dc_wake_status_storage,
         ));
 
         // TODO [POWER_SOURCE] we need to subscribe to messages that tell us if we're on AC or DC power so we can decide which alarms to trigger, but those notifications are not yet implemented - revisit when they are.
@@ -25,7 +25,7 @@ enum WakeState {
 
 mod persistent_storage {
     use crate::NvramStorage;
-    use crate::{AlarmExpiredWakePolicy, Datetime};
+    use crate::{AlarmExpiredWakePolicy, Datetime, TimerStatus};
 
     pub struct PersistentStorage<'hw> {
         /// When the timer is programmed to expire, or None if the timer is not set
@@ -35,21 +35,32 @@ mod persistent_storage {
 
         // Persistent storage for the AlarmExpiredWakePolicy
         wake_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+
+        /// Persistent storage for the TimerStatus reported by _GWS.
+        /// This needs to survive a reset so that the host can still see that the timer woke the system if the wake
+        /// involved resetting the EC.
+        wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
     }
 
     impl<'hw> PersistentStorage<'hw> {
         pub fn new(
             expiration_time_storage: &'hw mut dyn NvramStorage<'hw, u32>,
             wake_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+            wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         ) -> Self {
             Self {
                 expiration_time_storage,
                 wake_policy_storage,
+                wake_status_storage,
             }
         }
 
         const NO_EXPIRATION_TIME: u32 = u32::MAX;
 
+        const ERASED_WAKE_STATUS: u32 = u32::MAX;
+        const WAKE_STATUS_TIMER_EXPIRED: u32 = 1 << 0;
+        const WAKE_STATUS_TIMER_TRIGGERED_WAKE: u32 = 1 << 1;
+
         pub fn get_timer_wake_policy(&self) -> AlarmExpiredWakePolicy {
             AlarmExpiredWakePolicy(self.wake_policy_storage.read())
         }
@@ -58,6 +69,30 @@ mod persistent_storage {
             self.wake_policy_storage.write(wake_policy.0);
         }
 
+        /// Retrieves the stored wake status.  Erased NVRAM reads as all ones and is treated as no status, like
+        /// NO_EXPIRATION_TIME for the expiration time.  Otherwise bits that we don't know about are ignored.
+        pub fn get_wake_status(&self) -> TimerStatus {
+            let raw = self.wake_status_storage.read();
+            if raw == Self::ERASED_WAKE_STATUS {
+                return TimerStatus::default();
+            }
+            let mut status = TimerStatus::default();
+            status.set_timer_expired(raw & Self::WAKE_STATUS_TIMER_EXPIRED != 0);
+            status.set_timer_triggered_wake(raw & Self::WAKE_STATUS_TIMER_TRIGGERED_WAKE != 0);
+            status
+        }
+
+        pub fn set_wake_status(&mut self, status: TimerStatus) {
+            let mut raw = 0;
+            if status.timer_expired() {
+                raw |= Self::WAKE_STATUS_TIMER_EXPIRED;
+            }
+            if status.timer_triggered_wake() {
+                raw |= Self::WAKE_STATUS_TIMER_TRIGGERED_WAKE;
+            }
+            self.wake_status_storage.write(raw);
+        }
+
         pub fn get_expiration_time(&self) -> Option<Datetime> {
             match self.expiration_time_storage.read() {
                 Self::NO_EXPIRATION_TIME => None,
@@ -85,8 +120,6 @@ struct TimerState<'hw> {
 
     wake_state: WakeState,
 
-    timer_status: TimerStatus,
-
     // Whether or not this timer is currently active (i.e. the system is on the power source this timer manages)
     // Even if it's not active, it still counts down if it's programmed - it just won't trigger a wake event if it expires while inactive.
     is_active: bool,
@@ -102,12 +135,16 @@ impl<'hw> Timer<'hw> {
     pub fn new(
         expiration_time_storage: &'hw mut dyn NvramStorage<'hw, u32>,
         wake_policy_storage: &'hw mut dyn NvramStorage<'hw, u32>,
+        wake_status_storage: &'hw mut dyn NvramStorage<'hw, u32>,
     ) -> Self {
         Self {
             timer_state: Mutex::new(RefCell::new(TimerState {
-                persistent_storage: PersistentStorage::new(expiration_time_storage, wake_policy_storage),
+                persistent_storage: PersistentStorage::new(
+                    expiration_time_storage,
+                    wake_policy_storage,
+                    wake_status_storage,
+                ),
                 wake_state: WakeState::Clear,
-                timer_status: Default::default(),
                 is_active: false,
             })),
             timer_signal: Signal::new(),
@@ -119,6 +156,12 @@ impl<'hw> Timer<'hw> {
         clock_state: &Mutex<GlobalRawMutex, RefCell<ClockState<'hw>>>,
         active: bool,
     ) -> Result<(), DatetimeClockError> {
+        // Re-arming the timer below resets the wake status, so grab it first and restore it afterwards so that wakes
+        // which crossed a reset are still reported by _GWS.
+        let wake_status = self
+            .timer_state
+            .lock(|timer_state| timer_state.borrow().persistent_storage.get_wake_status());
+
         self.set_timer_wake_policy(
             clock_state,
             self.timer_state
@@ -131,22 +174,25 @@ impl<'hw> Timer<'hw> {
                 .lock(|timer_state| timer_state.borrow().persistent_storage.get_expiration_time()),
         )?;
 
+        self.timer_state
+            .lock(|timer_state| timer_state.borrow_mut().persistent_storage.set_wake_status(wake_status));
+
         self.set_active(clock_state, active);
 
         Ok(())
     }
 
     pub fn get_wake_status(&self) -> TimerStatus {
-        self.timer_state.lock(|timer_state| {
-            let timer_state = timer_state.borrow();
-            timer_state.timer_status
-        })
+        self.timer_state
+            .lock(|timer_state| timer_state.borrow().persistent_storage.get_wake_status())
     }
 
     pub fn clear_wake_status(&self) {
         self.timer_state.lock(|timer_state| {
-            let mut timer_state = timer_state.borrow_mut();
-            timer_state.timer_status = Default::default();
+            timer_state
+                .borrow_mut()
+                .persistent_storage
+                .set_wake_status(Default::default());
         });
     }
 
@@ -182,7 +228,7 @@ impl<'hw> Timer<'hw> {
             let mut timer_state = timer_state.borrow_mut();
 
             // Per ACPI 6.4 section 9.18.1: "The status of wake timers can be reset by setting the wake alarm".
-            timer_state.timer_status = Default::default();
+            timer_state.persistent_storage.set_wake_status(Default::default());
 
             match expiration_time {
                 Some(dt) => {
@@ -352,9 +398,14 @@ impl<'hw> Timer<'hw> {
                         }
                     }
 
-                    timer_state.timer_status.set_timer_expired(true);
+                    let mut wake_status = timer_state.persistent_storage.get_wake_status();
+                    wake_status.set_timer_expired(true);
+                    if timer_state.is_active {
+                        wake_status.set_timer_triggered_wake(true);
+                    }
+                    timer_state.persistent_storage.set_wake_status(wake_status);
+
                     if timer_state.is_active {
-                        timer_state.timer_status.set_timer_triggered_wake(true);
                         timer_state
                             .persistent_storage
                             .set_timer_wake_policy(AlarmExpiredWakePolicy::NEVER);
@@ -2,13 +2,18 @@
 #![allow(clippy::unwrap_used)]
 #![allow(clippy::expect_used)]
 
+use odp_test_support as _;
+
 #[cfg(test)]
 mod test {
     use embassy_time::Timer;
+    use embedded_mcu_hal::nvram::NvramStorage;
     use embedded_mcu_hal::time::{Datetime, DatetimeClock};
     use odp_service_common::runnable_service::ServiceRunner;
 
-    use time_alarm_service_interface::{AcpiDaylightSavingsTimeStatus, AcpiTimeZone, AcpiTimestamp, TimeAlarmService};
+    use time_alarm_service_interface::{
+        AcpiDaylightSavingsTimeStatus, AcpiTimeZone, AcpiTimerId, AcpiTimestamp, AlarmTimerSeconds, TimeAlarmService,
+    };
 
     use time_alarm_service::mock::*;
 
@@ -17,8 +22,10 @@ mod test {
         let mut tz_storage = MockNvramStorage::new(0);
         let mut ac_exp_storage = MockNvramStorage::new(0);
         let mut ac_pol_storage = MockNvramStorage::new(0);
+        let mut ac_status_storage = MockNvramStorage::new(0);
         let mut dc_exp_storage = MockNvramStorage::new(0);
         let mut dc_pol_storage = MockNvramStorage::new(0);
+        let mut dc_status_storage = MockNvramStorage::new(0);
 
         let mut clock = MockDatetimeClock::new_running();
         let mut storage = Default::default();
@@ -29,8 +36,10 @@ mod test {
             &mut tz_storage,
             &mut ac_exp_storage,
             &mut ac_pol_storage,
+            &mut ac_status_storage,
             &mut dc_exp_storage,
             &mut dc_pol_storage,
+            &mut dc_status_storage,
         )
         .await
         .unwrap();
@@ -62,8 +71,10 @@ mod test {
         let mut tz_storage = MockNvramStorage::new(0);
         let mut ac_exp_storage = MockNvramStorage::new(0);
         let mut ac_pol_storage = MockNvramStorage::new(0);
+        let mut ac_status_storage = MockNvramStorage::new(0);
         let mut dc_exp_storage = MockNvramStorage::new(0);
         let mut dc_pol_storage = MockNvramStorage::new(0);
+        let mut dc_status_storage = MockNvramStorage::new(0);
 
         let mut clock = MockDatetimeClock::new_paused();
         const TEST_UNIX_TIME: u64 = 1_234_567_890;
@@ -77,8 +88,10 @@ mod test {
             &mut tz_storage,
             &mut ac_exp_storage,
             &mut ac_pol_storage,
+            &mut ac_status_storage,
             &mut dc_exp_storage,
             &mut dc_pol_storage,
+            &mut dc_status_storage,
         )
         .await
         .unwrap();
@@ -103,4 +116,114 @@ mod test {
             } => {}
         }
     }
+
+    #[tokio::test]
+    async fn test_wake_status_persists_across_reset() {
+        // Start with both timers disarmed.
+        let mut tz_storage = MockNvramStorage::new(0);
+        let mut ac_exp_storage = MockNvramStorage::new(u32::MAX);
+        let mut ac_pol_storage = MockNvramStorage::new(0);
+        let mut ac_status_storage = MockNvramStorage::new(0);
+        let mut dc_exp_storage = MockNvramStorage::new(u32::MAX);
+        let mut dc_pol_storage = MockNvramStorage::new(0);
+        let mut dc_status_storage = MockNvramStorage::new(0);
+
+        let mut clock = MockDatetimeClock::new_running();
+
+        // Arm the AC timer and let it expire while AC is the active power source.
+        {
+            let mut storage = Default::default();
+            let (service, runner) = time_alarm_service::Service::new(
+                &mut storage,
+                &mut clock,
+                &mut tz_storage,
+                &mut ac_exp_storage,
+                &mut ac_pol_storage,
+                &mut ac_status_storage,
+                &mut dc_exp_storage,
+                &mut dc_pol_storage,
+                &mut dc_status_storage,
+            )
+            .await
+            .unwrap();
+
+            tokio::select! {
+                _ = runner.run() => unreachable!("time alarm service task finished unexpectedly"),
+                _ = async {
+                    service.set_timer_value(AcpiTimerId::AcPower, AlarmTimerSeconds(1)).unwrap();
+                    Timer::after(embassy_time::Duration::from_secs(3)).await;
+
+                    let status = service.get_wake_status(AcpiTimerId::AcPower);
+                    assert!(status.timer_expired());
+                    assert!(status.timer_triggered_wake());
+                } => {}
+            }
+        }
+
+        // Simulate a reset by recreating the service on top of the same NVRAM.
+        {
+            let mut storage = Default::default();
+            let (service, _runner) = time_alarm_service::Service::new(
+                &mut storage,
+                &mut clock,
+                &mut tz_storage,
+                &mut ac_exp_storage,
+                &mut ac_pol_storage,
+                &mut ac_status_storage,
+                &mut dc_exp_storage,
+                &mut dc_pol_storage,
+                &mut dc_status_storage,
+            )
+            .await
+            .unwrap();
+
+            let status = service.get_wake_status(AcpiTimerId::AcPower);
+            assert!(status.timer_expired());
+            assert!(status.timer_triggered_wake());
+
+            let status = service.get_wake_status(AcpiTimerId::DcPower);
+            assert!(!status.timer_expired());
+            assert!(!status.timer_triggered_wake());
+
+            service.clear_wake_status(AcpiTimerId::AcPower);
+        }
+
+        // Clearing the status must be persisted as well.
+        assert_eq!(ac_status_storage.read(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_wake_status_erased_nvram() {
+        // Erased NVRAM reads as all ones, which must not be reported as a timer wake.
+        let mut tz_storage = MockNvramStorage::new(0);
+        let mut ac_exp_storage = MockNvramStorage::new(u32::MAX);
+        let mut ac_pol_storage = MockNvramStorage::new(0);
+        let mut ac_status_storage = MockNvramStorage::new(u32::MAX);
+        let mut dc_exp_storage = MockNvramStorage::new(u32::MAX);
+        let mut dc_pol_storage = MockNvramStorage::new(0);
+        let mut dc_status_storage = MockNvramStorage::new(u32::MAX);
+
+        let mut clock = MockDatetimeClock::new_running();
+
+        let mut storage = Default::default();
+        let (service, _runner) = time_alarm_service::Service::new(
+            &mut storage,
+            &mut clock,
+            &mut tz_storage,
+            &mut ac_exp_storage,
+            &mut ac_pol_storage,
+            &mut ac_status_storage,
+            &mut dc_exp_storage,
+            &mut dc_pol_storage,
+            &mut dc_status_storage,
+        )
+        .await
+        .unwrap();
+
+        for timer_id in [AcpiTimerId::AcPower, AcpiTimerId::DcPower] {
+            let status = service.get_wake_status(timer_id);
+            assert!(!status.timer_expired());
+            assert!(!status.timer_triggered_wake());
+        }
+    }
 }
@@ -0,0 +1,25 @@
+//! Mock implementation of [`type_c_interface::controller::current_limit::CurrentLimit`]
+
+use embedded_usb_pd::{LocalPortId, PdError};
+use type_c_interface::controller::current_limit::CurrentLimit;
+
+use super::FnCall as ControllerFnCall;
+use super::Mock;
+
+/// Contains a [`CurrentLimit`] function call and its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FnCall {
+    SetCurrentLimit(LocalPortId, Option<u16>),
+}
+
+impl CurrentLimit for Mock {
+    async fn set_current_limit(&mut self, port: LocalPortId, current_ma: Option<u16>) -> Result<(), PdError> {
+        self.fn_calls
+            .push_back(ControllerFnCall::CurrentLimit(FnCall::SetCurrentLimit(
+                port, current_ma,
+            )));
+        self.next_result_set_current_limit
+            .pop_front()
+            .expect("next_result_set_current_limit not set")
+    }
+}
@@ -9,16 +9,27 @@ use type_c_interface::control::{
     pd::PortStatus,
     vdm::{AttnVdm, OtherVdm},
 };
+use type_c_interface::controller::Controller;
 
+pub mod current_limit;
 pub mod max_sink_voltage;
 pub mod pd;
+pub mod port_enable;
+mod script;
+pub mod temperature;
+pub mod type_c;
 pub mod ucsi;
 
 /// Contains a controller function call and its arguments
 pub enum FnCall {
+    ResetController,
     Pd(pd::FnCall),
     Ucsi(ucsi::FnCall),
     MaxSinkVoltage(max_sink_voltage::FnCall),
+    CurrentLimit(current_limit::FnCall),
+    PortEnable(port_enable::FnCall),
+    Temperature(temperature::FnCall),
+    TypeC(type_c::FnCall),
 }
 
 /// Mock PD controller for use in tests
@@ -26,6 +37,8 @@ pub struct Mock {
     name: &'static str,
     /// Recorded function calls
     pub fn_calls: VecDeque<FnCall>,
+    /// Next results to return for [`type_c_interface::controller::Controller::reset_controller`]
+    pub next_result_reset_controller: VecDeque<Result<(), PdError>>,
     /// Next results to return for [`type_c_interface::controller::pd::Pd::get_port_status`]
     pub next_result_get_port_status: VecDeque<Result<PortStatus, PdError>>,
     /// Next results to return for [`type_c_interface::controller::pd::Pd::clear_dead_battery_flag`]
@@ -58,6 +71,8 @@ pub struct Mock {
     pub next_result_execute_lpm_command: VecDeque<Result<Option<embedded_usb_pd::ucsi::lpm::ResponseData>, PdError>>,
     /// Next results to return for [`type_c_interface::controller::pd::Pd::hard_reset`]
     pub next_result_hard_reset: VecDeque<Result<(), PdError>>,
+    /// Next results to return for [`type_c_interface::controller::pd::Pd::execute_dr_swap`]
+    pub next_result_execute_dr_swap: VecDeque<Result<(), PdError>>,
     /// Next results to return for [`type_c_interface::controller::pd::Pd::get_discovered_svids`]
     pub next_result_get_discovered_svids: VecDeque<Result<type_c_interface::control::svid::DiscoveredSvids, PdError>>,
     /// Next results to return for [`type_c_interface::controller::pd::Pd::get_discover_identity_sop_response`]
@@ -67,6 +82,20 @@ pub struct Mock {
     pub next_result_get_discover_identity_sop_prime_response: VecDeque<
         Result<embedded_usb_pd::vdm::structured::command::discover_identity::sop_prime::ResponseVdos, PdError>,
     >,
+    /// Next results to return for [`type_c_interface::controller::pd::Pd::get_partner_pdos`]
+    pub next_result_get_partner_pdos: VecDeque<Result<type_c_interface::control::pd::Pdos, PdError>>,
+    /// Next results to return for [`type_c_interface::controller::pd::Pd::set_pd_state_machine_config`]
+    pub next_result_set_pd_state_machine_config: VecDeque<Result<(), PdError>>,
+    /// Next results to return for [`type_c_interface::controller::port_enable::PortEnable::set_port_enable`]
+    pub next_result_set_port_enable: VecDeque<Result<(), PdError>>,
+    /// Next results to return for [`type_c_interface::controller::type_c::StateMachine::set_type_c_state_machine_config`]
+    pub next_result_set_type_c_state_machine_config: VecDeque<Result<(), PdError>>,
+    /// Next results to return for [`type_c_interface::controller::type_c::StateMachine::set_try_role`]
+    pub next_result_set_try_role: VecDeque<Result<(), PdError>>,
+    /// Next results to return for [`type_c_interface::controller::temperature::Temperature::connector_temperature`]
+    pub next_result_connector_temperature: VecDeque<Result<f32, PdError>>,
+    /// Next results to return for [`type_c_interface::controller::current_limit::CurrentLimit::set_current_limit`]
+    pub next_result_set_current_limit: VecDeque<Result<(), PdError>>,
 }
 
 impl Mock {
@@ -75,6 +104,7 @@ impl Mock {
         Self {
             fn_calls: VecDeque::new(),
             name,
+            next_result_reset_controller: VecDeque::new(),
             next_result_get_port_status: VecDeque::new(),
             next_result_clear_dead_battery_flag: VecDeque::new(),
             next_result_enable_sink_path: VecDeque::new(),
@@ -91,9 +121,17 @@ impl Mock {
             next_result_set_usb_control: VecDeque::new(),
             next_result_execute_lpm_command: VecDeque::new(),
             next_result_hard_reset: VecDeque::new(),
+            next_result_execute_dr_swap: VecDeque::new(),
             next_result_get_discovered_svids: VecDeque::new(),
             next_result_get_discover_identity_sop_response: VecDeque::new(),
             next_result_get_discover_identity_sop_prime_response: VecDeque::new(),
+            next_result_get_partner_pdos: VecDeque::new(),
+            next_result_set_pd_state_machine_config: VecDeque::new(),
+            next_result_set_port_enable: VecDeque::new(),
+            next_result_set_type_c_state_machine_config: VecDeque::new(),
+            next_result_set_try_role: VecDeque::new(),
+            next_result_connector_temperature: VecDeque::new(),
+            next_result_set_current_limit: VecDeque::new(),
         }
     }
 }
@@ -103,3 +141,12 @@ impl Named for Mock {
         self.name
     }
 }
+
+impl Controller for Mock {
+    async fn reset_controller(&mut self) -> Result<(), PdError> {
+        self.fn_calls.push_back(FnCall::ResetController);
+        self.next_result_reset_controller
+            .pop_front()
+            .expect("next_result_reset_controller not set")
+    }
+}
@@ -1,10 +1,10 @@
 //! Mock implementation of [`type_c_interface::controller::pd::Pd`]
 
-use embedded_usb_pd::{LocalPortId, PdError, ado::Ado};
+use embedded_usb_pd::{LocalPortId, PdError, PowerRole, ado::Ado};
 use type_c_interface::{
     control::{
         dp::{DpConfig, DpStatus},
-        pd::PortStatus,
+        pd::{PdStateMachineConfig, Pdos, PortStatus},
         tbt::TbtConfig,
         usb::UsbControlConfig,
         vdm::{AttnVdm, OtherVdm, SendVdm},
@@ -32,9 +32,12 @@ pub enum FnCall {
     SetTbtConfig(LocalPortId, TbtConfig),
     SetUsbControl(LocalPortId, UsbControlConfig),
     HardReset(LocalPortId),
+    ExecuteDrSwap(LocalPortId),
     GetDiscoveredSvids(LocalPortId),
     GetDiscoverIdentitySopResponse(LocalPortId),
     GetDiscoverIdentitySopPrimeResponse(LocalPortId),
+    GetPartnerPdos(LocalPortId, PowerRole),
+    SetPdStateMachineConfig(LocalPortId, PdStateMachineConfig),
 }
 
 impl Pd for Mock {
@@ -144,6 +147,14 @@ impl Pd for Mock {
             .expect("next_result_hard_reset not set")
     }
 
+    async fn execute_dr_swap(&mut self, port: LocalPortId) -> Result<(), PdError> {
+        self.fn_calls
+            .push_back(ControllerFnCall::Pd(FnCall::ExecuteDrSwap(port)));
+        self.next_result_execute_dr_swap
+            .pop_front()
+            .expect("next_result_execute_dr_swap not set")
+    }
+
     async fn get_discovered_svids(
         &mut self,
         port: LocalPortId,
@@ -176,4 +187,24 @@ impl Pd for Mock {
             .pop_front()
             .expect("next_result_get_discover_identity_sop_prime_response not set")
     }
+
+    async fn get_partner_pdos(&mut self, port: LocalPortId, role: PowerRole) -> Result<Pdos, PdError> {
+        self.fn_calls
+            .push_back(ControllerFnCall::Pd(FnCall::GetPartnerPdos(port, role)));
+        self.next_result_get_partner_pdos
+            .pop_front()
+            .expect("next_result_get_partner_pdos not set")
+    }
+
+    async fn set_pd_state_machine_config(
+        &mut self,
+        port: LocalPortId,
+        config: PdStateMachineConfig,
+    ) -> Result<(), PdError> {
+        self.fn_calls
+            .push_back(ControllerFnCall::Pd(FnCall::SetPdStateMachineConfig(port, config)));
+        self.next_result_set_pd_state_machine_config
+            .pop_front()
+            .expect("next_result_set_pd_state_machine_config not set")
+    }
 }
@@ -0,0 +1,130 @@
+//! Scripting and assertion helpers for common controller flows
+//!
+//! The scripting functions queue the results the port will read back and return the interrupt the controller
+//! would raise. Send the returned interrupt to the port's event receiver to drive the flow end-to-end.
+
+use embedded_usb_pd::ado::Ado;
+use type_c_interface::control::pd::PortStatus;
+use type_c_interface::port::event::PortEventBitfield;
+
+use super::{FnCall, Mock, pd};
+
+impl Mock {
+    /// Script an attach reporting `status`
+    ///
+    /// The returned interrupt contains a new consumer contract and sink ready if `status` has a sink contract
+    /// and a new provider contract if `status` has a source contract.
+    pub fn script_attach(&mut self, status: PortStatus) -> PortEventBitfield {
+        let mut interrupt = PortEventBitfield::none();
+        interrupt.status.set_plug_inserted_or_removed(true);
+        if status.available_sink_contract.is_some() {
+            interrupt.status.set_new_power_contract_as_consumer(true);
+            interrupt.status.set_sink_ready(true);
+        }
+        if status.available_source_contract.is_some() {
+            interrupt.status.set_new_power_contract_as_provider(true);
+        }
+
+        self.next_result_get_port_status.push_back(Ok(status));
+        interrupt
+    }
+
+    /// Script a detach
+    pub fn script_detach(&mut self) -> PortEventBitfield {
+        let mut interrupt = PortEventBitfield::none();
+        interrupt.status.set_plug_inserted_or_removed(true);
+
+        self.next_result_get_port_status.push_back(Ok(PortStatus::default()));
+        interrupt
+    }
+
+    /// Script a PD alert
+    pub fn script_pd_alert(&mut self, ado: Ado) -> PortEventBitfield {
+        let mut interrupt = PortEventBitfield::none();
+        interrupt.notification.set_alert(true);
+
+        self.next_result_get_pd_alert.push_back(Ok(Some(ado)));
+        interrupt
+    }
+
+    /// Assert that the next recorded calls are exactly the given [`pd::Pd`](type_c_interface::controller::pd::Pd)
+    /// calls, in order, and consume them
+    pub fn assert_pd_calls(&mut self, expected: &[pd::FnCall]) {
+        for (i, expected) in expected.iter().enumerate() {
+            let call = self.fn_calls.pop_front();
+            assert!(
+                matches!(&call, Some(FnCall::Pd(call)) if call == expected),
+                "{}: call {} does not match expected {:?}",
+                self.name,
+                i,
+                expected
+            );
+        }
+    }
+
+    /// Assert that no calls have been recorded since the last time they were consumed
+    pub fn assert_no_fn_calls(&self) {
+        assert!(
+            self.fn_calls.is_empty(),
+            "{}: {} unexpected calls recorded",
+            self.name,
+            self.fn_calls.len()
+        );
+    }
+
+    /// Assert that every scripted result has been consumed
+    pub fn assert_results_consumed(&self) {
+        let remaining = [
+            ("reset_controller", self.next_result_reset_controller.len()),
+            ("get_port_status", self.next_result_get_port_status.len()),
+            (
+                "clear_dead_battery_flag",
+                self.next_result_clear_dead_battery_flag.len(),
+            ),
+            ("enable_sink_path", self.next_result_enable_sink_path.len()),
+            ("set_max_sink_voltage", self.next_result_set_max_sink_voltage.len()),
+            ("get_pd_alert", self.next_result_get_pd_alert.len()),
+            (
+                "set_unconstrained_power",
+                self.next_result_set_unconstrained_power.len(),
+            ),
+            ("get_other_vdm", self.next_result_get_other_vdm.len()),
+            ("get_attn_vdm", self.next_result_get_attn_vdm.len()),
+            ("send_vdm", self.next_result_send_vdm.len()),
+            ("execute_drst", self.next_result_execute_drst.len()),
+            ("get_dp_status", self.next_result_get_dp_status.len()),
+            ("set_dp_config", self.next_result_set_dp_config.len()),
+            ("set_tbt_config", self.next_result_set_tbt_config.len()),
+            ("set_usb_control", self.next_result_set_usb_control.len()),
+            ("execute_lpm_command", self.next_result_execute_lpm_command.len()),
+            ("hard_reset", self.next_result_hard_reset.len()),
+            ("execute_dr_swap", self.next_result_execute_dr_swap.len()),
+            ("get_discovered_svids", self.next_result_get_discovered_svids.len()),
+            (
+                "get_discover_identity_sop_response",
+                self.next_result_get_discover_identity_sop_response.len(),
+            ),
+            (
+                "get_discover_identity_sop_prime_response",
+                self.next_result_get_discover_identity_sop_prime_response.len(),
+            ),
+            ("get_partner_pdos", self.next_result_get_partner_pdos.len()),
+            (
+                "set_pd_state_machine_config",
+                self.next_result_set_pd_state_machine_config.len(),
+            ),
+            ("set_port_enable", self.next_result_set_port_enable.len()),
+            (
+                "set_type_c_state_machine_config",
+                self.next_result_set_type_c_state_machine_config.len(),
+            ),
+            ("set_try_role", self.next_result_set_try_role.len()),
+            ("connector_temperature", self.next_result_connector_temperature.len()),
+            ("set_current_limit", self.next_result_set_current_limit.len()),
+        ];
+
+        for (function, count) in remaining {
+            assert_eq!(count, 0, "{}: {} unconsumed results for {}", self.name, count, function);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Mock implementation of [`type_c_interface::controller::temperature::Temperature`]
+
+use embedded_usb_pd::{LocalPortId, PdError};
+use type_c_interface::controller::temperature::Temperature;
+
+use super::FnCall as ControllerFnCall;
+use super::Mock;
+
+/// Contains a [`Temperature`] function call and its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FnCall {
+    ConnectorTemperature(LocalPortId),
+}
+
+impl Temperature for Mock {
+    async fn connector_temperature(&mut self, port: LocalPortId) -> Result<f32, PdError> {
+        self.fn_calls
+            .push_back(ControllerFnCall::Temperature(FnCall::ConnectorTemperature(port)));
+        self.next_result_connector_temperature
+            .pop_front()
+            .expect("next_result_connector_temperature not set")
+    }
+}
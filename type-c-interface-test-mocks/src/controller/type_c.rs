@@ -0,0 +1,37 @@
+//! Mock implementation of [`type_c_interface::controller::type_c::StateMachine`]
+
+use embedded_usb_pd::{LocalPortId, PdError};
+use type_c_interface::control::type_c::{TryRole, TypeCStateMachineState};
+use type_c_interface::controller::type_c::StateMachine;
+
+use super::FnCall as ControllerFnCall;
+use super::Mock;
+
+/// Contains a [`StateMachine`] function call and its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FnCall {
+    SetTypeCStateMachineConfig(LocalPortId, TypeCStateMachineState),
+    SetTryRole(LocalPortId, TryRole),
+}
+
+impl StateMachine for Mock {
+    async fn set_type_c_state_machine_config(
+        &mut self,
+        port: LocalPortId,
+        state: TypeCStateMachineState,
+    ) -> Result<(), PdError> {
+        self.fn_calls
+            .push_back(ControllerFnCall::TypeC(FnCall::SetTypeCStateMachineConfig(port, state)));
+        self.next_result_set_type_c_state_machine_config
+            .pop_front()
+            .expect("next_result_set_type_c_state_machine_config not set")
+    }
+
+    async fn set_try_role(&mut self, port: LocalPortId, role: TryRole) -> Result<(), PdError> {
+        self.fn_calls
+            .push_back(ControllerFnCall::TypeC(FnCall::SetTryRole(port, role)));
+        self.next_result_set_try_role
+            .pop_front()
+            .expect("next_result_set_try_role not set")
+    }
+}
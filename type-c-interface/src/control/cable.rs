@@ -0,0 +1,123 @@
+//! Cable capabilities discovered through the cable eMarker
+
+/// VBUS current handling of a cable
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CableCurrent {
+    /// 3A, also assumed for cables without an eMarker
+    #[default]
+    Current3A,
+    /// 5A
+    Current5A,
+}
+
+impl CableCurrent {
+    /// Maximum current the cable can carry in mA
+    pub const fn max_current_ma(self) -> u16 {
+        match self {
+            CableCurrent::Current3A => 3000,
+            CableCurrent::Current5A => 5000,
+        }
+    }
+}
+
+/// Highest USB data speed supported by a cable
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CableSpeed {
+    /// USB 2.0 only, also assumed for cables without an eMarker
+    #[default]
+    Usb20,
+    /// USB 3.2 Gen1
+    Usb32Gen1,
+    /// USB 3.2 Gen2 or USB4 Gen2
+    Usb4Gen2,
+    /// USB4 Gen3
+    Usb4Gen3,
+    /// USB4 Gen4
+    Usb4Gen4,
+}
+
+/// Cable discovery results
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CableInfo {
+    /// The cable responded to SOP' Discover Identity
+    pub emarker: bool,
+    /// VBUS current handling
+    pub current: CableCurrent,
+    /// Highest USB data speed
+    pub speed: CableSpeed,
+}
+
+impl CableInfo {
+    /// Cable without an eMarker
+    /// Needed because default() is not const
+    pub const fn new() -> Self {
+        Self {
+            emarker: false,
+            current: CableCurrent::Current3A,
+            speed: CableSpeed::Usb20,
+        }
+    }
+
+    /// Decode a passive or active cable VDO from an SOP' Discover Identity response
+    pub const fn from_cable_vdo(vdo: u32) -> Self {
+        let current = match (vdo >> 5) & 0x3 {
+            0b10 => CableCurrent::Current5A,
+            _ => CableCurrent::Current3A,
+        };
+        let speed = match vdo & 0x7 {
+            0b001 => CableSpeed::Usb32Gen1,
+            0b010 => CableSpeed::Usb4Gen2,
+            0b011 => CableSpeed::Usb4Gen3,
+            0b100 => CableSpeed::Usb4Gen4,
+            _ => CableSpeed::Usb20,
+        };
+
+        Self {
+            emarker: true,
+            current,
+            speed,
+        }
+    }
+
+    /// Maximum current the cable can carry in mA
+    ///
+    /// Only cables with an eMarker can be rated above 3A.
+    pub const fn max_current_ma(&self) -> u16 {
+        if self.emarker {
+            self.current.max_current_ma()
+        } else {
+            CableCurrent::Current3A.max_current_ma()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_cable_vdo() {
+        // 5A USB4 Gen3 passive cable
+        let cable = CableInfo::from_cable_vdo(0x0000_0043);
+        assert!(cable.emarker);
+        assert_eq!(cable.current, CableCurrent::Current5A);
+        assert_eq!(cable.speed, CableSpeed::Usb4Gen3);
+        assert_eq!(cable.max_current_ma(), 5000);
+
+        // 3A USB 2.0 passive cable
+        let cable = CableInfo::from_cable_vdo(0x0000_0020);
+        assert_eq!(cable.current, CableCurrent::Current3A);
+        assert_eq!(cable.speed, CableSpeed::Usb20);
+        assert_eq!(cable.max_current_ma(), 3000);
+
+        // A 5A rating is ignored without an eMarker
+        let cable = CableInfo {
+            current: CableCurrent::Current5A,
+            ..CableInfo::new()
+        };
+        assert_eq!(cable.max_current_ma(), 3000);
+    }
+}
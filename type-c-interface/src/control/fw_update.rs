@@ -0,0 +1,23 @@
+//! Port transitions around a controller firmware update
+
+/// Firmware update notice reported to the host
+///
+/// The discriminant is the reason code reported to the host.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum FwUpdateNotice {
+    /// A controller firmware update is starting, the port is reduced or detached until it completes
+    Started = 0,
+    /// The update completed and the port was restored
+    Completed = 1,
+    /// The update didn't complete in time and the port was restored
+    TimedOut = 2,
+}
+
+impl FwUpdateNotice {
+    /// Reason code reported to the host
+    pub const fn reason_code(self) -> u8 {
+        self as u8
+    }
+}
@@ -1,5 +1,8 @@
 //! Shared types for controlling a PD port
+pub mod cable;
 pub mod dp;
+pub mod fw_update;
+pub mod otp;
 pub mod pd;
 pub mod power;
 pub mod retimer;
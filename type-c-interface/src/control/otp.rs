@@ -0,0 +1,24 @@
+//! Connector over-temperature protection
+
+/// Over-temperature protection state of a port
+///
+/// The discriminant is the reason code reported to the host.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum OtpState {
+    /// Connector temperature is normal
+    #[default]
+    Normal = 0,
+    /// Connector temperature exceeded the warning threshold, port current is limited
+    Throttled = 1,
+    /// Connector temperature exceeded the critical threshold, the port is disabled
+    Shutdown = 2,
+}
+
+impl OtpState {
+    /// Reason code reported to the host
+    pub const fn reason_code(self) -> u8 {
+        self as u8
+    }
+}
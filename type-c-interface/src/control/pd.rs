@@ -6,6 +6,8 @@ use embedded_usb_pd::{
     type_c::ConnectionState,
 };
 
+use crate::control::cable::CableInfo;
+
 /// Port status
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -32,6 +34,10 @@ pub struct PortStatus {
     pub epr: bool,
     /// Port partner is unconstrained
     pub unconstrained_power: bool,
+    /// Data role swap state, maintained by the type-C service
+    pub data_role_swap: DataRoleSwapState,
+    /// Cable discovered on the current connection
+    pub cable: CableInfo,
 }
 
 impl PortStatus {
@@ -50,6 +56,8 @@ impl PortStatus {
             power_path: PowerPathStatus::none(),
             epr: false,
             unconstrained_power: false,
+            data_role_swap: DataRoleSwapState::Idle,
+            cable: CableInfo::new(),
         }
     }
 
@@ -75,10 +83,119 @@ impl Default for PortStatus {
     }
 }
 
+/// Data role swap state
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataRoleSwapState {
+    /// No swap in progress
+    #[default]
+    Idle,
+    /// A DR_Swap has been initiated and is waiting for completion
+    InProgress,
+    /// The last DR_Swap was rejected by the port partner or failed
+    Rejected,
+}
+
 /// PD state-machine configuration
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[derive(Debug, Clone, Default, Copy, PartialEq)]
+#[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
 pub struct PdStateMachineConfig {
     /// Enable or disable the PD state-machine
     pub enabled: bool,
 }
+
+/// Maximum number of PDOs in a capabilities message
+///
+/// EPR capabilities carry up to 7 SPR PDOs followed by up to 4 EPR PDOs.
+pub const MAX_PDOS: usize = 11;
+
+/// Raw PDOs from a capabilities message
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Pdos {
+    pdos: [u32; MAX_PDOS],
+    len: u8,
+}
+
+impl Pdos {
+    /// Create a new set of PDOs, returns `None` if there are more than [`MAX_PDOS`]
+    pub fn new(pdos: &[u32]) -> Option<Self> {
+        let mut new = Self::default();
+        new.pdos.get_mut(..pdos.len())?.copy_from_slice(pdos);
+        new.len = pdos.len() as u8;
+        Some(new)
+    }
+
+    /// The PDOs, in the order they appeared in the capabilities message
+    pub fn as_slice(&self) -> &[u32] {
+        self.pdos.get(..self.len as usize).unwrap_or(&[])
+    }
+}
+
+/// Per-port event counters, used to find unreliable ports
+///
+/// Counters wrap on overflow and are only cleared on an explicit reset.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortStats {
+    /// Number of times a partner attached
+    pub attaches: u32,
+    /// Number of new contracts negotiated while a contract was already in place
+    pub contract_renegotiations: u32,
+    /// Number of PD hard resets
+    pub hard_resets: u32,
+    /// Number of PD alerts received
+    pub alerts: u32,
+    /// Number of VDM notifications received
+    pub vdms: u32,
+}
+
+impl PortStats {
+    /// Length of the serialized counters
+    pub const SERIALIZED_LEN: usize = 20;
+
+    /// Serialize the counters as little-endian `u32`s in field order
+    pub fn to_le_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut bytes = [0u8; Self::SERIALIZED_LEN];
+        let counters = [
+            self.attaches,
+            self.contract_renegotiations,
+            self.hard_resets,
+            self.alerts,
+            self.vdms,
+        ];
+        for (chunk, counter) in bytes.chunks_exact_mut(4).zip(counters) {
+            chunk.copy_from_slice(&counter.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdos_capacity() {
+        let pdos = Pdos::new(&[0x0001_912c, 0x0002_d12c]).unwrap();
+        assert_eq!(pdos.as_slice(), &[0x0001_912c, 0x0002_d12c]);
+        assert!(Pdos::new(&[0; MAX_PDOS]).is_some());
+        assert!(Pdos::new(&[0; MAX_PDOS + 1]).is_none());
+    }
+
+    #[test]
+    fn port_stats_serialization() {
+        let stats = PortStats {
+            attaches: 1,
+            contract_renegotiations: 2,
+            hard_resets: 3,
+            alerts: 0x0102_0304,
+            vdms: u32::MAX,
+        };
+        assert_eq!(
+            stats.to_le_bytes(),
+            [1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 3, 2, 1, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+}
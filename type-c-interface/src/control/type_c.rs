@@ -13,3 +13,16 @@ pub enum TypeCStateMachineState {
     /// Disabled
     Disabled,
 }
+
+/// Role preference applied when attaching to a dual-role partner
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TryRole {
+    /// No preference, the resulting role is determined by the DRP toggle
+    #[default]
+    Disabled,
+    /// Try.SRC, prefer sourcing power
+    TrySrc,
+    /// Try.SNK, prefer sinking power
+    TrySnk,
+}
@@ -0,0 +1,16 @@
+use embedded_usb_pd::{LocalPortId, PdError};
+
+use crate::controller::pd::Pd;
+
+/// Functionality related to limiting the current of a port.
+pub trait CurrentLimit: Pd {
+    /// Limit the current of the given port in mA, `None` removes the limit
+    ///
+    /// The limit applies to both the advertised source current and the requested sink current. This may trigger a
+    /// renegotiation.
+    fn set_current_limit(
+        &mut self,
+        port: LocalPortId,
+        current_ma: Option<u16>,
+    ) -> impl Future<Output = Result<(), PdError>>;
+}
@@ -3,11 +3,14 @@
 use embedded_services::named::Named;
 use embedded_usb_pd::PdError;
 
+pub mod current_limit;
 pub mod electrical_disconnect;
 pub mod max_sink_voltage;
 pub mod pd;
+pub mod port_enable;
 pub mod power;
 pub mod retimer;
+pub mod temperature;
 pub mod type_c;
 
 /// Controller ID
@@ -1,10 +1,10 @@
 use embedded_services::named::Named;
 use embedded_usb_pd::vdm::structured::command::discover_identity::{sop, sop_prime};
-use embedded_usb_pd::{LocalPortId, PdError, ado::Ado};
+use embedded_usb_pd::{LocalPortId, PdError, PowerRole, ado::Ado};
 
 use crate::control::{
     dp::{DpConfig, DpStatus},
-    pd::{PdStateMachineConfig, PortStatus},
+    pd::{PdStateMachineConfig, Pdos, PortStatus},
     svid::DiscoveredSvids,
     tbt::TbtConfig,
     usb::UsbControlConfig,
@@ -42,6 +42,8 @@ pub trait Pd: Named {
     fn execute_drst(&mut self, port: LocalPortId) -> impl Future<Output = Result<(), PdError>>;
     /// Execute a Hard Reset on the given port.
     fn hard_reset(&mut self, port: LocalPortId) -> impl Future<Output = Result<(), PdError>>;
+    /// Initiate a DR_Swap on the given port
+    fn execute_dr_swap(&mut self, port: LocalPortId) -> impl Future<Output = Result<(), PdError>>;
 
     /// Get DisplayPort status for the given port
     fn get_dp_status(&mut self, port: LocalPortId) -> impl Future<Output = Result<DpStatus, PdError>>;
@@ -72,6 +74,11 @@ pub trait Pd: Named {
         &mut self,
         port: LocalPortId,
     ) -> impl Future<Output = Result<sop_prime::ResponseVdos, PdError>>;
+
+    /// Get the PDOs from the port partner's most recent capabilities message.
+    ///
+    /// `role` selects the partner's source or sink capabilities.
+    fn get_partner_pdos(&mut self, port: LocalPortId, role: PowerRole) -> impl Future<Output = Result<Pdos, PdError>>;
 }
 
 /// PD state machine related controller functionality
@@ -0,0 +1,11 @@
+use embedded_usb_pd::{LocalPortId, PdError};
+
+use crate::controller::pd::Pd;
+
+/// Functionality related to reading temperature sensors attached to the controller.
+pub trait Temperature: Pd {
+    /// Read the connector temperature of the given port in degrees Celsius
+    ///
+    /// This is typically a thermistor next to the connector that is sampled by the controller.
+    fn connector_temperature(&mut self, port: LocalPortId) -> impl Future<Output = Result<f32, PdError>>;
+}
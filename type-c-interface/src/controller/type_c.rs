@@ -1,6 +1,9 @@
 use embedded_usb_pd::{LocalPortId, PdError};
 
-use crate::{control::type_c::TypeCStateMachineState, controller::pd::Pd};
+use crate::{
+    control::type_c::{TryRole, TypeCStateMachineState},
+    controller::pd::Pd,
+};
 
 /// Type-C state machine related controller functionality
 pub trait StateMachine: Pd {
@@ -10,4 +13,9 @@ pub trait StateMachine: Pd {
         port: LocalPortId,
         state: TypeCStateMachineState,
     ) -> impl Future<Output = Result<(), PdError>>;
+
+    /// Set the Try.SRC/Try.SNK preference for the given port
+    ///
+    /// The preference takes effect on the next attach, an existing connection is not affected.
+    fn set_try_role(&mut self, port: LocalPortId, role: TryRole) -> impl Future<Output = Result<(), PdError>>;
 }
@@ -54,6 +54,15 @@ impl PortStatusEventBitfield {
         PortStatusEventBitfield(PortStatusEventBitfieldRaw(self.0.0 | other.0.0))
     }
 
+    /// Returns true if any event should be delivered ahead of pending notifications
+    ///
+    /// Plug insertion/removal and new power contracts drive attach handling and are latency sensitive.
+    pub fn is_high_priority(self) -> bool {
+        self.plug_inserted_or_removed()
+            || self.new_power_contract_as_provider()
+            || self.new_power_contract_as_consumer()
+    }
+
     /// Returns true if a plug was inserted or removed
     pub fn plug_inserted_or_removed(self) -> bool {
         self.0.plug_inserted_or_removed() != 0
@@ -497,4 +506,21 @@ mod tests {
         assert_eq!(notification.next(), Some(PortEvent::DpStatusUpdate));
         assert_eq!(notification.next(), None);
     }
+
+    #[test]
+    fn test_port_status_is_high_priority() {
+        assert!(!PortStatusEventBitfield::none().is_high_priority());
+
+        let mut status = PortStatusEventBitfield::none();
+        status.set_sink_ready(true);
+        status.set_alt_mode_entered(true);
+        assert!(!status.is_high_priority());
+
+        status.set_new_power_contract_as_consumer(true);
+        assert!(status.is_high_priority());
+
+        let mut status = PortStatusEventBitfield::none();
+        status.set_plug_inserted_or_removed(true);
+        assert!(status.is_high_priority());
+    }
 }
@@ -1,8 +1,10 @@
 //! Type-C port related code
+pub mod current_limit;
 pub mod electrical_disconnect;
 pub mod event;
 pub mod max_sink_voltage;
 pub mod pd;
+pub mod port_enable;
 pub mod power;
 pub mod retimer;
 pub mod type_c;
@@ -1,10 +1,11 @@
 use embedded_services::named::Named;
 use embedded_usb_pd::vdm::structured::command::discover_identity::{sop, sop_prime};
-use embedded_usb_pd::{PdError, ado::Ado};
+use embedded_usb_pd::{PdError, PowerRole, ado::Ado};
 
 use crate::control::{
+    cable::CableInfo,
     dp::{DpConfig, DpStatus},
-    pd::{PdStateMachineConfig, PortStatus},
+    pd::{PdStateMachineConfig, Pdos, PortStats, PortStatus},
     svid::DiscoveredSvids,
     tbt::TbtConfig,
     usb::UsbControlConfig,
@@ -38,6 +39,8 @@ pub trait Pd: Named {
     fn execute_drst(&mut self) -> impl Future<Output = Result<(), PdError>>;
     /// Execute a Hard Reset on this port.
     fn hard_reset(&mut self) -> impl Future<Output = Result<(), PdError>>;
+    /// Initiate a DR_Swap on this port, regardless of the configured data role preference
+    fn execute_dr_swap(&mut self) -> impl Future<Output = Result<(), PdError>>;
 
     /// Get DisplayPort status for this port
     fn get_dp_status(&mut self) -> impl Future<Output = Result<DpStatus, PdError>>;
@@ -60,6 +63,17 @@ pub trait Pd: Named {
     fn get_discover_identity_sop_prime_response(
         &mut self,
     ) -> impl Future<Output = Result<sop_prime::ResponseVdos, PdError>>;
+
+    /// Get the PDOs from the port partner's most recent capabilities message.
+    ///
+    /// `role` selects the partner's source or sink capabilities.
+    fn get_partner_pdos(&mut self, role: PowerRole) -> impl Future<Output = Result<Pdos, PdError>>;
+
+    /// Get the event counters for this port.
+    fn get_port_stats(&mut self) -> impl Future<Output = Result<PortStats, PdError>>;
+
+    /// Get the cable discovered on the current connection.
+    fn get_cable_info(&mut self) -> impl Future<Output = Result<CableInfo, PdError>>;
 }
 
 /// PD state machine related controller functionality
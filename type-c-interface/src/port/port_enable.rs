@@ -0,0 +1,13 @@
+use embedded_usb_pd::PdError;
+
+use crate::port::pd::Pd;
+
+/// Functionality related to enabling and disabling a port.
+pub trait PortEnable: Pd {
+    /// Enable or disable this port
+    ///
+    /// A disabled port must not source Vbus and must present neither Rp nor Rd so that the partner sees a detach.
+    /// The port must remain disabled until it is explicitly re-enabled. Re-enabling the port resumes normal
+    /// Type-C attach detection.
+    fn set_port_enable(&mut self, enabled: bool) -> impl Future<Output = Result<(), PdError>>;
+}
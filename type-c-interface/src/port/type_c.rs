@@ -1,6 +1,9 @@
 use embedded_usb_pd::PdError;
 
-use crate::{control::type_c::TypeCStateMachineState, port::pd::Pd};
+use crate::{
+    control::type_c::{TryRole, TypeCStateMachineState},
+    port::pd::Pd,
+};
 
 /// Type-C state machine related controller functionality
 pub trait StateMachine: Pd {
@@ -9,4 +12,9 @@ pub trait StateMachine: Pd {
         &mut self,
         state: TypeCStateMachineState,
     ) -> impl Future<Output = Result<(), PdError>>;
+
+    /// Set the Try.SRC/Try.SNK preference for this port
+    ///
+    /// The preference takes effect on the next attach, an existing connection is not affected.
+    fn set_try_role(&mut self, role: TryRole) -> impl Future<Output = Result<(), PdError>>;
 }
@@ -0,0 +1,79 @@
+//! Arbitration between host UCSI commands and internal policy
+//!
+//! Both the OPM (through UCSI) and internal policy (power policy, thermal policy) can change some port settings. Each
+//! setting has an owner: the origin of its current value. A request from the other origin either overrides the owner
+//! or is rejected depending on the setting's [`precedence`], and is reported with a [`Conflict`] event either way.
+//!
+//! | Setting      | Precedence |
+//! |--------------|------------|
+//! | `PowerRole`  | Host       |
+//! | `DataRole`   | Host       |
+//! | `SinkPath`   | Policy     |
+//! | `PowerLevel` | Policy     |
+use embedded_usb_pd::GlobalPortId;
+
+/// Origin of a port setting
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Origin {
+    /// The OPM, through UCSI
+    Host,
+    /// Internal EC policy
+    Policy,
+}
+
+/// Port setting subject to arbitration
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Setting {
+    /// Power role, e.g. UCSI SET_PDR
+    PowerRole,
+    /// Data role, e.g. UCSI SET_UOR or a data reset
+    DataRole,
+    /// Sink path enable, e.g. UCSI SET_SINK_PATH
+    SinkPath,
+    /// Negotiated power level, e.g. UCSI SET_POWER_LEVEL or a thermal input power limit
+    PowerLevel,
+}
+
+impl Setting {
+    /// Number of settings
+    pub const COUNT: usize = 4;
+
+    /// Index of the setting, for per-setting tables
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Returns the origin that wins when the host and policy both request `setting`
+pub const fn precedence(setting: Setting) -> Origin {
+    match setting {
+        Setting::PowerRole | Setting::DataRole => Origin::Host,
+        Setting::SinkPath | Setting::PowerLevel => Origin::Policy,
+    }
+}
+
+/// How a conflicting request was resolved
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Resolution {
+    /// The request took precedence and replaced the owner's setting
+    Overridden,
+    /// The owner took precedence and the request was rejected
+    Rejected,
+}
+
+/// A request for a setting currently owned by the other origin
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Conflict {
+    /// Port
+    pub port: GlobalPortId,
+    /// Contested setting
+    pub setting: Setting,
+    /// Origin of the request
+    pub requester: Origin,
+    /// How the conflict was resolved
+    pub resolution: Resolution,
+}
@@ -0,0 +1,143 @@
+//! Mapping between power policy devices and global port IDs
+//!
+//! Power policy identifies devices by reference while the type-C service identifies ports by [`GlobalPortId`].
+//! A [`DeviceMap`] is populated at registration time and translates between the two, so power policy events can be
+//! attributed to the port they concern.
+use core::ptr;
+
+use embedded_services::sync::Lockable;
+use embedded_usb_pd::GlobalPortId;
+use power_policy_interface::capability::{ConsumerDisconnect, ConsumerPowerCapability, ProviderPowerCapability};
+use power_policy_interface::psu::Psu;
+use power_policy_interface::service::event::Event as PowerPolicyEvent;
+
+/// Device map registration errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The port or device is already mapped
+    AlreadyRegistered,
+    /// The map is full
+    Full,
+}
+
+/// Power policy event attributed to a port
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PortPowerEvent {
+    /// Consumer disconnected
+    ConsumerDisconnected(GlobalPortId, ConsumerDisconnect),
+    /// Consumer connected
+    ConsumerConnected(GlobalPortId, ConsumerPowerCapability),
+    /// Provider disconnected
+    ProviderDisconnected(GlobalPortId),
+    /// Provider connected
+    ProviderConnected(GlobalPortId, ProviderPowerCapability),
+}
+
+struct Entry<'device, Device> {
+    port: GlobalPortId,
+    device: &'device Device,
+}
+
+/// Two-way mapping between power policy devices and global port IDs
+pub struct DeviceMap<'device, Device, const N: usize> {
+    entries: heapless::Vec<Entry<'device, Device>, N>,
+}
+
+impl<Device, const N: usize> Default for DeviceMap<'_, Device, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'device, Device, const N: usize> DeviceMap<'device, Device, N> {
+    /// Create an empty map
+    pub const fn new() -> Self {
+        Self {
+            entries: heapless::Vec::new(),
+        }
+    }
+
+    /// Create a map where each device is mapped to the global port ID matching its index, the same numbering the
+    /// type-C service uses for its registered ports
+    pub fn from_ports(devices: &[&'device Device]) -> Result<Self, Error> {
+        let mut map = Self::new();
+        for (index, device) in devices.iter().enumerate() {
+            let port = u8::try_from(index).map_err(|_| Error::Full)?;
+            map.register(GlobalPortId(port), device)?;
+        }
+        Ok(map)
+    }
+
+    /// Map `port` to `device`
+    pub fn register(&mut self, port: GlobalPortId, device: &'device Device) -> Result<(), Error> {
+        if self.device(port).is_some() || self.port_id(device).is_some() {
+            return Err(Error::AlreadyRegistered);
+        }
+        self.entries.push(Entry { port, device }).map_err(|_| Error::Full)
+    }
+
+    /// Returns the global port ID mapped to `device`
+    pub fn port_id(&self, device: &Device) -> Option<GlobalPortId> {
+        self.entries
+            .iter()
+            .find(|entry| ptr::eq(entry.device, device))
+            .map(|entry| entry.port)
+    }
+
+    /// Returns the device mapped to `port`
+    pub fn device(&self, port: GlobalPortId) -> Option<&'device Device> {
+        self.entries
+            .iter()
+            .find(|entry| entry.port == port)
+            .map(|entry| entry.device)
+    }
+}
+
+impl<'device, Device: Lockable<Inner: Psu>, const N: usize> DeviceMap<'device, Device, N> {
+    /// Attribute a power policy event to its port
+    ///
+    /// Returns `None` for events that don't concern a single device or for devices that aren't mapped.
+    pub fn port_event(&self, event: PowerPolicyEvent<'_, Device>) -> Option<PortPowerEvent> {
+        match event {
+            PowerPolicyEvent::ConsumerDisconnected(device, disconnect) => self
+                .port_id(device)
+                .map(|port| PortPowerEvent::ConsumerDisconnected(port, disconnect)),
+            PowerPolicyEvent::ConsumerConnected(device, capability) => self
+                .port_id(device)
+                .map(|port| PortPowerEvent::ConsumerConnected(port, capability)),
+            PowerPolicyEvent::ProviderDisconnected(device) => {
+                self.port_id(device).map(PortPowerEvent::ProviderDisconnected)
+            }
+            PowerPolicyEvent::ProviderConnected(device, capability) => self
+                .port_id(device)
+                .map(|port| PortPowerEvent::ProviderConnected(port, capability)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_both_ways() {
+        let devices = [0u8; 3];
+        let [device0, device1, device2] = &devices;
+        let mut map: DeviceMap<'_, u8, 2> = DeviceMap::from_ports(&[device0]).unwrap();
+
+        assert_eq!(map.register(GlobalPortId(0), device1), Err(Error::AlreadyRegistered));
+        assert_eq!(map.register(GlobalPortId(3), device0), Err(Error::AlreadyRegistered));
+        map.register(GlobalPortId(3), device1).unwrap();
+        assert_eq!(map.register(GlobalPortId(4), device2), Err(Error::Full));
+
+        assert_eq!(map.port_id(device0), Some(GlobalPortId(0)));
+        assert_eq!(map.port_id(device1), Some(GlobalPortId(3)));
+        assert_eq!(map.port_id(device2), None);
+        assert!(ptr::eq(map.device(GlobalPortId(3)).unwrap(), device1));
+        assert!(map.device(GlobalPortId(1)).is_none());
+    }
+}
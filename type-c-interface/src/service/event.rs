@@ -3,8 +3,10 @@
 use embedded_services::sync::Lockable;
 use embedded_usb_pd::{GlobalPortId, ado::Ado};
 
+use super::arbitration::Conflict;
+
 use crate::{
-    control::{dp::DpStatus, pd::PortStatus},
+    control::{dp::DpStatus, fw_update::FwUpdateNotice, otp::OtpState, pd::PortStatus},
     port::{
         event::{PortStatusEventBitfield, VdmData},
         pd::Pd,
@@ -40,6 +42,14 @@ pub enum PortEventData {
     UsbMuxErrorRecovery,
     /// DP status update
     DpStatusUpdate(DpStatus),
+    /// Controller was soft reset and port state recovered after repeated errors
+    ControllerRecovered,
+    /// The controller didn't signal sink ready in time and a sink ready event was synthesized
+    SinkReadyTimeout,
+    /// Connector over-temperature protection state changed
+    OverTemperature(OtpState),
+    /// The port was transitioned around a controller firmware update
+    FwUpdate(FwUpdateNotice),
 }
 
 /// Struct containing a complete port event
@@ -75,6 +85,12 @@ pub struct UsciChangeIndicatorData {
 pub enum EventData {
     DebugAccessory(DebugAccessoryData),
     UsciChangeIndicator(UsciChangeIndicatorData),
+    /// Host and internal policy requested the same setting
+    SettingConflict(Conflict),
+    /// Connector over-temperature protection state changed, forwarded to the host with its reason code
+    OverTemperature(OtpState),
+    /// The port is unavailable for a controller firmware update, or was restored afterwards
+    FwUpdate(FwUpdateNotice),
 }
 
 /// Top-level comms message
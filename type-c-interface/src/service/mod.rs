@@ -1 +1,3 @@
+pub mod arbitration;
+pub mod device_map;
 pub mod event;
@@ -1,3 +1,8 @@
+use core::num::NonZeroU8;
+
+use embedded_usb_pd::DataRole;
+use type_c_interface::control::type_c::TryRole;
+
 /// Configuration for Type-C controller wrapper
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -5,6 +10,83 @@
 pub struct Config {
     /// Unconstrained behavior for sink role
     pub unconstrained_sink: UnconstrainedSink,
+    /// Preferred data role, a DR_Swap is initiated on connect if the negotiated data role doesn't match
+    pub preferred_data_role: Option<DataRole>,
+    /// Number of consecutive controller errors before a soft reset and recovery is attempted, `None` disables recovery
+    pub error_recovery_threshold: Option<NonZeroU8>,
+    /// Sink ready timeout after accepting a new consumer contract
+    pub sink_ready_timeout: SinkReadyTimeout,
+    /// Initial Try.SRC/Try.SNK preference, applied through [`Port::apply_try_role`](super::Port::apply_try_role)
+    pub try_role: TryRole,
+    /// Current limit in mA while the connector is over the warning temperature, `None` uses
+    /// [`DEFAULT_CURRENT_LIMIT_MA`](super::otp::DEFAULT_CURRENT_LIMIT_MA)
+    pub otp_current_limit_ma: Option<u16>,
+    /// Port transition applied while the controller firmware is updated, see
+    /// [`Port::prepare_fw_update`](super::Port::prepare_fw_update)
+    pub fw_update_transition: FwUpdateTransition,
+    /// Time in milliseconds after which the port is restored if the firmware update hasn't completed, `None` waits
+    /// indefinitely
+    pub fw_update_timeout_ms: Option<u32>,
+    /// Dock identification, see [`Port::identify_partner`](super::Port::identify_partner)
+    pub dock_detection: DockDetection,
+}
+
+/// Partner identity reported by Discover Identity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DockId {
+    /// USB vendor ID
+    pub vid: u16,
+    /// USB product ID
+    pub pid: u16,
+}
+
+/// Dock identification
+///
+/// A partner identified as a dock is reported to the power policy, which can prefer it as a consumer and aggregates
+/// the docked state of the system.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DockDetection {
+    /// Partners identified as docks
+    pub ids: &'static [DockId],
+    /// Treat any partner that enters an alternate mode as a dock
+    pub on_alt_mode_entry: bool,
+    /// Power in mW the dock reserves for its downstream ports
+    pub downstream_power_mw: u32,
+}
+
+/// Port transition applied while the controller firmware is updated
+///
+/// The controller may stop servicing the port while it's updated, so active contracts are moved to a level that's
+/// safe to hold without PD management beforehand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum FwUpdateTransition {
+    /// Reduce the contract, a sink is limited to 5V and a source to
+    /// [`FW_UPDATE_CURRENT_LIMIT_MA`](super::fw_update::FW_UPDATE_CURRENT_LIMIT_MA)
+    #[default]
+    Reduce,
+    /// Disable the port, detaching the partner
+    Detach,
+}
+
+/// Sink ready timeout behavior
+///
+/// Some controllers don't always signal sink ready after a new consumer contract, so a sink ready event is
+/// synthesized once the timeout expires.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum SinkReadyTimeout {
+    /// Twice the spec maximum `tPSTransition` for the negotiated SPR or EPR contract
+    #[default]
+    Auto,
+    /// Fixed timeout in milliseconds
+    Milliseconds(u32),
+    /// Never synthesize sink ready, rely on the controller to signal it
+    Disabled,
 }
 
 /// Unconstrained behavior for sink role
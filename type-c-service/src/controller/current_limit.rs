@@ -0,0 +1,27 @@
+//! Current limit port trait implementation
+use embedded_services::{event::NonBlockingSender, sync::Lockable};
+use embedded_usb_pd::PdError;
+use type_c_interface::controller::current_limit::CurrentLimit;
+
+use super::*;
+use crate::controller::state::SharedState;
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd + CurrentLimit>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> type_c_interface::port::current_limit::CurrentLimit
+    for Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    async fn set_current_limit(&mut self, current_ma: Option<u16>) -> Result<(), PdError> {
+        debug!("({}): Setting current limit {:?} mA", self.name, current_ma);
+        self.controller
+            .lock()
+            .await
+            .set_current_limit(self.port, current_ma)
+            .await
+    }
+}
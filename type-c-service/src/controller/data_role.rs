@@ -0,0 +1,80 @@
+//! Data role management
+use embedded_services::{event::NonBlockingSender, sync::Lockable, warn};
+use embedded_usb_pd::PdError;
+use type_c_interface::control::pd::DataRoleSwapState;
+
+use super::*;
+use crate::controller::state::SharedState;
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Initiate a DR_Swap and track it in the cached port status
+    pub(super) async fn start_data_role_swap(&mut self) -> Result<(), PdError> {
+        let result = self.execute_data_role_swap().await;
+        self.status.data_role_swap = swap_state(&result);
+        result
+    }
+
+    /// Update the data role swap state and enforce the data role preference on connect
+    pub(super) async fn process_data_role(
+        &mut self,
+        status_event: PortStatusEventBitfield,
+        new_status: &mut PortStatus,
+    ) {
+        new_status.data_role_swap = if status_event.plug_inserted_or_removed()
+            || status_event.data_swap_completed()
+            || !new_status.is_connected()
+        {
+            DataRoleSwapState::Idle
+        } else {
+            self.status.data_role_swap
+        };
+
+        if status_event.data_swap_completed() {
+            info!(
+                "({}): DR_Swap completed, data role {:?}",
+                self.name, new_status.data_role
+            );
+        }
+
+        let Some(preferred) = self.config.preferred_data_role else {
+            return;
+        };
+
+        // Only attempt the swap once per connection, the partner is free to reject it
+        if status_event.plug_inserted_or_removed()
+            && new_status.is_connected()
+            && !new_status.is_debug_accessory()
+            && new_status.data_role != preferred
+        {
+            info!(
+                "({}): Data role {:?} doesn't match preference {:?}, initiating DR_Swap",
+                self.name, new_status.data_role, preferred
+            );
+            new_status.data_role_swap = swap_state(&self.execute_data_role_swap().await);
+        }
+    }
+
+    async fn execute_data_role_swap(&mut self) -> Result<(), PdError> {
+        let result = self.controller.lock().await.execute_dr_swap(self.port).await;
+        if let Err(e) = result {
+            warn!("({}): DR_Swap failed: {:?}", self.name, e);
+        }
+        result
+    }
+}
+
+fn swap_state(result: &Result<(), PdError>) -> DataRoleSwapState {
+    if result.is_ok() {
+        DataRoleSwapState::InProgress
+    } else {
+        DataRoleSwapState::Rejected
+    }
+}
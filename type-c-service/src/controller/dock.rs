@@ -0,0 +1,63 @@
+//! Dock identification
+//!
+//! The platform passes the VID/PID reported by Discover Identity to [`Port::identify_partner`], a partner matching
+//! [`DockDetection::ids`](config::DockDetection::ids) is reported to the power policy as a dock. Partners entering an
+//! alternate mode can also be treated as docks with
+//! [`DockDetection::on_alt_mode_entry`](config::DockDetection::on_alt_mode_entry). The dock is cleared when the
+//! partner detaches.
+use embedded_services::{event::NonBlockingSender, sync::Lockable};
+use power_policy_interface::dock::DockInfo;
+
+use super::config::{DockDetection, DockId};
+use super::*;
+use crate::controller::state::SharedState;
+
+impl DockDetection {
+    /// Dock information reported for an identified dock
+    pub fn dock_info(&self) -> DockInfo {
+        DockInfo {
+            downstream_power_mw: self.downstream_power_mw,
+        }
+    }
+}
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Returns true if the partner was identified as a dock
+    pub fn is_dock(&self) -> bool {
+        self.psu_state.dock.is_some()
+    }
+
+    /// Identify the partner from its Discover Identity VID/PID, returns true if it's a dock
+    pub fn identify_partner(&mut self, vid: u16, pid: u16) -> bool {
+        let detection = self.config.dock_detection;
+        let is_dock = detection.ids.contains(&DockId { vid, pid });
+        if is_dock {
+            info!("({}): Identified dock {:?}", self.name, DockId { vid, pid });
+            self.set_dock(Some(detection.dock_info()));
+        }
+        is_dock
+    }
+
+    /// Set or clear the dock on this port, notifying the power policy if it changed
+    pub fn set_dock(&mut self, dock: Option<DockInfo>) {
+        if !self.psu_state.update_dock(dock) {
+            return;
+        }
+
+        if self
+            .power_policy_sender
+            .try_send(power_policy_interface::psu::event::EventData::UpdatedDock(dock))
+            .is_none()
+        {
+            error!("({}): Failed to send power policy event", self.name);
+        }
+    }
+}
@@ -9,6 +9,8 @@ use type_c_interface::port::event::PortEventBitfield;
 pub enum Event {
     /// Port event
     PortEvent(type_c_interface::port::event::PortEvent),
+    /// The sink ready timeout expired without the controller signaling sink ready
+    SinkReadyTimeout,
 }
 
 /// Loopback event to allow `sync_state` and similar functions
@@ -48,6 +48,8 @@ pub struct PortEventReceiver<R: Receiver<PortEventBitfield>, LoopbackReceiver: R
     streaming_state: Option<PortEventStreamer<array::IntoIter<PortEventBitfield, 1>>>,
     /// Loopback receiver for software-generated events
     loopback_receiver: LoopbackReceiver,
+    /// Events received while streaming that are waiting for the current batch to complete
+    deferred: PortEventBitfield,
 }
 
 impl<R: Receiver<PortEventBitfield>, LoopbackReceiver: Receiver<Loopback>> PortEventReceiver<R, LoopbackReceiver> {
@@ -57,19 +59,58 @@ impl<R: Receiver<PortEventBitfield>, LoopbackReceiver: Receiver<Loopback>> PortE
             receiver,
             streaming_state: None,
             loopback_receiver,
+            deferred: PortEventBitfield::none(),
         }
     }
 
+    /// Check for events that arrived while streaming
+    ///
+    /// Returns high priority status changes so they can be delivered ahead of any pending notifications,
+    /// everything else is deferred until the current batch has been streamed.
+    fn poll_priority(&mut self) -> Option<PortStatusEventBitfield> {
+        let mut priority = PortStatusEventBitfield::none();
+        while let Some(events) = self
+            .loopback_receiver
+            .try_next()
+            .map(|Loopback::PortEvent(events)| events)
+            .or_else(|| self.receiver.try_next())
+        {
+            if events.status.is_high_priority() {
+                priority = priority.union(events.status);
+                self.deferred = self.deferred.union(events.notification.into());
+            } else {
+                self.deferred = self.deferred.union(events);
+            }
+        }
+
+        (priority != PortStatusEventBitfield::none()).then_some(priority)
+    }
+
     /// Wait for the next port event
+    ///
+    /// Plug and power contract status changes that arrive while notifications are being streamed are
+    /// delivered immediately rather than waiting behind the notifications.
     pub async fn wait_next(&mut self) -> type_c_interface::port::event::PortEvent {
         loop {
-            let streaming_state = if let Some(streaming_state) = &mut self.streaming_state {
+            let streaming_state = if self.streaming_state.is_some() {
                 // Yield to ensure we don't monopolize the executor
                 embassy_futures::yield_now().await;
+                if let Some(status) = self.poll_priority() {
+                    return PortEvent::StatusChanged(status);
+                }
+
+                let Some(streaming_state) = &mut self.streaming_state else {
+                    continue;
+                };
                 streaming_state
             } else {
-                let (Either::First(Loopback::PortEvent(events)) | Either::Second(events)) =
-                    select(self.loopback_receiver.wait_next(), self.receiver.wait_next()).await;
+                let events = if self.deferred != PortEventBitfield::none() {
+                    core::mem::replace(&mut self.deferred, PortEventBitfield::none())
+                } else {
+                    let (Either::First(Loopback::PortEvent(events)) | Either::Second(events)) =
+                        select(self.loopback_receiver.wait_next(), self.receiver.wait_next()).await;
+                    events
+                };
                 self.streaming_state
                     .insert(PortEventStreamer::new([events].into_iter()))
             };
@@ -131,11 +172,55 @@ impl<
         {
             Either::First(event) => Event::PortEvent(event),
             Either::Second(_) => {
-                let mut status_event = PortStatusEventBitfield::none();
-                status_event.set_sink_ready(true);
                 self.shared_state.lock().await.sink_ready_timeout = None;
-                Event::PortEvent(PortEvent::StatusChanged(status_event))
+                Event::SinkReadyTimeout
             }
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+    use embassy_sync::channel::Channel;
+    use embedded_services::GlobalRawMutex;
+
+    /// Four port controller reporting a fixed set of interrupts
+    struct FourPortController([PortEventBitfield; 4]);
+
+    impl InterruptReceiver<4> for FourPortController {
+        async fn wait_interrupt(&mut self) -> [PortEventBitfield; 4] {
+            self.0
+        }
+    }
+
+    #[test]
+    fn four_port_interrupts_are_split() {
+        let channels: [Channel<GlobalRawMutex, PortEventBitfield, 1>; 4] = core::array::from_fn(|_| Channel::new());
+        let mut splitter = PortEventSplitter::new(channels.each_ref().map(Channel::dyn_sender));
+
+        let mut plug = PortStatusEventBitfield::none();
+        plug.set_plug_inserted_or_removed(true);
+        let mut contract = PortStatusEventBitfield::none();
+        contract.set_new_power_contract_as_consumer(true);
+        let mut controller = FourPortController([
+            PortEventBitfield::none(),
+            plug.into(),
+            PortEventBitfield::none(),
+            contract.into(),
+        ]);
+
+        block_on(async {
+            let interrupts = controller.wait_interrupt().await;
+            splitter.process_interrupts(interrupts).await;
+        });
+
+        let [port0, port1, port2, port3] = &channels;
+        assert!(port0.try_receive().is_err());
+        assert_eq!(port1.try_receive().unwrap(), plug.into());
+        assert!(port2.try_receive().is_err());
+        assert_eq!(port3.try_receive().unwrap(), contract.into());
+    }
+}
@@ -0,0 +1,205 @@
+//! Port transitions around a controller firmware update
+//!
+//! The controller may stop managing its ports while its firmware is updated, so the platform calls
+//! [`Port::prepare_fw_update`] on each port of the controller before starting the update. This applies the configured
+//! [`FwUpdateTransition`] to the active contract and notifies the host that the port is unavailable. Once the update
+//! completes, or the returned deadline passes, [`Port::finish_fw_update`] restores the port.
+//!
+//! ```ignore
+//! let deadline = port.lock().await.prepare_fw_update().await?;
+//! let timed_out = match deadline {
+//!     Some(deadline) => with_deadline(deadline, update).await.is_err(),
+//!     None => {
+//!         update.await;
+//!         false
+//!     }
+//! };
+//! port.lock().await.finish_fw_update(timed_out).await?;
+//! ```
+use embassy_time::{Duration, Instant};
+use embedded_services::{event::NonBlockingSender, sync::Lockable};
+use embedded_usb_pd::PdError;
+use type_c_interface::control::fw_update::FwUpdateNotice;
+use type_c_interface::control::otp::OtpState;
+use type_c_interface::controller::current_limit::CurrentLimit;
+use type_c_interface::controller::max_sink_voltage::MaxSinkVoltage;
+use type_c_interface::controller::port_enable::PortEnable;
+use type_c_interface::port::current_limit::CurrentLimit as _;
+use type_c_interface::port::max_sink_voltage::MaxSinkVoltage as _;
+use type_c_interface::port::port_enable::PortEnable as _;
+
+use super::config::FwUpdateTransition;
+use super::*;
+use crate::controller::state::SharedState;
+
+/// Source current limit applied while the firmware is updated with [`FwUpdateTransition::Reduce`]
+pub const FW_UPDATE_CURRENT_LIMIT_MA: u16 = 1500;
+
+/// Sink voltage limit applied while the firmware is updated with [`FwUpdateTransition::Reduce`]
+const FW_UPDATE_SINK_VOLTAGE_MV: u16 = 5000;
+
+/// Change applied to the port for the firmware update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(super) enum Applied {
+    /// Nothing was connected or the port was already disabled
+    None,
+    /// The sink voltage was limited
+    Sink,
+    /// The source current was limited
+    Source,
+    /// The port was disabled
+    Detached,
+}
+
+/// Firmware update in progress on the port
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FwUpdate {
+    applied: Applied,
+    deadline: Option<Instant>,
+}
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Returns true while the port is transitioned for a controller firmware update
+    pub fn is_fw_updating(&self) -> bool {
+        self.fw_update.is_some()
+    }
+
+    /// Deadline after which the port should be restored if the firmware update hasn't completed
+    pub fn fw_update_deadline(&self) -> Option<Instant> {
+        self.fw_update.and_then(|update| update.deadline)
+    }
+
+    /// Returns true if the firmware update transition keeps the port disabled
+    pub(super) fn fw_update_detached(&self) -> bool {
+        self.fw_update.is_some_and(|update| update.applied == Applied::Detached)
+    }
+
+    /// Current limit for the port combining the over-temperature and firmware update policies
+    pub(super) fn port_current_limit(&self) -> Option<u16> {
+        let otp = (self.otp.state() == OtpState::Throttled).then(|| {
+            self.config
+                .otp_current_limit_ma
+                .unwrap_or(otp::DEFAULT_CURRENT_LIMIT_MA)
+        });
+        let fw_update = self
+            .fw_update
+            .is_some_and(|update| update.applied == Applied::Source)
+            .then_some(FW_UPDATE_CURRENT_LIMIT_MA);
+
+        match (otp, fw_update) {
+            (Some(otp), Some(fw_update)) => Some(otp.min(fw_update)),
+            (limit, None) | (None, limit) => limit,
+        }
+    }
+
+    fn send_fw_update_notice(&mut self, notice: FwUpdateNotice) {
+        if self
+            .type_c_sender
+            .try_send(ServicePortEventData::FwUpdate(notice))
+            .is_none()
+        {
+            error!("({}): Failed to send firmware update type-C event", self.name);
+        }
+    }
+}
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd + MaxSinkVoltage + CurrentLimit + PortEnable>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Transition the port ahead of a controller firmware update
+    ///
+    /// Applies [`Config::fw_update_transition`](config::Config::fw_update_transition) to the active contract and
+    /// notifies the type-C service with [`FwUpdateNotice::Started`]. Returns the deadline after which the port should
+    /// be restored with [`Self::finish_fw_update`] if the update hasn't completed, if a timeout is configured.
+    pub async fn prepare_fw_update(&mut self) -> Result<Option<Instant>, PdError> {
+        if self.fw_update.is_some() {
+            error!("({}): Firmware update already in progress", self.name);
+            return Err(PdError::InvalidMode);
+        }
+
+        let applied = if self.otp.state() == OtpState::Shutdown {
+            // Already disabled by over-temperature protection
+            Applied::None
+        } else {
+            match (self.config.fw_update_transition, self.psu_state.psu_state) {
+                (FwUpdateTransition::Detach, _) => Applied::Detached,
+                (FwUpdateTransition::Reduce, PsuState::ConnectedConsumer(_)) => Applied::Sink,
+                (FwUpdateTransition::Reduce, PsuState::ConnectedProvider(_)) => Applied::Source,
+                (FwUpdateTransition::Reduce, _) => Applied::None,
+            }
+        };
+
+        let deadline = self
+            .config
+            .fw_update_timeout_ms
+            .map(|timeout| Instant::now() + Duration::from_millis(timeout.into()));
+        self.fw_update = Some(FwUpdate { applied, deadline });
+
+        info!("({}): Preparing for firmware update: {:?}", self.name, applied);
+        let result = match applied {
+            Applied::None => Ok(()),
+            Applied::Sink => self.set_max_sink_voltage(Some(FW_UPDATE_SINK_VOLTAGE_MV)).await,
+            Applied::Source => self.set_current_limit(self.port_current_limit()).await,
+            Applied::Detached => self.set_port_enable(false).await,
+        };
+        if let Err(e) = result {
+            self.fw_update = None;
+            return Err(e);
+        }
+
+        self.send_fw_update_notice(FwUpdateNotice::Started);
+        Ok(deadline)
+    }
+
+    /// Restore the port after a controller firmware update
+    ///
+    /// `timed_out` is set if the update didn't complete before the deadline returned by [`Self::prepare_fw_update`].
+    /// The over-temperature protection state is respected, e.g. a port that overheated during the update stays
+    /// disabled. Does nothing if no update is in progress.
+    pub async fn finish_fw_update(&mut self, timed_out: bool) -> Result<(), PdError> {
+        let Some(update) = self.fw_update.take() else {
+            return Ok(());
+        };
+
+        if timed_out {
+            warn!("({}): Firmware update timed out, restoring port", self.name);
+        } else {
+            info!("({}): Firmware update complete, restoring port", self.name);
+        }
+
+        let result = match update.applied {
+            Applied::None => Ok(()),
+            Applied::Sink => self.set_max_sink_voltage(None).await,
+            Applied::Source => self.set_current_limit(self.port_current_limit()).await,
+            Applied::Detached if self.otp.state() == OtpState::Shutdown => Ok(()),
+            Applied::Detached => self.set_port_enable(true).await,
+        };
+        if let Err(e) = result {
+            // Keep the update state so the restore can be retried
+            self.fw_update = Some(update);
+            return Err(e);
+        }
+
+        self.send_fw_update_notice(if timed_out {
+            FwUpdateNotice::TimedOut
+        } else {
+            FwUpdateNotice::Completed
+        });
+        Ok(())
+    }
+}
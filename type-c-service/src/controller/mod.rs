@@ -1,8 +1,9 @@
 //! Struct that manages per-port state, interfacing with a controller object that exposes multiple ports.
-use embedded_services::{debug, error, event::NonBlockingSender, info, named::Named, sync::Lockable};
+use embedded_services::{debug, error, event::NonBlockingSender, info, named::Named, sync::Lockable, warn};
 use embedded_usb_pd::{LocalPortId, PdError};
 use power_policy_interface::psu::PsuState;
-use type_c_interface::control::pd::PortStatus;
+use type_c_interface::control::pd::{PortStats, PortStatus};
+use type_c_interface::control::type_c::TryRole;
 use type_c_interface::controller::pd::Pd;
 use type_c_interface::port::event::PortEventBitfield;
 use type_c_interface::port::{event::PortEvent as InterfacePortEvent, event::PortStatusEventBitfield};
@@ -12,17 +13,25 @@ use crate::controller::event::{Event, Loopback};
 use crate::controller::state::SharedState;
 
 pub mod config;
+pub mod current_limit;
+mod data_role;
+pub mod dock;
 pub mod electrical_disconnect;
 pub mod event;
 pub mod event_receiver;
+pub mod fw_update;
 pub mod macros;
 pub mod max_sink_voltage;
+pub mod otp;
 mod pd;
+pub mod port_enable;
 mod power;
+mod recovery;
 pub mod retimer;
 pub mod state;
 pub mod type_c;
 pub mod ucsi;
+pub mod vdm_queue;
 
 pub struct Port<
     'device,
@@ -52,6 +61,22 @@ pub struct Port<
     shared_state: &'device Shared,
     /// Loopback sender
     loopback_sender: LoopbackSender,
+    /// Number of consecutive failed events, used to trigger error recovery
+    consecutive_errors: u8,
+    /// Set while a controller reset hasn't been followed by a state restore, e.g. because recovery was cancelled
+    recovery_pending: bool,
+    /// Last unconstrained power state applied to the controller, replayed on recovery
+    unconstrained_power: Option<bool>,
+    /// Current Try.SRC/Try.SNK preference
+    try_role: TryRole,
+    /// Connector temperature thresholds currently exceeded
+    otp: otp::Exceeded,
+    /// Controller firmware update in progress
+    fw_update: Option<fw_update::FwUpdate>,
+    /// Queue for received VDMs, drained by the platform alt-mode handler
+    vdm_queue: Option<&'device dyn vdm_queue::VdmSink>,
+    /// Event counters
+    stats: PortStats,
 }
 
 impl<
@@ -75,6 +100,32 @@ impl<
         type_c_sender: TypeCSender,
         power_policy_sender: PowerSender,
         loopback_sender: LoopbackSender,
+    ) -> Self {
+        Self::new_with_vdm_queue(
+            name,
+            config,
+            port,
+            controller,
+            shared_state,
+            type_c_sender,
+            power_policy_sender,
+            loopback_sender,
+            None,
+        )
+    }
+
+    /// Create new Port instance that also queues received VDMs for the platform alt-mode handler
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_vdm_queue(
+        name: &'static str,
+        config: config::Config,
+        port: LocalPortId,
+        controller: &'device C,
+        shared_state: &'device Shared,
+        type_c_sender: TypeCSender,
+        power_policy_sender: PowerSender,
+        loopback_sender: LoopbackSender,
+        vdm_queue: Option<&'device dyn vdm_queue::VdmSink>,
     ) -> Self {
         Self {
             name,
@@ -87,24 +138,55 @@ impl<
             shared_state,
             loopback_sender,
             type_c_sender,
+            consecutive_errors: 0,
+            recovery_pending: false,
+            unconstrained_power: None,
+            try_role: config.try_role,
+            otp: otp::Exceeded::default(),
+            fw_update: None,
+            vdm_queue,
+            stats: PortStats::default(),
         }
     }
 
-    /// Top-level processing function
-    pub async fn process_event(&mut self, event: Event) -> Result<Option<ServicePortEventData>, PdError> {
+    /// Dispatch an event to its handler, see [`Self::process_event`] for the top-level processing function
+    async fn dispatch_event(&mut self, event: Event) -> Result<Option<ServicePortEventData>, PdError> {
         match event {
             Event::PortEvent(port_event) => self.process_port_event(port_event).await,
+            Event::SinkReadyTimeout => self.process_sink_ready_timeout().await.map(Some),
         }
     }
 
+    /// Process an expired sink ready timeout as if the controller had signaled sink ready
+    async fn process_sink_ready_timeout(&mut self) -> Result<ServicePortEventData, PdError> {
+        warn!("({}): Sink ready timeout expired, assuming sink ready", self.name);
+        if self
+            .type_c_sender
+            .try_send(ServicePortEventData::SinkReadyTimeout)
+            .is_none()
+        {
+            error!("Failed to send sink ready timeout type-C event");
+        }
+
+        let mut status_event = PortStatusEventBitfield::none();
+        status_event.set_sink_ready(true);
+        self.process_port_status_changed(status_event).await
+    }
+
     /// Process a port notification
     async fn process_port_event(&mut self, event: InterfacePortEvent) -> Result<Option<ServicePortEventData>, PdError> {
         match event {
             InterfacePortEvent::StatusChanged(status_event) => {
                 self.process_port_status_changed(status_event).await.map(Some)
             }
-            InterfacePortEvent::Alert => self.process_pd_alert().await,
-            InterfacePortEvent::Vdm(vdm_event) => self.process_vdm_event(vdm_event).await,
+            InterfacePortEvent::Alert => {
+                self.stats.alerts = self.stats.alerts.wrapping_add(1);
+                self.process_pd_alert().await
+            }
+            InterfacePortEvent::Vdm(vdm_event) => {
+                self.stats.vdms = self.stats.vdms.wrapping_add(1);
+                self.process_vdm_event(vdm_event).await
+            }
             InterfacePortEvent::DpStatusUpdate => self.process_dp_status_update().await.map(Some),
             rest => {
                 // Nothing currently implemented for these
@@ -119,25 +201,36 @@ impl<
         &mut self,
         status_event: PortStatusEventBitfield,
     ) -> Result<ServicePortEventData, PdError> {
-        let new_status = self.controller.lock().await.get_port_status(self.port).await?;
+        let mut new_status = self.controller.lock().await.get_port_status(self.port).await?;
         debug!("({}) status: {:#?}", self.name, new_status);
         debug!("({}) status events: {:#?}", self.name, status_event);
+        self.update_stats(status_event, &new_status);
 
         if status_event.plug_inserted_or_removed() {
             self.process_plug_event(&new_status).await?;
         }
 
+        self.process_data_role(status_event, &mut new_status).await;
+
         // Tear down the previous contract on a power role swap before establishing the new one
         if status_event.power_swap_completed() {
             self.process_power_role_swap(&new_status).await?;
         }
 
+        if status_event.pd_hard_reset() {
+            self.process_hard_reset().await?;
+        }
+
         // Only notify power policy of a contract after Sink Ready event (always after explicit or implicit contract)
         if status_event.sink_ready() {
             self.process_new_consumer_contract(&new_status).await?;
         }
 
-        if new_status.is_connected() && new_status.available_source_contract != self.status.available_source_contract {
+        // A hard reset drops the source contract even if the renegotiated one matches the previous one
+        if new_status.is_connected()
+            && (new_status.available_source_contract != self.status.available_source_contract
+                || (status_event.pd_hard_reset() && new_status.available_source_contract.is_some()))
+        {
             self.process_new_provider_contract(&new_status).await?;
         }
 
@@ -160,11 +253,43 @@ impl<
         Ok(event)
     }
 
+    /// Count the events in a status change
+    fn update_stats(&mut self, status_event: PortStatusEventBitfield, new_status: &PortStatus) {
+        let stats = &mut self.stats;
+        if status_event.plug_inserted_or_removed() && new_status.is_connected() {
+            stats.attaches = stats.attaches.wrapping_add(1);
+        }
+
+        let had_contract =
+            self.status.available_sink_contract.is_some() || self.status.available_source_contract.is_some();
+        if had_contract
+            && (status_event.new_power_contract_as_consumer() || status_event.new_power_contract_as_provider())
+        {
+            stats.contract_renegotiations = stats.contract_renegotiations.wrapping_add(1);
+        }
+
+        if status_event.pd_hard_reset() {
+            stats.hard_resets = stats.hard_resets.wrapping_add(1);
+        }
+    }
+
+    /// Event counters for this port
+    ///
+    /// Synchronous so a debug snapshot can read the counters with `try_lock`.
+    pub fn stats(&self) -> PortStats {
+        self.stats
+    }
+
+    /// Clear the event counters for this port
+    pub fn reset_stats(&mut self) {
+        self.stats = PortStats::default();
+    }
+
     /// Handle a plug event
     async fn process_plug_event(&mut self, new_status: &PortStatus) -> Result<(), PdError> {
         info!("Plug event");
         if new_status.is_connected() {
-            info!("Plug inserted");
+            info!("Plug inserted, cable: {:?}", new_status.cable);
             if self.psu_state.psu_state != PsuState::Detached {
                 info!("Device not in detached state, recovering");
                 self.psu_state.detach();
@@ -0,0 +1,108 @@
+//! Connector over-temperature protection
+//!
+//! The connector temperature is monitored by a thermal service sensor, typically a
+//! [`ConnectorSensor`](crate::thermal::ConnectorSensor), whose events are routed to
+//! [`Port::process_connector_temperature_event`]. Above the sensor's warn high threshold the port current is limited,
+//! above its critical threshold the port is disabled. The port recovers once the sensor clears the threshold, so the
+//! sensor hysteresis keeps the port from toggling around a threshold.
+use embedded_services::{event::NonBlockingSender, sync::Lockable};
+use embedded_usb_pd::PdError;
+use thermal_service_interface::sensor::{Event as SensorEvent, Threshold};
+use type_c_interface::control::otp::OtpState;
+use type_c_interface::controller::current_limit::CurrentLimit;
+use type_c_interface::controller::port_enable::PortEnable;
+use type_c_interface::port::current_limit::CurrentLimit as _;
+use type_c_interface::port::port_enable::PortEnable as _;
+
+use super::*;
+use crate::controller::state::SharedState;
+
+/// Current limit applied while throttled if [`Config::otp_current_limit_ma`](config::Config::otp_current_limit_ma)
+/// isn't set
+pub const DEFAULT_CURRENT_LIMIT_MA: u16 = 1500;
+
+/// Connector temperature thresholds currently exceeded
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Exceeded {
+    warn: bool,
+    critical: bool,
+}
+
+impl Exceeded {
+    pub(super) fn state(self) -> OtpState {
+        if self.critical {
+            OtpState::Shutdown
+        } else if self.warn {
+            OtpState::Throttled
+        } else {
+            OtpState::Normal
+        }
+    }
+}
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Current over-temperature protection state
+    pub fn otp_state(&self) -> OtpState {
+        self.otp.state()
+    }
+}
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd + PortEnable + CurrentLimit>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Apply the over-temperature protection policy to an event from the connector temperature sensor
+    ///
+    /// Notifies the type-C service with [`ServicePortEventData::OverTemperature`] when the state changes.
+    pub async fn process_connector_temperature_event(&mut self, event: SensorEvent) -> Result<(), PdError> {
+        let previous = self.otp.state();
+        match event {
+            SensorEvent::ThresholdExceeded(Threshold::WarnHigh) => self.otp.warn = true,
+            SensorEvent::ThresholdCleared(Threshold::WarnHigh) => self.otp.warn = false,
+            SensorEvent::ThresholdExceeded(Threshold::Critical) => self.otp.critical = true,
+            SensorEvent::ThresholdCleared(Threshold::Critical) => self.otp.critical = false,
+            _ => return Ok(()),
+        }
+
+        let state = self.otp.state();
+        if state == previous {
+            return Ok(());
+        }
+
+        warn!("({}): Connector over-temperature state {:?}", self.name, state);
+        match state {
+            OtpState::Shutdown => self.set_port_enable(false).await?,
+            OtpState::Throttled | OtpState::Normal => {
+                // Limit the current before re-enabling so the partner never sees the full current
+                self.set_current_limit(self.port_current_limit()).await?;
+                // A port detached for a firmware update stays down until the update finishes
+                if previous == OtpState::Shutdown && !self.fw_update_detached() {
+                    self.set_port_enable(true).await?;
+                }
+            }
+        }
+
+        if self
+            .type_c_sender
+            .try_send(ServicePortEventData::OverTemperature(state))
+            .is_none()
+        {
+            error!("({}): Failed to send over-temperature type-C event", self.name);
+        }
+
+        Ok(())
+    }
+}
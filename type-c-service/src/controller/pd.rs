@@ -1,11 +1,12 @@
 //! PD functionality unrelated to power contracts and general port status
 use embedded_services::{event::NonBlockingSender, sync::Lockable};
-use embedded_usb_pd::PdError;
 use embedded_usb_pd::ado::Ado;
 use embedded_usb_pd::vdm::structured::command::discover_identity::{sop, sop_prime};
+use embedded_usb_pd::{PdError, PowerRole};
 use type_c_interface::control::{
+    cable::CableInfo,
     dp::{DpConfig, DpStatus},
-    pd::{PdStateMachineConfig, PortStatus},
+    pd::{PdStateMachineConfig, Pdos, PortStats, PortStatus},
     svid::DiscoveredSvids,
     tbt::TbtConfig,
     usb::UsbControlConfig,
@@ -47,6 +48,14 @@ impl<
             }
         };
 
+        if let Some(vdm_queue) = self.vdm_queue {
+            vdm_queue.push(vdm_data);
+        }
+
+        if matches!(vdm_data, VdmData::Entered(_)) && self.config.dock_detection.on_alt_mode_entry {
+            self.set_dock(Some(self.config.dock_detection.dock_info()));
+        }
+
         let event = ServicePortEventData::Vdm(vdm_data);
         if self.type_c_sender.try_send(event).is_none() {
             error!("Failed to send VDM type-C event");
@@ -111,7 +120,9 @@ impl<
             .lock()
             .await
             .set_unconstrained_power(self.port, unconstrained)
-            .await
+            .await?;
+        self.unconstrained_power = Some(unconstrained);
+        Ok(())
     }
 
     async fn get_other_vdm(&mut self) -> Result<OtherVdm, PdError> {
@@ -147,9 +158,17 @@ impl<
     }
 
     async fn hard_reset(&mut self) -> Result<(), PdError> {
+        info!("({}): Host requested hard reset", self.name);
+        // VBUS drops during the reset, stop consuming or providing power before it does
+        self.disconnect_contract().await?;
         self.controller.lock().await.hard_reset(self.port).await
     }
 
+    async fn execute_dr_swap(&mut self) -> Result<(), PdError> {
+        info!("({}): Host requested DR_Swap", self.name);
+        self.start_data_role_swap().await
+    }
+
     async fn get_discovered_svids(&mut self) -> Result<DiscoveredSvids, PdError> {
         self.controller.lock().await.get_discovered_svids(self.port).await
     }
@@ -169,6 +188,18 @@ impl<
             .get_discover_identity_sop_prime_response(self.port)
             .await
     }
+
+    async fn get_partner_pdos(&mut self, role: PowerRole) -> Result<Pdos, PdError> {
+        self.controller.lock().await.get_partner_pdos(self.port, role).await
+    }
+
+    async fn get_port_stats(&mut self) -> Result<PortStats, PdError> {
+        Ok(self.stats)
+    }
+
+    async fn get_cable_info(&mut self) -> Result<CableInfo, PdError> {
+        Ok(self.status.cable)
+    }
 }
 
 impl<
@@ -0,0 +1,81 @@
+//! Port enable port trait implementation
+use embedded_services::{event::NonBlockingSender, sync::Lockable};
+use embedded_usb_pd::PdError;
+use power_policy_interface::capability::ConsumerDisconnect;
+use type_c_interface::controller::port_enable::PortEnable;
+
+use super::*;
+use crate::controller::state::SharedState;
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd + PortEnable>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> type_c_interface::port::port_enable::PortEnable
+    for Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    async fn set_port_enable(&mut self, enabled: bool) -> Result<(), PdError> {
+        info!(
+            "({}): {} port",
+            self.name,
+            if enabled { "Enabling" } else { "Disabling" }
+        );
+        if enabled {
+            // The next attach reconnects the port in whichever role it negotiates
+            return self.controller.lock().await.set_port_enable(self.port, true).await;
+        }
+
+        // Disabling the port drops any contract without waiting for the power policy, so the power policy is notified
+        // once the port is down instead of waiting for the detach event.
+        let was_consumer = match self.psu_state.psu_state {
+            PsuState::ConnectedConsumer(_) => Some(true),
+            PsuState::ConnectedProvider(_) => Some(false),
+            _ => None,
+        };
+
+        // The sink path must be off before the source goes away
+        if was_consumer == Some(true) {
+            debug!("({}): Disabling sink path before disabling port", self.name);
+            self.controller.lock().await.enable_sink_path(self.port, false).await?;
+        }
+
+        if let Err(e) = self.controller.lock().await.set_port_enable(self.port, false).await {
+            error!("({}): Failed to disable port: {:?}", self.name, e);
+            if was_consumer == Some(true) {
+                // The contract is still in place, keep consuming from the port
+                if let Err(e) = self.controller.lock().await.enable_sink_path(self.port, true).await {
+                    error!("({}): Failed to restore sink path: {:?}", self.name, e);
+                }
+            }
+            return Err(e);
+        }
+
+        if let Some(was_consumer) = was_consumer {
+            if let Err(e) = self.psu_state.disconnect(true) {
+                error!("({}): Error updating PSU state on port disable: {:?}", self.name, e);
+            }
+
+            // Consumers are disconnected without the renegotiation flag so the power policy selects another
+            // consumer, providers are removed from the provider budget. The power policy broadcasts the matching
+            // ConsumerDisconnected or ProviderDisconnected event.
+            debug!(
+                "({}): Notifying power policy of {} disconnect",
+                self.name,
+                if was_consumer { "consumer" } else { "provider" }
+            );
+            if self
+                .power_policy_sender
+                .try_send(power_policy_interface::psu::event::EventData::Disconnected(
+                    ConsumerDisconnect::none(),
+                ))
+                .is_none()
+            {
+                error!("({}): Failed to notify power policy of port disable", self.name);
+            }
+        }
+        Ok(())
+    }
+}
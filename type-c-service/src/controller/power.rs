@@ -1,17 +1,18 @@
 //! Module for power policy related functionality
 use embassy_time::{Duration, Instant};
-use embedded_services::{debug, error, event::NonBlockingSender, info, sync::Lockable};
+use embedded_services::{debug, error, event::NonBlockingSender, info, sync::Lockable, warn};
 use embedded_usb_pd::{
     PdError,
     constants::{T_PS_TRANSITION_EPR_MS, T_PS_TRANSITION_SPR_MS},
 };
 use power_policy_interface::{
-    capability::{ConsumerDisconnect, ConsumerPowerCapability, ProviderPowerCapability, PsuType},
+    capability::{ConsumerDisconnect, ConsumerPowerCapability, PowerCapability, ProviderPowerCapability, PsuType},
     psu::{Error as PsuError, Psu, State},
 };
+use type_c_interface::control::cable::CableInfo;
 use type_c_interface::controller::power::SystemPowerStateStatus;
 
-use crate::controller::config::UnconstrainedSink;
+use crate::controller::config::{SinkReadyTimeout, UnconstrainedSink};
 use type_c_interface::util::power_policy_error_from_pd_error;
 
 use super::*;
@@ -29,7 +30,7 @@ impl<
     pub(super) async fn process_new_consumer_contract(&mut self, new_status: &PortStatus) -> Result<(), PdError> {
         info!("Process new consumer contract");
         let available_sink_contract = new_status.available_sink_contract.map(|c| {
-            let mut c: ConsumerPowerCapability = c.into();
+            let mut c: ConsumerPowerCapability = self.limit_to_cable(c, &new_status.cable).into();
             let unconstrained = match self.config.unconstrained_sink {
                 UnconstrainedSink::Auto => new_status.unconstrained_power,
                 UnconstrainedSink::PowerThresholdMilliwatts(threshold) => c.capability.max_power_mw() >= threshold,
@@ -58,7 +59,7 @@ impl<
     pub(super) async fn process_new_provider_contract(&mut self, new_status: &PortStatus) -> Result<(), PdError> {
         info!("Process New provider contract");
         let capability = new_status.available_source_contract.map(|caps| {
-            let mut caps = ProviderPowerCapability::from(caps);
+            let mut caps = ProviderPowerCapability::from(self.limit_to_cable(caps, &new_status.cable));
             caps.flags.set_psu_type(PsuType::TypeC);
             caps
         });
@@ -76,18 +77,55 @@ impl<
         Ok(())
     }
 
+    /// Limit a contract to the current rating of the cable
+    ///
+    /// Contracts above 3A are only allowed with a 5A eMarked cable.
+    fn limit_to_cable(&self, capability: PowerCapability, cable: &CableInfo) -> PowerCapability {
+        let max_current_ma = cable.max_current_ma();
+        if capability.current_ma <= max_current_ma {
+            return capability;
+        }
+
+        warn!(
+            "({}): Limiting contract to {}mA, cable: {:?}",
+            self.name, max_current_ma, cable
+        );
+        PowerCapability {
+            current_ma: max_current_ma,
+            ..capability
+        }
+    }
+
     /// Handle a power role swap
     ///
     /// Called on a `power_swap_completed` event. The power policy rejects a new-role connection
-    /// while the device is still tracked in its old connected role, so tear the old role down first:
-    /// disable the sink path if we were consuming, reset the local PSU state, and notify the power
-    /// policy of the disconnect. The following contract event then connects the new role.
+    /// while the device is still tracked in its old connected role, so tear the old role down first.
+    /// The following contract event then connects the new role.
     pub(super) async fn process_power_role_swap(&mut self, new_status: &PortStatus) -> Result<(), PdError> {
         // Only act while the port stays connected.
         if !new_status.is_connected() {
             return Ok(());
         }
 
+        info!("({}): Power role swap completed", self.name);
+        self.disconnect_contract().await
+    }
+
+    /// Handle a completed hard reset
+    ///
+    /// A hard reset drops any contract. If the reset was initiated by the port partner rather than the host
+    /// we're still tracked in a power role, so tear it down here. The contract negotiated after the reset then
+    /// reconnects us.
+    pub(super) async fn process_hard_reset(&mut self) -> Result<(), PdError> {
+        info!("({}): Hard reset completed", self.name);
+        self.disconnect_contract().await
+    }
+
+    /// Tear down the current contract, if any
+    ///
+    /// Disables the sink path if we were consuming power so the source can safely stop, then resets our local
+    /// state and notifies the power policy so it stops tracking us and broadcasts the matching disconnect event.
+    pub(super) async fn disconnect_contract(&mut self) -> Result<(), PdError> {
         // Nothing to tear down unless we're currently connected in a power role.
         let was_consumer = match self.psu_state.psu_state {
             PsuState::ConnectedConsumer(_) => true,
@@ -95,20 +133,13 @@ impl<
             _ => return Ok(()),
         };
 
-        info!(
-            "({}): Power role swap completed, tearing down previous contract",
-            self.name
-        );
-
-        // Disable the sink path if we were consuming power so the source can safely stop.
+        info!("({}): Tearing down previous contract", self.name);
         if was_consumer {
             self.controller.lock().await.enable_sink_path(self.port, false).await?;
         }
 
-        // Reset our local state and notify the power policy so it stops tracking us in the previous
-        // role and broadcasts the matching disconnect event.
         if let Err(e) = self.psu_state.disconnect(true) {
-            error!("({}): Error updating PSU state on role swap: {:?}", self.name, e);
+            error!("({}): Error updating PSU state on disconnect: {:?}", self.name, e);
         }
         if self
             .power_policy_sender
@@ -117,7 +148,7 @@ impl<
             ))
             .is_none()
         {
-            error!("({}): Failed to notify power policy of role swap disconnect", self.name);
+            error!("({}): Failed to notify power policy of disconnect", self.name);
         }
 
         Ok(())
@@ -146,17 +177,25 @@ impl<
         );
         if new_contract && !sink_ready && contract_changed {
             // Start the timeout
-            // Double the spec maximum transition time to provide a safety margin for hardware/controller delays or out-of-spec controllers.
-            let timeout_ms = if new_status.epr {
-                T_PS_TRANSITION_EPR_MS
-            } else {
-                T_PS_TRANSITION_SPR_MS
-            }
-            .maximum
-            .0 * 2;
+            let timeout_ms = match self.config.sink_ready_timeout {
+                // Double the spec maximum transition time to provide a safety margin for hardware/controller delays or out-of-spec controllers.
+                SinkReadyTimeout::Auto => {
+                    u64::from(
+                        if new_status.epr {
+                            T_PS_TRANSITION_EPR_MS
+                        } else {
+                            T_PS_TRANSITION_SPR_MS
+                        }
+                        .maximum
+                        .0,
+                    ) * 2
+                }
+                SinkReadyTimeout::Milliseconds(timeout_ms) => u64::from(timeout_ms),
+                SinkReadyTimeout::Disabled => return Ok(()),
+            };
 
             debug!("({}): Sink ready timeout started for {}ms", self.name, timeout_ms);
-            *timeout = Some(Instant::now() + Duration::from_millis(timeout_ms as u64));
+            *timeout = Some(Instant::now() + Duration::from_millis(timeout_ms));
         } else if timeout.is_some()
             && (!new_status.is_connected() || new_status.available_sink_contract.is_none() || sink_ready)
         {
@@ -0,0 +1,114 @@
+//! Controller error recovery
+use embedded_services::{event::NonBlockingSender, sync::Lockable, warn};
+use embedded_usb_pd::PdError;
+use type_c_interface::controller::Controller;
+use type_c_interface::controller::current_limit::CurrentLimit;
+use type_c_interface::service::event::PortEventData as ServicePortEventData;
+
+use super::*;
+use crate::controller::state::SharedState;
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd + Controller + CurrentLimit>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Top-level processing function
+    ///
+    /// Once the configured number of consecutive communication errors is reached the controller is soft reset and
+    /// the port state restored, the result of the recovery is returned in place of the error that triggered it. A
+    /// recovery that was interrupted after resetting the controller is completed before the event is processed.
+    pub async fn process_event(&mut self, event: Event) -> Result<Option<ServicePortEventData>, PdError> {
+        if self.recovery_pending {
+            warn!("({}): Previous recovery was interrupted, resuming", self.name);
+            self.recover().await?;
+        }
+
+        let e = match self.dispatch_event(event).await {
+            Ok(output) => {
+                self.consecutive_errors = 0;
+                return Ok(output);
+            }
+            Err(e) => e,
+        };
+
+        if !is_communication_error(e) {
+            // The controller responded, so it doesn't need to be reset
+            self.consecutive_errors = 0;
+            return Err(e);
+        }
+
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        match self.config.error_recovery_threshold {
+            Some(threshold) if self.consecutive_errors >= threshold.get() => {
+                warn!(
+                    "({}): {} consecutive errors, last error {:?}, attempting recovery",
+                    self.name, self.consecutive_errors, e
+                );
+                self.recover().await.map(Some)
+            }
+            _ => Err(e),
+        }
+    }
+
+    /// Soft reset the controller and restore the port state
+    ///
+    /// The cached status is re-synced through [`Self::sync_state`] so any change that happened while the
+    /// controller was unresponsive is processed as a regular event. The unconstrained power state, the advertised
+    /// source current and the sink path for the current consumer contract are then replayed since the reset clears
+    /// them.
+    ///
+    /// DROP SAFETY: If dropped after the reset was issued, the recovery is resumed by the next call to
+    /// [`Self::process_event`] so the port state is still restored.
+    pub async fn recover(&mut self) -> Result<ServicePortEventData, PdError> {
+        info!("({}): Soft resetting controller", self.name);
+        self.recovery_pending = true;
+        if let Err(e) = self.controller.lock().await.reset_controller().await {
+            error!("({}): Controller soft reset failed: {:?}", self.name, e);
+            self.recovery_pending = false;
+            return Err(e);
+        }
+        self.consecutive_errors = 0;
+
+        self.sync_state().await?;
+        self.replay_policy().await?;
+        self.recovery_pending = false;
+
+        let event = ServicePortEventData::ControllerRecovered;
+        if self.type_c_sender.try_send(event).is_none() {
+            error!("Failed to send controller recovered type-C event");
+        }
+        Ok(event)
+    }
+
+    /// Re-apply the policy decisions made before the reset
+    async fn replay_policy(&mut self) -> Result<(), PdError> {
+        let mut controller = self.controller.lock().await;
+        if let Some(unconstrained) = self.unconstrained_power {
+            debug!("({}): Replaying unconstrained power: {}", self.name, unconstrained);
+            controller.set_unconstrained_power(self.port, unconstrained).await?;
+        }
+
+        // The current limit caps both the source PDOs and Rp, the reset restores the controller defaults
+        if let Some(current_ma) = self.port_current_limit() {
+            debug!("({}): Replaying current limit: {} mA", self.name, current_ma);
+            controller.set_current_limit(self.port, Some(current_ma)).await?;
+        }
+
+        if let PsuState::ConnectedConsumer(capability) = self.psu_state.psu_state {
+            debug!("({}): Replaying consumer contract: {:?}", self.name, capability);
+            controller.enable_sink_path(self.port, true).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if the error indicates the controller couldn't be reached, as opposed to the controller rejecting
+/// a request
+fn is_communication_error(e: PdError) -> bool {
+    matches!(e, PdError::Failed | PdError::Timeout)
+}
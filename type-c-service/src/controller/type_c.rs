@@ -1,7 +1,7 @@
 //! Type-C state machine port trait implementation
 use embedded_services::{event::NonBlockingSender, sync::Lockable};
 use embedded_usb_pd::PdError;
-use type_c_interface::control::type_c::TypeCStateMachineState;
+use type_c_interface::control::type_c::{TryRole, TypeCStateMachineState};
 use type_c_interface::controller::type_c::StateMachine;
 
 use super::*;
@@ -23,4 +23,31 @@ impl<
             .set_type_c_state_machine_config(self.port, state)
             .await
     }
+
+    async fn set_try_role(&mut self, role: TryRole) -> Result<(), PdError> {
+        info!("({}): Setting try role {:?}", self.name, role);
+        self.controller.lock().await.set_try_role(self.port, role).await?;
+        self.try_role = role;
+        Ok(())
+    }
+}
+
+impl<
+    'device,
+    C: Lockable<Inner: Pd + StateMachine>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Apply the current Try.SRC/Try.SNK preference to the controller
+    ///
+    /// The preference starts out as [`Config::try_role`](config::Config::try_role). Call this at init and after a
+    /// [`ControllerRecovered`](ServicePortEventData::ControllerRecovered) event since a controller reset clears it.
+    pub async fn apply_try_role(&mut self) -> Result<(), PdError> {
+        let role = self.try_role;
+        debug!("({}): Applying try role {:?}", self.name, role);
+        self.controller.lock().await.set_try_role(self.port, role).await
+    }
 }
@@ -1,11 +1,45 @@
 //! UCSI LPM port trait implementation
 use embedded_services::{event::NonBlockingSender, sync::Lockable};
+use embedded_usb_pd::ucsi::lpm::get_connector_status::{ConnectedStatus, ProviderCapsLimitedReason};
 use embedded_usb_pd::{PdError, ucsi::lpm};
 use type_c_interface::ucsi::Lpm as UcsiLpm;
 
 use super::*;
 use crate::controller::state::SharedState;
 
+impl<
+    'device,
+    C: Lockable<Inner: Pd>,
+    Shared: Lockable<Inner = SharedState>,
+    TypeCSender: NonBlockingSender<type_c_interface::service::event::PortEventData>,
+    PowerSender: NonBlockingSender<power_policy_interface::psu::event::EventData>,
+    LoopbackSender: NonBlockingSender<event::Loopback>,
+> Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
+{
+    /// Reason the source capabilities offered on this port are limited, if they are
+    fn provider_caps_limited_reason(&self) -> Option<ProviderCapsLimitedReason> {
+        let PsuState::ConnectedProvider(connected) = self.psu_state.psu_state else {
+            return None;
+        };
+
+        if self.port_current_limit().is_some() {
+            // Over-temperature or firmware update current limit
+            return Some(ProviderCapsLimitedReason::PowerBudgetLowered);
+        }
+
+        // The power policy grants less than requested once the system reaches its provider power budget
+        let requested = self.psu_state.requested_provider_capability?;
+        (connected.capability.max_power_mw() < requested.capability.max_power_mw())
+            .then_some(ProviderCapsLimitedReason::ReachingPowerBudgetLimit)
+    }
+
+    /// Fill in the GET_CONNECTOR_STATUS fields that depend on port state rather than the controller
+    fn fill_connector_status(&self, status: &mut ConnectedStatus) {
+        status.orientation = self.status.plug_orientation;
+        status.provider_caps_limited_reason = self.provider_caps_limited_reason();
+    }
+}
+
 impl<
     'device,
     C: Lockable<Inner: Pd + UcsiLpm>,
@@ -16,6 +50,14 @@ impl<
 > type_c_interface::ucsi::Lpm for Port<'device, C, Shared, TypeCSender, PowerSender, LoopbackSender>
 {
     async fn execute_lpm_command(&mut self, command: lpm::LocalCommand) -> Result<Option<lpm::ResponseData>, PdError> {
-        self.controller.lock().await.execute_lpm_command(command).await
+        let mut response = self.controller.lock().await.execute_lpm_command(command).await;
+        if let Ok(Some(lpm::ResponseData::GetConnectorStatus(lpm::get_connector_status::ResponseData {
+            status: Some(ref mut status),
+            ..
+        }))) = response
+        {
+            self.fill_connector_status(status);
+        }
+        response
     }
 }
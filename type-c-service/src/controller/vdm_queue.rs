@@ -0,0 +1,125 @@
+//! Per-port VDM receive queue
+//!
+//! VDMs can arrive in bursts faster than the platform alt-mode handler processes them. A [`VdmQueue`] buffers
+//! received VDMs so the handler can drain them at its own pace. When the queue is full the oldest VDM is dropped, on
+//! the basis that alt-mode state is driven by the most recent messages, and the drop is counted.
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embedded_services::{AtomicUsize, GlobalRawMutex, Ordering};
+use heapless::Deque;
+use type_c_interface::port::event::VdmData;
+
+/// Destination for VDMs received on a port
+pub trait VdmSink: Sync {
+    /// Queue a received VDM, must not block
+    fn push(&self, vdm: VdmData);
+}
+
+/// Bounded VDM receive queue that drops the oldest VDM on overflow
+pub struct VdmQueue<const N: usize> {
+    queue: Mutex<GlobalRawMutex, RefCell<Deque<VdmData, N>>>,
+    dropped: AtomicUsize,
+    available: Signal<GlobalRawMutex, ()>,
+}
+
+impl<const N: usize> VdmQueue<N> {
+    /// Create a new, empty queue
+    pub const fn new() -> Self {
+        Self {
+            queue: Mutex::new(RefCell::new(Deque::new())),
+            dropped: AtomicUsize::new(0),
+            available: Signal::new(),
+        }
+    }
+
+    /// Take the oldest queued VDM, if any
+    pub fn try_receive(&self) -> Option<VdmData> {
+        self.queue.lock(|queue| queue.borrow_mut().pop_front())
+    }
+
+    /// Wait for and take the oldest queued VDM
+    pub async fn receive(&self) -> VdmData {
+        loop {
+            if let Some(vdm) = self.try_receive() {
+                return vdm;
+            }
+            self.available.wait().await;
+        }
+    }
+
+    /// Number of queued VDMs
+    pub fn len(&self) -> usize {
+        self.queue.lock(|queue| queue.borrow().len())
+    }
+
+    /// Returns true if no VDMs are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of VDMs dropped because the queue was full
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<const N: usize> Default for VdmQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> VdmSink for VdmQueue<N> {
+    fn push(&self, vdm: VdmData) {
+        let overflowed = self.queue.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            let overflowed = queue.is_full() && queue.pop_front().is_some();
+            // There's always room after dropping the oldest entry
+            let _ = queue.push_back(vdm);
+            overflowed
+        });
+
+        if overflowed {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        self.available.signal(());
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use type_c_interface::control::vdm::OtherVdm;
+
+    fn vdm(tag: u8) -> VdmData {
+        let mut other = OtherVdm::default();
+        if let Some(byte) = other.data.first_mut() {
+            *byte = tag;
+        }
+        VdmData::ReceivedOther(other)
+    }
+
+    fn tag(vdm: VdmData) -> u8 {
+        match vdm {
+            VdmData::ReceivedOther(other) => other.data.first().copied().unwrap(),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn drop_oldest_on_overflow() {
+        let queue: VdmQueue<2> = VdmQueue::new();
+        queue.push(vdm(1));
+        queue.push(vdm(2));
+        queue.push(vdm(3));
+
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.try_receive().map(tag), Some(2));
+        assert_eq!(tag(embassy_futures::block_on(queue.receive())), 3);
+        assert!(queue.is_empty());
+    }
+}
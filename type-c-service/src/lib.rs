@@ -1,7 +1,13 @@
 #![no_std]
+
+// Host tests use the embassy-time driver from the test support crate
+#[cfg(test)]
+use odp_test_support as _;
+
 pub mod controller;
 pub mod service;
 pub mod task;
+pub mod thermal;
 pub mod util;
 
 use core::iter::Enumerate;
@@ -0,0 +1,137 @@
+//! Tracking of port setting owners, see [`type_c_interface::service::arbitration`]
+use type_c_interface::service::arbitration::{Conflict, Origin, Resolution, Setting, precedence};
+
+use super::*;
+
+/// Owner of each setting of a port, indexed by [`Setting::index`]
+type PortOwners = [Option<Origin>; Setting::COUNT];
+
+/// Owners of the settings of all ports
+#[derive(Default)]
+pub(super) struct Ownership {
+    ports: heapless::Vec<PortOwners, MAX_SUPPORTED_PORTS>,
+}
+
+impl Ownership {
+    fn owner_mut(&mut self, port: GlobalPortId, setting: Setting) -> Option<&mut Option<Origin>> {
+        let index = usize::from(port.0);
+        while self.ports.len() <= index {
+            self.ports.push([None; Setting::COUNT]).ok()?;
+        }
+        self.ports.get_mut(index)?.get_mut(setting.index())
+    }
+
+    /// Request `setting` on behalf of `origin`
+    ///
+    /// Returns `Ok` with any conflict that was resolved in favor of the request, or `Err` with the conflict if the
+    /// request was rejected. The requester becomes the owner unless the request was rejected.
+    pub fn request(
+        &mut self,
+        port: GlobalPortId,
+        setting: Setting,
+        origin: Origin,
+    ) -> Result<Option<Conflict>, Conflict> {
+        let Some(owner) = self.owner_mut(port, setting) else {
+            // Untracked port, nothing to arbitrate
+            return Ok(None);
+        };
+
+        let conflict = |resolution| Conflict {
+            port,
+            setting,
+            requester: origin,
+            resolution,
+        };
+        match *owner {
+            Some(current) if current != origin && precedence(setting) != origin => Err(conflict(Resolution::Rejected)),
+            Some(current) if current != origin => {
+                *owner = Some(origin);
+                Ok(Some(conflict(Resolution::Overridden)))
+            }
+            _ => {
+                *owner = Some(origin);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Release `setting` if it's owned by `origin`
+    pub fn release(&mut self, port: GlobalPortId, setting: Setting, origin: Origin) {
+        if let Some(owner) = self.owner_mut(port, setting)
+            && *owner == Some(origin)
+        {
+            *owner = None;
+        }
+    }
+
+    /// Release all settings of a port, e.g. on disconnect
+    pub fn release_port(&mut self, port: GlobalPortId) {
+        if let Some(owners) = self.ports.get_mut(usize::from(port.0)) {
+            *owners = [None; Setting::COUNT];
+        }
+    }
+}
+
+impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
+    /// Arbitrate a request for `setting`, broadcasting any conflict
+    ///
+    /// Returns false if the request was rejected.
+    pub(super) fn arbitrate(&mut self, port_id: GlobalPortId, setting: Setting, origin: Origin) -> bool {
+        let (conflict, accepted) = match self.ownership.request(port_id, setting, origin) {
+            Ok(conflict) => (conflict, true),
+            Err(conflict) => (Some(conflict), false),
+        };
+
+        if let Some(conflict) = conflict {
+            info!("{:?}: Setting conflict: {:?}", port_id, conflict);
+            if let Ok(port) = self.lookup_port(port_id) {
+                self.broadcast_event(ServiceEvent {
+                    port,
+                    event: EventData::SettingConflict(conflict),
+                });
+            }
+        }
+
+        accepted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence_decides_conflicts() {
+        let mut ownership = Ownership::default();
+        let port = GlobalPortId(1);
+
+        // Policy lowers the power level, the host can't raise it back
+        assert_eq!(ownership.request(port, Setting::PowerLevel, Origin::Policy), Ok(None));
+        assert_eq!(
+            ownership.request(port, Setting::PowerLevel, Origin::Host),
+            Err(Conflict {
+                port,
+                setting: Setting::PowerLevel,
+                requester: Origin::Host,
+                resolution: Resolution::Rejected,
+            })
+        );
+        ownership.release(port, Setting::PowerLevel, Origin::Policy);
+        assert_eq!(ownership.request(port, Setting::PowerLevel, Origin::Host), Ok(None));
+
+        // The host takes precedence for the data role
+        assert_eq!(ownership.request(port, Setting::DataRole, Origin::Policy), Ok(None));
+        assert_eq!(
+            ownership.request(port, Setting::DataRole, Origin::Host),
+            Ok(Some(Conflict {
+                port,
+                setting: Setting::DataRole,
+                requester: Origin::Host,
+                resolution: Resolution::Overridden,
+            }))
+        );
+
+        ownership.release_port(port);
+        assert_eq!(ownership.request(port, Setting::PowerLevel, Origin::Host), Ok(None));
+    }
+}
@@ -1,3 +1,4 @@
+use embassy_time::Duration;
 use embedded_usb_pd::ucsi::{self, lpm::get_connector_status::BatteryChargingCapabilityStatus};
 
 /// UCSI battery charging capability status configuration.
@@ -108,6 +109,11 @@ pub struct Config {
     pub ucsi_port_capabilities: Option<ucsi::lpm::get_connector_capability::ResponseData>,
     /// UCSI battery charging configuration
     pub ucsi_battery_charging_config: UcsiBatteryChargingThresholdConfig,
+    /// Timeout for LPM commands executed on a controller
+    ///
+    /// If the controller doesn't respond within this time the command completes with an error so the OPM
+    /// isn't left waiting on a command that will never complete. `None` waits indefinitely.
+    pub ucsi_lpm_command_timeout: Option<Duration>,
 }
 
 #[cfg(test)]
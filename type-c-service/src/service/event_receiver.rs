@@ -17,6 +17,30 @@ impl<PowerReceiver: Receiver<PowerPolicyEventData>> PowerPolicySubscriber<PowerR
     }
 }
 
+/// Wait for an event from any of the receivers, returning it along with the receiver's item and index.
+///
+/// Receivers are polled starting from `start` and wrapping around, so whichever receiver is first in that order wins
+/// when several have events pending.
+async fn select_from<E, R: Receiver<E>, T: Copy, const N: usize>(
+    receivers: &mut [R; N],
+    items: &[T; N],
+    start: usize,
+) -> (E, T, usize) {
+    let mut futures = heapless::Vec::<_, N>::new();
+    for (index, (receiver, item)) in receivers.iter_mut().zip(items.iter()).enumerate() {
+        // Push will never fail since the number of receivers is the same as the capacity of the vector
+        let _ = futures.push(async move { (receiver.wait_next().await, *item, index) });
+    }
+    if !futures.is_empty() {
+        futures.rotate_left(start % futures.len());
+    }
+    // Pin the futures and deference to a slice
+    let pinned = pin!(futures);
+    // Safety: The backing buffer is contained within the heapless::Vec so it won't be moved either.
+    let (result, _) = select_slice(unsafe { pinned.map_unchecked_mut(|f| f.as_mut()) }).await;
+    result
+}
+
 pub struct ArrayPortReceivers<
     'port,
     const N: usize,
@@ -25,6 +49,8 @@ pub struct ArrayPortReceivers<
 > {
     ports: [&'port Port; N],
     port_receivers: [PortReceiver; N],
+    /// Port polled first on the next wait, so that a busy port can't starve the others
+    next_start: usize,
 }
 
 impl<
@@ -34,21 +60,14 @@ impl<
     PortReceiver: Receiver<type_c_interface::service::event::PortEventData>,
 > ArrayPortReceivers<'port, N, Port, PortReceiver>
 {
-    /// Get the next pending PSU event
+    /// Get the next pending port event
+    ///
+    /// Ports are serviced round-robin, the port after the one that was just serviced is polled first on the next call.
     pub async fn wait_next(&mut self) -> Event<'port, Port> {
-        let ((event, port), _) = {
-            let mut futures = heapless::Vec::<_, N>::new();
-            for (receiver, psu) in self.port_receivers.iter_mut().zip(self.ports.iter()) {
-                // Push will never fail since the number of receivers is the same as the capacity of the vector
-                let _ = futures.push(async move { (receiver.wait_next().await, psu) });
-            }
-            // Pin the futures and deference to a slice
-            let pinned = pin!(futures);
-            // Safety: The backing buffer is contained within the heapless::Vec so it won't be moved either.
-            select_slice(unsafe { pinned.map_unchecked_mut(|f| f.as_mut()) }).await
-        };
+        let (event, port, index) = select_from(&mut self.port_receivers, &self.ports, self.next_start).await;
+        self.next_start = index + 1;
 
-        Event::PortEvent(PortEvent { port: *port, event })
+        Event::PortEvent(PortEvent { port, event })
     }
 }
 
@@ -81,7 +100,11 @@ impl<
         power_policy_event_receiver: PowerReceiver,
     ) -> Self {
         Self {
-            port_receivers: ArrayPortReceivers { ports, port_receivers },
+            port_receivers: ArrayPortReceivers {
+                ports,
+                port_receivers,
+                next_start: 0,
+            },
             power_policy_event_subscriber: PowerPolicySubscriber {
                 receiver: power_policy_event_receiver,
             },
@@ -101,3 +124,45 @@ impl<
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use embassy_sync::channel::Channel;
+    use embedded_services::GlobalRawMutex;
+
+    /// Test that the receiver polled first depends on the start index
+    #[test]
+    fn test_select_from_start() {
+        let channel0: Channel<GlobalRawMutex, u32, 2> = Channel::new();
+        let channel1: Channel<GlobalRawMutex, u32, 2> = Channel::new();
+        let channel2: Channel<GlobalRawMutex, u32, 2> = Channel::new();
+        let mut receivers = [
+            channel0.dyn_receiver(),
+            channel1.dyn_receiver(),
+            channel2.dyn_receiver(),
+        ];
+        let items = ['a', 'b', 'c'];
+
+        // Ports 0 and 2 have events pending, port 1 doesn't
+        channel0.try_send(10).unwrap();
+        channel0.try_send(11).unwrap();
+        channel2.try_send(20).unwrap();
+
+        assert_eq!(
+            embassy_futures::block_on(select_from(&mut receivers, &items, 0)),
+            (10, 'a', 0)
+        );
+        // Starting at a port without events moves on to the next one
+        assert_eq!(
+            embassy_futures::block_on(select_from(&mut receivers, &items, 1)),
+            (20, 'c', 2)
+        );
+        // Start indices past the end wrap around
+        assert_eq!(
+            embassy_futures::block_on(select_from(&mut receivers, &items, 3)),
+            (11, 'a', 0)
+        );
+    }
+}
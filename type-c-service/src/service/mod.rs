@@ -1,15 +1,20 @@
 use core::marker::PhantomData;
 use core::ptr;
 
+use embedded_services::broadcaster::immediate::Immediate;
 use embedded_services::event::NonBlockingSender as _;
 use embedded_services::named::Named as _;
+use embedded_services::service_state::RetainedState;
 use embedded_services::sync::Lockable;
 use embedded_services::{debug, error, info, trace};
 use embedded_usb_pd::GlobalPortId;
 use embedded_usb_pd::PdError as Error;
 use power_policy_interface::service::event::EventData as PowerPolicyEventData;
-use type_c_interface::control::pd::PortStatus;
+use type_c_interface::control::cable::CableInfo;
+use type_c_interface::control::otp::OtpState;
+use type_c_interface::control::pd::{PortStats, PortStatus};
 use type_c_interface::port::pd::Pd;
+use type_c_interface::service::arbitration::{Origin, Setting};
 use type_c_interface::service::event::{DebugAccessoryData, EventData, PortEvent, PortEventData};
 
 use type_c_interface::port::event::PortStatusEventBitfield;
@@ -17,12 +22,21 @@ use type_c_interface::service::event::Event as ServiceEvent;
 
 use crate::service::registration::Registration;
 
+mod arbitration;
 pub mod config;
 pub mod event_receiver;
 mod power;
 pub mod registration;
+mod snapshot;
 mod ucsi;
 
+pub use ucsi::{CommandStatus, UcsiResponse};
+
+/// Maximum number of ports across all controllers registered with the service
+///
+/// Sized for a 4-port controller alongside other controllers.
+pub const MAX_SUPPORTED_PORTS: usize = 8;
+
 /// Type-C service
 ///
 /// Constructing a Service is the first step in using the Type-C service.
@@ -30,10 +44,20 @@ mod ucsi;
 pub struct Service<'port, Reg: Registration<'port>> {
     /// UCSI state
     ucsi: ucsi::State,
+    /// Owners of port settings shared between the host and internal policy
+    ownership: arbitration::Ownership,
     /// Config
     config: config::Config,
     /// Service registration
     registration: Reg,
+    /// Status of the UCSI command in progress, shared with the host interface
+    command_status: Option<&'port CommandStatus>,
+    /// Broadcasters for port status changes, indexed by global port ID
+    port_status_broadcasters: [Option<&'port Immediate<PortStatus>>; MAX_SUPPORTED_PORTS],
+    /// Snapshot of the port states for the debug service, if any
+    state_snapshot: Option<&'port RetainedState>,
+    /// Snapshot records, indexed by global port ID
+    port_snapshots: [snapshot::PortSnapshot; MAX_SUPPORTED_PORTS],
     _phantom: PhantomData<&'port ()>,
 }
 
@@ -51,12 +75,31 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
     pub fn new(config: config::Config, registration: Reg) -> Self {
         Self {
             ucsi: ucsi::State::default(),
+            ownership: arbitration::Ownership::default(),
             config,
             registration,
+            command_status: None,
+            port_status_broadcasters: [None; MAX_SUPPORTED_PORTS],
+            state_snapshot: None,
+            port_snapshots: [snapshot::PortSnapshot::default(); MAX_SUPPORTED_PORTS],
             _phantom: PhantomData,
         }
     }
 
+    /// Create a new service that reports UCSI command progress through `command_status`
+    ///
+    /// Lets the host interface report the PPM as busy and cancel LPM commands while the service is locked.
+    pub fn new_with_command_status(
+        config: config::Config,
+        registration: Reg,
+        command_status: &'port CommandStatus,
+    ) -> Self {
+        Self {
+            command_status: Some(command_status),
+            ..Self::new(config, registration)
+        }
+    }
+
     fn get_port_index(&self, port: &'port Reg::Port) -> Result<usize, Error> {
         self.registration
             .ports()
@@ -74,6 +117,34 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
             .copied()
     }
 
+    /// Broadcast status changes of a port through `broadcaster`
+    ///
+    /// The current status is read from the port and broadcast immediately, so with a retained broadcaster (see
+    /// [`Immediate::new_retained`]) subscribers always start from the latest status.
+    pub async fn set_port_status_broadcaster(
+        &mut self,
+        port_id: GlobalPortId,
+        broadcaster: &'port Immediate<PortStatus>,
+    ) -> Result<(), Error> {
+        let status = self.lookup_port(port_id)?.lock().await.get_port_status().await?;
+        *self
+            .port_status_broadcasters
+            .get_mut(port_id.0 as usize)
+            .ok_or(Error::InvalidPort)? = Some(broadcaster);
+        broadcaster.broadcast(status).await;
+        Ok(())
+    }
+
+    /// Get the event counters for a port
+    pub async fn get_port_stats(&self, port_id: GlobalPortId) -> Result<PortStats, Error> {
+        self.lookup_port(port_id)?.lock().await.get_port_stats().await
+    }
+
+    /// Get the cable discovered on a port, e.g. for display to the user
+    pub async fn get_cable_info(&self, port_id: GlobalPortId) -> Result<CableInfo, Error> {
+        self.lookup_port(port_id)?.lock().await.get_cable_info().await
+    }
+
     /// Send an event to all registered listeners
     fn broadcast_event(&mut self, event: ServiceEvent<'port, Reg::Port>) {
         for sender in self.registration.event_senders() {
@@ -114,8 +185,18 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
             });
         }
 
-        self.handle_ucsi_port_event(port, GlobalPortId(self.get_port_index(port)? as u8), event, &new_status)
-            .await;
+        let port_id = GlobalPortId(self.get_port_index(port)? as u8);
+        if connection_changed && !new_status.is_connected() {
+            // Settings don't carry over to the next connection
+            self.ownership.release_port(port_id);
+        }
+
+        if let Some(broadcaster) = self.port_status_broadcasters.get(port_id.0 as usize).copied().flatten() {
+            broadcaster.broadcast(new_status).await;
+        }
+        self.snapshot_port_status(port_id, &new_status);
+
+        self.handle_ucsi_port_event(port, port_id, event, &new_status).await;
 
         Ok(())
     }
@@ -131,6 +212,20 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
                 )
                 .await
             }
+            PortEventData::OverTemperature(state) => {
+                self.process_over_temperature(event.port, *state);
+                self.pend_ucsi_power_limit_change(event.port).await;
+                Ok(())
+            }
+            PortEventData::FwUpdate(notice) => {
+                info!("({}): Firmware update {:?}", event.port.lock().await.name(), notice);
+                self.broadcast_event(ServiceEvent {
+                    port: event.port,
+                    event: EventData::FwUpdate(*notice),
+                });
+                self.pend_ucsi_power_limit_change(event.port).await;
+                Ok(())
+            }
             unhandled => {
                 // Currently just log notifications, but may want to do more in the future
                 debug!(
@@ -143,6 +238,29 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         }
     }
 
+    /// Process a connector over-temperature state change
+    fn process_over_temperature(&mut self, port: &'port Reg::Port, state: OtpState) {
+        let Ok(port_index) = self.get_port_index(port) else {
+            error!("Over-temperature event from unregistered port");
+            return;
+        };
+
+        // Policy owns the power level of the port until it cools down
+        let port_id = GlobalPortId(port_index as u8);
+        if state != OtpState::Normal {
+            let _ = self.arbitrate(port_id, Setting::PowerLevel, Origin::Policy);
+        } else if !self.ucsi.thermally_limited.contains(&port_id) {
+            self.ownership.release(port_id, Setting::PowerLevel, Origin::Policy);
+        }
+
+        self.snapshot_port_otp(port_id, state);
+        info!("{:?}: Over-temperature state {:?}", port_id, state);
+        self.broadcast_event(ServiceEvent {
+            port,
+            event: EventData::OverTemperature(state),
+        });
+    }
+
     /// Process the given event
     pub async fn process_event(&mut self, event: Event<'port, Reg::Port>) -> Result<(), Error> {
         match event {
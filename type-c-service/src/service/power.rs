@@ -1,9 +1,12 @@
 use core::ptr;
 
 use embedded_services::sync::Lockable as _;
+use embedded_usb_pd::PowerRole;
+use power_policy_interface::capability::ConsumerPowerCapability;
 use power_policy_interface::service as power_policy;
 use power_policy_interface::service::event::EventData as PowerPolicyEventData;
 use type_c_interface::port::pd::Pd as _;
+use type_c_interface::service::arbitration::{Origin, Setting};
 
 use super::*;
 
@@ -70,20 +73,62 @@ impl<'a, Reg: Registration<'a>> Service<'a, Reg> {
         Ok(())
     }
 
+    /// Track which ports supply a consumer whose input power is limited by thermal policy
+    ///
+    /// The limited port is the sink port whose contract matches the consumer capability. While limited, the power level
+    /// of the port is owned by policy so host requests to change it are rejected.
+    async fn set_thermally_limited(&mut self, consumer: Option<&ConsumerPowerCapability>) {
+        let mut limited = heapless::index_set::FnvIndexSet::new();
+        if let Some(consumer) = consumer.filter(|consumer| consumer.flags.thermally_limited()) {
+            for (i, port) in self.registration.ports().iter().enumerate() {
+                let mut port = port.lock().await;
+                match port.get_port_status().await {
+                    Ok(status)
+                        if status.power_role == PowerRole::Sink
+                            && status.available_sink_contract == Some(consumer.capability) =>
+                    {
+                        // Can't fail, the set is as large as the maximum number of ports
+                        let _ = limited.insert(GlobalPortId(i as u8));
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("({}): Failed to get port status: {:?}", port.name(), e),
+                }
+            }
+        }
+
+        for i in 0..self.registration.ports().len() {
+            let port_id = GlobalPortId(i as u8);
+            match (
+                self.ucsi.thermally_limited.contains(&port_id),
+                limited.contains(&port_id),
+            ) {
+                // Policy takes precedence for the power level, so the request can't be rejected
+                (false, true) => {
+                    let _ = self.arbitrate(port_id, Setting::PowerLevel, Origin::Policy);
+                }
+                (true, false) => self.ownership.release(port_id, Setting::PowerLevel, Origin::Policy),
+                _ => {}
+            }
+        }
+        self.ucsi.thermally_limited = limited;
+    }
+
     /// Process power policy events
     pub(super) async fn process_power_policy_event(&mut self, message: &PowerPolicyEventData) -> Result<(), Error> {
         match message {
             PowerPolicyEventData::Unconstrained(state) => self.process_unconstrained_state_change(state).await,
             PowerPolicyEventData::ConsumerDisconnected(_) => {
                 self.ucsi.psu_connected = false;
+                self.set_thermally_limited(None).await;
                 // Notify OPM because this can affect battery charging capability status
                 if self.ucsi.notifications_enabled.battery_charge_change() {
                     self.pend_ucsi_connected_ports().await;
                 }
                 Ok(())
             }
-            PowerPolicyEventData::ConsumerConnected(_) => {
+            PowerPolicyEventData::ConsumerConnected(capability) => {
                 self.ucsi.psu_connected = true;
+                self.set_thermally_limited(Some(capability)).await;
                 // Notify OPM because this can affect battery charging capability status
                 if self.ucsi.notifications_enabled.battery_charge_change() {
                     self.pend_ucsi_connected_ports().await;
@@ -6,6 +6,8 @@ use type_c_interface::port::pd::Pd;
 use type_c_interface::service::event::Event as ServiceEvent;
 use type_c_interface::ucsi::Lpm as UcsiLpm;
 
+use crate::service::MAX_SUPPORTED_PORTS;
+
 /// Registration trait that abstracts over various registration details.
 pub trait Registration<'port> {
     type Port: Lockable<Inner: Pd + UcsiLpm> + 'port;
@@ -56,6 +58,7 @@ impl<
     }
 
     fn ports(&self) -> &[&'port Self::Port] {
+        const { assert!(PORT_COUNT <= MAX_SUPPORTED_PORTS, "Too many type-C ports") };
         &self.ports
     }
 
@@ -0,0 +1,116 @@
+//! Type-C service state snapshot for the debug service
+//!
+//! The snapshot is published to a [`RetainedState`] whenever a port's status changes, register it with a
+//! [`StateProvider`](embedded_services::service_state::StateProvider) under
+//! [`id::TYPE_C`](embedded_services::service_state::id::TYPE_C). The first byte holds the number of ports, followed
+//! by a record for each port in global port ID order. Record fields are little-endian:
+//!
+//! | Offset | Size | Field                                                          |
+//! |--------|------|----------------------------------------------------------------|
+//! | 0      | 1    | Port flags, see below                                          |
+//! | 1      | 4    | Maximum power of the contract in the current power role, in mW |
+//!
+//! Port flags: bit 0 connected, bit 1 debug accessory, bit 2 power source, bit 3 DFP, bit 4 EPR, bit 5 unconstrained
+//! power, bit 6 over-temperature.
+use embedded_usb_pd::{DataRole, PowerRole};
+
+use super::*;
+
+const CONNECTED: u8 = 1 << 0;
+const DEBUG_ACCESSORY: u8 = 1 << 1;
+const SOURCE: u8 = 1 << 2;
+const DFP: u8 = 1 << 3;
+const EPR: u8 = 1 << 4;
+const UNCONSTRAINED: u8 = 1 << 5;
+const OVER_TEMPERATURE: u8 = 1 << 6;
+
+/// Snapshot record of a single port
+#[derive(Clone, Copy, Default)]
+pub(super) struct PortSnapshot {
+    flags: u8,
+    contract_mw: u32,
+}
+
+impl PortSnapshot {
+    fn update_status(&mut self, status: &PortStatus) {
+        let contract = if status.power_role == PowerRole::Source {
+            status.available_source_contract
+        } else {
+            status.available_sink_contract
+        };
+        self.contract_mw = contract.map_or(0, |contract| contract.max_power_mw());
+
+        let mut flags = self.flags & OVER_TEMPERATURE;
+        for (flag, set) in [
+            (CONNECTED, status.is_connected()),
+            (DEBUG_ACCESSORY, status.is_debug_accessory()),
+            (SOURCE, status.power_role == PowerRole::Source),
+            (DFP, status.data_role == DataRole::Dfp),
+            (EPR, status.epr),
+            (UNCONSTRAINED, status.unconstrained_power),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        self.flags = flags;
+    }
+
+    fn update_otp(&mut self, state: OtpState) {
+        if state == OtpState::Normal {
+            self.flags &= !OVER_TEMPERATURE;
+        } else {
+            self.flags |= OVER_TEMPERATURE;
+        }
+    }
+}
+
+impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
+    /// Publish state snapshots to `snapshot`
+    ///
+    /// The current status is read from each port and published immediately.
+    pub async fn set_state_snapshot(&mut self, snapshot: &'port RetainedState) -> Result<(), Error> {
+        for (port, record) in self.registration.ports().iter().zip(self.port_snapshots.iter_mut()) {
+            record.update_status(&port.lock().await.get_port_status().await?);
+        }
+        self.state_snapshot = Some(snapshot);
+        self.publish_state_snapshot();
+        Ok(())
+    }
+
+    /// Update the snapshot record of a port with its new status
+    pub(super) fn snapshot_port_status(&mut self, port_id: GlobalPortId, status: &PortStatus) {
+        if let Some(record) = self.port_snapshots.get_mut(port_id.0 as usize) {
+            record.update_status(status);
+            self.publish_state_snapshot();
+        }
+    }
+
+    /// Update the snapshot record of a port with its over-temperature state
+    pub(super) fn snapshot_port_otp(&mut self, port_id: GlobalPortId, state: OtpState) {
+        if let Some(record) = self.port_snapshots.get_mut(port_id.0 as usize) {
+            record.update_otp(state);
+            self.publish_state_snapshot();
+        }
+    }
+
+    fn publish_state_snapshot(&self) {
+        let Some(snapshot) = self.state_snapshot else {
+            return;
+        };
+
+        let ports = self
+            .port_snapshots
+            .get(..self.registration.ports().len())
+            .unwrap_or(&self.port_snapshots);
+        snapshot.publish(|writer| {
+            ports
+                .iter()
+                .fold(
+                    writer.put(&[u8::try_from(ports.len()).unwrap_or(u8::MAX)]),
+                    |writer, record| writer.put(&[record.flags]).put(&record.contract_mw.to_le_bytes()),
+                )
+                .finish()
+        });
+    }
+}
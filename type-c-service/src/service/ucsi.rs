@@ -1,6 +1,14 @@
-use embedded_services::sync::Lockable;
-use embedded_services::warn;
+use core::cell::Cell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use bitfield::bitfield;
+use embassy_futures::select::{Either, select};
+use embassy_sync::signal::Signal;
+use embassy_time::with_timeout;
+use embedded_services::sync::{Lockable, cancel_safe};
+use embedded_services::{GlobalRawMutex, warn};
 use embedded_usb_pd::ucsi::cci::{Cci, GlobalCci};
+use embedded_usb_pd::ucsi::lpm::connector_reset::ResetType;
 use embedded_usb_pd::ucsi::lpm::get_connector_status::{BatteryChargingCapabilityStatus, ConnectorStatusChange};
 use embedded_usb_pd::ucsi::ppm::set_notification_enable::NotificationEnable;
 use embedded_usb_pd::ucsi::ppm::state_machine::{
@@ -8,12 +16,117 @@ use embedded_usb_pd::ucsi::ppm::state_machine::{
 };
 use embedded_usb_pd::ucsi::{GlobalCommand, ResponseData, lpm, ppm};
 use embedded_usb_pd::{PdError, PowerRole};
+use type_c_interface::control::pd::Pdos;
+use type_c_interface::service::arbitration::{Origin, Setting};
 use type_c_interface::service::event::{Event, UsciChangeIndicatorData};
 use type_c_interface::ucsi::Lpm as _;
 
 use super::*;
 
-const MAX_SUPPORTED_PORTS: usize = 4;
+bitfield! {
+    /// UCSI GET_ERROR_STATUS error information
+    #[derive(Copy, Clone, PartialEq, Eq, Default)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub(super) struct ErrorInformation(u16);
+    impl Debug;
+    /// Unrecognized command
+    pub bool, unrecognized_command, set_unrecognized_command: 0;
+    /// Non-existent connector number
+    pub bool, non_existent_connector, set_non_existent_connector: 1;
+    /// Invalid command specific parameters
+    pub bool, invalid_parameters, set_invalid_parameters: 2;
+    /// Incompatible connector partner
+    pub bool, incompatible_partner, set_incompatible_partner: 3;
+    /// CC communication error
+    pub bool, cc_communication_error, set_cc_communication_error: 4;
+    /// Command unsuccessful due to dead battery condition
+    pub bool, dead_battery, set_dead_battery: 5;
+    /// Contract negotiation failure
+    pub bool, contract_negotiation_failure, set_contract_negotiation_failure: 6;
+    /// Overcurrent
+    pub bool, overcurrent, set_overcurrent: 7;
+    /// Undefined
+    pub bool, undefined, set_undefined: 8;
+    /// Port partner rejected swap
+    pub bool, swap_rejected_by_partner, set_swap_rejected_by_partner: 9;
+    /// Hard reset
+    pub bool, hard_reset, set_hard_reset: 10;
+    /// PPM policy conflict
+    pub bool, policy_conflict, set_policy_conflict: 11;
+}
+
+/// Kind of command, as far as the reported error information is concerned
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CommandKind {
+    /// Power or data role swap
+    Swap,
+    /// Hard reset of the connector
+    HardReset,
+    /// Any other command
+    Other,
+}
+
+impl CommandKind {
+    fn new(command: &GlobalCommand) -> Self {
+        let GlobalCommand::LpmCommand(command) = command else {
+            return Self::Other;
+        };
+
+        match command.operation() {
+            lpm::CommandData::SetPdr { .. } | lpm::CommandData::SetUor { .. } => Self::Swap,
+            lpm::CommandData::ConnectorReset(args) if matches!(args.reset_type, ResetType::Hard) => Self::HardReset,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl ErrorInformation {
+    /// Build the error information reported for a command that completed with `error`
+    fn new(command: &GlobalCommand, error: PdError) -> Self {
+        Self::for_kind(CommandKind::new(command), error)
+    }
+
+    fn for_kind(kind: CommandKind, error: PdError) -> Self {
+        let mut information = Self::default();
+        match (error, kind) {
+            (PdError::UnrecognizedCommand, _) => information.set_unrecognized_command(true),
+            (PdError::InvalidPort, _) => information.set_non_existent_connector(true),
+            (PdError::InvalidParams, _) => information.set_invalid_parameters(true),
+            // The partner refused the swap
+            (PdError::Rejected, CommandKind::Swap) => information.set_swap_rejected_by_partner(true),
+            // The partner refused the request, the power contract couldn't be negotiated
+            (PdError::Rejected, _) => information.set_contract_negotiation_failure(true),
+            (_, CommandKind::HardReset) => information.set_hard_reset(true),
+            _ => information.set_undefined(true),
+        }
+        information
+    }
+}
+
+/// Details of the last command that completed with an error
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(super) struct CommandError {
+    /// Connector targeted by the command, `None` for PPM commands
+    pub port: Option<GlobalPortId>,
+    /// Error the command completed with
+    pub error: PdError,
+    /// Error information reported through GET_ERROR_STATUS
+    pub information: ErrorInformation,
+}
+
+impl CommandError {
+    fn new(command: &GlobalCommand, error: PdError) -> Self {
+        Self {
+            port: match command {
+                GlobalCommand::PpmCommand(_) => None,
+                GlobalCommand::LpmCommand(command) => Some(command.port()),
+            },
+            error,
+            information: ErrorInformation::new(command, error),
+        }
+    }
+}
 
 /// UCSI command response
 #[derive(Copy, Clone, Debug)]
@@ -27,6 +140,108 @@ pub struct UcsiResponse {
     pub data: Result<Option<ucsi::ResponseData>, PdError>,
 }
 
+/// Status of the UCSI command being processed, shared with the host interface
+///
+/// The service is locked while it processes a command, so the host interface checks each command from the OPM with
+/// [`Self::check`] before passing it to [`Service::process_ucsi_command`]. Only one command can be in progress at a
+/// time, the OPM is told the PPM is busy until it completes.
+pub struct CommandStatus {
+    /// A command is being processed
+    in_progress: AtomicBool,
+    /// Signalled to cancel the command in progress
+    cancel: Signal<GlobalRawMutex, ()>,
+}
+
+impl CommandStatus {
+    /// Create a new instance with no command in progress
+    pub const fn new() -> Self {
+        Self {
+            in_progress: AtomicBool::new(false),
+            cancel: Signal::new(),
+        }
+    }
+
+    /// Returns true if a command is in progress
+    pub fn is_busy(&self) -> bool {
+        self.in_progress.load(Ordering::Acquire)
+    }
+
+    /// Check a command received from the OPM against the command in progress
+    ///
+    /// Returns `None` if the command should be passed on to [`Service::process_ucsi_command`], otherwise the CCI to
+    /// report to the OPM immediately. CANCEL aborts the command in progress, which then completes with the cancel
+    /// completed indicator. PPM_RESET also aborts it and is then passed on.
+    pub fn check(&self, command: &GlobalCommand) -> Option<GlobalCci> {
+        if !self.is_busy() {
+            return None;
+        }
+
+        match command {
+            GlobalCommand::PpmCommand(ppm::Command::PpmReset) => {
+                self.cancel.signal(());
+                None
+            }
+            GlobalCommand::PpmCommand(ppm::Command::Cancel) => {
+                debug!("Cancelling command in progress");
+                self.cancel.signal(());
+                Some(Self::busy_cci())
+            }
+            _ => {
+                debug!("Command received while busy");
+                Some(Self::busy_cci())
+            }
+        }
+    }
+
+    fn busy_cci() -> GlobalCci {
+        let mut cci = Cci::default();
+        cci.set_busy(true);
+        cci
+    }
+
+    fn begin(&self) {
+        // Clear any cancellation left over from a command that completed before it was processed
+        self.cancel.reset();
+        self.in_progress.store(true, Ordering::Release);
+    }
+
+    fn end(&self) {
+        self.in_progress.store(false, Ordering::Release);
+    }
+}
+
+impl Default for CommandStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Partner PDOs cached for GET_PDOS
+///
+/// Filled on the first request after a contract is negotiated, invalidated when the contract changes or the partner
+/// detaches.
+#[derive(Default)]
+pub(super) struct PartnerPdos {
+    /// Partner source capabilities
+    source: Cell<Option<Pdos>>,
+    /// Partner sink capabilities
+    sink: Cell<Option<Pdos>>,
+}
+
+impl PartnerPdos {
+    fn get(&self, role: PowerRole) -> &Cell<Option<Pdos>> {
+        match role {
+            PowerRole::Source => &self.source,
+            PowerRole::Sink => &self.sink,
+        }
+    }
+
+    fn invalidate(&self) {
+        self.source.set(None);
+        self.sink.set(None);
+    }
+}
+
 /// UCSI state
 #[derive(Default)]
 pub(super) struct State {
@@ -36,6 +251,8 @@ pub(super) struct State {
     pub notifications_enabled: NotificationEnable,
     /// Queued pending port notifications
     pub pending_ports: heapless::Deque<GlobalPortId, MAX_SUPPORTED_PORTS>,
+    /// Enabled connector changes that haven't been acknowledged yet, indexed by port
+    pub pending_changes: [ConnectorStatusChange; MAX_SUPPORTED_PORTS],
     /// Ports that have a valid battery charging status capability
     ///
     /// We provide a battery charging status only after the port has negotiated power.
@@ -43,6 +260,12 @@ pub(super) struct State {
     pub valid_battery_charging_capability: heapless::index_set::FnvIndexSet<GlobalPortId, MAX_SUPPORTED_PORTS>,
     /// PSU connected
     pub psu_connected: bool,
+    /// Ports supplying a consumer whose input power is capped by thermal policy
+    pub thermally_limited: heapless::index_set::FnvIndexSet<GlobalPortId, MAX_SUPPORTED_PORTS>,
+    /// Last command that completed with an error, cleared by the next successful command other than GET_ERROR_STATUS
+    pub last_error: Option<CommandError>,
+    /// Cached partner PDOs, indexed by port
+    pub partner_pdos: [PartnerPdos; MAX_SUPPORTED_PORTS],
 }
 
 impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
@@ -51,13 +274,33 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         debug!("Resetting PPM");
         self.ucsi.notifications_enabled = NotificationEnable::default();
         self.ucsi.pending_ports.clear();
+        self.ucsi.pending_changes = Default::default();
         self.ucsi.valid_battery_charging_capability.clear();
+        self.ucsi.last_error = None;
     }
 
     /// Set notification enable implementation
+    ///
+    /// Pending connector changes that are no longer enabled are dropped, along with any port left without changes.
     fn process_set_notification_enable(&mut self, enable: NotificationEnable) {
         debug!("Set Notification Enable: {:?}", enable);
         self.ucsi.notifications_enabled = enable;
+
+        for changes in self.ucsi.pending_changes.iter_mut() {
+            *changes = changes.filter_enabled(enable);
+        }
+        let pending_ports = core::mem::take(&mut self.ucsi.pending_ports);
+        for port_id in pending_ports {
+            if self
+                .ucsi
+                .pending_changes
+                .get(port_id.0 as usize)
+                .is_some_and(|changes| !changes.is_empty())
+            {
+                // Can't overflow since at most as many ports as before are pushed back
+                let _ = self.ucsi.pending_ports.push_back(port_id);
+            }
+        }
     }
 
     /// PPM get capabilities implementation
@@ -75,6 +318,8 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
                 Ok(None)
             }
             ppm::Command::GetCapability => Ok(Some(self.process_get_capabilities())),
+            // The previous command already completed, nothing to cancel
+            ppm::Command::Cancel => Ok(None),
             _ => Ok(None), // Other commands are currently no-ops
         }
     }
@@ -86,7 +331,12 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         port_status: &PortStatus,
     ) -> Option<BatteryChargingCapabilityStatus> {
         if port_status.power_role == PowerRole::Sink {
-            if self.ucsi.valid_battery_charging_capability.contains(&port_id) && !self.ucsi.psu_connected {
+            if self.ucsi.valid_battery_charging_capability.contains(&port_id)
+                && self.ucsi.thermally_limited.contains(&port_id)
+            {
+                // Tell the OPM why charging is slow even though the contract itself would be sufficient
+                Some(BatteryChargingCapabilityStatus::Slow)
+            } else if self.ucsi.valid_battery_charging_capability.contains(&port_id) && !self.ucsi.psu_connected {
                 // Only run this logic when no PSU is attached to prevent excessive notifications
                 // when new type-C PSUs are attached
                 let power_mw = port_status
@@ -105,8 +355,18 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         }
     }
 
+    /// GET_ERROR_STATUS implementation
+    ///
+    /// Handled by the service rather than the controller since the error may not have come from the controller, e.g.
+    /// a command for a non-existent connector.
+    fn process_get_error_status(&self) -> lpm::ResponseData {
+        let information = self.ucsi.last_error.map(|e| e.information).unwrap_or_default();
+        debug!("Get error status: {:?}", information);
+        lpm::ResponseData::GetErrorStatus(lpm::get_error_status::ResponseData::from(information.0))
+    }
+
     async fn process_lpm_command(
-        &mut self,
+        &self,
         command: &ucsi::lpm::GlobalCommand,
     ) -> Result<Option<lpm::ResponseData>, PdError> {
         debug!("Processing LPM command: {:?}", command);
@@ -117,6 +377,43 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
             .ok_or(PdError::InvalidPort)?;
         let local_command = ucsi::lpm::LocalCommand::new(local_port_id, command.operation());
 
+        let Some(timeout) = self.config.ucsi_lpm_command_timeout else {
+            return self.execute_lpm_command(command, &mut *port, local_command).await;
+        };
+
+        with_timeout(timeout, self.execute_lpm_command(command, &mut *port, local_command))
+            .await
+            .unwrap_or_else(|_| {
+                error!("{:?}: LPM command timed out", command.port());
+                Err(PdError::Timeout)
+            })
+    }
+
+    /// Process an LPM command that can be cancelled by the OPM, returns `None` if it was cancelled
+    async fn process_cancellable_lpm_command(
+        &self,
+        command: &ucsi::lpm::GlobalCommand,
+    ) -> Option<Result<Option<lpm::ResponseData>, PdError>> {
+        let Some(status) = self.command_status else {
+            return Some(self.process_lpm_command(command).await);
+        };
+
+        match select(self.process_lpm_command(command), status.cancel.wait()).await {
+            Either::First(result) => Some(result),
+            Either::Second(()) => {
+                debug!("{:?}: LPM command cancelled", command.port());
+                None
+            }
+        }
+    }
+
+    /// Execute an LPM command on the given port, applying any service level overrides
+    async fn execute_lpm_command(
+        &self,
+        command: &ucsi::lpm::GlobalCommand,
+        port: &mut <Reg::Port as Lockable>::Inner,
+        local_command: ucsi::lpm::LocalCommand,
+    ) -> Result<Option<lpm::ResponseData>, PdError> {
         match command.operation() {
             lpm::CommandData::GetConnectorCapability => {
                 // Override the capabilities if present in the config
@@ -130,26 +427,104 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
                 let mut response = port.execute_lpm_command(local_command).await;
                 if let Ok(Some(lpm::ResponseData::GetConnectorStatus(lpm::get_connector_status::ResponseData {
                     status_change: ref mut states_change,
-                    status:
-                        Some(lpm::get_connector_status::ConnectedStatus {
-                            ref mut battery_charging_status,
-                            ..
-                        }),
+                    ref mut status,
                     ..
                 }))) = response
                 {
-                    let port_status = port.get_port_status().await?;
-                    *battery_charging_status =
-                        self.determine_battery_charging_capability_status(command.port(), &port_status);
-                    states_change.set_battery_charging_status_change(battery_charging_status.is_some());
+                    if let Some(lpm::get_connector_status::ConnectedStatus {
+                        battery_charging_status,
+                        ..
+                    }) = status
+                    {
+                        let port_status = port.get_port_status().await?;
+                        *battery_charging_status =
+                            self.determine_battery_charging_capability_status(command.port(), &port_status);
+                        states_change.set_battery_charging_status_change(battery_charging_status.is_some());
+                    }
+
+                    // Only report the changes the OPM enabled notifications for
+                    *states_change = states_change.filter_enabled(self.ucsi.notifications_enabled);
                 }
 
                 response
             }
+            lpm::CommandData::GetPdos(args) if args.partner_pdo => {
+                let role = if args.source {
+                    PowerRole::Source
+                } else {
+                    PowerRole::Sink
+                };
+                let pdos = self.partner_pdos(command.port(), port, role).await?;
+                let response = requested_pdos(&pdos, args.pdo_offset as usize, args.num_pdos as usize);
+                Ok(Some(lpm::ResponseData::GetPdos(lpm::get_pdos::ResponseData::new(
+                    &response,
+                ))))
+            }
+            lpm::CommandData::ConnectorReset(args) => {
+                // Route through the port so the power policy is kept in sync with the reset
+                match args.reset_type {
+                    ResetType::Hard => port.hard_reset().await?,
+                    ResetType::Data => port.execute_drst().await?,
+                }
+                Ok(None)
+            }
             _ => port.execute_lpm_command(local_command).await,
         }
     }
 
+    /// Returns the partner's PDOs for `role`, fetching them from the port if they aren't cached
+    async fn partner_pdos(
+        &self,
+        port_id: GlobalPortId,
+        port: &mut <Reg::Port as Lockable>::Inner,
+        role: PowerRole,
+    ) -> Result<Pdos, PdError> {
+        let cache = self
+            .ucsi
+            .partner_pdos
+            .get(port_id.0 as usize)
+            .ok_or(PdError::InvalidPort)?
+            .get(role);
+        if let Some(pdos) = cache.get() {
+            return Ok(pdos);
+        }
+
+        let pdos = port.get_partner_pdos(role).await?;
+        cache.set(Some(pdos));
+        Ok(pdos)
+    }
+
+    /// Update the CCI completion indicators based on the result of the command
+    ///
+    /// Commands the PPM or controller don't recognize complete with the not supported indicator rather than the
+    /// error indicator, as required by the UCSI spec. Any failure is recorded so the OPM can retrieve the details
+    /// with GET_ERROR_STATUS.
+    fn set_cci_completion_status(
+        &mut self,
+        cci: &mut GlobalCci,
+        command: &GlobalCommand,
+        data: &Result<Option<ResponseData>, PdError>,
+    ) {
+        match data {
+            Ok(_) => {
+                // Keep the error around so GET_ERROR_STATUS can be issued more than once
+                if !is_get_error_status(command) {
+                    self.ucsi.last_error = None;
+                }
+            }
+            Err(e) => {
+                if matches!(e, PdError::UnrecognizedCommand) {
+                    debug!("Command not supported");
+                    cci.set_not_supported(true);
+                } else {
+                    debug!("Command completed with error: {:?}", e);
+                    cci.set_error(true);
+                }
+                self.ucsi.last_error = Some(CommandError::new(command, *e));
+            }
+        }
+    }
+
     /// Update the CCI connector change field based on the current pending port
     fn set_cci_connector_change(&self, cci: &mut GlobalCci) {
         if let Some(current_port) = self.ucsi.pending_ports.front() {
@@ -164,10 +539,13 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
     /// Acknowledge the current connector change and move to the next if present
     async fn ack_connector_change(&mut self, cci: &mut GlobalCci) {
         // Pop the just acknowledged port and move to the next if present
-        let Some(_current_port) = self.ucsi.pending_ports.pop_front() else {
+        let Some(current_port) = self.ucsi.pending_ports.pop_front() else {
             warn!("Received ACK_CCI with no pending connector changes");
             return;
         };
+        if let Some(changes) = self.ucsi.pending_changes.get_mut(current_port.0 as usize) {
+            *changes = ConnectorStatusChange::default();
+        }
 
         let Some(next_port) = self.ucsi.pending_ports.front() else {
             debug!("ACK_CCI processed, no more pending ports");
@@ -193,8 +571,25 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
     }
 
     /// Process a UCSI command
+    ///
+    /// If the service was created with a [`CommandStatus`], the command is reported as in progress until this returns
+    /// and LPM commands can be cancelled through it.
     pub async fn process_ucsi_command(&mut self, command: &GlobalCommand) -> UcsiResponse {
+        let Some(status) = self.command_status else {
+            return self.execute_ucsi_command(command).await;
+        };
+
+        status.begin();
+        let response = cancel_safe(self.execute_ucsi_command(command), || status.end()).await;
+        status.end();
+        response
+    }
+
+    /// Run a UCSI command through the PPM state machine
+    async fn execute_ucsi_command(&mut self, command: &GlobalCommand) -> UcsiResponse {
         let mut next_input = Some(PpmInput::Command(command));
+        let mut policy_conflict = false;
+        let mut cancelled = false;
         let mut response = UcsiResponse {
             notify_opm: false,
             cci: Cci::default(),
@@ -220,6 +615,7 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
                 Ok(output) => output,
                 Err(e @ InvalidTransition { .. }) => {
                     error!("PPM state machine transition failed: {:#?}", e);
+                    self.ucsi.last_error = Some(CommandError::new(command, PdError::Failed));
                     return UcsiResponse {
                         notify_opm: true,
                         cci: Cci::new_error(),
@@ -239,11 +635,20 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
                                     .process_ppm_command(ppm_command)
                                     .map(|inner| inner.map(ResponseData::Ppm));
                             }
+                            ucsi::GlobalCommand::LpmCommand(_) if is_get_error_status(command) => {
+                                response.data = Ok(Some(ResponseData::Lpm(self.process_get_error_status())));
+                            }
                             ucsi::GlobalCommand::LpmCommand(lpm_command) => {
-                                response.data = self
-                                    .process_lpm_command(lpm_command)
-                                    .await
-                                    .map(|inner| inner.map(ResponseData::Lpm));
+                                policy_conflict = lpm_setting(lpm_command.operation())
+                                    .is_some_and(|setting| !self.arbitrate(lpm_command.port(), setting, Origin::Host));
+                                response.data = if policy_conflict {
+                                    Err(PdError::Rejected)
+                                } else if let Some(result) = self.process_cancellable_lpm_command(lpm_command).await {
+                                    result.map(|inner| inner.map(ResponseData::Lpm))
+                                } else {
+                                    cancelled = true;
+                                    Ok(None)
+                                };
                             }
                         }
 
@@ -252,7 +657,12 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
                     PpmOutput::OpmNotifyCommandComplete => {
                         response.notify_opm = self.ucsi.notifications_enabled.cmd_complete();
                         response.cci.set_cmd_complete(true);
-                        response.cci.set_error(response.data.is_err());
+                        response.cci.set_cancel_complete(cancelled);
+                        self.set_cci_completion_status(&mut response.cci, command, &response.data);
+                        if policy_conflict && let Some(error) = self.ucsi.last_error.as_mut() {
+                            error.information = ErrorInformation::default();
+                            error.information.set_policy_conflict(true);
+                        }
                         self.set_cci_connector_change(&mut response.cci);
                         return response;
                     }
@@ -311,6 +721,8 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         ucsi_event.set_connect_change(port_event.plug_inserted_or_removed());
         ucsi_event.set_power_direction_changed(port_event.power_swap_completed());
         ucsi_event.set_pd_reset_complete(port_event.pd_hard_reset());
+        // A hard reset drops the contract back to Type-C current
+        ucsi_event.set_power_op_mode_change(port_event.pd_hard_reset());
 
         if port_event.data_swap_completed() || port_event.alt_mode_entered() {
             ucsi_event.set_connector_partner_changed(true);
@@ -326,6 +738,11 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
             ucsi_event.set_power_direction_changed(true);
             ucsi_event.set_battery_charging_status_change(true);
 
+            // The partner sent new capabilities, refetch them on the next GET_PDOS
+            if let Some(partner_pdos) = self.ucsi.partner_pdos.get(port_id.0 as usize) {
+                partner_pdos.invalidate();
+            }
+
             // Power negotiation completed, battery charging capability status is now valid
             if self.ucsi.valid_battery_charging_capability.insert(port_id).is_err() {
                 error!(
@@ -336,16 +753,14 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         }
 
         if !port_status.is_connected() {
-            // Reset battery charging capability status when disconnected
+            // Reset battery charging capability status and partner PDOs when disconnected
             let _ = self.ucsi.valid_battery_charging_capability.remove(&port_id);
+            if let Some(partner_pdos) = self.ucsi.partner_pdos.get(port_id.0 as usize) {
+                partner_pdos.invalidate();
+            }
         }
 
-        if ucsi_event.filter_enabled(self.ucsi.notifications_enabled).is_empty() {
-            trace!("{:?}: event received, but no UCSI notifications enabled", port_id);
-            return;
-        }
-
-        self.pend_ucsi_port(port, port_id).await;
+        self.pend_ucsi_port(port, port_id, ucsi_event).await;
     }
 
     /// Pend UCSI events for all connected ports
@@ -361,7 +776,9 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
 
             if let Ok(port_status) = port.lock().await.get_port_status().await {
                 if port_status.is_connected() {
-                    self.pend_ucsi_port(port, port_id).await;
+                    let mut changes = ConnectorStatusChange::default();
+                    changes.set_battery_charging_status_change(true);
+                    self.pend_ucsi_port(port, port_id, changes).await;
                 }
             } else {
                 error!("({}): Failed to get status for port", port.lock().await.name());
@@ -369,8 +786,49 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         }
     }
 
+    /// Pend a power change for a connected port whose current limit was applied or lifted
+    ///
+    /// The OPM rereads GET_CONNECTOR_STATUS to pick up the new power operation mode and provider capabilities limited
+    /// reason.
+    pub(super) async fn pend_ucsi_power_limit_change(&mut self, port: &'port Reg::Port) {
+        let mut changes = ConnectorStatusChange::default();
+        changes.set_power_op_mode_change(true);
+        changes.set_negotiated_power_level_change(true);
+        if changes.filter_enabled(self.ucsi.notifications_enabled).is_empty() {
+            // Nothing to notify, don't bother querying the port
+            return;
+        }
+
+        let Ok(port_index) = self.get_port_index(port) else {
+            error!("Power limit change from unregistered port");
+            return;
+        };
+
+        match port.lock().await.get_port_status().await {
+            Ok(port_status) if port_status.is_connected() => {
+                self.pend_ucsi_port(port, GlobalPortId(port_index as u8), changes).await;
+            }
+            Ok(_) => {}
+            Err(_) => error!("({}): Failed to get status for port", port.lock().await.name()),
+        }
+    }
+
     /// Pend a UCSI event for the given port
-    async fn pend_ucsi_port(&mut self, port: &'port Reg::Port, port_id: GlobalPortId) {
+    ///
+    /// Changes the OPM hasn't enabled notifications for are ignored.
+    async fn pend_ucsi_port(&mut self, port: &'port Reg::Port, port_id: GlobalPortId, changes: ConnectorStatusChange) {
+        let changes = changes.filter_enabled(self.ucsi.notifications_enabled);
+        if changes.is_empty() {
+            trace!("{:?}: event received, but no UCSI notifications enabled", port_id);
+            return;
+        }
+
+        let Some(pending_changes) = self.ucsi.pending_changes.get_mut(port_id.0 as usize) else {
+            error!("Invalid port ID: {:?}", port_id);
+            return;
+        };
+        *pending_changes = merge_status_changes(*pending_changes, changes);
+
         if self.ucsi.pending_ports.iter().any(|pending| *pending == port_id) {
             // Already have a pending event for this port, don't need to process it twice
             return;
@@ -395,3 +853,129 @@ impl<'port, Reg: Registration<'port>> Service<'port, Reg> {
         }
     }
 }
+
+/// Returns the arbitrated setting changed by an LPM command, if any
+fn lpm_setting(command: lpm::CommandData) -> Option<Setting> {
+    match command {
+        lpm::CommandData::SetPdr { .. } => Some(Setting::PowerRole),
+        lpm::CommandData::SetUor { .. } => Some(Setting::DataRole),
+        lpm::CommandData::SetSinkPath { .. } => Some(Setting::SinkPath),
+        lpm::CommandData::SetPowerLevel { .. } => Some(Setting::PowerLevel),
+        lpm::CommandData::ConnectorReset(args) if matches!(args.reset_type, ResetType::Data) => Some(Setting::DataRole),
+        _ => None,
+    }
+}
+
+/// Combine the connector changes generated by the service
+fn merge_status_changes(a: ConnectorStatusChange, b: ConnectorStatusChange) -> ConnectorStatusChange {
+    let mut merged = a;
+    merged.set_connect_change(a.connect_change() || b.connect_change());
+    merged.set_power_direction_changed(a.power_direction_changed() || b.power_direction_changed());
+    merged.set_pd_reset_complete(a.pd_reset_complete() || b.pd_reset_complete());
+    merged.set_connector_partner_changed(a.connector_partner_changed() || b.connector_partner_changed());
+    merged.set_negotiated_power_level_change(a.negotiated_power_level_change() || b.negotiated_power_level_change());
+    merged.set_power_op_mode_change(a.power_op_mode_change() || b.power_op_mode_change());
+    merged.set_external_supply_change(a.external_supply_change() || b.external_supply_change());
+    merged.set_battery_charging_status_change(a.battery_charging_status_change() || b.battery_charging_status_change());
+    merged
+}
+
+/// Returns the PDOs requested by GET_PDOS, starting at `offset`
+///
+/// The UCSI response holds at most four PDOs, so the OPM reads the rest of an EPR capabilities message with
+/// additional requests at higher offsets.
+fn requested_pdos(pdos: &Pdos, offset: usize, num_pdos: usize) -> heapless::Vec<u32, 4> {
+    pdos.as_slice()
+        .get(offset..)
+        .unwrap_or(&[])
+        .iter()
+        .take(num_pdos)
+        .take(4)
+        .copied()
+        .collect()
+}
+
+/// Returns true if the command is GET_ERROR_STATUS
+fn is_get_error_status(command: &GlobalCommand) -> bool {
+    matches!(command, GlobalCommand::LpmCommand(command) if matches!(command.operation(), lpm::CommandData::GetErrorStatus))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Test the GET_ERROR_STATUS bit reported for each error
+    #[test]
+    fn test_error_information() {
+        let information = ErrorInformation::for_kind(CommandKind::Other, PdError::UnrecognizedCommand);
+        assert!(information.unrecognized_command());
+        let information = ErrorInformation::for_kind(CommandKind::Other, PdError::InvalidPort);
+        assert!(information.non_existent_connector());
+        let information = ErrorInformation::for_kind(CommandKind::Swap, PdError::InvalidParams);
+        assert!(information.invalid_parameters());
+        let information = ErrorInformation::for_kind(CommandKind::Other, PdError::Failed);
+        assert!(information.undefined());
+
+        // Exactly one bit is reported
+        assert_eq!(information.0.count_ones(), 1);
+    }
+
+    /// Test that a rejected swap is reported as such rather than as a contract negotiation failure
+    #[test]
+    fn test_error_information_rejected() {
+        let information = ErrorInformation::for_kind(CommandKind::Swap, PdError::Rejected);
+        assert!(information.swap_rejected_by_partner());
+        assert!(!information.contract_negotiation_failure());
+
+        let information = ErrorInformation::for_kind(CommandKind::Other, PdError::Rejected);
+        assert!(information.contract_negotiation_failure());
+        assert!(!information.swap_rejected_by_partner());
+    }
+
+    /// Test that a failed hard reset is reported as a hard reset
+    #[test]
+    fn test_error_information_hard_reset() {
+        let information = ErrorInformation::for_kind(CommandKind::HardReset, PdError::Failed);
+        assert!(information.hard_reset());
+        assert!(!information.undefined());
+
+        // Errors with a dedicated bit take precedence
+        let information = ErrorInformation::for_kind(CommandKind::HardReset, PdError::InvalidPort);
+        assert!(information.non_existent_connector());
+        assert!(!information.hard_reset());
+    }
+
+    /// Test classifying commands that don't change a role or reset the connector
+    #[test]
+    fn test_command_kind_other() {
+        let command = GlobalCommand::LpmCommand(lpm::GlobalCommand::new(
+            GlobalPortId(0),
+            lpm::CommandData::GetConnectorStatus,
+        ));
+        assert_eq!(CommandKind::new(&command), CommandKind::Other);
+        assert!(ErrorInformation::new(&command, PdError::Rejected).contract_negotiation_failure());
+    }
+
+    /// Test reading all PDOs of an EPR capabilities message with GET_PDOS
+    #[test]
+    fn test_requested_pdos_epr() {
+        let all: [u32; 11] = core::array::from_fn(|i| i as u32);
+        let pdos = Pdos::new(&all).unwrap();
+
+        // Each response is capped at four PDOs, so the OPM reads the EPR PDOs at higher offsets
+        assert_eq!(requested_pdos(&pdos, 0, 7).as_slice(), &[0, 1, 2, 3]);
+        assert_eq!(requested_pdos(&pdos, 4, 4).as_slice(), &[4, 5, 6, 7]);
+        assert_eq!(requested_pdos(&pdos, 8, 4).as_slice(), &[8, 9, 10]);
+        assert!(requested_pdos(&pdos, 11, 4).is_empty());
+    }
+
+    /// Test that GET_PDOS returns no more than the requested number of PDOs
+    #[test]
+    fn test_requested_pdos_count() {
+        let pdos = Pdos::new(&[0x1, 0x2, 0x3]).unwrap();
+        assert_eq!(requested_pdos(&pdos, 1, 1).as_slice(), &[0x2]);
+        assert!(requested_pdos(&pdos, 0, 0).is_empty());
+        assert!(requested_pdos(&pdos, 7, 4).is_empty());
+    }
+}
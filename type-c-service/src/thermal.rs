@@ -0,0 +1,57 @@
+//! Thermal service integration for temperature sensors behind a PD controller
+//!
+//! Many PD controllers sample a thermistor next to each connector. [`ConnectorSensor`] exposes that temperature as a
+//! thermal service sensor driver, so connector over-temperature protection uses the standard threshold, prochot and
+//! critical handling instead of a separate path.
+//!
+//! ```ignore
+//! let sensor = ConnectorSensor::new(&CONTROLLER, LocalPortId(0));
+//! let connector_temp = thermal_service::sensor::Service::new(sensor, ...);
+//! ```
+use embedded_sensors_hal_async::sensor::{Error, ErrorKind, ErrorType};
+use embedded_sensors_hal_async::temperature::{DegreesCelsius, TemperatureSensor};
+use embedded_services::sync::Lockable;
+use embedded_usb_pd::{LocalPortId, PdError};
+use thermal_service_interface::sensor;
+use type_c_interface::controller::temperature::Temperature;
+
+/// Error reading a connector temperature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SensorError(pub PdError);
+
+impl Error for SensorError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Connector temperature of a single port, read through the PD controller
+pub struct ConnectorSensor<'device, C: Lockable<Inner: Temperature>> {
+    controller: &'device C,
+    port: LocalPortId,
+}
+
+impl<'device, C: Lockable<Inner: Temperature>> ConnectorSensor<'device, C> {
+    /// Create a sensor for the connector of `port` on `controller`
+    pub fn new(controller: &'device C, port: LocalPortId) -> Self {
+        Self { controller, port }
+    }
+}
+
+impl<C: Lockable<Inner: Temperature>> ErrorType for ConnectorSensor<'_, C> {
+    type Error = SensorError;
+}
+
+impl<C: Lockable<Inner: Temperature>> TemperatureSensor for ConnectorSensor<'_, C> {
+    async fn temperature(&mut self) -> Result<DegreesCelsius, Self::Error> {
+        self.controller
+            .lock()
+            .await
+            .connector_temperature(self.port)
+            .await
+            .map_err(SensorError)
+    }
+}
+
+impl<C: Lockable<Inner: Temperature>> sensor::Driver for ConnectorSensor<'_, C> {}
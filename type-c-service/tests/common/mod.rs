@@ -13,6 +13,7 @@ use embassy_sync::{
 use embassy_time::{Duration, with_timeout};
 use embedded_services::{GlobalRawMutex, event::NonBlockingSender};
 use embedded_usb_pd::LocalPortId;
+use odp_test_support as _;
 use paste::paste;
 use power_policy_interface::charger::mock::NoopCharger;
 use type_c_service::service::registration::PortData;
@@ -154,6 +155,42 @@ pub trait Test {
     ) -> impl Future<Output = ()>;
 }
 
+/// Type-C service handles passed to a [`ServiceTest`]
+pub struct TestService<'port, 'ch> {
+    /// Type-C service
+    pub service: &'port TypeCServiceMutexType<'port, 'ch>,
+    /// Status of the UCSI command in progress
+    pub command_status: &'port type_c_service::service::CommandStatus,
+}
+
+/// Integration test that also drives the type-C service directly, e.g. to issue UCSI commands
+pub trait ServiceTest {
+    /// Run the test
+    fn run<'port, 'ch>(
+        &mut self,
+        type_c_service: TestService<'port, 'ch>,
+        type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        port1: TestPort<'port, 'ch>,
+        port2: TestPort<'port, 'ch>,
+    ) -> impl Future<Output = ()>;
+}
+
+impl<T: Test> ServiceTest for T {
+    fn run<'port, 'ch>(
+        &mut self,
+        _type_c_service: TestService<'port, 'ch>,
+        type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        port1: TestPort<'port, 'ch>,
+        port2: TestPort<'port, 'ch>,
+    ) -> impl Future<Output = ()> {
+        Test::run(self, type_c_receiver, power_policy_receiver, port0, port1, port2)
+    }
+}
+
 /// Used by the [`define_port`] macro to work around macro hygiene issues.
 struct PortComponents<'port, 'ch> {
     port: PortMutexType<'port, 'ch>,
@@ -288,7 +325,7 @@ pub async fn run_test(
     duration: Duration,
     type_c_service_config: type_c_service::service::config::Config,
     port_config: [type_c_service::controller::config::Config; TYPE_C_PORT_COUNT],
-    mut test: impl Test,
+    mut test: impl ServiceTest,
 ) {
     // Tokio runs tests in parallel, but logging is global so we need to run tests sequentially to avoid interleaved logs.
     static TEST_MUTEX: OnceLock<Mutex<GlobalRawMutex, ()>> = OnceLock::new();
@@ -338,7 +375,8 @@ pub async fn run_test(
     let type_c_service_sender = type_c_service_channel.dyn_sender();
     let type_c_service_receiver = type_c_service_channel.dyn_receiver();
 
-    let type_c_service = Mutex::new(type_c_service::service::Service::new(
+    let command_status = type_c_service::service::CommandStatus::new();
+    let type_c_service = Mutex::new(type_c_service::service::Service::new_with_command_status(
         type_c_service_config,
         TypeCRegistrationType {
             ports: [&port0, &port1, &port2],
@@ -355,6 +393,7 @@ pub async fn run_test(
             ],
             service_senders: [type_c_service_sender],
         },
+        &command_status,
     ));
 
     // Channel for events from the power policy service to the type-C service
@@ -419,6 +458,10 @@ pub async fn run_test(
             ),
             async {
                 test.run(
+                    TestService {
+                        service: &type_c_service,
+                        command_status: &command_status,
+                    },
                     type_c_service_receiver,
                     power_policy_service_receiver,
                     TestPort {
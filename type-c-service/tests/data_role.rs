@@ -0,0 +1,182 @@
+#![allow(dead_code)]
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::panic)]
+
+use embedded_usb_pd::{DataRole, PdError, type_c::ConnectionState};
+use type_c_interface::{
+    control::pd::{DataRoleSwapState, PortStatus},
+    port::event::{PortEvent, PortStatusEventBitfield},
+    port::pd::Pd,
+};
+use type_c_interface_test_mocks::controller::{FnCall as ControllerFnCall, pd::FnCall as PdFnCall};
+use type_c_service::controller::event::Event;
+
+use crate::common::{DEFAULT_TEST_DURATION, PowerPolicyServiceReceiver, Test, TestPort, TypeCServiceReceiver};
+
+mod common;
+
+/// Send a plug inserted event with the given data role and return the cached port status afterwards.
+async fn connect(port: &TestPort<'_, '_>, data_role: DataRole) -> PortStatus {
+    port.mock
+        .lock()
+        .await
+        .next_result_get_port_status
+        .push_back(Ok(PortStatus {
+            connection_state: Some(ConnectionState::Attached),
+            data_role,
+            ..Default::default()
+        }));
+
+    let mut status_event = PortStatusEventBitfield::none();
+    status_event.set_plug_inserted_or_removed(true);
+
+    let mut port = port.port.lock().await;
+    port.process_event(Event::PortEvent(PortEvent::StatusChanged(status_event)))
+        .await
+        .unwrap();
+    port.get_cached_port_status()
+}
+
+/// Test data role preference enforcement on connect.
+///
+/// Port 0 prefers UFP and connects as DFP, so a DR_Swap should be initiated and tracked until
+/// the controller reports completion. Port 1 prefers UFP and connects as UFP, so no swap is needed.
+/// Port 2 prefers DFP but the partner rejects the swap.
+struct TestDataRolePreference;
+
+impl Test for TestDataRolePreference {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        port1: TestPort<'port, 'ch>,
+        port2: TestPort<'port, 'ch>,
+    ) {
+        // Preference not met, swap initiated
+        port0.mock.lock().await.next_result_execute_dr_swap.push_back(Ok(()));
+        let status = connect(&port0, DataRole::Dfp).await;
+        assert_eq!(status.data_role_swap, DataRoleSwapState::InProgress);
+        {
+            let mut mock0 = port0.mock.lock().await;
+            assert!(matches!(
+                mock0.fn_calls.pop_front(),
+                Some(ControllerFnCall::Pd(PdFnCall::GetPortStatus(_)))
+            ));
+            assert!(matches!(
+                mock0.fn_calls.pop_front(),
+                Some(ControllerFnCall::Pd(PdFnCall::ExecuteDrSwap(_)))
+            ));
+            assert!(mock0.fn_calls.is_empty());
+
+            mock0.next_result_get_port_status.push_back(Ok(PortStatus {
+                connection_state: Some(ConnectionState::Attached),
+                data_role: DataRole::Ufp,
+                ..Default::default()
+            }));
+        }
+
+        // Swap completes
+        let mut status_event = PortStatusEventBitfield::none();
+        status_event.set_data_swap_completed(true);
+        let status = {
+            let mut port = port0.port.lock().await;
+            port.process_event(Event::PortEvent(PortEvent::StatusChanged(status_event)))
+                .await
+                .unwrap();
+            port.get_cached_port_status()
+        };
+        assert_eq!(status.data_role, DataRole::Ufp);
+        assert_eq!(status.data_role_swap, DataRoleSwapState::Idle);
+
+        // Preference already met
+        let status = connect(&port1, DataRole::Ufp).await;
+        assert_eq!(status.data_role_swap, DataRoleSwapState::Idle);
+        {
+            let mut mock1 = port1.mock.lock().await;
+            assert!(matches!(
+                mock1.fn_calls.pop_front(),
+                Some(ControllerFnCall::Pd(PdFnCall::GetPortStatus(_)))
+            ));
+            assert!(mock1.fn_calls.is_empty());
+        }
+
+        // Swap rejected by the partner
+        port2
+            .mock
+            .lock()
+            .await
+            .next_result_execute_dr_swap
+            .push_back(Err(PdError::Failed));
+        let status = connect(&port2, DataRole::Ufp).await;
+        assert_eq!(status.data_role_swap, DataRoleSwapState::Rejected);
+    }
+}
+
+/// Test a host requested DR_Swap.
+///
+/// The host can force a swap regardless of the configured preference, the swap state is surfaced
+/// in the cached port status.
+struct TestHostDataRoleSwap;
+
+impl Test for TestHostDataRoleSwap {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        // No preference configured, no swap on connect
+        let status = connect(&port0, DataRole::Dfp).await;
+        assert_eq!(status.data_role_swap, DataRoleSwapState::Idle);
+
+        port0.mock.lock().await.next_result_execute_dr_swap.push_back(Ok(()));
+        let mut port = port0.port.lock().await;
+        port.execute_dr_swap().await.unwrap();
+        assert_eq!(
+            port.get_cached_port_status().data_role_swap,
+            DataRoleSwapState::InProgress
+        );
+
+        port0
+            .mock
+            .lock()
+            .await
+            .next_result_execute_dr_swap
+            .push_back(Err(PdError::Failed));
+        assert_eq!(port.execute_dr_swap().await, Err(PdError::Failed));
+        assert_eq!(
+            port.get_cached_port_status().data_role_swap,
+            DataRoleSwapState::Rejected
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_data_role_preference() {
+    let mut ufp = type_c_service::controller::config::Config::default();
+    ufp.preferred_data_role = Some(DataRole::Ufp);
+    let mut dfp = type_c_service::controller::config::Config::default();
+    dfp.preferred_data_role = Some(DataRole::Dfp);
+
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        [ufp, ufp, dfp],
+        TestDataRolePreference,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_host_data_role_swap() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestHostDataRoleSwap,
+    )
+    .await;
+}
@@ -0,0 +1,122 @@
+#![allow(dead_code)]
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::panic)]
+
+use embassy_time::with_timeout;
+use type_c_interface::{control::fw_update::FwUpdateNotice, service::event::EventData};
+use type_c_interface_test_mocks::controller::{FnCall as ControllerFnCall, port_enable::FnCall as PortEnableFnCall};
+use type_c_service::controller::config::FwUpdateTransition;
+
+use crate::common::{
+    DEFAULT_PER_CALL_TIMEOUT, DEFAULT_TEST_DURATION, PowerPolicyServiceReceiver, Test, TestPort, TypeCServiceReceiver,
+};
+
+mod common;
+
+/// Wait for the next firmware update notice broadcast by the type-C service.
+async fn fw_update_notice(type_c_receiver: &TypeCServiceReceiver<'_, '_>) -> FwUpdateNotice {
+    match with_timeout(DEFAULT_PER_CALL_TIMEOUT, type_c_receiver.receive())
+        .await
+        .unwrap()
+        .event
+    {
+        EventData::FwUpdate(notice) => notice,
+        other => panic!("Expected EventData::FwUpdate, got {other:?}"),
+    }
+}
+
+/// Test detaching a port for a controller firmware update.
+///
+/// The port is disabled before the update and re-enabled once it completes, with the host notified of both.
+struct TestFwUpdateDetach;
+
+impl Test for TestFwUpdateDetach {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        port0
+            .mock
+            .lock()
+            .await
+            .next_result_set_port_enable
+            .extend([Ok(()), Ok(())]);
+
+        let deadline = port0.port.lock().await.prepare_fw_update().await.unwrap();
+        assert!(deadline.is_some());
+        assert_eq!(port0.port.lock().await.fw_update_deadline(), deadline);
+        assert_eq!(fw_update_notice(&type_c_receiver).await, FwUpdateNotice::Started);
+        assert!(matches!(
+            port0.mock.lock().await.fn_calls.pop_front(),
+            Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(_, false)))
+        ));
+
+        // Only one update at a time
+        assert!(port0.port.lock().await.prepare_fw_update().await.is_err());
+
+        port0.port.lock().await.finish_fw_update(false).await.unwrap();
+        assert_eq!(fw_update_notice(&type_c_receiver).await, FwUpdateNotice::Completed);
+        assert!(matches!(
+            port0.mock.lock().await.fn_calls.pop_front(),
+            Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(_, true)))
+        ));
+        assert!(!port0.port.lock().await.is_fw_updating());
+
+        // Finishing again does nothing
+        port0.port.lock().await.finish_fw_update(true).await.unwrap();
+        assert!(port0.mock.lock().await.fn_calls.is_empty());
+    }
+}
+
+/// Test reducing a port with no contract for a controller firmware update.
+///
+/// Nothing needs to change on the controller but the host is still notified, including when the update times out.
+struct TestFwUpdateReduceDisconnected;
+
+impl Test for TestFwUpdateReduceDisconnected {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        assert_eq!(port0.port.lock().await.prepare_fw_update().await.unwrap(), None);
+        assert_eq!(fw_update_notice(&type_c_receiver).await, FwUpdateNotice::Started);
+
+        port0.port.lock().await.finish_fw_update(true).await.unwrap();
+        assert_eq!(fw_update_notice(&type_c_receiver).await, FwUpdateNotice::TimedOut);
+        assert!(port0.mock.lock().await.fn_calls.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_fw_update_detach() {
+    let mut config = type_c_service::controller::config::Config::default();
+    config.fw_update_transition = FwUpdateTransition::Detach;
+    config.fw_update_timeout_ms = Some(30_000);
+
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        [config, Default::default(), Default::default()],
+        TestFwUpdateDetach,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_fw_update_reduce_disconnected() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestFwUpdateReduceDisconnected,
+    )
+    .await;
+}
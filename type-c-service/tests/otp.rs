@@ -0,0 +1,142 @@
+#![allow(dead_code)]
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::panic)]
+
+use embassy_time::with_timeout;
+use thermal_service_interface::sensor::{Event as SensorEvent, Threshold};
+use type_c_interface::{control::otp::OtpState, service::event::EventData};
+use type_c_interface_test_mocks::controller::{
+    FnCall as ControllerFnCall, current_limit::FnCall as CurrentLimitFnCall, port_enable::FnCall as PortEnableFnCall,
+};
+
+use crate::common::{
+    DEFAULT_PER_CALL_TIMEOUT, DEFAULT_TEST_DURATION, PowerPolicyServiceReceiver, Test, TestPort, TypeCServiceReceiver,
+};
+
+mod common;
+
+/// Send a connector temperature event to the port and return the resulting type-C service broadcast, if any.
+async fn temperature_event(
+    port: &TestPort<'_, '_>,
+    type_c_receiver: &TypeCServiceReceiver<'_, '_>,
+    event: SensorEvent,
+) -> Option<OtpState> {
+    port.port
+        .lock()
+        .await
+        .process_connector_temperature_event(event)
+        .await
+        .unwrap();
+
+    match with_timeout(DEFAULT_PER_CALL_TIMEOUT, type_c_receiver.receive()).await {
+        Ok(event) => match event.event {
+            EventData::OverTemperature(state) => Some(state),
+            other => panic!("Expected EventData::OverTemperature, got {other:?}"),
+        },
+        Err(_) => None,
+    }
+}
+
+/// Test connector over-temperature protection.
+///
+/// Exceeding the warn threshold limits the current, exceeding the critical threshold disables the port. The port
+/// recovers in reverse order as the thresholds clear.
+struct TestOverTemperature;
+
+impl Test for TestOverTemperature {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        {
+            let mut mock = port0.mock.lock().await;
+            mock.next_result_set_current_limit.extend([Ok(()), Ok(()), Ok(())]);
+            mock.next_result_set_port_enable.extend([Ok(()), Ok(())]);
+        }
+
+        let warn = SensorEvent::ThresholdExceeded(Threshold::WarnHigh);
+        assert_eq!(
+            temperature_event(&port0, &type_c_receiver, warn).await,
+            Some(OtpState::Throttled)
+        );
+        assert!(matches!(
+            port0.mock.lock().await.fn_calls.pop_front(),
+            Some(ControllerFnCall::CurrentLimit(CurrentLimitFnCall::SetCurrentLimit(
+                _,
+                Some(1000)
+            )))
+        ));
+
+        // Repeated event doesn't change anything
+        assert_eq!(temperature_event(&port0, &type_c_receiver, warn).await, None);
+        assert!(port0.mock.lock().await.fn_calls.is_empty());
+
+        let critical = SensorEvent::ThresholdExceeded(Threshold::Critical);
+        assert_eq!(
+            temperature_event(&port0, &type_c_receiver, critical).await,
+            Some(OtpState::Shutdown)
+        );
+        assert!(matches!(
+            port0.mock.lock().await.fn_calls.pop_front(),
+            Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(_, false)))
+        ));
+
+        let critical_cleared = SensorEvent::ThresholdCleared(Threshold::Critical);
+        assert_eq!(
+            temperature_event(&port0, &type_c_receiver, critical_cleared).await,
+            Some(OtpState::Throttled)
+        );
+        {
+            let mut mock = port0.mock.lock().await;
+            // Current is limited before the port comes back up
+            assert!(matches!(
+                mock.fn_calls.pop_front(),
+                Some(ControllerFnCall::CurrentLimit(CurrentLimitFnCall::SetCurrentLimit(
+                    _,
+                    Some(1000)
+                )))
+            ));
+            assert!(matches!(
+                mock.fn_calls.pop_front(),
+                Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(_, true)))
+            ));
+        }
+
+        let warn_cleared = SensorEvent::ThresholdCleared(Threshold::WarnHigh);
+        assert_eq!(
+            temperature_event(&port0, &type_c_receiver, warn_cleared).await,
+            Some(OtpState::Normal)
+        );
+        assert!(matches!(
+            port0.mock.lock().await.fn_calls.pop_front(),
+            Some(ControllerFnCall::CurrentLimit(CurrentLimitFnCall::SetCurrentLimit(
+                _,
+                None
+            )))
+        ));
+        assert_eq!(port0.port.lock().await.otp_state(), OtpState::Normal);
+
+        // Other thresholds don't affect the port
+        let prochot = SensorEvent::ThresholdExceeded(Threshold::Prochot);
+        assert_eq!(temperature_event(&port0, &type_c_receiver, prochot).await, None);
+        assert!(port0.mock.lock().await.fn_calls.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_over_temperature() {
+    let mut config = type_c_service::controller::config::Config::default();
+    config.otp_current_limit_ma = Some(1000);
+
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        [config, Default::default(), Default::default()],
+        TestOverTemperature,
+    )
+    .await;
+}
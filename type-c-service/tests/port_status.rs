@@ -0,0 +1,205 @@
+#![allow(dead_code)]
+#![allow(clippy::unwrap_used)]
+
+use embassy_sync::pubsub::PubSubChannel;
+use embedded_services::GlobalRawMutex;
+use embedded_services::broadcaster::immediate::{Immediate, Receiver, Subscriber};
+use embedded_services::event::Receiver as _;
+use embedded_services::service_state::{MAX_SNAPSHOT_LEN, RetainedState, ServiceState};
+use embedded_usb_pd::type_c::ConnectionState;
+use embedded_usb_pd::{GlobalPortId, PdError};
+use type_c_interface::control::otp::OtpState;
+use type_c_interface::control::pd::PortStatus;
+use type_c_interface::port::event::PortStatusEventBitfield;
+use type_c_interface::service::event::{PortEvent, PortEventData, StatusChangedData};
+use type_c_service::service::Event;
+
+use crate::common::{
+    DEFAULT_TEST_DURATION, PowerPolicyServiceReceiver, ServiceTest, TestPort, TestService, TypeCServiceReceiver,
+};
+
+mod common;
+
+/// Retained status broadcaster for port 0
+static PORT0_STATUS: Immediate<PortStatus> = Immediate::new_retained();
+
+/// Type-C service state snapshot
+static SNAPSHOT: RetainedState = RetainedState::new();
+
+/// Number of bytes in the snapshot record of each port
+const PORT_RECORD_LEN: usize = 5;
+
+/// Test that port status changes reach the retained broadcaster and late subscribers start from the latest status.
+struct TestPortStatusRetained;
+
+impl ServiceTest for TestPortStatusRetained {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_service: TestService<'port, 'ch>,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let service = type_c_service.service;
+
+        // The current status is broadcast as soon as the broadcaster is set
+        port0
+            .mock
+            .lock()
+            .await
+            .next_result_get_port_status
+            .push_back(Ok(PortStatus::default()));
+        service
+            .lock()
+            .await
+            .set_port_status_broadcaster(GlobalPortId(0), &PORT0_STATUS)
+            .await
+            .unwrap();
+        assert_eq!(PORT0_STATUS.latest(), Some(PortStatus::default()));
+
+        let attached = PortStatus {
+            connection_state: Some(ConnectionState::Attached),
+            ..Default::default()
+        };
+        let mut status_event = PortStatusEventBitfield::none();
+        status_event.set_plug_inserted_or_removed(true);
+        service
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent {
+                port: port0.port,
+                event: PortEventData::StatusChanged(StatusChangedData {
+                    status_event,
+                    previous_status: PortStatus::default(),
+                    current_status: attached,
+                }),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(PORT0_STATUS.latest(), Some(attached));
+
+        // A late subscriber starts from the latest status
+        let channel: &'static PubSubChannel<GlobalRawMutex, PortStatus, 1, 1, 0> =
+            Box::leak(Box::new(PubSubChannel::new()));
+        let mut subscriber = Subscriber::new(channel.dyn_subscriber().unwrap());
+        let receiver = Box::leak(Box::new(Receiver::new(channel.dyn_immediate_publisher())));
+        PORT0_STATUS.register_receiver(receiver).unwrap();
+        assert_eq!(subscriber.try_next(), Some(attached));
+
+        // Ports that aren't registered are rejected
+        assert_eq!(
+            service
+                .lock()
+                .await
+                .set_port_status_broadcaster(GlobalPortId(3), &PORT0_STATUS)
+                .await,
+            Err(PdError::InvalidPort)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_port_status_retained() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestPortStatusRetained,
+    )
+    .await;
+}
+
+/// Test that the state snapshot follows port status and over-temperature changes.
+struct TestStateSnapshot;
+
+impl TestStateSnapshot {
+    fn port0_flags() -> u8 {
+        let mut buffer = [0; MAX_SNAPSHOT_LEN];
+        assert_eq!(SNAPSHOT.snapshot(&mut buffer), 1 + 3 * PORT_RECORD_LEN);
+        assert_eq!(buffer.first(), Some(&3));
+        *buffer.get(1).unwrap()
+    }
+}
+
+impl ServiceTest for TestStateSnapshot {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_service: TestService<'port, 'ch>,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        port1: TestPort<'port, 'ch>,
+        port2: TestPort<'port, 'ch>,
+    ) {
+        let service = type_c_service.service;
+
+        // The current status of every port is published as soon as the snapshot is set
+        for port in [&port0, &port1, &port2] {
+            port.mock
+                .lock()
+                .await
+                .next_result_get_port_status
+                .push_back(Ok(PortStatus::default()));
+        }
+        service.lock().await.set_state_snapshot(&SNAPSHOT).await.unwrap();
+        let detached = Self::port0_flags();
+        assert_eq!(detached & 1, 0);
+
+        let attached = PortStatus {
+            connection_state: Some(ConnectionState::Attached),
+            ..Default::default()
+        };
+        let mut status_event = PortStatusEventBitfield::none();
+        status_event.set_plug_inserted_or_removed(true);
+        service
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent {
+                port: port0.port,
+                event: PortEventData::StatusChanged(StatusChangedData {
+                    status_event,
+                    previous_status: PortStatus::default(),
+                    current_status: attached,
+                }),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(Self::port0_flags(), detached | 1);
+
+        // Over-temperature is kept across status changes until the port cools down
+        service
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent {
+                port: port0.port,
+                event: PortEventData::OverTemperature(OtpState::Throttled),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(Self::port0_flags(), detached | 1 | 1 << 6);
+
+        service
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent {
+                port: port0.port,
+                event: PortEventData::OverTemperature(OtpState::Normal),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(Self::port0_flags(), detached | 1);
+    }
+}
+
+#[tokio::test]
+async fn test_state_snapshot() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestStateSnapshot,
+    )
+    .await;
+}
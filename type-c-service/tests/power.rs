@@ -4,23 +4,31 @@ use std::ptr;
 
 use embassy_futures::join::join;
 use embassy_time::{Duration, Instant, TimeoutError, with_timeout};
-use embedded_usb_pd::{PowerRole, constants::T_PS_TRANSITION_SPR_MS, type_c::ConnectionState};
+use embedded_usb_pd::{LocalPortId, PdError, PowerRole, constants::T_PS_TRANSITION_SPR_MS, type_c::ConnectionState};
 use power_policy_interface::{
     capability::{
-        ConsumerDisconnect, ConsumerFlags, ConsumerPowerCapability, ProviderFlags, ProviderPowerCapability, PsuType,
+        ConsumerDisconnect, ConsumerFlags, ConsumerPowerCapability, PowerCapability, ProviderFlags,
+        ProviderPowerCapability, PsuType,
     },
     psu::{Psu, PsuState},
     service::event::Event as PowerPolicyEvent,
 };
 use type_c_interface::{
-    control::pd::PortStatus,
+    control::cable::CableInfo,
+    control::pd::{PortStats, PortStatus},
+    control::type_c::TryRole,
     port::event::{PortEvent, PortEventBitfield, PortStatusEventBitfield},
     port::max_sink_voltage::MaxSinkVoltage,
+    port::pd::Pd,
+    port::port_enable::PortEnable,
+    port::type_c::StateMachine,
     util::POWER_CAPABILITY_5V_1A5,
 };
 use type_c_interface_test_mocks::controller::{
     FnCall as ControllerFnCall, max_sink_voltage::FnCall as MaxSinkVoltageFnCall, pd::FnCall as PdFnCall,
+    port_enable::FnCall as PortEnableFnCall, type_c::FnCall as TypeCFnCall,
 };
+use type_c_service::controller::config::SinkReadyTimeout;
 use type_c_service::controller::event::Event;
 
 use crate::common::{
@@ -98,6 +106,15 @@ impl Test for TestBasicConsumerFlow {
             PsuState::ConnectedConsumer(_)
         ));
 
+        // The first contract on a new connection isn't a renegotiation
+        assert_eq!(
+            port0.port.lock().await.get_port_stats().await.unwrap(),
+            PortStats {
+                attaches: 1,
+                ..Default::default()
+            }
+        );
+
         {
             // Set up the mock to report an unplug
             let mut mock0 = port0.mock.lock().await;
@@ -255,11 +272,11 @@ impl Test for TestBasicProviderFlow {
 /// End-to-end test of the software sink-ready timeout that drives the real `EventReceiver` and
 /// exercises every internal state transition along with the power-policy broadcasts.
 ///
-/// The controller never raises a hardware sink-ready event, so a real `embassy_time::Timer` inside
+/// The controller never raises a hardware sink-ready event, so the `embassy_time::Timer` inside
 /// a live [`type_c_service::controller::event_receiver::EventReceiver`] must elapse and synthesize
-/// the sink-ready event that completes the consumer contract. The event receiver is driven
-/// manually, one event at a time, so the port's internal state can be asserted deterministically
-/// between transitions:
+/// the sink-ready event that completes the consumer contract. Time is paused and advanced past the
+/// timeout rather than waited out. The event receiver is driven manually, one event at a time, so
+/// the port's internal state can be asserted deterministically between transitions:
 ///
 /// * `Detached` with no armed timeout initially,
 /// * `Idle` with the sink-ready timeout armed after the plug (no consumer broadcast yet),
@@ -312,6 +329,7 @@ impl Test for TestConsumerFlowTimerSinkReady {
         assert_eq!(port.lock().await.state().psu_state, PsuState::Detached);
         assert!(shared_state.lock().await.sink_ready_timeout().is_none());
 
+        let time = odp_test_support::time::pause();
         let start = Instant::now();
 
         // Plug in with a new consumer contract but WITHOUT a hardware sink-ready event.
@@ -331,16 +349,19 @@ impl Test for TestConsumerFlowTimerSinkReady {
         assert!(shared_state.lock().await.sink_ready_timeout().is_some());
         assert!(power_policy_receiver.try_receive().is_err());
 
-        // The next event is synthesized *inside* `wait_event` by a real timer; nothing in this test
-        // injects a sink-ready event. This call blocks until that timer elapses.
-        let event = event_receiver.wait_event().await;
+        // The next event is synthesized *inside* `wait_event` by the timer; nothing in this test
+        // injects a sink-ready event. Advancing time past the timeout lets that timer elapse.
+        let sink_ready_timeout = Duration::from_millis(T_PS_TRANSITION_SPR_MS.maximum.0 as u64);
+        let (event, _) = join(event_receiver.wait_event(), time.advance(sink_ready_timeout)).await;
         let elapsed = start.elapsed();
+        // The remainder of the flow doesn't depend on timeouts
+        drop(time);
         port.lock().await.process_event(event).await.unwrap();
 
         // The connect must have waited for the sink-ready timer to elapse, proving it was
         // timer-driven rather than an immediate hardware sink-ready event.
         assert!(
-            elapsed >= Duration::from_millis(T_PS_TRANSITION_SPR_MS.maximum.0 as u64),
+            elapsed >= sink_ready_timeout,
             "consumer connected before the sink-ready timer could elapse: {}ms",
             elapsed.as_millis()
         );
@@ -394,6 +415,80 @@ impl Test for TestConsumerFlowTimerSinkReady {
     }
 }
 
+/// Test per-port sink-ready timeout configuration.
+///
+/// Port 0 uses a short fixed timeout, which expires into a distinct [`Event::SinkReadyTimeout`] well before the
+/// spec-derived default would. Port 1 has the timeout disabled, so no deadline is armed without a hardware sink-ready.
+struct TestSinkReadyTimeoutConfig;
+
+impl Test for TestSinkReadyTimeoutConfig {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let sink_status = PortStatus {
+            available_sink_contract: Some(POWER_CAPABILITY_5V_1A5),
+            connection_state: Some(ConnectionState::Attached),
+            power_role: PowerRole::Sink,
+            ..Default::default()
+        };
+
+        let TestPort {
+            port,
+            mock,
+            shared_state,
+            interrupt_sender,
+            mut event_receiver,
+        } = port0;
+        {
+            let mut mock0 = mock.lock().await;
+            mock0.next_result_get_port_status.push_back(Ok(sink_status));
+            mock0.next_result_get_port_status.push_back(Ok(sink_status));
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+        }
+
+        let start = Instant::now();
+        let mut interrupt = PortEventBitfield::none();
+        interrupt.status.set_plug_inserted_or_removed(true);
+        interrupt.status.set_new_power_contract_as_consumer(true);
+        interrupt_sender.send(interrupt).await;
+
+        let event = event_receiver.wait_event().await;
+        port.lock().await.process_event(event).await.unwrap();
+        let deadline = shared_state.lock().await.sink_ready_timeout().unwrap();
+        assert!(deadline <= Instant::now() + Duration::from_millis(50));
+
+        // The synthesized sink-ready is reported as a timeout rather than a controller status change
+        let event = event_receiver.wait_event().await;
+        assert!(matches!(event, Event::SinkReadyTimeout));
+        assert!(start.elapsed() < Duration::from_millis(T_PS_TRANSITION_SPR_MS.maximum.0 as u64));
+        port.lock().await.process_event(event).await.unwrap();
+        assert!(shared_state.lock().await.sink_ready_timeout().is_none());
+
+        port1
+            .mock
+            .lock()
+            .await
+            .next_result_get_port_status
+            .push_back(Ok(sink_status));
+        let mut port_event = PortStatusEventBitfield::none();
+        port_event.set_plug_inserted_or_removed(true);
+        port_event.set_new_power_contract_as_consumer(true);
+        port1
+            .port
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent::StatusChanged(port_event)))
+            .await
+            .unwrap();
+        assert!(port1.shared_state.lock().await.sink_ready_timeout().is_none());
+    }
+}
+
 /// Test that changing the max sink voltage while a consumer is connected disables the sink path and
 /// notifies the power policy, which broadcasts a `ConsumerDisconnected` event with the renegotiation
 /// flag set. Setting the same voltage should do neither.
@@ -807,6 +902,441 @@ impl Test for TestProviderToConsumerRoleSwap {
     }
 }
 
+/// Test that disabling a port with a connected consumer disables the sink path before the controller disables the
+/// port, and disconnects from the power policy once the port is down.
+struct TestPortDisableWithConsumer;
+
+impl Test for TestPortDisableWithConsumer {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        // Bring up a connected consumer at 5V.
+        let interrupt = {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+            mock0.script_attach(PortStatus {
+                available_sink_contract: Some(POWER_CAPABILITY_5V_1A5),
+                connection_state: Some(ConnectionState::Attached),
+                power_role: PowerRole::Sink,
+                ..Default::default()
+            })
+        };
+        port0
+            .port
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent::StatusChanged(interrupt.status)))
+            .await
+            .unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerConnected(psu, _)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive consumer connected event"),
+        }
+
+        // Disable the port
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.fn_calls.clear();
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+            mock0.next_result_set_port_enable.push_back(Ok(()));
+        }
+        port0.port.lock().await.set_port_enable(false).await.unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerDisconnected(psu, _)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive consumer disconnected event"),
+        }
+        assert_eq!(port0.port.lock().await.state().psu_state, PsuState::Idle);
+
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.assert_pd_calls(&[PdFnCall::EnableSinkPath(LocalPortId(0), false)]);
+            assert!(
+                matches!(
+                    mock0.fn_calls.pop_front(),
+                    Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(
+                        LocalPortId(0),
+                        false
+                    )))
+                ),
+                "expected the port to be disabled after the sink path"
+            );
+            mock0.assert_no_fn_calls();
+        }
+
+        // Re-enabling the port doesn't touch the power policy, the next attach reconnects.
+        port0.mock.lock().await.next_result_set_port_enable.push_back(Ok(()));
+        port0.port.lock().await.set_port_enable(true).await.unwrap();
+        assert!(matches!(
+            with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await,
+            Err(TimeoutError)
+        ));
+
+        let mut mock0 = port0.mock.lock().await;
+        assert!(matches!(
+            mock0.fn_calls.pop_front(),
+            Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(
+                LocalPortId(0),
+                true
+            )))
+        ));
+        mock0.assert_no_fn_calls();
+        mock0.assert_results_consumed();
+    }
+}
+
+/// Test that disabling a port with a connected provider removes it from the power policy once the port is down.
+struct TestPortDisableWithProvider;
+
+impl Test for TestPortDisableWithProvider {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        // Bring up a connected provider at 5V.
+        let interrupt = port0.mock.lock().await.script_attach(PortStatus {
+            available_source_contract: Some(POWER_CAPABILITY_5V_1A5),
+            connection_state: Some(ConnectionState::Attached),
+            power_role: PowerRole::Source,
+            ..Default::default()
+        });
+        port0
+            .port
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent::StatusChanged(interrupt.status)))
+            .await
+            .unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ProviderConnected(psu, _)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive provider connected event"),
+        }
+
+        // Disable the port, the sink path isn't touched for a provider
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.fn_calls.clear();
+            mock0.next_result_set_port_enable.push_back(Ok(()));
+        }
+        port0.port.lock().await.set_port_enable(false).await.unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ProviderDisconnected(psu)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive provider disconnected event"),
+        }
+        assert_eq!(port0.port.lock().await.state().psu_state, PsuState::Idle);
+
+        let mut mock0 = port0.mock.lock().await;
+        assert!(matches!(
+            mock0.fn_calls.pop_front(),
+            Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(
+                LocalPortId(0),
+                false
+            )))
+        ));
+        mock0.assert_no_fn_calls();
+        mock0.assert_results_consumed();
+    }
+}
+
+/// Test that a port the controller fails to disable stays connected as a consumer.
+struct TestPortDisableFailure;
+
+impl Test for TestPortDisableFailure {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        // Bring up a connected consumer at 5V.
+        let interrupt = {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+            mock0.script_attach(PortStatus {
+                available_sink_contract: Some(POWER_CAPABILITY_5V_1A5),
+                connection_state: Some(ConnectionState::Attached),
+                power_role: PowerRole::Sink,
+                ..Default::default()
+            })
+        };
+        port0
+            .port
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent::StatusChanged(interrupt.status)))
+            .await
+            .unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerConnected(psu, _)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive consumer connected event"),
+        }
+
+        // The controller rejects the disable
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.fn_calls.clear();
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+            mock0.next_result_set_port_enable.push_back(Err(PdError::Failed));
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+        }
+        assert_eq!(
+            port0.port.lock().await.set_port_enable(false).await,
+            Err(PdError::Failed)
+        );
+
+        // The power policy keeps the consumer and the sink path is restored
+        assert!(matches!(
+            with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await,
+            Err(TimeoutError)
+        ));
+        assert!(matches!(
+            port0.port.lock().await.state().psu_state,
+            PsuState::ConnectedConsumer(_)
+        ));
+
+        let mut mock0 = port0.mock.lock().await;
+        mock0.assert_pd_calls(&[PdFnCall::EnableSinkPath(LocalPortId(0), false)]);
+        assert!(matches!(
+            mock0.fn_calls.pop_front(),
+            Some(ControllerFnCall::PortEnable(PortEnableFnCall::SetPortEnable(
+                LocalPortId(0),
+                false
+            )))
+        ));
+        mock0.assert_pd_calls(&[PdFnCall::EnableSinkPath(LocalPortId(0), true)]);
+        mock0.assert_no_fn_calls();
+        mock0.assert_results_consumed();
+    }
+}
+
+/// Test that the configured try role is applied and that a runtime change only sticks once the controller accepts it.
+struct TestTryRole;
+
+impl Test for TestTryRole {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let is_set_try_role = |call: Option<ControllerFnCall>, expected: TryRole| {
+            matches!(
+                call,
+                Some(ControllerFnCall::TypeC(TypeCFnCall::SetTryRole(LocalPortId(0), role))) if role == expected
+            )
+        };
+
+        // Configured preference
+        port0.mock.lock().await.next_result_set_try_role.push_back(Ok(()));
+        port0.port.lock().await.apply_try_role().await.unwrap();
+        assert!(is_set_try_role(
+            port0.mock.lock().await.fn_calls.pop_front(),
+            TryRole::TrySnk
+        ));
+
+        // A rejected change keeps the previous preference
+        port0
+            .mock
+            .lock()
+            .await
+            .next_result_set_try_role
+            .push_back(Err(PdError::Failed));
+        assert!(port0.port.lock().await.set_try_role(TryRole::TrySrc).await.is_err());
+        port0.mock.lock().await.next_result_set_try_role.push_back(Ok(()));
+        port0.port.lock().await.apply_try_role().await.unwrap();
+        {
+            let mut mock0 = port0.mock.lock().await;
+            assert!(is_set_try_role(mock0.fn_calls.pop_front(), TryRole::TrySrc));
+            assert!(is_set_try_role(mock0.fn_calls.pop_front(), TryRole::TrySnk));
+        }
+
+        // An accepted change is applied from then on, e.g. after a controller reset
+        port0.mock.lock().await.next_result_set_try_role.push_back(Ok(()));
+        port0.port.lock().await.set_try_role(TryRole::TrySrc).await.unwrap();
+        port0.mock.lock().await.next_result_set_try_role.push_back(Ok(()));
+        port0.port.lock().await.apply_try_role().await.unwrap();
+
+        let mut mock0 = port0.mock.lock().await;
+        assert!(is_set_try_role(mock0.fn_calls.pop_front(), TryRole::TrySrc));
+        assert!(is_set_try_role(mock0.fn_calls.pop_front(), TryRole::TrySrc));
+        mock0.assert_no_fn_calls();
+        mock0.assert_results_consumed();
+    }
+}
+
+/// A host-requested hard reset must tear down the consumer contract before the reset is issued, and the contract
+/// negotiated after the reset must reconnect the consumer.
+struct TestHardResetWithConsumer;
+
+impl Test for TestHardResetWithConsumer {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let status = PortStatus {
+            available_sink_contract: Some(POWER_CAPABILITY_5V_1A5),
+            connection_state: Some(ConnectionState::Attached),
+            power_role: PowerRole::Sink,
+            ..Default::default()
+        };
+
+        // Bring up a connected consumer at 5V.
+        let interrupt = {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+            mock0.script_attach(status)
+        };
+        port0
+            .port
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent::StatusChanged(interrupt.status)))
+            .await
+            .unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerConnected(psu, _)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive consumer connected event"),
+        }
+
+        // Request the hard reset
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.fn_calls.clear();
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+            mock0.next_result_hard_reset.push_back(Ok(()));
+        }
+        port0.port.lock().await.hard_reset().await.unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerDisconnected(psu, _)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive consumer disconnected event"),
+        }
+        assert_eq!(port0.port.lock().await.state().psu_state, PsuState::Idle);
+        port0.mock.lock().await.assert_pd_calls(&[
+            PdFnCall::EnableSinkPath(LocalPortId(0), false),
+            PdFnCall::HardReset(LocalPortId(0)),
+        ]);
+
+        // The reset completes and the same contract is renegotiated
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.next_result_get_port_status.push_back(Ok(status));
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+        }
+        let mut status_event = PortStatusEventBitfield::none();
+        status_event.set_pd_hard_reset(true);
+        status_event.set_new_power_contract_as_consumer(true);
+        status_event.set_sink_ready(true);
+        port0
+            .port
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent::StatusChanged(status_event)))
+            .await
+            .unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerConnected(psu, _)) => assert!(ptr::eq(psu, port0.port)),
+            _ => panic!("Did not receive consumer connected event after hard reset"),
+        }
+
+        let mut mock0 = port0.mock.lock().await;
+        mock0.assert_pd_calls(&[
+            PdFnCall::GetPortStatus(LocalPortId(0)),
+            PdFnCall::EnableSinkPath(LocalPortId(0), true),
+        ]);
+        mock0.assert_no_fn_calls();
+        mock0.assert_results_consumed();
+    }
+}
+
+/// Test that contracts above 3A are limited without a 5A eMarked cable
+struct TestCableCurrentLimit;
+
+impl Test for TestCableCurrentLimit {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        const POWER_CAPABILITY_20V_5A: PowerCapability = PowerCapability {
+            voltage_mv: 20000,
+            current_ma: 5000,
+        };
+
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.next_result_get_port_status.push_back(Ok(PortStatus {
+                available_sink_contract: Some(POWER_CAPABILITY_20V_5A),
+                connection_state: Some(ConnectionState::Attached),
+                power_role: PowerRole::Sink,
+                ..Default::default()
+            }));
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+        }
+
+        let mut port_event = PortStatusEventBitfield::none();
+        port_event.set_plug_inserted_or_removed(true);
+        port_event.set_new_power_contract_as_consumer(true);
+        port_event.set_sink_ready(true);
+
+        port0
+            .port
+            .lock()
+            .await
+            .process_event(Event::PortEvent(PortEvent::StatusChanged(port_event)))
+            .await
+            .unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerConnected(psu, capability)) => {
+                assert_eq!(
+                    capability,
+                    ConsumerPowerCapability {
+                        capability: PowerCapability {
+                            voltage_mv: 20000,
+                            current_ma: 3000,
+                        },
+                        flags: ConsumerFlags::none().with_psu_type(PsuType::TypeC),
+                    }
+                );
+                assert!(ptr::eq(psu, port0.port));
+            }
+            _ => panic!("Did not receive consumer connected event"),
+        }
+
+        assert_eq!(
+            port0.port.lock().await.get_cable_info().await.unwrap(),
+            CableInfo::new()
+        );
+    }
+}
+
 #[tokio::test]
 async fn test_basic_consumer_flow() {
     common::run_test(
@@ -840,6 +1370,22 @@ async fn test_consumer_flow_timer_sink_ready() {
     .await;
 }
 
+#[tokio::test]
+async fn test_sink_ready_timeout_config() {
+    let mut fixed = type_c_service::controller::config::Config::default();
+    fixed.sink_ready_timeout = SinkReadyTimeout::Milliseconds(50);
+    let mut disabled = type_c_service::controller::config::Config::default();
+    disabled.sink_ready_timeout = SinkReadyTimeout::Disabled;
+
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        [fixed, disabled, Default::default()],
+        TestSinkReadyTimeoutConfig,
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn test_sink_disable_on_voltage_change() {
     common::run_test(
@@ -872,3 +1418,72 @@ async fn test_provider_to_consumer_role_swap() {
     )
     .await;
 }
+
+#[tokio::test]
+async fn test_port_disable_with_consumer() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestPortDisableWithConsumer,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_port_disable_with_provider() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestPortDisableWithProvider,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_port_disable_failure() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestPortDisableFailure,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_hard_reset_with_consumer() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestHardResetWithConsumer,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_cable_current_limit() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestCableCurrentLimit,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_try_role() {
+    let mut config = type_c_service::controller::config::Config::default();
+    config.try_role = TryRole::TrySnk;
+
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        [config, Default::default(), Default::default()],
+        TestTryRole,
+    )
+    .await;
+}
@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::panic)]
+
+use core::num::NonZeroU8;
+
+use embedded_usb_pd::PdError;
+use thermal_service_interface::sensor::{Event as SensorEvent, Threshold};
+use type_c_interface::{control::pd::PortStatus, port::event::PortEvent, port::pd::Pd, service::event::PortEventData};
+use type_c_interface_test_mocks::controller::{
+    FnCall as ControllerFnCall, current_limit::FnCall as CurrentLimitFnCall, pd::FnCall as PdFnCall,
+};
+use type_c_service::controller::event::Event;
+use type_c_service::controller::otp::DEFAULT_CURRENT_LIMIT_MA;
+
+use crate::common::{DEFAULT_TEST_DURATION, PowerPolicyServiceReceiver, Test, TestPort, TypeCServiceReceiver};
+
+mod common;
+
+/// Process a PD alert that fails on the controller with the given error
+async fn failing_alert_with(port: &TestPort<'_, '_>, error: PdError) -> Result<Option<PortEventData>, PdError> {
+    port.mock.lock().await.next_result_get_pd_alert.push_back(Err(error));
+    port.port
+        .lock()
+        .await
+        .process_event(Event::PortEvent(PortEvent::Alert))
+        .await
+}
+
+/// Process a PD alert that fails to reach the controller
+async fn failing_alert(port: &TestPort<'_, '_>) -> Result<Option<PortEventData>, PdError> {
+    failing_alert_with(port, PdError::Failed).await
+}
+
+/// Test controller soft reset and recovery after repeated errors.
+///
+/// Port 0 recovers after two consecutive communication errors and replays its unconstrained power state and current
+/// limit. Errors reported by the controller itself don't count towards recovery.
+/// Port 1 has recovery disabled so errors are always returned.
+struct TestErrorRecovery;
+
+impl Test for TestErrorRecovery {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        port0
+            .mock
+            .lock()
+            .await
+            .next_result_set_unconstrained_power
+            .push_back(Ok(()));
+        port0.port.lock().await.set_unconstrained_power(true).await.unwrap();
+
+        port0.mock.lock().await.next_result_set_current_limit.push_back(Ok(()));
+        port0
+            .port
+            .lock()
+            .await
+            .process_connector_temperature_event(SensorEvent::ThresholdExceeded(Threshold::WarnHigh))
+            .await
+            .unwrap();
+
+        // First error is below the threshold and bubbles up
+        assert!(matches!(failing_alert(&port0).await, Err(PdError::Failed)));
+
+        // A rejected request shows the controller is responsive and resets the count
+        assert!(matches!(
+            failing_alert_with(&port0, PdError::Rejected).await,
+            Err(PdError::Rejected)
+        ));
+        assert!(matches!(
+            failing_alert_with(&port0, PdError::Timeout).await,
+            Err(PdError::Timeout)
+        ));
+
+        // Second consecutive communication error triggers recovery
+        {
+            let mut mock0 = port0.mock.lock().await;
+            mock0.fn_calls.clear();
+            mock0.next_result_reset_controller.push_back(Ok(()));
+            mock0.next_result_get_port_status.push_back(Ok(PortStatus::default()));
+            mock0.next_result_set_unconstrained_power.push_back(Ok(()));
+            mock0.next_result_set_current_limit.push_back(Ok(()));
+        }
+        assert!(matches!(
+            failing_alert(&port0).await,
+            Ok(Some(PortEventData::ControllerRecovered))
+        ));
+        {
+            let mut mock0 = port0.mock.lock().await;
+            assert!(matches!(
+                mock0.fn_calls.pop_front(),
+                Some(ControllerFnCall::Pd(PdFnCall::GetPdAlert(_)))
+            ));
+            assert!(matches!(
+                mock0.fn_calls.pop_front(),
+                Some(ControllerFnCall::ResetController)
+            ));
+            assert!(matches!(
+                mock0.fn_calls.pop_front(),
+                Some(ControllerFnCall::Pd(PdFnCall::GetPortStatus(_)))
+            ));
+            assert!(matches!(
+                mock0.fn_calls.pop_front(),
+                Some(ControllerFnCall::Pd(PdFnCall::SetUnconstrainedPower(_, true)))
+            ));
+            assert!(matches!(
+                mock0.fn_calls.pop_front(),
+                Some(ControllerFnCall::CurrentLimit(CurrentLimitFnCall::SetCurrentLimit(
+                    _,
+                    Some(DEFAULT_CURRENT_LIMIT_MA)
+                )))
+            ));
+            assert!(mock0.fn_calls.is_empty());
+        }
+
+        // The error count was reset by the recovery
+        assert!(matches!(failing_alert(&port0).await, Err(PdError::Failed)));
+
+        // Recovery disabled
+        for _ in 0..4 {
+            assert!(matches!(failing_alert(&port1).await, Err(PdError::Failed)));
+        }
+        assert!(
+            !port1
+                .mock
+                .lock()
+                .await
+                .fn_calls
+                .iter()
+                .any(|call| matches!(call, ControllerFnCall::ResetController))
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_error_recovery() {
+    let mut recovery = type_c_service::controller::config::Config::default();
+    recovery.error_recovery_threshold = NonZeroU8::new(2);
+
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        [recovery, Default::default(), Default::default()],
+        TestErrorRecovery,
+    )
+    .await;
+}
@@ -0,0 +1,106 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::panic)]
+use std::ptr;
+
+use embassy_time::with_timeout;
+use embedded_usb_pd::{LocalPortId, PowerRole, ado::Ado, type_c::ConnectionState};
+use power_policy_interface::{psu::PsuState, service::event::Event as PowerPolicyEvent};
+use type_c_interface::{control::pd::PortStatus, service::event::PortEventData, util::POWER_CAPABILITY_5V_1A5};
+use type_c_interface_test_mocks::controller::pd::FnCall as PdFnCall;
+
+use crate::common::{
+    DEFAULT_PER_CALL_TIMEOUT, DEFAULT_TEST_DURATION, PowerPolicyServiceReceiver, Test, TestPort, TypeCServiceReceiver,
+};
+
+mod common;
+
+/// Drive an attach, PD alert, and detach end-to-end through the port's event receiver using the mock's
+/// scripting helpers.
+struct TestScriptedConsumerFlow;
+
+impl Test for TestScriptedConsumerFlow {
+    async fn run<'port, 'ch>(
+        &mut self,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let TestPort {
+            port,
+            mock,
+            interrupt_sender,
+            mut event_receiver,
+            ..
+        } = port0;
+
+        // Attach a sink
+        let interrupt = {
+            let mut mock0 = mock.lock().await;
+            mock0.next_result_enable_sink_path.push_back(Ok(()));
+            mock0.script_attach(PortStatus {
+                available_sink_contract: Some(POWER_CAPABILITY_5V_1A5),
+                connection_state: Some(ConnectionState::Attached),
+                power_role: PowerRole::Sink,
+                ..Default::default()
+            })
+        };
+        interrupt_sender.send(interrupt).await;
+        let event = event_receiver.wait_event().await;
+        port.lock().await.process_event(event).await.unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerConnected(psu, _)) => assert!(ptr::eq(psu, port)),
+            _ => panic!("Did not receive consumer connected event"),
+        }
+        assert!(matches!(
+            port.lock().await.state().psu_state,
+            PsuState::ConnectedConsumer(_)
+        ));
+        mock.lock().await.assert_pd_calls(&[
+            PdFnCall::GetPortStatus(LocalPortId(0)),
+            PdFnCall::EnableSinkPath(LocalPortId(0), true),
+        ]);
+
+        // PD alert
+        let interrupt = mock.lock().await.script_pd_alert(Ado::PowerButtonPress);
+        interrupt_sender.send(interrupt).await;
+        let event = event_receiver.wait_event().await;
+        match port.lock().await.process_event(event).await.unwrap() {
+            Some(PortEventData::Alert(ado)) => assert_eq!(ado, Ado::PowerButtonPress),
+            other => panic!("Expected PortEventData::Alert, got {other:?}"),
+        }
+        mock.lock()
+            .await
+            .assert_pd_calls(&[PdFnCall::GetPdAlert(LocalPortId(0))]);
+
+        // Detach
+        let interrupt = mock.lock().await.script_detach();
+        interrupt_sender.send(interrupt).await;
+        let event = event_receiver.wait_event().await;
+        port.lock().await.process_event(event).await.unwrap();
+
+        match with_timeout(DEFAULT_PER_CALL_TIMEOUT, power_policy_receiver.receive()).await {
+            Ok(PowerPolicyEvent::ConsumerDisconnected(psu, _)) => assert!(ptr::eq(psu, port)),
+            _ => panic!("Did not receive consumer disconnected event"),
+        }
+        assert_eq!(port.lock().await.state().psu_state, PsuState::Detached);
+
+        let mut mock0 = mock.lock().await;
+        mock0.assert_pd_calls(&[PdFnCall::GetPortStatus(LocalPortId(0))]);
+        mock0.assert_no_fn_calls();
+        mock0.assert_results_consumed();
+    }
+}
+
+#[tokio::test]
+async fn test_scripted_consumer_flow() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestScriptedConsumerFlow,
+    )
+    .await;
+}
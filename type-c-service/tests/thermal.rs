@@ -0,0 +1,31 @@
+#![allow(clippy::unwrap_used)]
+
+use embassy_sync::mutex::Mutex;
+use embedded_sensors_hal_async::temperature::TemperatureSensor;
+use embedded_services::GlobalRawMutex;
+use embedded_usb_pd::{LocalPortId, PdError};
+use odp_test_support as _;
+use type_c_interface_test_mocks::controller::{FnCall as ControllerFnCall, Mock, temperature::FnCall};
+use type_c_service::thermal::{ConnectorSensor, SensorError};
+
+/// Test that connector temperatures are read from the right port and controller errors are reported
+#[tokio::test]
+async fn test_connector_temperature() {
+    let controller: Mutex<GlobalRawMutex, Mock> = Mutex::new(Mock::new("PD0"));
+    controller
+        .lock()
+        .await
+        .next_result_connector_temperature
+        .extend([Ok(45.5), Err(PdError::Timeout)]);
+
+    let mut sensor = ConnectorSensor::new(&controller, LocalPortId(1));
+    assert_eq!(sensor.temperature().await, Ok(45.5));
+    assert_eq!(sensor.temperature().await, Err(SensorError(PdError::Timeout)));
+
+    let mut mock = controller.lock().await;
+    assert_eq!(mock.fn_calls.len(), 2);
+    assert!(mock.fn_calls.drain(..).all(|call| matches!(
+        call,
+        ControllerFnCall::Temperature(FnCall::ConnectorTemperature(LocalPortId(1)))
+    )));
+}
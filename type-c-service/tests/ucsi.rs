@@ -0,0 +1,310 @@
+#![allow(dead_code)]
+#![allow(clippy::panic)]
+#![allow(clippy::unwrap_used)]
+
+use embassy_futures::join::join;
+use embedded_usb_pd::GlobalPortId;
+use embedded_usb_pd::type_c::ConnectionState;
+use embedded_usb_pd::ucsi::lpm::get_connector_status::ConnectorStatusChange;
+use embedded_usb_pd::ucsi::ppm::ack_cc_ci::Ack;
+use embedded_usb_pd::ucsi::ppm::set_notification_enable::NotificationEnable;
+use embedded_usb_pd::ucsi::{GlobalCommand, ResponseData, lpm, ppm};
+use type_c_interface::control::pd::PortStatus;
+use type_c_interface::port::event::PortStatusEventBitfield;
+use type_c_interface::service::event::{PortEvent, PortEventData, StatusChangedData};
+use type_c_service::service::Event;
+
+use crate::common::{
+    DEFAULT_TEST_DURATION, PortMutexType, PowerPolicyServiceReceiver, ServiceTest, TestPort, TestService,
+    TypeCServiceMutexType, TypeCServiceReceiver,
+};
+
+mod common;
+
+const CANCEL: GlobalCommand = GlobalCommand::PpmCommand(ppm::Command::Cancel);
+
+fn get_connector_status(port: GlobalPortId) -> GlobalCommand {
+    GlobalCommand::LpmCommand(lpm::GlobalCommand::new(port, lpm::CommandData::GetConnectorStatus))
+}
+
+fn ack_command_complete() -> GlobalCommand {
+    GlobalCommand::PpmCommand(ppm::Command::AckCcCi(ppm::ack_cc_ci::Args {
+        ack: *Ack::default().set_command_complete(true),
+    }))
+}
+
+fn ack_connector_change() -> GlobalCommand {
+    GlobalCommand::PpmCommand(ppm::Command::AckCcCi(ppm::ack_cc_ci::Args {
+        ack: *Ack::default().set_command_complete(true).set_connector_change(true),
+    }))
+}
+
+/// Enable the given notifications, returns the pending connector reported in the CCI
+async fn set_notification_enable(
+    service: &TypeCServiceMutexType<'_, '_>,
+    notifications: NotificationEnable,
+) -> GlobalPortId {
+    let mut service = service.lock().await;
+    let response = service
+        .process_ucsi_command(&GlobalCommand::PpmCommand(ppm::Command::SetNotificationEnable(
+            ppm::set_notification_enable::Args {
+                notification_enable: notifications,
+            },
+        )))
+        .await;
+    assert!(response.cci.cmd_complete());
+    assert!(!response.cci.error());
+    let connector = response.cci.connector_change();
+
+    let response = service.process_ucsi_command(&ack_command_complete()).await;
+    assert!(response.cci.ack_command());
+    connector
+}
+
+/// Returns the pending connector reported in the CCI of a completed command
+async fn pending_connector(service: &TypeCServiceMutexType<'_, '_>) -> GlobalPortId {
+    let mut service = service.lock().await;
+    let response = service
+        .process_ucsi_command(&GlobalCommand::PpmCommand(ppm::Command::GetCapability))
+        .await;
+    assert!(response.cci.cmd_complete());
+    let connector = response.cci.connector_change();
+
+    let response = service.process_ucsi_command(&ack_command_complete()).await;
+    assert!(response.cci.ack_command());
+    connector
+}
+
+/// Reset the PPM and enable command complete notifications
+async fn init_ppm(service: &TypeCServiceMutexType<'_, '_>) {
+    let response = service
+        .lock()
+        .await
+        .process_ucsi_command(&GlobalCommand::PpmCommand(ppm::Command::PpmReset))
+        .await;
+    assert!(response.cci.reset_complete());
+
+    let mut notifications = NotificationEnable::default();
+    notifications.set_cmd_complete(true);
+    set_notification_enable(service, notifications).await;
+}
+
+/// Command complete and connect change notifications
+fn connect_notifications() -> NotificationEnable {
+    let mut notifications = NotificationEnable::default();
+    notifications.set_cmd_complete(true);
+    notifications.set_connect_change(true);
+    notifications
+}
+
+/// A partner attaching and negotiating a consumer contract
+fn attach_event<'port, 'ch>(port: &'port PortMutexType<'port, 'ch>) -> Event<'port, PortMutexType<'port, 'ch>> {
+    let mut status_event = PortStatusEventBitfield::none();
+    status_event.set_plug_inserted_or_removed(true);
+    status_event.set_new_power_contract_as_consumer(true);
+    Event::PortEvent(PortEvent {
+        port,
+        event: PortEventData::StatusChanged(StatusChangedData {
+            status_event,
+            previous_status: PortStatus::default(),
+            current_status: PortStatus {
+                connection_state: Some(ConnectionState::Attached),
+                ..Default::default()
+            },
+        }),
+    })
+}
+
+/// Test that the PPM reports busy while a command is in progress and that CANCEL aborts it.
+struct TestCancel;
+
+impl ServiceTest for TestCancel {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_service: TestService<'port, 'ch>,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let TestService {
+            service,
+            command_status,
+        } = type_c_service;
+        init_ppm(service).await;
+        assert!(!command_status.is_busy());
+        assert!(command_status.check(&CANCEL).is_none());
+
+        // Hold the port so the command can't complete
+        let port_guard = port0.port.lock().await;
+        let command = get_connector_status(GlobalPortId(0));
+        let (response, ()) = join(
+            async { service.lock().await.process_ucsi_command(&command).await },
+            async {
+                assert!(command_status.is_busy());
+
+                // Other commands are rejected until the command in progress completes
+                let cci = command_status.check(&command).unwrap();
+                assert!(cci.busy());
+                assert!(!cci.cmd_complete());
+
+                let cci = command_status.check(&CANCEL).unwrap();
+                assert!(cci.busy());
+            },
+        )
+        .await;
+        drop(port_guard);
+
+        assert!(response.cci.cmd_complete());
+        assert!(response.cci.cancel_complete());
+        assert!(!response.cci.error());
+        assert!(response.data.unwrap().is_none());
+        assert!(!command_status.is_busy());
+        assert!(port0.mock.lock().await.fn_calls.is_empty());
+
+        // The cancelled command is acknowledged like any other and the next command goes through
+        let response = service.lock().await.process_ucsi_command(&ack_command_complete()).await;
+        assert!(response.cci.ack_command());
+        assert!(!response.cci.cancel_complete());
+        assert!(command_status.check(&command).is_none());
+    }
+}
+
+/// Test that GET_CONNECTOR_STATUS only reports the changes the OPM enabled notifications for.
+struct TestConnectorStatusFiltered;
+
+impl ServiceTest for TestConnectorStatusFiltered {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_service: TestService<'port, 'ch>,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let service = type_c_service.service;
+        init_ppm(service).await;
+        assert_eq!(
+            set_notification_enable(service, connect_notifications()).await,
+            GlobalPortId(0)
+        );
+
+        // The contract negotiation changes are disabled, only the connect change is pended
+        service
+            .lock()
+            .await
+            .process_event(attach_event(port0.port))
+            .await
+            .unwrap();
+
+        // The controller reports every change, only the enabled connect change reaches the OPM
+        let mut all_changes = ConnectorStatusChange::default();
+        all_changes.set_connect_change(true);
+        all_changes.set_negotiated_power_level_change(true);
+        all_changes.set_power_op_mode_change(true);
+        all_changes.set_external_supply_change(true);
+        let mut status = lpm::get_connector_status::ResponseData::default();
+        status.status_change = all_changes;
+        port0
+            .mock
+            .lock()
+            .await
+            .next_result_execute_lpm_command
+            .push_back(Ok(Some(lpm::ResponseData::GetConnectorStatus(status))));
+
+        let response = service
+            .lock()
+            .await
+            .process_ucsi_command(&get_connector_status(GlobalPortId(0)))
+            .await;
+        assert!(response.cci.cmd_complete());
+        assert!(!response.cci.error());
+        // UCSI connector numbers are 1-based
+        assert_eq!(response.cci.connector_change(), GlobalPortId(1));
+        let Ok(Some(ResponseData::Lpm(lpm::ResponseData::GetConnectorStatus(status)))) = response.data else {
+            panic!("Unexpected GET_CONNECTOR_STATUS response: {:?}", response.data);
+        };
+        assert!(status.status_change.connect_change());
+        assert!(!status.status_change.negotiated_power_level_change());
+        assert!(!status.status_change.power_op_mode_change());
+        assert!(!status.status_change.external_supply_change());
+
+        // Acknowledging the change leaves nothing pending
+        let response = service.lock().await.process_ucsi_command(&ack_connector_change()).await;
+        assert!(response.cci.ack_command());
+        assert_eq!(response.cci.connector_change(), GlobalPortId(0));
+    }
+}
+
+#[tokio::test]
+async fn test_connector_status_filtered() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestConnectorStatusFiltered,
+    )
+    .await;
+}
+
+/// Test that disabling a notification drops the pending changes of that kind, and ports left without changes.
+struct TestDisabledChangesDropped;
+
+impl ServiceTest for TestDisabledChangesDropped {
+    async fn run<'port, 'ch>(
+        &mut self,
+        type_c_service: TestService<'port, 'ch>,
+        _type_c_receiver: TypeCServiceReceiver<'port, 'ch>,
+        _power_policy_receiver: PowerPolicyServiceReceiver<'port, 'ch>,
+        port0: TestPort<'port, 'ch>,
+        _port1: TestPort<'port, 'ch>,
+        _port2: TestPort<'port, 'ch>,
+    ) {
+        let service = type_c_service.service;
+        init_ppm(service).await;
+        set_notification_enable(service, connect_notifications()).await;
+
+        service
+            .lock()
+            .await
+            .process_event(attach_event(port0.port))
+            .await
+            .unwrap();
+        assert_eq!(pending_connector(service).await, GlobalPortId(1));
+
+        // Disabling connect change notifications leaves the port without changes, so it's no longer pending
+        let mut notifications = NotificationEnable::default();
+        notifications.set_cmd_complete(true);
+        assert_eq!(set_notification_enable(service, notifications).await, GlobalPortId(0));
+
+        // The dropped change isn't reported once notifications are enabled again
+        assert_eq!(
+            set_notification_enable(service, connect_notifications()).await,
+            GlobalPortId(0)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_disabled_changes_dropped() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestDisabledChangesDropped,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_cancel() {
+    common::run_test(
+        DEFAULT_TEST_DURATION,
+        Default::default(),
+        Default::default(),
+        TestCancel,
+    )
+    .await;
+}
@@ -4,31 +4,47 @@
 //! Use [`MctpSerialService`] for the DSP0253 serial baseline; use
 //! [`Service::new`] directly with another medium for other callers.
 //!
+//! The UART can be shared with a debug console through the channel multiplexer in [`mux`], the
+//! service then runs on a [`mux::Port`] instead of the UART itself.
+//!
 //! Revisit: Will also need to consider how to handle notifications (likely need to have user
 //! provide GPIO pin we can use).
 #![no_std]
 
+pub mod mux;
 pub mod task;
 
 use embassy_sync::channel::Channel;
 use embedded_io_async::Read as UartRead;
 use embedded_io_async::Write as UartWrite;
 use embedded_services::GlobalRawMutex;
+use embedded_services::metrics::Metric;
+use embedded_services::relay::correlation::{self, Cookie};
 use embedded_services::relay::mctp::{RelayHandler, RelayHeader, RelayResponse};
 use embedded_services::trace;
 use mctp_rs::MctpMedium;
 
 // Should be as large as the largest possible MCTP packet and its metadata.
 const BUF_SIZE: usize = 256;
-const HOST_TX_QUEUE_SIZE: usize = 5;
+/// Default number of host responses that can be queued
+pub const HOST_TX_QUEUE_SIZE: usize = 5;
 
 #[derive(Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub(crate) struct HostResultMessage<R: RelayHandler> {
-    pub handler_service_id: R::ServiceIdType,
+    /// Identifies the request being answered in the correlation table
+    pub cookie: Cookie,
     pub message: R::ResultEnumType,
 }
 
+/// Context needed to reply to a host request, kept in the correlation table while the request is in flight
+#[derive(Clone, Copy)]
+pub(crate) struct RequestContext<ServiceId> {
+    service_id: ServiceId,
+    /// Message tag of the request, the response must carry the same tag
+    message_tag: mctp_rs::MctpMessageTag,
+}
+
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error<M: MctpMedium> {
@@ -42,6 +58,8 @@ pub enum Error<M: MctpMedium> {
     Serialize(&'static str),
     /// Buffer error.
     Buffer(embedded_services::buffer::Error),
+    /// Request/response correlation error.
+    Correlation(correlation::Error),
 }
 
 /// UART-driven MCTP relay service, generic over the medium `M`.
@@ -59,21 +77,42 @@ pub enum Error<M: MctpMedium> {
 /// cannot be `Copy` would need either an `&'_ M`-based redesign of
 /// `MctpPacketContext` or an interior-mutability wrapper.
 ///
+/// # Queue depth
+///
+/// `HOST_TX_QUEUE` is the number of host responses that can be queued, defaulting to [`HOST_TX_QUEUE_SIZE`].
+/// Memory-constrained platforms can shrink it and busy platforms can grow it.
+///
 /// [`MctpPacketContext`]: mctp_rs::MctpPacketContext
-pub struct Service<R: RelayHandler, M: MctpMedium + Copy> {
-    host_tx_queue: Channel<GlobalRawMutex, HostResultMessage<R>, HOST_TX_QUEUE_SIZE>,
+pub struct Service<R: RelayHandler, M: MctpMedium + Copy, const HOST_TX_QUEUE: usize = HOST_TX_QUEUE_SIZE> {
+    host_tx_queue: Channel<GlobalRawMutex, HostResultMessage<R>, HOST_TX_QUEUE>,
+    /// Host requests awaiting a response
+    pending: correlation::Table<GlobalRawMutex, RequestContext<R::ServiceIdType>, HOST_TX_QUEUE>,
     relay_handler: R,
     medium: M,
     reply_context: mctp_rs::MctpReplyContext<M>,
+    host_tx_queue_metric: Option<&'static Metric>,
 }
 
-impl<R: RelayHandler, M: MctpMedium + Copy> Service<R, M> {
+impl<R: RelayHandler, M: MctpMedium + Copy, const HOST_TX_QUEUE: usize> Service<R, M, HOST_TX_QUEUE> {
     pub fn new(relay_handler: R, medium: M, reply_context: mctp_rs::MctpReplyContext<M>) -> Result<Self, Error<M>> {
+        Self::new_with_metric(relay_handler, medium, reply_context, None)
+    }
+
+    /// Create a new service that records the high-water mark of the host response queue, which holds
+    /// `HOST_TX_QUEUE` responses
+    pub fn new_with_metric(
+        relay_handler: R,
+        medium: M,
+        reply_context: mctp_rs::MctpReplyContext<M>,
+        host_tx_queue_metric: Option<&'static Metric>,
+    ) -> Result<Self, Error<M>> {
         Ok(Self {
             host_tx_queue: Channel::new(),
+            pending: correlation::Table::default(),
             relay_handler,
             medium,
             reply_context,
+            host_tx_queue_metric,
         })
     }
 
@@ -82,15 +121,21 @@ impl<R: RelayHandler, M: MctpMedium + Copy> Service<R, M> {
         uart: &mut T,
         response: HostResultMessage<R>,
     ) -> Result<(), Error<M>> {
+        // A response to a request that timed out is dropped, the host has given up on it and could mistake the
+        // response for the response to a later request
+        let context = self.pending.complete(response.cookie).map_err(Error::Correlation)?;
+
         let mut assembly_buf = [0u8; BUF_SIZE];
         let mut mctp_ctx = mctp_rs::MctpPacketContext::<M>::new(self.medium, &mut assembly_buf);
 
         // Start from the stored reply_context, override the per-response
-        // destination_endpoint_id from the responding service.
+        // destination_endpoint_id from the responding service and the
+        // message tag from the request.
         let mut reply_context = self.reply_context;
-        reply_context.destination_endpoint_id = mctp_rs::EndpointId::Id(response.handler_service_id.into());
+        reply_context.destination_endpoint_id = mctp_rs::EndpointId::Id(context.service_id.into());
+        reply_context.message_tag = context.message_tag;
 
-        let header = response.message.create_header(&response.handler_service_id);
+        let header = response.message.create_header(&context.service_id);
         let mut packet_state = mctp_ctx
             .serialize_packet(reply_context, (header, response.message))
             .map_err(Error::Mctp)?;
@@ -141,16 +186,34 @@ impl<R: RelayHandler, M: MctpMedium + Copy> Service<R, M> {
             .map_err(Error::Mctp)?
             .ok_or(Error::Serialize("Partial message not supported"))?;
 
+        let message_tag = message.reply_context.message_tag;
         let (header, body) = message.parse_as::<R::RequestEnumType>().map_err(Error::Mctp)?;
         trace!("Received host request");
 
+        let cookie = self
+            .pending
+            .register(RequestContext {
+                service_id: header.get_service_id(),
+                message_tag,
+            })
+            .map_err(Error::Correlation)?;
+
         let response = self.relay_handler.process_request(body).await;
-        self.host_tx_queue
+        if self
+            .host_tx_queue
             .try_send(HostResultMessage {
-                handler_service_id: header.get_service_id(),
+                cookie,
                 message: response,
             })
-            .map_err(|_| Error::Comms)?;
+            .is_err()
+        {
+            // Free the slot, the response is lost
+            let _ = self.pending.complete(cookie);
+            return Err(Error::Comms);
+        }
+        if let Some(metric) = self.host_tx_queue_metric {
+            metric.record(self.host_tx_queue.len());
+        }
 
         Ok(())
     }
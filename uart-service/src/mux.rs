@@ -0,0 +1,499 @@
+//! Logical channel multiplexer, carrying the MCTP relay and a debug console over one UART.
+//!
+//! Each write is sent as a byte-stuffed frame tagged with its channel:
+//!
+//! ```text
+//! FLAG (0x7E) | channel | payload | FLAG (0x7E)
+//! ```
+//!
+//! `FLAG` and `ESC` (0x7D) bytes in the payload are sent as `ESC` followed by the byte XOR 0x20. Frames on the
+//! control channel (0xFF) carry a channel and a pause (0) or resume (1) request, so each side can stop the other
+//! from sending on one channel while its receive buffer is full without stalling the other channels.
+//!
+//! Legacy hosts that don't understand the framing are supported by disabling the mux in [`Config`]. The UART then
+//! only carries raw MCTP traffic and console output is discarded.
+//!
+//! ```ignore
+//! static MUX: Mux = Mux::new(Config::default());
+//!
+//! spawner.must_spawn(mux_task(&MUX, uart_rx, uart_tx));
+//! spawner.must_spawn(relay_task(&RELAY, MUX.port(Channel::Mctp)));
+//! spawner.must_spawn(console_task(MUX.port(Channel::Console)));
+//! ```
+use core::cell::Cell;
+use core::convert::Infallible;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::pipe::Pipe;
+use embassy_sync::signal::Signal;
+use embedded_io_async::Read as UartRead;
+use embedded_io_async::Write as UartWrite;
+use embedded_services::{GlobalRawMutex, error, warn};
+
+/// Frame delimiter
+const FLAG: u8 = 0x7E;
+/// Escape byte
+const ESC: u8 = 0x7D;
+/// Value XORed with escaped bytes
+const ESC_XOR: u8 = 0x20;
+/// Channel ID of flow control frames
+const CONTROL_CHANNEL: u8 = 0xFF;
+/// Maximum payload of a single frame
+const MAX_PAYLOAD: usize = 64;
+/// Maximum size of an encoded frame, every payload byte may be escaped
+const MAX_FRAME: usize = 2 * MAX_PAYLOAD + 3;
+
+/// Default size of each channel's receive and transmit buffers
+pub const DEFAULT_BUFFER_SIZE: usize = 256;
+
+/// Logical channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Channel {
+    /// MCTP host relay
+    Mctp = 0,
+    /// Raw debug console
+    Console = 1,
+}
+
+impl Channel {
+    const ALL: [Channel; 2] = [Channel::Mctp, Channel::Console];
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Channel::Mctp),
+            1 => Some(Channel::Console),
+            _ => None,
+        }
+    }
+}
+
+/// Mux configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Frame traffic with channel IDs, disable for legacy hosts that only speak raw MCTP
+    pub enabled: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Per-channel flow control state
+#[derive(Clone, Copy, Default)]
+struct Flow {
+    /// The peer asked us to stop sending on this channel
+    peer_paused: bool,
+    /// We asked the peer to stop sending on this channel
+    rx_paused: bool,
+    /// A pause or resume request must be sent to the peer
+    control_pending: bool,
+}
+
+struct ChannelState<const BUF: usize> {
+    rx: Pipe<GlobalRawMutex, BUF>,
+    tx: Pipe<GlobalRawMutex, BUF>,
+    flow: Mutex<GlobalRawMutex, Cell<Flow>>,
+}
+
+impl<const BUF: usize> ChannelState<BUF> {
+    const fn new() -> Self {
+        Self {
+            rx: Pipe::new(),
+            tx: Pipe::new(),
+            flow: Mutex::new(Cell::new(Flow {
+                peer_paused: false,
+                rx_paused: false,
+                control_pending: false,
+            })),
+        }
+    }
+
+    fn update_flow(&self, f: impl FnOnce(&mut Flow)) -> Flow {
+        self.flow.lock(|flow| {
+            let mut value = flow.get();
+            f(&mut value);
+            flow.set(value);
+            value
+        })
+    }
+}
+
+/// Decoded frame content
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Decoded {
+    /// A payload byte on the given channel
+    Byte(u8, u8),
+    /// End of a frame on the given channel
+    End(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DecodeState {
+    /// Waiting for the first frame delimiter
+    Sync,
+    /// Expecting the channel ID
+    Channel,
+    /// Receiving the payload
+    Data { channel: u8, escaped: bool },
+}
+
+/// Incremental frame decoder
+struct Decoder {
+    state: DecodeState,
+}
+
+impl Decoder {
+    const fn new() -> Self {
+        Self {
+            state: DecodeState::Sync,
+        }
+    }
+
+    fn decode(&mut self, byte: u8) -> Option<Decoded> {
+        match (self.state, byte) {
+            (state, FLAG) => {
+                self.state = DecodeState::Channel;
+                match state {
+                    DecodeState::Data { channel, .. } => Some(Decoded::End(channel)),
+                    _ => None,
+                }
+            }
+            (DecodeState::Sync, _) => None,
+            (DecodeState::Channel, channel) => {
+                self.state = DecodeState::Data {
+                    channel,
+                    escaped: false,
+                };
+                None
+            }
+            (
+                DecodeState::Data {
+                    channel,
+                    escaped: false,
+                },
+                ESC,
+            ) => {
+                self.state = DecodeState::Data { channel, escaped: true };
+                None
+            }
+            (DecodeState::Data { channel, escaped }, byte) => {
+                self.state = DecodeState::Data {
+                    channel,
+                    escaped: false,
+                };
+                Some(Decoded::Byte(channel, if escaped { byte ^ ESC_XOR } else { byte }))
+            }
+        }
+    }
+}
+
+/// Encode a frame into `frame`, returns the frame length or `None` if `frame` is too small
+fn encode(channel: u8, payload: &[u8], frame: &mut [u8]) -> Option<usize> {
+    let mut len = 0;
+    let mut push = |byte: u8| {
+        *frame.get_mut(len)? = byte;
+        len += 1;
+        Some(())
+    };
+
+    push(FLAG)?;
+    push(channel)?;
+    for &byte in payload {
+        if byte == FLAG || byte == ESC {
+            push(ESC)?;
+            push(byte ^ ESC_XOR)?;
+        } else {
+            push(byte)?;
+        }
+    }
+    push(FLAG)?;
+    Some(len)
+}
+
+/// UART channel multiplexer, each channel buffers `BUF` bytes in each direction
+pub struct Mux<const BUF: usize = DEFAULT_BUFFER_SIZE> {
+    config: Config,
+    mctp: ChannelState<BUF>,
+    console: ChannelState<BUF>,
+    /// Signaled when there may be something to transmit
+    tx_ready: Signal<GlobalRawMutex, ()>,
+}
+
+impl<const BUF: usize> Mux<BUF> {
+    /// Create a new mux
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            mctp: ChannelState::new(),
+            console: ChannelState::new(),
+            tx_ready: Signal::new(),
+        }
+    }
+
+    /// Returns a port to read from and write to the given channel
+    ///
+    /// Only one port should be used per channel at a time.
+    pub fn port(&self, channel: Channel) -> Port<'_, BUF> {
+        Port { mux: self, channel }
+    }
+
+    fn channel(&self, channel: Channel) -> &ChannelState<BUF> {
+        match channel {
+            Channel::Mctp => &self.mctp,
+            Channel::Console => &self.console,
+        }
+    }
+
+    /// Pause the peer once the receive buffer is running out of space
+    fn check_rx_pause(&self, channel: Channel) {
+        let state = self.channel(channel);
+        if !self.config.enabled || state.rx.free_capacity() >= BUF / 4 {
+            return;
+        }
+
+        let mut changed = false;
+        state.update_flow(|flow| {
+            if !flow.rx_paused {
+                flow.rx_paused = true;
+                flow.control_pending = true;
+                changed = true;
+            }
+        });
+        if changed {
+            self.tx_ready.signal(());
+        }
+    }
+
+    /// Resume the peer once the receive buffer has been drained
+    fn check_rx_resume(&self, channel: Channel) {
+        let state = self.channel(channel);
+        if !self.config.enabled || state.rx.free_capacity() < BUF / 2 {
+            return;
+        }
+
+        let mut changed = false;
+        state.update_flow(|flow| {
+            if flow.rx_paused {
+                flow.rx_paused = false;
+                flow.control_pending = true;
+                changed = true;
+            }
+        });
+        if changed {
+            self.tx_ready.signal(());
+        }
+    }
+
+    fn process_control(&self, control: &[u8]) {
+        let [channel, request] = control else {
+            warn!("uart mux: malformed control frame");
+            return;
+        };
+        let Some(channel) = Channel::from_id(*channel) else {
+            warn!("uart mux: control frame for unknown channel {}", channel);
+            return;
+        };
+
+        let paused = *request == 0;
+        self.channel(channel).update_flow(|flow| flow.peer_paused = paused);
+        if !paused {
+            self.tx_ready.signal(());
+        }
+    }
+
+    /// Receive from the UART and dispatch to the channels
+    pub async fn receive<T: UartRead>(&self, mut uart: T) -> embedded_services::Never {
+        let mut buf = [0u8; MAX_PAYLOAD];
+        let mut decoder = Decoder::new();
+        let mut control = [0u8; 2];
+        let mut control_len = 0usize;
+
+        loop {
+            let n = match uart.read(&mut buf).await {
+                Ok(n) => n,
+                Err(_) => {
+                    error!("uart mux: uart read error");
+                    continue;
+                }
+            };
+            let Some(bytes) = buf.get(..n) else {
+                continue;
+            };
+
+            if !self.config.enabled {
+                self.mctp.rx.write_all(bytes).await;
+                continue;
+            }
+
+            for &byte in bytes {
+                match decoder.decode(byte) {
+                    Some(Decoded::Byte(CONTROL_CHANNEL, byte)) => {
+                        if let Some(slot) = control.get_mut(control_len) {
+                            *slot = byte;
+                        }
+                        control_len = control_len.saturating_add(1);
+                    }
+                    Some(Decoded::End(CONTROL_CHANNEL)) => {
+                        self.process_control(control.get(..control_len).unwrap_or(&[]));
+                        control_len = 0;
+                    }
+                    Some(Decoded::Byte(channel, byte)) => {
+                        let Some(channel) = Channel::from_id(channel) else {
+                            continue;
+                        };
+                        if self.channel(channel).rx.try_write(&[byte]).is_err() {
+                            warn!("uart mux: {:?} receive buffer overflow", channel);
+                        }
+                        self.check_rx_pause(channel);
+                    }
+                    Some(Decoded::End(_)) | None => (),
+                }
+            }
+        }
+    }
+
+    /// Transmit pending channel data and flow control requests to the UART
+    pub async fn transmit<T: UartWrite>(&self, mut uart: T) -> embedded_services::Never {
+        let mut payload = [0u8; MAX_PAYLOAD];
+        let mut frame = [0u8; MAX_FRAME];
+
+        loop {
+            let mut sent = false;
+            for channel in Channel::ALL {
+                let state = self.channel(channel);
+                let mut control_pending = false;
+                let flow = state.update_flow(|flow| {
+                    control_pending = flow.control_pending;
+                    flow.control_pending = false;
+                });
+
+                if control_pending {
+                    let request = [channel as u8, u8::from(!flow.rx_paused)];
+                    if let Some(frame) = encode(CONTROL_CHANNEL, &request, &mut frame).and_then(|len| frame.get(..len))
+                        && uart.write_all(frame).await.is_err()
+                    {
+                        error!("uart mux: uart write error");
+                    }
+                    sent = true;
+                }
+
+                if flow.peer_paused {
+                    continue;
+                }
+
+                let Ok(n) = state.tx.try_read(&mut payload) else {
+                    continue;
+                };
+                let Some(payload) = payload.get(..n) else {
+                    continue;
+                };
+
+                let result = if self.config.enabled {
+                    match encode(channel as u8, payload, &mut frame).and_then(|len| frame.get(..len)) {
+                        Some(frame) => uart.write_all(frame).await,
+                        None => continue,
+                    }
+                } else {
+                    uart.write_all(payload).await
+                };
+                if result.is_err() {
+                    error!("uart mux: uart write error");
+                }
+                sent = true;
+            }
+
+            if !sent {
+                self.tx_ready.wait().await;
+            }
+        }
+    }
+}
+
+/// Reader and writer for a single channel of a [`Mux`]
+pub struct Port<'a, const BUF: usize = DEFAULT_BUFFER_SIZE> {
+    mux: &'a Mux<BUF>,
+    channel: Channel,
+}
+
+impl<const BUF: usize> embedded_io_async::ErrorType for Port<'_, BUF> {
+    type Error = Infallible;
+}
+
+impl<const BUF: usize> UartRead for Port<'_, BUF> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.mux.channel(self.channel).rx.read(buf).await;
+        self.mux.check_rx_resume(self.channel);
+        Ok(n)
+    }
+}
+
+impl<const BUF: usize> UartWrite for Port<'_, BUF> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if !self.mux.config.enabled && self.channel != Channel::Mctp {
+            // Only raw MCTP traffic is carried without the mux
+            return Ok(buf.len());
+        }
+
+        let n = self.mux.channel(self.channel).tx.write(buf).await;
+        self.mux.tx_ready.signal(());
+        Ok(n)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+#[allow(clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_round_trip() {
+        let payload = [0x01, FLAG, 0x02, ESC, 0x03];
+        let mut frame = [0u8; MAX_FRAME];
+        let len = encode(Channel::Console as u8, &payload, &mut frame).unwrap();
+        assert_eq!(
+            &frame[..len],
+            &[FLAG, 1, 0x01, ESC, FLAG ^ ESC_XOR, 0x02, ESC, ESC ^ ESC_XOR, 0x03, FLAG]
+        );
+
+        // Leading garbage is discarded until the first delimiter
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode(0x55), None);
+
+        let mut decoded = [0u8; 8];
+        let mut decoded_len = 0;
+        let mut ended = false;
+        for &byte in &frame[..len] {
+            match decoder.decode(byte) {
+                Some(Decoded::Byte(channel, byte)) => {
+                    assert_eq!(channel, Channel::Console as u8);
+                    decoded[decoded_len] = byte;
+                    decoded_len += 1;
+                }
+                Some(Decoded::End(channel)) => {
+                    assert_eq!(channel, Channel::Console as u8);
+                    ended = true;
+                }
+                None => (),
+            }
+        }
+
+        assert!(ended);
+        assert_eq!(&decoded[..decoded_len], &payload);
+    }
+
+    #[test]
+    fn frame_too_large() {
+        let mut frame = [0u8; 4];
+        assert_eq!(encode(0, &[FLAG, FLAG], &mut frame), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::mux::Mux;
 use crate::{Error, Service};
 use embedded_io_async::Read as UartRead;
 use embedded_io_async::Write as UartWrite;
@@ -5,8 +6,13 @@ use embedded_services::error;
 use embedded_services::relay::mctp::RelayHandler;
 use mctp_rs::MctpMedium;
 
-pub async fn uart_service<R: RelayHandler, M: MctpMedium + Copy, T: UartRead + UartWrite>(
-    uart_service: &Service<R, M>,
+pub async fn uart_service<
+    R: RelayHandler,
+    M: MctpMedium + Copy,
+    T: UartRead + UartWrite,
+    const HOST_TX_QUEUE: usize,
+>(
+    uart_service: &Service<R, M, HOST_TX_QUEUE>,
     mut uart: T,
 ) -> Result<embedded_services::Never, Error<M>> {
     // Note: eSPI service uses `select!` to seemingly allow asyncrhonous `responses` from services,
@@ -33,5 +39,17 @@ fn log_error<M: MctpMedium>(direction: &str, e: &Error<M>) {
         Error::Mctp(_) => error!("uart-service {}: mctp error", direction),
         Error::Serialize(s) => error!("uart-service {}: serialize error: {}", direction, s),
         Error::Buffer(_) => error!("uart-service {}: buffer error", direction),
+        Error::Correlation(e) => error!("uart-service {}: correlation error: {:?}", direction, e),
+    }
+}
+
+/// Runs the UART channel multiplexer over the receive and transmit halves of the UART.
+pub async fn mux_task<const BUF: usize, Rx: UartRead, Tx: UartWrite>(
+    mux: &Mux<BUF>,
+    rx: Rx,
+    tx: Tx,
+) -> embedded_services::Never {
+    match embassy_futures::select::select(mux.receive(rx), mux.transmit(tx)).await {
+        embassy_futures::select::Either::First(never) | embassy_futures::select::Either::Second(never) => never,
     }
 }